@@ -42,6 +42,7 @@ import {
   SelectValue,
 } from "@/components/ui/select";
 import { useProfiles } from "@/hooks/use-profiles";
+import { PROFILE_TYPE_LABELS, type ProfileType } from "@/lib/tauri";
 
 interface DashboardProps {
   setCurrentView: (
@@ -50,11 +51,12 @@ interface DashboardProps {
 }
 
 // Profile colors mapping
-const PROFILE_COLORS: Record<string, string> = {
-  Work: "from-blue-400 to-blue-600",
-  Gaming: "from-purple-400 to-pink-600",
-  Research: "from-green-400 to-emerald-600",
-  Custom: "from-orange-400 to-red-500",
+const PROFILE_COLORS: Record<ProfileType, string> = {
+  work: "from-blue-400 to-blue-600",
+  personal: "from-sky-400 to-cyan-600",
+  gaming: "from-purple-400 to-pink-600",
+  research: "from-green-400 to-emerald-600",
+  custom: "from-orange-400 to-red-500",
 };
 
 export function Dashboard({ setCurrentView }: DashboardProps) {
@@ -73,7 +75,7 @@ export function Dashboard({ setCurrentView }: DashboardProps) {
   const [isCreateDialogOpen, setIsCreateDialogOpen] = useState(false)
   const [newProfileName, setNewProfileName] = useState("")
   const [newProfileDescription, setNewProfileDescription] = useState("")
-  const [newProfileType, setNewProfileType] = useState("Custom")
+  const [newProfileType, setNewProfileType] = useState<ProfileType>("custom")
   const [captureCurrentMonitors, setCaptureCurrentMonitors] = useState(true)
   const [isCreating, setIsCreating] = useState(false)
 
@@ -109,7 +111,7 @@ export function Dashboard({ setCurrentView }: DashboardProps) {
       setIsCreateDialogOpen(false);
       setNewProfileName("");
       setNewProfileDescription("");
-      setNewProfileType("Custom");
+      setNewProfileType("custom");
       setCaptureCurrentMonitors(true);
       // Refresh profiles to ensure UI is updated
       await fetchProfiles();
@@ -314,16 +316,19 @@ export function Dashboard({ setCurrentView }: DashboardProps) {
                   <Label htmlFor="type">Type</Label>
                   <Select
                     value={newProfileType}
-                    onValueChange={setNewProfileType}
+                    onValueChange={(value) =>
+                      setNewProfileType(value as ProfileType)
+                    }
                   >
                     <SelectTrigger>
                       <SelectValue placeholder="Select type" />
                     </SelectTrigger>
                     <SelectContent>
-                      <SelectItem value="Work">Work</SelectItem>
-                      <SelectItem value="Gaming">Gaming</SelectItem>
-                      <SelectItem value="Research">Research</SelectItem>
-                      <SelectItem value="Custom">Custom</SelectItem>
+                      {Object.entries(PROFILE_TYPE_LABELS).map(([value, label]) => (
+                        <SelectItem key={value} value={value}>
+                          {label}
+                        </SelectItem>
+                      ))}
                     </SelectContent>
                   </Select>
                 </div>
@@ -401,7 +406,7 @@ export function Dashboard({ setCurrentView }: DashboardProps) {
 
                 <CardHeader>
                   <div
-                    className={`w-full h-28 bg-linear-to-br ${PROFILE_COLORS[profile.profileType] || PROFILE_COLORS.Custom} rounded-lg mb-4 opacity-75 group-hover:opacity-100 transition-opacity duration-300 flex items-center justify-center`}
+                    className={`w-full h-28 bg-linear-to-br ${PROFILE_COLORS[profile.profileType] || PROFILE_COLORS.custom} rounded-lg mb-4 opacity-75 group-hover:opacity-100 transition-opacity duration-300 flex items-center justify-center`}
                   >
                     {profile.isActive && (
                       <div className="text-white font-bold text-sm bg-black/30 px-3 py-1 rounded">
@@ -11,7 +11,7 @@ import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/com
 import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "@/components/ui/select"
 import { useSystemDetection } from "@/hooks/use-system-detection"
 import { useToast } from "@/hooks/use-toast"
-import { monitorApi, isTauri, systemApi, profileApi, type Profile } from "@/lib/tauri"
+import { monitorApi, isTauri, systemApi, profileApi, commandErrorMessage, type Profile } from "@/lib/tauri"
 
 // Internal monitor representation uses ACTUAL pixel coordinates
 interface Monitor {
@@ -391,7 +391,7 @@ export function MonitorEditor({ selectedProfile, onSelectProfile }: MonitorEdito
         })
       }
     } catch (error) {
-      const errorMessage = error instanceof Error ? error.message : String(error)
+      const errorMessage = commandErrorMessage(error)
       
       // Check if this is a manual command error
       if (errorMessage.includes("Please run this command manually")) {
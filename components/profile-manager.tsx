@@ -55,10 +55,13 @@ import {
   browserTabApi,
   profileApi,
   isTauri,
+  PROFILE_TYPE_LABELS,
   type App,
   type BrowserTab,
+  type CommandError,
   type RunningApp,
   type InstalledApp,
+  type ProfileType,
 } from "@/lib/tauri";
 
 interface ProfileManagerProps {
@@ -126,7 +129,7 @@ export function ProfileManager({ onSelectProfile }: ProfileManagerProps) {
   const [dialogOpen, setDialogOpen] = useState(false);
   const [newProfileName, setNewProfileName] = useState("");
   const [newProfileDescription, setNewProfileDescription] = useState("");
-  const [newProfileType, setNewProfileType] = useState<string>("Custom");
+  const [newProfileType, setNewProfileType] = useState<ProfileType>("custom");
   const [captureMonitorsOnCreate, setCaptureMonitorsOnCreate] = useState(true);
   const [isCreating, setIsCreating] = useState(false);
   const [capturingProfileId, setCapturingProfileId] = useState<string | null>(
@@ -167,7 +170,7 @@ export function ProfileManager({ onSelectProfile }: ProfileManagerProps) {
       );
       setNewProfileName("");
       setNewProfileDescription("");
-      setNewProfileType("Custom");
+      setNewProfileType("custom");
       setCaptureMonitorsOnCreate(true);
       setDialogOpen(false);
       toast({
@@ -356,10 +359,11 @@ export function ProfileManager({ onSelectProfile }: ProfileManagerProps) {
         title: "Profile Started",
         description: `${monitorStatus}, launched ${successApps} apps, opened ${successTabs} tabs`,
       });
-    } catch {
+    } catch (err) {
+      const failed = (err as CommandError).steps?.find((s) => s.status === "failed");
       toast({
         title: "Error",
-        description: "Failed to start profile",
+        description: failed ? `Failed to start profile: ${failed.message}` : "Failed to start profile",
         variant: "destructive",
       });
     } finally {
@@ -471,16 +475,19 @@ export function ProfileManager({ onSelectProfile }: ProfileManagerProps) {
                 <Label htmlFor="type">Type</Label>
                 <Select
                   value={newProfileType}
-                  onValueChange={setNewProfileType}
+                  onValueChange={(value) =>
+                    setNewProfileType(value as ProfileType)
+                  }
                 >
                   <SelectTrigger>
                     <SelectValue placeholder="Select type" />
                   </SelectTrigger>
                   <SelectContent>
-                    <SelectItem value="Work">Work</SelectItem>
-                    <SelectItem value="Gaming">Gaming</SelectItem>
-                    <SelectItem value="Research">Research</SelectItem>
-                    <SelectItem value="Custom">Custom</SelectItem>
+                    {Object.entries(PROFILE_TYPE_LABELS).map(([value, label]) => (
+                      <SelectItem key={value} value={value}>
+                        {label}
+                      </SelectItem>
+                    ))}
                   </SelectContent>
                 </Select>
               </div>
@@ -602,7 +609,8 @@ export function ProfileManager({ onSelectProfile }: ProfileManagerProps) {
                                 {profile.name}
                               </CardTitle>
                               <span className="text-xs bg-primary/10 text-primary px-2 py-1 rounded-full font-medium">
-                                {profile.profileType}
+                                {PROFILE_TYPE_LABELS[profile.profileType] ??
+                                  profile.profileType}
                               </span>
                               {profile.isActive && (
                                 <span className="text-xs bg-green-500/10 text-green-500 px-2 py-1 rounded-full font-medium">
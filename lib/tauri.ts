@@ -1,14 +1,27 @@
 // Tauri API wrapper for frontend-backend communication
 
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 
 // Types matching Rust backend models (camelCase to match serde rename_all)
+
+/** Profile types, lowercase as the backend sends them */
+export type ProfileType = 'work' | 'personal' | 'gaming' | 'research' | 'custom';
+
+export const PROFILE_TYPE_LABELS: Record<ProfileType, string> = {
+  work: 'Work',
+  personal: 'Personal',
+  gaming: 'Gaming',
+  research: 'Research',
+  custom: 'Custom',
+};
+
 export interface Profile {
   id: string;
   userId: string;
   name: string;
   description?: string;
-  profileType: string;
+  profileType: ProfileType;
   isActive: boolean;
   tags?: string[];
   monitorCount: number;
@@ -24,12 +37,41 @@ export interface Profile {
   color?: string;
   icon?: string;
   sortOrder: number;
+  /** Battery percentage below which app launches and tabs are skipped */
+  lowPowerBatteryThreshold?: number | null;
+  /** Never uploaded by cloud sync */
+  localOnly: boolean;
+  /** Picked automatically when this many monitors are connected */
+  requiredMonitorCount?: number | null;
+  /** Picked automatically when exactly these displays are connected */
+  monitorSignature?: string | null;
+  /** Markdown notes on what the profile is for; HTML is stripped when saved */
+  notes?: string | null;
+  /** Sidecar, AirPlay and virtual displays count for the monitor binding and layout */
+  includeVirtualDisplays: boolean;
+  /** Edits are rejected until the profile is unlocked */
+  isLocked: boolean;
   // Related entities (optional, loaded on demand)
   monitors?: Monitor[];
   apps?: App[];
   browserTabs?: BrowserTab[];
 }
 
+/** The profile picked for the connected displays */
+export interface ProfileMatch {
+  profileId: string;
+  profileName: string;
+  reason: 'signature' | 'monitor_count' | 'default_mapping';
+  monitorCount: number;
+  monitorSignature: string;
+}
+
+export interface ProfileShare {
+  /** `XXXXX-XXXXX`; case, spaces and dashes are ignored on import */
+  code: string;
+  profileName: string;
+}
+
 export interface Monitor {
   id: string;
   profileId: string;
@@ -51,6 +93,11 @@ export interface Monitor {
   colorDepth?: number;
   createdAt?: string;
   updatedAt?: string;
+  /** Physical display identity, set on monitors saved by a capture */
+  fingerprint?: string | null;
+  capturedAt?: string | null;
+  /** Kept dark while the profile is active */
+  blanked?: boolean;
 }
 
 export interface App {
@@ -83,19 +130,141 @@ export interface BrowserTab {
   updatedAt?: string;
 }
 
+export type BookmarkSource = 'chrome_bookmarks' | 'firefox_bookmarks' | 'firefox_session';
+
+export interface BookmarkItem {
+  title: string;
+  url: string;
+  /** e.g. `Bookmarks Bar / Work`, or `Window 2` for sessions */
+  folder: string;
+  /** Already in the profile or earlier in the file; never imported */
+  duplicate: boolean;
+}
+
+export interface BookmarkImportPreview {
+  source: BookmarkSource;
+  browser: string;
+  items: BookmarkItem[];
+}
+
+export interface ImportBookmarksRequest {
+  path: string;
+  folder?: string;
+  /** Only these URLs; every non-duplicate item when omitted */
+  urls?: string[];
+  browser?: string;
+}
+
 export interface AutomationRule {
   id: string;
   profileId: string;
   ruleType: string;
   triggerConfig: Record<string, unknown>;
+  /** JSON-logic style expression checked before the rule acts */
+  conditions?: Record<string, unknown> | null;
+  /** Higher runs first when several rules match the same trigger */
+  priority?: number;
+  stopAfterMatch?: boolean;
+  cooldownMinutes?: number;
+  lastTriggeredAt?: string | null;
+  /** Only set while the snooze is still running */
+  snoozedUntil?: string | null;
+  activeHours?: ActiveHoursWindow[] | null;
   isEnabled: boolean;
   createdAt: string;
 }
 
+export interface ActiveHoursWindow {
+  /** "HH:MM" in local time; an end before the start wraps past midnight */
+  start: string;
+  end: string;
+  /** 1 = Monday ... 7 = Sunday; empty means every day */
+  weekdays?: number[];
+}
+
+export type RuleOutcome =
+  | 'chosen'
+  | 'snoozed'
+  | 'outside_active_hours'
+  | 'cooling_down'
+  | 'conditions_not_met'
+  | 'invalid_conditions'
+  | 'superseded';
+
+export interface ConditionContext {
+  monitorCount: number;
+  time: string;
+  hour: number;
+  minute: number;
+  weekday: number;
+  onAcPower: boolean | null;
+  batteryPercent: number | null;
+  runningApps: string[];
+}
+
+export interface ConditionResult {
+  condition: string;
+  passed: boolean;
+  children?: ConditionResult[];
+}
+
+export type RuleActionType =
+  | 'activate_profile'
+  | 'run_hook'
+  | 'send_notification'
+  | 'set_focus_mode'
+  | 'open_url';
+
+export interface RuleAction {
+  id: string;
+  ruleId: string;
+  position: number;
+  actionType: RuleActionType;
+  config: Record<string, unknown>;
+}
+
+export interface RuleActionInput {
+  actionType: RuleActionType;
+  config?: Record<string, unknown>;
+}
+
+export interface PlannedAction {
+  position: number;
+  type: RuleActionType;
+  config: Record<string, unknown>;
+}
+
+export interface SimulatedRule {
+  ruleId: string;
+  priority: number;
+  outcome: RuleOutcome;
+  profileId: string;
+  conditions: ConditionResult | null;
+  actions: PlannedAction[];
+}
+
+export interface SimulationResult {
+  triggerType: string;
+  at: string;
+  context: ConditionContext;
+  rules: SimulatedRule[];
+}
+
+export interface RuleTestResult {
+  ruleId: string;
+  passed: boolean;
+  context: ConditionContext;
+  conditions: ConditionResult | null;
+  coolingDownUntil: string | null;
+  outcome: RuleOutcome;
+}
+
 export interface CreateProfileRequest {
   name: string;
   description?: string;
-  profileType: string;
+  profileType: ProfileType;
+  /** Markdown, at most 20,000 characters */
+  notes?: string;
 }
 
 export interface UpdateProfileRequest {
@@ -105,12 +274,31 @@ export interface UpdateProfileRequest {
   color?: string;
   icon?: string;
   sortOrder?: number;
+  tags?: string[];
+  /** Markdown; an empty string clears the notes */
+  notes?: string;
 }
 
 export interface LaunchResult {
   name: string;
   success: boolean;
   message: string;
+  bundleId: string;
+  /** Whether the app's windows appeared and were moved to their saved frames */
+  windowPositioned: boolean;
+  /** The profile app launched, when it was one */
+  appId: string | null;
+  /** Set when the launch failed in a way `systemApi.getFixes` has actions for */
+  failureCode: FailureCode | null;
+}
+
+/** A profile app that isn't installed any more */
+export interface MissingApp {
+  appId: string;
+  name: string;
+  bundleId: string;
+  /** Installed apps most like the missing one, best match first */
+  suggestions: InstalledApp[];
 }
 
 export interface OpenTabResult {
@@ -120,10 +308,47 @@ export interface OpenTabResult {
   message: string;
 }
 
+export interface MonitorFrame {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+export interface MonitorVerification {
+  displayId: number;
+  name: string;
+  status: 'applied' | 'mismatch' | 'missing';
+  expected: MonitorFrame;
+  actual?: MonitorFrame | null;
+}
+
 export interface MonitorLayoutResult {
   applied: boolean;
   monitorCount: number;
   message: string;
+  attempts: number;
+  monitors: MonitorVerification[];
+}
+
+export interface SavedWindow {
+  id: string;
+  profileId: string;
+  appId: string;
+  monitorId: string;
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+  isMaximized: boolean;
+  state: string;
+  /** Zoom steps from the app's default (-5 to 10); null leaves the app's zoom alone */
+  zoomSteps: number | null;
+}
+
+export interface RecoveredWindows {
+  primaryMonitor: string;
+  windows: SavedWindow[];
 }
 
 export interface StartProfileResult {
@@ -131,11 +356,250 @@ export interface StartProfileResult {
   appsLaunched: LaunchResult[];
   tabsOpened: OpenTabResult[];
   monitorLayout: MonitorLayoutResult;
+  /** Set when apps and tabs were skipped to save battery */
+  lowPower: PowerState | null;
+  steps: ActivationStepReport[];
+  /** Started from the offline cache while the database was unreachable */
+  offline: boolean;
+}
+
+export type ActivationStepKind = 'monitor_layout' | 'blank_displays' | 'apps' | 'browser_tabs' | 'files';
+
+export interface ActivationStepReport {
+  step: ActivationStepKind;
+  status: 'completed' | 'skipped' | 'failed' | 'rolled_back' | 'rollback_failed';
+  message: string;
+  durationMs: number;
+}
+
+/** A failed run of osascript or displayplacer */
+export interface ToolFailure {
+  tool: string;
+  exitCode: number | null;
+  stderr: string;
+  failedAt: string;
+}
+
+/** What went wrong in a failed activation */
+export interface ActivationReport {
+  activationId: string;
+  profileId: string;
+  failedStep: ActivationStepKind | null;
+  error: string;
+  steps: ActivationStepReport[];
+  toolOutput: ToolFailure[];
+  permissions: { status: 'ok' | 'not_configured' | 'degraded' | 'down'; screenRecording: boolean };
+  tools: {
+    name: string;
+    status: 'ok' | 'not_configured' | 'degraded' | 'down';
+    path: string | null;
+    usedFor: string;
+  }[];
+  suggestedFixes: string[];
+  /** Known failures `systemApi.getFixes` has actions for */
+  failureCodes: FailureCode[];
+  createdAt: string;
+}
+
+/** A known failure with a remedy */
+export type FailureCode = 'displayplacer_missing' | 'screen_recording_denied' | 'app_not_installed';
+
+export type FixAction =
+  | 'install_displayplacer'
+  | 'open_screen_recording_settings'
+  | 'remove_app_from_profile';
+
+export interface Fix {
+  action: FixAction;
+  title: string;
+  description: string;
+  /** `applyFix` needs the id of the profile app it's about */
+  needsAppId: boolean;
+}
+
+export interface FixResult {
+  action: FixAction;
+  /** False when the fix was started but needs the user to finish it */
+  completed: boolean;
+  message: string;
+}
+
+/** An activation an earlier launch didn't finish */
+export interface InterruptedActivation {
+  id: string;
+  profileId: string;
+  /** Null when the profile has been deleted since */
+  profileName: string | null;
+  startedAt: string;
+  completed: ActivationStepKind[];
+  /** The step that was running when the app went down */
+  interrupted: ActivationStepKind | null;
+  pending: ActivationStepKind[];
+}
+
+export type DeactivationActionType = 'quit_apps' | 'close_tabs' | 'restore_audio';
+
+export interface DeactivationAction {
+  id: string;
+  profileId: string;
+  position: number;
+  actionType: DeactivationActionType;
+  config: Record<string, unknown>;
+}
+
+export interface DeactivationActionInput {
+  actionType: DeactivationActionType;
+  config?: Record<string, unknown>;
+}
+
+export interface DeactivationResult {
+  position: number;
+  actionType: DeactivationActionType;
+  success: boolean;
+  message: string;
+  durationMs: number;
+}
+
+/** Cleanup run by the outgoing profile, stored in the next activation's metadata */
+export interface DeactivationPhase {
+  profileId: string;
+  success: boolean;
+  durationMs: number;
+  actions: DeactivationResult[];
+}
+
+/** What happens to a blocked app launched while its profile is active */
+export type BlockedAppAction = 'hide' | 'quit';
+
+export interface BlockedApp {
+  id: string;
+  profileId: string;
+  bundleId: string;
+  appName: string;
+  action: BlockedAppAction;
+}
+
+export interface BlockedAppInput {
+  bundleId: string;
+  appName: string;
+  action: BlockedAppAction;
+}
+
+/** `until` is unset when the override lasts until another profile is activated */
+export interface BlockedAppOverride {
+  profileId: string;
+  bundleId: string;
+  until?: string | null;
+}
+
+export interface ChecklistItem {
+  id: string;
+  profileId: string;
+  position: number;
+  label: string;
+}
+
+/** Pass `id` to keep an existing item and what's been checked off against it */
+export interface ChecklistItemInput {
+  id?: string;
+  label: string;
+}
+
+export interface ChecklistItemStatus {
+  id: string;
+  label: string;
+  position: number;
+  completed: boolean;
+  completedAt: string | null;
+}
+
+/** A profile's checklist and what's been checked since it was last activated */
+export interface ActivationChecklist {
+  profileId: string;
+  activatedAt: string | null;
+  items: ChecklistItemStatus[];
+}
+
+export interface PowerState {
+  source: 'ac' | 'battery' | 'unknown';
+  batteryPercent: number | null;
+  isCharging: boolean | null;
 }
 
 export interface SuccessResponse<T> {
   success: boolean;
   data: T;
+  requestId: string;
+  durationMs: number;
+}
+
+// Rejection value of a failed command; quote requestId when reporting a bug
+export interface CommandError {
+  message: string;
+  requestId: string;
+  fields?: Record<string, string[]>;
+  /** How far a failed profile start got, with `code` "activation_failed" */
+  steps?: ActivationStepReport[];
+  /**
+   * `starting_up` when the call came before startup finished; retry it.
+   * `profile_locked` when the profile must be unlocked first.
+   * `read_only` when the machine is in read-only mode.
+   * `db_offline` when the database can't be reached.
+   * `activation_failed` when a profile start failed part way; see `steps`.
+   */
+  code?: 'starting_up' | 'profile_locked' | 'read_only' | 'db_offline' | 'activation_failed';
+}
+
+export function isStartingUp(err: unknown): boolean {
+  return !!err && typeof err === 'object' && (err as CommandError).code === 'starting_up';
+}
+
+export type StartupStage =
+  | 'config'
+  | 'database'
+  | 'migrations'
+  | 'auditSession'
+  | 'watchers'
+  | 'window'
+  | 'ready';
+
+export interface StartupStatus {
+  stage: StartupStage;
+  ready: boolean;
+  readyAfterMs: number | null;
+}
+
+/** Command API version this frontend is built against */
+export const API_VERSION = 1;
+
+/** `compatible` is null when no client version was sent */
+/** Whether Postgres answers; while `online` is false the UI is in offline mode */
+export interface ConnectionStatus {
+  online: boolean;
+  /** When the connection last went down or came back */
+  since: string;
+  checkedAt: string | null;
+  lastError: string | null;
+  consecutiveFailures: number;
+  outages: number;
+}
+
+export interface ApiVersion {
+  version: number;
+  minClientVersion: number;
+  appVersion: string;
+  compatible: boolean | null;
+  capabilities: string[];
+  /** Edits are rejected with code `read_only`; only activation and reads work */
+  readOnly: boolean;
+}
+
+export function commandErrorMessage(err: unknown): string {
+  if (err instanceof Error) return err.message;
+  if (err && typeof err === 'object' && 'message' in err) {
+    return String((err as CommandError).message);
+  }
+  return String(err);
 }
 
 // Activity and Analytics types
@@ -152,6 +616,24 @@ export interface ProfileActivation {
   durationSeconds?: number;
   success: boolean;
   errorMessage?: string;
+  previousProfileId?: string;
+  metadata?: { deactivation?: DeactivationPhase; [key: string]: unknown };
+}
+
+/** Undo history is kept in memory for the last 50 edits of each profile */
+export interface EditState {
+  canUndo: boolean;
+  canRedo: boolean;
+}
+
+export interface RevertedEdit {
+  entityType: 'profile' | 'monitor' | 'app' | 'browser_tab';
+  /** A restored item comes back with a new id */
+  entityId: string;
+  fields: string[];
+  /** An item was re-created (undo) or deleted again (redo) */
+  restored: boolean;
+  state: EditState;
 }
 
 export interface ActivityLog {
@@ -210,6 +692,34 @@ export function getCurrentUserId(): string {
   return currentUserId;
 }
 
+/** A saved copy of every profile; `pre_restore` ones are taken just before a restore */
+export interface Snapshot {
+  id: string;
+  kind: 'daily' | 'pre_restore';
+  takenAt: string;
+  profileCount: number;
+  sizeBytes: number;
+}
+
+export interface RestoreSnapshotResult {
+  snapshotId: string;
+  /** Existing profiles put back the way they were */
+  restored: number;
+  /** Profiles deleted since the snapshot, created again */
+  recreated: number;
+  /** Restore this one to undo */
+  undoSnapshotId: string;
+}
+
+/** Profile names affected by a declarative sync */
+export interface DeclarativeSyncReport {
+  created: string[];
+  updated: string[];
+  removed: string[];
+  unchanged: number;
+  dryRun: boolean;
+}
+
 // Profile API
 export const profileApi = {
   async getProfiles(userId: string = currentUserId): Promise<Profile[]> {
@@ -261,129 +771,504 @@ export const profileApi = {
     return response.data;
   },
 
-  async deleteProfile(profileId: string): Promise<void> {
-    await invoke<SuccessResponse<string>>('delete_profile', { profileId });
+  async setLowPowerThreshold(profileId: string, threshold: number | null): Promise<Profile> {
+    const response = await invoke<SuccessResponse<Profile>>('set_profile_low_power_threshold', {
+      profileId,
+      threshold,
+    });
+    return response.data;
   },
 
-  async activateProfile(profileId: string, userId: string = currentUserId): Promise<Profile> {
-    const response = await invoke<SuccessResponse<Profile>>('activate_profile', { profileId, userId });
+  async setLocalOnly(profileId: string, localOnly: boolean): Promise<Profile> {
+    const response = await invoke<SuccessResponse<Profile>>('set_profile_local_only', {
+      profileId,
+      localOnly,
+    });
     return response.data;
   },
 
-  async duplicateProfile(profileId: string, userId: string = currentUserId): Promise<Profile> {
-    const response = await invoke<SuccessResponse<Profile>>('duplicate_profile', { profileId, userId });
+  async setVirtualDisplays(profileId: string, include: boolean): Promise<Profile> {
+    const response = await invoke<SuccessResponse<Profile>>('set_profile_virtual_displays', {
+      profileId,
+      include,
+    });
     return response.data;
   },
 
-  async startProfile(profileId: string, userId: string = currentUserId): Promise<StartProfileResult> {
-    const response = await invoke<SuccessResponse<StartProfileResult>>('start_profile', { profileId, userId });
+  /** Locked profiles still activate, but edits fail with code "profile_locked" until unlocked. */
+  async setLocked(profileId: string, locked: boolean): Promise<Profile> {
+    const response = await invoke<SuccessResponse<Profile>>('set_profile_locked', {
+      profileId,
+      locked,
+    });
     return response.data;
   },
-};
 
-// Monitor API
-export const monitorApi = {
-  async getMonitors(profileId: string): Promise<Monitor[]> {
-    const response = await invoke<SuccessResponse<Monitor[]>>('get_monitors', { profileId });
+  /**
+   * Bind a profile to a monitor count and/or display setup so it is switched to when
+   * the displays change. `useCurrentMonitors` binds to the displays connected now.
+   */
+  async setMonitorBinding(
+    profileId: string,
+    binding: {
+      requiredMonitorCount?: number | null;
+      monitorSignature?: string | null;
+      useCurrentMonitors?: boolean;
+    }
+  ): Promise<Profile> {
+    const response = await invoke<SuccessResponse<Profile>>('set_profile_monitor_binding', {
+      profileId,
+      ...binding,
+    });
     return response.data;
   },
 
-  async createMonitor(monitor: Omit<Monitor, 'id' | 'createdAt' | 'updatedAt'>): Promise<Monitor> {
-    const response = await invoke<SuccessResponse<Monitor>>('create_monitor', { 
-      profileId: monitor.profileId,
-      name: monitor.name,
-      resolution: monitor.resolution,
-      orientation: monitor.orientation,
-      isPrimary: monitor.isPrimary,
-      x: monitor.x,
-      y: monitor.y,
-      width: monitor.width,
-      height: monitor.height,
-      displayIndex: monitor.displayIndex,
-      brand: monitor.brand,
-      model: monitor.model,
-      refreshRate: monitor.refreshRate,
-      scaleFactor: monitor.scaleFactor,
-      isBuiltin: monitor.isBuiltin,
-      colorDepth: monitor.colorDepth,
-    });
+  /** The profile the connected displays would switch to; null if none fits */
+  async resolveForMonitors(userId: string = currentUserId): Promise<ProfileMatch | null> {
+    const response = await invoke<SuccessResponse<ProfileMatch | null>>(
+      'resolve_profile_for_monitors',
+      { userId }
+    );
     return response.data;
   },
 
-  async updateMonitor(monitorId: string, updates: Partial<Monitor>): Promise<Monitor> {
-    const response = await invoke<SuccessResponse<Monitor>>('update_monitor', { 
-      monitorId,
-      ...updates,
+  /** Unlisted profiles keep their relative order after the listed ones */
+  async reorderProfiles(idsInOrder: string[], userId: string = currentUserId): Promise<Profile[]> {
+    const response = await invoke<SuccessResponse<Profile[]>>('reorder_profiles', {
+      userId,
+      idsInOrder,
     });
     return response.data;
   },
 
-  async deleteMonitor(monitorId: string): Promise<void> {
-    await invoke<SuccessResponse<string>>('delete_monitor', { monitorId });
+  /**
+   * Edits to a profile and its monitors, apps and tabs, newest first. Updates carry
+   * `details.changes` as `{ field, old, new }[]`; deletes carry the removed item in
+   * `details.deleted`.
+   */
+  async getHistory(profileId: string, limit?: number): Promise<ActivityLog[]> {
+    const response = await invoke<SuccessResponse<ActivityLog[]>>('get_profile_history', {
+      profileId,
+      limit,
+    });
+    return response.data;
   },
-};
 
-// App API
-export const appApi = {
-  async getApps(profileId: string): Promise<App[]> {
-    const response = await invoke<SuccessResponse<App[]>>('get_apps', { profileId });
+  /** Revert the profile's last edit, restoring a deleted monitor, app or tab; null if none */
+  async undoLastChange(profileId: string): Promise<RevertedEdit | null> {
+    const response = await invoke<SuccessResponse<RevertedEdit | null>>('undo_last_change', {
+      profileId,
+    });
     return response.data;
   },
 
-  async createApp(app: Omit<App, 'id' | 'createdAt' | 'updatedAt'>, userId: string = currentUserId): Promise<App> {
-    const response = await invoke<SuccessResponse<App>>('create_app', {
-      profileId: app.profileId,
-      userId,
-      name: app.name,
-      bundleId: app.bundleId,
-      exePath: app.exePath,
-      launchOnActivate: app.launchOnActivate,
-      monitorPreference: app.monitorPreference,
-      iconPath: app.iconPath,
-      launchArgs: app.launchArgs,
-      workingDirectory: app.workingDirectory,
-      startupDelayMs: app.startupDelayMs,
-      orderIndex: app.orderIndex,
+  async redoLastChange(profileId: string): Promise<RevertedEdit | null> {
+    const response = await invoke<SuccessResponse<RevertedEdit | null>>('redo_last_change', {
+      profileId,
     });
     return response.data;
   },
 
-  async updateApp(appId: string, updates: Partial<App>): Promise<App> {
-    const response = await invoke<SuccessResponse<App>>('update_app', { appId, ...updates });
+  async listSnapshots(): Promise<Snapshot[]> {
+    const response = await invoke<SuccessResponse<Snapshot[]>>('list_snapshots');
     return response.data;
   },
 
-  async deleteApp(appId: string): Promise<void> {
-    await invoke<SuccessResponse<string>>('delete_app', { appId });
+  /** Restores every profile in the snapshot unless `profileIds` names some */
+  async restoreSnapshot(
+    snapshotId: string,
+    profileIds?: string[],
+    userId: string = currentUserId
+  ): Promise<RestoreSnapshotResult> {
+    const response = await invoke<SuccessResponse<RestoreSnapshotResult>>('restore_snapshot', {
+      userId,
+      snapshotId,
+      profileIds,
+    });
+    return response.data;
   },
 
-  async launchApps(profileId: string): Promise<LaunchResult[]> {
-    const response = await invoke<SuccessResponse<LaunchResult[]>>('launch_apps', { profileId });
+  async getEditState(profileId: string): Promise<EditState> {
+    const response = await invoke<SuccessResponse<EditState>>('get_profile_edit_state', {
+      profileId,
+    });
     return response.data;
   },
-};
 
-// Browser Tab API
-export const browserTabApi = {
-  async getBrowserTabs(profileId: string): Promise<BrowserTab[]> {
-    const response = await invoke<SuccessResponse<BrowserTab[]>>('get_browser_tabs', { profileId });
+  async getDeactivationActions(profileId: string): Promise<DeactivationAction[]> {
+    const response = await invoke<SuccessResponse<DeactivationAction[]>>(
+      'get_profile_deactivation_actions',
+      { profileId }
+    );
     return response.data;
   },
 
-  async createBrowserTab(tab: Omit<BrowserTab, 'id' | 'createdAt' | 'updatedAt'>): Promise<BrowserTab> {
-    const response = await invoke<SuccessResponse<BrowserTab>>('create_browser_tab', {
-      profileId: tab.profileId,
-      url: tab.url,
-      browser: tab.browser,
-      monitorId: tab.monitorId || null,
-      tabOrder: tab.tabOrder,
-      favicon: tab.favicon,
-    });
+  async setDeactivationActions(
+    profileId: string,
+    actions: DeactivationActionInput[]
+  ): Promise<DeactivationAction[]> {
+    const response = await invoke<SuccessResponse<DeactivationAction[]>>(
+      'set_profile_deactivation_actions',
+      { profileId, actions }
+    );
     return response.data;
   },
 
-  async updateBrowserTab(tabId: string, updates: Partial<BrowserTab>): Promise<BrowserTab> {
-    const response = await invoke<SuccessResponse<BrowserTab>>('update_browser_tab', { tabId, ...updates });
+  async getBlockedApps(profileId: string): Promise<BlockedApp[]> {
+    const response = await invoke<SuccessResponse<BlockedApp[]>>('get_profile_blocked_apps', {
+      profileId,
+    });
+    return response.data;
+  },
+
+  async setBlockedApps(profileId: string, apps: BlockedAppInput[]): Promise<BlockedApp[]> {
+    const response = await invoke<SuccessResponse<BlockedApp[]>>('set_profile_blocked_apps', {
+      profileId,
+      apps,
+    });
+    return response.data;
+  },
+
+  /** Keep a blocked app open under the active profile, for a while or until the profile changes */
+  async allowBlockedApp(
+    bundleId: string,
+    durationMinutes?: number,
+    userId: string = currentUserId
+  ): Promise<BlockedAppOverride> {
+    const response = await invoke<SuccessResponse<BlockedAppOverride>>('allow_blocked_app', {
+      userId,
+      bundleId,
+      durationMinutes,
+    });
+    return response.data;
+  },
+
+  async getChecklist(profileId: string): Promise<ChecklistItem[]> {
+    const response = await invoke<SuccessResponse<ChecklistItem[]>>('get_profile_checklist', {
+      profileId,
+    });
+    return response.data;
+  },
+
+  async setChecklist(profileId: string, items: ChecklistItemInput[]): Promise<ChecklistItem[]> {
+    const response = await invoke<SuccessResponse<ChecklistItem[]>>('set_profile_checklist', {
+      profileId,
+      items,
+    });
+    return response.data;
+  },
+
+  async getActivationChecklist(profileId: string): Promise<ActivationChecklist> {
+    const response = await invoke<SuccessResponse<ActivationChecklist>>(
+      'get_activation_checklist',
+      { profileId }
+    );
+    return response.data;
+  },
+
+  /** Check an item off (or take the check back) for the current activation */
+  async setChecklistItemCompleted(
+    profileId: string,
+    itemId: string,
+    completed: boolean
+  ): Promise<ActivationChecklist> {
+    const response = await invoke<SuccessResponse<ActivationChecklist>>(
+      'set_checklist_item_completed',
+      { profileId, itemId, completed }
+    );
+    return response.data;
+  },
+
+  async deleteProfile(profileId: string): Promise<void> {
+    await invoke<SuccessResponse<string>>('delete_profile', { profileId });
+  },
+
+  async activateProfile(profileId: string, userId: string = currentUserId): Promise<Profile> {
+    const response = await invoke<SuccessResponse<Profile>>('activate_profile', { profileId, userId });
+    return response.data;
+  },
+
+  async duplicateProfile(profileId: string, userId: string = currentUserId): Promise<Profile> {
+    const response = await invoke<SuccessResponse<Profile>>('duplicate_profile', { profileId, userId });
+    return response.data;
+  },
+
+  /** Upload a sanitized copy and get back a code others can import it with */
+  async share(profileId: string): Promise<ProfileShare> {
+    const response = await invoke<SuccessResponse<ProfileShare>>('share_profile', { profileId });
+    return response.data;
+  },
+
+  /**
+   * Make profiles match a YAML/TOML profile file: create, update and delete the
+   * ones it manages. `dryRun` only reports what would change.
+   */
+  async syncFromDeclarative(
+    path: string,
+    dryRun = false,
+    userId: string = currentUserId
+  ): Promise<DeclarativeSyncReport> {
+    const response = await invoke<SuccessResponse<DeclarativeSyncReport>>('sync_from_declarative', {
+      userId,
+      path,
+      dryRun,
+    });
+    return response.data;
+  },
+
+  /** A standalone shell script (displayplacer + open) that reproduces the profile */
+  async exportAsScript(profileId: string): Promise<string> {
+    const response = await invoke<SuccessResponse<string>>('export_profile_as_script', {
+      profileId,
+    });
+    return response.data;
+  },
+
+  async importShared(code: string, userId: string = currentUserId): Promise<Profile> {
+    const response = await invoke<SuccessResponse<Profile>>('import_shared_profile', { userId, code });
+    return response.data;
+  },
+
+  /** `steps` picks which activation steps run and in what order; all of them by default */
+  async startProfile(
+    profileId: string,
+    userId: string = currentUserId,
+    steps?: ActivationStepKind[]
+  ): Promise<StartProfileResult> {
+    const response = await invoke<SuccessResponse<StartProfileResult>>('start_profile', {
+      profileId,
+      userId,
+      steps,
+    });
+    return response.data;
+  },
+
+  async getInterruptedActivations(): Promise<InterruptedActivation[]> {
+    const response = await invoke<SuccessResponse<InterruptedActivation[]>>(
+      'get_interrupted_activations'
+    );
+    return response.data;
+  },
+
+  /** Runs the steps the interrupted activation hadn't finished */
+  async resumeInterruptedActivation(journalId: string): Promise<StartProfileResult> {
+    const response = await invoke<SuccessResponse<StartProfileResult>>(
+      'resume_interrupted_activation',
+      { journalId }
+    );
+    return response.data;
+  },
+
+  /** Undoes the steps the interrupted activation got through, newest first */
+  async rollbackInterruptedActivation(journalId: string): Promise<ActivationStepReport[]> {
+    const response = await invoke<SuccessResponse<ActivationStepReport[]>>(
+      'rollback_interrupted_activation',
+      { journalId }
+    );
+    return response.data;
+  },
+
+  async discardInterruptedActivation(journalId: string): Promise<void> {
+    await invoke<SuccessResponse<null>>('discard_interrupted_activation', { journalId });
+  },
+
+  /** The post-mortem kept for a failed activation */
+  async getActivationReport(activationId: string): Promise<ActivationReport> {
+    const response = await invoke<SuccessResponse<ActivationReport>>('get_activation_report', {
+      activationId,
+    });
+    return response.data;
+  },
+};
+
+// Monitor API
+export const monitorApi = {
+  async getMonitors(profileId: string): Promise<Monitor[]> {
+    const response = await invoke<SuccessResponse<Monitor[]>>('get_monitors', { profileId });
+    return response.data;
+  },
+
+  async createMonitor(monitor: Omit<Monitor, 'id' | 'createdAt' | 'updatedAt'>): Promise<Monitor> {
+    const response = await invoke<SuccessResponse<Monitor>>('create_monitor', { 
+      profileId: monitor.profileId,
+      name: monitor.name,
+      resolution: monitor.resolution,
+      orientation: monitor.orientation,
+      isPrimary: monitor.isPrimary,
+      x: monitor.x,
+      y: monitor.y,
+      width: monitor.width,
+      height: monitor.height,
+      displayIndex: monitor.displayIndex,
+      brand: monitor.brand,
+      model: monitor.model,
+      refreshRate: monitor.refreshRate,
+      scaleFactor: monitor.scaleFactor,
+      isBuiltin: monitor.isBuiltin,
+      colorDepth: monitor.colorDepth,
+    });
+    return response.data;
+  },
+
+  async updateMonitor(monitorId: string, updates: Partial<Monitor>): Promise<Monitor> {
+    const response = await invoke<SuccessResponse<Monitor>>('update_monitor', { 
+      monitorId,
+      ...updates,
+    });
+    return response.data;
+  },
+
+  /** Keep a captured display dark while its profile is active */
+  async setBlanked(monitorId: string, blanked: boolean): Promise<Monitor> {
+    const response = await invoke<SuccessResponse<Monitor>>('set_monitor_blanked', { monitorId, blanked });
+    return response.data;
+  },
+
+  /** Save the connected displays; ones the profile already has are updated in place */
+  async captureLayout(profileId: string): Promise<Monitor[]> {
+    const response = await invoke<SuccessResponse<Monitor[]>>('capture_monitor_layout', { profileId });
+    return response.data;
+  },
+
+  /** Remove captured monitors the latest capture didn't see; returns the removed ones */
+  async pruneStaleMonitors(profileId: string): Promise<Monitor[]> {
+    const response = await invoke<SuccessResponse<Monitor[]>>('prune_stale_monitors', { profileId });
+    return response.data;
+  },
+
+  /** Move saved windows left on disconnected displays onto the primary display */
+  async recoverOffscreenWindows(profileId: string): Promise<RecoveredWindows> {
+    const response = await invoke<SuccessResponse<RecoveredWindows>>('recover_offscreen_windows', { profileId });
+    return response.data;
+  },
+
+  /** Open a saved window zoomed in or out when the profile launches its app */
+  async setWindowZoom(windowId: string, zoomSteps: number | null): Promise<SavedWindow> {
+    const response = await invoke<SuccessResponse<SavedWindow>>('set_window_zoom', {
+      windowId,
+      zoomSteps,
+    });
+    return response.data;
+  },
+
+  async deleteMonitor(monitorId: string): Promise<void> {
+    await invoke<SuccessResponse<string>>('delete_monitor', { monitorId });
+  },
+};
+
+// App API
+export const appApi = {
+  async getApps(profileId: string): Promise<App[]> {
+    const response = await invoke<SuccessResponse<App[]>>('get_apps', { profileId });
+    return response.data;
+  },
+
+  /** Unlisted apps keep their relative order after the listed ones */
+  async reorderApps(profileId: string, idsInOrder: string[]): Promise<App[]> {
+    const response = await invoke<SuccessResponse<App[]>>('reorder_apps', { profileId, idsInOrder });
+    return response.data;
+  },
+
+  async createApp(app: Omit<App, 'id' | 'createdAt' | 'updatedAt'>, userId: string = currentUserId): Promise<App> {
+    const response = await invoke<SuccessResponse<App>>('create_app', {
+      profileId: app.profileId,
+      userId,
+      name: app.name,
+      bundleId: app.bundleId,
+      exePath: app.exePath,
+      launchOnActivate: app.launchOnActivate,
+      monitorPreference: app.monitorPreference,
+      iconPath: app.iconPath,
+      launchArgs: app.launchArgs,
+      workingDirectory: app.workingDirectory,
+      startupDelayMs: app.startupDelayMs,
+      orderIndex: app.orderIndex,
+    });
+    return response.data;
+  },
+
+  async updateApp(appId: string, updates: Partial<App>): Promise<App> {
+    const response = await invoke<SuccessResponse<App>>('update_app', { appId, ...updates });
+    return response.data;
+  },
+
+  async deleteApp(appId: string): Promise<void> {
+    await invoke<SuccessResponse<string>>('delete_app', { appId });
+  },
+
+  async getMissingApps(profileId: string): Promise<MissingApp[]> {
+    const response = await invoke<SuccessResponse<MissingApp[]>>('get_missing_apps', {
+      profileId,
+    });
+    return response.data;
+  },
+
+  /** Swaps a profile app for an installed one, keeping its saved windows */
+  async replaceProfileApp(oldAppId: string, newBundleId: string): Promise<App> {
+    const response = await invoke<SuccessResponse<App>>('replace_profile_app', {
+      oldAppId,
+      newBundleId,
+    });
+    return response.data;
+  },
+
+  async launchApps(profileId: string): Promise<LaunchResult[]> {
+    const response = await invoke<SuccessResponse<LaunchResult[]>>('launch_apps', { profileId });
+    return response.data;
+  },
+};
+
+// Browser Tab API
+export const browserTabApi = {
+  async getBrowserTabs(profileId: string): Promise<BrowserTab[]> {
+    const response = await invoke<SuccessResponse<BrowserTab[]>>('get_browser_tabs', { profileId });
+    return response.data;
+  },
+
+  /** Chrome/Firefox bookmarks or a Firefox session file, before anything is created */
+  async previewBookmarkImport(
+    profileId: string,
+    path: string,
+    folder?: string
+  ): Promise<BookmarkImportPreview> {
+    const response = await invoke<SuccessResponse<BookmarkImportPreview>>(
+      'preview_bookmark_import',
+      { profileId, path, folder }
+    );
+    return response.data;
+  },
+
+  async importBookmarks(profileId: string, request: ImportBookmarksRequest): Promise<BrowserTab[]> {
+    const response = await invoke<SuccessResponse<BrowserTab[]>>('import_bookmarks', {
+      profileId,
+      request,
+    });
+    return response.data;
+  },
+
+  /** Unlisted tabs keep their relative order after the listed ones */
+  async reorderBrowserTabs(profileId: string, idsInOrder: string[]): Promise<BrowserTab[]> {
+    const response = await invoke<SuccessResponse<BrowserTab[]>>('reorder_tabs', {
+      profileId,
+      idsInOrder,
+    });
+    return response.data;
+  },
+
+  async createBrowserTab(tab: Omit<BrowserTab, 'id' | 'createdAt' | 'updatedAt'>): Promise<BrowserTab> {
+    const response = await invoke<SuccessResponse<BrowserTab>>('create_browser_tab', {
+      profileId: tab.profileId,
+      url: tab.url,
+      browser: tab.browser,
+      monitorId: tab.monitorId || null,
+      tabOrder: tab.tabOrder,
+      favicon: tab.favicon,
+    });
+    return response.data;
+  },
+
+  async updateBrowserTab(tabId: string, updates: Partial<BrowserTab>): Promise<BrowserTab> {
+    const response = await invoke<SuccessResponse<BrowserTab>>('update_browser_tab', { tabId, ...updates });
     return response.data;
   },
 
@@ -398,6 +1283,12 @@ export const browserTabApi = {
 };
 
 // Automation API
+/** `until` is unset while paused until resumed */
+export interface AutomationPause {
+  paused: boolean;
+  until?: string | null;
+}
+
 export const automationApi = {
   async getRules(profileId: string): Promise<AutomationRule[]> {
     const response = await invoke<SuccessResponse<AutomationRule[]>>('get_rules', { profileId });
@@ -409,22 +1300,179 @@ export const automationApi = {
       profileId: rule.profileId,
       ruleType: rule.ruleType,
       triggerConfig: rule.triggerConfig,
+      conditions: rule.conditions ?? null,
       isEnabled: rule.isEnabled,
     });
     return response.data;
   },
 
+  async setRuleConditions(
+    ruleId: string,
+    conditions: Record<string, unknown> | null
+  ): Promise<AutomationRule> {
+    const response = await invoke<SuccessResponse<AutomationRule>>('set_rule_conditions', {
+      ruleId,
+      conditions,
+    });
+    return response.data;
+  },
+
+  async setRulePriority(
+    ruleId: string,
+    priority: number,
+    stopAfterMatch: boolean,
+    cooldownMinutes: number
+  ): Promise<AutomationRule> {
+    const response = await invoke<SuccessResponse<AutomationRule>>('set_rule_priority', {
+      ruleId,
+      priority,
+      stopAfterMatch,
+      cooldownMinutes,
+    });
+    return response.data;
+  },
+
+  async snoozeRule(ruleId: string, hours: number): Promise<AutomationRule> {
+    const response = await invoke<SuccessResponse<AutomationRule>>('snooze_rule', { ruleId, hours });
+    return response.data;
+  },
+
+  async setRuleActiveHours(
+    ruleId: string,
+    activeHours: ActiveHoursWindow[] | null
+  ): Promise<AutomationRule> {
+    const response = await invoke<SuccessResponse<AutomationRule>>('set_rule_active_hours', {
+      ruleId,
+      activeHours,
+    });
+    return response.data;
+  },
+
+  async getRuleActions(ruleId: string): Promise<RuleAction[]> {
+    const response = await invoke<SuccessResponse<RuleAction[]>>('get_rule_actions', { ruleId });
+    return response.data;
+  },
+
+  async setRuleActions(ruleId: string, actions: RuleActionInput[]): Promise<RuleAction[]> {
+    const response = await invoke<SuccessResponse<RuleAction[]>>('set_rule_actions', {
+      ruleId,
+      actions,
+    });
+    return response.data;
+  },
+
+  /**
+   * Run the rule engine against a synthetic event without executing anything.
+   * `payload` overrides condition context fields; `at` ("YYYY-MM-DDTHH:MM") sets the local time.
+   */
+  async simulateTrigger(
+    triggerType: string,
+    payload?: Partial<ConditionContext> & { at?: string }
+  ): Promise<SimulationResult> {
+    const response = await invoke<SuccessResponse<SimulationResult>>('simulate_trigger', {
+      triggerType,
+      payload: payload ?? null,
+    });
+    return response.data;
+  },
+
+  async testRule(ruleId: string): Promise<RuleTestResult> {
+    const response = await invoke<SuccessResponse<RuleTestResult>>('test_rule', { ruleId });
+    return response.data;
+  },
+
   async updateRule(ruleId: string, updates: Partial<AutomationRule>): Promise<AutomationRule> {
     const response = await invoke<SuccessResponse<AutomationRule>>('update_rule', { ruleId, ...updates });
     return response.data;
   },
 
-  async deleteRule(ruleId: string): Promise<void> {
-    await invoke<SuccessResponse<string>>('delete_rule', { ruleId });
+  async deleteRule(ruleId: string): Promise<void> {
+    await invoke<SuccessResponse<string>>('delete_rule', { ruleId });
+  },
+
+  async evaluateRules(profileId: string): Promise<void> {
+    await invoke<SuccessResponse<string>>('evaluate_rules', { profileId });
+  },
+
+  /**
+   * Presentation mode: rules stop firing and monitor changes stop switching profiles
+   * for `durationMinutes`, or until `resume` when omitted
+   */
+  async pause(durationMinutes?: number): Promise<AutomationPause> {
+    const response = await invoke<SuccessResponse<AutomationPause>>('pause_automation', {
+      durationMinutes,
+    });
+    return response.data;
+  },
+
+  async resume(): Promise<AutomationPause> {
+    const response = await invoke<SuccessResponse<AutomationPause>>('resume_automation');
+    return response.data;
+  },
+
+  async getPause(): Promise<AutomationPause> {
+    const response = await invoke<SuccessResponse<AutomationPause>>('get_automation_pause');
+    return response.data;
+  },
+};
+
+// Focus session API
+export interface StartFocusSessionRequest {
+  profileId: string;
+  durationMinutes: number;
+  /** Bundle IDs quit whenever they're running */
+  blockedApps?: string[];
+  /** URL prefixes whose tabs are closed in scriptable browsers */
+  blockedUrls?: string[];
+  /** Shortcuts.app shortcuts that turn Do Not Disturb on and back off */
+  dndShortcut?: string | null;
+  dndOffShortcut?: string | null;
+}
+
+export interface FocusSession {
+  id: string;
+  profileId: string;
+  plannedMinutes: number;
+  status: 'active' | 'completed' | 'cancelled';
+  blockedApps: string[];
+  blockedUrls: string[];
+  dndEnabled: boolean;
+  blockedAppQuits: number;
+  startedAt: string;
+  endsAt: string;
+  endedAt?: string | null;
+  remainingSeconds?: number | null;
+}
+
+export const focusApi = {
+  async start(req: StartFocusSessionRequest, userId: string = currentUserId): Promise<FocusSession> {
+    const response = await invoke<SuccessResponse<FocusSession>>('start_focus_session', { userId, req });
+    return response.data;
+  },
+
+  async getActive(userId: string = currentUserId): Promise<FocusSession | null> {
+    const response = await invoke<SuccessResponse<FocusSession | null>>('get_active_focus_session', {
+      userId,
+    });
+    return response.data;
+  },
+
+  async cancel(userId: string = currentUserId): Promise<FocusSession> {
+    const response = await invoke<SuccessResponse<FocusSession>>('cancel_focus_session', { userId });
+    return response.data;
   },
 
-  async evaluateRules(profileId: string): Promise<void> {
-    await invoke<SuccessResponse<string>>('evaluate_rules', { profileId });
+  async getSessions(
+    profileId?: string,
+    limit?: number,
+    userId: string = currentUserId
+  ): Promise<FocusSession[]> {
+    const response = await invoke<SuccessResponse<FocusSession[]>>('get_focus_sessions', {
+      userId,
+      profileId,
+      limit,
+    });
+    return response.data;
   },
 };
 
@@ -444,8 +1492,12 @@ export interface SystemMonitor {
   isPrimary: boolean;
   isBuiltin: boolean;
   orientation: string;
+  kind: DisplayKind;
 }
 
+/** Sidecar, AirPlay and virtual displays are left out of monitor matching unless a profile includes them */
+export type DisplayKind = 'physical' | 'sidecar' | 'airPlay' | 'virtual';
+
 export interface SystemWindow {
   windowId: number;
   pid: number;
@@ -460,6 +1512,27 @@ export interface SystemWindow {
   isMinimized: boolean;
   isFullscreen: boolean;
   layer: number;
+  source: WindowSource;
+}
+
+/**
+ * `offScreen`: minimized, in a hidden app or on another Space.
+ * `accessibility`: only found through Accessibility, so `windowId` isn't a window server ID.
+ */
+export type WindowSource = 'onScreen' | 'offScreen' | 'accessibility';
+
+export interface UsbDevice {
+  name: string;
+  vendorId: number;
+  productId: number;
+  serialNumber: string | null;
+  isThunderbolt: boolean;
+}
+
+export interface BluetoothDevice {
+  name: string;
+  address: string | null;
+  minorType: string | null;
 }
 
 export interface RunningApp {
@@ -480,6 +1553,18 @@ export interface InstalledApp {
   category?: string;
 }
 
+export type BackgroundTaskState = 'running' | 'restarting' | 'completed' | 'failed';
+
+export interface BackgroundTaskStatus {
+  name: string;
+  state: BackgroundTaskState;
+  restarts: number;
+  startedAt: string;
+  finishedAt: string | null;
+  lastPanic: string | null;
+  lastPanicAt: string | null;
+}
+
 export interface CapturedLayout {
   capturedAt: string;
   monitors: SystemMonitor[];
@@ -487,8 +1572,56 @@ export interface CapturedLayout {
   runningApps: RunningApp[];
 }
 
+export interface UpdateInfo {
+  currentVersion: string;
+  latestVersion: string;
+  available: boolean;
+  notes?: string;
+  pubDate?: string;
+  downloadUrl?: string;
+  /** False when only the Tauri updater can install the release */
+  verifiable: boolean;
+}
+
+export interface ReleaseNotes {
+  version: string;
+  notes?: string;
+  pubDate?: string;
+}
+
+export interface DownloadedUpdate {
+  version: string;
+  /** The verified installer, ready to open */
+  path: string;
+  sha256: string;
+}
+
 // System API
 export const systemApi = {
+  /** Other commands reject with `code: 'starting_up'` until this reports ready */
+  async getStartupStatus(): Promise<StartupStatus> {
+    const response = await invoke<SuccessResponse<StartupStatus>>('get_startup_status');
+    return response.data;
+  },
+
+  async getDbConnectionStatus(): Promise<ConnectionStatus> {
+    const response = await invoke<SuccessResponse<ConnectionStatus>>('get_db_connection_status');
+    return response.data;
+  },
+
+  /** Called each time the database connection drops or comes back */
+  onDbConnectionChanged(listener: (status: ConnectionStatus) => void): Promise<UnlistenFn> {
+    return listen<ConnectionStatus>('db-connection-changed', (event) => listener(event.payload));
+  },
+
+  /** Which API the backend serves; check `capabilities` before showing newer features */
+  async getApiVersion(clientVersion: number = API_VERSION): Promise<ApiVersion> {
+    const response = await invoke<SuccessResponse<ApiVersion>>('get_api_version', {
+      clientVersion,
+    });
+    return response.data;
+  },
+
   async getConnectedMonitors(): Promise<SystemMonitor[]> {
     const response = await invoke<SuccessResponse<SystemMonitor[]>>('get_connected_monitors');
     return response.data;
@@ -504,8 +1637,44 @@ export const systemApi = {
     return response.data;
   },
 
-  async getInstalledApps(): Promise<InstalledApp[]> {
-    const response = await invoke<SuccessResponse<InstalledApp[]>>('get_installed_apps');
+  /** Attached USB/Thunderbolt devices, for `usb_device` rule triggers */
+  async getUsbDevices(): Promise<UsbDevice[]> {
+    const response = await invoke<SuccessResponse<UsbDevice[]>>('get_usb_devices');
+    return response.data;
+  },
+
+  /** Connected Bluetooth devices, for `bluetooth_device` rule triggers */
+  async getBluetoothDevices(): Promise<BluetoothDevice[]> {
+    const response = await invoke<SuccessResponse<BluetoothDevice[]>>('get_bluetooth_devices');
+    return response.data;
+  },
+
+  async checkForUpdates(): Promise<UpdateInfo> {
+    const response = await invoke<SuccessResponse<UpdateInfo>>('check_for_updates');
+    return response.data;
+  },
+
+  /** Notes for `version`, by default the version running */
+  async getReleaseNotes(version?: string): Promise<ReleaseNotes> {
+    const response = await invoke<SuccessResponse<ReleaseNotes>>('get_release_notes', { version });
+    return response.data;
+  },
+
+  /** Download the newest installer, kept only if its checksum matches */
+  async downloadUpdate(): Promise<DownloadedUpdate> {
+    const response = await invoke<SuccessResponse<DownloadedUpdate>>('download_update');
+    return response.data;
+  },
+
+  /** Watchers, rollups and startup jobs, with their restart history */
+  async getBackgroundTasks(): Promise<BackgroundTaskStatus[]> {
+    const response = await invoke<SuccessResponse<BackgroundTaskStatus[]>>('get_background_tasks');
+    return response.data;
+  },
+
+  /** Served from the list built at startup unless `refresh` rescans */
+  async getInstalledApps(refresh = false): Promise<InstalledApp[]> {
+    const response = await invoke<SuccessResponse<InstalledApp[]>>('get_installed_apps', { refresh });
     return response.data;
   },
 
@@ -519,6 +1688,12 @@ export const systemApi = {
     return response.data;
   },
 
+  /** Light up every blanked display; returns the display IDs released */
+  async releaseBlankedDisplays(): Promise<number[]> {
+    const response = await invoke<SuccessResponse<number[]>>('release_blanked_displays');
+    return response.data;
+  },
+
   async checkDisplayPermission(): Promise<boolean> {
     const response = await invoke<SuccessResponse<boolean>>('check_display_permission');
     return response.data;
@@ -528,6 +1703,59 @@ export const systemApi = {
     const response = await invoke<SuccessResponse<boolean>>('request_display_permission');
     return response.data;
   },
+
+  /** What can be done about a known failure */
+  async getFixes(errorCode: FailureCode): Promise<Fix[]> {
+    const response = await invoke<SuccessResponse<Fix[]>>('get_fixes', { errorCode });
+    return response.data;
+  },
+
+  async applyFix(action: FixAction, appId?: string): Promise<FixResult> {
+    const response = await invoke<SuccessResponse<FixResult>>('apply_fix', { action, appId });
+    return response.data;
+  },
+};
+
+// Blob storage API
+export type BlobKind = 'icon' | 'favicon' | 'attachment';
+
+export interface BlobUsage {
+  kind: BlobKind;
+  blobs: number;
+  bytes: number;
+  quota: number;
+}
+
+export interface BlobGcReport {
+  removed: number;
+  bytesFreed: number;
+}
+
+export const storageApi = {
+  /** Store bytes by content hash; save the returned `blob:` reference in their place */
+  async storeBlob(kind: BlobKind, data: Uint8Array): Promise<string> {
+    const response = await invoke<SuccessResponse<string>>('store_blob', {
+      kind,
+      data: Array.from(data),
+    });
+    return response.data;
+  },
+
+  async getBlob(reference: string): Promise<Uint8Array> {
+    const response = await invoke<SuccessResponse<number[]>>('get_blob', { reference });
+    return Uint8Array.from(response.data);
+  },
+
+  async getUsage(): Promise<BlobUsage[]> {
+    const response = await invoke<SuccessResponse<BlobUsage[]>>('get_storage_usage');
+    return response.data;
+  },
+
+  /** Remove blobs nothing refers to any more */
+  async collectGarbage(): Promise<BlobGcReport> {
+    const response = await invoke<SuccessResponse<BlobGcReport>>('collect_storage_garbage');
+    return response.data;
+  },
 };
 
 // User Preferences API
@@ -551,6 +1779,91 @@ export interface UserSettings {
   featureFlags?: Record<string, unknown>;
   keyboardShortcuts?: Record<string, string>;
   uiPreferences?: Record<string, unknown>;
+  /** Location for sunrise/sunset schedules; unset uses the device location */
+  latitude?: number | null;
+  longitude?: number | null;
+  syncScope: SyncScope;
+  /** Default profile per monitor count, used when no profile is bound more specifically */
+  monitorProfileMappings: MonitorProfileMapping[];
+  /** Save last week's usage report every Monday morning */
+  weeklyReportEnabled: boolean;
+  weeklyReportFormat: ReportFormat;
+  /** Daily profile snapshots kept; 0 turns them off */
+  snapshotsKept: number;
+  /** Declarative profile file synced whenever it's saved */
+  declarativeWatchPath?: string;
+  /** Start Smoothie when the user logs in */
+  launchAtLogin: boolean;
+  /** No Dock icon, and closing the window leaves monitor watching and automations running */
+  backgroundAgent: boolean;
+  /** Upload an encrypted diagnostic bundle to support when a critical error occurs */
+  diagnosticUploads: boolean;
+  /** Off unless the user opts in; see `getTelemetryPreview` for what is sent */
+  telemetryEnabled: boolean;
+  telemetrySentAt?: string | null;
+  /** displayplacer used instead of the one found on the system */
+  displayplacerPath?: string | null;
+}
+
+/** Anonymous counters sent once a week when telemetry is on */
+export interface TelemetryPayload {
+  format: number;
+  /** ISO week, e.g. `2026-W42` */
+  week: string;
+  appVersion: string;
+  osName: string;
+  /** Major.minor only */
+  osVersion?: string | null;
+  activationsLastWeek: number;
+  connectedMonitors: number;
+  profilesByMonitorCount: { monitors: number; profiles: number }[];
+}
+
+export interface MonitorProfileMapping {
+  monitorCount: number;
+  profileId: string;
+}
+
+export interface ShortcutConflict {
+  kind: 'smoothie' | 'system';
+  description: string;
+  /** The profile already bound to the shortcut */
+  profileId?: string | null;
+}
+
+export interface ShortcutValidation {
+  /** Canonical spelling, e.g. `Shift+Cmd+1` */
+  shortcut: string;
+  conflicts: ShortcutConflict[];
+}
+
+/** What cloud sync uploads; `all` adds the activity log to profiles and settings */
+export type SyncScope = 'profiles' | 'profiles_and_settings' | 'all';
+
+export interface SyncPayload {
+  scope: SyncScope;
+  profiles: Profile[];
+  settings?: UserSettings | null;
+  activityLogs: ActivityLog[];
+  /** Local-only profiles the sync leaves out */
+  withheldProfileIds: string[];
+}
+
+export interface FolderSyncStatus {
+  enabled: boolean;
+  folder?: string | null;
+}
+
+/** What one folder sync pass did */
+export interface FolderSyncReport {
+  exported: number;
+  imported: number;
+  /** Local profiles deleted because another machine deleted them */
+  deleted: number;
+  /** Profiles changed on both machines; the more recent edit was kept */
+  conflicts: number;
+  /** Bundles that couldn't be read, e.g. written under another passphrase */
+  unreadable: number;
 }
 
 export const userApi = {
@@ -570,6 +1883,191 @@ export const userApi = {
     return response.data;
   },
 
+  /** Pass nulls to fall back to the device location */
+  async setLocation(
+    latitude: number | null,
+    longitude: number | null,
+    userId: string = currentUserId
+  ): Promise<UserSettings> {
+    const response = await invoke<SuccessResponse<UserSettings>>('set_user_location', {
+      userId,
+      latitude,
+      longitude,
+    });
+    return response.data;
+  },
+
+  async setSyncScope(scope: SyncScope, userId: string = currentUserId): Promise<UserSettings> {
+    const response = await invoke<SuccessResponse<UserSettings>>('set_sync_scope', { userId, scope });
+    return response.data;
+  },
+
+  async setMonitorProfileMappings(
+    mappings: MonitorProfileMapping[],
+    userId: string = currentUserId
+  ): Promise<UserSettings> {
+    const response = await invoke<SuccessResponse<UserSettings>>('set_monitor_profile_mappings', {
+      userId,
+      mappings,
+    });
+    return response.data;
+  },
+
+  /** Check a shortcut before binding it; pass the profile it's for so its own binding isn't a conflict */
+  async validateShortcut(
+    shortcut: string,
+    profileId?: string,
+    userId: string = currentUserId
+  ): Promise<ShortcutValidation> {
+    const response = await invoke<SuccessResponse<ShortcutValidation>>('validate_shortcut', {
+      userId,
+      shortcut,
+      profileId,
+    });
+    return response.data;
+  },
+
+  /** Pass null to unbind; conflicting shortcuts are refused */
+  async setProfileShortcut(
+    profileId: string,
+    shortcut: string | null,
+    userId: string = currentUserId
+  ): Promise<UserSettings> {
+    const response = await invoke<SuccessResponse<UserSettings>>('set_profile_shortcut', {
+      userId,
+      profileId,
+      shortcut,
+    });
+    return response.data;
+  },
+
+  async setWeeklyReport(
+    enabled: boolean,
+    format?: ReportFormat,
+    userId: string = currentUserId
+  ): Promise<UserSettings> {
+    const response = await invoke<SuccessResponse<UserSettings>>('set_weekly_report', {
+      userId,
+      enabled,
+      format,
+    });
+    return response.data;
+  },
+
+  async setSnapshotsKept(kept: number, userId: string = currentUserId): Promise<UserSettings> {
+    const response = await invoke<SuccessResponse<UserSettings>>('set_snapshots_kept', {
+      userId,
+      kept,
+    });
+    return response.data;
+  },
+
+  /** Watch a YAML/TOML profile file; omit the path to stop watching */
+  async setDeclarativeWatchPath(path?: string, userId: string = currentUserId): Promise<UserSettings> {
+    const response = await invoke<SuccessResponse<UserSettings>>('set_declarative_watch_path', {
+      userId,
+      path,
+    });
+    return response.data;
+  },
+
+  /** Login launches start in the background when `backgroundAgent` is on */
+  async setLoginItem(
+    launchAtLogin: boolean,
+    backgroundAgent: boolean,
+    userId: string = currentUserId
+  ): Promise<UserSettings> {
+    const response = await invoke<SuccessResponse<UserSettings>>('set_login_item', {
+      userId,
+      launchAtLogin,
+      backgroundAgent,
+    });
+    return response.data;
+  },
+
+  /** Needs an active paid subscription to turn on; turning it off stops uploads at once */
+  async setDiagnosticUploads(
+    enabled: boolean,
+    userId: string = currentUserId
+  ): Promise<UserSettings> {
+    const response = await invoke<SuccessResponse<UserSettings>>('set_diagnostic_uploads', {
+      userId,
+      enabled,
+    });
+    return response.data;
+  },
+
+  async setTelemetryEnabled(
+    enabled: boolean,
+    userId: string = currentUserId
+  ): Promise<UserSettings> {
+    const response = await invoke<SuccessResponse<UserSettings>>('set_telemetry_enabled', {
+      userId,
+      enabled,
+    });
+    return response.data;
+  },
+
+  /** Use a displayplacer that isn't on the PATH; omit the path to go back to finding it */
+  async setDisplayplacerPath(path?: string, userId: string = currentUserId): Promise<UserSettings> {
+    const response = await invoke<SuccessResponse<UserSettings>>('set_displayplacer_path', {
+      userId,
+      path,
+    });
+    return response.data;
+  },
+
+  /** Exactly what the next weekly telemetry report would send */
+  async getTelemetryPreview(userId: string = currentUserId): Promise<TelemetryPayload> {
+    const response = await invoke<SuccessResponse<TelemetryPayload>>(
+      'get_telemetry_payload_preview',
+      { userId }
+    );
+    return response.data;
+  },
+
+  /** What the next cloud sync would upload under the current scope */
+  async getSyncPreview(userId: string = currentUserId): Promise<SyncPayload> {
+    const response = await invoke<SuccessResponse<SyncPayload>>('get_sync_preview', { userId });
+    return response.data;
+  },
+
+  /** Upload what the current scope allows now; null while cloud sync is off */
+  async pushCloudSync(userId: string = currentUserId): Promise<SyncPayload | null> {
+    const response = await invoke<SuccessResponse<SyncPayload | null>>('push_cloud_sync', {
+      userId,
+    });
+    return response.data;
+  },
+
+  /** Sync profiles through a folder such as iCloud Drive; every machine needs the same passphrase */
+  async enableFolderSync(
+    folder: string,
+    passphrase: string,
+    userId: string = currentUserId
+  ): Promise<FolderSyncReport> {
+    const response = await invoke<SuccessResponse<FolderSyncReport>>('enable_folder_sync', {
+      userId,
+      folder,
+      passphrase,
+    });
+    return response.data;
+  },
+
+  async disableFolderSync(userId: string = currentUserId): Promise<void> {
+    await invoke<SuccessResponse<null>>('disable_folder_sync', { userId });
+  },
+
+  async getFolderSyncStatus(): Promise<FolderSyncStatus> {
+    const response = await invoke<SuccessResponse<FolderSyncStatus>>('get_folder_sync_status');
+    return response.data;
+  },
+
+  async syncFolderNow(userId: string = currentUserId): Promise<FolderSyncReport> {
+    const response = await invoke<SuccessResponse<FolderSyncReport>>('sync_folder_now', { userId });
+    return response.data;
+  },
+
   // Keep old methods for backward compatibility
   async getPreferences(userId: string = currentUserId): Promise<UserSettings> {
     return this.getSettings(userId);
@@ -596,6 +2094,7 @@ export interface SystemEvent {
   stackTrace?: string;
   osInfo?: Record<string, unknown>;
   appVersion?: string;
+  requestId?: string;
   createdAt: string;
 }
 
@@ -607,22 +2106,105 @@ export interface GetSystemEventsParams {
   offset?: number;
 }
 
+export type ReportFormat = 'markdown' | 'html';
+
+/** Dates are `YYYY-MM-DD`; the default range is the last 7 days */
+export interface UsageReportRequest {
+  from?: string;
+  to?: string;
+  format?: ReportFormat;
+}
+
+export interface UsageReport {
+  from: string;
+  to: string;
+  format: ReportFormat;
+  /** Where the rendered report was saved */
+  path: string;
+  totalFocusMs: number;
+  totalActivations: number;
+  profiles: { profileId: string; name: string; activations: number; focusMs: number }[];
+  topApps: { bundleId: string; appName: string; focusMs: number; sessions: number; share: number }[];
+  errors: {
+    errorType: string;
+    message: string;
+    severity: string;
+    occurrences: number;
+    lastOccurredAt: string;
+  }[];
+}
+
+/** Errors sharing a fingerprint, for triage */
+export interface ErrorGroup {
+  fingerprint: string;
+  errorType: string;
+  /** Message of the latest occurrence */
+  message: string;
+  severity: string;
+  occurrences: number;
+  firstSeen: string;
+  lastSeen: string;
+  resolved: boolean;
+  regressions: number;
+  affectedVersions: string[];
+  /** Occurrences per day for the last 30 days, oldest first and ending today */
+  dailyOccurrences: number[];
+}
+
+/** What a sanitized log export redacts; each defaults to true */
+export interface SanitizeOptions {
+  urls?: boolean;
+  emails?: boolean;
+  filePaths?: boolean;
+  /** App names and bundle ids */
+  appNames?: boolean;
+}
+
+export interface SanitizedLogExport {
+  /** Where the export was saved */
+  path: string;
+  activityLogs: number;
+  systemEvents: number;
+  errorLogs: number;
+  redactions: { urls: number; emails: number; filePaths: number; appNames: number };
+}
+
 // Notification / Audit API
 export const notificationApi = {
   async getSystemEvents(params: GetSystemEventsParams = {}): Promise<SystemEvent[]> {
-    // Note: get_system_events returns Vec<SystemEventDto> directly, not wrapped in SuccessResponse
-    const events = await invoke<SystemEvent[]>('get_system_events', {
+    const response = await invoke<SuccessResponse<SystemEvent[]>>('get_system_events', {
       limit: params.limit,
       offset: params.offset,
       severity: params.severity,
       eventType: params.eventType,
     });
-    return events;
+    return response.data;
   },
 
   async getRecentNotifications(limit: number = 10): Promise<SystemEvent[]> {
     return this.getSystemEvents({ limit });
   },
+
+  async generateUsageReport(range: UsageReportRequest = {}): Promise<UsageReport> {
+    const response = await invoke<SuccessResponse<UsageReport>>('generate_usage_report', { range });
+    return response.data;
+  },
+
+  async getErrorGroups(includeResolved = false, limit = 50): Promise<ErrorGroup[]> {
+    const response = await invoke<SuccessResponse<ErrorGroup[]>>('get_error_groups', {
+      includeResolved,
+      limit,
+    });
+    return response.data;
+  },
+
+  /** Save recent logs with identifying details redacted, for public bug reports */
+  async exportSanitizedLogs(options: SanitizeOptions = {}): Promise<SanitizedLogExport> {
+    const response = await invoke<SuccessResponse<SanitizedLogExport>>('export_sanitized_logs', {
+      options,
+    });
+    return response.data;
+  },
 };
 
 // Feedback types
@@ -650,6 +2232,8 @@ export interface CreateFeedbackRequest {
   priority?: string;
   category?: string;
   contactEmail?: string;
+  /** `blob:` references from `storageApi.storeBlob('attachment', ...)` */
+  attachments?: string[];
 }
 
 // Feedback API
@@ -677,6 +2261,74 @@ export const feedbackApi = {
   },
 };
 
+// Team workspaces API
+export type TeamRole = 'admin' | 'member';
+
+export interface Team {
+  id: string;
+  name: string;
+  organizationId: string;
+  organizationName: string;
+  /** The current user's role; only admins can publish */
+  role: TeamRole;
+}
+
+export interface TeamLibraryProfile {
+  id: string;
+  teamId: string;
+  name: string;
+  description?: string;
+  profileType: ProfileType;
+  tags: string[];
+  monitorCount: number;
+  appCount: number;
+  browserTabCount: number;
+  publishedBy: string;
+  publishedAt: string;
+}
+
+export const teamApi = {
+  /** Pull teams and team libraries from Supabase */
+  async sync(userId: string = currentUserId): Promise<Team[]> {
+    const response = await invoke<SuccessResponse<Team[]>>('sync_teams', { userId });
+    return response.data;
+  },
+
+  async getTeams(userId: string = currentUserId): Promise<Team[]> {
+    const response = await invoke<SuccessResponse<Team[]>>('get_teams', { userId });
+    return response.data;
+  },
+
+  async getLibrary(teamId: string, userId: string = currentUserId): Promise<TeamLibraryProfile[]> {
+    const response = await invoke<SuccessResponse<TeamLibraryProfile[]>>('get_team_library', {
+      userId,
+      teamId,
+    });
+    return response.data;
+  },
+
+  async cloneProfile(libraryProfileId: string, userId: string = currentUserId): Promise<Profile> {
+    const response = await invoke<SuccessResponse<Profile>>('clone_team_profile', {
+      userId,
+      libraryProfileId,
+    });
+    return response.data;
+  },
+
+  async publishProfile(
+    teamId: string,
+    profileId: string,
+    userId: string = currentUserId
+  ): Promise<TeamLibraryProfile> {
+    const response = await invoke<SuccessResponse<TeamLibraryProfile>>('publish_team_profile', {
+      userId,
+      teamId,
+      profileId,
+    });
+    return response.data;
+  },
+};
+
 // Helper to check if running in Tauri environment
 export function isTauri(): boolean {
   return typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
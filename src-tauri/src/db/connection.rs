@@ -1,10 +1,93 @@
 // Database connection pool management for PostgreSQL
 
-use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::ConnectOptions;
+use std::str::FromStr;
 use std::time::Duration;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-pub async fn create_pool() -> anyhow::Result<PgPool> {
+/// Prepared statements kept per connection. sqlx's default of 100 is below the
+/// number of distinct queries the repositories issue, which made the hot ones
+/// get evicted and re-prepared.
+const STATEMENT_CACHE_CAPACITY: usize = 512;
+
+pub const MAX_CONNECTIONS_ENV: &str = "SMOOTHIE_DB_MAX_CONNECTIONS";
+pub const ACQUIRE_TIMEOUT_ENV: &str = "SMOOTHIE_DB_ACQUIRE_TIMEOUT_MS";
+pub const STATEMENT_TIMEOUT_ENV: &str = "SMOOTHIE_DB_STATEMENT_TIMEOUT_MS";
+pub const QUERY_TIMEOUT_ENV: &str = "SMOOTHIE_DB_QUERY_TIMEOUT_MS";
+pub const SLOW_QUERY_ENV: &str = "SMOOTHIE_DB_SLOW_QUERY_MS";
+
+/// Pool sizing and time limits, overridable through `SMOOTHIE_DB_*` variables
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+  pub max_connections: u32,
+  pub min_connections: u32,
+  /// How long a query waits for a free connection before failing
+  pub acquire_timeout: Duration,
+  /// Server-side `statement_timeout`; 0 in the environment disables it
+  pub statement_timeout: Option<Duration>,
+  /// Limit for operations wrapped in [`Database::with_timeout`](super::Database::with_timeout)
+  pub query_timeout: Duration,
+  /// Statements running longer are reported as slow queries (see
+  /// [`SlowQueryLayer`](crate::logging::SlowQueryLayer))
+  pub slow_query_threshold: Duration,
+}
+
+impl Default for PoolConfig {
+  fn default() -> Self {
+    Self {
+      max_connections: 5,
+      min_connections: 1,
+      acquire_timeout: Duration::from_secs(5),
+      statement_timeout: Some(Duration::from_secs(30)),
+      query_timeout: Duration::from_secs(10),
+      slow_query_threshold: Duration::from_millis(500),
+    }
+  }
+}
+
+impl PoolConfig {
+  pub fn from_env() -> Self {
+    let defaults = Self::default();
+    let statement_timeout_ms = env_or(
+      STATEMENT_TIMEOUT_ENV,
+      defaults
+        .statement_timeout
+        .map_or(0, |timeout| timeout.as_millis() as u64),
+    );
+
+    Self {
+      max_connections: env_or(MAX_CONNECTIONS_ENV, defaults.max_connections).max(1),
+      min_connections: defaults.min_connections,
+      acquire_timeout: Duration::from_millis(env_or(
+        ACQUIRE_TIMEOUT_ENV,
+        defaults.acquire_timeout.as_millis() as u64,
+      )),
+      statement_timeout: (statement_timeout_ms > 0)
+        .then(|| Duration::from_millis(statement_timeout_ms)),
+      query_timeout: Duration::from_millis(env_or(
+        QUERY_TIMEOUT_ENV,
+        defaults.query_timeout.as_millis() as u64,
+      )),
+      slow_query_threshold: Duration::from_millis(env_or(
+        SLOW_QUERY_ENV,
+        defaults.slow_query_threshold.as_millis() as u64,
+      )),
+    }
+  }
+}
+
+fn env_or<T: FromStr + Copy + std::fmt::Display>(name: &str, default: T) -> T {
+  match std::env::var(name) {
+    Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+      warn!("Ignoring {}={:?}; using {}", name, raw, default);
+      default
+    }),
+    Err(_) => default,
+  }
+}
+
+pub async fn create_pool(config: &PoolConfig) -> anyhow::Result<PgPool> {
   info!("Creating PostgreSQL connection pool");
   let start = std::time::Instant::now();
 
@@ -14,14 +97,23 @@ pub async fn create_pool() -> anyhow::Result<PgPool> {
   });
 
   info!("Connecting to PostgreSQL database");
+  // Every statement is logged at trace level, slow ones as warnings
+  let mut connect_options = PgConnectOptions::from_str(&database_url)?
+    .statement_cache_capacity(STATEMENT_CACHE_CAPACITY)
+    .log_statements(log::LevelFilter::Trace)
+    .log_slow_statements(log::LevelFilter::Warn, config.slow_query_threshold);
+  if let Some(timeout) = config.statement_timeout {
+    connect_options =
+      connect_options.options([("statement_timeout", format!("{}ms", timeout.as_millis()))]);
+  }
 
   let pool = PgPoolOptions::new()
-    .max_connections(5)
-    .min_connections(1)
-    .acquire_timeout(Duration::from_secs(30))
+    .max_connections(config.max_connections)
+    .min_connections(config.min_connections.min(config.max_connections))
+    .acquire_timeout(config.acquire_timeout)
     .idle_timeout(Duration::from_secs(600))
     .max_lifetime(Duration::from_secs(1800))
-    .connect(&database_url)
+    .connect_with(connect_options)
     .await;
 
   let duration = start.elapsed();
@@ -1,14 +1,140 @@
 // Database migrations for Smoothie schema
 // PostgreSQL version - v1
 
+use crate::models::enums::{
+  ActivationSource, ActivityStatus, BlockedAppAction, DeactivationActionType, FeedbackStatus,
+  FocusSessionStatus, MonitorChangeType, ProfileType, ReportFormat, RuleActionType, Severity,
+  SyncScope, TeamRole,
+};
+use crate::services::snapshot_service::MAX_SNAPSHOTS_KEPT;
+use crate::services::window_service::{MAX_ZOOM_STEPS, MIN_ZOOM_STEPS};
+use crate::utils::markdown::MAX_NOTES_LENGTH;
 use sqlx::PgPool;
-use tracing::info;
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::{info, warn};
+
+/// Schema version produced by `run`
+pub const SCHEMA_VERSION: i32 = 42;
+
+/// A table (and optionally a column, constraint or index, or `column::type`
+/// for a type change) each migration creates, used to tell which ones have
+/// been applied
+const VERSION_MARKERS: [(i32, &str, Option<&str>); 42] = [
+  (1, "profiles", None),
+  (2, "profile_files", None),
+  (3, "user_settings", Some("log_retention_days")),
+  (4, "error_logs", Some("fingerprint")),
+  (5, "alerts", None),
+  (6, "profiles", Some("profiles_type_check")),
+  (7, "error_logs", Some("request_id")),
+  (8, "daily_stats", None),
+  (9, "automation_rules", Some("conditions")),
+  (10, "automation_rules", Some("priority")),
+  (11, "automation_rules", Some("snoozed_until")),
+  (12, "rule_actions", None),
+  (13, "profiles", Some("low_power_battery_threshold")),
+  (14, "user_settings", Some("latitude")),
+  (15, "profile_deactivation_actions", None),
+  (16, "activity_logs", Some("idx_activity_logs_details_gin")),
+  (17, "profiles", Some("local_only")),
+  (18, "team_library_profiles", None),
+  (19, "monitors", Some("idx_monitors_profile_fingerprint")),
+  (20, "user_settings", Some("monitor_profile_mappings")),
+  (21, "app_focus_events", None),
+  (22, "focus_sessions", None),
+  (23, "profile_blocked_apps", None),
+  (24, "monitors", Some("blanked")),
+  (25, "user_settings", Some("weekly_report_enabled")),
+  (26, "folder_sync_items", None),
+  (27, "user_settings", Some("snapshots_kept")),
+  (28, "declarative_profiles", None),
+  (29, "user_settings", Some("declarative_watch_path")),
+  (30, "user_settings", Some("background_agent")),
+  (31, "user_settings", Some("diagnostic_uploads")),
+  (32, "user_settings", Some("telemetry_enabled")),
+  (33, "error_daily_counts", None),
+  (34, "profiles", Some("notes")),
+  (35, "profile_checklist_items", None),
+  (36, "windows", Some("zoom_steps")),
+  (37, "user_settings", Some("displayplacer_path")),
+  (38, "profiles", Some("include_virtual_displays")),
+  (39, "monitor_changes", Some("summary")),
+  (40, "activation_reports", None),
+  (41, "profiles", Some("is_locked")),
+  (42, "users", Some("id::uuid")),
+];
+
+/// `_id` columns kept as TEXT: they hold identifiers from outside the
+/// database rather than row references
+const TEXT_ID_COLUMNS: [&str; 3] = ["bundle_id", "device_id", "request_id"];
+
+/// Free-form payload columns, stored as JSONB so they can be queried by content
+const JSON_COLUMNS: [&str; 16] = [
+  "details",
+  "metadata",
+  "context",
+  "os_info",
+  "trigger_config",
+  "conditions",
+  "config",
+  "document",
+  "report",
+  "feature_flags",
+  "keyboard_shortcuts",
+  "ui_preferences",
+  "trigger_details",
+  "actions_taken",
+  "monitors_before",
+  "monitors_after",
+];
 
 pub async fn run(pool: &PgPool) -> anyhow::Result<()> {
   info!("Starting database migrations");
   let start = std::time::Instant::now();
 
   run_migration_v1(pool).await?;
+  // Every later migration references the v1 tables by UUID
+  run_migration_v42(pool).await?;
+  run_migration_v2(pool).await?;
+  run_migration_v3(pool).await?;
+  run_migration_v4(pool).await?;
+  run_migration_v5(pool).await?;
+  run_migration_v6(pool).await?;
+  run_migration_v7(pool).await?;
+  run_migration_v8(pool).await?;
+  run_migration_v9(pool).await?;
+  run_migration_v10(pool).await?;
+  run_migration_v11(pool).await?;
+  run_migration_v12(pool).await?;
+  run_migration_v13(pool).await?;
+  run_migration_v14(pool).await?;
+  run_migration_v15(pool).await?;
+  run_migration_v16(pool).await?;
+  run_migration_v17(pool).await?;
+  run_migration_v18(pool).await?;
+  run_migration_v19(pool).await?;
+  run_migration_v20(pool).await?;
+  run_migration_v21(pool).await?;
+  run_migration_v22(pool).await?;
+  run_migration_v23(pool).await?;
+  run_migration_v24(pool).await?;
+  run_migration_v25(pool).await?;
+  run_migration_v26(pool).await?;
+  run_migration_v27(pool).await?;
+  run_migration_v28(pool).await?;
+  run_migration_v29(pool).await?;
+  run_migration_v30(pool).await?;
+  run_migration_v31(pool).await?;
+  run_migration_v32(pool).await?;
+  run_migration_v33(pool).await?;
+  run_migration_v34(pool).await?;
+  run_migration_v35(pool).await?;
+  run_migration_v36(pool).await?;
+  run_migration_v37(pool).await?;
+  run_migration_v38(pool).await?;
+  run_migration_v39(pool).await?;
+  run_migration_v40(pool).await?;
+  run_migration_v41(pool).await?;
 
   let duration = start.elapsed();
   info!(
@@ -18,6 +144,47 @@ pub async fn run(pool: &PgPool) -> anyhow::Result<()> {
   Ok(())
 }
 
+/// Highest migration whose marker exists (0 for an empty database)
+pub async fn applied_version(pool: &PgPool) -> anyhow::Result<i32> {
+  let mut version = 0;
+  for (marker_version, table, column) in VERSION_MARKERS {
+    let exists: bool = sqlx::query_scalar(
+      r#"
+      SELECT EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_schema = current_schema()
+          AND table_name = $1
+          AND ($2::text IS NULL OR column_name = $2)
+      ) OR EXISTS (
+        SELECT 1 FROM information_schema.table_constraints
+        WHERE table_schema = current_schema()
+          AND table_name = $1
+          AND constraint_name = $2
+      ) OR EXISTS (
+        SELECT 1 FROM pg_indexes
+        WHERE schemaname = current_schema()
+          AND tablename = $1
+          AND indexname = $2
+      ) OR EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_schema = current_schema()
+          AND table_name = $1
+          AND column_name || '::' || udt_name = $2
+      )
+      "#,
+    )
+    .bind(table)
+    .bind(column)
+    .fetch_one(pool)
+    .await?;
+    if !exists {
+      break;
+    }
+    version = marker_version;
+  }
+  Ok(version)
+}
+
 /// Migration v1: Complete Smoothie schema for PostgreSQL
 async fn run_migration_v1(pool: &PgPool) -> anyhow::Result<()> {
   info!("Running migration v1: Complete schema setup");
@@ -644,3 +811,1668 @@ async fn run_migration_v1(pool: &PgPool) -> anyhow::Result<()> {
   info!("Migration v1 completed in {}ms", duration.as_millis());
   Ok(())
 }
+
+/// Migration v2: Documents and folders opened on profile activation
+async fn run_migration_v2(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v2: Profile files");
+  let start = std::time::Instant::now();
+
+  // Profile Files table - `target_app` overrides the default handler for the file
+  sqlx::query(
+    r#"
+    CREATE TABLE IF NOT EXISTS profile_files (
+      id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+      profile_id UUID NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+      path TEXT NOT NULL,
+      target_app TEXT,
+      open_on_activate BOOLEAN NOT NULL DEFAULT TRUE,
+      open_order INTEGER NOT NULL DEFAULT 0,
+      created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+      updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Profile files table created");
+
+  sqlx::query(
+    "CREATE INDEX IF NOT EXISTS idx_profile_files_profile_id ON profile_files(profile_id, open_order)",
+  )
+  .execute(pool)
+  .await?;
+
+  let duration = start.elapsed();
+  info!("Migration v2 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v3: Log retention settings
+async fn run_migration_v3(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v3: Log retention settings");
+  let start = std::time::Instant::now();
+
+  // Read by the background log maintenance job
+  sqlx::query(
+    r#"
+    ALTER TABLE user_settings
+      ADD COLUMN IF NOT EXISTS log_retention_days INTEGER NOT NULL DEFAULT 30,
+      ADD COLUMN IF NOT EXISTS auto_cleanup_logs BOOLEAN NOT NULL DEFAULT true
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("User settings log retention columns added");
+
+  let duration = start.elapsed();
+  info!("Migration v3 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v4: Error fingerprints and regression tracking
+async fn run_migration_v4(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v4: Error fingerprints");
+  let start = std::time::Instant::now();
+
+  // Rows logged before this migration keep a NULL fingerprint and simply never merge
+  sqlx::query(
+    r#"
+    ALTER TABLE error_logs
+      ADD COLUMN IF NOT EXISTS fingerprint TEXT,
+      ADD COLUMN IF NOT EXISTS regression_count INTEGER NOT NULL DEFAULT 0,
+      ADD COLUMN IF NOT EXISTS reopened_at TIMESTAMPTZ
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Error logs fingerprint columns added");
+
+  sqlx::query(
+    "CREATE INDEX IF NOT EXISTS idx_error_logs_fingerprint ON error_logs(fingerprint, last_occurred_at DESC)",
+  )
+  .execute(pool)
+  .await?;
+
+  let duration = start.elapsed();
+  info!("Migration v4 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v5: Alert rules and fired alerts
+async fn run_migration_v5(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v5: Alerting");
+  let start = std::time::Instant::now();
+
+  // Alert rules - `metric` is evaluated over the last `window_minutes`
+  sqlx::query(
+    r#"
+    CREATE TABLE IF NOT EXISTS alert_rules (
+      id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+      user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+      name TEXT NOT NULL,
+      metric TEXT NOT NULL,
+      threshold DOUBLE PRECISION NOT NULL,
+      window_minutes INTEGER NOT NULL DEFAULT 10,
+      min_samples INTEGER NOT NULL DEFAULT 0,
+      severity TEXT NOT NULL DEFAULT 'warning',
+      cooldown_minutes INTEGER NOT NULL DEFAULT 30,
+      is_enabled BOOLEAN NOT NULL DEFAULT true,
+      created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+      updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+      UNIQUE(user_id, name)
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Alert rules table created");
+
+  // Alerts - one row per firing, open until the metric drops back under the threshold
+  sqlx::query(
+    r#"
+    CREATE TABLE IF NOT EXISTS alerts (
+      id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+      rule_id UUID NOT NULL REFERENCES alert_rules(id) ON DELETE CASCADE,
+      user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+      value DOUBLE PRECISION NOT NULL,
+      threshold DOUBLE PRECISION NOT NULL,
+      message TEXT NOT NULL,
+      triggered_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+      resolved_at TIMESTAMPTZ
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Alerts table created");
+
+  sqlx::query(
+    "CREATE INDEX IF NOT EXISTS idx_alerts_rule_triggered ON alerts(rule_id, triggered_at DESC)",
+  )
+  .execute(pool)
+  .await?;
+
+  let duration = start.elapsed();
+  info!("Migration v5 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v6: CHECK constraints on enumerated columns
+///
+/// Values are lowercased first and known legacy spellings renamed (activity
+/// rows used to record failures as "error"). A constraint is only rebuilt when
+/// the enum's variants change, since that rewrites and locks the table.
+async fn run_migration_v6(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v6: Enum check constraints");
+  let start = std::time::Instant::now();
+
+  // Values the first releases wrote under other names, as (old, new)
+  type Aliases = &'static [(&'static str, &'static str)];
+  let severity_aliases: Aliases = &[("warn", "warning"), ("fatal", "critical")];
+  let checks: [(&str, &str, Vec<&str>, Aliases); 8] = [
+    ("profiles", "type", ProfileType::values(), &[]),
+    (
+      "activity_logs",
+      "status",
+      ActivityStatus::values(),
+      &[("error", "failure"), ("failed", "failure")],
+    ),
+    (
+      "system_events",
+      "severity",
+      Severity::values(),
+      severity_aliases,
+    ),
+    (
+      "error_logs",
+      "severity",
+      Severity::values(),
+      severity_aliases,
+    ),
+    (
+      "alert_rules",
+      "severity",
+      Severity::values(),
+      severity_aliases,
+    ),
+    (
+      "profile_activations",
+      "activation_source",
+      ActivationSource::values(),
+      &[],
+    ),
+    (
+      "monitor_changes",
+      "change_type",
+      MonitorChangeType::values(),
+      &[],
+    ),
+    ("feedback", "status", FeedbackStatus::values(), &[]),
+  ];
+
+  for (table, column, values, aliases) in checks {
+    let constraint = format!("{}_{}_check", table, column);
+
+    // Rebuilt only when the enum's variants differ from the constraint's
+    let definition: Option<String> = sqlx::query_scalar(
+      "SELECT pg_get_constraintdef(oid) FROM pg_constraint WHERE conname = $1 AND conrelid = $2::regclass",
+    )
+    .bind(&constraint)
+    .bind(table)
+    .fetch_optional(pool)
+    .await?;
+    let wanted: BTreeSet<&str> = values.iter().copied().collect();
+    if definition.is_some_and(|definition| quoted_values(&definition) == wanted) {
+      continue;
+    }
+
+    let allowed = values
+      .iter()
+      .map(|v| format!("'{}'", v))
+      .collect::<Vec<_>>()
+      .join(", ");
+    let renames: String = aliases
+      .iter()
+      .map(|(from, to)| format!(r#" WHEN lower(trim("{column}")) = '{from}' THEN '{to}'"#))
+      .collect();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(&format!(
+      r#"
+      UPDATE {table}
+      SET "{column}" = CASE
+        WHEN lower(trim("{column}")) IN ({allowed}) THEN lower(trim("{column}")){renames}
+        ELSE "{column}"
+      END
+      WHERE "{column}" IS NOT NULL AND "{column}" NOT IN ({allowed})
+      "#,
+    ))
+    .execute(&mut *tx)
+    .await?;
+
+    // Anything still unrecognised is kept as it is rather than guessed at;
+    // the constraint then only applies to new rows
+    let unknown: i64 = sqlx::query_scalar(&format!(
+      r#"SELECT COUNT(*) FROM {table} WHERE "{column}" IS NOT NULL AND "{column}" NOT IN ({allowed})"#
+    ))
+    .fetch_one(&mut *tx)
+    .await?;
+    if unknown > 0 {
+      warn!(
+        table,
+        column, unknown, "Rows with unrecognised values were left unchanged"
+      );
+    }
+
+    sqlx::query(&format!(
+      r#"ALTER TABLE {table} DROP CONSTRAINT IF EXISTS {constraint}"#
+    ))
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(&format!(
+      r#"ALTER TABLE {table} ADD CONSTRAINT {constraint} CHECK ("{column}" IN ({allowed})){not_valid}"#,
+      not_valid = if unknown > 0 { " NOT VALID" } else { "" },
+    ))
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+  }
+  info!("Enum check constraints added");
+
+  let duration = start.elapsed();
+  info!("Migration v6 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v7: Request ids on audit rows, for correlating them with backend logs
+async fn run_migration_v7(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v7: Request correlation");
+  let start = std::time::Instant::now();
+
+  for table in ["activity_logs", "system_events", "error_logs"] {
+    sqlx::query(&format!(
+      "ALTER TABLE {} ADD COLUMN IF NOT EXISTS request_id TEXT",
+      table
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::query(&format!(
+      "CREATE INDEX IF NOT EXISTS idx_{table}_request_id ON {table}(request_id) \
+       WHERE request_id IS NOT NULL"
+    ))
+    .execute(pool)
+    .await?;
+  }
+  info!("Request id columns added");
+
+  let duration = start.elapsed();
+  info!("Migration v7 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v8: Per-day rollup of activations, launches, errors and active time
+///
+/// Rows with a NULL profile_id hold whatever happened while no profile was active.
+async fn run_migration_v8(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v8: Daily statistics");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    CREATE TABLE IF NOT EXISTS daily_stats (
+      id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+      user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+      day DATE NOT NULL,
+      profile_id UUID REFERENCES profiles(id) ON DELETE CASCADE,
+      activations INTEGER NOT NULL DEFAULT 0,
+      app_launches INTEGER NOT NULL DEFAULT 0,
+      errors INTEGER NOT NULL DEFAULT 0,
+      active_minutes INTEGER NOT NULL DEFAULT 0,
+      updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Daily stats table created");
+
+  // NULLs are distinct in a plain unique index, so fold them onto the nil uuid
+  sqlx::query(
+    r#"
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_daily_stats_user_day_profile ON daily_stats(
+      user_id, day, COALESCE(profile_id, '00000000-0000-0000-0000-000000000000'::uuid)
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+
+  let duration = start.elapsed();
+  info!("Migration v8 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v9: Conditions checked before an automation rule acts
+async fn run_migration_v9(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v9: Automation rule conditions");
+  let start = std::time::Instant::now();
+
+  // NULL means the rule acts whenever its trigger fires
+  sqlx::query("ALTER TABLE automation_rules ADD COLUMN IF NOT EXISTS conditions JSONB")
+    .execute(pool)
+    .await?;
+  info!("Automation rule conditions column added");
+
+  let duration = start.elapsed();
+  info!("Migration v9 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v10: Rule priorities, stop-after-match and cooldowns
+async fn run_migration_v10(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v10: Automation rule priorities");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    ALTER TABLE automation_rules
+      ADD COLUMN IF NOT EXISTS priority INTEGER NOT NULL DEFAULT 0,
+      ADD COLUMN IF NOT EXISTS stop_after_match BOOLEAN NOT NULL DEFAULT false,
+      ADD COLUMN IF NOT EXISTS cooldown_minutes INTEGER NOT NULL DEFAULT 0
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Automation rule priority columns added");
+
+  let duration = start.elapsed();
+  info!("Migration v10 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v11: Snoozing rules and limiting them to active hours
+async fn run_migration_v11(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v11: Automation rule schedules");
+  let start = std::time::Instant::now();
+
+  // A past snoozed_until simply has no effect, so expiry needs no cleanup job
+  sqlx::query(
+    r#"
+    ALTER TABLE automation_rules
+      ADD COLUMN IF NOT EXISTS snoozed_until TIMESTAMPTZ,
+      ADD COLUMN IF NOT EXISTS active_hours JSONB
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Automation rule snooze and active hours columns added");
+
+  let duration = start.elapsed();
+  info!("Migration v11 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v12: Ordered actions per automation rule
+async fn run_migration_v12(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v12: Automation rule actions");
+  let start = std::time::Instant::now();
+
+  let action_types = RuleActionType::values()
+    .iter()
+    .map(|v| format!("'{}'", v))
+    .collect::<Vec<_>>()
+    .join(", ");
+
+  // Rules without rows here keep activating their own profile
+  sqlx::query(&format!(
+    r#"
+    CREATE TABLE IF NOT EXISTS rule_actions (
+      id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+      rule_id UUID NOT NULL REFERENCES automation_rules(id) ON DELETE CASCADE,
+      position INTEGER NOT NULL,
+      action_type TEXT NOT NULL CHECK (action_type IN ({action_types})),
+      config JSONB NOT NULL DEFAULT '{{}}',
+      created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+      UNIQUE(rule_id, position)
+    )
+    "#
+  ))
+  .execute(pool)
+  .await?;
+  info!("Rule actions table created");
+
+  let duration = start.elapsed();
+  info!("Migration v12 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v13: Low-power threshold per profile
+async fn run_migration_v13(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v13: Profile low-power threshold");
+  let start = std::time::Instant::now();
+
+  // NULL means the profile always runs in full, whatever the battery level
+  sqlx::query(
+    r#"
+    ALTER TABLE profiles
+      ADD COLUMN IF NOT EXISTS low_power_battery_threshold INTEGER
+        CHECK (low_power_battery_threshold BETWEEN 1 AND 100)
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Profile low_power_battery_threshold column added");
+
+  let duration = start.elapsed();
+  info!("Migration v13 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v14: Location for sunrise/sunset schedules
+async fn run_migration_v14(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v14: User location");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    ALTER TABLE user_settings
+      ADD COLUMN IF NOT EXISTS latitude DOUBLE PRECISION
+        CHECK (latitude BETWEEN -90 AND 90),
+      ADD COLUMN IF NOT EXISTS longitude DOUBLE PRECISION
+        CHECK (longitude BETWEEN -180 AND 180)
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("User settings latitude and longitude columns added");
+
+  let duration = start.elapsed();
+  info!("Migration v14 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v15: Cleanup actions run when a profile is deactivated
+async fn run_migration_v15(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v15: Profile deactivation actions");
+  let start = std::time::Instant::now();
+
+  let action_types = DeactivationActionType::values()
+    .iter()
+    .map(|v| format!("'{}'", v))
+    .collect::<Vec<_>>()
+    .join(", ");
+
+  sqlx::query(&format!(
+    r#"
+    CREATE TABLE IF NOT EXISTS profile_deactivation_actions (
+      id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+      profile_id UUID NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+      position INTEGER NOT NULL,
+      action_type TEXT NOT NULL CHECK (action_type IN ({action_types})),
+      config JSONB NOT NULL DEFAULT '{{}}',
+      created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+      UNIQUE(profile_id, position)
+    )
+    "#
+  ))
+  .execute(pool)
+  .await?;
+  info!("Profile deactivation actions table created");
+
+  let duration = start.elapsed();
+  info!("Migration v15 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v16: GIN indexes on the JSONB columns queried by content
+async fn run_migration_v16(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v16: JSONB GIN indexes");
+  let start = std::time::Instant::now();
+
+  // jsonb_path_ops covers @>, @? and @@, which is all JsonFilter emits, and
+  // is a fraction of the size of the default operator class
+  let indexes = [
+    ("idx_activity_logs_details_gin", "activity_logs", "details"),
+    ("idx_error_logs_context_gin", "error_logs", "context"),
+    (
+      "idx_automation_rules_trigger_config_gin",
+      "automation_rules",
+      "trigger_config",
+    ),
+  ];
+  for (name, table, column) in indexes {
+    sqlx::query(&format!(
+      "CREATE INDEX IF NOT EXISTS {name} ON {table} USING GIN ({column} jsonb_path_ops)"
+    ))
+    .execute(pool)
+    .await?;
+  }
+  info!("JSONB GIN indexes created");
+
+  let duration = start.elapsed();
+  info!("Migration v16 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v17: Sync scope and local-only profiles
+async fn run_migration_v17(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v17: Selective sync");
+  let start = std::time::Instant::now();
+
+  let scopes = SyncScope::values()
+    .iter()
+    .map(|v| format!("'{}'", v))
+    .collect::<Vec<_>>()
+    .join(", ");
+
+  sqlx::query(&format!(
+    r#"
+    ALTER TABLE user_settings
+      ADD COLUMN IF NOT EXISTS sync_scope TEXT NOT NULL DEFAULT '{default}'
+        CHECK (sync_scope IN ({scopes}))
+    "#,
+    default = SyncScope::Profiles
+  ))
+  .execute(pool)
+  .await?;
+  info!("User settings sync_scope column added");
+
+  sqlx::query(
+    "ALTER TABLE profiles ADD COLUMN IF NOT EXISTS local_only BOOLEAN NOT NULL DEFAULT false",
+  )
+  .execute(pool)
+  .await?;
+  info!("Profile local_only column added");
+
+  let duration = start.elapsed();
+  info!("Migration v17 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v18: Teams and their profile libraries, mirrored from Supabase
+async fn run_migration_v18(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v18: Team workspaces");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    CREATE TABLE IF NOT EXISTS organizations (
+      id UUID PRIMARY KEY,
+      name TEXT NOT NULL,
+      created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+
+  sqlx::query(
+    r#"
+    CREATE TABLE IF NOT EXISTS teams (
+      id UUID PRIMARY KEY,
+      organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+      name TEXT NOT NULL,
+      created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+
+  let roles = TeamRole::values()
+    .iter()
+    .map(|v| format!("'{}'", v))
+    .collect::<Vec<_>>()
+    .join(", ");
+  sqlx::query(&format!(
+    r#"
+    CREATE TABLE IF NOT EXISTS team_members (
+      team_id UUID NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+      user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+      role TEXT NOT NULL CHECK (role IN ({roles})),
+      joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+      PRIMARY KEY (team_id, user_id)
+    )
+    "#
+  ))
+  .execute(pool)
+  .await?;
+
+  // Publishers are usually other people, so published_by has no local user to reference
+  sqlx::query(
+    r#"
+    CREATE TABLE IF NOT EXISTS team_library_profiles (
+      id UUID PRIMARY KEY,
+      team_id UUID NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+      name TEXT NOT NULL,
+      published_by UUID NOT NULL,
+      published_at TIMESTAMPTZ NOT NULL,
+      document JSONB NOT NULL,
+      synced_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  sqlx::query(
+    "CREATE INDEX IF NOT EXISTS idx_team_library_profiles_team_id ON team_library_profiles(team_id)",
+  )
+  .execute(pool)
+  .await?;
+  sqlx::query("CREATE INDEX IF NOT EXISTS idx_team_members_user_id ON team_members(user_id)")
+    .execute(pool)
+    .await?;
+  info!("Team tables created");
+
+  let duration = start.elapsed();
+  info!("Migration v18 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v19: Monitors keyed by display fingerprint, so re-capturing a
+/// layout updates rows in place instead of adding new ones
+async fn run_migration_v19(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v19: Monitor fingerprints");
+  let start = std::time::Instant::now();
+
+  // The backfill and de-duplication below must only ever run once: monitors
+  // added by hand later have no fingerprint on purpose
+  let applied: bool = sqlx::query_scalar(
+    "SELECT EXISTS (SELECT 1 FROM pg_indexes WHERE schemaname = current_schema() AND indexname = 'idx_monitors_profile_fingerprint')",
+  )
+  .fetch_one(pool)
+  .await?;
+  if applied {
+    info!("Migration v19 already applied");
+    return Ok(());
+  }
+
+  sqlx::query(
+    r#"
+    ALTER TABLE monitors
+      ADD COLUMN IF NOT EXISTS fingerprint TEXT,
+      ADD COLUMN IF NOT EXISTS captured_at TIMESTAMPTZ
+    "#,
+  )
+  .execute(pool)
+  .await?;
+
+  let mut tx = pool.begin().await?;
+  // Same format as `SystemMonitor::fingerprint`
+  sqlx::query(
+    r#"
+    UPDATE monitors
+    SET fingerprint = COALESCE(brand, 'unknown') || '|' || COALESCE(model, name) || '|'
+                      || width || 'x' || height || '|'
+                      || CASE WHEN COALESCE(is_builtin, false) THEN 'builtin' ELSE 'external' END
+    WHERE fingerprint IS NULL
+    "#,
+  )
+  .execute(&mut *tx)
+  .await?;
+
+  // Repeated captures left copies of the same display at the same index; keep
+  // the newest and move windows and tabs over to it
+  sqlx::query(
+    r#"
+    CREATE TEMP TABLE monitor_duplicates ON COMMIT DROP AS
+    SELECT id, FIRST_VALUE(id) OVER (
+             PARTITION BY profile_id, fingerprint, display_index
+             ORDER BY updated_at DESC NULLS LAST, created_at DESC NULLS LAST, id
+           ) AS keep_id
+    FROM monitors
+    "#,
+  )
+  .execute(&mut *tx)
+  .await?;
+  for table in ["windows", "browser_tabs"] {
+    sqlx::query(&format!(
+      r#"
+      UPDATE {table} t SET monitor_id = d.keep_id
+      FROM monitor_duplicates d
+      WHERE t.monitor_id = d.id AND d.id <> d.keep_id
+      "#,
+    ))
+    .execute(&mut *tx)
+    .await?;
+  }
+  let removed = sqlx::query(
+    "DELETE FROM monitors m USING monitor_duplicates d WHERE m.id = d.id AND d.id <> d.keep_id",
+  )
+  .execute(&mut *tx)
+  .await?
+  .rows_affected();
+  info!("Removed {} duplicate monitors", removed);
+
+  // Identical displays connected side by side are told apart by position
+  sqlx::query(
+    r#"
+    UPDATE monitors m SET fingerprint = m.fingerprint || '#' || r.n
+    FROM (
+      SELECT id, ROW_NUMBER() OVER (
+               PARTITION BY profile_id, fingerprint ORDER BY display_index, id
+             ) AS n
+      FROM monitors
+    ) r
+    WHERE m.id = r.id AND r.n > 1
+    "#,
+  )
+  .execute(&mut *tx)
+  .await?;
+
+  sqlx::query(
+    r#"
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_monitors_profile_fingerprint
+      ON monitors(profile_id, fingerprint)
+    "#,
+  )
+  .execute(&mut *tx)
+  .await?;
+  tx.commit().await?;
+  info!("Monitor fingerprint index created");
+
+  let duration = start.elapsed();
+  info!("Migration v19 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v20: Profiles bound to a monitor setup, and per-user default
+/// profiles by monitor count for picking one when displays change
+async fn run_migration_v20(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v20: Monitor-bound profiles");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    ALTER TABLE profiles
+      ADD COLUMN IF NOT EXISTS required_monitor_count INTEGER
+        CHECK (required_monitor_count > 0),
+      ADD COLUMN IF NOT EXISTS monitor_signature TEXT
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Profile monitor binding columns added");
+
+  sqlx::query(
+    "ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS monitor_profile_mappings JSONB NOT NULL DEFAULT '[]'",
+  )
+  .execute(pool)
+  .await?;
+  info!("User settings monitor_profile_mappings column added");
+
+  let duration = start.elapsed();
+  info!("Migration v20 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v21: Time spent in each foreground app while a profile is active
+async fn run_migration_v21(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v21: App focus events");
+  let start = std::time::Instant::now();
+
+  // One row per stretch an app stayed frontmost, written when it ends
+  sqlx::query(
+    r#"
+    CREATE TABLE IF NOT EXISTS app_focus_events (
+      id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+      user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+      profile_id UUID NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+      bundle_id TEXT NOT NULL,
+      app_name TEXT NOT NULL,
+      started_at TIMESTAMPTZ NOT NULL,
+      ended_at TIMESTAMPTZ NOT NULL,
+      duration_ms INTEGER NOT NULL CHECK (duration_ms >= 0)
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  sqlx::query(
+    "CREATE INDEX IF NOT EXISTS idx_app_focus_events_profile ON app_focus_events(profile_id, started_at DESC)",
+  )
+  .execute(pool)
+  .await?;
+  info!("App focus events table created");
+
+  let duration = start.elapsed();
+  info!("Migration v21 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v22: Timed focus sessions bound to a profile
+async fn run_migration_v22(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v22: Focus sessions");
+  let start = std::time::Instant::now();
+
+  let statuses = FocusSessionStatus::values()
+    .iter()
+    .map(|v| format!("'{}'", v))
+    .collect::<Vec<_>>()
+    .join(", ");
+
+  // Blocklists are copied onto the session so editing them later doesn't
+  // rewrite what a finished session enforced
+  sqlx::query(&format!(
+    r#"
+    CREATE TABLE IF NOT EXISTS focus_sessions (
+      id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+      user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+      profile_id UUID NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+      planned_minutes INTEGER NOT NULL CHECK (planned_minutes > 0),
+      status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ({statuses})),
+      blocked_apps JSONB NOT NULL DEFAULT '[]',
+      blocked_urls JSONB NOT NULL DEFAULT '[]',
+      dnd_shortcut TEXT,
+      dnd_off_shortcut TEXT,
+      blocked_app_quits INTEGER NOT NULL DEFAULT 0,
+      started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+      ended_at TIMESTAMPTZ
+    )
+    "#
+  ))
+  .execute(pool)
+  .await?;
+  sqlx::query(
+    "CREATE UNIQUE INDEX IF NOT EXISTS idx_focus_sessions_one_active ON focus_sessions(user_id) WHERE status = 'active'",
+  )
+  .execute(pool)
+  .await?;
+  sqlx::query(
+    "CREATE INDEX IF NOT EXISTS idx_focus_sessions_user ON focus_sessions(user_id, started_at DESC)",
+  )
+  .execute(pool)
+  .await?;
+  info!("Focus sessions table created");
+
+  let duration = start.elapsed();
+  info!("Migration v22 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v23: Apps a profile keeps closed while it is active
+async fn run_migration_v23(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v23: Profile blocked apps");
+  let start = std::time::Instant::now();
+
+  let actions = BlockedAppAction::values()
+    .iter()
+    .map(|v| format!("'{}'", v))
+    .collect::<Vec<_>>()
+    .join(", ");
+
+  sqlx::query(&format!(
+    r#"
+    CREATE TABLE IF NOT EXISTS profile_blocked_apps (
+      id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+      profile_id UUID NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+      bundle_id TEXT NOT NULL,
+      app_name TEXT NOT NULL,
+      action TEXT NOT NULL DEFAULT 'hide' CHECK (action IN ({actions})),
+      created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+      UNIQUE(profile_id, bundle_id)
+    )
+    "#
+  ))
+  .execute(pool)
+  .await?;
+  info!("Profile blocked apps table created");
+
+  let duration = start.elapsed();
+  info!("Migration v23 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v24: Displays a profile blanks while it is active
+async fn run_migration_v24(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v24: Blanked monitors");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    ALTER TABLE monitors
+      ADD COLUMN IF NOT EXISTS blanked BOOLEAN NOT NULL DEFAULT false
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Monitors blanked column added");
+
+  let duration = start.elapsed();
+  info!("Migration v24 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v25: Weekly usage report settings
+async fn run_migration_v25(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v25: Weekly usage reports");
+  let start = std::time::Instant::now();
+
+  let formats = ReportFormat::values()
+    .iter()
+    .map(|v| format!("'{}'", v))
+    .collect::<Vec<_>>()
+    .join(", ");
+
+  sqlx::query(&format!(
+    r#"
+    ALTER TABLE user_settings
+      ADD COLUMN IF NOT EXISTS weekly_report_enabled BOOLEAN NOT NULL DEFAULT false,
+      ADD COLUMN IF NOT EXISTS weekly_report_format TEXT NOT NULL DEFAULT 'markdown'
+        CHECK (weekly_report_format IN ({formats}))
+    "#
+  ))
+  .execute(pool)
+  .await?;
+  info!("User settings weekly report columns added");
+
+  let duration = start.elapsed();
+  info!("Migration v25 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v26: What folder sync last agreed on for each profile
+async fn run_migration_v26(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v26: Folder sync");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    CREATE TABLE IF NOT EXISTS folder_sync_items (
+      sync_id UUID NOT NULL,
+      user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+      profile_id UUID REFERENCES profiles(id) ON DELETE SET NULL,
+      content_hash TEXT NOT NULL,
+      modified_at TIMESTAMPTZ NOT NULL,
+      deleted BOOLEAN NOT NULL DEFAULT false,
+      synced_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+      PRIMARY KEY (user_id, sync_id)
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  sqlx::query(
+    r#"
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_folder_sync_items_profile
+      ON folder_sync_items(profile_id) WHERE profile_id IS NOT NULL
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Folder sync items table created");
+
+  let duration = start.elapsed();
+  info!("Migration v26 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v27: How many daily profile snapshots to keep
+async fn run_migration_v27(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v27: Profile snapshots");
+  let start = std::time::Instant::now();
+
+  sqlx::query(&format!(
+    r#"
+    ALTER TABLE user_settings
+      ADD COLUMN IF NOT EXISTS snapshots_kept INTEGER NOT NULL DEFAULT 14
+        CHECK (snapshots_kept BETWEEN 0 AND {MAX_SNAPSHOTS_KEPT})
+    "#
+  ))
+  .execute(pool)
+  .await?;
+  info!("User settings snapshots_kept column added");
+
+  let duration = start.elapsed();
+  info!("Migration v27 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v28: Profiles managed by a declarative file
+async fn run_migration_v28(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v28: Declarative profiles");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    CREATE TABLE IF NOT EXISTS declarative_profiles (
+      user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+      source_path TEXT NOT NULL,
+      profile_key TEXT NOT NULL,
+      profile_id UUID NOT NULL UNIQUE REFERENCES profiles(id) ON DELETE CASCADE,
+      content_hash TEXT NOT NULL,
+      synced_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+      PRIMARY KEY (user_id, source_path, profile_key)
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Declarative profiles table created");
+
+  let duration = start.elapsed();
+  info!("Migration v28 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v29: Declarative profile file to watch for changes
+async fn run_migration_v29(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v29: Declarative watch mode");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    ALTER TABLE user_settings
+      ADD COLUMN IF NOT EXISTS declarative_watch_path TEXT
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("User settings declarative_watch_path column added");
+
+  let duration = start.elapsed();
+  info!("Migration v29 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v30: Launch at login and background agent mode
+async fn run_migration_v30(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v30: Login item settings");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    ALTER TABLE user_settings
+      ADD COLUMN IF NOT EXISTS launch_at_login BOOLEAN NOT NULL DEFAULT false,
+      ADD COLUMN IF NOT EXISTS background_agent BOOLEAN NOT NULL DEFAULT false
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("User settings login item columns added");
+
+  let duration = start.elapsed();
+  info!("Migration v30 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+async fn run_migration_v31(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v31: Diagnostic uploads setting");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    ALTER TABLE user_settings
+      ADD COLUMN IF NOT EXISTS diagnostic_uploads BOOLEAN NOT NULL DEFAULT false
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("User settings diagnostic_uploads column added");
+
+  let duration = start.elapsed();
+  info!("Migration v31 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+async fn run_migration_v32(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v32: Telemetry settings");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    ALTER TABLE user_settings
+      ADD COLUMN IF NOT EXISTS telemetry_enabled BOOLEAN NOT NULL DEFAULT false,
+      ADD COLUMN IF NOT EXISTS telemetry_sent_at TIMESTAMPTZ
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("User settings telemetry columns added");
+
+  let duration = start.elapsed();
+  info!("Migration v32 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v33: Error occurrences per day and app version, for trends.
+/// `error_logs` keeps one row per incident, so it can't say when within an
+/// incident each occurrence happened.
+async fn run_migration_v33(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v33: Error daily counts");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    CREATE TABLE IF NOT EXISTS error_daily_counts (
+      fingerprint TEXT NOT NULL,
+      day DATE NOT NULL,
+      -- Empty for occurrences logged before versions were recorded
+      app_version TEXT NOT NULL DEFAULT '',
+      occurrences INTEGER NOT NULL DEFAULT 0,
+      PRIMARY KEY (fingerprint, day, app_version)
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Error daily counts table created");
+
+  // Existing incidents are counted on the day they were last seen
+  sqlx::query(
+    r#"
+    INSERT INTO error_daily_counts (fingerprint, day, occurrences)
+    SELECT fingerprint, (last_occurred_at AT TIME ZONE 'UTC')::date,
+           SUM(COALESCE(occurrence_count, 1))
+    FROM error_logs
+    WHERE fingerprint IS NOT NULL
+    GROUP BY 1, 2
+    ON CONFLICT DO NOTHING
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Error daily counts backfilled");
+
+  let duration = start.elapsed();
+  info!("Migration v33 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v34: Markdown notes on profiles
+async fn run_migration_v34(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v34: Profile notes");
+  let start = std::time::Instant::now();
+
+  sqlx::query(&format!(
+    "ALTER TABLE profiles ADD COLUMN IF NOT EXISTS notes TEXT CHECK (char_length(notes) <= {})",
+    MAX_NOTES_LENGTH
+  ))
+  .execute(pool)
+  .await?;
+  info!("Profile notes column added");
+
+  let duration = start.elapsed();
+  info!("Migration v34 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v35: Checklist items shown after a profile activates
+async fn run_migration_v35(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v35: Profile checklists");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    CREATE TABLE IF NOT EXISTS profile_checklist_items (
+      id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+      profile_id UUID NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+      position INTEGER NOT NULL,
+      label TEXT NOT NULL,
+      created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  sqlx::query(
+    "CREATE INDEX IF NOT EXISTS idx_profile_checklist_items_profile ON profile_checklist_items(profile_id, position)",
+  )
+  .execute(pool)
+  .await?;
+  info!("Profile checklist items table created");
+
+  let duration = start.elapsed();
+  info!("Migration v35 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v36: Zoom to open saved windows at
+async fn run_migration_v36(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v36: Window zoom");
+  let start = std::time::Instant::now();
+
+  sqlx::query(&format!(
+    "ALTER TABLE windows ADD COLUMN IF NOT EXISTS zoom_steps INTEGER CHECK (zoom_steps BETWEEN {} AND {})",
+    MIN_ZOOM_STEPS, MAX_ZOOM_STEPS
+  ))
+  .execute(pool)
+  .await?;
+  info!("Window zoom column added");
+
+  let duration = start.elapsed();
+  info!("Migration v36 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// Migration v37: Where to find displayplacer when it isn't on the PATH
+async fn run_migration_v37(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v37: displayplacer path setting");
+  let start = std::time::Instant::now();
+
+  sqlx::query("ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS displayplacer_path TEXT")
+    .execute(pool)
+    .await?;
+  info!("User settings displayplacer_path column added");
+
+  let duration = start.elapsed();
+  info!("Migration v37 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+async fn run_migration_v38(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v38: virtual displays per profile");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    "ALTER TABLE profiles ADD COLUMN IF NOT EXISTS include_virtual_displays BOOLEAN NOT NULL DEFAULT false",
+  )
+  .execute(pool)
+  .await?;
+  info!("Profile include_virtual_displays column added");
+
+  let duration = start.elapsed();
+  info!("Migration v38 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+async fn run_migration_v39(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v39: monitor change summaries");
+  let start = std::time::Instant::now();
+
+  sqlx::query("ALTER TABLE monitor_changes ADD COLUMN IF NOT EXISTS summary TEXT")
+    .execute(pool)
+    .await?;
+  info!("Monitor changes summary column added");
+
+  let duration = start.elapsed();
+  info!("Migration v39 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+async fn run_migration_v40(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v40: activation reports");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    r#"
+    CREATE TABLE IF NOT EXISTS activation_reports (
+      id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+      activation_id UUID NOT NULL UNIQUE REFERENCES profile_activations(id) ON DELETE CASCADE,
+      profile_id UUID NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+      report JSONB NOT NULL,
+      created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    )
+    "#,
+  )
+  .execute(pool)
+  .await?;
+  info!("Activation reports table created");
+
+  let duration = start.elapsed();
+  info!("Migration v40 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+async fn run_migration_v41(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v41: profile locks");
+  let start = std::time::Instant::now();
+
+  sqlx::query(
+    "ALTER TABLE profiles ADD COLUMN IF NOT EXISTS is_locked BOOLEAN NOT NULL DEFAULT false",
+  )
+  .execute(pool)
+  .await?;
+  info!("Profile is_locked column added");
+
+  let duration = start.elapsed();
+  info!("Migration v41 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+/// The quoted literals in a constraint definition, i.e. the values a check allows
+fn quoted_values(definition: &str) -> BTreeSet<&str> {
+  definition.split('\'').skip(1).step_by(2).collect()
+}
+
+/// The type a column created by the first release should have instead, with
+/// the expression converting its values, or `None` if it is already right
+fn native_type(column: &str, data_type: &str) -> Option<(&'static str, String)> {
+  let is_reference = column == "id" || column.ends_with("_id");
+  match data_type {
+    "text" if JSON_COLUMNS.contains(&column) => Some((
+      "JSONB",
+      format!(r#"pg_temp.smoothie_to_jsonb("{}")"#, column),
+    )),
+    "text" if is_reference && !TEXT_ID_COLUMNS.contains(&column) => {
+      Some(("UUID", format!(r#""{}"::uuid"#, column)))
+    }
+    "timestamp without time zone" => {
+      Some(("TIMESTAMPTZ", format!(r#""{}" AT TIME ZONE 'UTC'"#, column)))
+    }
+    "real" => Some(("DOUBLE PRECISION", format!(r#""{}""#, column))),
+    _ => None,
+  }
+}
+
+/// Migration v42: Native column types for databases created by the first release
+///
+/// The original v1 created ids and references as TEXT, timestamps without a
+/// time zone, REAL scale factors and JSON payloads as TEXT, while the later
+/// migrations and the repositories expect UUID, TIMESTAMPTZ, DOUBLE PRECISION
+/// and JSONB. Runs straight after v1. Naive timestamps were written in UTC,
+/// and a payload that isn't valid JSON is kept as a JSON string.
+async fn run_migration_v42(pool: &PgPool) -> anyhow::Result<()> {
+  info!("Running migration v42: Native column types");
+  let start = std::time::Instant::now();
+
+  let columns: Vec<(String, String, String, Option<String>)> = sqlx::query_as(
+    r#"
+    SELECT c.table_name::text, c.column_name::text, c.data_type::text, c.column_default::text
+    FROM information_schema.columns c
+    JOIN information_schema.tables t
+      ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+    WHERE c.table_schema = current_schema() AND t.table_type = 'BASE TABLE'
+    ORDER BY c.table_name, c.ordinal_position
+    "#,
+  )
+  .fetch_all(pool)
+  .await?;
+
+  let mut changes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+  for (table, column, data_type, default) in columns {
+    let Some((new_type, using)) = native_type(&column, &data_type) else {
+      continue;
+    };
+    let steps = changes.entry(table).or_default();
+    if default.is_some() {
+      steps.push(format!(r#"ALTER COLUMN "{}" DROP DEFAULT"#, column));
+    }
+    steps.push(format!(
+      r#"ALTER COLUMN "{}" TYPE {} USING {}"#,
+      column, new_type, using
+    ));
+    let new_default = match (default, new_type) {
+      (Some(default), "JSONB") => Some(format!("({})::jsonb", default)),
+      (Some(default), _) => Some(default),
+      (None, "UUID") if column == "id" => Some("uuid_generate_v4()".to_string()),
+      (None, _) => None,
+    };
+    if let Some(default) = new_default {
+      steps.push(format!(
+        r#"ALTER COLUMN "{}" SET DEFAULT {}"#,
+        column, default
+      ));
+    }
+  }
+  if changes.is_empty() {
+    info!("Migration v42 already applied");
+    return Ok(());
+  }
+
+  let mut tx = pool.begin().await?;
+
+  sqlx::query(
+    r#"
+    CREATE OR REPLACE FUNCTION pg_temp.smoothie_to_jsonb(value TEXT) RETURNS JSONB AS $$
+    BEGIN
+      RETURN value::jsonb;
+    EXCEPTION WHEN invalid_text_representation THEN
+      RETURN to_jsonb(value);
+    END
+    $$ LANGUAGE plpgsql IMMUTABLE
+    "#,
+  )
+  .execute(&mut *tx)
+  .await?;
+
+  // A foreign key can't span a type change, so the ones touching the
+  // converted tables are dropped and added back once both sides match
+  let tables: Vec<String> = changes.keys().cloned().collect();
+  let foreign_keys: Vec<(String, String, String)> = sqlx::query_as(
+    r#"
+    SELECT rel.relname::text, con.conname::text, pg_get_constraintdef(con.oid)
+    FROM pg_constraint con
+    JOIN pg_class rel ON rel.oid = con.conrelid
+    JOIN pg_class ref ON ref.oid = con.confrelid
+    WHERE con.contype = 'f'
+      AND rel.relnamespace = current_schema()::regnamespace
+      AND (rel.relname::text = ANY($1) OR ref.relname::text = ANY($1))
+    "#,
+  )
+  .bind(&tables)
+  .fetch_all(&mut *tx)
+  .await?;
+  for (table, name, _) in &foreign_keys {
+    sqlx::query(&format!(
+      r#"ALTER TABLE {} DROP CONSTRAINT "{}""#,
+      table, name
+    ))
+    .execute(&mut *tx)
+    .await?;
+  }
+
+  for (table, steps) in &changes {
+    sqlx::query(&format!("ALTER TABLE {} {}", table, steps.join(", ")))
+      .execute(&mut *tx)
+      .await?;
+    info!("Converted the columns of {}", table);
+  }
+
+  for (table, name, definition) in &foreign_keys {
+    sqlx::query(&format!(
+      r#"ALTER TABLE {} ADD CONSTRAINT "{}" {}"#,
+      table, name, definition
+    ))
+    .execute(&mut *tx)
+    .await?;
+  }
+  tx.commit().await?;
+  info!("Column types converted");
+
+  let duration = start.elapsed();
+  info!("Migration v42 completed in {}ms", duration.as_millis());
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{JSON_COLUMNS, TEXT_ID_COLUMNS};
+  use crate::db::test_support::{test_db_or_skip, TestDb};
+
+  #[tokio::test]
+  async fn test_schema_uses_native_column_types() {
+    let test_db = test_db_or_skip!();
+
+    let columns: Vec<(String, String, String)> = sqlx::query_as(
+      "SELECT table_name::text, column_name::text, data_type::text \
+       FROM information_schema.columns WHERE table_schema = current_schema()",
+    )
+    .fetch_all(test_db.pool())
+    .await
+    .unwrap();
+    assert!(!columns.is_empty());
+
+    let misaligned: Vec<String> = columns
+      .iter()
+      .filter(|(_, column, data_type)| {
+        let is_ref = column == "id" || column.ends_with("_id");
+        (is_ref && data_type != "uuid" && !TEXT_ID_COLUMNS.contains(&column.as_str()))
+          || data_type == "timestamp without time zone"
+          || (JSON_COLUMNS.contains(&column.as_str()) && data_type != "jsonb")
+      })
+      .map(|(table, column, data_type)| format!("{}.{} ({})", table, column, data_type))
+      .collect();
+    assert!(
+      misaligned.is_empty(),
+      "misaligned columns: {:?}",
+      misaligned
+    );
+  }
+
+  #[tokio::test]
+  async fn test_applied_version_sees_index_markers() {
+    let test_db = test_db_or_skip!();
+    assert_eq!(
+      super::applied_version(test_db.pool()).await.unwrap(),
+      super::SCHEMA_VERSION
+    );
+
+    sqlx::query("DROP INDEX idx_activity_logs_details_gin")
+      .execute(test_db.pool())
+      .await
+      .unwrap();
+    assert_eq!(super::applied_version(test_db.pool()).await.unwrap(), 15);
+  }
+
+  #[tokio::test]
+  async fn test_v6_renames_legacy_values_and_keeps_unknown_ones() {
+    let test_db = test_db_or_skip!();
+    let pool = test_db.pool();
+    sqlx::raw_sql(
+      r#"
+      ALTER TABLE system_events DROP CONSTRAINT system_events_severity_check;
+      INSERT INTO system_events (event_type, severity, source, message) VALUES
+        ('a', 'WARN', 'test', 'a'), ('b', ' Info', 'test', 'b'), ('c', 'loud', 'test', 'c');
+      "#,
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+
+    super::run_migration_v6(pool).await.unwrap();
+    super::run_migration_v6(pool).await.unwrap();
+
+    let severities: Vec<String> =
+      sqlx::query_scalar("SELECT severity FROM system_events ORDER BY event_type")
+        .fetch_all(pool)
+        .await
+        .unwrap();
+    assert_eq!(severities, vec!["warning", "info", "loud"]);
+    let rejected = sqlx::query(
+      "INSERT INTO system_events (event_type, severity, source, message) VALUES ('d', 'loud', 'test', 'd')",
+    )
+    .execute(pool)
+    .await;
+    assert!(rejected.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_upgrade_from_first_release_schema() {
+    let Some(test_db) = TestDb::unmigrated().await else {
+      return;
+    };
+    let pool = test_db.pool();
+    super::run_migration_v1(pool).await.unwrap();
+    sqlx::raw_sql(
+      r#"
+      INSERT INTO profiles (id, user_id, name, type, last_used)
+      VALUES ('00000000-0000-0000-0000-00000000000a', '00000000-0000-0000-0000-000000000001',
+              'Desk', 'work', '2024-03-01 12:00:00');
+      INSERT INTO automation_rules (id, profile_id, rule_type, trigger_config)
+      VALUES ('00000000-0000-0000-0000-00000000000b', '00000000-0000-0000-0000-00000000000a',
+              'time', '{"at": "09:00"}');
+      INSERT INTO activity_logs (id, user_id, action, details) VALUES
+        ('00000000-0000-0000-0000-00000000000c', '00000000-0000-0000-0000-000000000001',
+         'profile_activated', '{"source": "manual"}'),
+        ('00000000-0000-0000-0000-00000000000d', '00000000-0000-0000-0000-000000000001',
+         'profile_activated', 'activated from tray');
+      "#,
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+
+    super::run(pool).await.unwrap();
+    assert_eq!(
+      super::applied_version(pool).await.unwrap(),
+      super::SCHEMA_VERSION
+    );
+
+    let details: Vec<serde_json::Value> =
+      sqlx::query_scalar("SELECT details FROM activity_logs ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .unwrap();
+    assert_eq!(
+      details,
+      vec![
+        serde_json::json!({ "source": "manual" }),
+        serde_json::json!("activated from tray")
+      ]
+    );
+    let rules: i64 = sqlx::query_scalar(
+      r#"SELECT COUNT(*) FROM automation_rules WHERE trigger_config @> '{"at": "09:00"}'"#,
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    assert_eq!(rules, 1);
+    let last_used: chrono::DateTime<chrono::Utc> =
+      sqlx::query_scalar("SELECT last_used FROM profiles")
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert_eq!(last_used.to_rfc3339(), "2024-03-01T12:00:00+00:00");
+  }
+
+  #[tokio::test]
+  async fn test_v42_converts_first_release_column_types() {
+    let Some(test_db) = TestDb::unmigrated().await else {
+      return;
+    };
+    let pool = test_db.pool();
+    sqlx::raw_sql(
+      r#"
+      CREATE TABLE users (id TEXT PRIMARY KEY, created_at TIMESTAMP NOT NULL DEFAULT NOW());
+      CREATE TABLE profiles (
+        id TEXT PRIMARY KEY,
+        user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        metadata TEXT DEFAULT '{}',
+        scale REAL DEFAULT 1.0
+      );
+      INSERT INTO users (id, created_at)
+      VALUES ('00000000-0000-0000-0000-000000000001', '2024-03-01 12:00:00');
+      INSERT INTO profiles (id, user_id, metadata)
+      VALUES ('00000000-0000-0000-0000-00000000000a', '00000000-0000-0000-0000-000000000001', '{"a": 1}');
+      "#,
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+
+    super::run_migration_v42(pool).await.unwrap();
+    super::run_migration_v42(pool).await.unwrap();
+
+    let (created_at, metadata): (chrono::DateTime<chrono::Utc>, serde_json::Value) =
+      sqlx::query_as(
+        "SELECT u.created_at, p.metadata FROM users u JOIN profiles p ON p.user_id = u.id",
+      )
+      .fetch_one(pool)
+      .await
+      .unwrap();
+    assert_eq!(created_at.to_rfc3339(), "2024-03-01T12:00:00+00:00");
+    assert_eq!(metadata["a"], 1);
+
+    // Defaults carried over, and ids are generated now
+    let (id, metadata): (uuid::Uuid, serde_json::Value) = sqlx::query_as(
+      "INSERT INTO profiles (user_id) VALUES ('00000000-0000-0000-0000-000000000001') RETURNING id, metadata",
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    assert!(!id.is_nil());
+    assert_eq!(metadata, serde_json::json!({}));
+
+    // The foreign key was added back
+    sqlx::query("DELETE FROM users")
+      .execute(pool)
+      .await
+      .unwrap();
+    let profiles: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM profiles")
+      .fetch_one(pool)
+      .await
+      .unwrap();
+    assert_eq!(profiles, 0);
+  }
+}
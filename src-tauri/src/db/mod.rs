@@ -2,22 +2,61 @@
 
 pub mod connection;
 pub mod migrations;
+pub mod supabase;
 
-use sqlx::postgres::PgPool;
+#[cfg(test)]
+pub mod test_support;
+
+use crate::error::{Result, SmoothieError};
+use connection::PoolConfig;
+use serde::Serialize;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use std::future::Future;
+use std::time::Duration;
 use tracing::{error, info};
 
 #[derive(Clone)]
 pub struct Database {
   pool: PgPool,
+  /// A single connection without `statement_timeout`, for migrations and
+  /// maintenance that can run longer than any request should
+  maintenance_pool: PgPool,
+  query_timeout: Duration,
+}
+
+/// Connection pool occupancy, reported alongside the app metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolStats {
+  pub size: u32,
+  pub idle: usize,
+  pub in_use: u32,
+  pub max_connections: u32,
+  /// Share of `max_connections` currently checked out, 0.0 to 1.0
+  pub utilization: f64,
 }
 
 impl Database {
-  /// Initialize PostgreSQL database
+  /// Initialize PostgreSQL database: connect, then run migrations
   pub async fn new() -> anyhow::Result<Self> {
+    let start = std::time::Instant::now();
+    let db = Self::connect().await?;
+    db.migrate().await?;
+
+    let total_duration = start.elapsed();
+    info!(
+      "PostgreSQL database initialization completed successfully in {}ms",
+      total_duration.as_millis()
+    );
+    Ok(db)
+  }
+
+  /// Create the connection pool without touching the schema
+  pub async fn connect() -> anyhow::Result<Self> {
     info!("Initializing PostgreSQL database");
     let start = std::time::Instant::now();
 
-    let pool = match connection::create_pool().await {
+    let config = PoolConfig::from_env();
+    let pool = match connection::create_pool(&config).await {
       Ok(pool) => {
         let duration = start.elapsed();
         info!(
@@ -35,9 +74,13 @@ impl Database {
       }
     };
 
-    // Run migrations
+    Ok(Self::from_pool(pool, &config))
+  }
+
+  /// Bring the schema up to date
+  pub async fn migrate(&self) -> anyhow::Result<()> {
     let migration_start = std::time::Instant::now();
-    match migrations::run(&pool).await {
+    match migrations::run(&self.maintenance_pool).await {
       Ok(_) => {
         let duration = migration_start.elapsed();
         info!(
@@ -50,18 +93,120 @@ impl Database {
         return Err(e);
       }
     }
+    Ok(())
+  }
 
-    let total_duration = start.elapsed();
-    info!(
-      "PostgreSQL database initialization completed successfully in {}ms",
-      total_duration.as_millis()
-    );
+  pub fn from_pool(pool: PgPool, config: &PoolConfig) -> Self {
+    // A later `-c` wins over the pool's own statement_timeout
+    let maintenance_options = pool
+      .connect_options()
+      .as_ref()
+      .clone()
+      .options([("statement_timeout", "0")]);
+    let maintenance_pool = PgPoolOptions::new()
+      .max_connections(1)
+      .connect_lazy_with(maintenance_options);
 
-    Ok(Self { pool })
+    Self {
+      pool,
+      maintenance_pool,
+      query_timeout: config.query_timeout,
+    }
   }
 
   /// Get connection pool reference (for backward compatibility)
   pub fn pool(&self) -> &PgPool {
     &self.pool
   }
+
+  /// Pool for migrations, rollups and cleanups, which have no statement timeout
+  pub fn maintenance_pool(&self) -> &PgPool {
+    &self.maintenance_pool
+  }
+
+  /// Fail `operation` with [`SmoothieError::Timeout`] if it outlasts the
+  /// configured query timeout, instead of letting it hold a caller indefinitely
+  pub async fn with_timeout<T>(
+    &self,
+    operation: &str,
+    fut: impl Future<Output = Result<T>>,
+  ) -> Result<T> {
+    tokio::time::timeout(self.query_timeout, fut)
+      .await
+      .map_err(|_| {
+        SmoothieError::Timeout(format!(
+          "{} exceeded {}ms",
+          operation,
+          self.query_timeout.as_millis()
+        ))
+      })?
+  }
+
+  pub fn pool_stats(&self) -> PoolStats {
+    let size = self.pool.size();
+    let idle = self.pool.num_idle();
+    let in_use = size.saturating_sub(idle as u32);
+    let max_connections = self.pool.options().get_max_connections();
+    PoolStats {
+      size,
+      idle,
+      in_use,
+      max_connections,
+      utilization: in_use as f64 / max_connections.max(1) as f64,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use sqlx::postgres::PgPoolOptions;
+
+  #[tokio::test]
+  async fn test_with_timeout_fails_slow_operations() {
+    let pool = PgPoolOptions::new()
+      .max_connections(4)
+      .connect_lazy("postgres://localhost/unused")
+      .unwrap();
+    let config = PoolConfig {
+      query_timeout: Duration::from_millis(20),
+      ..PoolConfig::default()
+    };
+    let db = Database::from_pool(pool, &config);
+
+    let fast = db.with_timeout("fast", async { Ok(7) }).await.unwrap();
+    assert_eq!(fast, 7);
+
+    let slow = db
+      .with_timeout("slow", async {
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        Ok(())
+      })
+      .await;
+    assert!(matches!(slow, Err(SmoothieError::Timeout(m)) if m.contains("slow")));
+
+    let stats = db.pool_stats();
+    assert_eq!((stats.in_use, stats.max_connections), (0, 4));
+  }
+
+  #[tokio::test]
+  async fn test_maintenance_pool_has_no_statement_timeout() {
+    let Ok(url) = std::env::var(test_support::TEST_DATABASE_URL_ENV) else {
+      return;
+    };
+    let options = url
+      .parse::<sqlx::postgres::PgConnectOptions>()
+      .unwrap()
+      .options([("statement_timeout", "5s")]);
+    let pool = PgPoolOptions::new().connect_lazy_with(options);
+    let db = Database::from_pool(pool, &PoolConfig::default());
+
+    let show = "SHOW statement_timeout";
+    let regular: String = sqlx::query_scalar(show).fetch_one(db.pool()).await.unwrap();
+    let maintenance: String = sqlx::query_scalar(show)
+      .fetch_one(db.maintenance_pool())
+      .await
+      .unwrap();
+    assert_eq!((regular.as_str(), maintenance.as_str()), ("5s", "0"));
+  }
 }
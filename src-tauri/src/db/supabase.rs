@@ -0,0 +1,505 @@
+// Supabase client for the tables (PostgREST) and Storage objects the app
+// writes remotely
+//
+// Requests are retried with exponential backoff on 5xx, 429 and transport
+// failures. With a refresh token configured the client acts as that user,
+// refreshing the access token shortly before it expires or after a 401;
+// otherwise the project key itself is the bearer. Only the anon (publishable)
+// key belongs in the app; a service-role key would bypass row-level security,
+// so it's refused. Reads page through results with Range headers, and counts
+// come from PostgREST's exact count.
+
+use crate::error::{Result, SmoothieError};
+use chrono::Utc;
+use reqwest::{header, Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+pub const SUPABASE_URL_ENV: &str = "SUPABASE_URL";
+pub const SUPABASE_KEY_ENV: &str = "SUPABASE_KEY";
+pub const SUPABASE_REFRESH_TOKEN_ENV: &str = "SUPABASE_REFRESH_TOKEN";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tries per request, including the first
+const MAX_ATTEMPTS: u32 = 4;
+
+/// Delay before the first retry; doubled for each one after
+const RETRY_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Access tokens with less than this many seconds left are refreshed before use
+const REFRESH_MARGIN_SECS: i64 = 60;
+
+/// Rows requested per page by [`SupabaseClient::select`]; PostgREST's default
+/// `max-rows` is 1000, so a larger page would come back short and end early
+const PAGE_SIZE: i64 = 1000;
+
+/// Postgres `query_canceled`, raised when `statement_timeout` fires
+const QUERY_CANCELED: &str = "57014";
+
+/// Postgres `insufficient_privilege`, raised when a row-level security policy
+/// refuses a write
+const INSUFFICIENT_PRIVILEGE: &str = "42501";
+
+#[derive(Debug, Clone)]
+struct AuthSession {
+  access_token: String,
+  refresh_token: String,
+  /// Unix seconds
+  expires_at: i64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+  access_token: String,
+  refresh_token: String,
+  expires_in: i64,
+}
+
+/// Body PostgREST and Storage send with a failed request
+#[derive(Deserialize)]
+struct PostgrestError {
+  code: Option<String>,
+  message: Option<String>,
+  /// Storage reports the real status here, often under a 400
+  #[serde(rename = "statusCode")]
+  status_code: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct SupabaseClient {
+  http: reqwest::Client,
+  project_url: String,
+  key: String,
+  /// Signed-in user; `None` sends the project key as the bearer
+  session: Option<Arc<Mutex<AuthSession>>>,
+}
+
+impl SupabaseClient {
+  pub fn new(project_url: &str, key: &str) -> Self {
+    let http = reqwest::Client::builder()
+      .timeout(REQUEST_TIMEOUT)
+      .build()
+      .unwrap_or_default();
+    Self {
+      http,
+      project_url: project_url.trim_end_matches('/').to_string(),
+      key: key.to_string(),
+      session: None,
+    }
+  }
+
+  /// Act as the user `refresh_token` belongs to; the first request signs in
+  pub fn with_refresh_token(mut self, refresh_token: &str) -> Self {
+    self.session = Some(Arc::new(Mutex::new(AuthSession {
+      access_token: String::new(),
+      refresh_token: refresh_token.to_string(),
+      expires_at: 0,
+    })));
+    self
+  }
+
+  /// Client for `SUPABASE_URL` / `SUPABASE_KEY`, if both are set, signed in
+  /// with `SUPABASE_REFRESH_TOKEN` when that is set too
+  pub fn from_env() -> Option<Self> {
+    let env = |name| {
+      std::env::var(name)
+        .ok()
+        .map(|v: String| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+    };
+    let key = env(SUPABASE_KEY_ENV)?;
+    if is_service_role_key(&key) {
+      tracing::error!(
+        "{} is a service-role key; use the project's anon key instead",
+        SUPABASE_KEY_ENV
+      );
+      return None;
+    }
+    let client = Self::new(&env(SUPABASE_URL_ENV)?, &key);
+    Some(match env(SUPABASE_REFRESH_TOKEN_ENV) {
+      Some(refresh_token) => client.with_refresh_token(&refresh_token),
+      None => client,
+    })
+  }
+
+  /// Whether requests go out as a user rather than with the project key alone
+  pub fn is_signed_in(&self) -> bool {
+    self.session.is_some()
+  }
+
+  /// [`from_env`](Self::from_env), or a validation error naming what `feature`
+  /// needs configured
+  pub fn require_env(feature: &str) -> Result<Self> {
+    Self::from_env().ok_or_else(|| {
+      SmoothieError::ValidationError(format!(
+        "{} requires {} and {}",
+        feature, SUPABASE_URL_ENV, SUPABASE_KEY_ENV
+      ))
+    })
+  }
+
+  /// Insert one row or an array of rows. Rows whose primary key already
+  /// exists are skipped, so a retried insert doesn't fail or duplicate.
+  pub async fn insert<T: Serialize + ?Sized>(&self, table: &str, rows: &T) -> Result<()> {
+    self
+      .send(table, || {
+        self
+          .request(Method::POST, table)
+          .header("Prefer", "return=minimal,resolution=ignore-duplicates")
+          .json(rows)
+      })
+      .await
+      .map(|_| ())
+  }
+
+  /// Rows matching PostgREST query parameters, e.g. `("id", "eq.<uuid>")`,
+  /// fetched [`PAGE_SIZE`] at a time so tables past the server's `max-rows`
+  /// cap come back whole. Pass an `order` parameter so pages don't overlap.
+  pub async fn select<T: DeserializeOwned>(
+    &self,
+    table: &str,
+    params: &[(&str, String)],
+  ) -> Result<Vec<T>> {
+    let mut rows = Vec::new();
+    loop {
+      let page: Vec<T> = self
+        .select_page(table, params, rows.len() as i64, PAGE_SIZE)
+        .await?;
+      let last = (page.len() as i64) < PAGE_SIZE;
+      rows.extend(page);
+      if last {
+        return Ok(rows);
+      }
+    }
+  }
+
+  /// `limit` rows starting at `offset`, requested with a Range header
+  async fn select_page<T: DeserializeOwned>(
+    &self,
+    table: &str,
+    params: &[(&str, String)],
+    offset: i64,
+    limit: i64,
+  ) -> Result<Vec<T>> {
+    let offset = offset.max(0);
+    let range = format!("{}-{}", offset, offset + limit.max(1) - 1);
+    let response = self
+      .send(table, || {
+        self
+          .request(Method::GET, table)
+          .query(params)
+          .header("Range-Unit", "items")
+          .header(header::RANGE, &range)
+      })
+      .await?;
+
+    // Asking for a range past the end is answered with 416 and no body
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+      return Ok(Vec::new());
+    }
+    decode(table, response).await
+  }
+
+  /// Exact number of rows matching `params`, without fetching any
+  pub async fn count(&self, table: &str, params: &[(&str, String)]) -> Result<i64> {
+    let response = self
+      .send(table, || {
+        self
+          .request(Method::HEAD, table)
+          .query(params)
+          .header("Prefer", "count=exact")
+      })
+      .await?;
+    response
+      .headers()
+      .get(header::CONTENT_RANGE)
+      .and_then(|value| value.to_str().ok())
+      .and_then(content_range_total)
+      .ok_or_else(|| {
+        SmoothieError::SerializationError(format!("Supabase {}: no count in response", table))
+      })
+  }
+
+  /// Store `body` as a JSON object at `path` in a Storage bucket. An existing
+  /// object is left alone and the upload fails.
+  pub async fn upload_object<T: Serialize + ?Sized>(
+    &self,
+    bucket: &str,
+    path: &str,
+    body: &T,
+  ) -> Result<()> {
+    self
+      .send(bucket, || {
+        self
+          .storage_request(Method::POST, bucket, path)
+          .header("x-upsert", "false")
+          .json(body)
+      })
+      .await
+      .map(|_| ())
+  }
+
+  /// A JSON object from a Storage bucket
+  pub async fn download_object<T: DeserializeOwned>(&self, bucket: &str, path: &str) -> Result<T> {
+    let response = self
+      .send(bucket, || self.storage_request(Method::GET, bucket, path))
+      .await?;
+    decode(bucket, response).await
+  }
+
+  fn storage_request(&self, method: Method, bucket: &str, path: &str) -> RequestBuilder {
+    self.http.request(
+      method,
+      format!("{}/storage/v1/object/{}/{}", self.project_url, bucket, path),
+    )
+  }
+
+  fn request(&self, method: Method, table: &str) -> RequestBuilder {
+    self
+      .http
+      .request(method, format!("{}/rest/v1/{}", self.project_url, table))
+  }
+
+  /// Send the request `build` makes, authenticated, retrying what can be retried
+  async fn send(&self, table: &str, build: impl Fn() -> RequestBuilder) -> Result<Response> {
+    let mut token = self.bearer(None).await?;
+    let mut refreshed = false;
+    let mut attempt = 1;
+    loop {
+      let result = build()
+        .header("apikey", &self.key)
+        .bearer_auth(&token)
+        .send()
+        .await;
+
+      let retryable = match &result {
+        Ok(response) if response.status() == StatusCode::UNAUTHORIZED => {
+          // An expired or revoked access token gets one refresh, not a retry budget
+          if self.session.is_none() || refreshed {
+            false
+          } else {
+            refreshed = true;
+            token = self.bearer(Some(&token)).await?;
+            continue;
+          }
+        }
+        Ok(response) => {
+          response.status().is_server_error() || response.status() == StatusCode::TOO_MANY_REQUESTS
+        }
+        Err(e) => e.is_timeout() || e.is_connect(),
+      };
+      if !retryable || attempt >= MAX_ATTEMPTS {
+        return check(table, result).await;
+      }
+
+      let delay = RETRY_BACKOFF.saturating_mul(1 << (attempt - 1));
+      tracing::debug!(
+        table,
+        attempt,
+        retry_in_ms = delay.as_millis() as u64,
+        "Retrying Supabase request"
+      );
+      tokio::time::sleep(delay).await;
+      attempt += 1;
+    }
+  }
+
+  /// Token to authenticate with. `stale` is a token the server rejected; it is
+  /// replaced unless another request already refreshed it.
+  async fn bearer(&self, stale: Option<&str>) -> Result<String> {
+    let Some(session) = &self.session else {
+      return Ok(self.key.clone());
+    };
+    let mut session = session.lock().await;
+    let expiring = session.expires_at - Utc::now().timestamp() < REFRESH_MARGIN_SECS;
+    if expiring || stale == Some(session.access_token.as_str()) {
+      *session = self.refresh(&session.refresh_token).await?;
+      tracing::debug!(
+        expires_at = session.expires_at,
+        "Refreshed Supabase access token"
+      );
+    }
+    Ok(session.access_token.clone())
+  }
+
+  async fn refresh(&self, refresh_token: &str) -> Result<AuthSession> {
+    let result = self
+      .http
+      .post(format!(
+        "{}/auth/v1/token?grant_type=refresh_token",
+        self.project_url
+      ))
+      .header("apikey", &self.key)
+      .json(&serde_json::json!({ "refresh_token": refresh_token }))
+      .send()
+      .await;
+    let token: TokenResponse = decode("auth", check("auth", result).await?).await?;
+    Ok(AuthSession {
+      access_token: token.access_token,
+      refresh_token: token.refresh_token,
+      expires_at: Utc::now().timestamp() + token.expires_in,
+    })
+  }
+}
+
+async fn check(table: &str, result: reqwest::Result<Response>) -> Result<Response> {
+  let response = result.map_err(|e| {
+    if e.is_timeout() {
+      SmoothieError::Timeout(format!("Supabase {}", table))
+    } else {
+      SmoothieError::DatabaseError(format!("Supabase {}: {}", table, e))
+    }
+  })?;
+  let status = response.status();
+  if status.is_success() || status == StatusCode::RANGE_NOT_SATISFIABLE {
+    return Ok(response);
+  }
+  let body = response.text().await.unwrap_or_default();
+  Err(map_error(table, status, &body))
+}
+
+async fn decode<T: DeserializeOwned>(table: &str, response: Response) -> Result<T> {
+  response
+    .json()
+    .await
+    .map_err(|e| SmoothieError::SerializationError(format!("Supabase {}: {}", table, e)))
+}
+
+/// Turn a failed response into the error a local query would have produced
+fn map_error(table: &str, status: StatusCode, body: &str) -> SmoothieError {
+  let parsed: Option<PostgrestError> = serde_json::from_str(body).ok();
+  let code = parsed.as_ref().and_then(|e| e.code.clone());
+  let status = parsed
+    .as_ref()
+    .and_then(|e| e.status_code.as_deref())
+    .and_then(|s| s.parse().ok())
+    .and_then(|s| StatusCode::from_u16(s).ok())
+    .unwrap_or(status);
+  let message = parsed
+    .and_then(|e| e.message)
+    .unwrap_or_else(|| body.trim().to_string());
+  let message = format!("Supabase {}: {}", table, message);
+
+  match code.as_deref() {
+    Some(QUERY_CANCELED) => return SmoothieError::Timeout(message),
+    Some(INSUFFICIENT_PRIVILEGE) => return SmoothieError::Forbidden(message),
+    _ => {}
+  }
+  match status {
+    StatusCode::FORBIDDEN => SmoothieError::Forbidden(message),
+    StatusCode::BAD_REQUEST => SmoothieError::ValidationError(message),
+    StatusCode::NOT_FOUND => SmoothieError::NotFound(message),
+    StatusCode::TOO_MANY_REQUESTS => SmoothieError::RateLimited(message),
+    StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => SmoothieError::Timeout(message),
+    _ => SmoothieError::DatabaseError(format!("{} ({})", message, status)),
+  }
+}
+
+/// Secret keys, either the `sb_secret_` kind or a legacy JWT whose role is
+/// `service_role`
+fn is_service_role_key(key: &str) -> bool {
+  if key.starts_with("sb_secret_") {
+    return true;
+  }
+  let Some(payload) = key.split('.').nth(1).and_then(base64url_decode) else {
+    return false;
+  };
+  serde_json::from_slice::<serde_json::Value>(&payload)
+    .is_ok_and(|claims| claims["role"] == "service_role")
+}
+
+/// Unpadded base64url, as used in JWT segments
+fn base64url_decode(input: &str) -> Option<Vec<u8>> {
+  let mut bits = 0u32;
+  let mut bit_count = 0;
+  let mut out = Vec::with_capacity(input.len() * 3 / 4);
+  for byte in input.trim_end_matches('=').bytes() {
+    let value = match byte {
+      b'A'..=b'Z' => byte - b'A',
+      b'a'..=b'z' => byte - b'a' + 26,
+      b'0'..=b'9' => byte - b'0' + 52,
+      b'-' => 62,
+      b'_' => 63,
+      _ => return None,
+    };
+    bits = (bits << 6) | u32::from(value);
+    bit_count += 6;
+    if bit_count >= 8 {
+      bit_count -= 8;
+      out.push((bits >> bit_count) as u8);
+    }
+  }
+  Some(out)
+}
+
+/// Total from a `Content-Range` header like `0-24/3573`; `None` for `*`
+fn content_range_total(value: &str) -> Option<i64> {
+  value.rsplit_once('/')?.1.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_reads_totals_and_maps_errors() {
+    assert_eq!(content_range_total("0-24/3573"), Some(3573));
+    assert_eq!(content_range_total("*/0"), Some(0));
+    assert_eq!(content_range_total("0-24/*"), None);
+
+    let bad_filter = map_error(
+      "audit_events",
+      StatusCode::BAD_REQUEST,
+      r#"{"code":"PGRST100","message":"failed to parse filter"}"#,
+    );
+    assert_eq!(
+      bad_filter.to_string(),
+      "Validation error: Supabase audit_events: failed to parse filter"
+    );
+    assert!(matches!(
+      map_error(
+        "audit_events",
+        StatusCode::INTERNAL_SERVER_ERROR,
+        r#"{"code":"57014","message":"canceling statement due to statement timeout"}"#,
+      ),
+      SmoothieError::Timeout(_)
+    ));
+    assert!(matches!(
+      map_error(
+        "shared-profiles",
+        StatusCode::BAD_REQUEST,
+        r#"{"statusCode":"404","error":"not_found","message":"Object not found"}"#,
+      ),
+      SmoothieError::NotFound(_)
+    ));
+    assert!(matches!(
+      map_error(
+        "team_profiles",
+        StatusCode::UNAUTHORIZED,
+        r#"{"code":"42501","message":"new row violates row-level security policy"}"#,
+      ),
+      SmoothieError::Forbidden(_)
+    ));
+    assert!(matches!(
+      map_error("audit_events", StatusCode::BAD_GATEWAY, "upstream down"),
+      SmoothieError::DatabaseError(m) if m.contains("upstream down")
+    ));
+  }
+
+  #[test]
+  fn test_service_role_keys_are_recognised() {
+    // Payloads {"role":"service_role"} and {"role":"anon"}
+    assert!(is_service_role_key(
+      "eyJhbGciOiJIUzI1NiJ9.eyJyb2xlIjoic2VydmljZV9yb2xlIn0.sig"
+    ));
+    assert!(!is_service_role_key(
+      "eyJhbGciOiJIUzI1NiJ9.eyJyb2xlIjoiYW5vbiJ9.sig"
+    ));
+    assert!(is_service_role_key("sb_secret_abc123"));
+    assert!(!is_service_role_key("sb_publishable_abc123"));
+  }
+}
@@ -0,0 +1,145 @@
+// Ephemeral Postgres schemas for repository integration tests
+//
+// Point SMOOTHIE_TEST_DATABASE_URL at a scratch database to run them. Every
+// `TestDb` creates its own schema, runs the migrations into it and drops it
+// again when the value goes out of scope, so tests can run in parallel
+// without seeing each other's rows. Without the variable the tests skip.
+
+use super::{connection::PoolConfig, migrations, Database};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::{Connection, Executor, PgConnection};
+use std::str::FromStr;
+use uuid::Uuid;
+
+pub const TEST_DATABASE_URL_ENV: &str = "SMOOTHIE_TEST_DATABASE_URL";
+
+pub struct TestDb {
+  pub db: Database,
+  url: String,
+  schema: String,
+}
+
+impl TestDb {
+  /// Create an isolated, migrated schema, or `None` when no test database is configured
+  pub async fn new() -> Option<Self> {
+    let test_db = Self::unmigrated().await?;
+    migrations::run(test_db.db.maintenance_pool())
+      .await
+      .expect("failed to run migrations on test schema");
+    Some(test_db)
+  }
+
+  /// Create an isolated, empty schema, for tests that build an older schema
+  /// and migrate it themselves
+  pub async fn unmigrated() -> Option<Self> {
+    let url = std::env::var(TEST_DATABASE_URL_ENV).ok()?;
+    let schema = format!("test_{}", Uuid::new_v4().simple());
+
+    let mut admin = PgConnection::connect(&url)
+      .await
+      .expect("failed to connect to test database");
+    // Keep the extension in `public` so it survives the per-test schema being dropped
+    admin
+      .execute(r#"CREATE EXTENSION IF NOT EXISTS "uuid-ossp" SCHEMA public"#)
+      .await
+      .expect("failed to enable uuid-ossp");
+    admin
+      .execute(format!("CREATE SCHEMA {}", schema).as_str())
+      .await
+      .expect("failed to create test schema");
+    admin.close().await.ok();
+
+    // Set through the connect options so the maintenance pool shares it
+    let options = PgConnectOptions::from_str(&url)
+      .expect("invalid test database url")
+      .options([("search_path", format!("{},public", schema))]);
+    let pool = PgPoolOptions::new()
+      .max_connections(2)
+      .connect_with(options)
+      .await
+      .expect("failed to create test pool");
+
+    Some(Self {
+      db: Database::from_pool(pool, &PoolConfig::default()),
+      url,
+      schema,
+    })
+  }
+
+  pub fn pool(&self) -> &PgPool {
+    self.db.pool()
+  }
+
+  /// Insert a user, returning its id
+  pub async fn seed_user(&self) -> Uuid {
+    let user_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO users (id) VALUES ($1)")
+      .bind(user_id)
+      .execute(self.pool())
+      .await
+      .expect("failed to seed user");
+    user_id
+  }
+
+  /// Insert a work profile named `name` for `user_id`, returning its id
+  pub async fn seed_profile(&self, user_id: Uuid, name: &str) -> Uuid {
+    sqlx::query_scalar(
+      "INSERT INTO profiles (user_id, name, type) VALUES ($1, $2, 'work') RETURNING id",
+    )
+    .bind(user_id)
+    .bind(name)
+    .fetch_one(self.pool())
+    .await
+    .expect("failed to seed profile")
+  }
+
+  /// Insert a user with one work profile named `name`, returning both ids
+  pub async fn seed_user_and_profile(&self, name: &str) -> (Uuid, Uuid) {
+    let user_id = self.seed_user().await;
+    (user_id, self.seed_profile(user_id, name).await)
+  }
+}
+
+impl Drop for TestDb {
+  fn drop(&mut self) {
+    let url = self.url.clone();
+    let schema = self.schema.clone();
+
+    // Drop runs on the test's runtime thread, so clean up from a fresh runtime.
+    // The pool's connections are idle by now and don't block the DROP.
+    let cleanup = std::thread::spawn(move || {
+      tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build cleanup runtime")
+        .block_on(async move {
+          if let Ok(mut conn) = PgConnection::connect(&url).await {
+            let drop_schema = format!("DROP SCHEMA IF EXISTS {} CASCADE", schema);
+            if let Err(e) = conn.execute(drop_schema.as_str()).await {
+              eprintln!("failed to drop test schema {}: {}", schema, e);
+            }
+            conn.close().await.ok();
+          }
+        });
+    });
+    cleanup.join().ok();
+  }
+}
+
+/// Set up a `TestDb`, or return from the test early when no database is configured
+macro_rules! test_db_or_skip {
+  () => {
+    match $crate::db::test_support::TestDb::new().await {
+      Some(test_db) => test_db,
+      None => {
+        eprintln!(
+          "skipping: {} not set",
+          $crate::db::test_support::TEST_DATABASE_URL_ENV
+        );
+        return;
+      }
+    }
+  };
+}
+
+pub(crate) use test_db_or_skip;
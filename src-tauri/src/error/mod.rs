@@ -1,5 +1,10 @@
 // Error handling for Smoothie
 
+use crate::models::SuccessResponse;
+use crate::services::activation::StepReport;
+use crate::services::ConnectionMonitor;
+use crate::utils::validation::FieldErrors;
+use serde::ser::SerializeStruct;
 use serde::Serialize;
 use thiserror::Error;
 
@@ -8,12 +13,21 @@ pub enum SmoothieError {
   #[error("Database error: {0}")]
   DatabaseError(String),
 
+  /// Postgres couldn't be reached: the connection was refused or lost, or
+  /// none came free before the pool gave up
+  #[error("Database unavailable: {0}")]
+  DatabaseUnavailable(String),
+
   #[error("Not found: {0}")]
   NotFound(String),
 
   #[error("Validation error: {0}")]
   ValidationError(String),
 
+  /// Per-field messages from `utils::validation`
+  #[error("Validation error: {}", summarize_fields(.0))]
+  InvalidFields(FieldErrors),
+
   #[error("IO error: {0}")]
   IoError(String),
 
@@ -22,26 +36,152 @@ pub enum SmoothieError {
 
   #[error("System error: {0}")]
   SystemError(String),
+
+  #[error("Rate limited: {0}")]
+  RateLimited(String),
+
+  /// The caller's role doesn't allow the operation
+  #[error("Forbidden: {0}")]
+  Forbidden(String),
+
+  /// A query or other bounded operation ran out of time
+  #[error("Timed out: {0}")]
+  Timeout(String),
+
+  /// The app hasn't finished starting; retry once it's ready
+  #[error("Starting up: {0}")]
+  StartingUp(String),
+
+  /// The profile is locked against edits
+  #[error("Locked: {0}")]
+  Locked(String),
+
+  /// Read-only mode rejects every change
+  #[error("Read-only: {0}")]
+  ReadOnly(String),
+
+  /// A profile start stopped at a failed step; `steps` shows how far it got
+  /// and what was rolled back
+  #[error("{error}")]
+  ActivationFailed {
+    error: Box<SmoothieError>,
+    steps: Vec<StepReport>,
+  },
+}
+
+/// A failed command, tagged with the request id that appears in the backend logs
+#[derive(Debug)]
+pub struct CommandError {
+  pub request_id: String,
+  pub error: SmoothieError,
 }
 
-// Implement Serialize manually for Tauri error handling
-impl Serialize for SmoothieError {
+// Implement Serialize manually for Tauri error handling.
+// Errors go out as `{ message, requestId }`; field errors add `fields` so forms
+// can render them inline. Errors the UI acts on add a `code`: "starting_up"
+// so the call can be retried, "profile_locked" so it can offer to unlock,
+// "read_only" so it can hide editing, "db_offline" so it can show offline mode
+// instead of the driver's error. A failed profile start adds its `steps`.
+impl Serialize for CommandError {
   fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
   where
     S: serde::Serializer,
   {
-    serializer.serialize_str(&self.to_string())
+    let fields = match &self.error {
+      SmoothieError::InvalidFields(fields) => Some(fields),
+      _ => None,
+    };
+    let steps = match &self.error {
+      SmoothieError::ActivationFailed { steps, .. } => Some(steps),
+      _ => None,
+    };
+    let code = match &self.error {
+      SmoothieError::StartingUp(_) => Some("starting_up"),
+      SmoothieError::ActivationFailed { .. } => Some("activation_failed"),
+      SmoothieError::Locked(_) => Some("profile_locked"),
+      SmoothieError::ReadOnly(_) => Some("read_only"),
+      SmoothieError::DatabaseUnavailable(_) => Some("db_offline"),
+      SmoothieError::DatabaseError(_) | SmoothieError::Timeout(_)
+        if !ConnectionMonitor::is_online() =>
+      {
+        Some("db_offline")
+      }
+      _ => None,
+    };
+    let len = 2
+      + usize::from(fields.is_some())
+      + usize::from(steps.is_some())
+      + usize::from(code.is_some());
+    let mut state = serializer.serialize_struct("CommandError", len)?;
+    state.serialize_field("message", &self.error.to_string())?;
+    state.serialize_field("requestId", &self.request_id)?;
+    if let Some(fields) = fields {
+      state.serialize_field("fields", fields)?;
+    }
+    if let Some(steps) = steps {
+      state.serialize_field("steps", steps)?;
+    }
+    if let Some(code) = code {
+      state.serialize_field("code", code)?;
+    }
+    state.end()
   }
 }
 
+fn summarize_fields(fields: &FieldErrors) -> String {
+  fields
+    .iter()
+    .map(|(field, messages)| format!("{}: {}", field, messages.join(", ")))
+    .collect::<Vec<_>>()
+    .join("; ")
+}
+
 pub type Result<T> = std::result::Result<T, SmoothieError>;
 
+/// What every Tauri command returns
+pub type CommandResult<T> = std::result::Result<SuccessResponse<T>, CommandError>;
+
 impl From<std::io::Error> for SmoothieError {
   fn from(err: std::io::Error) -> Self {
     SmoothieError::IoError(err.to_string())
   }
 }
 
+/// Postgres `query_canceled`, raised when `statement_timeout` fires
+const QUERY_CANCELED: &str = "57014";
+
+/// Postgres error classes meaning the server went away mid-connection:
+/// connection exceptions, and the server shutting down or still starting
+const CONNECTION_LOST: &[&str] = &["08", "57P"];
+
+impl From<sqlx::Error> for SmoothieError {
+  fn from(err: sqlx::Error) -> Self {
+    SmoothieError::from(&err)
+  }
+}
+
+impl From<&sqlx::Error> for SmoothieError {
+  fn from(err: &sqlx::Error) -> Self {
+    let code = match err {
+      sqlx::Error::Database(db_err) => db_err.code().map(|code| code.into_owned()),
+      _ => None,
+    };
+    let unreachable = match err {
+      sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_) => true,
+      _ => code
+        .as_deref()
+        .is_some_and(|code| CONNECTION_LOST.iter().any(|class| code.starts_with(class))),
+    };
+    if unreachable {
+      SmoothieError::DatabaseUnavailable(err.to_string())
+    } else if code.as_deref() == Some(QUERY_CANCELED) {
+      SmoothieError::Timeout(err.to_string())
+    } else {
+      SmoothieError::DatabaseError(err.to_string())
+    }
+  }
+}
+
 impl From<serde_json::Error> for SmoothieError {
   fn from(err: serde_json::Error) -> Self {
     SmoothieError::SerializationError(err.to_string())
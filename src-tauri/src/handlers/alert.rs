@@ -0,0 +1,65 @@
+use crate::logging::instrumented;
+use crate::{
+  error::CommandResult,
+  models::dto::{AlertDto, AlertRuleDto},
+  services::AlertService,
+  state::AppState,
+};
+use std::sync::Arc;
+use tauri::State;
+
+/// Alerts that are currently firing
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_active_alerts(
+  state: State<'_, Arc<AppState>>,
+  user_id: String,
+) -> CommandResult<Vec<AlertDto>> {
+  instrumented!("get_active_alerts", (&user_id,), async {
+    let alerts = AlertService::get_active_alerts(&state.db, &user_id).await?;
+    Ok(alerts)
+  })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_alert_rules(
+  state: State<'_, Arc<AppState>>,
+  user_id: String,
+) -> CommandResult<Vec<AlertRuleDto>> {
+  instrumented!("get_alert_rules", (&user_id,), async {
+    let rules = AlertService::get_rules(&state.db, &user_id).await?;
+    Ok(rules)
+  })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn update_alert_rule(
+  state: State<'_, Arc<AppState>>,
+  rule_id: String,
+  enabled: Option<bool>,
+  threshold: Option<f64>,
+  window_minutes: Option<i32>,
+  cooldown_minutes: Option<i32>,
+) -> CommandResult<AlertRuleDto> {
+  instrumented!(
+    "update_alert_rule",
+    (
+      &rule_id,
+      &enabled,
+      &threshold,
+      &window_minutes,
+      &cooldown_minutes
+    ),
+    async {
+      let rule = AlertService::update_rule(
+        &state.db,
+        &rule_id,
+        enabled,
+        threshold,
+        window_minutes,
+        cooldown_minutes,
+      )
+      .await?;
+      Ok(rule)
+    }
+  )
+}
@@ -1,5 +1,12 @@
+use crate::logging::instrumented;
+use crate::services::app_presence::MissingAppDto;
 use crate::services::app_service::LaunchResult;
-use crate::{error::Result, models::SuccessResponse, services::AppService, state::AppState};
+use crate::{
+  error::CommandResult,
+  models::{AppDto, CreateAppRequest},
+  services::{AppService, InstalledApp},
+  state::{AppState, INSTALLED_APPS_CACHE_KEY},
+};
 use std::sync::Arc;
 use tauri::State;
 
@@ -15,43 +22,69 @@ pub async fn create_app(
   monitor_preference: Option<i32>,
   startup_delay_ms: Option<i32>,
   order_index: Option<i32>,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let app = AppService::create_app(
-    &state.db,
-    &profile_id,
-    &user_id,
-    name,
-    bundle_id,
-    exe_path,
-    launch_on_activate,
-    monitor_preference,
-    startup_delay_ms,
-    order_index,
-  )
-  .await?;
+) -> CommandResult<AppDto> {
+  instrumented!(
+    "create_app",
+    (
+      &profile_id,
+      &user_id,
+      &name,
+      &bundle_id,
+      &exe_path,
+      &launch_on_activate,
+      &monitor_preference,
+      &startup_delay_ms,
+      &order_index
+    ),
+    async {
+      let app = AppService::create_app(
+        &state.db,
+        &profile_id,
+        &user_id,
+        CreateAppRequest {
+          name,
+          bundle_id,
+          exe_path,
+          launch_on_activate,
+          monitor_preference,
+          startup_delay_ms,
+          order_index,
+        },
+      )
+      .await?;
 
-  state.invalidate_cache(&format!("apps_{}", profile_id));
+      state.invalidate_cache(&format!("apps_{}", profile_id));
+      state.invalidate_cache(&format!("profile_{}", profile_id));
 
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(app)?,
-  })
+      Ok(app)
+    }
+  )
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn get_apps(
   state: State<'_, Arc<AppState>>,
   profile_id: String,
-) -> Result<SuccessResponse<Vec<serde_json::Value>>> {
-  let apps = AppService::get_apps(&state.db, &profile_id).await?;
-  let data: Vec<serde_json::Value> = apps
-    .into_iter()
-    .map(|a| serde_json::to_value(a).unwrap())
-    .collect();
-
-  Ok(SuccessResponse {
-    success: true,
-    data,
+) -> CommandResult<Vec<AppDto>> {
+  instrumented!("get_apps", (&profile_id,), async {
+    let apps = AppService::get_apps(&state.db, &profile_id).await?;
+    Ok(apps)
+  })
+}
+
+/// Order the profile's apps as listed; unlisted ones follow in their current order
+#[tauri::command(rename_all = "camelCase")]
+pub async fn reorder_apps(
+  state: State<'_, Arc<AppState>>,
+  profile_id: String,
+  ids_in_order: Vec<String>,
+) -> CommandResult<Vec<AppDto>> {
+  instrumented!("reorder_apps", (&profile_id, &ids_in_order), async {
+    let apps = AppService::reorder_apps(&state.db, &profile_id, &ids_in_order).await?;
+    state.invalidate_cache(&format!("apps_{}", profile_id));
+    state.invalidate_cache(&format!("profile_{}", profile_id));
+
+    Ok(apps)
   })
 }
 
@@ -60,44 +93,82 @@ pub async fn update_app(
   state: State<'_, Arc<AppState>>,
   app_id: String,
   launch_on_activate: Option<bool>,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let app = AppService::update_app(&state.db, &app_id, launch_on_activate).await?;
+) -> CommandResult<AppDto> {
+  instrumented!("update_app", (&app_id, &launch_on_activate), async {
+    let app = AppService::update_app(&state.db, &app_id, launch_on_activate).await?;
+    state.invalidate_cache(&format!("profile_{}", app.profile_id));
 
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(app)?,
+    Ok(app)
   })
 }
 
 #[tauri::command(rename_all = "camelCase")]
-pub async fn delete_app(
-  state: State<'_, Arc<AppState>>,
-  app_id: String,
-) -> Result<SuccessResponse<String>> {
-  AppService::delete_app(&state.db, &app_id).await?;
+pub async fn delete_app(state: State<'_, Arc<AppState>>, app_id: String) -> CommandResult<String> {
+  instrumented!("delete_app", (&app_id,), async {
+    AppService::delete_app(&state.db, &app_id).await?;
+    state.invalidate_profile_caches();
 
-  Ok(SuccessResponse {
-    success: true,
-    data: "App deleted successfully".to_string(),
+    Ok("App deleted successfully".to_string())
   })
 }
 
+/// A fresh installed-app index, which also refreshes the cached one
+async fn installed_apps(state: &AppState) -> Vec<InstalledApp> {
+  let apps = AppService::scan_installed_apps(state.system.clone()).await;
+  state.set_cache(INSTALLED_APPS_CACHE_KEY, &apps);
+  apps
+}
+
+/// The profile's apps that are no longer installed, each with installed apps
+/// that could replace it
 #[tauri::command(rename_all = "camelCase")]
-pub async fn launch_apps(
+pub async fn get_missing_apps(
   state: State<'_, Arc<AppState>>,
   profile_id: String,
-) -> Result<SuccessResponse<Vec<LaunchResult>>> {
-  let results = AppService::launch_profile_apps(
-    &state.db,
-    &profile_id,
-    "00000000-0000-0000-0000-000000000001",
+) -> CommandResult<Vec<MissingAppDto>> {
+  instrumented!("get_missing_apps", (&profile_id,), async {
+    let installed = installed_apps(&state).await;
+    AppService::get_missing_apps(&state.db, &profile_id, &installed).await
+  })
+}
+
+/// Replace profile app `oldAppId` with the installed app `newBundleId`,
+/// keeping its saved windows
+#[tauri::command(rename_all = "camelCase")]
+pub async fn replace_profile_app(
+  state: State<'_, Arc<AppState>>,
+  old_app_id: String,
+  new_bundle_id: String,
+) -> CommandResult<AppDto> {
+  instrumented!(
+    "replace_profile_app",
+    (&old_app_id, &new_bundle_id),
+    async {
+      let installed = installed_apps(&state).await;
+      let app = AppService::replace_app(&state.db, &old_app_id, &new_bundle_id, &installed).await?;
+      state.invalidate_cache(&format!("profile_{}", app.profile_id));
+
+      Ok(app)
+    }
   )
-  .await?;
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn launch_apps(
+  state: State<'_, Arc<AppState>>,
+  profile_id: String,
+) -> CommandResult<Vec<LaunchResult>> {
+  instrumented!("launch_apps", (&profile_id,), async {
+    let results = AppService::launch_profile_apps(
+      &state.db,
+      state.system.clone(),
+      &profile_id,
+      "00000000-0000-0000-0000-000000000001",
+    )
+    .await?;
 
-  tracing::info!("Launched {} apps for profile {}", results.len(), profile_id);
+    tracing::info!("Launched {} apps for profile {}", results.len(), profile_id);
 
-  Ok(SuccessResponse {
-    success: true,
-    data: results,
+    Ok(results)
   })
 }
@@ -1,6 +1,20 @@
 // Audit and logging handlers - Tauri commands for log management
 
-use crate::{db::Database, error::Result, models::dto::*, services::AUDIT_SERVICE};
+use crate::logging::instrumented;
+use crate::{
+  db::Database,
+  error::CommandResult,
+  models::dto::*,
+  models::enums::{ActivationSource, ActivityStatus, MonitorChangeType, Severity},
+  models::UserId,
+  repositories::JsonFilter,
+  services::{
+    log_export_service::SanitizedLogExportDto, LogExportService, UsageReportService, AUDIT_SERVICE,
+  },
+  state::AppState,
+  utils::log_sanitizer::SanitizeOptions,
+};
+use std::sync::Arc;
 use tauri::State;
 
 const DEFAULT_USER_ID: &str = "00000000-0000-0000-0000-000000000001";
@@ -14,16 +28,23 @@ const DEFAULT_USER_ID: &str = "00000000-0000-0000-0000-000000000001";
 pub async fn start_session(
   db: State<'_, Database>,
   device_info: Option<serde_json::Value>,
-) -> Result<SessionDto> {
-  AUDIT_SERVICE
-    .start_session(&db, DEFAULT_USER_ID, device_info)
-    .await
+) -> CommandResult<SessionDto> {
+  instrumented!("start_session", (&device_info,), async {
+    AUDIT_SERVICE
+      .start_session(&db, DEFAULT_USER_ID, device_info)
+      .await
+  })
 }
 
 /// End the current session
 #[tauri::command]
-pub async fn end_session(db: State<'_, Database>, reason: String) -> Result<Option<SessionDto>> {
-  AUDIT_SERVICE.end_session(&db, &reason).await
+pub async fn end_session(
+  db: State<'_, Database>,
+  reason: String,
+) -> CommandResult<Option<SessionDto>> {
+  instrumented!("end_session", (&reason,), async {
+    AUDIT_SERVICE.end_session(&db, &reason).await
+  })
 }
 
 /// Get session history
@@ -32,19 +53,22 @@ pub async fn get_sessions(
   db: State<'_, Database>,
   limit: Option<i64>,
   offset: Option<i64>,
-) -> Result<Vec<SessionDto>> {
-  let params = LogQueryParams {
-    limit,
-    offset,
-    start_date: None,
-    end_date: None,
-    action: None,
-    entity_type: None,
-    severity: None,
-  };
-  AUDIT_SERVICE
-    .get_sessions(&db, DEFAULT_USER_ID, params)
-    .await
+) -> CommandResult<Vec<SessionDto>> {
+  instrumented!("get_sessions", (&limit, &offset), async {
+    let params = LogQueryParams {
+      limit,
+      offset,
+      start_date: None,
+      end_date: None,
+      action: None,
+      entity_type: None,
+      severity: None,
+      details: None,
+    };
+    AUDIT_SERVICE
+      .get_sessions(&db, DEFAULT_USER_ID, params)
+      .await
+  })
 }
 
 // ============================================================================
@@ -60,24 +84,39 @@ pub async fn log_activity(
   entity_id: Option<String>,
   entity_name: Option<String>,
   details: Option<serde_json::Value>,
-  status: Option<String>,
+  status: Option<ActivityStatus>,
   error_message: Option<String>,
   duration_ms: Option<i32>,
-) -> Result<ActivityLogDto> {
-  AUDIT_SERVICE
-    .log_activity(
-      &db,
-      DEFAULT_USER_ID,
+) -> CommandResult<ActivityLogDto> {
+  instrumented!(
+    "log_activity",
+    (
       &action,
-      entity_type.as_deref(),
-      entity_id.as_deref(),
-      entity_name.as_deref(),
-      details,
-      &status.unwrap_or_else(|| "success".to_string()),
-      error_message.as_deref(),
-      duration_ms,
-    )
-    .await
+      &entity_type,
+      &entity_id,
+      &entity_name,
+      &details,
+      &status,
+      &error_message,
+      &duration_ms
+    ),
+    async {
+      AUDIT_SERVICE
+        .log_activity(
+          &db,
+          DEFAULT_USER_ID,
+          &action,
+          entity_type.as_deref(),
+          entity_id.as_deref(),
+          entity_name.as_deref(),
+          details,
+          status.unwrap_or(ActivityStatus::Success),
+          error_message.as_deref(),
+          duration_ms,
+        )
+        .await
+    }
+  )
 }
 
 /// Get activity logs
@@ -90,19 +129,35 @@ pub async fn get_activity_logs(
   entity_type: Option<String>,
   start_date: Option<String>,
   end_date: Option<String>,
-) -> Result<Vec<ActivityLogDto>> {
-  let params = LogQueryParams {
-    limit,
-    offset,
-    start_date,
-    end_date,
-    action,
-    entity_type,
-    severity: None,
-  };
-  AUDIT_SERVICE
-    .get_activity_logs(&db, DEFAULT_USER_ID, params)
-    .await
+  details: Option<JsonFilter>,
+) -> CommandResult<Vec<ActivityLogDto>> {
+  instrumented!(
+    "get_activity_logs",
+    (
+      &limit,
+      &offset,
+      &action,
+      &entity_type,
+      &start_date,
+      &end_date,
+      &details
+    ),
+    async {
+      let params = LogQueryParams {
+        limit,
+        offset,
+        start_date,
+        end_date,
+        action,
+        entity_type,
+        severity: None,
+        details,
+      };
+      AUDIT_SERVICE
+        .get_activity_logs(&db, DEFAULT_USER_ID, params)
+        .await
+    }
+  )
 }
 
 // ============================================================================
@@ -114,23 +169,36 @@ pub async fn get_activity_logs(
 pub async fn log_system_event(
   db: State<'_, Database>,
   event_type: String,
-  severity: Option<String>,
+  severity: Option<Severity>,
   source: String,
   message: String,
   details: Option<serde_json::Value>,
   stack_trace: Option<String>,
-) -> Result<SystemEventDto> {
-  AUDIT_SERVICE
-    .log_system_event(
-      &db,
+) -> CommandResult<SystemEventDto> {
+  instrumented!(
+    "log_system_event",
+    (
       &event_type,
-      &severity.unwrap_or_else(|| "info".to_string()),
+      &severity,
       &source,
       &message,
-      details,
-      stack_trace.as_deref(),
-    )
-    .await
+      &details,
+      &stack_trace
+    ),
+    async {
+      AUDIT_SERVICE
+        .log_system_event(
+          &db,
+          &event_type,
+          severity.unwrap_or(Severity::Info),
+          &source,
+          &message,
+          details,
+          stack_trace.as_deref(),
+        )
+        .await
+    }
+  )
 }
 
 /// Get system events
@@ -139,19 +207,26 @@ pub async fn get_system_events(
   db: State<'_, Database>,
   limit: Option<i64>,
   offset: Option<i64>,
-  severity: Option<String>,
+  severity: Option<Severity>,
   event_type: Option<String>,
-) -> Result<Vec<SystemEventDto>> {
-  let params = LogQueryParams {
-    limit,
-    offset,
-    start_date: None,
-    end_date: None,
-    action: event_type, // Used as event_type filter
-    entity_type: None,
-    severity,
-  };
-  AUDIT_SERVICE.get_system_events(&db, params).await
+) -> CommandResult<Vec<SystemEventDto>> {
+  instrumented!(
+    "get_system_events",
+    (&limit, &offset, &severity, &event_type),
+    async {
+      let params = LogQueryParams {
+        limit,
+        offset,
+        start_date: None,
+        end_date: None,
+        action: event_type, // Used as event_type filter
+        entity_type: None,
+        severity,
+        details: None,
+      };
+      AUDIT_SERVICE.get_system_events(&db, params).await
+    }
+  )
 }
 
 // ============================================================================
@@ -164,7 +239,7 @@ pub async fn get_system_events(
 pub async fn record_profile_activation(
   db: State<'_, Database>,
   profile_id: String,
-  activation_source: String,
+  activation_source: ActivationSource,
   previous_profile_id: Option<String>,
   monitors_detected: Option<i32>,
   monitors_applied: Option<i32>,
@@ -178,28 +253,50 @@ pub async fn record_profile_activation(
   success: bool,
   error_message: Option<String>,
   metadata: Option<serde_json::Value>,
-) -> Result<ProfileActivationDto> {
-  AUDIT_SERVICE
-    .record_profile_activation(
-      &db,
-      DEFAULT_USER_ID,
+) -> CommandResult<ProfileActivationDto> {
+  instrumented!(
+    "record_profile_activation",
+    (
       &profile_id,
       &activation_source,
-      previous_profile_id.as_deref(),
-      monitors_detected,
-      monitors_applied,
-      apps_detected,
-      apps_launched,
-      apps_failed,
-      tabs_detected,
-      tabs_opened,
-      windows_restored,
-      duration_ms,
-      success,
-      error_message.as_deref(),
-      metadata,
-    )
-    .await
+      &previous_profile_id,
+      &monitors_detected,
+      &monitors_applied,
+      &apps_detected,
+      &apps_launched,
+      &apps_failed,
+      &tabs_detected,
+      &tabs_opened,
+      &windows_restored,
+      &duration_ms,
+      &success,
+      &error_message,
+      &metadata
+    ),
+    async {
+      AUDIT_SERVICE
+        .record_profile_activation(
+          &db,
+          DEFAULT_USER_ID,
+          &profile_id,
+          activation_source,
+          previous_profile_id.as_deref(),
+          monitors_detected,
+          monitors_applied,
+          apps_detected,
+          apps_launched,
+          apps_failed,
+          tabs_detected,
+          tabs_opened,
+          windows_restored,
+          duration_ms,
+          success,
+          error_message.as_deref(),
+          metadata,
+        )
+        .await
+    }
+  )
 }
 
 /// Get profile activations
@@ -209,19 +306,26 @@ pub async fn get_profile_activations(
   limit: Option<i64>,
   offset: Option<i64>,
   profile_id: Option<String>,
-) -> Result<Vec<ProfileActivationDto>> {
-  let params = LogQueryParams {
-    limit,
-    offset,
-    start_date: None,
-    end_date: None,
-    action: None,
-    entity_type: None,
-    severity: None,
-  };
-  AUDIT_SERVICE
-    .get_profile_activations(&db, DEFAULT_USER_ID, params, profile_id.as_deref())
-    .await
+) -> CommandResult<Vec<ProfileActivationDto>> {
+  instrumented!(
+    "get_profile_activations",
+    (&limit, &offset, &profile_id),
+    async {
+      let params = LogQueryParams {
+        limit,
+        offset,
+        start_date: None,
+        end_date: None,
+        action: None,
+        entity_type: None,
+        severity: None,
+        details: None,
+      };
+      AUDIT_SERVICE
+        .get_profile_activations(&db, DEFAULT_USER_ID, params, profile_id.as_deref())
+        .await
+    }
+  )
 }
 
 // ============================================================================
@@ -240,23 +344,54 @@ pub async fn log_error(
   source_file: Option<String>,
   source_line: Option<i32>,
   source_function: Option<String>,
-  severity: Option<String>,
-) -> Result<ErrorLogDto> {
-  AUDIT_SERVICE
-    .log_error(
-      &db,
-      Some(DEFAULT_USER_ID),
-      error_code.as_deref(),
+  severity: Option<Severity>,
+) -> CommandResult<ErrorLogDto> {
+  instrumented!(
+    "log_error",
+    (
+      &error_code,
       &error_type,
       &message,
-      stack_trace.as_deref(),
-      context,
-      source_file.as_deref(),
-      source_line,
-      source_function.as_deref(),
-      &severity.unwrap_or_else(|| "error".to_string()),
-    )
-    .await
+      &stack_trace,
+      &context,
+      &source_file,
+      &source_line,
+      &source_function,
+      &severity
+    ),
+    async {
+      AUDIT_SERVICE
+        .log_error(
+          &db,
+          Some(DEFAULT_USER_ID),
+          error_code.as_deref(),
+          &error_type,
+          &message,
+          stack_trace.as_deref(),
+          context,
+          source_file.as_deref(),
+          source_line,
+          source_function.as_deref(),
+          severity.unwrap_or(Severity::Error),
+        )
+        .await
+    }
+  )
+}
+
+/// Errors grouped by fingerprint with first/last seen, affected versions and
+/// occurrences per day over the last 30 days, for triage
+#[tauri::command]
+pub async fn get_error_groups(
+  db: State<'_, Database>,
+  include_resolved: Option<bool>,
+  limit: Option<i64>,
+) -> CommandResult<Vec<ErrorGroupDto>> {
+  instrumented!("get_error_groups", (&include_resolved, &limit), async {
+    AUDIT_SERVICE
+      .get_error_groups(&db, include_resolved.unwrap_or(false), limit.unwrap_or(50))
+      .await
+  })
 }
 
 /// Get error logs
@@ -265,21 +400,28 @@ pub async fn get_error_logs(
   db: State<'_, Database>,
   limit: Option<i64>,
   offset: Option<i64>,
-  severity: Option<String>,
+  severity: Option<Severity>,
   include_resolved: Option<bool>,
-) -> Result<Vec<ErrorLogDto>> {
-  let params = LogQueryParams {
-    limit,
-    offset,
-    start_date: None,
-    end_date: None,
-    action: None,
-    entity_type: None,
-    severity,
-  };
-  AUDIT_SERVICE
-    .get_error_logs(&db, params, include_resolved.unwrap_or(false))
-    .await
+) -> CommandResult<Vec<ErrorLogDto>> {
+  instrumented!(
+    "get_error_logs",
+    (&limit, &offset, &severity, &include_resolved),
+    async {
+      let params = LogQueryParams {
+        limit,
+        offset,
+        start_date: None,
+        end_date: None,
+        action: None,
+        entity_type: None,
+        severity,
+        details: None,
+      };
+      AUDIT_SERVICE
+        .get_error_logs(&db, params, include_resolved.unwrap_or(false))
+        .await
+    }
+  )
 }
 
 /// Resolve an error
@@ -288,10 +430,12 @@ pub async fn resolve_error(
   db: State<'_, Database>,
   error_id: String,
   resolution_notes: Option<String>,
-) -> Result<ErrorLogDto> {
-  AUDIT_SERVICE
-    .resolve_error(&db, &error_id, resolution_notes.as_deref())
-    .await
+) -> CommandResult<ErrorLogDto> {
+  instrumented!("resolve_error", (&error_id, &resolution_notes), async {
+    AUDIT_SERVICE
+      .resolve_error(&db, &error_id, resolution_notes.as_deref())
+      .await
+  })
 }
 
 // ============================================================================
@@ -302,23 +446,35 @@ pub async fn resolve_error(
 #[tauri::command]
 pub async fn record_monitor_change(
   db: State<'_, Database>,
-  change_type: String,
+  change_type: MonitorChangeType,
   monitors_before: Option<serde_json::Value>,
   monitors_after: Option<serde_json::Value>,
   auto_profile_activated: Option<bool>,
   activated_profile_id: Option<String>,
-) -> Result<MonitorChangeDto> {
-  AUDIT_SERVICE
-    .record_monitor_change(
-      &db,
-      Some(DEFAULT_USER_ID),
+) -> CommandResult<MonitorChangeDto> {
+  instrumented!(
+    "record_monitor_change",
+    (
       &change_type,
-      monitors_before,
-      monitors_after,
-      auto_profile_activated.unwrap_or(false),
-      activated_profile_id.as_deref(),
-    )
-    .await
+      &monitors_before,
+      &monitors_after,
+      &auto_profile_activated,
+      &activated_profile_id
+    ),
+    async {
+      AUDIT_SERVICE
+        .record_monitor_change(
+          &db,
+          Some(DEFAULT_USER_ID),
+          change_type,
+          monitors_before,
+          monitors_after,
+          auto_profile_activated.unwrap_or(false),
+          activated_profile_id.as_deref(),
+        )
+        .await
+    }
+  )
 }
 
 // ============================================================================
@@ -340,25 +496,40 @@ pub async fn record_app_launch(
   error_message: Option<String>,
   pid: Option<i32>,
   launch_duration_ms: Option<i32>,
-  window_positioned: Option<bool>,
-) -> Result<AppLaunchDto> {
-  AUDIT_SERVICE
-    .record_app_launch(
-      &db,
-      DEFAULT_USER_ID,
-      profile_id.as_deref(),
-      activation_id.as_deref(),
-      app_id.as_deref(),
+) -> CommandResult<AppLaunchDto> {
+  instrumented!(
+    "record_app_launch",
+    (
+      &profile_id,
+      &activation_id,
+      &app_id,
       &bundle_id,
       &app_name,
-      exe_path.as_deref(),
-      success,
-      error_message.as_deref(),
-      pid,
-      launch_duration_ms,
-      window_positioned.unwrap_or(false),
-    )
-    .await
+      &exe_path,
+      &success,
+      &error_message,
+      &pid,
+      &launch_duration_ms
+    ),
+    async {
+      AUDIT_SERVICE
+        .record_app_launch(
+          &db,
+          DEFAULT_USER_ID,
+          profile_id.as_deref(),
+          activation_id.as_deref(),
+          app_id.as_deref(),
+          &bundle_id,
+          &app_name,
+          exe_path.as_deref(),
+          success,
+          error_message.as_deref(),
+          pid,
+          launch_duration_ms,
+        )
+        .await
+    }
+  )
 }
 
 // ============================================================================
@@ -377,21 +548,36 @@ pub async fn record_automation_execution(
   error_message: Option<String>,
   actions_taken: Option<serde_json::Value>,
   duration_ms: Option<i32>,
-) -> Result<AutomationExecutionDto> {
-  AUDIT_SERVICE
-    .record_automation_execution(
-      &db,
-      DEFAULT_USER_ID,
+) -> CommandResult<AutomationExecutionDto> {
+  instrumented!(
+    "record_automation_execution",
+    (
       &rule_id,
-      profile_id.as_deref(),
+      &profile_id,
       &trigger_type,
-      trigger_details,
-      success,
-      error_message.as_deref(),
-      actions_taken,
-      duration_ms,
-    )
-    .await
+      &trigger_details,
+      &success,
+      &error_message,
+      &actions_taken,
+      &duration_ms
+    ),
+    async {
+      AUDIT_SERVICE
+        .record_automation_execution(
+          &db,
+          DEFAULT_USER_ID,
+          &rule_id,
+          profile_id.as_deref(),
+          &trigger_type,
+          trigger_details,
+          success,
+          error_message.as_deref(),
+          actions_taken,
+          duration_ms,
+        )
+        .await
+    }
+  )
 }
 
 // ============================================================================
@@ -400,34 +586,129 @@ pub async fn record_automation_execution(
 
 /// Get dashboard statistics
 #[tauri::command]
-pub async fn get_dashboard_stats(db: State<'_, Database>) -> Result<DashboardStatsDto> {
-  AUDIT_SERVICE
-    .get_dashboard_stats(&db, DEFAULT_USER_ID)
-    .await
+pub async fn get_dashboard_stats(db: State<'_, Database>) -> CommandResult<DashboardStatsDto> {
+  instrumented!("get_dashboard_stats", async {
+    AUDIT_SERVICE
+      .get_dashboard_stats(&db, DEFAULT_USER_ID)
+      .await
+  })
 }
 
 /// Get log summary for analytics
 #[tauri::command]
-pub async fn get_log_summary(db: State<'_, Database>) -> Result<LogSummaryDto> {
-  AUDIT_SERVICE.get_log_summary(&db, DEFAULT_USER_ID).await
+pub async fn get_log_summary(db: State<'_, Database>) -> CommandResult<LogSummaryDto> {
+  instrumented!("get_log_summary", async {
+    AUDIT_SERVICE.get_log_summary(&db, DEFAULT_USER_ID).await
+  })
 }
 
-/// Get application metrics
+/// Get rolled-up daily statistics for the last `days` days (30 by default)
 #[tauri::command]
-pub async fn get_app_metrics() -> Result<serde_json::Value> {
-  Ok(crate::logging::METRICS.get_summary())
+pub async fn get_daily_stats(
+  db: State<'_, Database>,
+  days: Option<i64>,
+  profile_id: Option<String>,
+) -> CommandResult<Vec<DailyStatsDto>> {
+  instrumented!("get_daily_stats", (&days, &profile_id), async {
+    AUDIT_SERVICE
+      .get_daily_stats(
+        &db,
+        DEFAULT_USER_ID,
+        days.unwrap_or(30),
+        profile_id.as_deref(),
+      )
+      .await
+  })
+}
+
+/// How long each app was in front while a profile was active, over the last
+/// `days` days (7 by default)
+#[tauri::command]
+pub async fn get_profile_focus_time(
+  db: State<'_, Database>,
+  profile_id: String,
+  days: Option<i64>,
+) -> CommandResult<ProfileFocusTimeDto> {
+  instrumented!("get_profile_focus_time", (&profile_id, &days), async {
+    AUDIT_SERVICE
+      .get_focus_time(&db, DEFAULT_USER_ID, &profile_id, days.unwrap_or(7))
+      .await
+  })
+}
+
+/// Render a usage report for a range of days (the last 7 by default) and save
+/// it as Markdown or HTML
+#[tauri::command]
+pub async fn generate_usage_report(
+  db: State<'_, Database>,
+  range: Option<UsageReportRequest>,
+) -> CommandResult<UsageReportDto> {
+  instrumented!("generate_usage_report", (&range,), async {
+    let user_uuid = UserId::DEFAULT.as_uuid();
+    UsageReportService::generate_usage_report(&db, user_uuid, range.unwrap_or_default()).await
+  })
+}
+
+/// Save the recent logs with URLs, emails, file paths and app names redacted
+/// (each can be kept through `options`), for attaching to public bug reports
+#[tauri::command]
+pub async fn export_sanitized_logs(
+  state: State<'_, Arc<AppState>>,
+  options: Option<SanitizeOptions>,
+) -> CommandResult<SanitizedLogExportDto> {
+  instrumented!("export_sanitized_logs", (&options,), async {
+    let user_id = UserId::DEFAULT;
+    LogExportService::export_sanitized(&state, user_id, options.unwrap_or_default()).await
+  })
+}
+
+/// Get application metrics, including connection pool utilization
+#[tauri::command]
+pub async fn get_app_metrics(db: State<'_, Database>) -> CommandResult<serde_json::Value> {
+  instrumented!("get_app_metrics", async {
+    let mut summary = crate::logging::METRICS.get_summary();
+    summary["db_pool"] = serde_json::to_value(db.pool_stats())?;
+    Ok(summary)
+  })
+}
+
+/// Get per-command IPC timings, slowest first
+#[tauri::command]
+pub async fn get_command_stats(
+  limit: Option<usize>,
+) -> CommandResult<Vec<crate::logging::CommandStatsSnapshot>> {
+  instrumented!("get_command_stats", (&limit,), async {
+    Ok(crate::logging::METRICS.get_command_stats(limit.unwrap_or(20)))
+  })
+}
+
+/// Change the tracing level at runtime, globally or for a single target
+#[tauri::command]
+pub async fn set_log_level(
+  level: String,
+  target: Option<String>,
+) -> CommandResult<crate::logging::LogLevels> {
+  instrumented!("set_log_level", (&level, &target), async {
+    crate::logging::SmoothieLogger::set_log_level(&level, target.as_deref())
+  })
 }
 
 // ============================================================================
 // Maintenance
 // ============================================================================
 
-/// Cleanup old logs (retention policy)
+/// Cleanup old logs (retention policy), optionally refreshing table statistics
 #[tauri::command]
-pub async fn cleanup_old_logs(db: State<'_, Database>, days: Option<i64>) -> Result<()> {
-  AUDIT_SERVICE
-    .cleanup_old_logs(&db, days.unwrap_or(30))
-    .await
+pub async fn cleanup_old_logs(
+  db: State<'_, Database>,
+  days: Option<i64>,
+  analyze: Option<bool>,
+) -> CommandResult<LogCleanupDto> {
+  instrumented!("cleanup_old_logs", (&days, &analyze), async {
+    AUDIT_SERVICE
+      .cleanup_old_logs(&db, days.unwrap_or(30), analyze.unwrap_or(false))
+      .await
+  })
 }
 
 /// Get monitor change history
@@ -436,13 +717,15 @@ pub async fn get_monitor_changes(
   db: State<'_, Database>,
   limit: Option<i64>,
   offset: Option<i64>,
-) -> Result<Vec<MonitorChangeDto>> {
-  use crate::repositories::AuditRepository;
-  let repo = AuditRepository::new(db.pool());
-  let changes = repo
-    .get_monitor_changes(limit.unwrap_or(50), offset.unwrap_or(0))
-    .await?;
-  Ok(changes.into_iter().map(MonitorChangeDto::from).collect())
+) -> CommandResult<Vec<MonitorChangeDto>> {
+  instrumented!("get_monitor_changes", (&limit, &offset), async {
+    use crate::repositories::AuditRepository;
+    let repo = AuditRepository::new(db.pool());
+    let changes = repo
+      .get_monitor_changes(limit.unwrap_or(50), offset.unwrap_or(0))
+      .await?;
+    Ok(changes.into_iter().map(MonitorChangeDto::from).collect())
+  })
 }
 
 /// Get app launch history
@@ -452,26 +735,28 @@ pub async fn get_app_launches(
   profile_id: Option<String>,
   limit: Option<i64>,
   offset: Option<i64>,
-) -> Result<Vec<AppLaunchDto>> {
-  use crate::repositories::AuditRepository;
-  use uuid::Uuid;
-
-  let user_uuid = Uuid::parse_str(DEFAULT_USER_ID).unwrap();
-  let profile_uuid = profile_id
-    .map(|id| Uuid::parse_str(&id))
-    .transpose()
-    .map_err(|_| crate::error::SmoothieError::ValidationError("Invalid profile ID".into()))?;
-
-  let repo = AuditRepository::new(db.pool());
-  let launches = repo
-    .get_app_launches(
-      user_uuid,
-      limit.unwrap_or(50),
-      offset.unwrap_or(0),
-      profile_uuid,
-    )
-    .await?;
-  Ok(launches.into_iter().map(AppLaunchDto::from).collect())
+) -> CommandResult<Vec<AppLaunchDto>> {
+  instrumented!("get_app_launches", (&profile_id, &limit, &offset), async {
+    use crate::repositories::AuditRepository;
+    use uuid::Uuid;
+
+    let user_uuid = Uuid::parse_str(DEFAULT_USER_ID).unwrap();
+    let profile_uuid = profile_id
+      .map(|id| Uuid::parse_str(&id))
+      .transpose()
+      .map_err(|_| crate::error::SmoothieError::ValidationError("Invalid profile ID".into()))?;
+
+    let repo = AuditRepository::new(db.pool());
+    let launches = repo
+      .get_app_launches(
+        user_uuid,
+        limit.unwrap_or(50),
+        offset.unwrap_or(0),
+        profile_uuid,
+      )
+      .await?;
+    Ok(launches.into_iter().map(AppLaunchDto::from).collect())
+  })
 }
 
 /// Get automation execution history
@@ -481,29 +766,35 @@ pub async fn get_automation_executions(
   rule_id: Option<String>,
   limit: Option<i64>,
   offset: Option<i64>,
-) -> Result<Vec<AutomationExecutionDto>> {
-  use crate::repositories::AuditRepository;
-  use uuid::Uuid;
-
-  let user_uuid = Uuid::parse_str(DEFAULT_USER_ID).unwrap();
-  let rule_uuid = rule_id
-    .map(|id| Uuid::parse_str(&id))
-    .transpose()
-    .map_err(|_| crate::error::SmoothieError::ValidationError("Invalid rule ID".into()))?;
-
-  let repo = AuditRepository::new(db.pool());
-  let executions = repo
-    .get_automation_executions(
-      user_uuid,
-      limit.unwrap_or(50),
-      offset.unwrap_or(0),
-      rule_uuid,
-    )
-    .await?;
-  Ok(
-    executions
-      .into_iter()
-      .map(AutomationExecutionDto::from)
-      .collect(),
+) -> CommandResult<Vec<AutomationExecutionDto>> {
+  instrumented!(
+    "get_automation_executions",
+    (&rule_id, &limit, &offset),
+    async {
+      use crate::repositories::AuditRepository;
+      use uuid::Uuid;
+
+      let user_uuid = Uuid::parse_str(DEFAULT_USER_ID).unwrap();
+      let rule_uuid = rule_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|_| crate::error::SmoothieError::ValidationError("Invalid rule ID".into()))?;
+
+      let repo = AuditRepository::new(db.pool());
+      let executions = repo
+        .get_automation_executions(
+          user_uuid,
+          limit.unwrap_or(50),
+          offset.unwrap_or(0),
+          rule_uuid,
+        )
+        .await?;
+      Ok(
+        executions
+          .into_iter()
+          .map(AutomationExecutionDto::from)
+          .collect(),
+      )
+    }
   )
 }
@@ -1,39 +1,52 @@
-use crate::{error::Result, models::SuccessResponse, services::AutomationService, state::AppState};
+use crate::logging::instrumented;
+use crate::services::automation_service::{ActiveHoursWindow, RuleTestResult, SimulationResult};
+use crate::{
+  error::{CommandResult, SmoothieError},
+  models::{AutomationPauseDto, AutomationRuleDto, RuleActionDto, RuleActionRequest},
+  services::AutomationService,
+  state::AppState,
+};
 use std::sync::Arc;
 use tauri::State;
 
+const DEFAULT_USER_ID: &str = "00000000-0000-0000-0000-000000000001";
+
 #[tauri::command(rename_all = "camelCase")]
 pub async fn create_rule(
   state: State<'_, Arc<AppState>>,
   profile_id: String,
   rule_type: String,
   trigger_config: serde_json::Value,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let rule =
-    AutomationService::create_rule(&state.db, &profile_id, rule_type, trigger_config).await?;
+  conditions: Option<serde_json::Value>,
+) -> CommandResult<AutomationRuleDto> {
+  instrumented!(
+    "create_rule",
+    (&profile_id, &rule_type, &trigger_config, &conditions),
+    async {
+      let rule = AutomationService::create_rule(
+        &state.db,
+        &profile_id,
+        rule_type,
+        trigger_config,
+        conditions,
+      )
+      .await?;
 
-  state.invalidate_cache(&format!("rules_{}", profile_id));
+      state.invalidate_cache(&format!("rules_{}", profile_id));
 
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(rule)?,
-  })
+      Ok(rule)
+    }
+  )
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn get_rules(
   state: State<'_, Arc<AppState>>,
   profile_id: String,
-) -> Result<SuccessResponse<Vec<serde_json::Value>>> {
-  let rules = AutomationService::get_rules(&state.db, &profile_id).await?;
-  let data: Vec<serde_json::Value> = rules
-    .into_iter()
-    .map(|r| serde_json::to_value(r).unwrap())
-    .collect();
-
-  Ok(SuccessResponse {
-    success: true,
-    data,
+) -> CommandResult<Vec<AutomationRuleDto>> {
+  instrumented!("get_rules", (&profile_id,), async {
+    let rules = AutomationService::get_rules(&state.db, &profile_id).await?;
+    Ok(rules)
   })
 }
 
@@ -42,12 +55,124 @@ pub async fn update_rule(
   state: State<'_, Arc<AppState>>,
   rule_id: String,
   enabled: bool,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let rule = AutomationService::toggle_rule(&state.db, &rule_id, enabled).await?;
+) -> CommandResult<AutomationRuleDto> {
+  instrumented!("update_rule", (&rule_id, &enabled), async {
+    let rule = AutomationService::toggle_rule(&state.db, &rule_id, enabled).await?;
 
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(rule)?,
+    Ok(rule)
+  })
+}
+
+/// Replace a rule's conditions; pass null to remove them
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_rule_conditions(
+  state: State<'_, Arc<AppState>>,
+  rule_id: String,
+  conditions: Option<serde_json::Value>,
+) -> CommandResult<AutomationRuleDto> {
+  instrumented!("set_rule_conditions", (&rule_id, &conditions), async {
+    AutomationService::set_rule_conditions(&state.db, &rule_id, conditions).await
+  })
+}
+
+/// Set where a rule sits when several match the same trigger
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_rule_priority(
+  state: State<'_, Arc<AppState>>,
+  rule_id: String,
+  priority: i32,
+  stop_after_match: bool,
+  cooldown_minutes: i32,
+) -> CommandResult<AutomationRuleDto> {
+  instrumented!(
+    "set_rule_priority",
+    (&rule_id, &priority, &stop_after_match, &cooldown_minutes),
+    async {
+      AutomationService::set_rule_priority(
+        &state.db,
+        &rule_id,
+        priority,
+        stop_after_match,
+        cooldown_minutes,
+      )
+      .await
+    }
+  )
+}
+
+/// Keep a rule from acting for `hours` hours; 0 ends the snooze
+#[tauri::command(rename_all = "camelCase")]
+pub async fn snooze_rule(
+  state: State<'_, Arc<AppState>>,
+  rule_id: String,
+  hours: i64,
+) -> CommandResult<AutomationRuleDto> {
+  instrumented!("snooze_rule", (&rule_id, &hours), async {
+    AutomationService::snooze_rule(&state.db, &rule_id, hours).await
+  })
+}
+
+/// Limit a rule to daily windows; pass null to let it act at any time
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_rule_active_hours(
+  state: State<'_, Arc<AppState>>,
+  rule_id: String,
+  active_hours: Option<Vec<ActiveHoursWindow>>,
+) -> CommandResult<AutomationRuleDto> {
+  instrumented!("set_rule_active_hours", (&rule_id, &active_hours), async {
+    AutomationService::set_rule_active_hours(&state.db, &rule_id, active_hours).await
+  })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_rule_actions(
+  state: State<'_, Arc<AppState>>,
+  rule_id: String,
+) -> CommandResult<Vec<RuleActionDto>> {
+  instrumented!("get_rule_actions", (&rule_id,), async {
+    AutomationService::get_rule_actions(&state.db, &rule_id).await
+  })
+}
+
+/// Replace a rule's actions with `actions`, run in list order when the rule fires
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_rule_actions(
+  state: State<'_, Arc<AppState>>,
+  rule_id: String,
+  actions: Vec<RuleActionRequest>,
+) -> CommandResult<Vec<RuleActionDto>> {
+  instrumented!("set_rule_actions", (&rule_id, &actions), async {
+    AutomationService::set_rule_actions(&state.db, &rule_id, actions).await
+  })
+}
+
+/// Show which rules a synthetic trigger would fire and what they'd do, without running them
+#[tauri::command(rename_all = "camelCase")]
+pub async fn simulate_trigger(
+  state: State<'_, Arc<AppState>>,
+  trigger_type: String,
+  payload: Option<serde_json::Value>,
+) -> CommandResult<SimulationResult> {
+  instrumented!("simulate_trigger", (&trigger_type, &payload), async {
+    AutomationService::simulate_trigger(
+      &state.db,
+      state.system.as_ref(),
+      DEFAULT_USER_ID,
+      &trigger_type,
+      payload,
+    )
+    .await
+  })
+}
+
+/// Report which of a rule's conditions pass right now
+#[tauri::command(rename_all = "camelCase")]
+pub async fn test_rule(
+  state: State<'_, Arc<AppState>>,
+  rule_id: String,
+) -> CommandResult<RuleTestResult> {
+  instrumented!("test_rule", (&rule_id,), async {
+    AutomationService::test_rule(&state.db, state.system.as_ref(), &rule_id).await
   })
 }
 
@@ -55,25 +180,80 @@ pub async fn update_rule(
 pub async fn delete_rule(
   state: State<'_, Arc<AppState>>,
   rule_id: String,
-) -> Result<SuccessResponse<String>> {
-  AutomationService::delete_rule(&state.db, &rule_id).await?;
+) -> CommandResult<String> {
+  instrumented!("delete_rule", (&rule_id,), async {
+    AutomationService::delete_rule(&state.db, &rule_id).await?;
 
-  Ok(SuccessResponse {
-    success: true,
-    data: "Rule deleted successfully".to_string(),
+    Ok("Rule deleted successfully".to_string())
   })
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn evaluate_rules(
   state: State<'_, Arc<AppState>>,
-) -> Result<SuccessResponse<Vec<(String, String)>>> {
-  let triggered = AutomationService::evaluate_schedule_triggers(&state.db).await?;
+) -> CommandResult<Vec<(String, String)>> {
+  instrumented!("evaluate_rules", async {
+    if state.automation_pause.is_paused() {
+      tracing::info!("Automation paused; skipping rule evaluation");
+      return Ok(Vec::new());
+    }
+    let triggered = AutomationService::evaluate_schedule_triggers(
+      &state.db,
+      state.system.as_ref(),
+      DEFAULT_USER_ID,
+    )
+    .await?;
+
+    tracing::info!("Evaluated rules, triggered count: {}", triggered.len());
+    if !triggered.is_empty() {
+      state.invalidate_profile_caches();
+    }
+
+    Ok(triggered)
+  })
+}
+
+/// Longest a timed pause can last
+const MAX_PAUSE_MINUTES: i64 = 24 * 60;
+
+/// Presentation mode: stop rules firing and monitor changes switching profiles
+/// for `durationMinutes`, or until `resume_automation` when omitted
+#[tauri::command(rename_all = "camelCase")]
+pub async fn pause_automation(
+  state: State<'_, Arc<AppState>>,
+  duration_minutes: Option<i64>,
+) -> CommandResult<AutomationPauseDto> {
+  instrumented!("pause_automation", (&duration_minutes,), async {
+    if duration_minutes.is_some_and(|m| !(1..=MAX_PAUSE_MINUTES).contains(&m)) {
+      return Err(SmoothieError::ValidationError(format!(
+        "Pause must last between 1 and {} minutes",
+        MAX_PAUSE_MINUTES
+      )));
+    }
+    let status = state
+      .automation_pause
+      .pause(duration_minutes.map(chrono::Duration::minutes));
+    tracing::info!(until = ?status.until, "Automation paused");
+
+    Ok(status)
+  })
+}
 
-  tracing::info!("Evaluated rules, triggered count: {}", triggered.len());
+#[tauri::command(rename_all = "camelCase")]
+pub async fn resume_automation(
+  state: State<'_, Arc<AppState>>,
+) -> CommandResult<AutomationPauseDto> {
+  instrumented!("resume_automation", async {
+    tracing::info!("Automation resumed");
+    Ok(state.automation_pause.resume())
+  })
+}
 
-  Ok(SuccessResponse {
-    success: true,
-    data: triggered,
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_automation_pause(
+  state: State<'_, Arc<AppState>>,
+) -> CommandResult<AutomationPauseDto> {
+  instrumented!("get_automation_pause", async {
+    Ok(state.automation_pause.status())
   })
 }
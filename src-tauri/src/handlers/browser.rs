@@ -1,5 +1,13 @@
+use crate::logging::instrumented;
 use crate::services::browser_service::OpenTabResult;
-use crate::{error::Result, models::SuccessResponse, services::BrowserService, state::AppState};
+use crate::{
+  error::CommandResult,
+  models::{
+    BookmarkImportPreviewDto, BrowserTabDto, CreateBrowserTabRequest, ImportBookmarksRequest,
+  },
+  services::{BookmarkImportService, BrowserService},
+  state::AppState,
+};
 use std::sync::Arc;
 use tauri::State;
 
@@ -12,23 +20,76 @@ pub async fn create_browser_tab(
   monitor_id: Option<String>,
   tab_order: i32,
   favicon: Option<String>,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let tab = BrowserService::create_browser_tab(
-    &state.db,
-    &profile_id,
-    url,
-    browser,
-    monitor_id,
-    tab_order,
-    favicon,
+) -> CommandResult<BrowserTabDto> {
+  instrumented!(
+    "create_browser_tab",
+    (
+      &profile_id,
+      &url,
+      &browser,
+      &monitor_id,
+      &tab_order,
+      &favicon
+    ),
+    async {
+      let tab = BrowserService::create_browser_tab(
+        &state.db,
+        &profile_id,
+        CreateBrowserTabRequest {
+          url,
+          browser,
+          monitor_id,
+          tab_order,
+          favicon,
+        },
+      )
+      .await?;
+
+      state.invalidate_cache(&format!("browser_tabs_{}", profile_id));
+      state.invalidate_cache(&format!("profile_{}", profile_id));
+
+      Ok(tab)
+    }
   )
-  .await?;
+}
+
+/// Links in a Chrome/Firefox bookmarks or Firefox session file, with the ones
+/// the profile already has marked as duplicates
+#[tauri::command(rename_all = "camelCase")]
+pub async fn preview_bookmark_import(
+  state: State<'_, Arc<AppState>>,
+  profile_id: String,
+  path: String,
+  folder: Option<String>,
+) -> CommandResult<BookmarkImportPreviewDto> {
+  instrumented!(
+    "preview_bookmark_import",
+    (&profile_id, &path, &folder),
+    async {
+      BookmarkImportService::preview_bookmark_import(
+        &state.db,
+        &profile_id,
+        &path,
+        folder.as_deref(),
+      )
+      .await
+    }
+  )
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn import_bookmarks(
+  state: State<'_, Arc<AppState>>,
+  profile_id: String,
+  request: ImportBookmarksRequest,
+) -> CommandResult<Vec<BrowserTabDto>> {
+  instrumented!("import_bookmarks", (&profile_id, &request), async {
+    let tabs = BookmarkImportService::import_bookmarks(&state.db, &profile_id, request).await?;
 
-  state.invalidate_cache(&format!("browser_tabs_{}", profile_id));
+    state.invalidate_cache(&format!("browser_tabs_{}", profile_id));
+    state.invalidate_cache(&format!("profile_{}", profile_id));
 
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(tab)?,
+    Ok(tabs)
   })
 }
 
@@ -36,16 +97,26 @@ pub async fn create_browser_tab(
 pub async fn get_browser_tabs(
   state: State<'_, Arc<AppState>>,
   profile_id: String,
-) -> Result<SuccessResponse<Vec<serde_json::Value>>> {
-  let tabs = BrowserService::get_browser_tabs(&state.db, &profile_id).await?;
-  let data: Vec<serde_json::Value> = tabs
-    .into_iter()
-    .map(|t| serde_json::to_value(t).unwrap())
-    .collect();
-
-  Ok(SuccessResponse {
-    success: true,
-    data,
+) -> CommandResult<Vec<BrowserTabDto>> {
+  instrumented!("get_browser_tabs", (&profile_id,), async {
+    let tabs = BrowserService::get_browser_tabs(&state.db, &profile_id).await?;
+    Ok(tabs)
+  })
+}
+
+/// Order the profile's tabs as listed; unlisted ones follow in their current order
+#[tauri::command(rename_all = "camelCase")]
+pub async fn reorder_tabs(
+  state: State<'_, Arc<AppState>>,
+  profile_id: String,
+  ids_in_order: Vec<String>,
+) -> CommandResult<Vec<BrowserTabDto>> {
+  instrumented!("reorder_tabs", (&profile_id, &ids_in_order), async {
+    let tabs = BrowserService::reorder_browser_tabs(&state.db, &profile_id, &ids_in_order).await?;
+    state.invalidate_cache(&format!("browser_tabs_{}", profile_id));
+    state.invalidate_cache(&format!("profile_{}", profile_id));
+
+    Ok(tabs)
   })
 }
 
@@ -54,12 +125,12 @@ pub async fn update_browser_tab(
   state: State<'_, Arc<AppState>>,
   tab_id: String,
   url: Option<String>,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let tab = BrowserService::update_browser_tab(&state.db, &tab_id, url).await?;
+) -> CommandResult<BrowserTabDto> {
+  instrumented!("update_browser_tab", (&tab_id, &url), async {
+    let tab = BrowserService::update_browser_tab(&state.db, &tab_id, url).await?;
+    state.invalidate_cache(&format!("profile_{}", tab.profile_id));
 
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(tab)?,
+    Ok(tab)
   })
 }
 
@@ -67,12 +138,12 @@ pub async fn update_browser_tab(
 pub async fn delete_browser_tab(
   state: State<'_, Arc<AppState>>,
   tab_id: String,
-) -> Result<SuccessResponse<String>> {
-  BrowserService::delete_browser_tab(&state.db, &tab_id).await?;
+) -> CommandResult<String> {
+  instrumented!("delete_browser_tab", (&tab_id,), async {
+    BrowserService::delete_browser_tab(&state.db, &tab_id).await?;
+    state.invalidate_profile_caches();
 
-  Ok(SuccessResponse {
-    success: true,
-    data: "Browser tab deleted successfully".to_string(),
+    Ok("Browser tab deleted successfully".to_string())
   })
 }
 
@@ -80,17 +151,16 @@ pub async fn delete_browser_tab(
 pub async fn open_tabs(
   state: State<'_, Arc<AppState>>,
   profile_id: String,
-) -> Result<SuccessResponse<Vec<OpenTabResult>>> {
-  let results = BrowserService::open_profile_tabs(&state.db, &profile_id).await?;
-
-  tracing::info!(
-    "Opened {} browser tabs for profile {}",
-    results.len(),
-    profile_id
-  );
-
-  Ok(SuccessResponse {
-    success: true,
-    data: results,
+) -> CommandResult<Vec<OpenTabResult>> {
+  instrumented!("open_tabs", (&profile_id,), async {
+    let results = BrowserService::open_profile_tabs(&state.db, &profile_id).await?;
+
+    tracing::info!(
+      "Opened {} browser tabs for profile {}",
+      results.len(),
+      profile_id
+    );
+
+    Ok(results)
   })
 }
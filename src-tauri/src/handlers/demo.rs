@@ -0,0 +1,20 @@
+use crate::logging::instrumented;
+use crate::services::demo_service::DemoSeedResult;
+use crate::{error::CommandResult, models::UserId, services::DemoService, state::AppState};
+use std::sync::Arc;
+use tauri::State;
+
+/// Seed demo profiles, activations and logs (debug builds or SMOOTHIE_DEMO_MODE=1 only)
+#[tauri::command(rename_all = "camelCase")]
+pub async fn seed_demo_data(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+) -> CommandResult<DemoSeedResult> {
+  instrumented!("seed_demo_data", (&user_id,), async {
+    let result = DemoService::seed_demo_data(&state.db, user_id).await?;
+    state.invalidate_cache(&format!("profiles_{}", user_id));
+    state.invalidate_profile_caches();
+
+    Ok(result)
+  })
+}
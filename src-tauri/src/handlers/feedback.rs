@@ -1,10 +1,15 @@
 // Feedback and Feature Request handlers
 
+use crate::logging::instrumented;
 use crate::{
   db::Database,
-  error::Result,
-  models::{CreateFeedbackRequest, FeedbackDto, SuccessResponse},
+  error::{CommandResult, SmoothieError},
+  models::{CreateFeedbackRequest, FeedbackDto, FeedbackStatus, Severity},
+  state::AppState,
+  storage::BlobKind,
+  utils::validation,
 };
+use std::sync::Arc;
 use tauri::State;
 use uuid::Uuid;
 
@@ -14,22 +19,39 @@ const DEFAULT_USER_ID: &str = "00000000-0000-0000-0000-000000000001";
 #[tauri::command(rename_all = "camelCase")]
 pub async fn submit_feedback(
   db: State<'_, Database>,
+  state: State<'_, Arc<AppState>>,
   req: CreateFeedbackRequest,
-) -> Result<SuccessResponse<FeedbackDto>> {
-  let user_id = Uuid::parse_str(DEFAULT_USER_ID)
-    .map_err(|e| crate::error::SmoothieError::ValidationError(e.to_string()))?;
+) -> CommandResult<FeedbackDto> {
+  instrumented!("submit_feedback", (&req,), async {
+    validation::validate(&req)?;
+    for attachment in &req.attachments {
+      if !state
+        .storage
+        .contains(attachment, BlobKind::Attachment)
+        .await?
+      {
+        return Err(SmoothieError::NotFound(format!(
+          "Attachment {}",
+          attachment
+        )));
+      }
+    }
+    let metadata =
+      (!req.attachments.is_empty()).then(|| serde_json::json!({ "attachments": req.attachments }));
+    let user_id = Uuid::parse_str(DEFAULT_USER_ID)
+      .map_err(|e| crate::error::SmoothieError::ValidationError(e.to_string()))?;
 
-  // Get app version and OS info
-  let app_version = option_env!("CARGO_PKG_VERSION").map(|v| v.to_string());
-  let os_info = Some(serde_json::json!({
-    "os": std::env::consts::OS,
-    "arch": std::env::consts::ARCH,
-  }));
+    // Get app version and OS info
+    let app_version = option_env!("CARGO_PKG_VERSION").map(|v| v.to_string());
+    let os_info = Some(serde_json::json!({
+      "os": std::env::consts::OS,
+      "arch": std::env::consts::ARCH,
+    }));
 
-  let entity = sqlx::query_as::<_, crate::models::entities::FeedbackEntity>(
+    let entity = sqlx::query_as::<_, crate::models::entities::FeedbackEntity>(
     r#"
-    INSERT INTO feedback (user_id, feedback_type, title, description, priority, category, contact_email, app_version, os_info)
-    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+    INSERT INTO feedback (user_id, feedback_type, title, description, priority, category, contact_email, app_version, os_info, metadata)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
     RETURNING *
     "#,
   )
@@ -42,30 +64,29 @@ pub async fn submit_feedback(
   .bind(&req.contact_email)
   .bind(&app_version)
   .bind(&os_info)
+  .bind(&metadata)
   .fetch_one(db.pool())
   .await
-  .map_err(|e| crate::error::SmoothieError::DatabaseError(e.to_string()))?;
+  .map_err(crate::error::SmoothieError::from)?;
 
-  // Log this as a system event
-  let _ = crate::services::audit_service::AUDIT_SERVICE
-    .log_system_event(
-      &db,
-      "feedback_submitted",
-      "info",
-      "FeedbackHandler",
-      &format!("{}: {}", req.feedback_type, req.title),
-      Some(serde_json::json!({
-        "feedback_id": entity.id.to_string(),
-        "feedback_type": req.feedback_type,
-        "title": req.title,
-      })),
-      None,
-    )
-    .await;
+    // Log this as a system event
+    let _ = crate::services::audit_service::AUDIT_SERVICE
+      .log_system_event(
+        &db,
+        "feedback_submitted",
+        Severity::Info,
+        "FeedbackHandler",
+        &format!("{}: {}", req.feedback_type, req.title),
+        Some(serde_json::json!({
+          "feedback_id": entity.id.to_string(),
+          "feedback_type": req.feedback_type,
+          "title": req.title,
+        })),
+        None,
+      )
+      .await;
 
-  Ok(SuccessResponse {
-    success: true,
-    data: FeedbackDto::from(entity),
+    Ok(FeedbackDto::from(entity))
   })
 }
 
@@ -73,15 +94,16 @@ pub async fn submit_feedback(
 #[tauri::command(rename_all = "camelCase")]
 pub async fn get_feedback(
   db: State<'_, Database>,
-  status: Option<String>,
+  status: Option<FeedbackStatus>,
   feedback_type: Option<String>,
   limit: Option<i64>,
-) -> Result<SuccessResponse<Vec<FeedbackDto>>> {
-  let user_id = Uuid::parse_str(DEFAULT_USER_ID)
-    .map_err(|e| crate::error::SmoothieError::ValidationError(e.to_string()))?;
+) -> CommandResult<Vec<FeedbackDto>> {
+  instrumented!("get_feedback", (&status, &feedback_type, &limit), async {
+    let user_id = Uuid::parse_str(DEFAULT_USER_ID)
+      .map_err(|e| crate::error::SmoothieError::ValidationError(e.to_string()))?;
 
-  let entities = sqlx::query_as::<_, crate::models::entities::FeedbackEntity>(
-    r#"
+    let entities = sqlx::query_as::<_, crate::models::entities::FeedbackEntity>(
+      r#"
     SELECT * FROM feedback
     WHERE user_id = $1
       AND ($2::text IS NULL OR status = $2)
@@ -89,20 +111,18 @@ pub async fn get_feedback(
     ORDER BY created_at DESC
     LIMIT $4
     "#,
-  )
-  .bind(user_id)
-  .bind(&status)
-  .bind(&feedback_type)
-  .bind(limit.unwrap_or(50))
-  .fetch_all(db.pool())
-  .await
-  .map_err(|e| crate::error::SmoothieError::DatabaseError(e.to_string()))?;
+    )
+    .bind(user_id)
+    .bind(status)
+    .bind(&feedback_type)
+    .bind(limit.unwrap_or(50))
+    .fetch_all(db.pool())
+    .await
+    .map_err(crate::error::SmoothieError::from)?;
 
-  let data: Vec<FeedbackDto> = entities.into_iter().map(FeedbackDto::from).collect();
+    let data: Vec<FeedbackDto> = entities.into_iter().map(FeedbackDto::from).collect();
 
-  Ok(SuccessResponse {
-    success: true,
-    data,
+    Ok(data)
   })
 }
 
@@ -111,27 +131,26 @@ pub async fn get_feedback(
 pub async fn update_feedback_status(
   db: State<'_, Database>,
   feedback_id: String,
-  status: String,
-) -> Result<SuccessResponse<FeedbackDto>> {
-  let id = Uuid::parse_str(&feedback_id)
-    .map_err(|e| crate::error::SmoothieError::ValidationError(e.to_string()))?;
+  status: FeedbackStatus,
+) -> CommandResult<FeedbackDto> {
+  instrumented!("update_feedback_status", (&feedback_id, &status), async {
+    let id = Uuid::parse_str(&feedback_id)
+      .map_err(|e| crate::error::SmoothieError::ValidationError(e.to_string()))?;
 
-  let entity = sqlx::query_as::<_, crate::models::entities::FeedbackEntity>(
-    r#"
+    let entity = sqlx::query_as::<_, crate::models::entities::FeedbackEntity>(
+      r#"
     UPDATE feedback
     SET status = $2, updated_at = CURRENT_TIMESTAMP
     WHERE id = $1
     RETURNING *
     "#,
-  )
-  .bind(id)
-  .bind(&status)
-  .fetch_one(db.pool())
-  .await
-  .map_err(|e| crate::error::SmoothieError::DatabaseError(e.to_string()))?;
+    )
+    .bind(id)
+    .bind(status)
+    .fetch_one(db.pool())
+    .await
+    .map_err(crate::error::SmoothieError::from)?;
 
-  Ok(SuccessResponse {
-    success: true,
-    data: FeedbackDto::from(entity),
+    Ok(FeedbackDto::from(entity))
   })
 }
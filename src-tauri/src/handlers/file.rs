@@ -0,0 +1,101 @@
+use crate::logging::instrumented;
+use crate::services::file_service::OpenFileResult;
+use crate::{error::CommandResult, models::ProfileFileDto, services::FileService, state::AppState};
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn create_profile_file(
+  state: State<'_, Arc<AppState>>,
+  profile_id: String,
+  path: String,
+  target_app: Option<String>,
+  open_on_activate: Option<bool>,
+  open_order: i32,
+) -> CommandResult<ProfileFileDto> {
+  instrumented!(
+    "create_profile_file",
+    (
+      &profile_id,
+      &path,
+      &target_app,
+      &open_on_activate,
+      &open_order
+    ),
+    async {
+      let file = FileService::create_file(
+        &state.db,
+        &profile_id,
+        path,
+        target_app,
+        open_on_activate.unwrap_or(true),
+        open_order,
+      )
+      .await?;
+
+      state.invalidate_cache(&format!("profile_files_{}", profile_id));
+      state.invalidate_cache(&format!("profile_{}", profile_id));
+
+      Ok(file)
+    }
+  )
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_profile_files(
+  state: State<'_, Arc<AppState>>,
+  profile_id: String,
+) -> CommandResult<Vec<ProfileFileDto>> {
+  instrumented!("get_profile_files", (&profile_id,), async {
+    let files = FileService::get_files(&state.db, &profile_id).await?;
+    Ok(files)
+  })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn update_profile_file(
+  state: State<'_, Arc<AppState>>,
+  file_id: String,
+  path: Option<String>,
+  target_app: Option<String>,
+  open_on_activate: Option<bool>,
+) -> CommandResult<ProfileFileDto> {
+  instrumented!(
+    "update_profile_file",
+    (&file_id, &path, &target_app, &open_on_activate),
+    async {
+      let file =
+        FileService::update_file(&state.db, &file_id, path, target_app, open_on_activate).await?;
+      state.invalidate_cache(&format!("profile_{}", file.profile_id));
+
+      Ok(file)
+    }
+  )
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn delete_profile_file(
+  state: State<'_, Arc<AppState>>,
+  file_id: String,
+) -> CommandResult<String> {
+  instrumented!("delete_profile_file", (&file_id,), async {
+    FileService::delete_file(&state.db, &file_id).await?;
+    state.invalidate_profile_caches();
+
+    Ok("Profile file deleted successfully".to_string())
+  })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn open_files(
+  state: State<'_, Arc<AppState>>,
+  profile_id: String,
+) -> CommandResult<Vec<OpenFileResult>> {
+  instrumented!("open_files", (&profile_id,), async {
+    let results = FileService::open_profile_files(&state.db, &profile_id).await?;
+
+    tracing::info!("Opened {} files for profile {}", results.len(), profile_id);
+
+    Ok(results)
+  })
+}
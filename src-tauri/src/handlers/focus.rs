@@ -0,0 +1,63 @@
+use crate::logging::instrumented;
+use crate::{
+  error::CommandResult,
+  models::{FocusSessionDto, ProfileId, StartFocusSessionRequest, UserId},
+  services::FocusSessionService,
+  state::AppState,
+};
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn start_focus_session(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+  req: StartFocusSessionRequest,
+) -> CommandResult<FocusSessionDto> {
+  instrumented!("start_focus_session", (&user_id, &req), async {
+    let session = FocusSessionService::start(&state.db, user_id, req).await?;
+    Ok(session)
+  })
+}
+
+/// The running session, or null
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_active_focus_session(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+) -> CommandResult<Option<FocusSessionDto>> {
+  instrumented!("get_active_focus_session", (&user_id,), async {
+    let session = FocusSessionService::get_active(&state.db, user_id).await?;
+    Ok(session)
+  })
+}
+
+/// End the running session early
+#[tauri::command(rename_all = "camelCase")]
+pub async fn cancel_focus_session(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+) -> CommandResult<FocusSessionDto> {
+  instrumented!("cancel_focus_session", (&user_id,), async {
+    let session = FocusSessionService::cancel(&state.db, state.system.as_ref(), user_id).await?;
+    Ok(session)
+  })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_focus_sessions(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+  profile_id: Option<ProfileId>,
+  limit: Option<i64>,
+) -> CommandResult<Vec<FocusSessionDto>> {
+  instrumented!(
+    "get_focus_sessions",
+    (&user_id, &profile_id, &limit),
+    async {
+      let sessions =
+        FocusSessionService::get_sessions(&state.db, user_id, profile_id, limit).await?;
+      Ok(sessions)
+    }
+  )
+}
@@ -1,13 +1,19 @@
 // IPC Command handlers - communication between frontend and backend
 
+pub mod alert;
 pub mod app;
 pub mod audit;
 pub mod automation;
 pub mod browser;
+pub mod demo;
 pub mod feedback;
+pub mod file;
+pub mod focus;
 pub mod monitor;
 pub mod profile;
+pub mod storage;
 pub mod subscription;
 pub mod system;
+pub mod team;
 pub mod user;
 pub mod window;
@@ -1,4 +1,11 @@
-use crate::{error::Result, models::SuccessResponse, services::MonitorService, state::AppState};
+use crate::logging::instrumented;
+use crate::services::layout_service::LayoutPreview;
+use crate::{
+  error::CommandResult,
+  models::{CreateMonitorRequest, MonitorDto},
+  services::{LayoutService, MonitorService},
+  state::AppState,
+};
 use std::sync::Arc;
 use tauri::State;
 
@@ -16,44 +23,55 @@ pub async fn create_monitor(
   width: i32,
   height: i32,
   display_index: i32,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let monitor = MonitorService::create_monitor(
-    &state.db,
-    &profile_id,
-    name,
-    resolution,
-    orientation,
-    is_primary,
-    x,
-    y,
-    width,
-    height,
-    display_index,
-  )
-  .await?;
+) -> CommandResult<MonitorDto> {
+  instrumented!(
+    "create_monitor",
+    (
+      &profile_id,
+      &name,
+      &resolution,
+      &orientation,
+      &is_primary,
+      &x,
+      &y,
+      &width,
+      &height,
+      &display_index
+    ),
+    async {
+      let monitor = MonitorService::create_monitor(
+        &state.db,
+        &profile_id,
+        CreateMonitorRequest {
+          name,
+          resolution,
+          orientation,
+          is_primary,
+          x,
+          y,
+          width,
+          height,
+          display_index,
+        },
+      )
+      .await?;
 
-  state.invalidate_cache(&format!("monitors_{}", profile_id));
+      state.invalidate_cache(&format!("monitors_{}", profile_id));
+      state.invalidate_cache(&format!("profile_{}", profile_id));
 
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(monitor)?,
-  })
+      Ok(monitor)
+    }
+  )
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn get_monitors(
   state: State<'_, Arc<AppState>>,
   profile_id: String,
-) -> Result<SuccessResponse<Vec<serde_json::Value>>> {
-  let monitors = MonitorService::get_monitors(&state.db, &profile_id).await?;
-  let data: Vec<serde_json::Value> = monitors
-    .into_iter()
-    .map(|m| serde_json::to_value(m).unwrap())
-    .collect();
-
-  Ok(SuccessResponse {
-    success: true,
-    data,
+) -> CommandResult<Vec<MonitorDto>> {
+  instrumented!("get_monitors", (&profile_id,), async {
+    let monitors = MonitorService::get_monitors(&state.db, &profile_id).await?;
+    Ok(monitors)
   })
 }
 
@@ -65,13 +83,34 @@ pub async fn update_monitor(
   y: i32,
   width: i32,
   height: i32,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let monitor = MonitorService::update_monitor(&state.db, &monitor_id, x, y, width, height).await?;
-  state.invalidate_cache(&format!("monitor_{}", monitor_id));
+) -> CommandResult<MonitorDto> {
+  instrumented!(
+    "update_monitor",
+    (&monitor_id, &x, &y, &width, &height),
+    async {
+      let monitor =
+        MonitorService::update_monitor(&state.db, &monitor_id, x, y, width, height).await?;
+      state.invalidate_cache(&format!("monitor_{}", monitor_id));
+      state.invalidate_cache(&format!("profile_{}", monitor.profile_id));
+
+      Ok(monitor)
+    }
+  )
+}
+
+/// Choose whether a display is kept dark while its profile is active
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_monitor_blanked(
+  state: State<'_, Arc<AppState>>,
+  monitor_id: String,
+  blanked: bool,
+) -> CommandResult<MonitorDto> {
+  instrumented!("set_monitor_blanked", (&monitor_id, &blanked), async {
+    let monitor = MonitorService::set_monitor_blanked(&state.db, &monitor_id, blanked).await?;
+    state.invalidate_cache(&format!("monitor_{}", monitor_id));
+    state.invalidate_cache(&format!("profile_{}", monitor.profile_id));
 
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(monitor)?,
+    Ok(monitor)
   })
 }
 
@@ -79,11 +118,55 @@ pub async fn update_monitor(
 pub async fn delete_monitor(
   state: State<'_, Arc<AppState>>,
   monitor_id: String,
-) -> Result<SuccessResponse<String>> {
-  MonitorService::delete_monitor(&state.db, &monitor_id).await?;
+) -> CommandResult<String> {
+  instrumented!("delete_monitor", (&monitor_id,), async {
+    MonitorService::delete_monitor(&state.db, &monitor_id).await?;
+    state.invalidate_profile_caches();
+
+    Ok("Monitor deleted successfully".to_string())
+  })
+}
+
+/// Save the connected displays to a profile, updating ones it already has in place
+#[tauri::command(rename_all = "camelCase")]
+pub async fn capture_monitor_layout(
+  state: State<'_, Arc<AppState>>,
+  profile_id: String,
+) -> CommandResult<Vec<MonitorDto>> {
+  instrumented!("capture_monitor_layout", (&profile_id,), async {
+    let monitors =
+      MonitorService::capture_layout(&state.db, state.system.clone(), &profile_id).await?;
+    state.invalidate_cache(&format!("monitors_{}", profile_id));
+    state.invalidate_cache(&format!("profile_{}", profile_id));
+
+    Ok(monitors)
+  })
+}
+
+/// Remove captured monitors that the profile's latest capture didn't include
+#[tauri::command(rename_all = "camelCase")]
+pub async fn prune_stale_monitors(
+  state: State<'_, Arc<AppState>>,
+  profile_id: String,
+) -> CommandResult<Vec<MonitorDto>> {
+  instrumented!("prune_stale_monitors", (&profile_id,), async {
+    let removed = MonitorService::prune_stale_monitors(&state.db, &profile_id).await?;
+    state.invalidate_cache(&format!("monitors_{}", profile_id));
+    state.invalidate_cache(&format!("profile_{}", profile_id));
+
+    Ok(removed)
+  })
+}
+
+/// Get normalized monitor/window rects for drawing a mini-map of the profile layout
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_layout_preview(
+  state: State<'_, Arc<AppState>>,
+  profile_id: String,
+) -> CommandResult<LayoutPreview> {
+  instrumented!("get_layout_preview", (&profile_id,), async {
+    let preview = LayoutService::get_layout_preview(&state.db, &profile_id).await?;
 
-  Ok(SuccessResponse {
-    success: true,
-    data: "Monitor deleted successfully".to_string(),
+    Ok(preview)
   })
 }
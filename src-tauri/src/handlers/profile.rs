@@ -1,339 +1,831 @@
+use crate::logging::instrumented;
+use crate::services::activation::{
+  ActivationPipeline, ActivationStepKind, PipelineRun, StepReport,
+};
+use crate::services::activation_journal::{
+  ActivationJournal, ActivationJournalService, InterruptedActivationDto,
+};
+use crate::services::activation_report::{ActivationReportDto, ActivationReportService};
 use crate::services::app_service::LaunchResult;
 use crate::services::browser_service::OpenTabResult;
+use crate::services::change_log::ChangeLog;
+use crate::services::declarative_service::DeclarativeSyncReportDto;
+use crate::services::edit_history::{EditHistory, EditStateDto, RevertedEditDto};
+use crate::services::environment_service::PowerState;
+use crate::services::file_service::OpenFileResult;
+use crate::services::monitor_service::MonitorLayoutResult;
+use crate::services::offline_cache;
+use crate::services::profile_resolver::{self, ProfileMatchDto, ProfileResolver};
+use crate::services::share_service::{ProfileShareDto, ShareService};
 use crate::{
-  error::Result,
-  models::{CreateProfileRequest, SuccessResponse},
-  services::{AppService, BrowserService, MonitorService, ProfileService, SystemService},
+  error::{CommandResult, SmoothieError},
+  models::{
+    ActivationChecklistDto, ActivationSource, ActivityLogDto, BlockedAppDto, BlockedAppOverrideDto,
+    BlockedAppRequest, ChecklistItemDto, ChecklistItemRequest, CreateProfileRequest,
+    DeactivationActionDto, DeactivationActionRequest, ProfileDto, ProfileId, ProfileResponse,
+    RestoreSnapshotDto, Severity, SnapshotDto, UpdateProfileRequest, UserId,
+  },
+  services::{
+    AppBlocklistService, ChecklistService, DeactivationService, DeclarativeService, OfflineCache,
+    ProfileService, ScriptExportService, SnapshotService,
+  },
   state::AppState,
 };
 use std::sync::Arc;
 use tauri::State;
 
-/// Result of applying monitor layout
-#[derive(Debug, Clone, serde::Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct MonitorLayoutResult {
-  pub applied: bool,
-  pub monitor_count: usize,
-  pub message: String,
-}
-
 /// Result of starting a profile
 #[derive(Debug, Clone, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StartProfileResult {
-  pub profile_id: String,
+  pub profile_id: ProfileId,
   pub apps_launched: Vec<LaunchResult>,
   pub tabs_opened: Vec<OpenTabResult>,
+  pub files_opened: Vec<OpenFileResult>,
   pub monitor_layout: MonitorLayoutResult,
+  /// Set when app launches and browser tabs were skipped to save battery
+  pub low_power: Option<PowerState>,
+  /// What each activation step did, in the order they ran
+  pub steps: Vec<StepReport>,
+  /// Started from the offline cache while the database was unreachable
+  pub offline: bool,
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn create_profile(
   state: State<'_, Arc<AppState>>,
-  user_id: String,
+  user_id: UserId,
   req: CreateProfileRequest,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let profile_name = req.name.clone();
-  let profile = ProfileService::create_profile(&state.db, &user_id, req).await?;
-  state.invalidate_cache(&format!("profiles_{}", user_id));
-
-  // Log the creation as a system event
-  let _ = crate::services::audit_service::AUDIT_SERVICE
-    .log_system_event(
-      &state.db,
-      "profile_created",
-      "info",
-      "ProfileHandler",
-      &format!("Profile '{}' was created", profile_name),
-      Some(serde_json::json!({
-        "profile_id": profile.id,
-        "profile_name": profile_name
-      })),
-      None,
-    )
-    .await;
-
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(profile)?,
+) -> CommandResult<ProfileDto> {
+  instrumented!("create_profile", (&user_id, &req), async {
+    let profile_name = req.name.clone();
+    let profile = ProfileService::create_profile(&state.db, user_id, req).await?;
+    state.invalidate_cache(&format!("profiles_{}", user_id));
+
+    // Log the creation as a system event
+    let _ = crate::services::audit_service::AUDIT_SERVICE
+      .log_system_event(
+        &state.db,
+        "profile_created",
+        Severity::Info,
+        "ProfileHandler",
+        &format!("Profile '{}' was created", profile_name),
+        Some(serde_json::json!({
+          "profile_id": profile.id,
+          "profile_name": profile_name
+        })),
+        None,
+      )
+      .await;
+
+    Ok(profile)
   })
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn get_profiles(
   state: State<'_, Arc<AppState>>,
-  user_id: String,
-) -> Result<SuccessResponse<Vec<serde_json::Value>>> {
-  tracing::info!("get_profiles called with user_id: {}", user_id);
-  let profiles = ProfileService::get_profiles(&state.db, &user_id).await?;
-  tracing::info!("get_profiles found {} profiles", profiles.len());
-  let data: Vec<serde_json::Value> = profiles
-    .into_iter()
-    .map(|p| serde_json::to_value(p).unwrap())
-    .collect();
+  user_id: UserId,
+) -> CommandResult<Vec<ProfileDto>> {
+  instrumented!("get_profiles", (&user_id,), async {
+    tracing::info!("get_profiles called with user_id: {}", user_id);
+    let result = state
+      .db
+      .with_timeout(
+        "get_profiles",
+        ProfileService::get_profiles(&state.db, user_id),
+      )
+      .await;
+    if let Ok(profiles) = &result {
+      OfflineCache::save_profiles(user_id, profiles);
+    }
+    let profiles = OfflineCache::or_cached(result, "profiles", || OfflineCache::profiles(user_id))?;
+    tracing::info!("get_profiles found {} profiles", profiles.len());
+    Ok(profiles)
+  })
+}
 
-  Ok(SuccessResponse {
-    success: true,
-    data,
+/// Order the user's profiles as listed; unlisted ones follow in their current order
+#[tauri::command(rename_all = "camelCase")]
+pub async fn reorder_profiles(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+  ids_in_order: Vec<ProfileId>,
+) -> CommandResult<Vec<ProfileDto>> {
+  instrumented!("reorder_profiles", (&user_id, &ids_in_order), async {
+    let profiles = ProfileService::reorder_profiles(&state.db, user_id, &ids_in_order).await?;
+    state.invalidate_cache(&format!("profiles_{}", user_id));
+
+    Ok(profiles)
   })
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn get_profile(
   state: State<'_, Arc<AppState>>,
-  profile_id: String,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let profile = ProfileService::get_profile_response(&state.db, &profile_id).await?;
+  profile_id: ProfileId,
+) -> CommandResult<ProfileResponse> {
+  instrumented!("get_profile", (&profile_id,), async {
+    let key = format!("profile_{}", profile_id);
+    if let Some(profile) = state.cached::<ProfileResponse>(&key) {
+      return Ok(profile);
+    }
+    let result = state
+      .db
+      .with_timeout(
+        "get_profile",
+        ProfileService::get_profile_response(&state.db, profile_id),
+      )
+      .await;
+    match &result {
+      Ok(profile) => {
+        state.set_cache(&key, profile);
+        OfflineCache::save_profile(profile);
+      }
+      Err(SmoothieError::NotFound(_)) => OfflineCache::forget_profile(profile_id),
+      Err(_) => {}
+    }
 
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(profile)?,
+    OfflineCache::or_cached(result, "profile", || OfflineCache::profile(profile_id))
   })
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn update_profile(
   state: State<'_, Arc<AppState>>,
-  profile_id: String,
+  profile_id: ProfileId,
   name: Option<String>,
   description: Option<String>,
   is_favorite: Option<bool>,
   color: Option<String>,
   icon: Option<String>,
   sort_order: Option<i32>,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let profile = ProfileService::update_profile_extended(
-    &state.db,
-    &profile_id,
-    name,
-    description,
-    is_favorite,
-    color,
-    icon,
-    sort_order,
-  )
-  .await?;
-  state.invalidate_cache(&format!("profile_{}", profile_id));
+  tags: Option<Vec<String>>,
+  notes: Option<String>,
+) -> CommandResult<ProfileDto> {
+  instrumented!(
+    "update_profile",
+    (
+      &profile_id,
+      &name,
+      &description,
+      &is_favorite,
+      &color,
+      &icon,
+      &sort_order,
+      &tags,
+      &notes
+    ),
+    async {
+      let profile = ProfileService::update_profile_extended(
+        &state.db,
+        profile_id,
+        UpdateProfileRequest {
+          name,
+          description,
+          is_favorite,
+          color,
+          icon,
+          sort_order,
+          tags,
+          notes,
+        },
+      )
+      .await?;
+      state.invalidate_cache(&format!("profile_{}", profile_id));
 
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(profile)?,
-  })
+      Ok(profile)
+    }
+  )
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn delete_profile(
   state: State<'_, Arc<AppState>>,
-  profile_id: String,
-) -> Result<SuccessResponse<String>> {
-  // Get profile name before deletion for logging
-  let profile_name = ProfileService::get_profile(&state.db, &profile_id)
-    .await
-    .ok()
-    .map(|p| p.name.clone())
-    .unwrap_or_else(|| "Unknown".to_string());
-
-  ProfileService::delete_profile(&state.db, &profile_id).await?;
-  state.invalidate_cache(&format!("profile_{}", profile_id));
-
-  // Log the deletion as a system event
-  let _ = crate::services::audit_service::AUDIT_SERVICE
-    .log_system_event(
-      &state.db,
-      "profile_deleted",
-      "info",
-      "ProfileHandler",
-      &format!("Profile '{}' was deleted", profile_name),
-      Some(serde_json::json!({
-        "profile_id": profile_id,
-        "profile_name": profile_name
-      })),
-      None,
-    )
-    .await;
-
-  Ok(SuccessResponse {
-    success: true,
-    data: "Profile deleted successfully".to_string(),
+  profile_id: ProfileId,
+) -> CommandResult<String> {
+  instrumented!("delete_profile", (&profile_id,), async {
+    // Get profile name before deletion for logging
+    let profile_name = ProfileService::get_profile(&state.db, profile_id)
+      .await
+      .ok()
+      .map(|p| p.name.clone())
+      .unwrap_or_else(|| "Unknown".to_string());
+
+    ProfileService::delete_profile(&state.db, profile_id).await?;
+    state.invalidate_cache(&format!("profile_{}", profile_id));
+    OfflineCache::forget_profile(profile_id);
+
+    // Log the deletion as a system event
+    let _ = crate::services::audit_service::AUDIT_SERVICE
+      .log_system_event(
+        &state.db,
+        "profile_deleted",
+        Severity::Info,
+        "ProfileHandler",
+        &format!("Profile '{}' was deleted", profile_name),
+        Some(serde_json::json!({
+          "profile_id": profile_id,
+          "profile_name": profile_name
+        })),
+        None,
+      )
+      .await;
+
+    Ok("Profile deleted successfully".to_string())
   })
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn activate_profile(
   state: State<'_, Arc<AppState>>,
-  profile_id: String,
-  user_id: String,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let profile = ProfileService::activate_profile(&state.db, &profile_id, &user_id).await?;
-  state.invalidate_cache(&format!("profiles_{}", user_id));
+  profile_id: ProfileId,
+  user_id: UserId,
+) -> CommandResult<ProfileDto> {
+  instrumented!("activate_profile", (&profile_id, &user_id), async {
+    let profile =
+      ProfileService::activate_profile(&state.db, profile_id, user_id, ActivationSource::Manual)
+        .await?;
+    state.invalidate_cache(&format!("profiles_{}", user_id));
+    state.invalidate_profile_caches();
+
+    // Log the activation as a system event
+    let _ = crate::services::audit_service::AUDIT_SERVICE
+      .log_system_event(
+        &state.db,
+        "profile_activated",
+        Severity::Info,
+        "ProfileHandler",
+        &format!("Profile '{}' was activated", profile.name),
+        Some(serde_json::json!({
+          "profile_id": profile_id,
+          "profile_name": profile.name
+        })),
+        None,
+      )
+      .await;
+
+    ChecklistService::present(&state.db, state.system.as_ref(), profile_id).await;
+
+    Ok(profile)
+  })
+}
 
-  // Log the activation as a system event
-  let _ = crate::services::audit_service::AUDIT_SERVICE
-    .log_system_event(
-      &state.db,
-      "profile_activated",
-      "info",
-      "ProfileHandler",
-      &format!("Profile '{}' was activated", profile.name),
-      Some(serde_json::json!({
-        "profile_id": profile_id,
-        "profile_name": profile.name
-      })),
-      None,
-    )
-    .await;
+#[tauri::command(rename_all = "camelCase")]
+pub async fn duplicate_profile(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+  user_id: UserId,
+) -> CommandResult<ProfileDto> {
+  instrumented!("duplicate_profile", (&profile_id, &user_id), async {
+    let profile = ProfileService::duplicate_profile(&state.db, profile_id, user_id).await?;
+    state.invalidate_cache(&format!("profiles_{}", user_id));
+
+    Ok(profile)
+  })
+}
 
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(profile)?,
+/// Upload a sanitized copy of a profile and return a code others can import it by
+#[tauri::command(rename_all = "camelCase")]
+pub async fn share_profile(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+) -> CommandResult<ProfileShareDto> {
+  instrumented!("share_profile", (&profile_id,), async {
+    ShareService::share_profile(&state.db, profile_id).await
   })
 }
 
+/// Create, update and delete profiles to match a YAML or TOML profile file;
+/// `dry_run` only reports what would change
 #[tauri::command(rename_all = "camelCase")]
-pub async fn duplicate_profile(
+pub async fn sync_from_declarative(
   state: State<'_, Arc<AppState>>,
-  profile_id: String,
-  user_id: String,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let profile = ProfileService::duplicate_profile(&state.db, &profile_id, &user_id).await?;
-  state.invalidate_cache(&format!("profiles_{}", user_id));
+  user_id: UserId,
+  path: String,
+  dry_run: Option<bool>,
+) -> CommandResult<DeclarativeSyncReportDto> {
+  instrumented!(
+    "sync_from_declarative",
+    (&user_id, &path, &dry_run),
+    async {
+      let report = DeclarativeService::sync_from_declarative(
+        &state.db,
+        user_id,
+        &path,
+        dry_run.unwrap_or(false),
+      )
+      .await?;
+      state.invalidate_cache(&format!("profiles_{}", user_id));
+
+      Ok(report)
+    }
+  )
+}
 
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(profile)?,
+/// A standalone shell script (displayplacer + `open`) that reproduces the
+/// profile on a Mac without Smoothie
+#[tauri::command(rename_all = "camelCase")]
+pub async fn export_profile_as_script(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+) -> CommandResult<String> {
+  instrumented!("export_profile_as_script", (&profile_id,), async {
+    ScriptExportService::export_profile_as_script(&state.db, profile_id).await
   })
 }
 
 #[tauri::command(rename_all = "camelCase")]
-pub async fn get_favorite_profiles(
+pub async fn import_shared_profile(
   state: State<'_, Arc<AppState>>,
-  user_id: String,
-) -> Result<SuccessResponse<Vec<serde_json::Value>>> {
-  let profiles = ProfileService::get_favorite_profiles(&state.db, &user_id).await?;
-  let data: Vec<serde_json::Value> = profiles
-    .into_iter()
-    .map(|p| serde_json::to_value(p).unwrap())
-    .collect();
+  user_id: UserId,
+  code: String,
+) -> CommandResult<ProfileDto> {
+  instrumented!("import_shared_profile", (&user_id, &code), async {
+    let profile = ShareService::import_shared_profile(&state.db, user_id, &code).await?;
+    state.invalidate_cache(&format!("profiles_{}", user_id));
+
+    Ok(profile)
+  })
+}
 
-  Ok(SuccessResponse {
-    success: true,
-    data,
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_favorite_profiles(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+) -> CommandResult<Vec<ProfileDto>> {
+  instrumented!("get_favorite_profiles", (&user_id,), async {
+    let profiles = ProfileService::get_favorite_profiles(&state.db, user_id).await?;
+    Ok(profiles)
   })
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn get_most_used_profiles(
   state: State<'_, Arc<AppState>>,
-  user_id: String,
+  user_id: UserId,
   limit: Option<i64>,
-) -> Result<SuccessResponse<Vec<serde_json::Value>>> {
-  let profiles =
-    ProfileService::get_most_used_profiles(&state.db, &user_id, limit.unwrap_or(5)).await?;
-  let data: Vec<serde_json::Value> = profiles
-    .into_iter()
-    .map(|p| serde_json::to_value(p).unwrap())
-    .collect();
-
-  Ok(SuccessResponse {
-    success: true,
-    data,
+) -> CommandResult<Vec<ProfileDto>> {
+  instrumented!("get_most_used_profiles", (&user_id, &limit), async {
+    let profiles =
+      ProfileService::get_most_used_profiles(&state.db, user_id, limit.unwrap_or(5)).await?;
+    Ok(profiles)
   })
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn set_profile_favorite(
   state: State<'_, Arc<AppState>>,
-  profile_id: String,
+  profile_id: ProfileId,
   is_favorite: bool,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  let profile = ProfileService::set_favorite(&state.db, &profile_id, is_favorite).await?;
-  state.invalidate_cache(&format!("profile_{}", profile_id));
+) -> CommandResult<ProfileDto> {
+  instrumented!("set_profile_favorite", (&profile_id, &is_favorite), async {
+    let profile = ProfileService::set_favorite(&state.db, profile_id, is_favorite).await?;
+    state.invalidate_cache(&format!("profile_{}", profile_id));
 
-  Ok(SuccessResponse {
-    success: true,
-    data: serde_json::to_value(profile)?,
+    Ok(profile)
   })
 }
 
+/// Skip app launches and browser tabs on battery below `threshold` percent; null disables
 #[tauri::command(rename_all = "camelCase")]
-pub async fn start_profile(
+pub async fn set_profile_low_power_threshold(
   state: State<'_, Arc<AppState>>,
-  profile_id: String,
-  user_id: String,
-) -> Result<SuccessResponse<StartProfileResult>> {
-  tracing::info!("Starting profile: {}", profile_id);
-
-  // Apply monitor layout first (before launching apps)
-  let monitor_layout = match MonitorService::get_system_monitors(&state.db, &profile_id).await {
-    Ok(monitors) if !monitors.is_empty() => {
-      tracing::info!("Applying monitor layout with {} monitors", monitors.len());
-      let monitor_count = monitors.len();
-
-      // Try AppleScript method first, then fall back to direct execution
-      match SystemService::apply_monitor_layout_applescript(&monitors).await {
-        Ok(()) => MonitorLayoutResult {
-          applied: true,
-          monitor_count,
-          message: "Monitor layout applied successfully".to_string(),
-        },
-        Err(e) => {
-          tracing::warn!("AppleScript method failed: {:?}, trying direct method", e);
-          match SystemService::apply_monitor_layout(monitors) {
-            Ok(()) => MonitorLayoutResult {
-              applied: true,
-              monitor_count,
-              message: "Monitor layout applied successfully".to_string(),
-            },
-            Err(e) => {
-              let error_msg = e.to_string();
-              tracing::warn!("Monitor layout application failed: {}", error_msg);
-              MonitorLayoutResult {
-                applied: false,
-                monitor_count,
-                message: format!("Failed to apply monitor layout: {}", error_msg),
-              }
-            }
-          }
+  profile_id: ProfileId,
+  threshold: Option<i32>,
+) -> CommandResult<ProfileDto> {
+  instrumented!(
+    "set_profile_low_power_threshold",
+    (&profile_id, &threshold),
+    async {
+      let profile =
+        ProfileService::set_low_power_threshold(&state.db, profile_id, threshold).await?;
+      state.invalidate_cache(&format!("profile_{}", profile_id));
+
+      Ok(profile)
+    }
+  )
+}
+
+/// Keep a profile out of cloud sync, whatever the user's sync scope
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_profile_local_only(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+  local_only: bool,
+) -> CommandResult<ProfileDto> {
+  instrumented!(
+    "set_profile_local_only",
+    (&profile_id, &local_only),
+    async {
+      let profile = ProfileService::set_local_only(&state.db, profile_id, local_only).await?;
+      state.invalidate_cache(&format!("profile_{}", profile_id));
+
+      Ok(profile)
+    }
+  )
+}
+
+/// Let Sidecar, AirPlay and virtual displays count for a profile's monitor
+/// binding and layout
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_profile_virtual_displays(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+  include: bool,
+) -> CommandResult<ProfileDto> {
+  instrumented!(
+    "set_profile_virtual_displays",
+    (&profile_id, &include),
+    async {
+      let profile =
+        ProfileService::set_include_virtual_displays(&state.db, profile_id, include).await?;
+      state.invalidate_cache(&format!("profile_{}", profile_id));
+
+      Ok(profile)
+    }
+  )
+}
+
+/// Lock a profile so edits to it are rejected, or unlock it
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_profile_locked(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+  locked: bool,
+) -> CommandResult<ProfileDto> {
+  instrumented!("set_profile_locked", (&profile_id, &locked), async {
+    let profile = ProfileService::set_locked(&state.db, profile_id, locked).await?;
+    state.invalidate_cache(&format!("profile_{}", profile_id));
+
+    Ok(profile)
+  })
+}
+
+/// Bind a profile to a monitor count and/or the signature of a display setup;
+/// `useCurrentMonitors` takes the signature of the displays connected now
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_profile_monitor_binding(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+  required_monitor_count: Option<i32>,
+  monitor_signature: Option<String>,
+  use_current_monitors: Option<bool>,
+) -> CommandResult<ProfileDto> {
+  instrumented!(
+    "set_profile_monitor_binding",
+    (
+      &profile_id,
+      &required_monitor_count,
+      &monitor_signature,
+      &use_current_monitors
+    ),
+    async {
+      let monitor_signature = if use_current_monitors.unwrap_or(false) {
+        let system = state.system.clone();
+        let monitors = tokio::task::spawn_blocking(move || system.get_monitors())
+          .await
+          .map_err(|e| SmoothieError::SystemError(format!("Monitor detection failed: {}", e)))?;
+        let include_virtual = ProfileService::get_profile(&state.db, profile_id)
+          .await?
+          .include_virtual_displays;
+        let monitors = profile_resolver::displays_for(include_virtual, &monitors);
+        if monitors.is_empty() {
+          return Err(SmoothieError::SystemError("No displays detected".into()));
         }
-      }
+        Some(profile_resolver::monitor_signature(&monitors))
+      } else {
+        monitor_signature
+      };
+      let profile = ProfileService::set_monitor_binding(
+        &state.db,
+        profile_id,
+        required_monitor_count,
+        monitor_signature,
+      )
+      .await?;
+      state.invalidate_cache(&format!("profile_{}", profile_id));
+
+      Ok(profile)
     }
-    Ok(_) => {
-      tracing::info!("No monitors configured for this profile");
-      MonitorLayoutResult {
-        applied: false,
-        monitor_count: 0,
-        message: "No monitor layout configured for this profile".to_string(),
-      }
+  )
+}
+
+/// The profile the connected displays would switch to, without activating it
+#[tauri::command(rename_all = "camelCase")]
+pub async fn resolve_profile_for_monitors(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+) -> CommandResult<Option<ProfileMatchDto>> {
+  instrumented!("resolve_profile_for_monitors", (&user_id,), async {
+    let pick =
+      ProfileResolver::resolve_for_monitors(&state.db, state.system.clone(), user_id).await?;
+
+    Ok(pick)
+  })
+}
+
+/// Changes to a profile and its monitors, apps and tabs, newest first
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_profile_history(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+  limit: Option<i64>,
+) -> CommandResult<Vec<ActivityLogDto>> {
+  instrumented!("get_profile_history", (&profile_id, &limit), async {
+    ChangeLog::history(&state.db, profile_id, limit).await
+  })
+}
+
+/// Revert the profile's most recent edit, bringing back a deleted monitor, app or tab
+#[tauri::command(rename_all = "camelCase")]
+pub async fn undo_last_change(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+) -> CommandResult<Option<RevertedEditDto>> {
+  instrumented!("undo_last_change", (&profile_id,), async {
+    let reverted = EditHistory::undo(&state.db, profile_id).await?;
+    invalidate_profile_items(&state, profile_id);
+    Ok(reverted)
+  })
+}
+
+/// Re-apply the edit most recently undone
+#[tauri::command(rename_all = "camelCase")]
+pub async fn redo_last_change(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+) -> CommandResult<Option<RevertedEditDto>> {
+  instrumented!("redo_last_change", (&profile_id,), async {
+    let reverted = EditHistory::redo(&state.db, profile_id).await?;
+    invalidate_profile_items(&state, profile_id);
+    Ok(reverted)
+  })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_profile_edit_state(profile_id: ProfileId) -> CommandResult<EditStateDto> {
+  instrumented!("get_profile_edit_state", (&profile_id,), async {
+    Ok(EditHistory::state(profile_id))
+  })
+}
+
+fn invalidate_profile_items(state: &AppState, profile_id: ProfileId) {
+  for prefix in ["profile", "monitors", "apps", "browser_tabs"] {
+    state.invalidate_cache(&format!("{}_{}", prefix, profile_id));
+  }
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_profile_deactivation_actions(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+) -> CommandResult<Vec<DeactivationActionDto>> {
+  instrumented!("get_profile_deactivation_actions", (&profile_id,), async {
+    DeactivationService::get_actions(&state.db, profile_id).await
+  })
+}
+
+/// Replace the cleanup a profile runs, in list order, when another profile is activated
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_profile_deactivation_actions(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+  actions: Vec<DeactivationActionRequest>,
+) -> CommandResult<Vec<DeactivationActionDto>> {
+  instrumented!(
+    "set_profile_deactivation_actions",
+    (&profile_id, &actions),
+    async { DeactivationService::set_actions(&state.db, profile_id, actions).await }
+  )
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_profile_blocked_apps(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+) -> CommandResult<Vec<BlockedAppDto>> {
+  instrumented!("get_profile_blocked_apps", (&profile_id,), async {
+    AppBlocklistService::get_blocked_apps(&state.db, profile_id).await
+  })
+}
+
+/// Replace the apps hidden or quit whenever they're launched while the profile is active
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_profile_blocked_apps(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+  apps: Vec<BlockedAppRequest>,
+) -> CommandResult<Vec<BlockedAppDto>> {
+  instrumented!("set_profile_blocked_apps", (&profile_id, &apps), async {
+    AppBlocklistService::set_blocked_apps(&state.db, profile_id, apps).await
+  })
+}
+
+/// Keep a blocked app open under the active profile, for `durationMinutes` or
+/// until another profile is activated
+#[tauri::command(rename_all = "camelCase")]
+pub async fn allow_blocked_app(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+  bundle_id: String,
+  duration_minutes: Option<i64>,
+) -> CommandResult<BlockedAppOverrideDto> {
+  instrumented!(
+    "allow_blocked_app",
+    (&user_id, &bundle_id, &duration_minutes),
+    async { AppBlocklistService::allow_app(&state, user_id, &bundle_id, duration_minutes).await }
+  )
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_profile_checklist(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+) -> CommandResult<Vec<ChecklistItemDto>> {
+  instrumented!("get_profile_checklist", (&profile_id,), async {
+    ChecklistService::get_items(&state.db, profile_id).await
+  })
+}
+
+/// Replace the things to check after the profile activates; items sent with
+/// an `id` keep it
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_profile_checklist(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+  items: Vec<ChecklistItemRequest>,
+) -> CommandResult<Vec<ChecklistItemDto>> {
+  instrumented!("set_profile_checklist", (&profile_id, &items), async {
+    ChecklistService::set_items(&state.db, profile_id, items).await
+  })
+}
+
+/// The checklist and which items were checked since the last activation
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_activation_checklist(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+) -> CommandResult<ActivationChecklistDto> {
+  instrumented!("get_activation_checklist", (&profile_id,), async {
+    ChecklistService::activation_checklist(&state.db, profile_id).await
+  })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_checklist_item_completed(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+  item_id: String,
+  completed: bool,
+) -> CommandResult<ActivationChecklistDto> {
+  instrumented!(
+    "set_checklist_item_completed",
+    (&profile_id, &item_id, &completed),
+    async {
+      ChecklistService::set_item_completed(&state.db, profile_id, &item_id, completed).await
+    }
+  )
+}
+
+/// Daily snapshots of every profile and the ones taken before restores, newest first
+#[tauri::command(rename_all = "camelCase")]
+pub async fn list_snapshots() -> CommandResult<Vec<SnapshotDto>> {
+  instrumented!("list_snapshots", async {
+    SnapshotService::list_snapshots().await
+  })
+}
+
+/// Put profiles back the way they were in a snapshot, all of them unless
+/// `profileIds` names some
+#[tauri::command(rename_all = "camelCase")]
+pub async fn restore_snapshot(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+  snapshot_id: String,
+  profile_ids: Option<Vec<ProfileId>>,
+) -> CommandResult<RestoreSnapshotDto> {
+  instrumented!(
+    "restore_snapshot",
+    (&user_id, &snapshot_id, &profile_ids),
+    async {
+      SnapshotService::restore_snapshot(&state.db, user_id, &snapshot_id, profile_ids).await
     }
-    Err(e) => {
-      tracing::warn!("Failed to get profile monitors: {:?}", e);
-      MonitorLayoutResult {
-        applied: false,
-        monitor_count: 0,
-        message: format!("Failed to load monitor layout: {}", e),
+  )
+}
+
+/// Start a profile: apply its monitor layout, launch its apps and open its tabs and files.
+/// `steps` picks which of those run and in what order; all of them by default.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn start_profile(
+  state: State<'_, Arc<AppState>>,
+  profile_id: ProfileId,
+  user_id: UserId,
+  steps: Option<Vec<ActivationStepKind>>,
+) -> CommandResult<StartProfileResult> {
+  instrumented!("start_profile", (&profile_id, &user_id, &steps), async {
+    tracing::info!("Starting profile: {}", profile_id);
+
+    let (mut pipeline, low_power) = match ActivationPipeline::for_start(
+      state.db.clone(),
+      state.system.clone(),
+      profile_id,
+      user_id,
+    )
+    .await
+    {
+      Ok(started) => started,
+      Err(e) if offline_cache::is_outage(&e) => {
+        return start_offline(&state, profile_id, steps.as_deref(), e).await;
       }
+      Err(e) => return Err(e),
+    };
+    if let Some(steps) = &steps {
+      pipeline = pipeline.order(steps);
     }
-  };
 
-  // Launch all launchable apps
-  let apps_launched = AppService::launch_profile_apps(&state.db, &profile_id, &user_id).await?;
+    let started_at = chrono::Utc::now();
+    let run = pipeline
+      .journaled(ActivationJournal::new(profile_id, user_id))
+      .run()
+      .await;
+    ActivationReportService::record_failure(
+      &state.db,
+      state.system.as_ref(),
+      profile_id,
+      &run,
+      started_at,
+    )
+    .await;
+    if run.error.is_none() {
+      // Keep the snapshot current so the profile can start during an outage
+      let db = state.db.clone();
+      tokio::spawn(async move {
+        if let Ok(profile) = ProfileService::get_profile_response(&db, profile_id).await {
+          OfflineCache::save_profile(&profile);
+        }
+      });
+    }
+    start_result(profile_id, run, low_power)
+  })
+}
 
-  // Open all browser tabs
-  let tabs_opened = BrowserService::open_profile_tabs(&state.db, &profile_id).await?;
+/// Start a profile from the offline cache, or fail with `error` if it isn't there
+async fn start_offline(
+  state: &AppState,
+  profile_id: ProfileId,
+  steps: Option<&[ActivationStepKind]>,
+  error: SmoothieError,
+) -> crate::error::Result<StartProfileResult> {
+  let Some(started) = OfflineCache::start_profile(state.system.clone(), profile_id, steps).await
+  else {
+    return Err(error);
+  };
+  Ok(StartProfileResult {
+    profile_id,
+    apps_launched: started.apps_launched,
+    tabs_opened: started.tabs_opened,
+    files_opened: started.files_opened,
+    monitor_layout: started.monitor_layout,
+    low_power: None,
+    steps: started.steps,
+    offline: true,
+  })
+}
 
+/// The command result for a finished activation run
+fn start_result(
+  profile_id: ProfileId,
+  run: PipelineRun,
+  low_power: Option<PowerState>,
+) -> crate::error::Result<StartProfileResult> {
+  if let Some(e) = run.error {
+    return Err(SmoothieError::ActivationFailed {
+      error: Box::new(e),
+      steps: run.steps,
+    });
+  }
+
+  let output = run.output;
   let result = StartProfileResult {
-    profile_id: profile_id.clone(),
-    apps_launched,
-    tabs_opened,
-    monitor_layout,
+    profile_id,
+    apps_launched: output.apps_launched,
+    tabs_opened: output.tabs_opened,
+    files_opened: output.files_opened,
+    monitor_layout: output
+      .monitor_layout
+      .unwrap_or_else(|| MonitorLayoutResult::skipped("Monitor layout step was not run")),
+    low_power,
+    steps: run.steps,
+    offline: false,
   };
 
   tracing::info!(
-    "Started profile {}: {} apps launched, {} tabs opened, monitor layout {}",
+    "Started profile {}: {} apps launched, {} tabs opened, {} files opened, monitor layout {}",
     profile_id,
     result.apps_launched.len(),
     result.tabs_opened.len(),
+    result.files_opened.len(),
     if result.monitor_layout.applied {
       "applied"
     } else {
@@ -341,8 +833,67 @@ pub async fn start_profile(
     }
   );
 
-  Ok(SuccessResponse {
-    success: true,
-    data: result,
+  Ok(result)
+}
+
+/// Activations an earlier launch didn't finish, to offer resuming or rolling back
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_interrupted_activations(
+  state: State<'_, Arc<AppState>>,
+) -> CommandResult<Vec<InterruptedActivationDto>> {
+  instrumented!("get_interrupted_activations", async {
+    ActivationJournalService::list_interrupted(&state.db).await
+  })
+}
+
+/// Finish an interrupted activation by running the steps it hadn't completed
+#[tauri::command(rename_all = "camelCase")]
+pub async fn resume_interrupted_activation(
+  state: State<'_, Arc<AppState>>,
+  journal_id: uuid::Uuid,
+) -> CommandResult<StartProfileResult> {
+  instrumented!("resume_interrupted_activation", (&journal_id,), async {
+    let started_at = chrono::Utc::now();
+    let (profile_id, run, low_power) =
+      ActivationJournalService::resume(state.db.clone(), state.system.clone(), journal_id).await?;
+    ActivationReportService::record_failure(
+      &state.db,
+      state.system.as_ref(),
+      profile_id,
+      &run,
+      started_at,
+    )
+    .await;
+    start_result(profile_id, run, low_power)
+  })
+}
+
+/// What went wrong in a failed activation: the failing step, what external
+/// tools printed, the permissions and tools found, and what to try
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_activation_report(
+  state: State<'_, Arc<AppState>>,
+  activation_id: uuid::Uuid,
+) -> CommandResult<ActivationReportDto> {
+  instrumented!("get_activation_report", (&activation_id,), async {
+    ActivationReportService::get(&state.db, activation_id).await
+  })
+}
+
+/// Undo what an interrupted activation had done
+#[tauri::command(rename_all = "camelCase")]
+pub async fn rollback_interrupted_activation(
+  state: State<'_, Arc<AppState>>,
+  journal_id: uuid::Uuid,
+) -> CommandResult<Vec<StepReport>> {
+  instrumented!("rollback_interrupted_activation", (&journal_id,), async {
+    ActivationJournalService::rollback(state.db.clone(), state.system.clone(), journal_id).await
+  })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn discard_interrupted_activation(journal_id: uuid::Uuid) -> CommandResult<()> {
+  instrumented!("discard_interrupted_activation", (&journal_id,), async {
+    ActivationJournalService::discard(journal_id).await
   })
 }
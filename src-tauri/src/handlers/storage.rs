@@ -0,0 +1,50 @@
+// Blob storage handlers - icons, favicons and attachments by reference
+
+use crate::logging::instrumented;
+use crate::{
+  error::CommandResult,
+  services::StorageService,
+  state::AppState,
+  storage::{BlobGcReport, BlobKind, BlobUsage},
+};
+use std::sync::Arc;
+use tauri::State;
+
+/// Store a blob and get back the `blob:` reference to save in its place
+#[tauri::command(rename_all = "camelCase")]
+pub async fn store_blob(
+  state: State<'_, Arc<AppState>>,
+  kind: BlobKind,
+  data: Vec<u8>,
+) -> CommandResult<String> {
+  instrumented!("store_blob", (&kind, &data.len()), async {
+    state.storage.put(kind, &data).await
+  })
+}
+
+/// Read back the bytes behind a `blob:` reference
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_blob(
+  state: State<'_, Arc<AppState>>,
+  reference: String,
+) -> CommandResult<Vec<u8>> {
+  instrumented!("get_blob", (&reference,), async {
+    state.storage.get(&reference).await
+  })
+}
+
+/// Space used by each kind of blob against its quota
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_storage_usage(state: State<'_, Arc<AppState>>) -> CommandResult<Vec<BlobUsage>> {
+  instrumented!("get_storage_usage", async { state.storage.usage().await })
+}
+
+/// Remove blobs nothing refers to any more, without waiting for the daily pass
+#[tauri::command(rename_all = "camelCase")]
+pub async fn collect_storage_garbage(
+  state: State<'_, Arc<AppState>>,
+) -> CommandResult<BlobGcReport> {
+  instrumented!("collect_storage_garbage", async {
+    StorageService::collect_garbage(&state).await
+  })
+}
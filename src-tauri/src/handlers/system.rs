@@ -1,159 +1,306 @@
+use crate::logging::instrumented;
+use crate::services::api_version::{ApiVersion, ApiVersionDto};
+use crate::services::fix_service::{FailureCode, FixAction, FixDto, FixResultDto, FixService};
+use crate::services::update_service::{DownloadedUpdateDto, ReleaseNotesDto, UpdateInfoDto};
 use crate::{
-  error::Result,
-  models::SuccessResponse,
-  services::{InstalledApp, RunningApp, SystemMonitor, SystemService, SystemWindow},
-  state::AppState,
+  error::CommandResult,
+  services::{
+    BluetoothDevice, ConnectionMonitor, ConnectionStatus, HealthReport, HealthService,
+    InstalledApp, RunningApp, SystemMonitor, SystemWindow, UpdateService, UsbDevice,
+  },
+  state::{
+    startup::{Startup, StartupStatus},
+    supervisor::BackgroundTaskStatus,
+    AppState, INSTALLED_APPS_CACHE_KEY,
+  },
 };
+use serde::Serialize;
 use std::sync::Arc;
 use tauri::State;
 
+/// Current monitors, windows and running apps, for saving to a profile
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedLayout {
+  pub captured_at: String,
+  pub monitors: Vec<SystemMonitor>,
+  pub windows: Vec<SystemWindow>,
+  pub running_apps: Vec<RunningApp>,
+}
+
+/// The command API version this backend serves, and whether a frontend built
+/// against `clientVersion` can use it
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_api_version(client_version: Option<u32>) -> CommandResult<ApiVersionDto> {
+  instrumented!("get_api_version", (&client_version,), async {
+    Ok(ApiVersion::negotiate(client_version))
+  })
+}
+
 /// Check if the app has screen recording permission (required for display configuration)
 #[tauri::command(rename_all = "camelCase")]
-pub async fn check_display_permission(
-  _state: State<'_, Arc<AppState>>,
-) -> Result<SuccessResponse<bool>> {
-  let has_permission = SystemService::check_display_permission();
-  Ok(SuccessResponse {
-    success: true,
-    data: has_permission,
+pub async fn check_display_permission(state: State<'_, Arc<AppState>>) -> CommandResult<bool> {
+  instrumented!("check_display_permission", async {
+    let has_permission = state.system.check_display_permission();
+    Ok(has_permission)
   })
 }
 
 /// Request screen recording permission from the user
 #[tauri::command(rename_all = "camelCase")]
-pub async fn request_display_permission(
-  _state: State<'_, Arc<AppState>>,
-) -> Result<SuccessResponse<bool>> {
-  let granted = SystemService::request_display_permission();
-  Ok(SuccessResponse {
-    success: true,
-    data: granted,
+pub async fn request_display_permission(state: State<'_, Arc<AppState>>) -> CommandResult<bool> {
+  instrumented!("request_display_permission", async {
+    let granted = state.system.request_display_permission();
+    Ok(granted)
   })
 }
 
 /// Get all currently connected monitors with their properties
 #[tauri::command(rename_all = "camelCase")]
 pub async fn get_connected_monitors(
-  _state: State<'_, Arc<AppState>>,
-) -> Result<SuccessResponse<Vec<SystemMonitor>>> {
-  let monitors = SystemService::get_monitors();
+  state: State<'_, Arc<AppState>>,
+) -> CommandResult<Vec<SystemMonitor>> {
+  instrumented!("get_connected_monitors", async {
+    let monitors = state.system.get_monitors();
 
-  Ok(SuccessResponse {
-    success: true,
-    data: monitors,
+    Ok(monitors)
   })
 }
 
-/// Get all visible windows with their positions and sizes
+/// Get all visible windows with their positions and sizes; minimized ones
+/// and those of hidden apps are left out
 #[tauri::command(rename_all = "camelCase")]
 pub async fn get_visible_windows(
-  _state: State<'_, Arc<AppState>>,
-) -> Result<SuccessResponse<Vec<SystemWindow>>> {
-  let windows = SystemService::get_windows();
+  state: State<'_, Arc<AppState>>,
+) -> CommandResult<Vec<SystemWindow>> {
+  instrumented!("get_visible_windows", async {
+    let mut windows = state.system.get_windows();
+    windows.retain(SystemWindow::is_visible);
 
-  Ok(SuccessResponse {
-    success: true,
-    data: windows,
+    Ok(windows)
   })
 }
 
 /// Get all running applications
 #[tauri::command(rename_all = "camelCase")]
-pub async fn get_running_apps(
-  _state: State<'_, Arc<AppState>>,
-) -> Result<SuccessResponse<Vec<RunningApp>>> {
-  let apps = SystemService::get_running_apps();
+pub async fn get_running_apps(state: State<'_, Arc<AppState>>) -> CommandResult<Vec<RunningApp>> {
+  instrumented!("get_running_apps", async {
+    let apps = state.system.get_running_apps();
 
-  Ok(SuccessResponse {
-    success: true,
-    data: apps,
+    Ok(apps)
   })
 }
 
 /// Get all installed applications on the system
 #[tauri::command(rename_all = "camelCase")]
 pub async fn get_installed_apps(
-  _state: State<'_, Arc<AppState>>,
-) -> Result<SuccessResponse<Vec<InstalledApp>>> {
-  let apps = SystemService::get_installed_apps();
+  state: State<'_, Arc<AppState>>,
+  refresh: Option<bool>,
+) -> CommandResult<Vec<InstalledApp>> {
+  instrumented!("get_installed_apps", (&refresh,), async {
+    // Scanning is slow, so the list from startup is reused unless asked otherwise
+    if !refresh.unwrap_or(false) {
+      if let Some(apps) = state.cached::<Vec<InstalledApp>>(INSTALLED_APPS_CACHE_KEY) {
+        return Ok(apps);
+      }
+    }
+    let apps = state.system.get_installed_apps();
+    state.set_cache(INSTALLED_APPS_CACHE_KEY, &apps);
+
+    Ok(apps)
+  })
+}
+
+/// List attached USB and Thunderbolt devices, for picking one as a rule trigger
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_usb_devices(state: State<'_, Arc<AppState>>) -> CommandResult<Vec<UsbDevice>> {
+  instrumented!("get_usb_devices", async {
+    let system = state.system.clone();
+    let devices = tokio::task::spawn_blocking(move || system.get_usb_devices())
+      .await
+      .unwrap_or_default();
+
+    Ok(devices)
+  })
+}
+
+/// List connected Bluetooth devices, for picking one as a rule trigger
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_bluetooth_devices(
+  state: State<'_, Arc<AppState>>,
+) -> CommandResult<Vec<BluetoothDevice>> {
+  instrumented!("get_bluetooth_devices", async {
+    let system = state.system.clone();
+    let devices = tokio::task::spawn_blocking(move || system.get_bluetooth_devices())
+      .await
+      .unwrap_or_default();
+
+    Ok(devices)
+  })
+}
+
+/// Status of the supervised background tasks (watchers, rollups, startup jobs)
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_background_tasks(
+  state: State<'_, Arc<AppState>>,
+) -> CommandResult<Vec<BackgroundTaskStatus>> {
+  instrumented!("get_background_tasks", async { Ok(state.tasks.statuses()) })
+}
+
+/// Compare this version with the newest one in the release feed
+#[tauri::command(rename_all = "camelCase")]
+pub async fn check_for_updates(state: State<'_, Arc<AppState>>) -> CommandResult<UpdateInfoDto> {
+  instrumented!("check_for_updates", async {
+    UpdateService::check_for_updates(&state.db).await
+  })
+}
+
+/// Release notes for `version`, by default the version running
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_release_notes(version: Option<String>) -> CommandResult<ReleaseNotesDto> {
+  instrumented!("get_release_notes", (&version,), async {
+    UpdateService::get_release_notes(version.as_deref()).await
+  })
+}
 
-  Ok(SuccessResponse {
-    success: true,
-    data: apps,
+/// Download and verify the newest installer for this Mac
+#[tauri::command(rename_all = "camelCase")]
+pub async fn download_update(
+  state: State<'_, Arc<AppState>>,
+) -> CommandResult<DownloadedUpdateDto> {
+  instrumented!("download_update", async {
+    UpdateService::download_update(&state.db).await
   })
 }
 
 /// Capture the current layout (monitors + windows) for saving to a profile
 #[tauri::command(rename_all = "camelCase")]
 pub async fn capture_current_layout(
-  _state: State<'_, Arc<AppState>>,
-) -> Result<SuccessResponse<serde_json::Value>> {
-  // Use optimized single-call method to avoid double window detection
-  let (monitors, windows, apps) = SystemService::capture_system_layout();
+  state: State<'_, Arc<AppState>>,
+) -> CommandResult<CapturedLayout> {
+  instrumented!("capture_current_layout", async {
+    // Use optimized single-call method to avoid double window detection
+    let (monitors, windows, apps) = state.system.capture_system_layout();
 
-  let layout = serde_json::json!({
-      "capturedAt": chrono::Utc::now().to_rfc3339(),
-      "monitors": monitors,
-      "windows": windows,
-      "runningApps": apps,
-  });
-
-  Ok(SuccessResponse {
-    success: true,
-    data: layout,
+    Ok(CapturedLayout {
+      captured_at: chrono::Utc::now().to_rfc3339(),
+      monitors,
+      windows,
+      running_apps: apps,
+    })
   })
 }
 
 /// Apply a monitor layout configuration to the system
 #[tauri::command(rename_all = "camelCase")]
 pub async fn apply_monitor_layout(
-  _state: State<'_, Arc<AppState>>,
+  state: State<'_, Arc<AppState>>,
   monitors: Vec<SystemMonitor>,
-) -> Result<SuccessResponse<String>> {
-  // Log incoming monitor positions for debugging
-  tracing::info!(
-    "apply_monitor_layout called with {} monitors:",
-    monitors.len()
-  );
-  for m in &monitors {
+) -> CommandResult<String> {
+  instrumented!("apply_monitor_layout", (&monitors,), async {
+    // Log incoming monitor positions for debugging
     tracing::info!(
-      "  Monitor {}: {}x{} at ({}, {})",
-      m.display_id,
-      m.width,
-      m.height,
-      m.x,
-      m.y
+      "apply_monitor_layout called with {} monitors:",
+      monitors.len()
     );
-  }
-
-  // Skip native CoreGraphics API - it reports success but doesn't actually move monitors
-  // Go directly to displayplacer via AppleScript (prompts for admin password, actually works)
-  match SystemService::apply_monitor_layout_applescript(&monitors).await {
-    Ok(()) => Ok(SuccessResponse {
-      success: true,
-      data: "Monitor layout applied successfully".to_string(),
-    }),
-    Err(e) => {
-      tracing::warn!("AppleScript method failed: {:?}", e);
-      // Fall back to direct execution
-      match SystemService::apply_monitor_layout(monitors) {
-        Ok(()) => Ok(SuccessResponse {
-          success: true,
-          data: "Monitor layout applied successfully".to_string(),
-        }),
-        Err(e) => {
-          tracing::error!("apply_monitor_layout command failed: {:?}", e);
-          let error_msg = e.to_string();
-          if error_msg.contains("Please run this command manually") {
-            Ok(SuccessResponse {
-              success: true,
-              data: format!("MANUAL_COMMAND:{}", error_msg),
-            })
-          } else {
-            Err(e)
+    for m in &monitors {
+      tracing::info!(
+        "  Monitor {}: {}x{} at ({}, {})",
+        m.display_id,
+        m.width,
+        m.height,
+        m.x,
+        m.y
+      );
+    }
+
+    // Skip native CoreGraphics API - it reports success but doesn't actually move monitors
+    // Go directly to displayplacer via AppleScript (prompts for admin password, actually works)
+    match state
+      .system
+      .apply_monitor_layout_applescript(&monitors)
+      .await
+    {
+      Ok(()) => Ok("Monitor layout applied successfully".to_string()),
+      Err(e) => {
+        tracing::warn!("AppleScript method failed: {:?}", e);
+        // Fall back to direct execution
+        match state.system.apply_monitor_layout(monitors) {
+          Ok(()) => Ok("Monitor layout applied successfully".to_string()),
+          Err(e) => {
+            tracing::error!("apply_monitor_layout command failed: {:?}", e);
+            let error_msg = e.to_string();
+            if error_msg.contains("Please run this command manually") {
+              Ok(format!("MANUAL_COMMAND:{}", error_msg))
+            } else {
+              Err(e)
+            }
           }
         }
       }
     }
-  }
+  })
+}
+
+/// Light up every display a profile blanked; returns the IDs released
+#[tauri::command(rename_all = "camelCase")]
+pub async fn release_blanked_displays(state: State<'_, Arc<AppState>>) -> CommandResult<Vec<u32>> {
+  instrumented!("release_blanked_displays", async {
+    let blanked = state.system.blanked_displays();
+    for display_id in &blanked {
+      state.system.unblank_display(*display_id)?;
+    }
+    Ok(blanked)
+  })
+}
+
+/// Which startup stage is running; other commands fail until it's `ready`
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_startup_status() -> CommandResult<StartupStatus> {
+  instrumented!("get_startup_status", async { Ok(Startup::status()) })
+}
+
+/// Whether the database is reachable; `db-connection-changed` events follow
+/// every change after this
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_db_connection_status() -> CommandResult<ConnectionStatus> {
+  instrumented!("get_db_connection_status", async {
+    Ok(ConnectionMonitor::status())
+  })
+}
+
+/// Report the status of the database, schema, permissions and external tools
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_health(state: State<'_, Arc<AppState>>) -> CommandResult<HealthReport> {
+  instrumented!("get_health", async {
+    let report =
+      HealthService::check(&state.db, state.system.as_ref(), &state.tasks.statuses()).await;
+    Ok(report)
+  })
+}
+
+/// What can be done about a known failure
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_fixes(error_code: FailureCode) -> CommandResult<Vec<FixDto>> {
+  instrumented!("get_fixes", (&error_code,), async {
+    Ok(FixService::get_fixes(error_code))
+  })
+}
+
+/// Carry out a fix from `get_fixes`; `appId` names the profile app for fixes
+/// about one
+#[tauri::command(rename_all = "camelCase")]
+pub async fn apply_fix(
+  state: State<'_, Arc<AppState>>,
+  action: FixAction,
+  app_id: Option<String>,
+) -> CommandResult<FixResultDto> {
+  instrumented!("apply_fix", (&action, &app_id), async {
+    let result =
+      FixService::apply_fix(&state.db, state.system.as_ref(), action, app_id.as_deref()).await?;
+    if action == FixAction::RemoveAppFromProfile {
+      state.invalidate_profile_caches();
+    }
+    Ok(result)
+  })
 }
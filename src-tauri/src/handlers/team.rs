@@ -0,0 +1,79 @@
+use crate::logging::instrumented;
+use crate::{
+  error::{CommandResult, SmoothieError},
+  models::{ProfileDto, ProfileId, TeamDto, TeamId, TeamLibraryProfileDto, UserId},
+  services::TeamService,
+  state::AppState,
+};
+use std::sync::Arc;
+use tauri::State;
+use uuid::Uuid;
+
+/// Pull the user's teams and team libraries from Supabase
+#[tauri::command(rename_all = "camelCase")]
+pub async fn sync_teams(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+) -> CommandResult<Vec<TeamDto>> {
+  instrumented!("sync_teams", (&user_id,), async {
+    TeamService::sync(&state.db, user_id).await
+  })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_teams(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+) -> CommandResult<Vec<TeamDto>> {
+  instrumented!("get_teams", (&user_id,), async {
+    TeamService::get_teams(&state.db, user_id).await
+  })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_team_library(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+  team_id: TeamId,
+) -> CommandResult<Vec<TeamLibraryProfileDto>> {
+  instrumented!("get_team_library", (&user_id, &team_id), async {
+    TeamService::get_library(&state.db, user_id, team_id).await
+  })
+}
+
+/// Copy a team library profile into the user's own profiles
+#[tauri::command(rename_all = "camelCase")]
+pub async fn clone_team_profile(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+  library_profile_id: String,
+) -> CommandResult<ProfileDto> {
+  instrumented!(
+    "clone_team_profile",
+    (&user_id, &library_profile_id),
+    async {
+      let id = Uuid::parse_str(&library_profile_id).map_err(|e| {
+        SmoothieError::ValidationError(format!("Invalid library profile ID: {}", e))
+      })?;
+      let profile = TeamService::clone_profile(&state.db, user_id, id).await?;
+      state.invalidate_cache(&format!("profiles_{}", user_id));
+
+      Ok(profile)
+    }
+  )
+}
+
+/// Publish one of the user's profiles to a team library; team admins only
+#[tauri::command(rename_all = "camelCase")]
+pub async fn publish_team_profile(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+  team_id: TeamId,
+  profile_id: ProfileId,
+) -> CommandResult<TeamLibraryProfileDto> {
+  instrumented!(
+    "publish_team_profile",
+    (&user_id, &team_id, &profile_id),
+    async { TeamService::publish_profile(&state.db, user_id, team_id, profile_id).await }
+  )
+}
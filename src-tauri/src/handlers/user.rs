@@ -1,7 +1,14 @@
+use crate::logging::instrumented;
 use crate::{
-  error::{Result, SmoothieError},
-  models::{SuccessResponse, UserSettingsDto},
-  services::UserSettingsService,
+  error::{CommandResult, SmoothieError},
+  models::{
+    MonitorProfileMapping, ReportFormat, ShortcutValidationDto, SyncScope, UserId, UserSettingsDto,
+  },
+  services::{
+    folder_sync_service::{FolderSyncReportDto, FolderSyncStatusDto},
+    FolderSyncService, OfflineCache, SyncPayload, SyncService, TelemetryPayload, TelemetryService,
+    UserSettingsService,
+  },
   state::AppState,
 };
 use std::sync::Arc;
@@ -12,15 +19,19 @@ use uuid::Uuid;
 pub async fn get_user_settings(
   state: State<'_, Arc<AppState>>,
   user_id: String,
-) -> Result<SuccessResponse<UserSettingsDto>> {
-  let user_uuid = Uuid::parse_str(&user_id)
-    .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
+) -> CommandResult<UserSettingsDto> {
+  instrumented!("get_user_settings", (&user_id,), async {
+    let user_uuid = Uuid::parse_str(&user_id)
+      .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
 
-  let settings = UserSettingsService::get_settings(&state.db, user_uuid).await?;
+    let result = UserSettingsService::get_settings(&state.db, user_uuid).await;
+    if let Ok(settings) = &result {
+      OfflineCache::save_settings(settings);
+    }
 
-  Ok(SuccessResponse {
-    success: true,
-    data: settings,
+    OfflineCache::or_cached(result, "settings", || {
+      OfflineCache::settings(&user_uuid.to_string())
+    })
   })
 }
 
@@ -36,30 +47,49 @@ pub async fn update_user_settings(
   auto_activate_time: Option<String>,
   keyboard_shortcut: Option<String>,
   notifications_enabled: Option<bool>,
-) -> Result<SuccessResponse<UserSettingsDto>> {
-  let user_uuid = Uuid::parse_str(&user_id)
-    .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
-
-  let settings = UserSettingsService::update_settings(
-    &state.db,
-    user_uuid,
-    theme,
-    auto_restore,
-    monitor_detection,
-    animations_enabled,
-    cloud_sync,
-    auto_activate_time,
-    keyboard_shortcut,
-    notifications_enabled,
-  )
-  .await?;
+  log_retention_days: Option<i32>,
+  auto_cleanup_logs: Option<bool>,
+) -> CommandResult<UserSettingsDto> {
+  instrumented!(
+    "update_user_settings",
+    (
+      &user_id,
+      &theme,
+      &auto_restore,
+      &monitor_detection,
+      &animations_enabled,
+      &cloud_sync,
+      &auto_activate_time,
+      &keyboard_shortcut,
+      &notifications_enabled,
+      &log_retention_days,
+      &auto_cleanup_logs
+    ),
+    async {
+      let user_uuid = Uuid::parse_str(&user_id)
+        .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
 
-  tracing::info!("User settings updated for {}", user_id);
+      let settings = UserSettingsService::update_settings(
+        &state.db,
+        user_uuid,
+        theme,
+        auto_restore,
+        monitor_detection,
+        animations_enabled,
+        cloud_sync,
+        auto_activate_time,
+        keyboard_shortcut,
+        notifications_enabled,
+        log_retention_days,
+        auto_cleanup_logs,
+      )
+      .await?;
 
-  Ok(SuccessResponse {
-    success: true,
-    data: settings,
-  })
+      tracing::info!("User settings updated for {}", user_id);
+
+      Ok(settings)
+    }
+  )
 }
 
 // Keep old function names as aliases for backward compatibility
@@ -67,8 +97,12 @@ pub async fn update_user_settings(
 pub async fn get_user_preferences(
   state: State<'_, Arc<AppState>>,
   user_id: String,
-) -> Result<SuccessResponse<UserSettingsDto>> {
-  get_user_settings(state, user_id).await
+) -> CommandResult<UserSettingsDto> {
+  instrumented!("get_user_preferences", (&user_id,), async {
+    let user_uuid = Uuid::parse_str(&user_id)
+      .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
+    UserSettingsService::get_settings(&state.db, user_uuid).await
+  })
 }
 
 #[tauri::command(rename_all = "camelCase")]
@@ -78,18 +112,320 @@ pub async fn update_user_preferences(
   theme: Option<String>,
   notifications_enabled: Option<bool>,
   auto_restore: Option<bool>,
-) -> Result<SuccessResponse<UserSettingsDto>> {
-  update_user_settings(
-    state,
-    user_id,
-    theme,
-    auto_restore,
-    None, // monitor_detection
-    None, // animations_enabled
-    None, // cloud_sync
-    None, // auto_activate_time
-    None, // keyboard_shortcut
-    notifications_enabled,
+) -> CommandResult<UserSettingsDto> {
+  instrumented!(
+    "update_user_preferences",
+    (&user_id, &theme, &notifications_enabled, &auto_restore),
+    async {
+      let user_uuid = Uuid::parse_str(&user_id)
+        .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
+      UserSettingsService::update_settings(
+        &state.db,
+        user_uuid,
+        theme,
+        auto_restore,
+        None, // monitor_detection
+        None, // animations_enabled
+        None, // cloud_sync
+        None, // auto_activate_time
+        None, // keyboard_shortcut
+        notifications_enabled,
+        None, // log_retention_days
+        None, // auto_cleanup_logs
+      )
+      .await
+    }
+  )
+}
+
+/// Set the location sunrise/sunset schedules use; pass nulls to fall back to the device location
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_user_location(
+  state: State<'_, Arc<AppState>>,
+  user_id: String,
+  latitude: Option<f64>,
+  longitude: Option<f64>,
+) -> CommandResult<UserSettingsDto> {
+  instrumented!(
+    "set_user_location",
+    (&user_id, &latitude, &longitude),
+    async {
+      let user_uuid = Uuid::parse_str(&user_id)
+        .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
+      let location = match (latitude, longitude) {
+        (Some(latitude), Some(longitude)) => Some((latitude, longitude)),
+        (None, None) => None,
+        _ => {
+          return Err(SmoothieError::ValidationError(
+            "Latitude and longitude must be set together".into(),
+          ))
+        }
+      };
+      UserSettingsService::set_location(&state.db, user_uuid, location).await
+    }
   )
-  .await
+}
+
+/// Choose what cloud sync uploads; local-only profiles stay out under every scope
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_sync_scope(
+  state: State<'_, Arc<AppState>>,
+  user_id: String,
+  scope: SyncScope,
+) -> CommandResult<UserSettingsDto> {
+  instrumented!("set_sync_scope", (&user_id, &scope), async {
+    let user_uuid = Uuid::parse_str(&user_id)
+      .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
+    UserSettingsService::set_sync_scope(&state.db, user_uuid, scope).await
+  })
+}
+
+/// Save a usage report for the previous week every Monday morning
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_weekly_report(
+  state: State<'_, Arc<AppState>>,
+  user_id: String,
+  enabled: bool,
+  format: Option<ReportFormat>,
+) -> CommandResult<UserSettingsDto> {
+  instrumented!("set_weekly_report", (&user_id, &enabled, &format), async {
+    let user_uuid = Uuid::parse_str(&user_id)
+      .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
+    UserSettingsService::set_weekly_report(
+      &state.db,
+      user_uuid,
+      enabled,
+      format.unwrap_or(ReportFormat::Markdown),
+    )
+    .await
+  })
+}
+
+/// How many daily profile snapshots to keep; 0 stops taking them
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_snapshots_kept(
+  state: State<'_, Arc<AppState>>,
+  user_id: String,
+  kept: i32,
+) -> CommandResult<UserSettingsDto> {
+  instrumented!("set_snapshots_kept", (&user_id, &kept), async {
+    let user_uuid = Uuid::parse_str(&user_id)
+      .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
+    UserSettingsService::set_snapshots_kept(&state.db, user_uuid, kept).await
+  })
+}
+
+/// Sync a declarative profile file whenever it changes; `None` stops watching
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_declarative_watch_path(
+  state: State<'_, Arc<AppState>>,
+  user_id: String,
+  path: Option<String>,
+) -> CommandResult<UserSettingsDto> {
+  instrumented!("set_declarative_watch_path", (&user_id, &path), async {
+    let user_uuid = Uuid::parse_str(&user_id)
+      .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
+    UserSettingsService::set_declarative_watch_path(&state.db, user_uuid, path.as_deref()).await
+  })
+}
+
+/// Use a displayplacer binary that isn't on the PATH; `None` goes back to
+/// looking for it
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_displayplacer_path(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+  path: Option<String>,
+) -> CommandResult<UserSettingsDto> {
+  instrumented!("set_displayplacer_path", (&user_id, &path), async {
+    UserSettingsService::set_displayplacer_path(&state.db, user_id.as_uuid(), path.as_deref()).await
+  })
+}
+
+/// Launch at login, optionally as a background agent without the window
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_login_item(
+  state: State<'_, Arc<AppState>>,
+  user_id: String,
+  launch_at_login: bool,
+  background_agent: bool,
+) -> CommandResult<UserSettingsDto> {
+  instrumented!(
+    "set_login_item",
+    (&user_id, &launch_at_login, &background_agent),
+    async {
+      let user_uuid = Uuid::parse_str(&user_id)
+        .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
+      UserSettingsService::set_login_item(&state.db, user_uuid, launch_at_login, background_agent)
+        .await
+    }
+  )
+}
+
+/// Opt in to encrypted diagnostic uploads after critical errors
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_diagnostic_uploads(
+  state: State<'_, Arc<AppState>>,
+  user_id: String,
+  enabled: bool,
+) -> CommandResult<UserSettingsDto> {
+  instrumented!("set_diagnostic_uploads", (&user_id, &enabled), async {
+    let user_uuid = Uuid::parse_str(&user_id)
+      .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
+    UserSettingsService::set_diagnostic_uploads(&state.db, user_uuid, enabled).await
+  })
+}
+
+/// Default profiles by monitor count, used when no profile is bound to the
+/// connected displays more specifically
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_monitor_profile_mappings(
+  state: State<'_, Arc<AppState>>,
+  user_id: String,
+  mappings: Vec<MonitorProfileMapping>,
+) -> CommandResult<UserSettingsDto> {
+  instrumented!(
+    "set_monitor_profile_mappings",
+    (&user_id, &mappings),
+    async {
+      let user_uuid = Uuid::parse_str(&user_id)
+        .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
+      UserSettingsService::set_monitor_profile_mappings(&state.db, user_uuid, mappings).await
+    }
+  )
+}
+
+/// The canonical spelling of a shortcut and what it clashes with, checked
+/// before it's bound to `profile_id`
+#[tauri::command(rename_all = "camelCase")]
+pub async fn validate_shortcut(
+  state: State<'_, Arc<AppState>>,
+  user_id: String,
+  shortcut: String,
+  profile_id: Option<String>,
+) -> CommandResult<ShortcutValidationDto> {
+  instrumented!(
+    "validate_shortcut",
+    (&user_id, &shortcut, &profile_id),
+    async {
+      let user_uuid = Uuid::parse_str(&user_id)
+        .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
+      UserSettingsService::validate_shortcut(&state.db, user_uuid, &shortcut, profile_id.as_deref())
+        .await
+    }
+  )
+}
+
+/// Bind a shortcut that activates a profile; a null shortcut unbinds it
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_profile_shortcut(
+  state: State<'_, Arc<AppState>>,
+  user_id: String,
+  profile_id: String,
+  shortcut: Option<String>,
+) -> CommandResult<UserSettingsDto> {
+  instrumented!(
+    "set_profile_shortcut",
+    (&user_id, &profile_id, &shortcut),
+    async {
+      let user_uuid = Uuid::parse_str(&user_id)
+        .map_err(|e| SmoothieError::ValidationError(format!("Invalid user ID: {}", e)))?;
+      UserSettingsService::set_profile_shortcut(
+        &state.db,
+        user_uuid,
+        &profile_id,
+        shortcut.as_deref(),
+      )
+      .await
+    }
+  )
+}
+
+/// What the next cloud sync would upload under the user's current scope
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_sync_preview(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+) -> CommandResult<SyncPayload> {
+  instrumented!("get_sync_preview", (&user_id,), async {
+    SyncService::preview(&state.db, user_id).await
+  })
+}
+
+/// Upload what the sync scope allows now; nothing while cloud sync is off
+#[tauri::command(rename_all = "camelCase")]
+pub async fn push_cloud_sync(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+) -> CommandResult<Option<SyncPayload>> {
+  instrumented!("push_cloud_sync", (&user_id,), async {
+    SyncService::push(&state.db, user_id).await
+  })
+}
+
+/// Turn the weekly anonymous usage report on or off
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_telemetry_enabled(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+  enabled: bool,
+) -> CommandResult<UserSettingsDto> {
+  instrumented!("set_telemetry_enabled", (&user_id, &enabled), async {
+    UserSettingsService::set_telemetry_enabled(&state.db, user_id.as_uuid(), enabled).await
+  })
+}
+
+/// Exactly what the next telemetry report would send, whether or not
+/// telemetry is on
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_telemetry_payload_preview(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+) -> CommandResult<TelemetryPayload> {
+  instrumented!("get_telemetry_payload_preview", (&user_id,), async {
+    TelemetryService::preview(&state.db, state.system.clone(), user_id).await
+  })
+}
+
+/// Sync profiles as encrypted bundles through a folder, e.g. in iCloud Drive or
+/// Dropbox, and run the first pass. Every machine needs the same passphrase.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn enable_folder_sync(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+  folder: String,
+  passphrase: String,
+) -> CommandResult<FolderSyncReportDto> {
+  // The passphrase stays out of the command log
+  instrumented!("enable_folder_sync", (&user_id, &folder), async {
+    FolderSyncService::enable(&state.db, user_id, &folder, &passphrase).await
+  })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn disable_folder_sync(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+) -> CommandResult<()> {
+  instrumented!("disable_folder_sync", (&user_id,), async {
+    FolderSyncService::disable(&state.db, user_id).await
+  })
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_folder_sync_status() -> CommandResult<FolderSyncStatusDto> {
+  instrumented!("get_folder_sync_status", async {
+    FolderSyncService::status().await
+  })
+}
+
+/// Sync with the folder now instead of waiting for the background pass
+#[tauri::command(rename_all = "camelCase")]
+pub async fn sync_folder_now(
+  state: State<'_, Arc<AppState>>,
+  user_id: UserId,
+) -> CommandResult<FolderSyncReportDto> {
+  instrumented!("sync_folder_now", (&user_id,), async {
+    FolderSyncService::sync_now(&state.db, user_id).await
+  })
 }
@@ -1,7 +1,9 @@
+use crate::logging::instrumented;
 use crate::{
-  error::Result,
-  models::SuccessResponse,
-  services::window_service::{WindowDto, WindowService},
+  error::CommandResult,
+  services::window_service::{
+    AssignWindowResult, RecoveredWindowsResult, WindowDto, WindowRect, WindowService,
+  },
   state::AppState,
 };
 use std::sync::Arc;
@@ -19,37 +21,59 @@ pub async fn create_window(
   height: i32,
   is_maximized: bool,
   window_state: String,
-) -> Result<SuccessResponse<WindowDto>> {
-  let window = WindowService::create_window(
-    &state.db,
-    &profile_id,
-    &app_id,
-    &monitor_id,
-    x,
-    y,
-    width,
-    height,
-    is_maximized,
-    window_state,
-  )
-  .await?;
+) -> CommandResult<WindowDto> {
+  instrumented!(
+    "create_window",
+    (
+      &profile_id,
+      &app_id,
+      &monitor_id,
+      &x,
+      &y,
+      &width,
+      &height,
+      &is_maximized,
+      &window_state
+    ),
+    async {
+      WindowService::ensure_on_screen(
+        state.system.clone(),
+        WindowRect {
+          x,
+          y,
+          width,
+          height,
+        },
+      )
+      .await?;
+      let window = WindowService::create_window(
+        &state.db,
+        &profile_id,
+        &app_id,
+        &monitor_id,
+        x,
+        y,
+        width,
+        height,
+        is_maximized,
+        window_state,
+      )
+      .await?;
 
-  Ok(SuccessResponse {
-    success: true,
-    data: window,
-  })
+      Ok(window)
+    }
+  )
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn get_windows(
   state: State<'_, Arc<AppState>>,
   profile_id: String,
-) -> Result<SuccessResponse<Vec<WindowDto>>> {
-  let windows = WindowService::get_windows(&state.db, &profile_id).await?;
+) -> CommandResult<Vec<WindowDto>> {
+  instrumented!("get_windows", (&profile_id,), async {
+    let windows = WindowService::get_windows(&state.db, &profile_id).await?;
 
-  Ok(SuccessResponse {
-    success: true,
-    data: windows,
+    Ok(windows)
   })
 }
 
@@ -61,13 +85,39 @@ pub async fn update_window_position(
   y: i32,
   width: i32,
   height: i32,
-) -> Result<SuccessResponse<WindowDto>> {
-  let window =
-    WindowService::update_window_position(&state.db, &window_id, x, y, width, height).await?;
+) -> CommandResult<WindowDto> {
+  instrumented!(
+    "update_window_position",
+    (&window_id, &x, &y, &width, &height),
+    async {
+      WindowService::ensure_on_screen(
+        state.system.clone(),
+        WindowRect {
+          x,
+          y,
+          width,
+          height,
+        },
+      )
+      .await?;
+      let window =
+        WindowService::update_window_position(&state.db, &window_id, x, y, width, height).await?;
 
-  Ok(SuccessResponse {
-    success: true,
-    data: window,
+      Ok(window)
+    }
+  )
+}
+
+/// Open the window zoomed in or out by `zoomSteps` when its profile launches
+/// the app; unset leaves the app's zoom alone
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_window_zoom(
+  state: State<'_, Arc<AppState>>,
+  window_id: String,
+  zoom_steps: Option<i32>,
+) -> CommandResult<WindowDto> {
+  instrumented!("set_window_zoom", (&window_id, &zoom_steps), async {
+    WindowService::set_window_zoom(&state.db, &window_id, zoom_steps).await
   })
 }
 
@@ -75,11 +125,61 @@ pub async fn update_window_position(
 pub async fn delete_window(
   state: State<'_, Arc<AppState>>,
   window_id: String,
-) -> Result<SuccessResponse<String>> {
-  WindowService::delete_window(&state.db, &window_id).await?;
+) -> CommandResult<String> {
+  instrumented!("delete_window", (&window_id,), async {
+    WindowService::delete_window(&state.db, &window_id).await?;
+
+    Ok("Window deleted successfully".to_string())
+  })
+}
+
+/// Record a live window's app and geometry into a profile in one call
+#[tauri::command(rename_all = "camelCase")]
+pub async fn assign_window_to_profile(
+  state: State<'_, Arc<AppState>>,
+  window_id: u32,
+  profile_id: String,
+  target_rect: Option<WindowRect>,
+) -> CommandResult<AssignWindowResult> {
+  instrumented!(
+    "assign_window_to_profile",
+    (&window_id, &profile_id, &target_rect),
+    async {
+      if let Some(rect) = target_rect {
+        WindowService::ensure_on_screen(state.system.clone(), rect).await?;
+      }
+      let result = WindowService::assign_window_to_profile(
+        &state.db,
+        state.system.clone(),
+        &profile_id,
+        window_id,
+        target_rect,
+      )
+      .await?;
+
+      state.invalidate_cache(&format!("apps_{}", profile_id));
+      state.invalidate_cache(&format!("monitors_{}", profile_id));
+      state.invalidate_cache(&format!("profile_{}", profile_id));
+
+      Ok(result)
+    }
+  )
+}
+
+/// Pull saved windows stranded on disconnected displays onto the primary display
+#[tauri::command(rename_all = "camelCase")]
+pub async fn recover_offscreen_windows(
+  state: State<'_, Arc<AppState>>,
+  profile_id: String,
+) -> CommandResult<RecoveredWindowsResult> {
+  instrumented!("recover_offscreen_windows", (&profile_id,), async {
+    let result =
+      WindowService::recover_offscreen_windows(&state.db, state.system.clone(), &profile_id)
+        .await?;
+
+    state.invalidate_cache(&format!("monitors_{}", profile_id));
+    state.invalidate_cache(&format!("profile_{}", profile_id));
 
-  Ok(SuccessResponse {
-    success: true,
-    data: "Window deleted successfully".to_string(),
+    Ok(result)
   })
 }
@@ -3,8 +3,10 @@ pub mod error;
 pub mod handlers;
 pub mod logging;
 pub mod models;
+pub mod query;
 pub mod repositories;
 pub mod security;
 pub mod services;
 pub mod state;
+pub mod storage;
 pub mod utils;
@@ -1,21 +1,150 @@
 // Structured logging with tracing
 
+use super::SlowQueryLayer;
+use crate::error::{Result, SmoothieError};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// Current global level and per-target overrides applied on top of RUST_LOG
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLevels {
+  pub level: String,
+  pub targets: BTreeMap<String, String>,
+}
+
+struct FilterState {
+  handle: reload::Handle<EnvFilter, Registry>,
+  level: LevelFilter,
+  targets: BTreeMap<String, LevelFilter>,
+}
+
+static FILTER: OnceLock<Mutex<FilterState>> = OnceLock::new();
+
 pub struct SmoothieLogger;
 
 impl SmoothieLogger {
   /// Initialize logging system with file and console output
   pub fn init() {
-    tracing_subscriber::fmt()
-      .with_env_filter(
-        tracing_subscriber::EnvFilter::from_default_env()
-          .add_directive(tracing_subscriber::filter::LevelFilter::INFO.into()),
+    Self::install(std::io::stdout);
+  }
+
+  /// Log to stderr instead, for modes whose stdout is machine-read
+  pub fn init_stderr() {
+    Self::install(std::io::stderr);
+  }
+
+  fn install<W>(writer: W)
+  where
+    W: for<'w> tracing_subscriber::fmt::MakeWriter<'w> + Send + Sync + 'static,
+  {
+    let (filter, handle) = reload::Layer::new(build_filter(LevelFilter::INFO, &BTreeMap::new()));
+
+    tracing_subscriber::registry()
+      .with(filter)
+      .with(
+        tracing_subscriber::fmt::layer()
+          .with_writer(writer)
+          .with_file(true)
+          .with_line_number(true)
+          .with_thread_ids(true)
+          .with_target(true),
       )
-      .with_file(true)
-      .with_line_number(true)
-      .with_thread_ids(true)
-      .with_target(true)
+      .with(SlowQueryLayer)
       .init();
 
+    FILTER.get_or_init(|| {
+      Mutex::new(FilterState {
+        handle,
+        level: LevelFilter::INFO,
+        targets: BTreeMap::new(),
+      })
+    });
+
     tracing::info!("Smoothie logging initialized");
   }
+
+  /// Change the log level without restarting.
+  ///
+  /// With a `target` (e.g. `smoothie_lib::services`) only that module's level
+  /// changes; without one the global level does. Passing `"reset"` for a
+  /// target removes its override.
+  pub fn set_log_level(level: &str, target: Option<&str>) -> Result<LogLevels> {
+    let state = FILTER
+      .get()
+      .ok_or_else(|| SmoothieError::SystemError("Logging is not initialized".into()))?;
+    let mut state = state.lock();
+
+    let target = target.map(str::trim).filter(|t| !t.is_empty());
+    match target {
+      Some(target) if level.eq_ignore_ascii_case("reset") => {
+        state.targets.remove(target);
+      }
+      Some(target) => {
+        if target.contains(|c: char| c == '=' || c == ',' || c.is_whitespace()) {
+          return Err(SmoothieError::ValidationError(format!(
+            "Invalid log target: {}",
+            target
+          )));
+        }
+        let level = parse_level(level)?;
+        state.targets.insert(target.to_string(), level);
+      }
+      None => state.level = parse_level(level)?,
+    }
+
+    state
+      .handle
+      .reload(build_filter(state.level, &state.targets))
+      .map_err(|e| SmoothieError::SystemError(format!("Failed to update log filter: {}", e)))?;
+
+    tracing::info!(level = %state.level, ?target, "Log level changed");
+
+    Ok(LogLevels {
+      level: state.level.to_string(),
+      targets: state
+        .targets
+        .iter()
+        .map(|(target, level)| (target.clone(), level.to_string()))
+        .collect(),
+    })
+  }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter> {
+  level
+    .trim()
+    .parse::<LevelFilter>()
+    .map_err(|_| SmoothieError::ValidationError(format!("Invalid log level: {}", level)))
+}
+
+fn build_filter(level: LevelFilter, targets: &BTreeMap<String, LevelFilter>) -> EnvFilter {
+  let mut filter = EnvFilter::from_default_env().add_directive(level.into());
+  for (target, level) in targets {
+    // Targets are validated before they're stored, so the directive always parses
+    if let Ok(directive) = format!("{}={}", target, level).parse() {
+      filter = filter.add_directive(directive);
+    }
+  }
+  filter
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_level_accepts_known_levels_only() {
+    assert_eq!(parse_level("DEBUG").unwrap(), LevelFilter::DEBUG);
+    assert_eq!(parse_level(" off ").unwrap(), LevelFilter::OFF);
+    assert!(matches!(
+      parse_level("verbose"),
+      Err(SmoothieError::ValidationError(_))
+    ));
+  }
 }
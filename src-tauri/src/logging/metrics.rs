@@ -1,9 +1,42 @@
 // Application metrics and performance monitoring
 
+use super::request;
+use crate::error::{CommandError, CommandResult, Result, SmoothieError};
+use crate::models::entities::{ActivityLogEntity, SystemEventEntity};
+use crate::models::{ActivityStatus, Severity, SuccessResponse, UserId};
+use crate::security::middleware::{self, RATE_LIMITER};
+use crate::services::audit_queue::{AuditQueue, AuditRecord};
+use crate::state::startup::Startup;
 use chrono::Utc;
 use lazy_static::lazy_static;
-use std::sync::atomic::{AtomicU64, Ordering};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Latency samples kept per command for the p95 estimate
+const COMMAND_SAMPLE_WINDOW: usize = 128;
+
+/// Commands slower than this are logged as they finish
+const SLOW_COMMAND_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Upper bounds in ms of the histogram buckets; a last bucket takes the rest
+const HISTOGRAM_BOUNDS_MS: [f64; 15] = [
+  1.0, 2.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1_000.0, 2_500.0, 5_000.0, 10_000.0,
+  30_000.0, 60_000.0,
+];
+
+/// Whole profile activation pipeline runs
+pub const ACTIVATION_DURATION: &str = "activation_duration";
+/// Launch until the app's first window shows up
+pub const APP_LAUNCH_DURATION: &str = "app_launch_duration";
+/// Every IPC command, from invocation to response
+pub const IPC_LATENCY: &str = "ipc_latency";
 
 lazy_static! {
   pub static ref METRICS: Arc<AppMetrics> = Arc::new(AppMetrics::new());
@@ -16,7 +49,105 @@ pub struct AppMetrics {
   pub total_windows_managed: AtomicU64,
   pub total_automations_triggered: AtomicU64,
   pub total_errors: AtomicU64,
+  pub total_timeouts: AtomicU64,
+  pub total_slow_queries: AtomicU64,
   pub startup_time: std::time::Instant,
+  commands: Mutex<HashMap<&'static str, CommandStats>>,
+  histograms: Mutex<BTreeMap<&'static str, Histogram>>,
+  audit_commands: AtomicBool,
+}
+
+#[derive(Default)]
+struct CommandStats {
+  calls: u64,
+  failures: u64,
+  total: Duration,
+  max: Duration,
+  arg_bytes: u64,
+  recent: VecDeque<Duration>,
+}
+
+/// Aggregated timings for one IPC command
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandStatsSnapshot {
+  pub command: String,
+  pub calls: u64,
+  pub failures: u64,
+  pub avg_ms: f64,
+  pub p95_ms: f64,
+  pub max_ms: f64,
+  pub avg_arg_bytes: u64,
+}
+
+/// Durations counted into fixed buckets, so the percentiles cover the whole
+/// session in constant memory
+#[derive(Clone, Default)]
+struct Histogram {
+  buckets: [u64; HISTOGRAM_BOUNDS_MS.len() + 1],
+  count: u64,
+  total: Duration,
+  max: Duration,
+}
+
+impl Histogram {
+  fn observe(&mut self, value: Duration) {
+    let ms = as_ms(value);
+    let bucket = HISTOGRAM_BOUNDS_MS
+      .iter()
+      .position(|bound| ms <= *bound)
+      .unwrap_or(HISTOGRAM_BOUNDS_MS.len());
+    self.buckets[bucket] += 1;
+    self.count += 1;
+    self.total += value;
+    self.max = self.max.max(value);
+  }
+
+  /// Estimate of quantile `q`, interpolated within the bucket it falls in
+  fn quantile(&self, q: f64) -> f64 {
+    let max_ms = as_ms(self.max);
+    let rank = (q * self.count as f64).ceil().max(1.0);
+    let mut seen = 0;
+    for (index, &count) in self.buckets.iter().enumerate() {
+      if count == 0 {
+        continue;
+      }
+      if (seen + count) as f64 >= rank {
+        let lower = index.checked_sub(1).map_or(0.0, |i| HISTOGRAM_BOUNDS_MS[i]);
+        let upper = HISTOGRAM_BOUNDS_MS.get(index).copied().unwrap_or(max_ms);
+        let fraction = (rank - seen as f64) / count as f64;
+        return (lower + (upper - lower) * fraction).min(max_ms);
+      }
+      seen += count;
+    }
+    max_ms
+  }
+
+  fn snapshot(&self) -> HistogramSnapshot {
+    HistogramSnapshot {
+      count: self.count,
+      avg_ms: as_ms(self.total) / self.count.max(1) as f64,
+      p50_ms: self.quantile(0.50),
+      p95_ms: self.quantile(0.95),
+      p99_ms: self.quantile(0.99),
+      max_ms: as_ms(self.max),
+      buckets: self.buckets.to_vec(),
+    }
+  }
+}
+
+/// Distribution of one kind of duration since startup
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistogramSnapshot {
+  pub count: u64,
+  pub avg_ms: f64,
+  pub p50_ms: f64,
+  pub p95_ms: f64,
+  pub p99_ms: f64,
+  pub max_ms: f64,
+  /// Counts per bucket of `HISTOGRAM_BOUNDS_MS`, then everything slower
+  pub buckets: Vec<u64>,
 }
 
 impl AppMetrics {
@@ -28,7 +159,12 @@ impl AppMetrics {
       total_windows_managed: AtomicU64::new(0),
       total_automations_triggered: AtomicU64::new(0),
       total_errors: AtomicU64::new(0),
+      total_timeouts: AtomicU64::new(0),
+      total_slow_queries: AtomicU64::new(0),
       startup_time: std::time::Instant::now(),
+      commands: Mutex::new(HashMap::new()),
+      histograms: Mutex::new(BTreeMap::new()),
+      audit_commands: AtomicBool::new(false),
     }
   }
 
@@ -62,6 +198,86 @@ impl AppMetrics {
     self.total_errors.fetch_add(1, Ordering::SeqCst);
   }
 
+  pub fn record_slow_query(&self) {
+    self.total_slow_queries.fetch_add(1, Ordering::SeqCst);
+  }
+
+  pub fn record_command(
+    &self,
+    command: &'static str,
+    elapsed: Duration,
+    ok: bool,
+    arg_bytes: usize,
+  ) {
+    let mut commands = self.commands.lock();
+    let stats = commands.entry(command).or_default();
+    stats.calls += 1;
+    if !ok {
+      stats.failures += 1;
+    }
+    stats.total += elapsed;
+    stats.max = stats.max.max(elapsed);
+    stats.arg_bytes += arg_bytes as u64;
+    if stats.recent.len() == COMMAND_SAMPLE_WINDOW {
+      stats.recent.pop_front();
+    }
+    stats.recent.push_back(elapsed);
+    drop(commands);
+    self.observe(IPC_LATENCY, elapsed);
+  }
+
+  /// Add a sample to the `name` histogram, e.g. [`ACTIVATION_DURATION`]
+  pub fn observe(&self, name: &'static str, value: Duration) {
+    self
+      .histograms
+      .lock()
+      .entry(name)
+      .or_default()
+      .observe(value);
+  }
+
+  pub fn get_histograms(&self) -> BTreeMap<&'static str, HistogramSnapshot> {
+    self
+      .histograms
+      .lock()
+      .iter()
+      .map(|(name, histogram)| (*name, histogram.snapshot()))
+      .collect()
+  }
+
+  /// Per-command timings, slowest (by p95) first
+  pub fn get_command_stats(&self, limit: usize) -> Vec<CommandStatsSnapshot> {
+    let commands = self.commands.lock();
+    let mut snapshots: Vec<CommandStatsSnapshot> = commands
+      .iter()
+      .map(|(command, stats)| {
+        let mut recent: Vec<Duration> = stats.recent.iter().copied().collect();
+        recent.sort();
+        let p95 = recent
+          .get((recent.len() * 95 / 100).min(recent.len().saturating_sub(1)))
+          .copied()
+          .unwrap_or_default();
+        CommandStatsSnapshot {
+          command: command.to_string(),
+          calls: stats.calls,
+          failures: stats.failures,
+          avg_ms: as_ms(stats.total) / stats.calls.max(1) as f64,
+          p95_ms: as_ms(p95),
+          max_ms: as_ms(stats.max),
+          avg_arg_bytes: stats.arg_bytes / stats.calls.max(1),
+        }
+      })
+      .collect();
+    snapshots.sort_by(|a, b| b.p95_ms.total_cmp(&a.p95_ms));
+    snapshots.truncate(limit);
+    snapshots
+  }
+
+  /// Also write every command invocation to the activity log
+  pub fn set_command_auditing(&self, enabled: bool) {
+    self.audit_commands.store(enabled, Ordering::Relaxed);
+  }
+
   pub fn get_uptime_secs(&self) -> u64 {
     self.startup_time.elapsed().as_secs()
   }
@@ -75,13 +291,219 @@ impl AppMetrics {
         "total_windows_managed": self.total_windows_managed.load(Ordering::SeqCst),
         "total_automations_triggered": self.total_automations_triggered.load(Ordering::SeqCst),
         "total_errors": self.total_errors.load(Ordering::SeqCst),
+        "total_timeouts": self.total_timeouts.load(Ordering::SeqCst),
+        "total_slow_queries": self.total_slow_queries.load(Ordering::SeqCst),
+        "histograms": self.get_histograms(),
         "timestamp": Utc::now().to_rfc3339()
     })
   }
+
+  /// The session's counters and histograms as a system event, written on
+  /// shutdown so they outlive the process
+  pub fn rollup_event(&self) -> SystemEventEntity {
+    let calls: u64 = self.commands.lock().values().map(|stats| stats.calls).sum();
+    SystemEventEntity {
+      id: Uuid::new_v4(),
+      event_type: "metrics_rollup".to_string(),
+      severity: Severity::Info,
+      source: "AppMetrics".to_string(),
+      message: format!(
+        "Session metrics: {} commands over {}s",
+        calls,
+        self.get_uptime_secs()
+      ),
+      details: Some(self.get_summary()),
+      stack_trace: None,
+      os_info: None,
+      app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+      request_id: None,
+      created_at: Utc::now(),
+    }
+  }
+}
+
+fn as_ms(duration: Duration) -> f64 {
+  duration.as_secs_f64() * 1000.0
+}
+
+/// Serialized size of a command's arguments, as a proxy for the IPC payload
+pub fn payload_size<T: Serialize + ?Sized>(args: &T) -> usize {
+  serde_json::to_vec(args)
+    .map(|bytes| bytes.len())
+    .unwrap_or(0)
+}
+
+/// Run a command body under a fresh request id, recording the outcome and
+/// wrapping it for the frontend; called by [`instrumented!`]
+pub async fn run_command<T: Serialize>(
+  command: &'static str,
+  arg_bytes: usize,
+  body: impl Future<Output = Result<T>>,
+) -> CommandResult<T> {
+  let started = Instant::now();
+  let request_id = request::new_request_id();
+  let span = tracing::info_span!("command", command, request_id = %request_id);
+  let result = match Startup::check(command)
+    .and_then(|()| RATE_LIMITER.check(command))
+    .and_then(|()| middleware::check_read_only(command))
+  {
+    Ok(()) => request::with_request_id(request_id.clone(), body.instrument(span)).await,
+    Err(e) => Err(e),
+  };
+
+  let elapsed = started.elapsed();
+  record_finished(command, &request_id, elapsed, arg_bytes, &result);
+  match result {
+    Ok(data) => Ok(SuccessResponse {
+      success: true,
+      data,
+      request_id,
+      duration_ms: elapsed.as_millis() as u64,
+    }),
+    Err(error) => Err(CommandError { request_id, error }),
+  }
 }
 
+fn record_finished<T>(
+  command: &'static str,
+  request_id: &str,
+  elapsed: Duration,
+  arg_bytes: usize,
+  result: &Result<T>,
+) {
+  METRICS.record_command(command, elapsed, result.is_ok(), arg_bytes);
+  if matches!(result, Err(SmoothieError::Timeout(_))) {
+    METRICS.total_timeouts.fetch_add(1, Ordering::SeqCst);
+  }
+  if elapsed >= SLOW_COMMAND_THRESHOLD {
+    tracing::warn!(
+      command,
+      request_id,
+      elapsed_ms = elapsed.as_millis() as u64,
+      "Slow command"
+    );
+  }
+
+  // Auditing throttled calls would put the flood right back into the activity
+  // log, and calls made while starting up are retried
+  let throttled = matches!(
+    result,
+    Err(SmoothieError::RateLimited(_) | SmoothieError::StartingUp(_))
+  );
+  match result {
+    Err(e) if throttled => tracing::debug!(command, request_id, "{}", e),
+    Err(e) => tracing::warn!(command, request_id, error = %e, "Command failed"),
+    Ok(_) => {}
+  }
+  if throttled || !METRICS.audit_commands.load(Ordering::Relaxed) {
+    return;
+  }
+  let Some(queue) = AuditQueue::global() else {
+    return;
+  };
+  let error = result.as_ref().err();
+  let record = AuditRecord::Activity(ActivityLogEntity {
+    id: Uuid::new_v4(),
+    user_id: UserId::DEFAULT.as_uuid(),
+    session_id: None,
+    action: "command_invoked".to_string(),
+    entity_type: Some("command".to_string()),
+    entity_id: None,
+    entity_name: Some(command.to_string()),
+    details: Some(serde_json::json!({ "arg_bytes": arg_bytes })),
+    ip_address: None,
+    user_agent: None,
+    status: if error.is_some() {
+      ActivityStatus::Failure
+    } else {
+      ActivityStatus::Success
+    },
+    error_message: error.map(|e| e.to_string()),
+    duration_ms: Some(elapsed.as_millis().min(i32::MAX as u128) as i32),
+    request_id: Some(request_id.to_string()),
+    created_at: Utc::now(),
+  });
+  tokio::spawn(async move {
+    queue.push(record).await;
+  });
+}
+
+/// Run a command body, recording its latency, outcome and argument size in [`METRICS`].
+/// Calls before startup is done or over the command's rate limit fail without
+/// running the body. The body
+/// returns `Result<T>`; the command gets it back as a [`CommandResult`].
+///
+/// The second argument is the command's payload (everything but `State`), e.g.
+/// `instrumented!("get_profiles", (&user_id,), async { ... })`.
+macro_rules! instrumented {
+  ($command:literal, $args:expr, $body:expr) => {
+    $crate::logging::metrics::run_command(
+      $command,
+      $crate::logging::metrics::payload_size(&$args),
+      $body,
+    )
+    .await
+  };
+  ($command:literal, $body:expr) => {
+    $crate::logging::metrics::run_command($command, 0, $body).await
+  };
+}
+
+pub(crate) use instrumented;
+
 impl Default for AppMetrics {
   fn default() -> Self {
     Self::new()
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_command_stats_sorted_by_p95() {
+    let metrics = AppMetrics::new();
+    for ms in [5, 7, 9] {
+      metrics.record_command("get_profiles", Duration::from_millis(ms), true, 40);
+    }
+    metrics.record_command(
+      "apply_monitor_layout",
+      Duration::from_millis(300),
+      false,
+      10,
+    );
+
+    let stats = metrics.get_command_stats(10);
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats[0].command, "apply_monitor_layout");
+    assert_eq!(stats[0].failures, 1);
+    assert_eq!(stats[1].calls, 3);
+    assert_eq!(stats[1].avg_ms, 7.0);
+    assert_eq!(stats[1].p95_ms, 9.0);
+    assert_eq!(stats[1].avg_arg_bytes, 40);
+
+    assert_eq!(metrics.get_command_stats(1).len(), 1);
+    assert_eq!(metrics.get_histograms()[IPC_LATENCY].count, 4);
+  }
+
+  #[test]
+  fn test_histogram_percentiles() {
+    let metrics = AppMetrics::new();
+    for ms in 1..=100 {
+      metrics.observe(ACTIVATION_DURATION, Duration::from_millis(ms));
+    }
+    let snapshot = &metrics.get_histograms()[ACTIVATION_DURATION];
+    assert_eq!(snapshot.count, 100);
+    assert_eq!(snapshot.p50_ms, 50.0);
+    assert_eq!(snapshot.p95_ms, 95.0);
+    assert_eq!(snapshot.p99_ms, 99.0);
+    assert_eq!(snapshot.max_ms, 100.0);
+
+    // Past the last bound the estimate can't go beyond the slowest sample
+    metrics.observe(APP_LAUNCH_DURATION, Duration::from_secs(90));
+    let launch = &metrics.get_histograms()[APP_LAUNCH_DURATION];
+    assert_eq!(launch.p99_ms, 90_000.0);
+    assert_eq!(launch.buckets.last(), Some(&1));
+  }
+}
@@ -2,6 +2,10 @@
 
 pub mod logger;
 pub mod metrics;
+pub mod request;
+pub mod slow_query;
 
 pub use logger::*;
 pub use metrics::*;
+pub use request::current_request_id;
+pub use slow_query::SlowQueryLayer;
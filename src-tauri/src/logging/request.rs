@@ -0,0 +1,40 @@
+// Request correlation - every IPC call gets an id that follows it through the
+// tracing span, the audit rows it writes and the error it returns, so a failure
+// a user reports can be found in the backend logs.
+//
+// The id lives in a task-local; work moved onto another task with
+// `tokio::spawn` does not inherit it.
+
+use std::future::Future;
+use uuid::Uuid;
+
+tokio::task_local! {
+  static REQUEST_ID: String;
+}
+
+pub fn new_request_id() -> String {
+  Uuid::new_v4().to_string()
+}
+
+/// Id of the command running on the current task, if any
+pub fn current_request_id() -> Option<String> {
+  REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Run `future` as part of request `request_id`
+pub async fn with_request_id<F: Future>(request_id: String, future: F) -> F::Output {
+  REQUEST_ID.scope(request_id, future).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_request_id_is_scoped_to_the_call() {
+    assert_eq!(current_request_id(), None);
+    let seen = with_request_id("req-1".into(), async { current_request_id() }).await;
+    assert_eq!(seen.as_deref(), Some("req-1"));
+    assert_eq!(current_request_id(), None);
+  }
+}
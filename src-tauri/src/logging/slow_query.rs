@@ -0,0 +1,195 @@
+// Slow query reporting
+//
+// sqlx warns about statements that run past the pool's slow-query threshold
+// (`SMOOTHIE_DB_SLOW_QUERY_MS`). This layer picks those warnings up, counts
+// them in METRICS and writes them to system_events, so a regression shows up
+// in the event log without anyone turning on debug logging. sqlx only logs
+// the SQL text, never the bound values; literals written into the SQL are
+// redacted as well before it's stored.
+
+use super::{current_request_id, METRICS};
+use crate::models::entities::SystemEventEntity;
+use crate::models::Severity;
+use crate::services::audit_queue::{AuditQueue, AuditRecord};
+use chrono::Utc;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use uuid::Uuid;
+
+const SQLX_QUERY_TARGET: &str = "sqlx::query";
+
+/// The same statement is written to system_events at most this often
+const REPORT_GAP: Duration = Duration::from_secs(60);
+
+/// Statements touching this table are counted but not written back into it,
+/// which would report the audit writer's own inserts in a loop
+const EVENTS_TABLE: &str = "system_events";
+
+lazy_static! {
+  static ref STRING_LITERAL: Regex = Regex::new(r"'(?:[^']|'')*'").unwrap();
+  // Not `$1` placeholders or digits inside identifiers like `v2_profiles`
+  static ref NUMBER_LITERAL: Regex = Regex::new(r"(^|[^$\w.])\d+(?:\.\d+)?\b").unwrap();
+  static ref LAST_REPORTED: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+}
+
+/// Replace string and number literals in `sql` with `?`
+pub fn redact_statement(sql: &str) -> String {
+  let sql = STRING_LITERAL.replace_all(sql.trim(), "'?'");
+  NUMBER_LITERAL.replace_all(&sql, "${1}?").into_owned()
+}
+
+#[derive(Default)]
+struct SlowQuery {
+  summary: String,
+  statement: String,
+  elapsed_secs: f64,
+  rows_returned: u64,
+  rows_affected: u64,
+}
+
+impl Visit for SlowQuery {
+  fn record_str(&mut self, field: &Field, value: &str) {
+    match field.name() {
+      "summary" => self.summary = value.to_string(),
+      "db.statement" => self.statement = value.trim().to_string(),
+      _ => {}
+    }
+  }
+
+  fn record_f64(&mut self, field: &Field, value: f64) {
+    if field.name() == "elapsed_secs" {
+      self.elapsed_secs = value;
+    }
+  }
+
+  fn record_u64(&mut self, field: &Field, value: u64) {
+    match field.name() {
+      "rows_returned" => self.rows_returned = value,
+      "rows_affected" => self.rows_affected = value,
+      _ => {}
+    }
+  }
+
+  fn record_debug(&mut self, _field: &Field, _value: &dyn fmt::Debug) {}
+}
+
+impl SlowQuery {
+  /// Short statements are only logged as their summary
+  fn statement(&self) -> &str {
+    if self.statement.is_empty() {
+      &self.summary
+    } else {
+      &self.statement
+    }
+  }
+
+  fn to_event(&self) -> SystemEventEntity {
+    let elapsed_ms = (self.elapsed_secs * 1000.0).round() as u64;
+    let summary = redact_statement(self.summary.trim_end_matches(" …"));
+    SystemEventEntity {
+      id: Uuid::new_v4(),
+      event_type: "slow_query".to_string(),
+      severity: Severity::Warning,
+      source: "Database".to_string(),
+      message: format!("Slow query ({}ms): {}", elapsed_ms, summary),
+      details: Some(serde_json::json!({
+        "statement": redact_statement(self.statement()),
+        "elapsed_ms": elapsed_ms,
+        "rows_returned": self.rows_returned,
+        "rows_affected": self.rows_affected,
+      })),
+      stack_trace: None,
+      os_info: None,
+      app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+      request_id: current_request_id(),
+      created_at: Utc::now(),
+    }
+  }
+}
+
+/// Claim the report for `statement` unless it was reported within `REPORT_GAP`
+fn claim_report(statement: &str) -> bool {
+  let mut last = LAST_REPORTED.lock();
+  if last
+    .get(statement)
+    .is_some_and(|at| at.elapsed() < REPORT_GAP)
+  {
+    return false;
+  }
+  last.retain(|_, at| at.elapsed() < REPORT_GAP);
+  last.insert(statement.to_string(), Instant::now());
+  true
+}
+
+/// Tracing layer that reports sqlx's slow statement warnings
+pub struct SlowQueryLayer;
+
+impl<S: Subscriber> Layer<S> for SlowQueryLayer {
+  fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+    let metadata = event.metadata();
+    // Only sqlx's slow statement warning carries the threshold
+    if metadata.target() != SQLX_QUERY_TARGET || metadata.fields().field("slow_threshold").is_none()
+    {
+      return;
+    }
+    let mut query = SlowQuery::default();
+    event.record(&mut query);
+    METRICS.record_slow_query();
+
+    let statement = redact_statement(query.statement());
+    if statement.contains(EVENTS_TABLE) || !claim_report(&statement) {
+      return;
+    }
+    let (Some(queue), Ok(runtime)) = (AuditQueue::global(), tokio::runtime::Handle::try_current())
+    else {
+      return;
+    };
+    let record = AuditRecord::SystemEvent(query.to_event());
+    runtime.spawn(async move {
+      queue.push(record).await;
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::Ordering;
+  use tracing_subscriber::prelude::*;
+
+  #[test]
+  fn test_slow_statements_are_counted_and_redacted() {
+    assert_eq!(
+      redact_statement(
+        "SELECT * FROM v2_profiles WHERE name = 'Jane''s' AND id = $1 AND score > 0.5 LIMIT 20"
+      ),
+      "SELECT * FROM v2_profiles WHERE name = '?' AND id = $1 AND score > ? LIMIT ?"
+    );
+
+    let before = METRICS.total_slow_queries.load(Ordering::SeqCst);
+    let subscriber = tracing_subscriber::registry().with(SlowQueryLayer);
+    tracing::subscriber::with_default(subscriber, || {
+      tracing::warn!(
+        target: "sqlx::query",
+        summary = "SELECT pg_sleep(2)",
+        db.statement = "",
+        elapsed_secs = 2.0,
+        slow_threshold = ?Duration::from_millis(500),
+        "slow statement: execution time exceeded alert threshold"
+      );
+      tracing::debug!(target: "sqlx::query", summary = "SELECT 1", elapsed_secs = 0.001);
+    });
+    assert!(METRICS.total_slow_queries.load(Ordering::SeqCst) > before);
+
+    // The layer reported it just now, under its redacted form
+    assert!(!claim_report("SELECT pg_sleep(?)"));
+    assert!(claim_report("SELECT pg_sleep(?) AS waited"));
+  }
+}
@@ -8,48 +8,202 @@ mod error;
 mod handlers;
 mod logging;
 mod models;
+mod query;
 mod repositories;
 mod security;
 mod services;
 mod state;
+mod storage;
 mod utils;
 
 use db::Database;
 use logging::{SmoothieLogger, METRICS};
-use services::AUDIT_SERVICE;
+use models::{Severity, UserId};
+use services::audit_backend::AuditBackend;
+use services::audit_queue::{AuditQueue, AuditQueueConfig, AuditRecord, PushOutcome};
+use services::system_service::SystemService;
+use services::{
+  AlertService, AppBlocklistService, AuditService, AutomationService, ConnectionMonitor,
+  DeclarativeService, FocusSessionService, FocusTracker, FolderSyncService, LoginItemService,
+  ProfileResolver, ReconciliationService, ResourceGuard, SnapshotService, StorageService,
+  TelemetryService, UpdateService, UsageReportService, UserSettingsService, WarmupService,
+  AUDIT_SERVICE,
+};
+use state::startup::{Startup, StartupStage};
 use state::AppState;
 use std::sync::Arc;
+use tauri::{Emitter, Manager};
+
+/// Show and focus the main window, which starts hidden
+fn show_main_window(app: &tauri::AppHandle) -> tauri::Result<()> {
+  if let Some(window) = app.get_webview_window("main") {
+    window.show()?;
+    window.set_focus()?;
+  }
+  Ok(())
+}
+
+/// Opening the app again while it runs in the background brings the window back
+#[cfg(target_os = "macos")]
+fn show_on_reopen(app: &tauri::AppHandle, event: &tauri::RunEvent) {
+  if let tauri::RunEvent::Reopen { .. } = event {
+    if let Err(e) = show_main_window(app) {
+      tracing::warn!("Failed to show the main window: {}", e);
+    }
+  }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn show_on_reopen(_app: &tauri::AppHandle, _event: &tauri::RunEvent) {}
+
+/// Tell the webview whenever the database connection drops or comes back
+fn forward_connection_events(app: tauri::AppHandle) {
+  let mut changes = ConnectionMonitor::subscribe();
+  tokio::spawn(async move {
+    while changes.changed().await.is_ok() {
+      let status = changes.borrow_and_update().clone();
+      if let Err(e) = app.emit(services::connection_monitor::CONNECTION_EVENT, status) {
+        tracing::warn!("Failed to send the connection status: {}", e);
+      }
+    }
+  });
+}
 
 #[tokio::main]
 async fn main() {
   // Load environment variables from .env file
   dotenv::dotenv().ok();
 
+  // `smoothie query ...` answers launcher extensions instead of opening the app
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  if args.first().map(String::as_str) == Some("query") {
+    SmoothieLogger::init_stderr();
+    let db = match Database::new().await {
+      Ok(db) => Arc::new(db),
+      Err(e) => {
+        tracing::error!("Failed to initialize database: {}", e);
+        std::process::exit(1);
+      }
+    };
+    std::process::exit(query::run(db, &args[1..]).await);
+  }
+
   // Initialize logging system
   SmoothieLogger::init();
 
   tracing::info!("=== Smoothie Desktop Application Starting ===");
+  Startup::begin(StartupStage::Config);
+
+  // Opt-in: record every IPC command in the activity log, not just METRICS
+  if std::env::var("SMOOTHIE_AUDIT_COMMANDS").is_ok_and(|v| v == "1" || v == "true") {
+    METRICS.set_command_auditing(true);
+  }
+
+  // Override the default per-command rate limits
+  if let Ok(spec) = std::env::var(security::middleware::RATE_LIMITS_ENV) {
+    if let Err(e) = security::middleware::RATE_LIMITER.configure(&spec) {
+      tracing::warn!("Ignoring rate limit config: {}", e);
+    }
+  }
+
+  // Kiosk and shared machines: activate only, never edit
+  if std::env::var(security::middleware::READ_ONLY_ENV).is_ok_and(|v| v == "1" || v == "true") {
+    security::middleware::set_read_only(true);
+  }
 
   // Initialize database
-  let db = Database::new()
+  Startup::begin(StartupStage::Database);
+  let db = Database::connect()
     .await
     .expect("Failed to initialize database");
+  Startup::begin(StartupStage::Migrations);
+  db.migrate()
+    .await
+    .expect("Failed to run database migrations");
   let db = Arc::new(db);
 
+  Startup::begin(StartupStage::AuditSession);
+
+  // Copy audit writes to Supabase as well as Postgres, if configured
+  match AuditBackend::from_env() {
+    Ok(backend) => backend.install(),
+    Err(e) => tracing::warn!("{}; keeping audit logs in Postgres", e),
+  }
+
+  // Batch audit writes in the background instead of awaiting each INSERT
+  AuditQueue::start(db.pool().clone(), AuditQueueConfig::default());
+
+  // Close out sessions, activations and flags an unclean shutdown left open.
+  // Its cutoff is taken here, before this launch's session starts.
+  ReconciliationService::spawn(db.clone());
+
+  // Start a session before anything can log activity against it
+  if let Err(e) = AUDIT_SERVICE
+    .start_session(&db, "00000000-0000-0000-0000-000000000001", None)
+    .await
+  {
+    tracing::warn!("Failed to start session: {}", e);
+  }
+
   // Create app state
   let app_state = AppState::new(db.clone());
   let app_state = Arc::new(app_state);
 
-  // Start a session
-  let db_clone = db.clone();
-  tokio::spawn(async move {
-    if let Err(e) = AUDIT_SERVICE
-      .start_session(&db_clone, "00000000-0000-0000-0000-000000000001", None)
-      .await
-    {
-      tracing::warn!("Failed to start session: {}", e);
-    }
-  });
+  Startup::begin(StartupStage::Watchers);
+
+  // Slow background polling down on battery and under load
+  ResourceGuard::spawn(app_state.clone());
+
+  // Notice when Postgres goes away or comes back
+  ConnectionMonitor::spawn(app_state.clone());
+
+  // Prefetch likely profiles and the installed-app list before the first activation
+  WarmupService::spawn(app_state.clone(), UserId::DEFAULT.as_uuid());
+
+  // Evaluate alert rules in the background
+  AlertService::spawn_alert_monitor(app_state.clone(), UserId::DEFAULT.as_uuid());
+
+  // Fire power-source automation rules on battery/AC switches
+  AutomationService::spawn_power_watcher(app_state.clone(), UserId::DEFAULT.as_uuid());
+
+  // Fire device automation rules when docks, peripherals and headphones come and go
+  AutomationService::spawn_device_watcher(app_state.clone(), UserId::DEFAULT.as_uuid());
+
+  // Hide or quit the active profile's blocked apps when they're launched
+  AppBlocklistService::spawn_watcher(app_state.clone(), UserId::DEFAULT.as_uuid());
+
+  // Time spent in each foreground app while a profile is active
+  FocusTracker::spawn(app_state.clone(), UserId::DEFAULT.as_uuid());
+
+  // Enforce focus session blocklists and complete sessions when their time is up
+  FocusSessionService::spawn_enforcer(app_state.clone(), UserId::DEFAULT.as_uuid());
+
+  // Save last week's usage report on Monday mornings when the weekly report is on
+  UsageReportService::spawn_weekly_report(app_state.clone(), UserId::DEFAULT.as_uuid());
+
+  // Exchange profile bundles with other machines through the sync folder, if one is set
+  FolderSyncService::spawn_watcher(app_state.clone(), UserId::DEFAULT.as_uuid());
+
+  // Re-apply the watched declarative profile file whenever it's saved
+  DeclarativeService::spawn_watcher(app_state.clone(), UserId::DEFAULT.as_uuid());
+
+  // Send the anonymous weekly usage report, only if the user opted in
+  TelemetryService::spawn(app_state.clone(), UserId::DEFAULT.as_uuid());
+
+  // Keep a rotating daily snapshot of every profile to roll back to
+  SnapshotService::spawn_daily(app_state.clone(), UserId::DEFAULT.as_uuid());
+
+  // Remove stored icons, favicons and attachments nothing refers to any more
+  StorageService::spawn_gc(app_state.clone());
+
+  // Switch to the profile bound to the displays whenever they change
+  ProfileResolver::spawn_monitor_watcher(app_state.clone(), UserId::DEFAULT.as_uuid());
+
+  // Prune old logs in the background per the user's retention settings
+  AuditService::spawn_log_maintenance(&app_state.tasks, db.clone(), UserId::DEFAULT.as_uuid());
+
+  // Roll the log tables up into daily_stats for analytics
+  AuditService::spawn_daily_stats_rollup(&app_state.tasks, db.clone(), UserId::DEFAULT.as_uuid());
 
   // Log application startup
   let db_clone = db.clone();
@@ -58,7 +212,7 @@ async fn main() {
       .log_system_event(
         &db_clone,
         "app_started",
-        "info",
+        Severity::Info,
         "main",
         "Smoothie Desktop Application started",
         None,
@@ -70,6 +224,23 @@ async fn main() {
     }
   });
 
+  // Note the first launch after an update
+  let db_clone = db.clone();
+  tokio::spawn(async move {
+    UpdateService::record_launch(&db_clone).await;
+  });
+
+  // Keep the login item pointing at this binary, pick up background agent mode
+  // and use the displayplacer set in settings
+  match UserSettingsService::get_settings(&db, UserId::DEFAULT.as_uuid()).await {
+    Ok(settings) => {
+      SystemService::set_displayplacer_path(settings.displayplacer_path.clone());
+      LoginItemService::apply_on_start(&settings).await
+    }
+    Err(e) => tracing::warn!("Failed to load login item settings: {}", e),
+  }
+  let start_hidden = LoginItemService::launched_in_background();
+
   tracing::info!("Application state initialized");
   tracing::info!("Smoothie started successfully");
 
@@ -82,57 +253,166 @@ async fn main() {
       // Profile handlers
       handlers::profile::create_profile,
       handlers::profile::get_profiles,
+      handlers::profile::reorder_profiles,
       handlers::profile::get_profile,
       handlers::profile::update_profile,
       handlers::profile::delete_profile,
       handlers::profile::activate_profile,
       handlers::profile::duplicate_profile,
+      handlers::profile::share_profile,
+      handlers::profile::export_profile_as_script,
+      handlers::profile::sync_from_declarative,
+      handlers::profile::import_shared_profile,
       handlers::profile::start_profile,
+      handlers::profile::get_interrupted_activations,
+      handlers::profile::resume_interrupted_activation,
+      handlers::profile::get_activation_report,
+      handlers::profile::rollback_interrupted_activation,
+      handlers::profile::discard_interrupted_activation,
+      handlers::profile::list_snapshots,
+      handlers::profile::restore_snapshot,
       handlers::profile::get_favorite_profiles,
       handlers::profile::get_most_used_profiles,
       handlers::profile::set_profile_favorite,
+      handlers::profile::set_profile_low_power_threshold,
+      handlers::profile::set_profile_local_only,
+      handlers::profile::set_profile_monitor_binding,
+      handlers::profile::set_profile_virtual_displays,
+      handlers::profile::set_profile_locked,
+      handlers::profile::resolve_profile_for_monitors,
+      handlers::profile::get_profile_history,
+      handlers::profile::undo_last_change,
+      handlers::profile::redo_last_change,
+      handlers::profile::get_profile_edit_state,
+      handlers::profile::get_profile_deactivation_actions,
+      handlers::profile::set_profile_deactivation_actions,
+      handlers::profile::get_profile_blocked_apps,
+      handlers::profile::set_profile_blocked_apps,
+      handlers::profile::allow_blocked_app,
+      handlers::profile::get_profile_checklist,
+      handlers::profile::set_profile_checklist,
+      handlers::profile::get_activation_checklist,
+      handlers::profile::set_checklist_item_completed,
       // Monitor handlers
       handlers::monitor::create_monitor,
       handlers::monitor::get_monitors,
       handlers::monitor::update_monitor,
+      handlers::monitor::set_monitor_blanked,
       handlers::monitor::delete_monitor,
+      handlers::monitor::capture_monitor_layout,
+      handlers::monitor::prune_stale_monitors,
+      handlers::monitor::get_layout_preview,
       // App handlers
       handlers::app::create_app,
       handlers::app::get_apps,
+      handlers::app::reorder_apps,
       handlers::app::update_app,
       handlers::app::delete_app,
+      handlers::app::get_missing_apps,
+      handlers::app::replace_profile_app,
       handlers::app::launch_apps,
       // Browser tab handlers
       handlers::browser::create_browser_tab,
       handlers::browser::get_browser_tabs,
+      handlers::browser::preview_bookmark_import,
+      handlers::browser::import_bookmarks,
+      handlers::browser::reorder_tabs,
       handlers::browser::update_browser_tab,
       handlers::browser::delete_browser_tab,
       handlers::browser::open_tabs,
+      // Profile file handlers
+      handlers::file::create_profile_file,
+      handlers::file::get_profile_files,
+      handlers::file::update_profile_file,
+      handlers::file::delete_profile_file,
+      handlers::file::open_files,
       // Automation rule handlers
       handlers::automation::create_rule,
       handlers::automation::get_rules,
       handlers::automation::update_rule,
+      handlers::automation::set_rule_conditions,
+      handlers::automation::set_rule_priority,
+      handlers::automation::snooze_rule,
+      handlers::automation::set_rule_active_hours,
+      handlers::automation::get_rule_actions,
+      handlers::automation::set_rule_actions,
       handlers::automation::delete_rule,
       handlers::automation::evaluate_rules,
+      handlers::automation::pause_automation,
+      handlers::automation::resume_automation,
+      handlers::automation::get_automation_pause,
+      handlers::automation::test_rule,
+      handlers::automation::simulate_trigger,
+      // Focus session handlers
+      handlers::focus::start_focus_session,
+      handlers::focus::get_active_focus_session,
+      handlers::focus::cancel_focus_session,
+      handlers::focus::get_focus_sessions,
       // Window handlers
       handlers::window::create_window,
       handlers::window::get_windows,
       handlers::window::update_window_position,
+      handlers::window::set_window_zoom,
       handlers::window::delete_window,
+      handlers::window::assign_window_to_profile,
+      handlers::window::recover_offscreen_windows,
       // User handlers
       handlers::user::get_user_preferences,
       handlers::user::update_user_preferences,
       handlers::user::get_user_settings,
       handlers::user::update_user_settings,
+      handlers::user::set_user_location,
+      handlers::user::set_sync_scope,
+      handlers::user::set_monitor_profile_mappings,
+      handlers::user::validate_shortcut,
+      handlers::user::set_profile_shortcut,
+      handlers::user::set_weekly_report,
+      handlers::user::set_snapshots_kept,
+      handlers::user::set_declarative_watch_path,
+      handlers::user::set_login_item,
+      handlers::user::set_diagnostic_uploads,
+      handlers::user::get_sync_preview,
+      handlers::user::push_cloud_sync,
+      handlers::user::set_telemetry_enabled,
+      handlers::user::set_displayplacer_path,
+      handlers::user::get_telemetry_payload_preview,
+      handlers::user::enable_folder_sync,
+      handlers::user::disable_folder_sync,
+      handlers::user::get_folder_sync_status,
+      handlers::user::sync_folder_now,
+      // Team handlers
+      handlers::team::sync_teams,
+      handlers::team::get_teams,
+      handlers::team::get_team_library,
+      handlers::team::clone_team_profile,
+      handlers::team::publish_team_profile,
       // System handlers
+      handlers::system::get_api_version,
       handlers::system::get_connected_monitors,
       handlers::system::get_running_apps,
       handlers::system::get_installed_apps,
       handlers::system::get_visible_windows,
       handlers::system::capture_current_layout,
       handlers::system::apply_monitor_layout,
+      handlers::system::release_blanked_displays,
       handlers::system::check_display_permission,
       handlers::system::request_display_permission,
+      handlers::system::get_startup_status,
+      handlers::system::get_db_connection_status,
+      handlers::system::get_health,
+      handlers::system::get_fixes,
+      handlers::system::apply_fix,
+      handlers::system::get_usb_devices,
+      handlers::system::get_background_tasks,
+      handlers::system::get_bluetooth_devices,
+      handlers::system::check_for_updates,
+      handlers::system::get_release_notes,
+      handlers::system::download_update,
+      // Blob storage handlers
+      handlers::storage::store_blob,
+      handlers::storage::get_blob,
+      handlers::storage::get_storage_usage,
+      handlers::storage::collect_storage_garbage,
       // Audit and logging handlers
       handlers::audit::start_session,
       handlers::audit::end_session,
@@ -145,13 +425,20 @@ async fn main() {
       handlers::audit::get_profile_activations,
       handlers::audit::log_error,
       handlers::audit::get_error_logs,
+      handlers::audit::get_error_groups,
       handlers::audit::resolve_error,
       handlers::audit::record_monitor_change,
       handlers::audit::record_app_launch,
       handlers::audit::record_automation_execution,
       handlers::audit::get_dashboard_stats,
       handlers::audit::get_log_summary,
+      handlers::audit::get_daily_stats,
+      handlers::audit::get_profile_focus_time,
+      handlers::audit::generate_usage_report,
+      handlers::audit::export_sanitized_logs,
       handlers::audit::get_app_metrics,
+      handlers::audit::get_command_stats,
+      handlers::audit::set_log_level,
       handlers::audit::cleanup_old_logs,
       handlers::audit::get_monitor_changes,
       handlers::audit::get_app_launches,
@@ -164,17 +451,61 @@ async fn main() {
       handlers::subscription::get_subscription,
       handlers::subscription::create_subscription,
       handlers::subscription::delete_subscription,
+      // Alert handlers
+      handlers::alert::get_active_alerts,
+      handlers::alert::get_alert_rules,
+      handlers::alert::update_alert_rule,
+      // Development handlers
+      handlers::demo::seed_demo_data,
     ])
-    .on_window_event(|_window, event| {
-      if let tauri::WindowEvent::Destroyed = event {
+    .setup(move |app| {
+      Startup::begin(StartupStage::Window);
+      forward_connection_events(app.handle().clone());
+      // A background agent has no Dock icon
+      #[cfg(target_os = "macos")]
+      if LoginItemService::keeps_running_when_closed() {
+        app.set_activation_policy(tauri::ActivationPolicy::Accessory);
+      }
+      if start_hidden {
+        tracing::info!("Started at login in the background");
+      } else {
+        show_main_window(app.handle())?;
+      }
+      Startup::begin(StartupStage::Ready);
+      Ok(())
+    })
+    .on_window_event(|window, event| match event {
+      // A background agent only hides its window, so the watchers keep running
+      tauri::WindowEvent::CloseRequested { api, .. }
+        if LoginItemService::keeps_running_when_closed() =>
+      {
+        api.prevent_close();
+        if let Err(e) = window.hide() {
+          tracing::warn!("Failed to hide the main window: {}", e);
+        }
+      }
+      tauri::WindowEvent::Destroyed => {
         tracing::info!("Window destroyed, cleanup initiated");
         // Log final metrics
         let metrics = METRICS.get_summary();
         tracing::info!("Final metrics: {}", metrics);
       }
+      _ => {}
     })
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .build(tauri::generate_context!())
+    .expect("error while building tauri application")
+    .run(|app, event| {
+      if let tauri::RunEvent::Exit = event {
+        if let Some(queue) = AuditQueue::global() {
+          let rollup = AuditRecord::SystemEvent(METRICS.rollup_event());
+          if queue.push_blocking(rollup) != PushOutcome::Queued {
+            tracing::warn!("Metrics rollup was not saved");
+          }
+          queue.shutdown_blocking(std::time::Duration::from_secs(5));
+        }
+      }
+      show_on_reopen(app, &event);
+    });
 
   tracing::info!("=== Smoothie Desktop Application Shutdown ===");
 }
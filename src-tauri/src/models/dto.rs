@@ -1,19 +1,134 @@
 // API DTOs (Data Transfer Objects) - for frontend communication
 // These types are serialized to JSON and sent to the frontend
 
+use super::enums::{
+  ActivationSource, ActivityStatus, BlockedAppAction, BookmarkSource, DeactivationActionType,
+  FeedbackStatus, FocusSessionStatus, MonitorChangeType, ProfileType, ReportFormat, RuleActionType,
+  Severity, SnapshotKind, SyncScope, TeamRole,
+};
+use super::ids::{ProfileId, TeamId, UserId};
+use crate::repositories::JsonFilter;
+use crate::utils::{markdown::MAX_NOTES_LENGTH, validation};
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
+use validator::Validate;
 
 // ============================================================================
 // Request DTOs
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateProfileRequest {
+  #[validate(length(max = 100), custom(function = validation::not_blank))]
   pub name: String,
+  #[validate(length(max = 500))]
   pub description: Option<String>,
-  pub profile_type: String,
+  pub profile_type: ProfileType,
+  #[validate(custom(function = validation::tags))]
   pub tags: Option<Vec<String>>,
+  /// Markdown; left out of shared copies that have none
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  #[validate(length(max = MAX_NOTES_LENGTH))]
+  pub notes: Option<String>,
+}
+
+/// Editable profile fields; `None` leaves a field unchanged
+#[derive(Debug, Default, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProfileRequest {
+  #[validate(length(max = 100), custom(function = validation::not_blank))]
+  pub name: Option<String>,
+  #[validate(length(max = 500))]
+  pub description: Option<String>,
+  pub is_favorite: Option<bool>,
+  #[validate(custom(function = validation::hex_color))]
+  pub color: Option<String>,
+  #[validate(length(max = 64))]
+  pub icon: Option<String>,
+  #[validate(range(min = 0))]
+  pub sort_order: Option<i32>,
+  /// Replaces the profile's tags when present
+  #[validate(custom(function = validation::tags))]
+  pub tags: Option<Vec<String>>,
+  /// Markdown; an empty string clears the notes
+  #[validate(length(max = MAX_NOTES_LENGTH))]
+  pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMonitorRequest {
+  #[validate(length(max = 100), custom(function = validation::not_blank))]
+  pub name: String,
+  #[validate(custom(function = validation::resolution))]
+  pub resolution: String,
+  #[validate(custom(function = validation::orientation))]
+  pub orientation: String,
+  pub is_primary: bool,
+  pub x: i32,
+  pub y: i32,
+  #[validate(range(min = 1, max = 32768))]
+  pub width: i32,
+  #[validate(range(min = 1, max = 32768))]
+  pub height: i32,
+  #[validate(range(min = 0))]
+  pub display_index: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAppRequest {
+  #[validate(length(max = 200), custom(function = validation::not_blank))]
+  pub name: String,
+  #[validate(length(max = 255), custom(function = validation::not_blank))]
+  pub bundle_id: String,
+  #[validate(length(max = 1024))]
+  pub exe_path: Option<String>,
+  pub launch_on_activate: bool,
+  #[validate(range(min = 0))]
+  pub monitor_preference: Option<i32>,
+  #[validate(range(min = 0, max = 60000))]
+  pub startup_delay_ms: Option<i32>,
+  #[validate(range(min = 0))]
+  pub order_index: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateBrowserTabRequest {
+  #[validate(length(max = 2048), custom(function = validation::web_url))]
+  pub url: String,
+  #[validate(custom(function = validation::browser))]
+  pub browser: String,
+  pub monitor_id: Option<String>,
+  #[validate(range(min = 0))]
+  pub tab_order: i32,
+  #[validate(length(max = 2048), custom(function = validation::web_url))]
+  pub favicon: Option<String>,
+}
+
+/// A focus session to start; the blocklists are enforced until it ends
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct StartFocusSessionRequest {
+  pub profile_id: ProfileId,
+  #[validate(range(min = 1, max = 480))]
+  pub duration_minutes: i32,
+  /// Bundle IDs quit whenever they're running
+  #[serde(default)]
+  #[validate(length(max = 50), custom(function = validation::bundle_ids))]
+  pub blocked_apps: Vec<String>,
+  /// URL prefixes whose tabs are closed in scriptable browsers
+  #[serde(default)]
+  #[validate(length(max = 50), custom(function = validation::web_urls))]
+  pub blocked_urls: Vec<String>,
+  /// Shortcuts.app shortcut that turns Do Not Disturb on
+  #[validate(length(max = 255), custom(function = validation::not_blank))]
+  pub dnd_shortcut: Option<String>,
+  /// Shortcut that turns it back off when the session ends
+  #[validate(length(max = 255), custom(function = validation::not_blank))]
+  pub dnd_off_shortcut: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -25,30 +140,35 @@ pub struct LogQueryParams {
   pub end_date: Option<String>,
   pub action: Option<String>,
   pub entity_type: Option<String>,
-  pub severity: Option<String>,
+  pub severity: Option<Severity>,
+  /// Condition on the activity `details` payload
+  pub details: Option<JsonFilter>,
 }
 
 // ============================================================================
 // Response DTOs
 // ============================================================================
 
-/// Generic success response (used by handlers)
+/// Envelope every command returns, built by `instrumented!`
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SuccessResponse<T: Serialize> {
   pub success: bool,
   pub data: T,
+  /// Identifies this invocation in the backend logs
+  pub request_id: String,
+  pub duration_ms: u64,
 }
 
 /// Profile response with related data
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProfileDto {
-  pub id: String,
-  pub user_id: String,
+  pub id: ProfileId,
+  pub user_id: UserId,
   pub name: String,
   pub description: Option<String>,
-  pub profile_type: String,
+  pub profile_type: ProfileType,
   pub is_active: bool,
   pub tags: Vec<String>,
   pub monitor_count: i64,
@@ -64,26 +184,35 @@ pub struct ProfileDto {
   pub color: Option<String>,
   pub icon: Option<String>,
   pub sort_order: i32,
+  pub low_power_battery_threshold: Option<i32>,
+  pub local_only: bool,
+  pub required_monitor_count: Option<i32>,
+  pub monitor_signature: Option<String>,
+  pub notes: Option<String>,
+  pub include_virtual_displays: bool,
+  pub is_locked: bool,
 }
 
 /// ProfileResponse is an alias for ProfileDetailDto (for backward compatibility)
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProfileResponse {
-  pub id: String,
+  pub id: ProfileId,
   pub name: String,
   pub description: Option<String>,
-  pub profile_type: String,
+  pub profile_type: ProfileType,
   pub is_active: bool,
   pub tags: Vec<String>,
   pub monitors: Vec<MonitorDto>,
   pub apps: Vec<AppDto>,
   pub browser_tabs: Vec<BrowserTabDto>,
+  pub files: Vec<ProfileFileDto>,
   pub created_at: String,
   pub last_used: Option<String>,
+  pub notes: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MonitorDto {
   pub id: String,
@@ -106,9 +235,13 @@ pub struct MonitorDto {
   pub color_depth: Option<i32>,
   pub created_at: Option<String>,
   pub updated_at: Option<String>,
+  pub fingerprint: Option<String>,
+  pub captured_at: Option<String>,
+  #[serde(default)]
+  pub blanked: bool,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AppDto {
   pub id: String,
@@ -128,7 +261,7 @@ pub struct AppDto {
   pub order_index: i32,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BrowserTabDto {
   pub id: String,
@@ -142,6 +275,19 @@ pub struct BrowserTabDto {
   pub updated_at: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileFileDto {
+  pub id: String,
+  pub profile_id: String,
+  pub path: String,
+  pub target_app: Option<String>,
+  pub open_on_activate: bool,
+  pub open_order: i32,
+  pub created_at: String,
+  pub updated_at: String,
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AutomationRuleDto {
@@ -149,13 +295,176 @@ pub struct AutomationRuleDto {
   pub profile_id: String,
   pub rule_type: String,
   pub trigger_config: serde_json::Value,
+  pub conditions: Option<serde_json::Value>,
+  pub priority: i32,
+  pub stop_after_match: bool,
+  pub cooldown_minutes: i32,
+  pub last_triggered_at: Option<String>,
+  /// Only set while the snooze is still running
+  pub snoozed_until: Option<String>,
+  pub active_hours: Option<serde_json::Value>,
   pub is_enabled: bool,
   pub created_at: String,
 }
 
-/// User settings DTO - all user preferences
+/// One step of an automation rule, run in `position` order
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleActionDto {
+  pub id: String,
+  pub rule_id: String,
+  pub position: i32,
+  pub action_type: RuleActionType,
+  pub config: serde_json::Value,
+}
+
+/// An action as sent by the rule editor; list order becomes execution order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleActionRequest {
+  pub action_type: RuleActionType,
+  #[serde(default)]
+  pub config: serde_json::Value,
+}
+
+/// Cleanup step a profile runs when another profile is activated
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeactivationActionDto {
+  pub id: String,
+  pub profile_id: String,
+  pub position: i32,
+  pub action_type: DeactivationActionType,
+  pub config: serde_json::Value,
+}
+
+/// A deactivation action as sent by the profile editor; list order becomes execution order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeactivationActionRequest {
+  pub action_type: DeactivationActionType,
+  #[serde(default)]
+  pub config: serde_json::Value,
+}
+
+/// An app kept closed while its profile is active
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockedAppDto {
+  pub id: String,
+  pub profile_id: String,
+  pub bundle_id: String,
+  pub app_name: String,
+  pub action: BlockedAppAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockedAppRequest {
+  #[validate(length(max = 255), custom(function = validation::not_blank))]
+  pub bundle_id: String,
+  #[validate(length(max = 200), custom(function = validation::not_blank))]
+  pub app_name: String,
+  pub action: BlockedAppAction,
+}
+
+/// Something to check after a profile activates
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistItemDto {
+  pub id: String,
+  pub profile_id: String,
+  pub position: i32,
+  pub label: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistItemRequest {
+  /// Set to keep an existing item, and what's been checked off against it
+  pub id: Option<String>,
+  #[validate(length(max = 200), custom(function = validation::not_blank))]
+  pub label: String,
+}
+
+/// A checklist item and whether it's been checked since the last activation
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistItemStatusDto {
+  pub id: String,
+  pub label: String,
+  pub position: i32,
+  pub completed: bool,
+  pub completed_at: Option<String>,
+}
+
+/// A profile's checklist for its current activation
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivationChecklistDto {
+  pub profile_id: String,
+  /// Unset when the profile has never been activated
+  pub activated_at: Option<String>,
+  pub items: Vec<ChecklistItemStatusDto>,
+}
+
+/// A blocked app let through for the active profile
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockedAppOverrideDto {
+  pub profile_id: String,
+  pub bundle_id: String,
+  /// Unset when it lasts until another profile is activated
+  pub until: Option<String>,
+}
+
+/// The profile to switch to when a given number of monitors is connected and
+/// no profile is bound to the setup more specifically
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorProfileMapping {
+  pub monitor_count: i32,
+  pub profile_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShortcutConflictKind {
+  /// Another of Smoothie's own bindings
+  Smoothie,
+  /// A shortcut macOS keeps for itself
+  System,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutConflictDto {
+  pub kind: ShortcutConflictKind,
+  pub description: String,
+  /// The profile already bound to the shortcut, for Smoothie conflicts
+  pub profile_id: Option<String>,
+}
+
+/// A shortcut in its canonical spelling and what it would clash with
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
+pub struct ShortcutValidationDto {
+  pub shortcut: String,
+  pub conflicts: Vec<ShortcutConflictDto>,
+}
+
+/// Whether automation is paused for presentation mode, and until when;
+/// `until` is unset while paused indefinitely
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationPauseDto {
+  pub paused: bool,
+  pub until: Option<String>,
+}
+
+/// User settings DTO - all user preferences
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct UserSettingsDto {
   pub id: String,
   pub user_id: String,
@@ -177,6 +486,22 @@ pub struct UserSettingsDto {
   pub feature_flags: Option<serde_json::Value>,
   pub keyboard_shortcuts: Option<serde_json::Value>,
   pub ui_preferences: Option<serde_json::Value>,
+  pub log_retention_days: i32,
+  pub auto_cleanup_logs: bool,
+  pub latitude: Option<f64>,
+  pub longitude: Option<f64>,
+  pub sync_scope: SyncScope,
+  pub monitor_profile_mappings: Vec<MonitorProfileMapping>,
+  pub weekly_report_enabled: bool,
+  pub weekly_report_format: ReportFormat,
+  pub snapshots_kept: i32,
+  pub declarative_watch_path: Option<String>,
+  pub launch_at_login: bool,
+  pub background_agent: bool,
+  pub diagnostic_uploads: bool,
+  pub telemetry_enabled: bool,
+  pub telemetry_sent_at: Option<String>,
+  pub displayplacer_path: Option<String>,
 }
 
 // ============================================================================
@@ -197,9 +522,10 @@ pub struct ActivityLogDto {
   pub details: Option<serde_json::Value>,
   pub ip_address: Option<String>,
   pub user_agent: Option<String>,
-  pub status: String,
+  pub status: ActivityStatus,
   pub error_message: Option<String>,
   pub duration_ms: Option<i32>,
+  pub request_id: Option<String>,
   pub created_at: String,
 }
 
@@ -209,13 +535,14 @@ pub struct ActivityLogDto {
 pub struct SystemEventDto {
   pub id: String,
   pub event_type: String,
-  pub severity: String,
+  pub severity: Severity,
   pub source: String,
   pub message: String,
   pub details: Option<serde_json::Value>,
   pub stack_trace: Option<String>,
   pub os_info: Option<serde_json::Value>,
   pub app_version: Option<String>,
+  pub request_id: Option<String>,
   pub created_at: String,
 }
 
@@ -228,7 +555,7 @@ pub struct ProfileActivationDto {
   pub profile_id: String,
   pub profile_name: Option<String>,
   pub session_id: Option<String>,
-  pub activation_source: String,
+  pub activation_source: ActivationSource,
   pub previous_profile_id: Option<String>,
   pub previous_profile_name: Option<String>,
   pub monitors_detected: i32,
@@ -263,7 +590,7 @@ pub struct ErrorLogDto {
   pub source_file: Option<String>,
   pub source_line: Option<i32>,
   pub source_function: Option<String>,
-  pub severity: String,
+  pub severity: Severity,
   pub is_resolved: bool,
   pub resolved_at: Option<String>,
   pub resolution_notes: Option<String>,
@@ -271,6 +598,11 @@ pub struct ErrorLogDto {
   pub first_occurred_at: String,
   pub last_occurred_at: String,
   pub created_at: String,
+  pub fingerprint: Option<String>,
+  /// Times the error came back after being resolved
+  pub regression_count: i32,
+  pub reopened_at: Option<String>,
+  pub request_id: Option<String>,
 }
 
 /// Session DTO - for user session tracking
@@ -321,13 +653,14 @@ pub struct MonitorChangeDto {
   pub id: String,
   pub user_id: Option<String>,
   pub session_id: Option<String>,
-  pub change_type: String,
+  pub change_type: MonitorChangeType,
   pub monitors_before: Option<serde_json::Value>,
   pub monitors_after: Option<serde_json::Value>,
   pub detected_at: String,
   pub auto_profile_activated: bool,
   pub activated_profile_id: Option<String>,
   pub activated_profile_name: Option<String>,
+  pub summary: Option<String>,
 }
 
 /// App launch DTO
@@ -367,6 +700,9 @@ pub struct DashboardStatsDto {
   pub most_used_profile_name: Option<String>,
   pub most_used_profile_count: i64,
   pub last_activation_at: Option<String>,
+  /// Focus sessions completed in the last 7 days and the minutes they ran
+  pub focus_sessions_week: i64,
+  pub focus_minutes_week: i64,
   pub uptime_seconds: u64,
 }
 
@@ -384,6 +720,202 @@ pub struct LogSummaryDto {
   pub activations_by_source: serde_json::Value,
 }
 
+/// Result of a log retention cleanup
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogCleanupDto {
+  pub retention_days: i64,
+  pub activity_logs: u64,
+  pub system_events: u64,
+  pub error_logs: u64,
+  pub sessions: u64,
+  pub app_launches: u64,
+  pub monitor_changes: u64,
+  pub automation_executions: u64,
+  pub app_focus_events: u64,
+  pub total_deleted: u64,
+  pub analyzed: bool,
+}
+
+/// One day of rolled-up activity for a profile (or for no profile)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyStatsDto {
+  pub day: String,
+  pub profile_id: Option<String>,
+  pub profile_name: Option<String>,
+  pub activations: i32,
+  pub app_launches: i32,
+  pub errors: i32,
+  pub active_minutes: i32,
+}
+
+/// How long each app was frontmost while a profile was active
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileFocusTimeDto {
+  pub profile_id: String,
+  pub days: i64,
+  pub total_focus_ms: i64,
+  pub apps: Vec<AppFocusTimeDto>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppFocusTimeDto {
+  pub bundle_id: String,
+  pub app_name: String,
+  pub focus_ms: i64,
+  /// Times the app was brought to the front
+  pub sessions: i64,
+  /// Fraction of the profile's total focus time, 0 to 1
+  pub share: f64,
+}
+
+/// Days a usage report covers and the format to render it in
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReportRequest {
+  /// First day covered; six days before `to` by default
+  pub from: Option<NaiveDate>,
+  /// Last day covered, inclusive; today by default
+  pub to: Option<NaiveDate>,
+  pub format: Option<ReportFormat>,
+}
+
+/// A rendered usage report and the figures in it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReportDto {
+  pub from: NaiveDate,
+  pub to: NaiveDate,
+  pub format: ReportFormat,
+  /// Where the rendered report was saved
+  pub path: String,
+  pub total_focus_ms: i64,
+  pub total_activations: i64,
+  pub profiles: Vec<ProfileUsageDto>,
+  pub top_apps: Vec<AppFocusTimeDto>,
+  pub errors: Vec<ErrorSummaryDto>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileUsageDto {
+  pub profile_id: String,
+  pub name: String,
+  pub activations: i64,
+  /// Time apps were in front while the profile was active
+  pub focus_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorSummaryDto {
+  pub error_type: String,
+  pub message: String,
+  pub severity: Severity,
+  pub occurrences: i64,
+  pub last_occurred_at: String,
+}
+
+/// Errors sharing a fingerprint, with their recent trend
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorGroupDto {
+  pub fingerprint: String,
+  pub error_type: String,
+  pub message: String,
+  pub severity: Severity,
+  pub occurrences: i64,
+  pub first_seen: String,
+  pub last_seen: String,
+  /// Whether every entry in the group is resolved
+  pub resolved: bool,
+  pub regressions: i64,
+  /// App versions the error occurred in, in the order it first did
+  pub affected_versions: Vec<String>,
+  /// Occurrences per day for the last 30 days, oldest first and ending today
+  pub daily_occurrences: Vec<i64>,
+}
+
+/// A saved copy of every profile, restorable with `restore_snapshot`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotDto {
+  pub id: String,
+  pub kind: SnapshotKind,
+  pub taken_at: String,
+  pub profile_count: usize,
+  pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreSnapshotDto {
+  pub snapshot_id: String,
+  /// Existing profiles put back the way they were
+  pub restored: u32,
+  /// Profiles deleted since the snapshot, created again
+  pub recreated: u32,
+  /// Snapshot of the profiles as they were just before the restore
+  pub undo_snapshot_id: String,
+}
+
+/// A link found in a bookmarks or session file
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookmarkItemDto {
+  pub title: String,
+  pub url: String,
+  /// Folder path like `Bookmarks Bar / Work`, or `Window 2` for sessions
+  pub folder: String,
+  /// Already a tab in the profile or listed earlier in the file; never imported
+  pub duplicate: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookmarkImportPreviewDto {
+  pub source: BookmarkSource,
+  /// Browser the imported tabs open in unless the import names another
+  pub browser: String,
+  pub items: Vec<BookmarkItemDto>,
+}
+
+/// Create browser tabs from a bookmarks or session file
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportBookmarksRequest {
+  pub path: String,
+  /// Only items in this folder or below
+  pub folder: Option<String>,
+  /// Only these URLs from the preview; every non-duplicate item when absent
+  pub urls: Option<Vec<String>>,
+  #[validate(custom(function = validation::browser))]
+  pub browser: Option<String>,
+}
+
+/// Focus session response
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusSessionDto {
+  pub id: String,
+  pub profile_id: String,
+  pub planned_minutes: i32,
+  pub status: FocusSessionStatus,
+  pub blocked_apps: Vec<String>,
+  pub blocked_urls: Vec<String>,
+  pub dnd_enabled: bool,
+  /// Times a blocked app was quit during the session
+  pub blocked_app_quits: i32,
+  pub started_at: String,
+  pub ends_at: String,
+  pub ended_at: Option<String>,
+  /// Seconds left while the session is active
+  pub remaining_seconds: Option<i64>,
+}
+
 // ============================================================================
 // Entity to DTO conversions
 // ============================================================================
@@ -393,8 +925,8 @@ use super::entities::*;
 impl From<ProfileEntity> for ProfileDto {
   fn from(entity: ProfileEntity) -> Self {
     Self {
-      id: entity.id.to_string(),
-      user_id: entity.user_id.to_string(),
+      id: entity.id.into(),
+      user_id: entity.user_id.into(),
       name: entity.name,
       description: entity.description,
       profile_type: entity.profile_type,
@@ -412,6 +944,13 @@ impl From<ProfileEntity> for ProfileDto {
       color: entity.color,
       icon: entity.icon,
       sort_order: entity.sort_order.unwrap_or(0),
+      low_power_battery_threshold: entity.low_power_battery_threshold,
+      local_only: entity.local_only,
+      required_monitor_count: entity.required_monitor_count,
+      monitor_signature: entity.monitor_signature,
+      notes: entity.notes,
+      include_virtual_displays: entity.include_virtual_displays,
+      is_locked: entity.is_locked,
     }
   }
 }
@@ -426,8 +965,8 @@ impl ProfileDto {
     browser_tab_count: i64,
   ) -> Self {
     Self {
-      id: entity.id.to_string(),
-      user_id: entity.user_id.to_string(),
+      id: entity.id.into(),
+      user_id: entity.user_id.into(),
       name: entity.name,
       description: entity.description,
       profile_type: entity.profile_type,
@@ -445,6 +984,13 @@ impl ProfileDto {
       color: entity.color,
       icon: entity.icon,
       sort_order: entity.sort_order.unwrap_or(0),
+      low_power_battery_threshold: entity.low_power_battery_threshold,
+      local_only: entity.local_only,
+      required_monitor_count: entity.required_monitor_count,
+      monitor_signature: entity.monitor_signature,
+      notes: entity.notes,
+      include_virtual_displays: entity.include_virtual_displays,
+      is_locked: entity.is_locked,
     }
   }
 }
@@ -471,6 +1017,9 @@ impl From<MonitorEntity> for MonitorDto {
       color_depth: entity.color_depth,
       created_at: entity.created_at.map(|dt| dt.to_rfc3339()),
       updated_at: entity.updated_at.map(|dt| dt.to_rfc3339()),
+      fingerprint: entity.fingerprint,
+      captured_at: entity.captured_at.map(|dt| dt.to_rfc3339()),
+      blanked: entity.blanked,
     }
   }
 }
@@ -512,6 +1061,21 @@ impl From<BrowserTabEntity> for BrowserTabDto {
   }
 }
 
+impl From<ProfileFileEntity> for ProfileFileDto {
+  fn from(entity: ProfileFileEntity) -> Self {
+    Self {
+      id: entity.id.to_string(),
+      profile_id: entity.profile_id.to_string(),
+      path: entity.path,
+      target_app: entity.target_app,
+      open_on_activate: entity.open_on_activate,
+      open_order: entity.open_order,
+      created_at: entity.created_at.to_rfc3339(),
+      updated_at: entity.updated_at.to_rfc3339(),
+    }
+  }
+}
+
 impl From<AutomationRuleEntity> for AutomationRuleDto {
   fn from(entity: AutomationRuleEntity) -> Self {
     Self {
@@ -519,12 +1083,69 @@ impl From<AutomationRuleEntity> for AutomationRuleDto {
       profile_id: entity.profile_id.to_string(),
       rule_type: entity.rule_type,
       trigger_config: entity.trigger_config,
+      conditions: entity.conditions,
+      priority: entity.priority,
+      stop_after_match: entity.stop_after_match,
+      cooldown_minutes: entity.cooldown_minutes,
+      last_triggered_at: entity.last_triggered_at.map(|dt| dt.to_rfc3339()),
+      snoozed_until: entity
+        .snoozed_until
+        .filter(|until| *until > chrono::Utc::now())
+        .map(|dt| dt.to_rfc3339()),
+      active_hours: entity.active_hours,
       is_enabled: entity.is_enabled,
       created_at: entity.created_at.to_rfc3339(),
     }
   }
 }
 
+impl From<RuleActionEntity> for RuleActionDto {
+  fn from(entity: RuleActionEntity) -> Self {
+    Self {
+      id: entity.id.to_string(),
+      rule_id: entity.rule_id.to_string(),
+      position: entity.position,
+      action_type: entity.action_type,
+      config: entity.config,
+    }
+  }
+}
+
+impl From<ProfileDeactivationActionEntity> for DeactivationActionDto {
+  fn from(entity: ProfileDeactivationActionEntity) -> Self {
+    Self {
+      id: entity.id.to_string(),
+      profile_id: entity.profile_id.to_string(),
+      position: entity.position,
+      action_type: entity.action_type,
+      config: entity.config,
+    }
+  }
+}
+
+impl From<ProfileBlockedAppEntity> for BlockedAppDto {
+  fn from(entity: ProfileBlockedAppEntity) -> Self {
+    Self {
+      id: entity.id.to_string(),
+      profile_id: entity.profile_id.to_string(),
+      bundle_id: entity.bundle_id,
+      app_name: entity.app_name,
+      action: entity.action,
+    }
+  }
+}
+
+impl From<ProfileChecklistItemEntity> for ChecklistItemDto {
+  fn from(entity: ProfileChecklistItemEntity) -> Self {
+    Self {
+      id: entity.id.to_string(),
+      profile_id: entity.profile_id.to_string(),
+      position: entity.position,
+      label: entity.label,
+    }
+  }
+}
+
 impl From<UserSettingsEntity> for UserSettingsDto {
   fn from(entity: UserSettingsEntity) -> Self {
     Self {
@@ -547,6 +1168,23 @@ impl From<UserSettingsEntity> for UserSettingsDto {
       feature_flags: entity.feature_flags,
       keyboard_shortcuts: entity.keyboard_shortcuts,
       ui_preferences: entity.ui_preferences,
+      log_retention_days: entity.log_retention_days,
+      auto_cleanup_logs: entity.auto_cleanup_logs,
+      latitude: entity.latitude,
+      longitude: entity.longitude,
+      sync_scope: entity.sync_scope,
+      monitor_profile_mappings: serde_json::from_value(entity.monitor_profile_mappings)
+        .unwrap_or_default(),
+      weekly_report_enabled: entity.weekly_report_enabled,
+      weekly_report_format: entity.weekly_report_format,
+      snapshots_kept: entity.snapshots_kept,
+      declarative_watch_path: entity.declarative_watch_path,
+      launch_at_login: entity.launch_at_login,
+      background_agent: entity.background_agent,
+      diagnostic_uploads: entity.diagnostic_uploads,
+      telemetry_enabled: entity.telemetry_enabled,
+      telemetry_sent_at: entity.telemetry_sent_at.map(|dt| dt.to_rfc3339()),
+      displayplacer_path: entity.displayplacer_path,
     }
   }
 }
@@ -571,6 +1209,7 @@ impl From<ActivityLogEntity> for ActivityLogDto {
       status: entity.status,
       error_message: entity.error_message,
       duration_ms: entity.duration_ms,
+      request_id: entity.request_id,
       created_at: entity.created_at.to_rfc3339(),
     }
   }
@@ -588,6 +1227,7 @@ impl From<SystemEventEntity> for SystemEventDto {
       stack_trace: entity.stack_trace,
       os_info: entity.os_info,
       app_version: entity.app_version,
+      request_id: entity.request_id,
       created_at: entity.created_at.to_rfc3339(),
     }
   }
@@ -599,11 +1239,11 @@ impl From<ProfileActivationEntity> for ProfileActivationDto {
       id: entity.id.to_string(),
       user_id: entity.user_id.to_string(),
       profile_id: entity.profile_id.to_string(),
-      profile_name: None, // Set by service layer
+      profile_name: None, // Joined in by `ProfileActivationRow`
       session_id: entity.session_id.map(|id| id.to_string()),
       activation_source: entity.activation_source,
       previous_profile_id: entity.previous_profile_id.map(|id| id.to_string()),
-      previous_profile_name: None, // Set by service layer
+      previous_profile_name: None, // Joined in by `ProfileActivationRow`
       monitors_detected: entity.monitors_detected.unwrap_or(0),
       monitors_applied: entity.monitors_applied.unwrap_or(0),
       apps_detected: entity.apps_detected.unwrap_or(0),
@@ -645,6 +1285,10 @@ impl From<ErrorLogEntity> for ErrorLogDto {
       first_occurred_at: entity.first_occurred_at.to_rfc3339(),
       last_occurred_at: entity.last_occurred_at.to_rfc3339(),
       created_at: entity.created_at.to_rfc3339(),
+      fingerprint: entity.fingerprint,
+      regression_count: entity.regression_count,
+      reopened_at: entity.reopened_at.map(|dt| dt.to_rfc3339()),
+      request_id: entity.request_id,
     }
   }
 }
@@ -681,10 +1325,10 @@ impl From<AutomationExecutionEntity> for AutomationExecutionDto {
     Self {
       id: entity.id.to_string(),
       rule_id: entity.rule_id.to_string(),
-      rule_name: None, // Set by service layer
+      rule_name: None, // Joined in by `AutomationExecutionRow`
       user_id: entity.user_id.to_string(),
       profile_id: entity.profile_id.map(|id| id.to_string()),
-      profile_name: None, // Set by service layer
+      profile_name: None, // Joined in by `AutomationExecutionRow`
       trigger_type: entity.trigger_type,
       trigger_details: entity.trigger_details,
       success: entity.success,
@@ -696,6 +1340,26 @@ impl From<AutomationExecutionEntity> for AutomationExecutionDto {
   }
 }
 
+impl From<ProfileActivationRow> for ProfileActivationDto {
+  fn from(row: ProfileActivationRow) -> Self {
+    Self {
+      profile_name: row.profile_name,
+      previous_profile_name: row.previous_profile_name,
+      ..Self::from(row.activation)
+    }
+  }
+}
+
+impl From<AutomationExecutionRow> for AutomationExecutionDto {
+  fn from(row: AutomationExecutionRow) -> Self {
+    Self {
+      rule_name: row.rule_name,
+      profile_name: row.profile_name,
+      ..Self::from(row.execution)
+    }
+  }
+}
+
 impl From<MonitorChangeEntity> for MonitorChangeDto {
   fn from(entity: MonitorChangeEntity) -> Self {
     Self {
@@ -709,6 +1373,7 @@ impl From<MonitorChangeEntity> for MonitorChangeDto {
       auto_profile_activated: entity.auto_profile_activated.unwrap_or(false),
       activated_profile_id: entity.activated_profile_id.map(|id| id.to_string()),
       activated_profile_name: None, // Set by service layer
+      summary: entity.summary,
     }
   }
 }
@@ -735,6 +1400,20 @@ impl From<AppLaunchEntity> for AppLaunchDto {
   }
 }
 
+impl From<DailyStatsRow> for DailyStatsDto {
+  fn from(row: DailyStatsRow) -> Self {
+    Self {
+      day: row.stats.day.to_string(),
+      profile_id: row.stats.profile_id.map(|id| id.to_string()),
+      profile_name: row.profile_name,
+      activations: row.stats.activations,
+      app_launches: row.stats.app_launches,
+      errors: row.stats.errors,
+      active_minutes: row.stats.active_minutes,
+    }
+  }
+}
+
 // ============================================================================
 // Feedback DTOs
 // ============================================================================
@@ -749,7 +1428,7 @@ pub struct FeedbackDto {
   pub title: String,
   pub description: String,
   pub priority: String,
-  pub status: String,
+  pub status: FeedbackStatus,
   pub category: Option<String>,
   pub contact_email: Option<String>,
   pub app_version: Option<String>,
@@ -760,15 +1439,25 @@ pub struct FeedbackDto {
 }
 
 /// Create feedback request
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateFeedbackRequest {
+  #[validate(custom(function = validation::feedback_type))]
   pub feedback_type: String,
+  #[validate(length(max = 200), custom(function = validation::not_blank))]
   pub title: String,
+  #[validate(length(max = 5000), custom(function = validation::not_blank))]
   pub description: String,
+  #[validate(custom(function = validation::feedback_priority))]
   pub priority: Option<String>,
+  #[validate(length(max = 100))]
   pub category: Option<String>,
+  #[validate(email)]
   pub contact_email: Option<String>,
+  /// `blob:` references of attachments stored with `store_blob`
+  #[serde(default)]
+  #[validate(length(max = 5))]
+  pub attachments: Vec<String>,
 }
 
 impl From<FeedbackEntity> for FeedbackDto {
@@ -780,7 +1469,7 @@ impl From<FeedbackEntity> for FeedbackDto {
       title: entity.title,
       description: entity.description,
       priority: entity.priority.unwrap_or_else(|| "medium".to_string()),
-      status: entity.status.unwrap_or_else(|| "open".to_string()),
+      status: entity.status.unwrap_or(FeedbackStatus::Open),
       category: entity.category,
       contact_email: entity.contact_email,
       app_version: entity.app_version,
@@ -791,3 +1480,137 @@ impl From<FeedbackEntity> for FeedbackDto {
     }
   }
 }
+
+// ============================================================================
+// Alert DTOs
+// ============================================================================
+
+/// Alert rule DTO
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRuleDto {
+  pub id: String,
+  pub name: String,
+  pub metric: String,
+  pub threshold: f64,
+  pub window_minutes: i32,
+  pub min_samples: i32,
+  pub severity: Severity,
+  pub cooldown_minutes: i32,
+  pub is_enabled: bool,
+  pub updated_at: String,
+}
+
+/// Alert DTO - a rule that fired
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertDto {
+  pub id: String,
+  pub rule_id: String,
+  pub rule_name: String,
+  pub severity: Severity,
+  pub value: f64,
+  pub threshold: f64,
+  pub message: String,
+  pub triggered_at: String,
+  pub resolved_at: Option<String>,
+}
+
+impl From<AlertRuleEntity> for AlertRuleDto {
+  fn from(entity: AlertRuleEntity) -> Self {
+    Self {
+      id: entity.id.to_string(),
+      name: entity.name,
+      metric: entity.metric,
+      threshold: entity.threshold,
+      window_minutes: entity.window_minutes,
+      min_samples: entity.min_samples,
+      severity: entity.severity,
+      cooldown_minutes: entity.cooldown_minutes,
+      is_enabled: entity.is_enabled,
+      updated_at: entity.updated_at.to_rfc3339(),
+    }
+  }
+}
+
+impl AlertDto {
+  pub fn from_entity(entity: AlertEntity, rule: &AlertRuleEntity) -> Self {
+    Self {
+      id: entity.id.to_string(),
+      rule_id: entity.rule_id.to_string(),
+      rule_name: rule.name.clone(),
+      severity: rule.severity,
+      value: entity.value,
+      threshold: entity.threshold,
+      message: entity.message,
+      triggered_at: entity.triggered_at.to_rfc3339(),
+      resolved_at: entity.resolved_at.map(|dt| dt.to_rfc3339()),
+    }
+  }
+}
+
+// ============================================================================
+// Team DTOs
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamDto {
+  pub id: TeamId,
+  pub name: String,
+  pub organization_id: String,
+  pub organization_name: String,
+  /// The current user's role
+  pub role: TeamRole,
+}
+
+/// A profile in a team library, summarized from its shared copy
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamLibraryProfileDto {
+  pub id: String,
+  pub team_id: TeamId,
+  pub name: String,
+  pub description: Option<String>,
+  pub profile_type: ProfileType,
+  pub tags: Vec<String>,
+  pub monitor_count: i64,
+  pub app_count: i64,
+  pub browser_tab_count: i64,
+  pub published_by: UserId,
+  pub published_at: String,
+}
+
+impl From<TeamMembershipRow> for TeamDto {
+  fn from(row: TeamMembershipRow) -> Self {
+    Self {
+      id: row.team_id.into(),
+      name: row.team_name,
+      organization_id: row.organization_id.to_string(),
+      organization_name: row.organization_name,
+      role: row.role,
+    }
+  }
+}
+
+impl From<FocusSessionEntity> for FocusSessionDto {
+  fn from(entity: FocusSessionEntity) -> Self {
+    let ends_at = entity.ends_at();
+    let remaining_seconds = (entity.status == FocusSessionStatus::Active)
+      .then(|| (ends_at - chrono::Utc::now()).num_seconds().max(0));
+    Self {
+      id: entity.id.to_string(),
+      profile_id: entity.profile_id.to_string(),
+      planned_minutes: entity.planned_minutes,
+      status: entity.status,
+      blocked_apps: entity.blocked_apps(),
+      blocked_urls: entity.blocked_urls(),
+      dnd_enabled: entity.dnd_shortcut.is_some(),
+      blocked_app_quits: entity.blocked_app_quits,
+      started_at: entity.started_at.to_rfc3339(),
+      ends_at: ends_at.to_rfc3339(),
+      ended_at: entity.ended_at.map(|dt| dt.to_rfc3339()),
+      remaining_seconds,
+    }
+  }
+}
@@ -1,7 +1,12 @@
 // Database entities - match PostgreSQL schema exactly
 // These are internal types used for database operations
 
-use chrono::{DateTime, Utc};
+use super::enums::{
+  ActivationSource, ActivityStatus, BlockedAppAction, DeactivationActionType, FeedbackStatus,
+  FocusSessionStatus, MonitorChangeType, ProfileType, ReportFormat, RuleActionType, Severity,
+  SyncScope, TeamRole,
+};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -14,7 +19,7 @@ pub struct ProfileEntity {
   pub name: String,
   pub description: Option<String>,
   #[sqlx(rename = "type")]
-  pub profile_type: String,
+  pub profile_type: ProfileType,
   pub is_active: bool,
   pub created_at: DateTime<Utc>,
   pub updated_at: DateTime<Utc>,
@@ -26,6 +31,19 @@ pub struct ProfileEntity {
   pub color: Option<String>,
   pub icon: Option<String>,
   pub sort_order: Option<i32>,
+  /// Skip heavy activation steps on battery below this percentage
+  pub low_power_battery_threshold: Option<i32>,
+  /// Kept out of cloud sync regardless of the sync scope
+  pub local_only: bool,
+  /// Monitor setup the profile is meant for, used to pick it automatically
+  pub required_monitor_count: Option<i32>,
+  pub monitor_signature: Option<String>,
+  /// Markdown, sanitized when saved
+  pub notes: Option<String>,
+  /// Match and lay out Sidecar, AirPlay and virtual displays too
+  pub include_virtual_displays: bool,
+  /// Edits are rejected until it's unlocked
+  pub is_locked: bool,
 }
 
 /// Monitor entity - maps directly to monitors table
@@ -51,6 +69,11 @@ pub struct MonitorEntity {
   pub color_depth: Option<i32>,
   pub created_at: Option<DateTime<Utc>>,
   pub updated_at: Option<DateTime<Utc>>,
+  /// Identity of the physical display, set when the monitor came from a capture
+  pub fingerprint: Option<String>,
+  pub captured_at: Option<DateTime<Utc>>,
+  /// Kept dark while the profile is active
+  pub blanked: bool,
 }
 
 /// App entity - maps directly to apps table
@@ -88,6 +111,19 @@ pub struct BrowserTabEntity {
   pub updated_at: Option<DateTime<Utc>>,
 }
 
+/// ProfileFile entity - maps directly to profile_files table
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ProfileFileEntity {
+  pub id: Uuid,
+  pub profile_id: Uuid,
+  pub path: String,
+  pub target_app: Option<String>,
+  pub open_on_activate: bool,
+  pub open_order: i32,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
 /// AutomationRule entity - maps directly to automation_rules table
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct AutomationRuleEntity {
@@ -95,10 +131,64 @@ pub struct AutomationRuleEntity {
   pub profile_id: Uuid,
   pub rule_type: String,
   pub trigger_config: serde_json::Value,
+  pub conditions: Option<serde_json::Value>,
+  /// Higher runs first when several rules match the same trigger
+  pub priority: i32,
+  /// Skip lower-priority rules once this one is chosen
+  pub stop_after_match: bool,
+  pub cooldown_minutes: i32,
+  pub last_triggered_at: Option<DateTime<Utc>>,
+  pub snoozed_until: Option<DateTime<Utc>>,
+  /// Windows the rule may act in; NULL means any time
+  pub active_hours: Option<serde_json::Value>,
   pub is_enabled: bool,
   pub created_at: DateTime<Utc>,
 }
 
+/// RuleAction entity - maps directly to rule_actions table
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct RuleActionEntity {
+  pub id: Uuid,
+  pub rule_id: Uuid,
+  pub position: i32,
+  pub action_type: RuleActionType,
+  pub config: serde_json::Value,
+  pub created_at: DateTime<Utc>,
+}
+
+/// ProfileDeactivationAction entity - maps directly to profile_deactivation_actions table
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ProfileDeactivationActionEntity {
+  pub id: Uuid,
+  pub profile_id: Uuid,
+  pub position: i32,
+  pub action_type: DeactivationActionType,
+  pub config: serde_json::Value,
+  pub created_at: DateTime<Utc>,
+}
+
+/// An app a profile keeps closed - maps to profile_blocked_apps table
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ProfileBlockedAppEntity {
+  pub id: Uuid,
+  pub profile_id: Uuid,
+  pub bundle_id: String,
+  pub app_name: String,
+  pub action: BlockedAppAction,
+  pub created_at: DateTime<Utc>,
+}
+
+/// Something to check after a profile activates - maps to
+/// profile_checklist_items table
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ProfileChecklistItemEntity {
+  pub id: Uuid,
+  pub profile_id: Uuid,
+  pub position: i32,
+  pub label: String,
+  pub created_at: DateTime<Utc>,
+}
+
 /// UserSettings entity - maps directly to user_settings table
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct UserSettingsEntity {
@@ -122,6 +212,32 @@ pub struct UserSettingsEntity {
   pub feature_flags: Option<serde_json::Value>,
   pub keyboard_shortcuts: Option<serde_json::Value>,
   pub ui_preferences: Option<serde_json::Value>,
+  pub log_retention_days: i32,
+  pub auto_cleanup_logs: bool,
+  /// Where sunrise/sunset schedules are computed for; unset falls back to CoreLocation
+  pub latitude: Option<f64>,
+  pub longitude: Option<f64>,
+  pub sync_scope: SyncScope,
+  /// `MonitorProfileMapping`s as stored
+  pub monitor_profile_mappings: serde_json::Value,
+  /// Save a usage report for the previous week every Monday morning
+  pub weekly_report_enabled: bool,
+  pub weekly_report_format: ReportFormat,
+  /// Daily profile snapshots kept on disk; 0 turns them off
+  pub snapshots_kept: i32,
+  /// Declarative profile file synced whenever it changes
+  pub declarative_watch_path: Option<String>,
+  /// Start Smoothie when the user logs in
+  pub launch_at_login: bool,
+  /// Run without a Dock icon and keep running when the window is closed
+  pub background_agent: bool,
+  /// Upload an encrypted diagnostic bundle when a critical error occurs
+  pub diagnostic_uploads: bool,
+  /// Send anonymous usage counters once a week
+  pub telemetry_enabled: bool,
+  pub telemetry_sent_at: Option<DateTime<Utc>>,
+  /// displayplacer to use instead of the one found on the system
+  pub displayplacer_path: Option<String>,
 }
 
 // ============================================================================
@@ -141,9 +257,11 @@ pub struct ActivityLogEntity {
   pub details: Option<serde_json::Value>,
   pub ip_address: Option<String>,
   pub user_agent: Option<String>,
-  pub status: String,
+  pub status: ActivityStatus,
   pub error_message: Option<String>,
   pub duration_ms: Option<i32>,
+  /// IPC call that wrote the row
+  pub request_id: Option<String>,
   pub created_at: DateTime<Utc>,
 }
 
@@ -152,13 +270,14 @@ pub struct ActivityLogEntity {
 pub struct SystemEventEntity {
   pub id: Uuid,
   pub event_type: String,
-  pub severity: String,
+  pub severity: Severity,
   pub source: String,
   pub message: String,
   pub details: Option<serde_json::Value>,
   pub stack_trace: Option<String>,
   pub os_info: Option<serde_json::Value>,
   pub app_version: Option<String>,
+  pub request_id: Option<String>,
   pub created_at: DateTime<Utc>,
 }
 
@@ -169,7 +288,7 @@ pub struct ProfileActivationEntity {
   pub user_id: Uuid,
   pub profile_id: Uuid,
   pub session_id: Option<Uuid>,
-  pub activation_source: String,
+  pub activation_source: ActivationSource,
   pub previous_profile_id: Option<Uuid>,
   pub monitors_detected: Option<i32>,
   pub monitors_applied: Option<i32>,
@@ -188,6 +307,16 @@ pub struct ProfileActivationEntity {
   pub completed_at: Option<DateTime<Utc>>,
 }
 
+/// What went wrong in a failed activation - maps to activation_reports table
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ActivationReportEntity {
+  pub id: Uuid,
+  pub activation_id: Uuid,
+  pub profile_id: Uuid,
+  pub report: serde_json::Value,
+  pub created_at: DateTime<Utc>,
+}
+
 /// Error log entity - persistent error tracking
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct ErrorLogEntity {
@@ -202,7 +331,7 @@ pub struct ErrorLogEntity {
   pub source_file: Option<String>,
   pub source_line: Option<i32>,
   pub source_function: Option<String>,
-  pub severity: String,
+  pub severity: Severity,
   pub is_resolved: Option<bool>,
   pub resolved_at: Option<DateTime<Utc>>,
   pub resolution_notes: Option<String>,
@@ -210,6 +339,11 @@ pub struct ErrorLogEntity {
   pub first_occurred_at: DateTime<Utc>,
   pub last_occurred_at: DateTime<Utc>,
   pub created_at: DateTime<Utc>,
+  pub fingerprint: Option<String>,
+  pub regression_count: i32,
+  pub reopened_at: Option<DateTime<Utc>>,
+  /// Most recent IPC call that hit this error
+  pub request_id: Option<String>,
 }
 
 /// Session entity - tracks user sessions
@@ -232,6 +366,62 @@ pub struct SessionEntity {
   pub metadata: Option<serde_json::Value>,
 }
 
+/// Any record written through an `AuditStore`, tagged with its kind. Backends
+/// that keep a single event log store these instead of one table per kind.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event_type", content = "payload", rename_all = "snake_case")]
+pub enum AuditEvent {
+  Activity(ActivityLogEntity),
+  SystemEvent(SystemEventEntity),
+  Session(SessionEntity),
+  ProfileActivation(ProfileActivationEntity),
+  Error(ErrorLogEntity),
+}
+
+impl AuditEvent {
+  /// Id of the wrapped record
+  pub fn record_id(&self) -> Uuid {
+    match self {
+      AuditEvent::Activity(e) => e.id,
+      AuditEvent::SystemEvent(e) => e.id,
+      AuditEvent::Session(e) => e.id,
+      AuditEvent::ProfileActivation(e) => e.id,
+      AuditEvent::Error(e) => e.id,
+    }
+  }
+
+  pub fn user_id(&self) -> Option<Uuid> {
+    match self {
+      AuditEvent::Activity(e) => Some(e.user_id),
+      AuditEvent::SystemEvent(_) => None,
+      AuditEvent::Session(e) => Some(e.user_id),
+      AuditEvent::ProfileActivation(e) => Some(e.user_id),
+      AuditEvent::Error(e) => e.user_id,
+    }
+  }
+
+  pub fn session_id(&self) -> Option<Uuid> {
+    match self {
+      AuditEvent::Activity(e) => e.session_id,
+      AuditEvent::SystemEvent(_) => None,
+      AuditEvent::Session(e) => Some(e.id),
+      AuditEvent::ProfileActivation(e) => e.session_id,
+      AuditEvent::Error(e) => e.session_id,
+    }
+  }
+
+  /// When the record last changed; a session that ends is a new event
+  pub fn occurred_at(&self) -> DateTime<Utc> {
+    match self {
+      AuditEvent::Activity(e) => e.created_at,
+      AuditEvent::SystemEvent(e) => e.created_at,
+      AuditEvent::Session(e) => e.ended_at.unwrap_or(e.started_at),
+      AuditEvent::ProfileActivation(e) => e.started_at,
+      AuditEvent::Error(e) => e.last_occurred_at,
+    }
+  }
+}
+
 /// Automation execution entity - tracks automation rule executions
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct AutomationExecutionEntity {
@@ -248,18 +438,38 @@ pub struct AutomationExecutionEntity {
   pub executed_at: DateTime<Utc>,
 }
 
+/// Profile activation with profile names joined in, for history views
+#[derive(Debug, Clone, FromRow)]
+pub struct ProfileActivationRow {
+  #[sqlx(flatten)]
+  pub activation: ProfileActivationEntity,
+  pub profile_name: Option<String>,
+  pub previous_profile_name: Option<String>,
+}
+
+/// Automation execution with rule and profile names joined in
+#[derive(Debug, Clone, FromRow)]
+pub struct AutomationExecutionRow {
+  #[sqlx(flatten)]
+  pub execution: AutomationExecutionEntity,
+  pub rule_name: Option<String>,
+  pub profile_name: Option<String>,
+}
+
 /// Monitor change entity - tracks monitor configuration changes
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct MonitorChangeEntity {
   pub id: Uuid,
   pub user_id: Option<Uuid>,
   pub session_id: Option<Uuid>,
-  pub change_type: String,
+  pub change_type: MonitorChangeType,
   pub monitors_before: Option<serde_json::Value>,
   pub monitors_after: Option<serde_json::Value>,
   pub detected_at: DateTime<Utc>,
   pub auto_profile_activated: Option<bool>,
   pub activated_profile_id: Option<Uuid>,
+  /// Readable description of the change, e.g. "Dell U2720Q disconnected"
+  pub summary: Option<String>,
 }
 
 /// App launch entity - tracks individual app launches
@@ -290,7 +500,7 @@ pub struct FeedbackEntity {
   pub title: String,
   pub description: String,
   pub priority: Option<String>,
-  pub status: Option<String>,
+  pub status: Option<FeedbackStatus>,
   pub category: Option<String>,
   pub contact_email: Option<String>,
   pub app_version: Option<String>,
@@ -314,3 +524,237 @@ pub struct SubscriptionEntity {
   pub created_at: DateTime<Utc>,
   pub updated_at: DateTime<Utc>,
 }
+
+/// Alert rule entity - maps directly to alert_rules table
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AlertRuleEntity {
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub name: String,
+  pub metric: String,
+  pub threshold: f64,
+  pub window_minutes: i32,
+  pub min_samples: i32,
+  pub severity: Severity,
+  pub cooldown_minutes: i32,
+  pub is_enabled: bool,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+/// Alert entity - maps directly to alerts table
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AlertEntity {
+  pub id: Uuid,
+  pub rule_id: Uuid,
+  pub user_id: Uuid,
+  pub value: f64,
+  pub threshold: f64,
+  pub message: String,
+  pub triggered_at: DateTime<Utc>,
+  pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Daily stats entity - one row per user, day and active profile
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct DailyStatsEntity {
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub day: NaiveDate,
+  pub profile_id: Option<Uuid>,
+  pub activations: i32,
+  pub app_launches: i32,
+  pub errors: i32,
+  pub active_minutes: i32,
+  pub updated_at: DateTime<Utc>,
+}
+
+/// Daily stats with the profile name joined in
+#[derive(Debug, Clone, FromRow)]
+pub struct DailyStatsRow {
+  #[sqlx(flatten)]
+  pub stats: DailyStatsEntity,
+  pub profile_name: Option<String>,
+}
+
+/// Focus time for one app from `AuditRepository::get_focus_breakdown`
+#[derive(Debug, Clone, FromRow)]
+pub struct AppFocusRow {
+  pub bundle_id: String,
+  pub app_name: String,
+  pub focus_ms: i64,
+  pub sessions: i64,
+}
+
+/// A profile's activations and focus time over a usage report's range
+#[derive(Debug, Clone, FromRow)]
+pub struct ProfileUsageRow {
+  pub profile_id: Uuid,
+  pub name: String,
+  pub activations: i64,
+  pub focus_ms: i64,
+}
+
+/// Errors with the same type and message over a usage report's range
+#[derive(Debug, Clone, FromRow)]
+pub struct ErrorSummaryRow {
+  pub error_type: String,
+  pub message: String,
+  pub severity: Severity,
+  pub occurrences: i64,
+  pub last_occurred_at: DateTime<Utc>,
+}
+
+/// Error log entries sharing a fingerprint
+#[derive(Debug, Clone, FromRow)]
+pub struct ErrorGroupRow {
+  pub fingerprint: String,
+  pub error_type: String,
+  /// Message of the latest entry
+  pub message: String,
+  pub severity: Severity,
+  pub occurrences: i64,
+  pub first_seen: DateTime<Utc>,
+  pub last_seen: DateTime<Utc>,
+  pub resolved: bool,
+  pub regressions: i64,
+}
+
+/// Focus session entity - maps to focus_sessions table
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct FocusSessionEntity {
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub profile_id: Uuid,
+  pub planned_minutes: i32,
+  pub status: FocusSessionStatus,
+  pub blocked_apps: serde_json::Value,
+  pub blocked_urls: serde_json::Value,
+  pub dnd_shortcut: Option<String>,
+  pub dnd_off_shortcut: Option<String>,
+  pub blocked_app_quits: i32,
+  pub started_at: DateTime<Utc>,
+  pub ended_at: Option<DateTime<Utc>>,
+}
+
+impl FocusSessionEntity {
+  /// When the session is due to complete
+  pub fn ends_at(&self) -> DateTime<Utc> {
+    self.started_at + chrono::Duration::minutes(self.planned_minutes as i64)
+  }
+
+  pub fn blocked_apps(&self) -> Vec<String> {
+    serde_json::from_value(self.blocked_apps.clone()).unwrap_or_default()
+  }
+
+  pub fn blocked_urls(&self) -> Vec<String> {
+    serde_json::from_value(self.blocked_urls.clone()).unwrap_or_default()
+  }
+}
+
+/// Single-row dashboard counters from `AuditRepository::get_dashboard_counts`
+#[derive(Debug, Clone, FromRow)]
+pub struct DashboardCountsRow {
+  pub total_profiles: i64,
+  pub total_activity_logs: i64,
+  pub activations_today: i64,
+  pub activations_week: i64,
+  pub last_activation_at: Option<DateTime<Utc>>,
+  pub total_errors: i64,
+  pub unresolved_errors: i64,
+  pub focus_sessions_week: i64,
+  pub focus_minutes_week: i64,
+}
+
+/// Most used profile and open session, if any, from `AuditRepository::get_dashboard_highlights`
+#[derive(Debug, Clone, FromRow)]
+pub struct DashboardHighlightsRow {
+  pub most_used_profile_id: Option<Uuid>,
+  pub most_used_profile_name: Option<String>,
+  pub most_used_profile_count: i64,
+  pub active_session_id: Option<Uuid>,
+  pub session_started_at: Option<DateTime<Utc>>,
+}
+
+/// Per-table totals for the log summary
+#[derive(Debug, Clone, FromRow)]
+pub struct LogTotalsRow {
+  pub total_activity_logs: i64,
+  pub total_system_events: i64,
+  pub total_profile_activations: i64,
+  pub total_error_logs: i64,
+  pub total_sessions: i64,
+}
+
+// ============================================================================
+// Team workspaces, mirrored from Supabase
+// ============================================================================
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct OrganizationEntity {
+  pub id: Uuid,
+  pub name: String,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct TeamEntity {
+  pub id: Uuid,
+  pub organization_id: Uuid,
+  pub name: String,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct TeamMemberEntity {
+  pub team_id: Uuid,
+  pub user_id: Uuid,
+  pub role: TeamRole,
+  pub joined_at: DateTime<Utc>,
+}
+
+/// A team member's own view of a team, from `TeamRepository::find_teams`
+#[derive(Debug, Clone, FromRow)]
+pub struct TeamMembershipRow {
+  pub team_id: Uuid,
+  pub team_name: String,
+  pub organization_id: Uuid,
+  pub organization_name: String,
+  pub role: TeamRole,
+}
+
+/// A profile published to a team library; `document` is a `SharedProfile`
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct TeamLibraryProfileEntity {
+  pub id: Uuid,
+  pub team_id: Uuid,
+  pub name: String,
+  pub published_by: Uuid,
+  pub published_at: DateTime<Utc>,
+  pub document: serde_json::Value,
+}
+
+/// What folder sync last wrote or applied for one profile bundle. `profile_id`
+/// goes null when the local profile is deleted, until the deletion is synced.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct FolderSyncItemEntity {
+  pub sync_id: Uuid,
+  pub user_id: Uuid,
+  pub profile_id: Option<Uuid>,
+  pub content_hash: String,
+  pub modified_at: DateTime<Utc>,
+  pub deleted: bool,
+  pub synced_at: DateTime<Utc>,
+}
+
+/// A profile created from an entry in a declarative file, with the hash of
+/// that entry when it was last applied
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct DeclarativeProfileEntity {
+  pub user_id: Uuid,
+  pub source_path: String,
+  pub profile_key: String,
+  pub profile_id: Uuid,
+  pub content_hash: String,
+  pub synced_at: DateTime<Utc>,
+}
@@ -0,0 +1,294 @@
+// Enumerated column values - stored as TEXT, checked by CHECK constraints (migration v6)
+//
+// Each enum serializes to the same lowercase string it is stored as. Parsing is
+// case-insensitive because the UI sends display casing ("Work", "Landscape").
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef};
+use sqlx::{Decode, Encode, Postgres, Type};
+
+macro_rules! string_enum {
+  (
+    $(#[$meta:meta])*
+    pub enum $name:ident {
+      $($variant:ident => $value:literal),+ $(,)?
+    }
+  ) => {
+    $(#[$meta])*
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum $name {
+      $($variant),+
+    }
+
+    impl $name {
+      pub const ALL: &'static [$name] = &[$($name::$variant),+];
+
+      pub fn as_str(&self) -> &'static str {
+        match self {
+          $($name::$variant => $value),+
+        }
+      }
+
+      /// Stored values, for error messages and CHECK constraints
+      pub fn values() -> Vec<&'static str> {
+        Self::ALL.iter().map(Self::as_str).collect()
+      }
+    }
+
+    impl std::fmt::Display for $name {
+      fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+      }
+    }
+
+    impl std::str::FromStr for $name {
+      type Err = String;
+
+      fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Self::ALL
+          .iter()
+          .copied()
+          .find(|v| v.as_str().eq_ignore_ascii_case(s))
+          .ok_or_else(|| format!("Must be one of: {}", Self::values().join(", ")))
+      }
+    }
+
+    impl Serialize for $name {
+      fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+      }
+    }
+
+    impl<'de> Deserialize<'de> for $name {
+      fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+      }
+    }
+
+    impl Type<Postgres> for $name {
+      fn type_info() -> PgTypeInfo {
+        <str as Type<Postgres>>::type_info()
+      }
+
+      fn compatible(ty: &PgTypeInfo) -> bool {
+        <str as Type<Postgres>>::compatible(ty)
+      }
+    }
+
+    impl<'q> Encode<'q, Postgres> for $name {
+      fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+      }
+    }
+
+    impl<'r> Decode<'r, Postgres> for $name {
+      fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        Ok(s.parse()?)
+      }
+    }
+  };
+}
+
+string_enum! {
+  pub enum ProfileType {
+    Work => "work",
+    Personal => "personal",
+    Gaming => "gaming",
+    Research => "research",
+    Custom => "custom",
+  }
+}
+
+string_enum! {
+  /// Severity of system events, error logs and alert rules
+  pub enum Severity {
+    Debug => "debug",
+    Info => "info",
+    Warning => "warning",
+    Error => "error",
+    Critical => "critical",
+  }
+}
+
+string_enum! {
+  /// What triggered a profile activation
+  pub enum ActivationSource {
+    Manual => "manual",
+    Shortcut => "shortcut",
+    Schedule => "schedule",
+    Automation => "automation",
+    MonitorChange => "monitor_change",
+    Launcher => "launcher",
+  }
+}
+
+string_enum! {
+  /// Outcome of a logged activity
+  pub enum ActivityStatus {
+    Success => "success",
+    Failure => "failure",
+    Pending => "pending",
+  }
+}
+
+string_enum! {
+  pub enum MonitorChangeType {
+    Connected => "connected",
+    Disconnected => "disconnected",
+    ResolutionChanged => "resolution_changed",
+    ArrangementChanged => "arrangement_changed",
+    PrimaryChanged => "primary_changed",
+  }
+}
+
+string_enum! {
+  /// Steps an automation rule can take when it fires
+  pub enum RuleActionType {
+    ActivateProfile => "activate_profile",
+    RunHook => "run_hook",
+    SendNotification => "send_notification",
+    SetFocusMode => "set_focus_mode",
+    OpenUrl => "open_url",
+  }
+}
+
+string_enum! {
+  /// Cleanup a profile runs when another profile takes over
+  pub enum DeactivationActionType {
+    QuitApps => "quit_apps",
+    CloseTabs => "close_tabs",
+    RestoreAudio => "restore_audio",
+  }
+}
+
+string_enum! {
+  /// What happens to a blocked app launched while its profile is active
+  pub enum BlockedAppAction {
+    Hide => "hide",
+    Quit => "quit",
+  }
+}
+
+string_enum! {
+  /// What cloud sync uploads; local-only profiles are left out under every scope
+  pub enum SyncScope {
+    Profiles => "profiles",
+    ProfilesAndSettings => "profiles_and_settings",
+    All => "all",
+  }
+}
+
+impl SyncScope {
+  pub fn includes_settings(&self) -> bool {
+    !matches!(self, SyncScope::Profiles)
+  }
+
+  pub fn includes_logs(&self) -> bool {
+    matches!(self, SyncScope::All)
+  }
+}
+
+string_enum! {
+  /// File format usage reports are rendered in
+  pub enum ReportFormat {
+    Markdown => "markdown",
+    Html => "html",
+  }
+}
+
+impl ReportFormat {
+  pub fn extension(&self) -> &'static str {
+    match self {
+      ReportFormat::Markdown => "md",
+      ReportFormat::Html => "html",
+    }
+  }
+}
+
+string_enum! {
+  /// A member's role in a team
+  pub enum TeamRole {
+    Admin => "admin",
+    Member => "member",
+  }
+}
+
+impl TeamRole {
+  /// Whether this role may do what `required` may; admins may do anything
+  pub fn grants(&self, required: TeamRole) -> bool {
+    *self == TeamRole::Admin || *self == required
+  }
+}
+
+string_enum! {
+  /// Where a focus session stands; only one per user is active at a time
+  pub enum FocusSessionStatus {
+    Active => "active",
+    Completed => "completed",
+    Cancelled => "cancelled",
+  }
+}
+
+string_enum! {
+  /// Why a profile snapshot was taken; `PreRestore` ones let a restore be undone
+  pub enum SnapshotKind {
+    Daily => "daily",
+    PreRestore => "pre_restore",
+  }
+}
+
+string_enum! {
+  /// What kind of file a bookmark import read
+  pub enum BookmarkSource {
+    ChromeBookmarks => "chrome_bookmarks",
+    FirefoxBookmarks => "firefox_bookmarks",
+    FirefoxSession => "firefox_session",
+  }
+}
+
+string_enum! {
+  pub enum FeedbackStatus {
+    Open => "open",
+    InProgress => "in_progress",
+    Resolved => "resolved",
+    Closed => "closed",
+  }
+}
+
+impl Severity {
+  /// Errors and worse count toward `METRICS.total_errors`
+  pub fn is_error(&self) -> bool {
+    match self {
+      Severity::Error | Severity::Critical => true,
+      Severity::Debug | Severity::Info | Severity::Warning => false,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_is_case_insensitive_and_round_trips() {
+    assert_eq!("Work".parse::<ProfileType>(), Ok(ProfileType::Work));
+    assert_eq!(
+      " monitor_change ".parse::<ActivationSource>(),
+      Ok(ActivationSource::MonitorChange)
+    );
+    assert!("wrok".parse::<ProfileType>().is_err());
+
+    let json = serde_json::to_string(&FeedbackStatus::InProgress).unwrap();
+    assert_eq!(json, "\"in_progress\"");
+    assert_eq!(
+      serde_json::from_str::<FeedbackStatus>(&json).unwrap(),
+      FeedbackStatus::InProgress
+    );
+    assert!(serde_json::from_str::<Severity>("\"fatal\"").is_err());
+  }
+}
@@ -0,0 +1,146 @@
+// Typed identifiers - UUIDs that know what they identify
+//
+// Commands receive ids as strings. Parsing them into these newtypes once, at
+// the edge, gives every malformed id the same "Invalid <kind> id" error and
+// keeps a user id from being passed where a profile id is expected. They
+// serialize back to the plain hyphenated string and bind as UUID columns.
+
+use crate::error::SmoothieError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef};
+use sqlx::{Decode, Encode, Postgres, Type};
+use uuid::Uuid;
+
+macro_rules! uuid_id {
+  (
+    $(#[$meta:meta])*
+    pub struct $name:ident($kind:literal);
+  ) => {
+    $(#[$meta])*
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct $name(Uuid);
+
+    impl $name {
+      pub fn as_uuid(&self) -> Uuid {
+        self.0
+      }
+    }
+
+    impl From<Uuid> for $name {
+      fn from(uuid: Uuid) -> Self {
+        Self(uuid)
+      }
+    }
+
+    impl From<$name> for Uuid {
+      fn from(id: $name) -> Self {
+        id.0
+      }
+    }
+
+    impl std::fmt::Display for $name {
+      fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+      }
+    }
+
+    impl std::str::FromStr for $name {
+      type Err = SmoothieError;
+
+      fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(s.trim())
+          .map(Self)
+          .map_err(|_| SmoothieError::ValidationError(format!("Invalid {} id: {}", $kind, s)))
+      }
+    }
+
+    impl TryFrom<&str> for $name {
+      type Error = SmoothieError;
+
+      fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+      }
+    }
+
+    impl TryFrom<String> for $name {
+      type Error = SmoothieError;
+
+      fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+      }
+    }
+
+    impl Serialize for $name {
+      fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+      }
+    }
+
+    impl<'de> Deserialize<'de> for $name {
+      fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+      }
+    }
+
+    impl Type<Postgres> for $name {
+      fn type_info() -> PgTypeInfo {
+        <Uuid as Type<Postgres>>::type_info()
+      }
+    }
+
+    impl Encode<'_, Postgres> for $name {
+      fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        <Uuid as Encode<Postgres>>::encode_by_ref(&self.0, buf)
+      }
+    }
+
+    impl<'r> Decode<'r, Postgres> for $name {
+      fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        <Uuid as Decode<Postgres>>::decode(value).map(Self)
+      }
+    }
+  };
+}
+
+uuid_id! {
+  pub struct ProfileId("profile");
+}
+
+uuid_id! {
+  pub struct UserId("user");
+}
+
+impl UserId {
+  /// The local account every command acts as until sign-in is wired up
+  pub const DEFAULT: UserId = UserId(Uuid::from_u128(1));
+}
+
+uuid_id! {
+  pub struct TeamId("team");
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_ids_round_trip_and_reject_garbage() {
+    let raw = "00000000-0000-0000-0000-000000000001";
+    let id: ProfileId = serde_json::from_value(serde_json::json!(raw)).unwrap();
+    assert_eq!(serde_json::to_value(id).unwrap(), serde_json::json!(raw));
+    assert_eq!(UserId::try_from(raw).unwrap(), UserId::DEFAULT);
+
+    let err = "not-a-uuid".parse::<UserId>().unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "Validation error: Invalid user id: not-a-uuid"
+    );
+    assert!(serde_json::from_value::<ProfileId>(serde_json::json!("42"))
+      .unwrap_err()
+      .to_string()
+      .contains("Invalid profile id"));
+  }
+}
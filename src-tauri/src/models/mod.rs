@@ -2,6 +2,10 @@
 
 pub mod dto;
 pub mod entities;
+pub mod enums;
+pub mod ids;
 
 // Re-export commonly used types
 pub use dto::*;
+pub use enums::*;
+pub use ids::{ProfileId, TeamId, UserId};
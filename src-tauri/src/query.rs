@@ -0,0 +1,445 @@
+//! Machine mode for launcher extensions (Raycast, Alfred)
+//!
+//! `smoothie query` reads JSON-RPC 2.0 requests from stdin, one per line, and
+//! answers each with one line on stdout. `smoothie query <method> [arg]`
+//! answers a single request and exits, for launchers that spawn a process per
+//! search. Both go through the same services as the app's commands.
+//!
+//! Methods:
+//! - `list` - every profile
+//! - `search` `{ "query": "..." }` - profiles whose name, description or tags
+//!   contain every word of the query, best matches first
+//! - `activate` `{ "profileId": "..." }` - switch to a profile and start it
+//!
+//! Every method also takes an optional `userId`. Logs go to stderr so stdout
+//! only ever carries responses, and the result shapes below only grow new
+//! fields, never lose or rename them.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::{ActivationSource, ProfileDto, ProfileId, UserId},
+  services::{
+    activation::ActivationPipeline, activation_report::ActivationReportService, system_backend,
+    ChecklistService, OfflineCache, ProfileService,
+  },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// Bumped only for changes that could break an existing extension
+pub const QUERY_API_VERSION: u32 = 1;
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+/// Start of the range JSON-RPC leaves to applications
+const APP_ERROR: i32 = -32000;
+const NOT_FOUND: i32 = -32001;
+
+/// A profile as launchers see it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryProfile {
+  pub id: String,
+  pub name: String,
+  pub description: Option<String>,
+  #[serde(rename = "type")]
+  pub profile_type: String,
+  pub tags: Vec<String>,
+  pub is_active: bool,
+  pub is_favorite: bool,
+  pub icon: Option<String>,
+  pub color: Option<String>,
+  pub last_activated_at: Option<String>,
+}
+
+impl From<ProfileDto> for QueryProfile {
+  fn from(profile: ProfileDto) -> Self {
+    Self {
+      id: profile.id.to_string(),
+      name: profile.name,
+      description: profile.description,
+      profile_type: profile.profile_type.to_string(),
+      tags: profile.tags,
+      is_active: profile.is_active,
+      is_favorite: profile.is_favorite,
+      icon: profile.icon,
+      color: profile.color,
+      last_activated_at: profile.last_activated_at,
+    }
+  }
+}
+
+/// How one activation step went
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryStep {
+  pub step: String,
+  pub status: String,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryActivation {
+  pub profile: QueryProfile,
+  pub steps: Vec<QueryStep>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+  jsonrpc: Option<String>,
+  /// Absent for notifications, which get no response
+  id: Option<Value>,
+  method: String,
+  #[serde(default)]
+  params: Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryParams {
+  user_id: Option<UserId>,
+  query: Option<String>,
+  profile_id: Option<ProfileId>,
+}
+
+/// A JSON-RPC error object
+#[derive(Debug, PartialEq)]
+struct RpcError {
+  code: i32,
+  message: String,
+}
+
+impl RpcError {
+  fn new(code: i32, message: impl Into<String>) -> Self {
+    Self {
+      code,
+      message: message.into(),
+    }
+  }
+}
+
+impl From<SmoothieError> for RpcError {
+  fn from(err: SmoothieError) -> Self {
+    let code = match err {
+      SmoothieError::NotFound(_) => NOT_FOUND,
+      SmoothieError::ValidationError(_) | SmoothieError::InvalidFields(_) => INVALID_PARAMS,
+      _ => APP_ERROR,
+    };
+    Self::new(code, err.to_string())
+  }
+}
+
+fn response(id: Value, result: std::result::Result<Value, RpcError>) -> Value {
+  match result {
+    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+    Err(error) => json!({
+      "jsonrpc": "2.0",
+      "id": id,
+      "error": { "code": error.code, "message": error.message },
+    }),
+  }
+}
+
+/// Profiles containing every word of `query` in their name, description or
+/// tags. Name prefixes rank first, then other name matches, then the rest;
+/// ties go to the most recently activated.
+fn search_profiles(profiles: Vec<ProfileDto>, query: &str) -> Vec<ProfileDto> {
+  let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+  let mut ranked: Vec<(u8, ProfileDto)> = profiles
+    .into_iter()
+    .filter_map(|profile| {
+      let name = profile.name.to_lowercase();
+      let haystack = format!(
+        "{} {} {}",
+        name,
+        profile.description.as_deref().unwrap_or("").to_lowercase(),
+        profile.tags.join(" ").to_lowercase()
+      );
+      if !terms.iter().all(|term| haystack.contains(term)) {
+        return None;
+      }
+      let rank = match terms.first() {
+        Some(first) if name.starts_with(first.as_str()) => 0,
+        Some(first) if name.contains(first.as_str()) => 1,
+        Some(_) => 2,
+        None => 0,
+      };
+      Some((rank, profile))
+    })
+    .collect();
+  ranked.sort_by(|(a_rank, a), (b_rank, b)| {
+    a_rank
+      .cmp(b_rank)
+      .then_with(|| b.last_activated_at.cmp(&a.last_activated_at))
+  });
+  ranked.into_iter().map(|(_, profile)| profile).collect()
+}
+
+/// Answers requests against the database
+pub struct QueryServer {
+  db: Arc<Database>,
+}
+
+impl QueryServer {
+  pub fn new(db: Arc<Database>) -> Self {
+    Self { db }
+  }
+
+  /// Answer one line of input; `None` for notifications
+  pub async fn handle_line(&self, line: &str) -> Option<Value> {
+    let request: RpcRequest = match serde_json::from_str(line) {
+      Ok(request) => request,
+      Err(e) => {
+        let error = if serde_json::from_str::<Value>(line).is_ok() {
+          RpcError::new(INVALID_REQUEST, format!("Invalid request: {}", e))
+        } else {
+          RpcError::new(PARSE_ERROR, format!("Parse error: {}", e))
+        };
+        return Some(response(Value::Null, Err(error)));
+      }
+    };
+    if request.jsonrpc.as_deref() != Some("2.0") {
+      let error = RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\"");
+      return Some(response(request.id.unwrap_or(Value::Null), Err(error)));
+    }
+
+    let result = self.call(&request.method, request.params).await;
+    request.id.map(|id| response(id, result))
+  }
+
+  async fn call(&self, method: &str, params: Value) -> std::result::Result<Value, RpcError> {
+    let params: QueryParams = match params {
+      Value::Null => QueryParams::default(),
+      params => serde_json::from_value(params)
+        .map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid params: {}", e)))?,
+    };
+    let user_id = params.user_id.unwrap_or(UserId::DEFAULT);
+
+    let result = match method {
+      "version" => json!({ "version": QUERY_API_VERSION }),
+      "list" => json!(self.list(user_id).await?),
+      "search" => {
+        let query = params
+          .query
+          .ok_or_else(|| RpcError::new(INVALID_PARAMS, "search needs a query"))?;
+        json!(self.search(user_id, &query).await?)
+      }
+      "activate" => {
+        let profile_id = params
+          .profile_id
+          .ok_or_else(|| RpcError::new(INVALID_PARAMS, "activate needs a profileId"))?;
+        json!(self.activate(user_id, profile_id).await?)
+      }
+      other => {
+        return Err(RpcError::new(
+          METHOD_NOT_FOUND,
+          format!("Unknown method: {}", other),
+        ))
+      }
+    };
+    Ok(result)
+  }
+
+  /// The user's profiles, from the offline cache while the database is down
+  async fn profiles(&self, user_id: UserId) -> Result<Vec<ProfileDto>> {
+    OfflineCache::or_cached(
+      ProfileService::get_profiles(&self.db, user_id).await,
+      "profiles",
+      || OfflineCache::profiles(user_id),
+    )
+  }
+
+  async fn list(&self, user_id: UserId) -> Result<Vec<QueryProfile>> {
+    let profiles = self.profiles(user_id).await?;
+    Ok(profiles.into_iter().map(QueryProfile::from).collect())
+  }
+
+  async fn search(&self, user_id: UserId, query: &str) -> Result<Vec<QueryProfile>> {
+    let profiles = self.profiles(user_id).await?;
+    Ok(
+      search_profiles(profiles, query)
+        .into_iter()
+        .map(QueryProfile::from)
+        .collect(),
+    )
+  }
+
+  /// Switch to the profile and start it, as the app's switcher does
+  async fn activate(&self, user_id: UserId, profile_id: ProfileId) -> Result<QueryActivation> {
+    let profile =
+      ProfileService::activate_profile(&self.db, profile_id, user_id, ActivationSource::Launcher)
+        .await?;
+
+    let system = system_backend::from_env();
+    let (pipeline, _) =
+      ActivationPipeline::for_start(self.db.clone(), system.clone(), profile_id, user_id).await?;
+    let started_at = chrono::Utc::now();
+    let run = pipeline.run().await;
+    ActivationReportService::record_failure(
+      &self.db,
+      system.as_ref(),
+      profile_id,
+      &run,
+      started_at,
+    )
+    .await;
+    if let Some(e) = run.error {
+      return Err(e);
+    }
+    ChecklistService::present(&self.db, system.as_ref(), profile_id).await;
+
+    Ok(QueryActivation {
+      profile: QueryProfile::from(profile),
+      steps: run
+        .steps
+        .iter()
+        .map(|report| QueryStep {
+          step: json_string(&report.step),
+          status: json_string(&report.status),
+          message: report.message.clone(),
+        })
+        .collect(),
+    })
+  }
+}
+
+/// A unit enum's serde name
+fn json_string<T: Serialize>(value: &T) -> String {
+  match serde_json::to_value(value) {
+    Ok(Value::String(s)) => s,
+    _ => String::new(),
+  }
+}
+
+/// The request `smoothie query <method> [arg]` stands for
+fn request_from_args(args: &[String]) -> Value {
+  let method = args[0].as_str();
+  let arg = (args.len() > 1).then(|| args[1..].join(" "));
+  let params = match (method, arg) {
+    ("search", Some(query)) => json!({ "query": query }),
+    ("activate", Some(profile_id)) => json!({ "profileId": profile_id }),
+    _ => Value::Null,
+  };
+  json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params })
+}
+
+/// Run query mode; `args` are the words after `query`. Returns the process
+/// exit code.
+pub async fn run(db: Arc<Database>, args: &[String]) -> i32 {
+  let server = QueryServer::new(db);
+  let mut stdout = tokio::io::stdout();
+
+  if !args.is_empty() {
+    let request = request_from_args(args).to_string();
+    let answer = server.handle_line(&request).await.unwrap_or(Value::Null);
+    let failed = answer.get("error").is_some();
+    let _ = stdout.write_all(format!("{}\n", answer).as_bytes()).await;
+    let _ = stdout.flush().await;
+    return if failed { 1 } else { 0 };
+  }
+
+  let mut lines = BufReader::new(tokio::io::stdin()).lines();
+  loop {
+    let line = match lines.next_line().await {
+      Ok(Some(line)) => line,
+      Ok(None) => return 0,
+      Err(e) => {
+        tracing::error!("Failed to read query input: {}", e);
+        return 1;
+      }
+    };
+    if line.trim().is_empty() {
+      continue;
+    }
+    if let Some(answer) = server.handle_line(&line).await {
+      // A closed stdout means the launcher went away
+      if stdout
+        .write_all(format!("{}\n", answer).as_bytes())
+        .await
+        .is_err()
+        || stdout.flush().await.is_err()
+      {
+        return 0;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::ProfileType;
+
+  fn profile(name: &str, tags: &[&str], last_activated_at: Option<&str>) -> ProfileDto {
+    ProfileDto {
+      id: ProfileId::from(uuid::Uuid::new_v4()),
+      user_id: UserId::from(uuid::Uuid::new_v4()),
+      name: name.to_string(),
+      description: None,
+      profile_type: ProfileType::Work,
+      is_active: false,
+      tags: tags.iter().map(|t| t.to_string()).collect(),
+      monitor_count: 0,
+      app_count: 0,
+      browser_tab_count: 0,
+      created_at: String::new(),
+      updated_at: String::new(),
+      last_used: None,
+      last_activated_at: last_activated_at.map(str::to_string),
+      activation_count: 0,
+      is_favorite: false,
+      color: None,
+      icon: None,
+      sort_order: 0,
+      low_power_battery_threshold: None,
+      local_only: false,
+      required_monitor_count: None,
+      monitor_signature: None,
+      notes: None,
+      include_virtual_displays: false,
+      is_locked: false,
+    }
+  }
+
+  #[test]
+  fn test_search_ranks_name_prefixes_first() {
+    let profiles = vec![
+      profile("Home office", &["deep"], Some("2026-10-01T09:00:00Z")),
+      profile("Deep work", &[], None),
+      profile("Weekend", &["personal"], None),
+      profile("My deep dive", &[], Some("2026-10-02T09:00:00Z")),
+    ];
+
+    let names: Vec<_> = search_profiles(profiles, "DEEP")
+      .into_iter()
+      .map(|p| p.name)
+      .collect();
+    assert_eq!(names, ["Deep work", "My deep dive", "Home office"]);
+  }
+
+  #[test]
+  fn test_args_map_to_requests() {
+    let args = ["search", "deep", "work"].map(String::from);
+    let request = request_from_args(&args);
+    assert_eq!(request["method"], "search");
+    assert_eq!(request["params"]["query"], "deep work");
+
+    let request = request_from_args(&["list".to_string()]);
+    assert_eq!(request["params"], Value::Null);
+  }
+
+  #[test]
+  fn test_smoothie_errors_map_to_rpc_codes() {
+    let error = RpcError::from(SmoothieError::NotFound("Profile not found".into()));
+    assert_eq!(error.code, NOT_FOUND);
+    let error = RpcError::from(SmoothieError::DatabaseError("down".into()));
+    assert_eq!(error.code, APP_ERROR);
+  }
+}
@@ -0,0 +1,223 @@
+// Alert repository - alert rules, fired alerts and the metrics rules are evaluated on
+
+use crate::error::{Result, SmoothieError};
+use crate::models::entities::{AlertEntity, AlertRuleEntity};
+use crate::models::Severity;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Rules every user starts with: (name, metric, threshold, window, min samples, severity)
+const DEFAULT_RULES: [(&str, &str, f64, i32, i32, Severity); 2] = [
+  (
+    "Critical error burst",
+    "critical_errors",
+    5.0,
+    10,
+    0,
+    Severity::Critical,
+  ),
+  (
+    "Activation failures",
+    "activation_failure_rate",
+    0.2,
+    60,
+    5,
+    Severity::Warning,
+  ),
+];
+
+pub struct AlertRepository<'a> {
+  pool: &'a PgPool,
+}
+
+impl<'a> AlertRepository<'a> {
+  pub fn new(pool: &'a PgPool) -> Self {
+    Self { pool }
+  }
+
+  // ============================================================================
+  // Rules
+  // ============================================================================
+
+  /// Create the default rules for a user if they don't have them yet
+  pub async fn ensure_default_rules(&self, user_id: Uuid) -> Result<()> {
+    for (name, metric, threshold, window_minutes, min_samples, severity) in DEFAULT_RULES {
+      sqlx::query(
+        r#"
+        INSERT INTO alert_rules (user_id, name, metric, threshold, window_minutes, min_samples, severity)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (user_id, name) DO NOTHING
+        "#,
+      )
+      .bind(user_id)
+      .bind(name)
+      .bind(metric)
+      .bind(threshold)
+      .bind(window_minutes)
+      .bind(min_samples)
+      .bind(severity)
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
+    }
+    Ok(())
+  }
+
+  /// Find all rules for a user
+  pub async fn find_rules(&self, user_id: Uuid) -> Result<Vec<AlertRuleEntity>> {
+    sqlx::query_as::<_, AlertRuleEntity>(
+      "SELECT * FROM alert_rules WHERE user_id = $1 ORDER BY created_at, name",
+    )
+    .bind(user_id)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Update the tunable parts of a rule; `None` leaves a field unchanged
+  pub async fn update_rule(
+    &self,
+    rule_id: Uuid,
+    is_enabled: Option<bool>,
+    threshold: Option<f64>,
+    window_minutes: Option<i32>,
+    cooldown_minutes: Option<i32>,
+  ) -> Result<AlertRuleEntity> {
+    sqlx::query_as::<_, AlertRuleEntity>(
+      r#"
+      UPDATE alert_rules
+      SET is_enabled = COALESCE($2, is_enabled),
+          threshold = COALESCE($3, threshold),
+          window_minutes = COALESCE($4, window_minutes),
+          cooldown_minutes = COALESCE($5, cooldown_minutes),
+          updated_at = NOW()
+      WHERE id = $1
+      RETURNING *
+      "#,
+    )
+    .bind(rule_id)
+    .bind(is_enabled)
+    .bind(threshold)
+    .bind(window_minutes)
+    .bind(cooldown_minutes)
+    .fetch_optional(self.pool)
+    .await
+    .map_err(SmoothieError::from)?
+    .ok_or_else(|| SmoothieError::NotFound("Alert rule not found".into()))
+  }
+
+  // ============================================================================
+  // Alerts
+  // ============================================================================
+
+  /// Open alerts for a user, newest first
+  pub async fn find_active_alerts(&self, user_id: Uuid) -> Result<Vec<AlertEntity>> {
+    sqlx::query_as::<_, AlertEntity>(
+      r#"
+      SELECT * FROM alerts
+      WHERE user_id = $1 AND resolved_at IS NULL
+      ORDER BY triggered_at DESC
+      "#,
+    )
+    .bind(user_id)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Most recent alert fired by a rule, open or resolved
+  pub async fn find_latest_alert(&self, rule_id: Uuid) -> Result<Option<AlertEntity>> {
+    sqlx::query_as::<_, AlertEntity>(
+      "SELECT * FROM alerts WHERE rule_id = $1 ORDER BY triggered_at DESC LIMIT 1",
+    )
+    .bind(rule_id)
+    .fetch_optional(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  pub async fn create_alert(
+    &self,
+    rule: &AlertRuleEntity,
+    value: f64,
+    message: &str,
+  ) -> Result<AlertEntity> {
+    sqlx::query_as::<_, AlertEntity>(
+      r#"
+      INSERT INTO alerts (rule_id, user_id, value, threshold, message)
+      VALUES ($1, $2, $3, $4, $5)
+      RETURNING *
+      "#,
+    )
+    .bind(rule.id)
+    .bind(rule.user_id)
+    .bind(value)
+    .bind(rule.threshold)
+    .bind(message)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  pub async fn resolve_alert(&self, alert_id: Uuid) -> Result<()> {
+    sqlx::query("UPDATE alerts SET resolved_at = NOW() WHERE id = $1 AND resolved_at IS NULL")
+      .bind(alert_id)
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
+    Ok(())
+  }
+
+  // ============================================================================
+  // Metrics
+  // ============================================================================
+
+  /// Error occurrences since `since`, optionally limited to one severity
+  pub async fn count_errors_since(
+    &self,
+    since: DateTime<Utc>,
+    severity: Option<Severity>,
+  ) -> Result<i64> {
+    // Deduplicated errors keep counting on one row. Rows first seen inside the
+    // window contribute all their occurrences; older rows only the one we know
+    // happened recently, so this is a lower bound rather than an overcount.
+    let (count,): (i64,) = sqlx::query_as(
+      r#"
+      SELECT COALESCE(SUM(
+        CASE WHEN first_occurred_at >= $1 THEN COALESCE(occurrence_count, 1) ELSE 1 END
+      ), 0)::BIGINT
+      FROM error_logs
+      WHERE last_occurred_at >= $1
+        AND ($2::text IS NULL OR severity = $2)
+      "#,
+    )
+    .bind(since)
+    .bind(severity)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    Ok(count)
+  }
+
+  /// (total, failed) profile activations for a user since `since`
+  pub async fn activation_counts_since(
+    &self,
+    user_id: Uuid,
+    since: DateTime<Utc>,
+  ) -> Result<(i64, i64)> {
+    sqlx::query_as(
+      r#"
+      SELECT COUNT(*), COUNT(*) FILTER (WHERE success = false)
+      FROM profile_activations
+      WHERE user_id = $1 AND started_at >= $2
+      "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+}
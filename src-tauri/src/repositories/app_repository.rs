@@ -3,7 +3,7 @@
 use crate::error::{Result, SmoothieError};
 use crate::models::entities::AppEntity;
 use chrono::Utc;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
 pub struct AppRepository<'a> {
@@ -30,7 +30,23 @@ impl<'a> AppRepository<'a> {
     .bind(profile_id)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    .map_err(SmoothieError::from)
+  }
+
+  /// Names of the apps in any of the user's profiles
+  pub async fn find_names_by_user_id(&self, user_id: Uuid) -> Result<Vec<String>> {
+    sqlx::query_scalar(
+      r#"
+      SELECT DISTINCT a.name
+      FROM apps a
+      JOIN profiles p ON p.id = a.profile_id
+      WHERE p.user_id = $1
+      "#,
+    )
+    .bind(user_id)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
   }
 
   /// Find launchable apps for a profile
@@ -48,7 +64,7 @@ impl<'a> AppRepository<'a> {
     .bind(profile_id)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    .map_err(SmoothieError::from)
   }
 
   /// Find an app by ID
@@ -65,7 +81,7 @@ impl<'a> AppRepository<'a> {
     .bind(id)
     .fetch_optional(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    .map_err(SmoothieError::from)
   }
 
   /// Create a new app
@@ -79,35 +95,61 @@ impl<'a> AppRepository<'a> {
     monitor_preference: Option<i32>,
     startup_delay_ms: Option<i32>,
     order_index: Option<i32>,
+  ) -> Result<AppEntity> {
+    let mut conn = self.pool.acquire().await.map_err(SmoothieError::from)?;
+    Self::create_in(
+      &mut conn,
+      profile_id,
+      name,
+      bundle_id,
+      exe_path,
+      launch_on_activate,
+      monitor_preference,
+      startup_delay_ms,
+      order_index,
+    )
+    .await
+  }
+
+  /// [`create`](Self::create) on the caller's connection, so it can be part
+  /// of a larger transaction
+  pub async fn create_in(
+    conn: &mut PgConnection,
+    profile_id: Uuid,
+    name: &str,
+    bundle_id: &str,
+    exe_path: Option<&str>,
+    launch_on_activate: bool,
+    monitor_preference: Option<i32>,
+    startup_delay_ms: Option<i32>,
+    order_index: Option<i32>,
   ) -> Result<AppEntity> {
     let id = Uuid::new_v4();
     let now = Utc::now();
 
-    sqlx::query(
-            r#"
+    sqlx::query_as::<_, AppEntity>(
+      r#"
             INSERT INTO apps (id, profile_id, name, bundle_id, exe_path, launch_on_activate,
                               monitor_preference, created_at, updated_at, startup_delay_ms, order_index)
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8, $9, $10)
+            RETURNING id, profile_id, name, bundle_id, exe_path, launch_on_activate,
+                   monitor_preference, created_at, updated_at, icon_path, launch_args,
+                   working_directory, startup_delay_ms, order_index
             "#,
-        )
-        .bind(id)
-        .bind(profile_id)
-        .bind(name)
-        .bind(bundle_id)
-        .bind(exe_path)
-        .bind(launch_on_activate)
-        .bind(monitor_preference)
-        .bind(now)
-        .bind(startup_delay_ms.unwrap_or(0))
-        .bind(order_index.unwrap_or(0))
-        .execute(self.pool)
-        .await
-        .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
-
-    self
-      .find_by_id(id)
-      .await?
-      .ok_or_else(|| SmoothieError::NotFound("App not found after creation".into()))
+    )
+    .bind(id)
+    .bind(profile_id)
+    .bind(name)
+    .bind(bundle_id)
+    .bind(exe_path)
+    .bind(launch_on_activate)
+    .bind(monitor_preference)
+    .bind(now)
+    .bind(startup_delay_ms.unwrap_or(0))
+    .bind(order_index.unwrap_or(0))
+    .fetch_one(conn)
+    .await
+    .map_err(SmoothieError::from)
   }
 
   /// Update an app
@@ -121,7 +163,7 @@ impl<'a> AppRepository<'a> {
     .bind(id)
     .execute(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     self
       .find_by_id(id)
@@ -129,13 +171,73 @@ impl<'a> AppRepository<'a> {
       .ok_or_else(|| SmoothieError::NotFound("App not found".into()))
   }
 
+  /// Point an app at a different installed app, keeping its settings and
+  /// saved windows
+  pub async fn replace(
+    &self,
+    id: Uuid,
+    name: &str,
+    bundle_id: &str,
+    exe_path: Option<&str>,
+  ) -> Result<AppEntity> {
+    sqlx::query(
+      "UPDATE apps SET name = $1, bundle_id = $2, exe_path = $3, icon_path = NULL, updated_at = $4 WHERE id = $5",
+    )
+    .bind(name)
+    .bind(bundle_id)
+    .bind(exe_path)
+    .bind(Utc::now())
+    .bind(id)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    self
+      .find_by_id(id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("App not found".into()))
+  }
+
+  /// Set `order_index` of the profile's apps to follow `ids` in a single
+  /// statement; apps left out keep their relative order after the listed ones.
+  /// Returns `false`, changing nothing, if an id isn't one of the profile's apps.
+  pub async fn reorder(&self, profile_id: Uuid, ids: &[Uuid]) -> Result<bool> {
+    let result = sqlx::query(
+      r#"
+      WITH listed AS (
+        SELECT id, position FROM unnest($2::uuid[]) WITH ORDINALITY AS l(id, position)
+      ), ranked AS (
+        SELECT a.id,
+               (ROW_NUMBER() OVER (
+                 ORDER BY l.position NULLS LAST, COALESCE(a.order_index, 0), a.name
+               ) - 1)::int AS order_index
+        FROM apps a
+        LEFT JOIN listed l ON l.id = a.id
+        WHERE a.profile_id = $1
+      )
+      UPDATE apps a
+      SET order_index = r.order_index
+      FROM ranked r
+      WHERE a.id = r.id
+        AND (SELECT COUNT(*) FROM apps WHERE profile_id = $1 AND id = ANY($2)) = cardinality($2)
+      "#,
+    )
+    .bind(profile_id)
+    .bind(ids)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    Ok(ids.is_empty() || result.rows_affected() > 0)
+  }
+
   /// Delete an app
   pub async fn delete(&self, id: Uuid) -> Result<bool> {
     let result = sqlx::query("DELETE FROM apps WHERE id = $1")
       .bind(id)
       .execute(self.pool)
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
 
     Ok(result.rows_affected() > 0)
   }
@@ -146,7 +248,7 @@ impl<'a> AppRepository<'a> {
       .bind(profile_id)
       .fetch_one(self.pool)
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
 
     Ok(count)
   }
@@ -1,12 +1,36 @@
 // Comprehensive audit and logging repository
 // Handles all logging operations for activity, system events, errors, sessions, etc.
 
+use super::traits::AuditStore;
+use super::JsonFilter;
 use crate::error::{Result, SmoothieError};
+use crate::logging::current_request_id;
 use crate::models::entities::*;
-use chrono::{DateTime, Utc};
-use sqlx::PgPool;
+use crate::models::enums::{ActivationSource, ActivityStatus, MonitorChangeType, Severity};
+use crate::utils::error_fingerprint::error_fingerprint;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
+/// Log tables pruned by `cleanup_old_logs` and the rows eligible once older
+/// than the cutoff (bound as `$1`). Only ended sessions and resolved errors
+/// are removed.
+const LOG_RETENTION_RULES: [(&str, &str); 9] = [
+  ("activity_logs", "created_at < $1"),
+  ("system_events", "created_at < $1"),
+  ("error_logs", "is_resolved = true AND resolved_at < $1"),
+  ("sessions", "ended_at < $1"),
+  ("app_launches", "launched_at < $1"),
+  ("monitor_changes", "detected_at < $1"),
+  ("automation_executions", "executed_at < $1"),
+  ("app_focus_events", "ended_at < $1"),
+  ("error_daily_counts", "day < $1"),
+];
+
+/// Occurrences of an open error further apart than this start a new entry
+const ERROR_DEDUP_WINDOW: chrono::Duration = chrono::Duration::hours(24);
+
 pub struct AuditRepository<'a> {
   pool: &'a PgPool,
 }
@@ -30,7 +54,7 @@ impl<'a> AuditRepository<'a> {
     entity_id: Option<Uuid>,
     entity_name: Option<&str>,
     details: Option<serde_json::Value>,
-    status: &str,
+    status: ActivityStatus,
     error_message: Option<&str>,
     duration_ms: Option<i32>,
   ) -> Result<ActivityLogEntity> {
@@ -38,9 +62,9 @@ impl<'a> AuditRepository<'a> {
       r#"
       INSERT INTO activity_logs (
         user_id, session_id, action, entity_type, entity_id, entity_name,
-        details, status, error_message, duration_ms
+        details, status, error_message, duration_ms, request_id
       )
-      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
       RETURNING *
       "#,
     )
@@ -54,9 +78,10 @@ impl<'a> AuditRepository<'a> {
     .bind(status)
     .bind(error_message)
     .bind(duration_ms)
+    .bind(current_request_id())
     .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entity)
   }
@@ -71,75 +96,91 @@ impl<'a> AuditRepository<'a> {
     entity_type_filter: Option<&str>,
     start_date: Option<DateTime<Utc>>,
     end_date: Option<DateTime<Utc>>,
+    details_filter: Option<&JsonFilter>,
   ) -> Result<Vec<ActivityLogEntity>> {
-    let mut query = String::from(
-      r#"
-      SELECT * FROM activity_logs
-      WHERE user_id = $1
-      "#,
-    );
-
-    let mut param_count = 1;
-
-    if action_filter.is_some() {
-      param_count += 1;
-      query.push_str(&format!(" AND action = ${}", param_count));
-    }
-    if entity_type_filter.is_some() {
-      param_count += 1;
-      query.push_str(&format!(" AND entity_type = ${}", param_count));
-    }
-    if start_date.is_some() {
-      param_count += 1;
-      query.push_str(&format!(" AND created_at >= ${}", param_count));
-    }
-    if end_date.is_some() {
-      param_count += 1;
-      query.push_str(&format!(" AND created_at <= ${}", param_count));
-    }
-
-    query.push_str(&format!(
-      " ORDER BY created_at DESC LIMIT ${} OFFSET ${}",
-      param_count + 1,
-      param_count + 2
-    ));
-
-    let mut query_builder = sqlx::query_as::<_, ActivityLogEntity>(&query).bind(user_id);
+    let mut query = QueryBuilder::<Postgres>::new("SELECT * FROM activity_logs WHERE user_id = ");
+    query.push_bind(user_id);
 
     if let Some(action) = action_filter {
-      query_builder = query_builder.bind(action);
+      query.push(" AND action = ").push_bind(action);
     }
     if let Some(entity_type) = entity_type_filter {
-      query_builder = query_builder.bind(entity_type);
+      query.push(" AND entity_type = ").push_bind(entity_type);
     }
     if let Some(start) = start_date {
-      query_builder = query_builder.bind(start);
+      query.push(" AND created_at >= ").push_bind(start);
     }
     if let Some(end) = end_date {
-      query_builder = query_builder.bind(end);
+      query.push(" AND created_at <= ").push_bind(end);
     }
+    if let Some(details) = details_filter {
+      query.push(" AND ");
+      details.push(&mut query, "details");
+    }
+
+    query
+      .push(" ORDER BY created_at DESC LIMIT ")
+      .push_bind(limit)
+      .push(" OFFSET ")
+      .push_bind(offset);
 
-    let entities = query_builder
-      .bind(limit)
-      .bind(offset)
+    let entities = query
+      .build_query_as::<ActivityLogEntity>()
       .fetch_all(self.pool)
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
 
     Ok(entities)
   }
 
   /// Count activity logs for a user
+  #[allow(dead_code)]
   pub async fn count_activity_logs(&self, user_id: Uuid) -> Result<i64> {
     let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM activity_logs WHERE user_id = $1")
       .bind(user_id)
       .fetch_one(self.pool)
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
 
     Ok(count)
   }
 
+  /// Insert already-built activity logs in one multi-row statement
+  pub async fn insert_activity_logs(&self, logs: &[ActivityLogEntity]) -> Result<u64> {
+    if logs.is_empty() {
+      return Ok(0);
+    }
+
+    let mut query = QueryBuilder::<Postgres>::new(
+      "INSERT INTO activity_logs (id, user_id, session_id, action, entity_type, entity_id, \
+       entity_name, details, status, error_message, duration_ms, request_id, created_at) ",
+    );
+    query.push_values(logs, |mut row, log| {
+      row
+        .push_bind(log.id)
+        .push_bind(log.user_id)
+        .push_bind(log.session_id)
+        .push_bind(&log.action)
+        .push_bind(&log.entity_type)
+        .push_bind(log.entity_id)
+        .push_bind(&log.entity_name)
+        .push_bind(&log.details)
+        .push_bind(log.status)
+        .push_bind(&log.error_message)
+        .push_bind(log.duration_ms)
+        .push_bind(&log.request_id)
+        .push_bind(log.created_at);
+    });
+
+    let result = query
+      .build()
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    Ok(result.rows_affected())
+  }
+
   // ============================================================================
   // System Events
   // ============================================================================
@@ -148,7 +189,7 @@ impl<'a> AuditRepository<'a> {
   pub async fn log_system_event(
     &self,
     event_type: &str,
-    severity: &str,
+    severity: Severity,
     source: &str,
     message: &str,
     details: Option<serde_json::Value>,
@@ -161,9 +202,9 @@ impl<'a> AuditRepository<'a> {
       r#"
       INSERT INTO system_events (
         id, event_type, severity, source, message, details,
-        stack_trace, os_info, app_version
+        stack_trace, os_info, app_version, request_id
       )
-      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
       RETURNING *
       "#,
     )
@@ -176,19 +217,54 @@ impl<'a> AuditRepository<'a> {
     .bind(stack_trace)
     .bind(os_info)
     .bind(app_version)
+    .bind(current_request_id())
     .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entity)
   }
 
+  /// Insert already-built system events in one multi-row statement
+  pub async fn insert_system_events(&self, events: &[SystemEventEntity]) -> Result<u64> {
+    if events.is_empty() {
+      return Ok(0);
+    }
+
+    let mut query = QueryBuilder::<Postgres>::new(
+      "INSERT INTO system_events (id, event_type, severity, source, message, details, \
+       stack_trace, os_info, app_version, request_id, created_at) ",
+    );
+    query.push_values(events, |mut row, event| {
+      row
+        .push_bind(event.id)
+        .push_bind(&event.event_type)
+        .push_bind(event.severity)
+        .push_bind(&event.source)
+        .push_bind(&event.message)
+        .push_bind(&event.details)
+        .push_bind(&event.stack_trace)
+        .push_bind(&event.os_info)
+        .push_bind(&event.app_version)
+        .push_bind(&event.request_id)
+        .push_bind(event.created_at);
+    });
+
+    let result = query
+      .build()
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    Ok(result.rows_affected())
+  }
+
   /// Get system events
   pub async fn get_system_events(
     &self,
     limit: i64,
     offset: i64,
-    severity_filter: Option<&str>,
+    severity_filter: Option<Severity>,
     event_type_filter: Option<&str>,
   ) -> Result<Vec<SystemEventEntity>> {
     let entities = sqlx::query_as::<_, SystemEventEntity>(
@@ -206,7 +282,7 @@ impl<'a> AuditRepository<'a> {
     .bind(offset)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entities)
   }
@@ -249,7 +325,7 @@ impl<'a> AuditRepository<'a> {
     .bind(metadata)
     .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entity)
   }
@@ -268,12 +344,13 @@ impl<'a> AuditRepository<'a> {
     .bind(reason)
     .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entity)
   }
 
   /// Get active session for user
+  #[allow(dead_code)]
   pub async fn get_active_session(&self, user_id: Uuid) -> Result<Option<SessionEntity>> {
     let entity = sqlx::query_as::<_, SessionEntity>(
       r#"
@@ -286,11 +363,29 @@ impl<'a> AuditRepository<'a> {
     .bind(user_id)
     .fetch_optional(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entity)
   }
 
+  /// End sessions started before `before` that were never ended, as of their
+  /// last activity. Returns how many there were.
+  pub async fn end_abandoned_sessions(&self, before: DateTime<Utc>) -> Result<u64> {
+    let result = sqlx::query(
+      r#"
+      UPDATE sessions
+      SET ended_at = last_activity_at, end_reason = 'unclean_shutdown', is_active = false
+      WHERE ended_at IS NULL AND started_at < $1
+      "#,
+    )
+    .bind(before)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    Ok(result.rows_affected())
+  }
+
   /// Get sessions for user
   pub async fn get_sessions(
     &self,
@@ -311,7 +406,7 @@ impl<'a> AuditRepository<'a> {
     .bind(offset)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entities)
   }
@@ -327,7 +422,7 @@ impl<'a> AuditRepository<'a> {
     user_id: Uuid,
     profile_id: Uuid,
     session_id: Option<Uuid>,
-    activation_source: &str,
+    activation_source: ActivationSource,
     previous_profile_id: Option<Uuid>,
     monitors_detected: Option<i32>,
     monitors_applied: Option<i32>,
@@ -373,7 +468,7 @@ impl<'a> AuditRepository<'a> {
     .bind(metadata)
     .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     // Update profile activation count and last_activated_at
     sqlx::query(
@@ -393,6 +488,38 @@ impl<'a> AuditRepository<'a> {
     Ok(entity)
   }
 
+  /// Mark activations started before `before` that never completed as
+  /// failed. Returns how many there were.
+  pub async fn fail_unfinished_activations(&self, before: DateTime<Utc>) -> Result<u64> {
+    let result = sqlx::query(
+      r#"
+      UPDATE profile_activations
+      SET completed_at = started_at + COALESCE(duration_ms, 0) * INTERVAL '1 millisecond',
+          success = false,
+          error_message = COALESCE(error_message, 'Interrupted before it completed')
+      WHERE completed_at IS NULL AND started_at < $1
+      "#,
+    )
+    .bind(before)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    Ok(result.rows_affected())
+  }
+
+  /// Activations the user started since `since`
+  pub async fn count_activations_since(&self, user_id: Uuid, since: DateTime<Utc>) -> Result<i64> {
+    sqlx::query_scalar(
+      "SELECT COUNT(*) FROM profile_activations WHERE user_id = $1 AND started_at >= $2",
+    )
+    .bind(user_id)
+    .bind(since)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
   /// Get the active profile activation for a user
   pub async fn get_active_profile_activation(
     &self,
@@ -409,25 +536,28 @@ impl<'a> AuditRepository<'a> {
     .bind(user_id)
     .fetch_optional(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entity)
   }
 
-  /// Get profile activations for a user
+  /// Get profile activations for a user, with profile names
   pub async fn get_profile_activations(
     &self,
     user_id: Uuid,
     limit: i64,
     offset: i64,
     profile_id_filter: Option<Uuid>,
-  ) -> Result<Vec<ProfileActivationEntity>> {
-    let entities = sqlx::query_as::<_, ProfileActivationEntity>(
+  ) -> Result<Vec<ProfileActivationRow>> {
+    let entities = sqlx::query_as::<_, ProfileActivationRow>(
       r#"
-      SELECT * FROM profile_activations
-      WHERE user_id = $1
-        AND ($2::uuid IS NULL OR profile_id = $2)
-      ORDER BY started_at DESC
+      SELECT pa.*, p.name AS profile_name, prev.name AS previous_profile_name
+      FROM profile_activations pa
+      LEFT JOIN profiles p ON p.id = pa.profile_id
+      LEFT JOIN profiles prev ON prev.id = pa.previous_profile_id
+      WHERE pa.user_id = $1
+        AND ($2::uuid IS NULL OR pa.profile_id = $2)
+      ORDER BY pa.started_at DESC
       LIMIT $3 OFFSET $4
       "#,
     )
@@ -437,41 +567,83 @@ impl<'a> AuditRepository<'a> {
     .bind(offset)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entities)
   }
 
-  /// Get activation count for today
-  pub async fn get_activations_today(&self, user_id: Uuid) -> Result<i64> {
-    let (count,): (i64,) = sqlx::query_as(
+  /// The profile's most recent activation
+  pub async fn get_latest_profile_activation(
+    &self,
+    profile_id: Uuid,
+  ) -> Result<Option<ProfileActivationEntity>> {
+    sqlx::query_as::<_, ProfileActivationEntity>(
+      "SELECT * FROM profile_activations WHERE profile_id = $1 ORDER BY started_at DESC LIMIT 1",
+    )
+    .bind(profile_id)
+    .fetch_optional(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Mark an activation as failed once its steps didn't all run
+  pub async fn fail_profile_activation(
+    &self,
+    activation_id: Uuid,
+    error_message: &str,
+    rollback_performed: bool,
+  ) -> Result<()> {
+    sqlx::query(
       r#"
-      SELECT COUNT(*) FROM profile_activations
-      WHERE user_id = $1 AND started_at >= CURRENT_DATE
+      UPDATE profile_activations
+      SET success = false, error_message = $2, rollback_performed = $3
+      WHERE id = $1
       "#,
     )
-    .bind(user_id)
-    .fetch_one(self.pool)
+    .bind(activation_id)
+    .bind(error_message)
+    .bind(rollback_performed)
+    .execute(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
-
-    Ok(count)
+    .map_err(SmoothieError::from)?;
+    Ok(())
   }
 
-  /// Get activation count for this week
-  pub async fn get_activations_this_week(&self, user_id: Uuid) -> Result<i64> {
-    let (count,): (i64,) = sqlx::query_as(
+  /// Store the report of a failed activation, replacing an earlier one
+  pub async fn save_activation_report(
+    &self,
+    activation_id: Uuid,
+    profile_id: Uuid,
+    report: serde_json::Value,
+  ) -> Result<ActivationReportEntity> {
+    sqlx::query_as::<_, ActivationReportEntity>(
       r#"
-      SELECT COUNT(*) FROM profile_activations
-      WHERE user_id = $1 AND started_at >= NOW() - INTERVAL '7 days'
+      INSERT INTO activation_reports (activation_id, profile_id, report)
+      VALUES ($1, $2, $3)
+      ON CONFLICT (activation_id) DO UPDATE
+      SET report = EXCLUDED.report, created_at = NOW()
+      RETURNING *
       "#,
     )
-    .bind(user_id)
+    .bind(activation_id)
+    .bind(profile_id)
+    .bind(report)
     .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)
+  }
 
-    Ok(count)
+  pub async fn get_activation_report(
+    &self,
+    activation_id: Uuid,
+  ) -> Result<Option<ActivationReportEntity>> {
+    sqlx::query_as::<_, ActivationReportEntity>(
+      "SELECT * FROM activation_reports WHERE activation_id = $1",
+    )
+    .bind(activation_id)
+    .fetch_optional(self.pool)
+    .await
+    .map_err(SmoothieError::from)
   }
 
   // ============================================================================
@@ -491,39 +663,79 @@ impl<'a> AuditRepository<'a> {
     source_file: Option<&str>,
     source_line: Option<i32>,
     source_function: Option<&str>,
-    severity: &str,
+    severity: Severity,
   ) -> Result<ErrorLogEntity> {
-    // Check if a similar error already exists (deduplication)
+    let fingerprint = error_fingerprint(error_type, message, source_file, source_function);
+    self.record_error_occurrence(&fingerprint).await?;
+
+    // Latest error in the same group, resolved or not
     let existing = sqlx::query_as::<_, ErrorLogEntity>(
       r#"
       SELECT * FROM error_logs
-      WHERE error_type = $1 AND message = $2 AND is_resolved = false
+      WHERE fingerprint = $1
+      ORDER BY last_occurred_at DESC
       LIMIT 1
       "#,
     )
-    .bind(error_type)
-    .bind(message)
+    .bind(&fingerprint)
     .fetch_optional(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
-
-    if let Some(existing_error) = existing {
-      // Update occurrence count
-      let updated = sqlx::query_as::<_, ErrorLogEntity>(
-        r#"
-        UPDATE error_logs
-        SET occurrence_count = occurrence_count + 1,
-            last_occurred_at = CURRENT_TIMESTAMP
-        WHERE id = $1
-        RETURNING *
-        "#,
-      )
-      .bind(existing_error.id)
-      .fetch_one(self.pool)
-      .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
+
+    match existing {
+      // A resolved error that happens again is a regression: re-open it
+      Some(existing_error) if existing_error.is_resolved == Some(true) => {
+        let reopened = sqlx::query_as::<_, ErrorLogEntity>(
+          r#"
+          UPDATE error_logs
+          SET is_resolved = false,
+              resolved_at = NULL,
+              reopened_at = CURRENT_TIMESTAMP,
+              regression_count = regression_count + 1,
+              occurrence_count = occurrence_count + 1,
+              last_occurred_at = CURRENT_TIMESTAMP,
+              message = $2,
+              context = COALESCE($3, context),
+              request_id = COALESCE($4, request_id)
+          WHERE id = $1
+          RETURNING *
+          "#,
+        )
+        .bind(existing_error.id)
+        .bind(message)
+        .bind(context)
+        .bind(current_request_id())
+        .fetch_one(self.pool)
+        .await
+        .map_err(SmoothieError::from)?;
+
+        tracing::warn!(error_id = %reopened.id, error_type = %error_type, "Resolved error regressed");
+        return Ok(reopened);
+      }
+      // Still open and seen recently: count it as another occurrence
+      Some(existing_error)
+        if existing_error.last_occurred_at >= Utc::now() - ERROR_DEDUP_WINDOW =>
+      {
+        let updated = sqlx::query_as::<_, ErrorLogEntity>(
+          r#"
+          UPDATE error_logs
+          SET occurrence_count = occurrence_count + 1,
+              last_occurred_at = CURRENT_TIMESTAMP,
+              request_id = COALESCE($2, request_id)
+          WHERE id = $1
+          RETURNING *
+          "#,
+        )
+        .bind(existing_error.id)
+        .bind(current_request_id())
+        .fetch_one(self.pool)
+        .await
+        .map_err(SmoothieError::from)?;
 
-      return Ok(updated);
+        return Ok(updated);
+      }
+      // Open but quiet for longer than the window: treat it as a new incident
+      _ => {}
     }
 
     // Create new error log
@@ -531,9 +743,10 @@ impl<'a> AuditRepository<'a> {
       r#"
       INSERT INTO error_logs (
         user_id, session_id, error_code, error_type, message, stack_trace,
-        context, source_file, source_line, source_function, severity
+        context, source_file, source_line, source_function, severity, fingerprint,
+        request_id
       )
-      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
       RETURNING *
       "#,
     )
@@ -548,19 +761,116 @@ impl<'a> AuditRepository<'a> {
     .bind(source_line)
     .bind(source_function)
     .bind(severity)
+    .bind(&fingerprint)
+    .bind(current_request_id())
     .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entity)
   }
 
+  /// Count one occurrence of the error group today, under the running version
+  async fn record_error_occurrence(&self, fingerprint: &str) -> Result<()> {
+    sqlx::query(
+      r#"
+      INSERT INTO error_daily_counts (fingerprint, day, app_version, occurrences)
+      VALUES ($1, (NOW() AT TIME ZONE 'UTC')::date, $2, 1)
+      ON CONFLICT (fingerprint, day, app_version)
+      DO UPDATE SET occurrences = error_daily_counts.occurrences + 1
+      "#,
+    )
+    .bind(fingerprint)
+    .bind(env!("CARGO_PKG_VERSION"))
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+    Ok(())
+  }
+
+  /// Error logs grouped by fingerprint, most recently seen first. Groups
+  /// where every entry is resolved are left out unless `include_resolved`.
+  pub async fn get_error_groups(
+    &self,
+    include_resolved: bool,
+    limit: i64,
+  ) -> Result<Vec<ErrorGroupRow>> {
+    sqlx::query_as::<_, ErrorGroupRow>(
+      r#"
+      SELECT fingerprint,
+             (ARRAY_AGG(error_type ORDER BY last_occurred_at DESC))[1] AS error_type,
+             (ARRAY_AGG(message ORDER BY last_occurred_at DESC))[1] AS message,
+             (ARRAY_AGG(severity ORDER BY last_occurred_at DESC))[1] AS severity,
+             SUM(COALESCE(occurrence_count, 1))::BIGINT AS occurrences,
+             MIN(first_occurred_at) AS first_seen,
+             MAX(last_occurred_at) AS last_seen,
+             BOOL_AND(COALESCE(is_resolved, false)) AS resolved,
+             SUM(regression_count)::BIGINT AS regressions
+      FROM error_logs
+      WHERE fingerprint IS NOT NULL
+      GROUP BY fingerprint
+      HAVING $1 OR NOT BOOL_AND(COALESCE(is_resolved, false))
+      ORDER BY last_seen DESC
+      LIMIT $2
+      "#,
+    )
+    .bind(include_resolved)
+    .bind(limit)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Occurrences per group and day from `since` on
+  pub async fn get_error_daily_counts(
+    &self,
+    fingerprints: &[String],
+    since: NaiveDate,
+  ) -> Result<Vec<(String, NaiveDate, i64)>> {
+    sqlx::query_as(
+      r#"
+      SELECT fingerprint, day, SUM(occurrences)::BIGINT
+      FROM error_daily_counts
+      WHERE fingerprint = ANY($1) AND day >= $2
+      GROUP BY fingerprint, day
+      "#,
+    )
+    .bind(fingerprints)
+    .bind(since)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// App versions each group has occurred in, in the order they first did
+  pub async fn get_error_versions(
+    &self,
+    fingerprints: &[String],
+  ) -> Result<Vec<(String, Vec<String>)>> {
+    sqlx::query_as(
+      r#"
+      SELECT fingerprint, ARRAY_AGG(app_version ORDER BY first_day, app_version)
+      FROM (
+        SELECT fingerprint, app_version, MIN(day) AS first_day
+        FROM error_daily_counts
+        WHERE fingerprint = ANY($1) AND app_version <> ''
+        GROUP BY fingerprint, app_version
+      ) versions
+      GROUP BY fingerprint
+      "#,
+    )
+    .bind(fingerprints)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
   /// Get error logs
   pub async fn get_error_logs(
     &self,
     limit: i64,
     offset: i64,
-    severity_filter: Option<&str>,
+    severity_filter: Option<Severity>,
     include_resolved: bool,
   ) -> Result<Vec<ErrorLogEntity>> {
     let entities = sqlx::query_as::<_, ErrorLogEntity>(
@@ -578,7 +888,7 @@ impl<'a> AuditRepository<'a> {
     .bind(offset)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entities)
   }
@@ -601,18 +911,19 @@ impl<'a> AuditRepository<'a> {
     .bind(resolution_notes)
     .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entity)
   }
 
   /// Count unresolved errors
+  #[allow(dead_code)]
   pub async fn count_unresolved_errors(&self) -> Result<i64> {
     let (count,): (i64,) =
       sqlx::query_as("SELECT COUNT(*) FROM error_logs WHERE is_resolved = false")
         .fetch_one(self.pool)
         .await
-        .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+        .map_err(SmoothieError::from)?;
 
     Ok(count)
   }
@@ -657,7 +968,7 @@ impl<'a> AuditRepository<'a> {
     .bind(duration_ms)
     .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     // Update rule trigger count
     sqlx::query(
@@ -676,20 +987,23 @@ impl<'a> AuditRepository<'a> {
     Ok(entity)
   }
 
-  /// Get automation executions
+  /// Get automation executions, with rule and profile names
   pub async fn get_automation_executions(
     &self,
     user_id: Uuid,
     limit: i64,
     offset: i64,
     rule_id_filter: Option<Uuid>,
-  ) -> Result<Vec<AutomationExecutionEntity>> {
-    let entities = sqlx::query_as::<_, AutomationExecutionEntity>(
+  ) -> Result<Vec<AutomationExecutionRow>> {
+    let entities = sqlx::query_as::<_, AutomationExecutionRow>(
       r#"
-      SELECT * FROM automation_executions
-      WHERE user_id = $1
-        AND ($2::uuid IS NULL OR rule_id = $2)
-      ORDER BY executed_at DESC
+      SELECT ae.*, r.name AS rule_name, p.name AS profile_name
+      FROM automation_executions ae
+      LEFT JOIN automation_rules r ON r.id = ae.rule_id
+      LEFT JOIN profiles p ON p.id = ae.profile_id
+      WHERE ae.user_id = $1
+        AND ($2::uuid IS NULL OR ae.rule_id = $2)
+      ORDER BY ae.executed_at DESC
       LIMIT $3 OFFSET $4
       "#,
     )
@@ -699,7 +1013,7 @@ impl<'a> AuditRepository<'a> {
     .bind(offset)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entities)
   }
@@ -713,19 +1027,20 @@ impl<'a> AuditRepository<'a> {
     &self,
     user_id: Option<Uuid>,
     session_id: Option<Uuid>,
-    change_type: &str,
+    change_type: MonitorChangeType,
     monitors_before: Option<serde_json::Value>,
     monitors_after: Option<serde_json::Value>,
     auto_profile_activated: bool,
     activated_profile_id: Option<Uuid>,
+    summary: Option<&str>,
   ) -> Result<MonitorChangeEntity> {
     let entity = sqlx::query_as::<_, MonitorChangeEntity>(
       r#"
       INSERT INTO monitor_changes (
         user_id, session_id, change_type, monitors_before, monitors_after,
-        auto_profile_activated, activated_profile_id
+        auto_profile_activated, activated_profile_id, summary
       )
-      VALUES ($1, $2, $3, $4, $5, $6, $7)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
       RETURNING *
       "#,
     )
@@ -736,9 +1051,10 @@ impl<'a> AuditRepository<'a> {
     .bind(monitors_after)
     .bind(auto_profile_activated)
     .bind(activated_profile_id)
+    .bind(summary)
     .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entity)
   }
@@ -760,7 +1076,7 @@ impl<'a> AuditRepository<'a> {
     .bind(offset)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entities)
   }
@@ -810,7 +1126,7 @@ impl<'a> AuditRepository<'a> {
     .bind(window_positioned)
     .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entity)
   }
@@ -838,7 +1154,7 @@ impl<'a> AuditRepository<'a> {
     .bind(offset)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(entities)
   }
@@ -847,43 +1163,124 @@ impl<'a> AuditRepository<'a> {
   // Statistics
   // ============================================================================
 
-  /// Get most used profile
-  pub async fn get_most_used_profile(&self, user_id: Uuid) -> Result<Option<(Uuid, String, i64)>> {
-    let result: Option<(Uuid, String, i64)> = sqlx::query_as(
+  /// Profile, activation and error counters for the dashboard in one round trip.
+  /// The week's activations before today come from `daily_stats`.
+  pub async fn get_dashboard_counts(&self, user_id: Uuid) -> Result<DashboardCountsRow> {
+    let row = sqlx::query_as::<_, DashboardCountsRow>(
       r#"
-      SELECT p.id, p.name, COUNT(pa.id) as activation_count
-      FROM profiles p
-      JOIN profile_activations pa ON p.id = pa.profile_id
-      WHERE p.user_id = $1
-      GROUP BY p.id, p.name
-      ORDER BY activation_count DESC
-      LIMIT 1
+      WITH today AS (
+        SELECT COUNT(*) AS activations_today
+        FROM profile_activations
+        WHERE user_id = $1 AND started_at >= CURRENT_DATE
+      ),
+      earlier AS (
+        SELECT COALESCE(SUM(activations), 0)::bigint AS activations
+        FROM daily_stats
+        WHERE user_id = $1 AND day >= CURRENT_DATE - 6 AND day < CURRENT_DATE
+      ),
+      activations AS (
+        SELECT
+          t.activations_today,
+          t.activations_today + e.activations AS activations_week,
+          (SELECT MAX(started_at) FROM profile_activations WHERE user_id = $1)
+            AS last_activation_at
+        FROM today t, earlier e
+      ),
+      errors AS (
+        SELECT
+          COUNT(*) AS total_errors,
+          COUNT(*) FILTER (WHERE is_resolved = false) AS unresolved_errors
+        FROM error_logs
+      ),
+      focus AS (
+        SELECT
+          COUNT(*) AS focus_sessions_week,
+          COALESCE(SUM(EXTRACT(EPOCH FROM ended_at - started_at)::bigint / 60), 0)::bigint
+            AS focus_minutes_week
+        FROM focus_sessions
+        WHERE user_id = $1 AND status = 'completed' AND ended_at >= NOW() - INTERVAL '7 days'
+      )
+      SELECT
+        (SELECT COUNT(*) FROM profiles WHERE user_id = $1) AS total_profiles,
+        (SELECT COUNT(*) FROM activity_logs WHERE user_id = $1) AS total_activity_logs,
+        a.activations_today,
+        a.activations_week,
+        a.last_activation_at,
+        e.total_errors,
+        e.unresolved_errors,
+        f.focus_sessions_week,
+        f.focus_minutes_week
+      FROM activations a, errors e, focus f
       "#,
     )
     .bind(user_id)
-    .fetch_optional(self.pool)
+    .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
-    Ok(result)
+    Ok(row)
   }
 
-  /// Get last activation time
-  pub async fn get_last_activation(&self, user_id: Uuid) -> Result<Option<DateTime<Utc>>> {
-    let result: Option<(DateTime<Utc>,)> = sqlx::query_as(
+  /// Most used profile, as of the last `daily_stats` rollup, and the open
+  /// session, each optional
+  pub async fn get_dashboard_highlights(&self, user_id: Uuid) -> Result<DashboardHighlightsRow> {
+    let row = sqlx::query_as::<_, DashboardHighlightsRow>(
       r#"
-      SELECT started_at FROM profile_activations
-      WHERE user_id = $1
-      ORDER BY started_at DESC
-      LIMIT 1
+      WITH most_used AS (
+        SELECT p.id, p.name, SUM(ds.activations)::bigint AS activation_count
+        FROM profiles p
+        JOIN daily_stats ds ON p.id = ds.profile_id
+        WHERE p.user_id = $1
+        GROUP BY p.id, p.name
+        HAVING SUM(ds.activations) > 0
+        ORDER BY activation_count DESC
+        LIMIT 1
+      ),
+      active_session AS (
+        SELECT id, started_at FROM sessions
+        WHERE user_id = $1 AND ended_at IS NULL
+        ORDER BY started_at DESC
+        LIMIT 1
+      )
+      SELECT
+        m.id AS most_used_profile_id,
+        m.name AS most_used_profile_name,
+        COALESCE(m.activation_count, 0) AS most_used_profile_count,
+        s.id AS active_session_id,
+        s.started_at AS session_started_at
+      FROM (SELECT 1) AS one
+      LEFT JOIN most_used m ON true
+      LEFT JOIN active_session s ON true
       "#,
     )
     .bind(user_id)
-    .fetch_optional(self.pool)
+    .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
-    Ok(result.map(|(dt,)| dt))
+    Ok(row)
+  }
+
+  /// Row counts per log table for the log summary, with activations totalled
+  /// from `daily_stats`
+  pub async fn get_log_totals(&self, user_id: Uuid) -> Result<LogTotalsRow> {
+    let row = sqlx::query_as::<_, LogTotalsRow>(
+      r#"
+      SELECT
+        (SELECT COUNT(*) FROM activity_logs WHERE user_id = $1) AS total_activity_logs,
+        (SELECT COUNT(*) FROM system_events) AS total_system_events,
+        (SELECT COALESCE(SUM(activations), 0)::bigint FROM daily_stats WHERE user_id = $1)
+          AS total_profile_activations,
+        (SELECT COUNT(*) FROM error_logs) AS total_error_logs,
+        (SELECT COUNT(*) FROM sessions WHERE user_id = $1) AS total_sessions
+      "#,
+    )
+    .bind(user_id)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    Ok(row)
   }
 
   /// Get actions count by type
@@ -900,7 +1297,7 @@ impl<'a> AuditRepository<'a> {
     .bind(user_id)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     let map: std::collections::HashMap<String, i64> = results.into_iter().collect();
     Ok(serde_json::to_value(map).unwrap_or_default())
@@ -919,7 +1316,7 @@ impl<'a> AuditRepository<'a> {
     )
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     let map: std::collections::HashMap<String, i64> = results.into_iter().collect();
     Ok(serde_json::to_value(map).unwrap_or_default())
@@ -939,81 +1336,1092 @@ impl<'a> AuditRepository<'a> {
     .bind(user_id)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     let map: std::collections::HashMap<String, i64> = results.into_iter().collect();
     Ok(serde_json::to_value(map).unwrap_or_default())
   }
 
   // ============================================================================
-  // Cleanup
+  // Daily Statistics
   // ============================================================================
 
-  /// Delete old logs (retention policy)
-  pub async fn cleanup_old_logs(&self, days: i64) -> Result<()> {
-    let cutoff = format!("NOW() - INTERVAL '{} days'", days);
+  /// Most recent day already rolled up for a user
+  pub async fn last_daily_stats_day(&self, user_id: Uuid) -> Result<Option<NaiveDate>> {
+    let (day,): (Option<NaiveDate>,) =
+      sqlx::query_as("SELECT MAX(day) FROM daily_stats WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(self.pool)
+        .await
+        .map_err(SmoothieError::from)?;
 
-    // Clean activity logs
-    sqlx::query(&format!(
-      "DELETE FROM activity_logs WHERE created_at < {}",
-      cutoff
-    ))
-    .execute(self.pool)
+    Ok(day)
+  }
+
+  /// Recompute `daily_stats` from the raw log tables for every day from
+  /// `since` (or all history when `None`) up to today, returning rows written.
+  ///
+  /// A profile counts as active from a successful activation until the next
+  /// one, so its minutes are split across the days that window covers.
+  /// Errors are attributed to whichever profile was active when they occurred.
+  /// Only activations from the one in effect at `since` onwards are read.
+  pub async fn refresh_daily_stats(&self, user_id: Uuid, since: Option<NaiveDate>) -> Result<u64> {
+    let mut tx = self.pool.begin().await.map_err(SmoothieError::from)?;
+
+    // The background rollup and a report may refresh at the same time
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('daily_stats:' || $1::text))")
+      .bind(user_id)
+      .execute(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    sqlx::query(
+      "DELETE FROM daily_stats WHERE user_id = $1 AND day >= COALESCE($2::date, '-infinity')",
+    )
+    .bind(user_id)
+    .bind(since)
+    .execute(&mut *tx)
     .await
-    .ok();
+    .map_err(SmoothieError::from)?;
 
-    // Clean system events
-    sqlx::query(&format!(
-      "DELETE FROM system_events WHERE created_at < {}",
-      cutoff
-    ))
-    .execute(self.pool)
+    let inserted = sqlx::query(
+      r#"
+      WITH bounds AS (
+        SELECT COALESCE($2::date, '-infinity') AS since
+      ),
+      first_window AS (
+        SELECT COALESCE(MAX(pa.started_at), bounds.since) AS started_at
+        FROM bounds
+        LEFT JOIN profile_activations pa
+          ON pa.user_id = $1 AND pa.success AND pa.started_at < bounds.since
+        GROUP BY bounds.since
+      ),
+      windows AS (
+        SELECT
+          pa.profile_id,
+          pa.started_at,
+          COALESCE(LEAD(pa.started_at) OVER (ORDER BY pa.started_at), NOW()) AS ended_at
+        FROM profile_activations pa, first_window fw
+        WHERE pa.user_id = $1 AND pa.success AND pa.started_at >= fw.started_at
+      ),
+      events AS (
+        SELECT pa.started_at::date AS day, pa.profile_id,
+          1 AS activations, 0 AS app_launches, 0 AS errors, 0.0::float8 AS minutes
+        FROM profile_activations pa, bounds
+        WHERE pa.user_id = $1 AND pa.started_at::date >= bounds.since
+        UNION ALL
+        SELECT al.launched_at::date, al.profile_id, 0, 1, 0, 0.0
+        FROM app_launches al, bounds
+        WHERE al.user_id = $1 AND al.launched_at::date >= bounds.since
+        UNION ALL
+        SELECT el.created_at::date, w.profile_id, 0, 0, 1, 0.0
+        FROM error_logs el
+        CROSS JOIN bounds
+        LEFT JOIN windows w ON el.created_at >= w.started_at AND el.created_at < w.ended_at
+        WHERE (el.user_id = $1 OR el.user_id IS NULL) AND el.created_at::date >= bounds.since
+        UNION ALL
+        SELECT d::date, w.profile_id, 0, 0, 0,
+          EXTRACT(EPOCH FROM LEAST(w.ended_at, d + INTERVAL '1 day') - GREATEST(w.started_at, d))
+            / 60
+        FROM windows w
+        CROSS JOIN bounds
+        CROSS JOIN generate_series(date_trunc('day', w.started_at), w.ended_at, INTERVAL '1 day') d
+        WHERE d::date >= bounds.since
+      )
+      INSERT INTO daily_stats (
+        user_id, day, profile_id, activations, app_launches, errors, active_minutes
+      )
+      SELECT
+        $1, day, profile_id,
+        SUM(activations)::int, SUM(app_launches)::int, SUM(errors)::int, ROUND(SUM(minutes))::int
+      FROM events
+      GROUP BY day, profile_id
+      "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .execute(&mut *tx)
     .await
-    .ok();
+    .map_err(SmoothieError::from)?
+    .rows_affected();
 
-    // Clean resolved error logs
-    sqlx::query(&format!(
-      "DELETE FROM error_logs WHERE is_resolved = true AND resolved_at < {}",
-      cutoff
-    ))
-    .execute(self.pool)
+    tx.commit().await.map_err(SmoothieError::from)?;
+
+    Ok(inserted)
+  }
+
+  /// Rolled-up days from `since` onwards, newest first
+  pub async fn get_daily_stats(
+    &self,
+    user_id: Uuid,
+    since: NaiveDate,
+    profile_id_filter: Option<Uuid>,
+  ) -> Result<Vec<DailyStatsRow>> {
+    let rows = sqlx::query_as::<_, DailyStatsRow>(
+      r#"
+      SELECT ds.*, p.name AS profile_name
+      FROM daily_stats ds
+      LEFT JOIN profiles p ON p.id = ds.profile_id
+      WHERE ds.user_id = $1
+        AND ds.day >= $2
+        AND ($3::uuid IS NULL OR ds.profile_id = $3)
+      ORDER BY ds.day DESC, ds.active_minutes DESC
+      "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .bind(profile_id_filter)
+    .fetch_all(self.pool)
     .await
-    .ok();
+    .map_err(SmoothieError::from)?;
 
-    // Clean old sessions
-    sqlx::query(&format!("DELETE FROM sessions WHERE ended_at < {}", cutoff))
-      .execute(self.pool)
-      .await
-      .ok();
+    Ok(rows)
+  }
+
+  // ============================================================================
+  // App Focus
+  // ============================================================================
 
-    // Clean old app launches
-    sqlx::query(&format!(
-      "DELETE FROM app_launches WHERE launched_at < {}",
-      cutoff
-    ))
+  /// Record a stretch of time `bundle_id` was the frontmost app under a profile
+  pub async fn record_app_focus(
+    &self,
+    user_id: Uuid,
+    profile_id: Uuid,
+    bundle_id: &str,
+    app_name: &str,
+    started_at: DateTime<Utc>,
+    ended_at: DateTime<Utc>,
+  ) -> Result<()> {
+    let duration_ms = (ended_at - started_at)
+      .num_milliseconds()
+      .clamp(0, i32::MAX as i64) as i32;
+    sqlx::query(
+      r#"
+      INSERT INTO app_focus_events (
+        user_id, profile_id, bundle_id, app_name, started_at, ended_at, duration_ms
+      )
+      VALUES ($1, $2, $3, $4, $5, $6, $7)
+      "#,
+    )
+    .bind(user_id)
+    .bind(profile_id)
+    .bind(bundle_id)
+    .bind(app_name)
+    .bind(started_at)
+    .bind(ended_at)
+    .bind(duration_ms)
     .execute(self.pool)
     .await
-    .ok();
+    .map_err(SmoothieError::from)?;
 
-    // Clean old monitor changes
-    sqlx::query(&format!(
-      "DELETE FROM monitor_changes WHERE detected_at < {}",
-      cutoff
-    ))
-    .execute(self.pool)
+    Ok(())
+  }
+
+  /// Focus time per app under a profile since `since`, most used first
+  pub async fn get_focus_breakdown(
+    &self,
+    user_id: Uuid,
+    profile_id: Uuid,
+    since: DateTime<Utc>,
+  ) -> Result<Vec<AppFocusRow>> {
+    sqlx::query_as::<_, AppFocusRow>(
+      r#"
+      SELECT bundle_id,
+             (ARRAY_AGG(app_name ORDER BY ended_at DESC))[1] AS app_name,
+             SUM(duration_ms)::BIGINT AS focus_ms,
+             COUNT(*) AS sessions
+      FROM app_focus_events
+      WHERE user_id = $1 AND profile_id = $2 AND started_at >= $3
+      GROUP BY bundle_id
+      ORDER BY focus_ms DESC, bundle_id
+      "#,
+    )
+    .bind(user_id)
+    .bind(profile_id)
+    .bind(since)
+    .fetch_all(self.pool)
     .await
-    .ok();
+    .map_err(SmoothieError::from)
+  }
 
-    // Clean old automation executions
-    sqlx::query(&format!(
-      "DELETE FROM automation_executions WHERE executed_at < {}",
-      cutoff
-    ))
-    .execute(self.pool)
+  // ============================================================================
+  // Usage Reports
+  // ============================================================================
+
+  /// Profiles activated or in use between `since` and `until`, most focus time
+  /// first. Activations are counted from the `daily_stats` rows for `days`,
+  /// first and last inclusive.
+  pub async fn get_profile_usage(
+    &self,
+    user_id: Uuid,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    days: (NaiveDate, NaiveDate),
+  ) -> Result<Vec<ProfileUsageRow>> {
+    sqlx::query_as::<_, ProfileUsageRow>(
+      r#"
+      WITH activations AS (
+        SELECT profile_id, SUM(activations)::bigint AS activations
+        FROM daily_stats
+        WHERE user_id = $1 AND day BETWEEN $4 AND $5 AND activations > 0
+        GROUP BY profile_id
+      ),
+      focus AS (
+        SELECT profile_id, SUM(duration_ms)::BIGINT AS focus_ms
+        FROM app_focus_events
+        WHERE user_id = $1 AND started_at >= $2 AND started_at < $3
+        GROUP BY profile_id
+      )
+      SELECT p.id AS profile_id, p.name,
+             COALESCE(a.activations, 0) AS activations,
+             COALESCE(f.focus_ms, 0) AS focus_ms
+      FROM profiles p
+      LEFT JOIN activations a ON a.profile_id = p.id
+      LEFT JOIN focus f ON f.profile_id = p.id
+      WHERE p.user_id = $1 AND (a.profile_id IS NOT NULL OR f.profile_id IS NOT NULL)
+      ORDER BY focus_ms DESC, activations DESC, p.name
+      "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .bind(until)
+    .bind(days.0)
+    .bind(days.1)
+    .fetch_all(self.pool)
     .await
-    .ok();
+    .map_err(SmoothieError::from)
+  }
+
+  /// Focus time per app across all profiles between `since` and `until`
+  pub async fn get_top_apps(
+    &self,
+    user_id: Uuid,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    limit: i64,
+  ) -> Result<Vec<AppFocusRow>> {
+    sqlx::query_as::<_, AppFocusRow>(
+      r#"
+      SELECT bundle_id,
+             (ARRAY_AGG(app_name ORDER BY ended_at DESC))[1] AS app_name,
+             SUM(duration_ms)::BIGINT AS focus_ms,
+             COUNT(*) AS sessions
+      FROM app_focus_events
+      WHERE user_id = $1 AND started_at >= $2 AND started_at < $3
+      GROUP BY bundle_id
+      ORDER BY focus_ms DESC, bundle_id
+      LIMIT $4
+      "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .bind(until)
+    .bind(limit)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
 
-    tracing::info!("Cleaned up logs older than {} days", days);
+  /// Errors that occurred between `since` and `until`, grouped by type and
+  /// message, most frequent first. Errors logged without a user count too.
+  pub async fn get_error_summary(
+    &self,
+    user_id: Uuid,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    limit: i64,
+  ) -> Result<Vec<ErrorSummaryRow>> {
+    sqlx::query_as::<_, ErrorSummaryRow>(
+      r#"
+      SELECT error_type, message,
+             (ARRAY_AGG(severity ORDER BY last_occurred_at DESC))[1] AS severity,
+             SUM(COALESCE(occurrence_count, 1))::BIGINT AS occurrences,
+             MAX(last_occurred_at) AS last_occurred_at
+      FROM error_logs
+      WHERE (user_id = $1 OR user_id IS NULL)
+        AND last_occurred_at >= $2 AND first_occurred_at < $3
+      GROUP BY error_type, message
+      ORDER BY occurrences DESC, last_occurred_at DESC
+      LIMIT $4
+      "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .bind(until)
+    .bind(limit)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  // ============================================================================
+  // Cleanup
+  // ============================================================================
+
+  /// Delete logs older than `cutoff` in one transaction, returning rows deleted per table
+  pub async fn cleanup_old_logs(&self, cutoff: DateTime<Utc>) -> Result<Vec<(&'static str, u64)>> {
+    let mut tx = self.pool.begin().await.map_err(SmoothieError::from)?;
+
+    let mut deleted = Vec::with_capacity(LOG_RETENTION_RULES.len());
+    for (table, condition) in LOG_RETENTION_RULES {
+      let result = sqlx::query(&format!("DELETE FROM {} WHERE {}", table, condition))
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| SmoothieError::DatabaseError(format!("Failed to clean {}: {}", table, e)))?;
+      deleted.push((table, result.rows_affected()));
+    }
+
+    tx.commit().await.map_err(SmoothieError::from)?;
+
+    Ok(deleted)
+  }
+
+  /// Refresh planner statistics for the log tables after a large cleanup
+  pub async fn analyze_log_tables(&self) -> Result<()> {
+    for (table, _) in LOG_RETENTION_RULES {
+      sqlx::query(&format!("ANALYZE {}", table))
+        .execute(self.pool)
+        .await
+        .map_err(|e| SmoothieError::DatabaseError(format!("Failed to analyze {}: {}", table, e)))?;
+    }
     Ok(())
   }
 }
+
+#[async_trait]
+impl AuditStore for AuditRepository<'_> {
+  async fn log_activity(
+    &self,
+    user_id: Uuid,
+    session_id: Option<Uuid>,
+    action: &str,
+    entity_type: Option<&str>,
+    entity_id: Option<Uuid>,
+    entity_name: Option<&str>,
+    details: Option<serde_json::Value>,
+    status: ActivityStatus,
+    error_message: Option<&str>,
+    duration_ms: Option<i32>,
+  ) -> Result<ActivityLogEntity> {
+    AuditRepository::log_activity(
+      self,
+      user_id,
+      session_id,
+      action,
+      entity_type,
+      entity_id,
+      entity_name,
+      details,
+      status,
+      error_message,
+      duration_ms,
+    )
+    .await
+  }
+
+  async fn log_system_event(
+    &self,
+    event_type: &str,
+    severity: Severity,
+    source: &str,
+    message: &str,
+    details: Option<serde_json::Value>,
+    stack_trace: Option<&str>,
+    os_info: Option<serde_json::Value>,
+    app_version: Option<&str>,
+  ) -> Result<SystemEventEntity> {
+    AuditRepository::log_system_event(
+      self,
+      event_type,
+      severity,
+      source,
+      message,
+      details,
+      stack_trace,
+      os_info,
+      app_version,
+    )
+    .await
+  }
+
+  async fn start_session(
+    &self,
+    user_id: Uuid,
+    device_id: Option<&str>,
+    device_name: Option<&str>,
+    device_type: Option<&str>,
+    os_name: Option<&str>,
+    os_version: Option<&str>,
+    app_version: Option<&str>,
+    metadata: Option<serde_json::Value>,
+  ) -> Result<SessionEntity> {
+    AuditRepository::start_session(
+      self,
+      user_id,
+      device_id,
+      device_name,
+      device_type,
+      os_name,
+      os_version,
+      app_version,
+      metadata,
+    )
+    .await
+  }
+
+  async fn end_session(&self, session_id: Uuid, reason: &str) -> Result<SessionEntity> {
+    AuditRepository::end_session(self, session_id, reason).await
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  async fn record_profile_activation(
+    &self,
+    user_id: Uuid,
+    profile_id: Uuid,
+    session_id: Option<Uuid>,
+    activation_source: ActivationSource,
+    previous_profile_id: Option<Uuid>,
+    monitors_detected: Option<i32>,
+    monitors_applied: Option<i32>,
+    apps_detected: Option<i32>,
+    apps_launched: Option<i32>,
+    apps_failed: Option<i32>,
+    tabs_detected: Option<i32>,
+    tabs_opened: Option<i32>,
+    windows_restored: Option<i32>,
+    duration_ms: Option<i32>,
+    success: bool,
+    error_message: Option<&str>,
+    metadata: Option<serde_json::Value>,
+  ) -> Result<ProfileActivationEntity> {
+    AuditRepository::record_profile_activation(
+      self,
+      user_id,
+      profile_id,
+      session_id,
+      activation_source,
+      previous_profile_id,
+      monitors_detected,
+      monitors_applied,
+      apps_detected,
+      apps_launched,
+      apps_failed,
+      tabs_detected,
+      tabs_opened,
+      windows_restored,
+      duration_ms,
+      success,
+      error_message,
+      metadata,
+    )
+    .await
+  }
+
+  async fn log_error(
+    &self,
+    user_id: Option<Uuid>,
+    session_id: Option<Uuid>,
+    error_code: Option<&str>,
+    error_type: &str,
+    message: &str,
+    stack_trace: Option<&str>,
+    context: Option<serde_json::Value>,
+    source_file: Option<&str>,
+    source_line: Option<i32>,
+    source_function: Option<&str>,
+    severity: Severity,
+  ) -> Result<ErrorLogEntity> {
+    AuditRepository::log_error(
+      self,
+      user_id,
+      session_id,
+      error_code,
+      error_type,
+      message,
+      stack_trace,
+      context,
+      source_file,
+      source_line,
+      source_function,
+      severity,
+    )
+    .await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+
+  #[tokio::test]
+  async fn test_activity_logs_filter_and_count() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    let repo = AuditRepository::new(test_db.pool());
+
+    for action in ["profile_created", "profile_activated", "profile_activated"] {
+      repo
+        .log_activity(
+          user_id,
+          None,
+          action,
+          Some("profile"),
+          None,
+          None,
+          Some(serde_json::json!({ "source": "test" })),
+          ActivityStatus::Success,
+          None,
+          Some(12),
+        )
+        .await
+        .unwrap();
+    }
+
+    let activated = repo
+      .get_activity_logs(
+        user_id,
+        10,
+        0,
+        Some("profile_activated"),
+        None,
+        None,
+        None,
+        None,
+      )
+      .await
+      .unwrap();
+    assert_eq!(activated.len(), 2);
+    assert_eq!(
+      activated[0].details,
+      Some(serde_json::json!({ "source": "test" }))
+    );
+    assert_eq!(repo.count_activity_logs(user_id).await.unwrap(), 3);
+
+    let since = Utc::now() - chrono::Duration::hours(1);
+    let paged = repo
+      .get_activity_logs(
+        user_id,
+        1,
+        1,
+        Some("profile_activated"),
+        Some("profile"),
+        Some(since),
+        Some(Utc::now()),
+        None,
+      )
+      .await
+      .unwrap();
+    assert_eq!(paged.len(), 1);
+    assert!(repo
+      .get_activity_logs(user_id, 10, 0, None, Some("app"), None, None, None)
+      .await
+      .unwrap()
+      .is_empty());
+
+    let tagged = JsonFilter::Contains(serde_json::json!({ "source": "test" }));
+    let tagged_created = repo
+      .get_activity_logs(
+        user_id,
+        10,
+        0,
+        Some("profile_created"),
+        None,
+        None,
+        None,
+        Some(&tagged),
+      )
+      .await
+      .unwrap();
+    assert_eq!(tagged_created.len(), 1);
+    let other_source = JsonFilter::Matches(r#"$.source == "other""#.to_string());
+    assert!(repo
+      .get_activity_logs(user_id, 10, 0, None, None, None, None, Some(&other_source))
+      .await
+      .unwrap()
+      .is_empty());
+    assert_eq!(
+      repo.get_actions_by_type(user_id).await.unwrap()["profile_activated"],
+      2
+    );
+  }
+
+  #[tokio::test]
+  async fn test_session_lifecycle() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    let repo = AuditRepository::new(test_db.pool());
+
+    let session = repo
+      .start_session(
+        user_id,
+        Some("device-1"),
+        Some("MacBook"),
+        None,
+        Some("macos"),
+        Some("14.4"),
+        Some("1.0.0"),
+        None,
+      )
+      .await
+      .unwrap();
+    let active = repo.get_active_session(user_id).await.unwrap().unwrap();
+    assert_eq!(active.id, session.id);
+
+    let ended = repo.end_session(session.id, "app_closed").await.unwrap();
+    assert_eq!(ended.end_reason.as_deref(), Some("app_closed"));
+    assert!(ended.ended_at.is_some());
+    assert!(repo.get_active_session(user_id).await.unwrap().is_none());
+    assert_eq!(repo.get_sessions(user_id, 10, 0).await.unwrap().len(), 1);
+  }
+
+  #[tokio::test]
+  async fn test_profile_activation_stats() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    let focus = test_db.seed_profile(user_id, "Focus").await;
+    let gaming = test_db.seed_profile(user_id, "Gaming").await;
+    let repo = AuditRepository::new(test_db.pool());
+
+    for (profile_id, source) in [
+      (focus, ActivationSource::Manual),
+      (focus, ActivationSource::Schedule),
+      (gaming, ActivationSource::Manual),
+    ] {
+      repo
+        .record_profile_activation(
+          user_id,
+          profile_id,
+          None,
+          source,
+          None,
+          Some(2),
+          Some(2),
+          Some(3),
+          Some(3),
+          Some(0),
+          None,
+          None,
+          None,
+          Some(850),
+          true,
+          None,
+          Some(serde_json::json!({ "environment": { "os": "macos" } })),
+        )
+        .await
+        .unwrap();
+    }
+
+    let focus_activations = repo
+      .get_profile_activations(user_id, 10, 0, Some(focus))
+      .await
+      .unwrap();
+    assert_eq!(focus_activations.len(), 2);
+    assert_eq!(focus_activations[0].profile_name.as_deref(), Some("Focus"));
+    assert_eq!(
+      focus_activations[0].activation.metadata.as_ref().unwrap()["environment"]["os"],
+      "macos"
+    );
+
+    repo.refresh_daily_stats(user_id, None).await.unwrap();
+    let highlights = repo.get_dashboard_highlights(user_id).await.unwrap();
+    assert_eq!(highlights.most_used_profile_id, Some(focus));
+    assert_eq!(highlights.most_used_profile_name.as_deref(), Some("Focus"));
+    assert_eq!(highlights.most_used_profile_count, 2);
+    let counts = repo.get_dashboard_counts(user_id).await.unwrap();
+    assert_eq!(counts.activations_today, 3);
+    assert_eq!(counts.activations_week, 3);
+    assert!(counts.last_activation_at.is_some());
+    assert_eq!(
+      repo.get_activations_by_source(user_id).await.unwrap()["manual"],
+      2
+    );
+    assert_eq!(
+      repo
+        .get_log_totals(user_id)
+        .await
+        .unwrap()
+        .total_profile_activations,
+      3
+    );
+  }
+
+  #[tokio::test]
+  async fn test_daily_stats_rollup_splits_activity_by_profile() {
+    let test_db = test_db_or_skip!();
+    let pool = test_db.pool();
+    let user_id = test_db.seed_user().await;
+    let focus = test_db.seed_profile(user_id, "Focus").await;
+    let gaming = test_db.seed_profile(user_id, "Gaming").await;
+    let repo = AuditRepository::new(pool);
+
+    for (profile_id, at) in [
+      (focus, "2026-01-05 10:00:00+00"),
+      (gaming, "2026-01-05 12:00:00+00"),
+    ] {
+      sqlx::query(
+        "INSERT INTO profile_activations (user_id, profile_id, activation_source, started_at) \
+         VALUES ($1, $2, 'manual', $3::timestamptz)",
+      )
+      .bind(user_id)
+      .bind(profile_id)
+      .bind(at)
+      .execute(pool)
+      .await
+      .unwrap();
+    }
+    sqlx::query(
+      "INSERT INTO app_launches (user_id, profile_id, bundle_id, app_name, launched_at) \
+       VALUES ($1, $2, 'com.apple.Safari', 'Safari', '2026-01-05 10:05:00+00')",
+    )
+    .bind(user_id)
+    .bind(focus)
+    .execute(pool)
+    .await
+    .unwrap();
+    sqlx::query(
+      "INSERT INTO error_logs (user_id, error_type, message, created_at) \
+       VALUES ($1, 'launch', 'boom', '2026-01-05 11:00:00+00')",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await
+    .unwrap();
+
+    assert!(repo.refresh_daily_stats(user_id, None).await.unwrap() > 0);
+
+    let since = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+    let focus_days = repo
+      .get_daily_stats(user_id, since, Some(focus))
+      .await
+      .unwrap();
+    assert_eq!(focus_days.len(), 1);
+    let day = &focus_days[0];
+    assert_eq!(day.profile_name.as_deref(), Some("Focus"));
+    assert_eq!(
+      (
+        day.stats.activations,
+        day.stats.app_launches,
+        day.stats.errors,
+        day.stats.active_minutes
+      ),
+      (1, 1, 1, 120)
+    );
+
+    // Refreshing from the last rolled-up day leaves earlier days alone
+    let last_day = repo.last_daily_stats_day(user_id).await.unwrap();
+    repo.refresh_daily_stats(user_id, last_day).await.unwrap();
+    assert_eq!(
+      repo
+        .get_daily_stats(user_id, since, Some(focus))
+        .await
+        .unwrap()[0]
+        .stats
+        .active_minutes,
+      120
+    );
+
+    // A refresh starting after an activation still counts the time it covers
+    let jan_6 = NaiveDate::from_ymd_opt(2026, 1, 6).unwrap();
+    repo
+      .refresh_daily_stats(user_id, Some(jan_6))
+      .await
+      .unwrap();
+    let gaming_days = repo
+      .get_daily_stats(user_id, jan_6, Some(gaming))
+      .await
+      .unwrap();
+    assert_eq!(gaming_days.last().unwrap().stats.day, jan_6);
+    assert_eq!(gaming_days.last().unwrap().stats.active_minutes, 24 * 60);
+  }
+
+  #[tokio::test]
+  async fn test_error_logs_deduplicate_until_resolved() {
+    let test_db = test_db_or_skip!();
+    let repo = AuditRepository::new(test_db.pool());
+
+    let log = |message: &'static str| {
+      let repo = &repo;
+      async move {
+        repo
+          .log_error(
+            None,
+            None,
+            Some("E42"),
+            "database",
+            message,
+            None,
+            Some(serde_json::json!({ "query": "SELECT 1" })),
+            Some("audit_repository.rs"),
+            Some(42),
+            None,
+            Severity::Error,
+          )
+          .await
+          .unwrap()
+      }
+    };
+
+    let first = log("connection refused").await;
+    let repeated = log("connection refused").await;
+    assert_eq!(repeated.id, first.id);
+    assert_eq!(repeated.occurrence_count, Some(2));
+    log("pool timed out").await;
+    assert_eq!(repo.count_unresolved_errors().await.unwrap(), 2);
+
+    let resolved = repo
+      .resolve_error(first.id, Some("restarted postgres"))
+      .await
+      .unwrap();
+    assert_eq!(resolved.is_resolved, Some(true));
+    assert_eq!(repo.count_unresolved_errors().await.unwrap(), 1);
+    assert_eq!(repo.get_errors_by_severity().await.unwrap()["error"], 1);
+
+    // A resolved error that comes back is re-opened as a regression
+    let recurring = log("connection refused").await;
+    assert_eq!(recurring.id, first.id);
+    assert_eq!(recurring.is_resolved, Some(false));
+    assert_eq!(recurring.regression_count, 1);
+    assert!(recurring.reopened_at.is_some());
+    assert_eq!(repo.count_unresolved_errors().await.unwrap(), 2);
+
+    // Messages differing only in interpolated values share a group...
+    let timeout = log("pool timed out after 30s").await;
+    let merged = log("pool timed out after 45s").await;
+    assert_eq!(merged.id, timeout.id);
+
+    // ...until the group has been quiet for longer than the dedup window
+    sqlx::query("UPDATE error_logs SET last_occurred_at = NOW() - INTERVAL '2 days' WHERE id = $1")
+      .bind(timeout.id)
+      .execute(test_db.pool())
+      .await
+      .unwrap();
+    let fresh = log("pool timed out after 60s").await;
+    assert_ne!(fresh.id, timeout.id);
+    assert_eq!(
+      repo
+        .get_error_logs(10, 0, Some(Severity::Error), true)
+        .await
+        .unwrap()
+        .len(),
+      4
+    );
+  }
+
+  #[tokio::test]
+  async fn test_error_groups_track_occurrences_per_day_and_version() {
+    let test_db = test_db_or_skip!();
+    let repo = AuditRepository::new(test_db.pool());
+    let log = |message: &'static str| {
+      let repo = &repo;
+      async move {
+        repo
+          .log_error(
+            None,
+            None,
+            None,
+            "sync",
+            message,
+            None,
+            None,
+            Some("sync_service.rs"),
+            None,
+            None,
+            Severity::Warning,
+          )
+          .await
+          .unwrap()
+      }
+    };
+
+    let first = log("upload failed after 3 retries").await;
+    log("upload failed after 4 retries").await;
+    log("bundle rejected").await;
+    let fingerprint = first.fingerprint.clone().unwrap();
+    // An occurrence two days ago under an older build
+    sqlx::query(
+      "INSERT INTO error_daily_counts (fingerprint, day, app_version, occurrences)
+       VALUES ($1, (NOW() AT TIME ZONE 'UTC')::date - 2, '0.0.1', 5)",
+    )
+    .bind(&fingerprint)
+    .execute(test_db.pool())
+    .await
+    .unwrap();
+
+    let groups = repo.get_error_groups(false, 10).await.unwrap();
+    assert_eq!(groups.len(), 2);
+    let upload = groups
+      .iter()
+      .find(|g| g.fingerprint == fingerprint)
+      .unwrap();
+    assert_eq!(upload.occurrences, 2);
+    assert_eq!(upload.message, "upload failed after 3 retries");
+
+    let fingerprints = vec![fingerprint.clone()];
+    let today = Utc::now().date_naive();
+    let mut daily = repo
+      .get_error_daily_counts(&fingerprints, today - chrono::Duration::days(29))
+      .await
+      .unwrap();
+    daily.sort();
+    assert_eq!(
+      daily,
+      vec![
+        (fingerprint.clone(), today - chrono::Duration::days(2), 5),
+        (fingerprint.clone(), today, 2),
+      ]
+    );
+    assert_eq!(
+      repo.get_error_versions(&fingerprints).await.unwrap(),
+      vec![(
+        fingerprint,
+        vec!["0.0.1".to_string(), env!("CARGO_PKG_VERSION").to_string()]
+      )]
+    );
+
+    repo.resolve_error(first.id, None).await.unwrap();
+    assert_eq!(repo.get_error_groups(false, 10).await.unwrap().len(), 1);
+    assert_eq!(repo.get_error_groups(true, 10).await.unwrap().len(), 2);
+  }
+
+  #[tokio::test]
+  async fn test_cleanup_old_logs_counts_per_table() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    let repo = AuditRepository::new(test_db.pool());
+
+    for action in ["old", "recent"] {
+      repo
+        .log_activity(
+          user_id,
+          None,
+          action,
+          None,
+          None,
+          None,
+          None,
+          ActivityStatus::Success,
+          None,
+          None,
+        )
+        .await
+        .unwrap();
+    }
+    sqlx::query(
+      "UPDATE activity_logs SET created_at = NOW() - INTERVAL '40 days' WHERE action = 'old'",
+    )
+    .execute(test_db.pool())
+    .await
+    .unwrap();
+
+    let deleted = repo
+      .cleanup_old_logs(Utc::now() - chrono::Duration::days(30))
+      .await
+      .unwrap();
+    assert!(deleted.contains(&("activity_logs", 1)));
+    assert!(deleted.contains(&("system_events", 0)));
+    assert_eq!(repo.count_activity_logs(user_id).await.unwrap(), 1);
+
+    repo.analyze_log_tables().await.unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_focus_breakdown_sums_per_app() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    let work = test_db.seed_profile(user_id, "Work").await;
+    let home = test_db.seed_profile(user_id, "Home").await;
+    let repo = AuditRepository::new(test_db.pool());
+
+    let start = Utc::now() - chrono::Duration::hours(1);
+    let spans = [
+      (work, "com.apple.Safari", 0, 60),
+      (work, "com.apple.Terminal", 60, 240),
+      (work, "com.apple.Safari", 240, 300),
+      (home, "com.apple.Safari", 300, 900),
+    ];
+    for (profile_id, bundle_id, from, to) in spans {
+      repo
+        .record_app_focus(
+          user_id,
+          profile_id,
+          bundle_id,
+          bundle_id.trim_start_matches("com.apple."),
+          start + chrono::Duration::seconds(from),
+          start + chrono::Duration::seconds(to),
+        )
+        .await
+        .unwrap();
+    }
+
+    let rows = repo
+      .get_focus_breakdown(user_id, work, start - chrono::Duration::minutes(1))
+      .await
+      .unwrap();
+    let summary: Vec<_> = rows
+      .iter()
+      .map(|row| (row.app_name.as_str(), row.focus_ms, row.sessions))
+      .collect();
+    assert_eq!(summary, [("Terminal", 180_000, 1), ("Safari", 120_000, 2)]);
+  }
+
+  #[tokio::test]
+  async fn test_profile_usage_covers_only_the_report_range() {
+    let test_db = test_db_or_skip!();
+    let pool = test_db.pool();
+    let user_id = test_db.seed_user().await;
+    let focus = test_db.seed_profile(user_id, "Focus").await;
+    let gaming = test_db.seed_profile(user_id, "Gaming").await;
+    test_db.seed_profile(user_id, "Unused").await;
+    let repo = AuditRepository::new(pool);
+
+    let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+    for (profile_id, started_at) in [
+      (focus, "2026-01-05T09:00:00Z"),
+      (focus, "2026-01-06T09:00:00Z"),
+      (gaming, "2025-12-31T20:00:00Z"),
+    ] {
+      sqlx::query(
+        "INSERT INTO profile_activations (user_id, profile_id, activation_source, started_at) \
+         VALUES ($1, $2, 'manual', $3)",
+      )
+      .bind(user_id)
+      .bind(profile_id)
+      .bind(at(started_at))
+      .execute(pool)
+      .await
+      .unwrap();
+    }
+    repo
+      .record_app_focus(
+        user_id,
+        focus,
+        "com.apple.Terminal",
+        "Terminal",
+        at("2026-01-05T09:00:00Z"),
+        at("2026-01-05T10:30:00Z"),
+      )
+      .await
+      .unwrap();
+
+    repo.refresh_daily_stats(user_id, None).await.unwrap();
+
+    let usage: Vec<_> = repo
+      .get_profile_usage(
+        user_id,
+        at("2026-01-05T00:00:00Z"),
+        at("2026-01-12T00:00:00Z"),
+        (
+          NaiveDate::from_ymd_opt(2026, 1, 5).unwrap(),
+          NaiveDate::from_ymd_opt(2026, 1, 11).unwrap(),
+        ),
+      )
+      .await
+      .unwrap()
+      .into_iter()
+      .map(|row| (row.name, row.activations, row.focus_ms))
+      .collect();
+    assert_eq!(usage, [("Focus".to_string(), 2, 90 * 60_000)]);
+  }
+}
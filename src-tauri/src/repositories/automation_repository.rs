@@ -1,9 +1,11 @@
 // Automation repository - database operations for automation rules
 
+use super::JsonFilter;
 use crate::error::{Result, SmoothieError};
-use crate::models::entities::AutomationRuleEntity;
-use chrono::Utc;
-use sqlx::PgPool;
+use crate::models::entities::{AutomationRuleEntity, RuleActionEntity};
+use crate::models::enums::RuleActionType;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
 pub struct AutomationRepository<'a> {
@@ -19,37 +21,68 @@ impl<'a> AutomationRepository<'a> {
   pub async fn find_by_profile_id(&self, profile_id: Uuid) -> Result<Vec<AutomationRuleEntity>> {
     sqlx::query_as::<_, AutomationRuleEntity>(
       r#"
-            SELECT id, profile_id, rule_type, trigger_config, is_enabled, created_at
+            SELECT id, profile_id, rule_type, trigger_config, conditions, priority,
+                   stop_after_match, cooldown_minutes, last_triggered_at, snoozed_until,
+                   active_hours, is_enabled, created_at
             FROM automation_rules
             WHERE profile_id = $1
+            ORDER BY priority DESC, created_at
             "#,
     )
     .bind(profile_id)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    .map_err(SmoothieError::from)
   }
 
   /// Find enabled rules by type
   pub async fn find_enabled_by_type(&self, rule_type: &str) -> Result<Vec<AutomationRuleEntity>> {
     sqlx::query_as::<_, AutomationRuleEntity>(
       r#"
-            SELECT id, profile_id, rule_type, trigger_config, is_enabled, created_at
+            SELECT id, profile_id, rule_type, trigger_config, conditions, priority,
+                   stop_after_match, cooldown_minutes, last_triggered_at, snoozed_until,
+                   active_hours, is_enabled, created_at
             FROM automation_rules
             WHERE rule_type = $1 AND is_enabled = true
+            ORDER BY priority DESC, created_at
             "#,
     )
     .bind(rule_type)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    .map_err(SmoothieError::from)
+  }
+
+  /// Find enabled rules by type whose trigger config satisfies `trigger`
+  pub async fn find_enabled_by_type_matching(
+    &self,
+    rule_type: &str,
+    trigger: &JsonFilter,
+  ) -> Result<Vec<AutomationRuleEntity>> {
+    let mut query = QueryBuilder::<Postgres>::new(
+      "SELECT id, profile_id, rule_type, trigger_config, conditions, priority, \
+       stop_after_match, cooldown_minutes, last_triggered_at, snoozed_until, \
+       active_hours, is_enabled, created_at \
+       FROM automation_rules WHERE is_enabled = true AND rule_type = ",
+    );
+    query.push_bind(rule_type).push(" AND ");
+    trigger.push(&mut query, "trigger_config");
+    query.push(" ORDER BY priority DESC, created_at");
+
+    query
+      .build_query_as::<AutomationRuleEntity>()
+      .fetch_all(self.pool)
+      .await
+      .map_err(SmoothieError::from)
   }
 
   /// Find an automation rule by ID
   pub async fn find_by_id(&self, id: Uuid) -> Result<Option<AutomationRuleEntity>> {
     sqlx::query_as::<_, AutomationRuleEntity>(
       r#"
-            SELECT id, profile_id, rule_type, trigger_config, is_enabled, created_at
+            SELECT id, profile_id, rule_type, trigger_config, conditions, priority,
+                   stop_after_match, cooldown_minutes, last_triggered_at, snoozed_until,
+                   active_hours, is_enabled, created_at
             FROM automation_rules
             WHERE id = $1
             "#,
@@ -57,7 +90,7 @@ impl<'a> AutomationRepository<'a> {
     .bind(id)
     .fetch_optional(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    .map_err(SmoothieError::from)
   }
 
   /// Create a new automation rule
@@ -66,24 +99,26 @@ impl<'a> AutomationRepository<'a> {
     profile_id: Uuid,
     rule_type: &str,
     trigger_config: serde_json::Value,
+    conditions: Option<serde_json::Value>,
   ) -> Result<AutomationRuleEntity> {
     let id = Uuid::new_v4();
     let now = Utc::now();
 
     sqlx::query(
             r#"
-            INSERT INTO automation_rules (id, profile_id, rule_type, trigger_config, is_enabled, created_at)
-            VALUES ($1, $2, $3, $4, true, $5)
+            INSERT INTO automation_rules (id, profile_id, rule_type, trigger_config, conditions, is_enabled, created_at)
+            VALUES ($1, $2, $3, $4, $5, true, $6)
             "#,
         )
         .bind(id)
         .bind(profile_id)
         .bind(rule_type)
         .bind(&trigger_config)
+        .bind(&conditions)
         .bind(now)
         .execute(self.pool)
         .await
-        .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+        .map_err(SmoothieError::from)?;
 
     self
       .find_by_id(id)
@@ -98,7 +133,55 @@ impl<'a> AutomationRepository<'a> {
       .bind(id)
       .execute(self.pool)
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
+
+    self
+      .find_by_id(id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Automation rule not found".into()))
+  }
+
+  /// Replace a rule's conditions; `None` clears them
+  pub async fn set_conditions(
+    &self,
+    id: Uuid,
+    conditions: Option<serde_json::Value>,
+  ) -> Result<AutomationRuleEntity> {
+    sqlx::query("UPDATE automation_rules SET conditions = $1, updated_at = NOW() WHERE id = $2")
+      .bind(&conditions)
+      .bind(id)
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    self
+      .find_by_id(id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Automation rule not found".into()))
+  }
+
+  /// Set how a rule is ordered against others matching the same trigger
+  pub async fn set_priority(
+    &self,
+    id: Uuid,
+    priority: i32,
+    stop_after_match: bool,
+    cooldown_minutes: i32,
+  ) -> Result<AutomationRuleEntity> {
+    sqlx::query(
+      r#"
+      UPDATE automation_rules
+      SET priority = $1, stop_after_match = $2, cooldown_minutes = $3, updated_at = NOW()
+      WHERE id = $4
+      "#,
+    )
+    .bind(priority)
+    .bind(stop_after_match)
+    .bind(cooldown_minutes)
+    .bind(id)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
 
     self
       .find_by_id(id)
@@ -106,13 +189,111 @@ impl<'a> AutomationRepository<'a> {
       .ok_or_else(|| SmoothieError::NotFound("Automation rule not found".into()))
   }
 
+  /// Snooze a rule until the given time; `None` wakes it
+  pub async fn snooze(
+    &self,
+    id: Uuid,
+    until: Option<DateTime<Utc>>,
+  ) -> Result<AutomationRuleEntity> {
+    sqlx::query("UPDATE automation_rules SET snoozed_until = $1, updated_at = NOW() WHERE id = $2")
+      .bind(until)
+      .bind(id)
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    self
+      .find_by_id(id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Automation rule not found".into()))
+  }
+
+  /// Replace a rule's active hours; `None` lets it act at any time
+  pub async fn set_active_hours(
+    &self,
+    id: Uuid,
+    active_hours: Option<serde_json::Value>,
+  ) -> Result<AutomationRuleEntity> {
+    sqlx::query("UPDATE automation_rules SET active_hours = $1, updated_at = NOW() WHERE id = $2")
+      .bind(&active_hours)
+      .bind(id)
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    self
+      .find_by_id(id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Automation rule not found".into()))
+  }
+
+  /// A rule's actions in execution order
+  pub async fn find_actions(&self, rule_id: Uuid) -> Result<Vec<RuleActionEntity>> {
+    sqlx::query_as::<_, RuleActionEntity>(
+      "SELECT * FROM rule_actions WHERE rule_id = $1 ORDER BY position",
+    )
+    .bind(rule_id)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Actions for several rules at once, grouped by rule and in execution order
+  pub async fn find_actions_for_rules(&self, rule_ids: &[Uuid]) -> Result<Vec<RuleActionEntity>> {
+    sqlx::query_as::<_, RuleActionEntity>(
+      "SELECT * FROM rule_actions WHERE rule_id = ANY($1) ORDER BY rule_id, position",
+    )
+    .bind(rule_ids)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Replace a rule's actions; positions follow the slice order
+  pub async fn replace_actions(
+    &self,
+    rule_id: Uuid,
+    actions: &[(RuleActionType, serde_json::Value)],
+  ) -> Result<Vec<RuleActionEntity>> {
+    let mut tx = self.pool.begin().await.map_err(SmoothieError::from)?;
+
+    sqlx::query("DELETE FROM rule_actions WHERE rule_id = $1")
+      .bind(rule_id)
+      .execute(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    let mut inserted = Vec::with_capacity(actions.len());
+    for (position, (action_type, config)) in actions.iter().enumerate() {
+      let entity = sqlx::query_as::<_, RuleActionEntity>(
+        r#"
+        INSERT INTO rule_actions (rule_id, position, action_type, config)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+      )
+      .bind(rule_id)
+      .bind(position as i32)
+      .bind(action_type)
+      .bind(config)
+      .fetch_one(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+      inserted.push(entity);
+    }
+
+    tx.commit().await.map_err(SmoothieError::from)?;
+
+    Ok(inserted)
+  }
+
   /// Delete an automation rule
   pub async fn delete(&self, id: Uuid) -> Result<bool> {
     let result = sqlx::query("DELETE FROM automation_rules WHERE id = $1")
       .bind(id)
       .execute(self.pool)
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
 
     Ok(result.rows_affected() > 0)
   }
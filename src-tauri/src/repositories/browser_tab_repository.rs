@@ -3,7 +3,7 @@
 use crate::error::{Result, SmoothieError};
 use crate::models::entities::BrowserTabEntity;
 use chrono::Utc;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
 pub struct BrowserTabRepository<'a> {
@@ -28,7 +28,7 @@ impl<'a> BrowserTabRepository<'a> {
     .bind(profile_id)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    .map_err(SmoothieError::from)
   }
 
   /// Find a browser tab by ID
@@ -43,7 +43,7 @@ impl<'a> BrowserTabRepository<'a> {
     .bind(id)
     .fetch_optional(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    .map_err(SmoothieError::from)
   }
 
   /// Create a new browser tab
@@ -55,32 +55,46 @@ impl<'a> BrowserTabRepository<'a> {
     monitor_id: Option<Uuid>,
     tab_order: i32,
     favicon: Option<&str>,
+  ) -> Result<BrowserTabEntity> {
+    let mut conn = self.pool.acquire().await.map_err(SmoothieError::from)?;
+    Self::create_in(
+      &mut conn, profile_id, url, browser, monitor_id, tab_order, favicon,
+    )
+    .await
+  }
+
+  /// [`create`](Self::create) on the caller's connection, so it can be part
+  /// of a larger transaction
+  pub async fn create_in(
+    conn: &mut PgConnection,
+    profile_id: Uuid,
+    url: &str,
+    browser: &str,
+    monitor_id: Option<Uuid>,
+    tab_order: i32,
+    favicon: Option<&str>,
   ) -> Result<BrowserTabEntity> {
     let id = Uuid::new_v4();
     let now = Utc::now();
 
-    sqlx::query(
-            r#"
+    sqlx::query_as::<_, BrowserTabEntity>(
+      r#"
             INSERT INTO browser_tabs (id, profile_id, url, browser, monitor_id, tab_order, favicon, created_at, updated_at)
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8)
+            RETURNING id, profile_id, url, browser, monitor_id, tab_order, favicon, created_at, updated_at
             "#,
-        )
-        .bind(id)
-        .bind(profile_id)
-        .bind(url)
-        .bind(browser)
-        .bind(monitor_id)
-        .bind(tab_order)
-        .bind(favicon)
-        .bind(now)
-        .execute(self.pool)
-        .await
-        .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
-
-    self
-      .find_by_id(id)
-      .await?
-      .ok_or_else(|| SmoothieError::NotFound("Browser tab not found after creation".into()))
+    )
+    .bind(id)
+    .bind(profile_id)
+    .bind(url)
+    .bind(browser)
+    .bind(monitor_id)
+    .bind(tab_order)
+    .bind(favicon)
+    .bind(now)
+    .fetch_one(conn)
+    .await
+    .map_err(SmoothieError::from)
   }
 
   /// Update a browser tab
@@ -92,7 +106,7 @@ impl<'a> BrowserTabRepository<'a> {
       .bind(id)
       .execute(self.pool)
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
 
     self
       .find_by_id(id)
@@ -100,13 +114,47 @@ impl<'a> BrowserTabRepository<'a> {
       .ok_or_else(|| SmoothieError::NotFound("Browser tab not found".into()))
   }
 
+  /// Set `tab_order` of the profile's tabs to follow `ids` in a single
+  /// statement; tabs left out keep their relative order after the listed ones.
+  /// Returns `false`, changing nothing, if an id isn't one of the profile's tabs.
+  pub async fn reorder(&self, profile_id: Uuid, ids: &[Uuid]) -> Result<bool> {
+    let result = sqlx::query(
+      r#"
+      WITH listed AS (
+        SELECT id, position FROM unnest($2::uuid[]) WITH ORDINALITY AS l(id, position)
+      ), ranked AS (
+        SELECT t.id,
+               (ROW_NUMBER() OVER (
+                 ORDER BY l.position NULLS LAST, t.tab_order, t.created_at
+               ) - 1)::int AS tab_order
+        FROM browser_tabs t
+        LEFT JOIN listed l ON l.id = t.id
+        WHERE t.profile_id = $1
+      )
+      UPDATE browser_tabs t
+      SET tab_order = r.tab_order
+      FROM ranked r
+      WHERE t.id = r.id
+        AND (SELECT COUNT(*) FROM browser_tabs WHERE profile_id = $1 AND id = ANY($2))
+            = cardinality($2)
+      "#,
+    )
+    .bind(profile_id)
+    .bind(ids)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    Ok(ids.is_empty() || result.rows_affected() > 0)
+  }
+
   /// Delete a browser tab
   pub async fn delete(&self, id: Uuid) -> Result<bool> {
     let result = sqlx::query("DELETE FROM browser_tabs WHERE id = $1")
       .bind(id)
       .execute(self.pool)
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
 
     Ok(result.rows_affected() > 0)
   }
@@ -118,7 +166,7 @@ impl<'a> BrowserTabRepository<'a> {
         .bind(profile_id)
         .fetch_one(self.pool)
         .await
-        .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+        .map_err(SmoothieError::from)?;
 
     Ok(count)
   }
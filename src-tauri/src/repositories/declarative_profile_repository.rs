@@ -0,0 +1,71 @@
+// Declarative profile repository - which profiles a declarative file manages
+
+use crate::error::{Result, SmoothieError};
+use crate::models::entities::DeclarativeProfileEntity;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct DeclarativeProfileRepository<'a> {
+  pool: &'a PgPool,
+}
+
+impl<'a> DeclarativeProfileRepository<'a> {
+  pub fn new(pool: &'a PgPool) -> Self {
+    Self { pool }
+  }
+
+  pub async fn find_by_source(
+    &self,
+    user_id: Uuid,
+    source_path: &str,
+  ) -> Result<Vec<DeclarativeProfileEntity>> {
+    sqlx::query_as::<_, DeclarativeProfileEntity>(
+      "SELECT * FROM declarative_profiles WHERE user_id = $1 AND source_path = $2",
+    )
+    .bind(user_id)
+    .bind(source_path)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Profiles any declarative file manages for `user_id`
+  pub async fn find_managed_profile_ids(&self, user_id: Uuid) -> Result<Vec<Uuid>> {
+    sqlx::query_scalar("SELECT profile_id FROM declarative_profiles WHERE user_id = $1")
+      .bind(user_id)
+      .fetch_all(self.pool)
+      .await
+      .map_err(SmoothieError::from)
+  }
+
+  /// Record that `profile_id` now matches the entry `profile_key` of the file
+  pub async fn upsert(
+    &self,
+    user_id: Uuid,
+    source_path: &str,
+    profile_key: &str,
+    profile_id: Uuid,
+    content_hash: &str,
+  ) -> Result<()> {
+    sqlx::query(
+      r#"
+      INSERT INTO declarative_profiles
+        (user_id, source_path, profile_key, profile_id, content_hash, synced_at)
+      VALUES ($1, $2, $3, $4, $5, NOW())
+      ON CONFLICT (user_id, source_path, profile_key) DO UPDATE SET
+        profile_id = EXCLUDED.profile_id,
+        content_hash = EXCLUDED.content_hash,
+        synced_at = NOW()
+      "#,
+    )
+    .bind(user_id)
+    .bind(source_path)
+    .bind(profile_key)
+    .bind(profile_id)
+    .bind(content_hash)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+    Ok(())
+  }
+}
@@ -0,0 +1,118 @@
+// Focus session repository - timed sessions and what they blocked
+
+use crate::error::{Result, SmoothieError};
+use crate::models::entities::FocusSessionEntity;
+use crate::models::FocusSessionStatus;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct FocusSessionRepository<'a> {
+  pool: &'a PgPool,
+}
+
+impl<'a> FocusSessionRepository<'a> {
+  pub fn new(pool: &'a PgPool) -> Self {
+    Self { pool }
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  pub async fn create(
+    &self,
+    user_id: Uuid,
+    profile_id: Uuid,
+    planned_minutes: i32,
+    blocked_apps: &[String],
+    blocked_urls: &[String],
+    dnd_shortcut: Option<&str>,
+    dnd_off_shortcut: Option<&str>,
+  ) -> Result<FocusSessionEntity> {
+    sqlx::query_as::<_, FocusSessionEntity>(
+      r#"
+      INSERT INTO focus_sessions
+        (user_id, profile_id, planned_minutes, blocked_apps, blocked_urls, dnd_shortcut, dnd_off_shortcut)
+      VALUES ($1, $2, $3, $4, $5, $6, $7)
+      RETURNING *
+      "#,
+    )
+    .bind(user_id)
+    .bind(profile_id)
+    .bind(planned_minutes)
+    .bind(serde_json::json!(blocked_apps))
+    .bind(serde_json::json!(blocked_urls))
+    .bind(dnd_shortcut)
+    .bind(dnd_off_shortcut)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// The user's running session, if any
+  pub async fn find_active(&self, user_id: Uuid) -> Result<Option<FocusSessionEntity>> {
+    sqlx::query_as::<_, FocusSessionEntity>(
+      "SELECT * FROM focus_sessions WHERE user_id = $1 AND status = 'active'",
+    )
+    .bind(user_id)
+    .fetch_optional(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Recent sessions, newest first, optionally for one profile
+  pub async fn find_recent(
+    &self,
+    user_id: Uuid,
+    profile_id: Option<Uuid>,
+    limit: i64,
+  ) -> Result<Vec<FocusSessionEntity>> {
+    sqlx::query_as::<_, FocusSessionEntity>(
+      r#"
+      SELECT * FROM focus_sessions
+      WHERE user_id = $1 AND ($2::uuid IS NULL OR profile_id = $2)
+      ORDER BY started_at DESC
+      LIMIT $3
+      "#,
+    )
+    .bind(user_id)
+    .bind(profile_id)
+    .bind(limit)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Close an active session; `None` when it already ended
+  pub async fn finish(
+    &self,
+    id: Uuid,
+    status: FocusSessionStatus,
+    ended_at: DateTime<Utc>,
+  ) -> Result<Option<FocusSessionEntity>> {
+    sqlx::query_as::<_, FocusSessionEntity>(
+      r#"
+      UPDATE focus_sessions
+      SET status = $2, ended_at = $3
+      WHERE id = $1 AND status = 'active'
+      RETURNING *
+      "#,
+    )
+    .bind(id)
+    .bind(status)
+    .bind(ended_at)
+    .fetch_optional(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  pub async fn add_blocked_app_quits(&self, id: Uuid, count: i32) -> Result<()> {
+    sqlx::query(
+      "UPDATE focus_sessions SET blocked_app_quits = blocked_app_quits + $2 WHERE id = $1",
+    )
+    .bind(id)
+    .bind(count)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+    Ok(())
+  }
+}
@@ -0,0 +1,70 @@
+// Folder sync repository - the last agreed state of each synced profile bundle
+
+use crate::error::{Result, SmoothieError};
+use crate::models::entities::FolderSyncItemEntity;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct FolderSyncRepository<'a> {
+  pool: &'a PgPool,
+}
+
+impl<'a> FolderSyncRepository<'a> {
+  pub fn new(pool: &'a PgPool) -> Self {
+    Self { pool }
+  }
+
+  pub async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<FolderSyncItemEntity>> {
+    sqlx::query_as::<_, FolderSyncItemEntity>("SELECT * FROM folder_sync_items WHERE user_id = $1")
+      .bind(user_id)
+      .fetch_all(self.pool)
+      .await
+      .map_err(SmoothieError::from)
+  }
+
+  /// Record the state both sides now agree on for `sync_id`
+  pub async fn upsert(
+    &self,
+    sync_id: Uuid,
+    user_id: Uuid,
+    profile_id: Option<Uuid>,
+    content_hash: &str,
+    modified_at: DateTime<Utc>,
+    deleted: bool,
+  ) -> Result<()> {
+    sqlx::query(
+      r#"
+      INSERT INTO folder_sync_items
+        (sync_id, user_id, profile_id, content_hash, modified_at, deleted, synced_at)
+      VALUES ($1, $2, $3, $4, $5, $6, NOW())
+      ON CONFLICT (user_id, sync_id) DO UPDATE SET
+        profile_id = EXCLUDED.profile_id,
+        content_hash = EXCLUDED.content_hash,
+        modified_at = EXCLUDED.modified_at,
+        deleted = EXCLUDED.deleted,
+        synced_at = NOW()
+      "#,
+    )
+    .bind(sync_id)
+    .bind(user_id)
+    .bind(profile_id)
+    .bind(content_hash)
+    .bind(modified_at)
+    .bind(deleted)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+    Ok(())
+  }
+
+  /// Forget everything synced, e.g. when the sync folder changes
+  pub async fn delete_for_user(&self, user_id: Uuid) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM folder_sync_items WHERE user_id = $1")
+      .bind(user_id)
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
+    Ok(result.rows_affected())
+  }
+}
@@ -0,0 +1,58 @@
+// JSON filters - conditions on JSONB columns, pushed into a QueryBuilder
+//
+// Both forms are answered by the jsonb_path_ops GIN indexes from migration
+// v16, so filtering on a payload doesn't fall back to a sequential scan.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Postgres, QueryBuilder};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JsonFilter {
+  /// The column contains this document (`@>`), e.g. `{"demo": true}`
+  Contains(Value),
+  /// The JSON-path predicate holds (`@@`), e.g. `$.duration_ms > 500`
+  Matches(String),
+}
+
+impl JsonFilter {
+  /// Append `<column> <op> $n` to the query
+  pub fn push<'args>(&self, query: &mut QueryBuilder<'args, Postgres>, column: &str) {
+    query.push(column);
+    match self {
+      JsonFilter::Contains(document) => {
+        query.push(" @> ").push_bind(document.clone());
+      }
+      JsonFilter::Matches(path) => {
+        query
+          .push(" @@ ")
+          .push_bind(path.clone())
+          .push("::jsonpath");
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_deserializes_and_renders_both_forms() {
+    let contains: JsonFilter =
+      serde_json::from_value(json!({ "contains": { "demo": true } })).unwrap();
+    let matches: JsonFilter = serde_json::from_value(json!({ "matches": "$.count > 1" })).unwrap();
+    assert_eq!(contains, JsonFilter::Contains(json!({ "demo": true })));
+
+    let mut query = QueryBuilder::<Postgres>::new("SELECT 1 WHERE ");
+    contains.push(&mut query, "details");
+    query.push(" AND ");
+    matches.push(&mut query, "details");
+    assert_eq!(
+      query.sql(),
+      "SELECT 1 WHERE details @> $1 AND details @@ $2::jsonpath"
+    );
+  }
+}
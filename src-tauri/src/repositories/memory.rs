@@ -0,0 +1,398 @@
+// In-memory repository fakes for service unit tests
+
+use super::traits::{AuditStore, ProfileChildCounts, ProfileStore};
+use crate::error::{Result, SmoothieError};
+use crate::logging::current_request_id;
+use crate::models::entities::*;
+use crate::models::enums::{ActivationSource, ActivityStatus, ProfileType, Severity};
+use crate::utils::error_fingerprint::error_fingerprint;
+use async_trait::async_trait;
+use chrono::Utc;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Default)]
+pub struct InMemoryProfileStore {
+  pub profiles: Mutex<Vec<ProfileEntity>>,
+  pub tags: Mutex<HashMap<Uuid, Vec<String>>>,
+  pub counts: Mutex<HashMap<Uuid, ProfileChildCounts>>,
+}
+
+impl InMemoryProfileStore {
+  /// Set the monitor/app/tab counts reported for a profile
+  pub fn set_counts(&self, profile_id: Uuid, monitors: i64, apps: i64, browser_tabs: i64) {
+    self.counts.lock().insert(
+      profile_id,
+      ProfileChildCounts {
+        monitors,
+        apps,
+        browser_tabs,
+      },
+    );
+  }
+
+  fn update<F>(&self, id: Uuid, apply: F) -> Result<ProfileEntity>
+  where
+    F: FnOnce(&mut ProfileEntity),
+  {
+    let mut profiles = self.profiles.lock();
+    let profile = profiles
+      .iter_mut()
+      .find(|p| p.id == id)
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?;
+    apply(profile);
+    profile.updated_at = Utc::now();
+    Ok(profile.clone())
+  }
+}
+
+#[async_trait]
+impl ProfileStore for InMemoryProfileStore {
+  async fn find_by_user_id(&self, user_id: Uuid) -> Result<Vec<ProfileEntity>> {
+    Ok(
+      self
+        .profiles
+        .lock()
+        .iter()
+        .filter(|p| p.user_id == user_id)
+        .cloned()
+        .collect(),
+    )
+  }
+
+  async fn find_by_id(&self, id: Uuid) -> Result<Option<ProfileEntity>> {
+    Ok(self.profiles.lock().iter().find(|p| p.id == id).cloned())
+  }
+
+  async fn find_favorites(&self, user_id: Uuid) -> Result<Vec<ProfileEntity>> {
+    Ok(
+      self
+        .find_by_user_id(user_id)
+        .await?
+        .into_iter()
+        .filter(|p| p.is_favorite == Some(true))
+        .collect(),
+    )
+  }
+
+  async fn find_most_used(&self, user_id: Uuid, limit: i64) -> Result<Vec<ProfileEntity>> {
+    let mut profiles = self.find_by_user_id(user_id).await?;
+    profiles.sort_by_key(|p| std::cmp::Reverse(p.activation_count.unwrap_or(0)));
+    profiles.truncate(limit.max(0) as usize);
+    Ok(profiles)
+  }
+
+  async fn create(
+    &self,
+    user_id: Uuid,
+    name: &str,
+    description: Option<&str>,
+    profile_type: ProfileType,
+  ) -> Result<ProfileEntity> {
+    let now = Utc::now();
+    let profile = ProfileEntity {
+      id: Uuid::new_v4(),
+      user_id,
+      name: name.to_string(),
+      description: description.map(str::to_string),
+      profile_type,
+      is_active: false,
+      created_at: now,
+      updated_at: now,
+      last_used: None,
+      last_activated_at: None,
+      activation_count: Some(0),
+      is_favorite: Some(false),
+      color: None,
+      icon: None,
+      sort_order: None,
+      low_power_battery_threshold: None,
+      local_only: false,
+      required_monitor_count: None,
+      monitor_signature: None,
+      notes: None,
+      include_virtual_displays: false,
+      is_locked: false,
+    };
+    self.profiles.lock().push(profile.clone());
+    Ok(profile)
+  }
+
+  async fn set_favorite(&self, id: Uuid, is_favorite: bool) -> Result<ProfileEntity> {
+    self.update(id, |p| p.is_favorite = Some(is_favorite))
+  }
+
+  async fn activate(&self, id: Uuid, user_id: Uuid) -> Result<ProfileEntity> {
+    for profile in self
+      .profiles
+      .lock()
+      .iter_mut()
+      .filter(|p| p.user_id == user_id)
+    {
+      profile.is_active = false;
+    }
+    let now = Utc::now();
+    self.update(id, |p| {
+      p.is_active = true;
+      p.last_used = Some(now);
+      p.last_activated_at = Some(now);
+      p.activation_count = Some(p.activation_count.unwrap_or(0) + 1);
+    })
+  }
+
+  async fn find_tags(&self, profile_id: Uuid) -> Result<Vec<String>> {
+    Ok(
+      self
+        .tags
+        .lock()
+        .get(&profile_id)
+        .cloned()
+        .unwrap_or_default(),
+    )
+  }
+
+  async fn set_tags(&self, profile_id: Uuid, tags: &[String]) -> Result<()> {
+    let mut unique: Vec<String> = Vec::new();
+    for tag in tags {
+      if !unique.contains(tag) {
+        unique.push(tag.clone());
+      }
+    }
+    self.tags.lock().insert(profile_id, unique);
+    Ok(())
+  }
+
+  async fn set_notes(&self, id: Uuid, notes: Option<&str>) -> Result<()> {
+    self.update(id, |p| p.notes = notes.map(str::to_string))?;
+    Ok(())
+  }
+
+  async fn count_children(&self, profile_id: Uuid) -> Result<ProfileChildCounts> {
+    Ok(
+      self
+        .counts
+        .lock()
+        .get(&profile_id)
+        .copied()
+        .unwrap_or_default(),
+    )
+  }
+}
+
+#[derive(Default)]
+pub struct InMemoryAuditStore {
+  pub activities: Mutex<Vec<ActivityLogEntity>>,
+  pub system_events: Mutex<Vec<SystemEventEntity>>,
+  pub sessions: Mutex<Vec<SessionEntity>>,
+  pub activations: Mutex<Vec<ProfileActivationEntity>>,
+  pub errors: Mutex<Vec<ErrorLogEntity>>,
+}
+
+#[async_trait]
+impl AuditStore for InMemoryAuditStore {
+  async fn log_activity(
+    &self,
+    user_id: Uuid,
+    session_id: Option<Uuid>,
+    action: &str,
+    entity_type: Option<&str>,
+    entity_id: Option<Uuid>,
+    entity_name: Option<&str>,
+    details: Option<serde_json::Value>,
+    status: ActivityStatus,
+    error_message: Option<&str>,
+    duration_ms: Option<i32>,
+  ) -> Result<ActivityLogEntity> {
+    let entity = ActivityLogEntity {
+      id: Uuid::new_v4(),
+      user_id,
+      session_id,
+      action: action.to_string(),
+      entity_type: entity_type.map(str::to_string),
+      entity_id,
+      entity_name: entity_name.map(str::to_string),
+      details,
+      ip_address: None,
+      user_agent: None,
+      status,
+      error_message: error_message.map(str::to_string),
+      duration_ms,
+      request_id: current_request_id(),
+      created_at: Utc::now(),
+    };
+    self.activities.lock().push(entity.clone());
+    Ok(entity)
+  }
+
+  async fn log_system_event(
+    &self,
+    event_type: &str,
+    severity: Severity,
+    source: &str,
+    message: &str,
+    details: Option<serde_json::Value>,
+    stack_trace: Option<&str>,
+    os_info: Option<serde_json::Value>,
+    app_version: Option<&str>,
+  ) -> Result<SystemEventEntity> {
+    let entity = SystemEventEntity {
+      id: Uuid::new_v4(),
+      event_type: event_type.to_string(),
+      severity,
+      source: source.to_string(),
+      message: message.to_string(),
+      details,
+      stack_trace: stack_trace.map(str::to_string),
+      os_info,
+      app_version: app_version.map(str::to_string),
+      request_id: current_request_id(),
+      created_at: Utc::now(),
+    };
+    self.system_events.lock().push(entity.clone());
+    Ok(entity)
+  }
+
+  async fn start_session(
+    &self,
+    user_id: Uuid,
+    device_id: Option<&str>,
+    device_name: Option<&str>,
+    device_type: Option<&str>,
+    os_name: Option<&str>,
+    os_version: Option<&str>,
+    app_version: Option<&str>,
+    metadata: Option<serde_json::Value>,
+  ) -> Result<SessionEntity> {
+    let now = Utc::now();
+    let entity = SessionEntity {
+      id: Uuid::new_v4(),
+      user_id,
+      device_id: device_id.map(str::to_string),
+      device_name: device_name.map(str::to_string),
+      device_type: device_type.map(str::to_string),
+      os_name: os_name.map(str::to_string),
+      os_version: os_version.map(str::to_string),
+      app_version: app_version.map(str::to_string),
+      ip_address: None,
+      started_at: now,
+      last_activity_at: now,
+      ended_at: None,
+      end_reason: None,
+      is_active: Some(true),
+      metadata,
+    };
+    self.sessions.lock().push(entity.clone());
+    Ok(entity)
+  }
+
+  async fn end_session(&self, session_id: Uuid, reason: &str) -> Result<SessionEntity> {
+    let mut sessions = self.sessions.lock();
+    let session = sessions
+      .iter_mut()
+      .find(|s| s.id == session_id)
+      .ok_or_else(|| SmoothieError::NotFound("Session not found".into()))?;
+    session.ended_at = Some(Utc::now());
+    session.end_reason = Some(reason.to_string());
+    session.is_active = Some(false);
+    Ok(session.clone())
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  async fn record_profile_activation(
+    &self,
+    user_id: Uuid,
+    profile_id: Uuid,
+    session_id: Option<Uuid>,
+    activation_source: ActivationSource,
+    previous_profile_id: Option<Uuid>,
+    monitors_detected: Option<i32>,
+    monitors_applied: Option<i32>,
+    apps_detected: Option<i32>,
+    apps_launched: Option<i32>,
+    apps_failed: Option<i32>,
+    tabs_detected: Option<i32>,
+    tabs_opened: Option<i32>,
+    windows_restored: Option<i32>,
+    duration_ms: Option<i32>,
+    success: bool,
+    error_message: Option<&str>,
+    metadata: Option<serde_json::Value>,
+  ) -> Result<ProfileActivationEntity> {
+    let now = Utc::now();
+    let entity = ProfileActivationEntity {
+      id: Uuid::new_v4(),
+      user_id,
+      profile_id,
+      session_id,
+      activation_source,
+      previous_profile_id,
+      monitors_detected,
+      monitors_applied,
+      apps_detected,
+      apps_launched,
+      apps_failed,
+      tabs_detected,
+      tabs_opened,
+      windows_restored,
+      duration_ms,
+      success,
+      error_message: error_message.map(str::to_string),
+      rollback_performed: Some(false),
+      metadata,
+      started_at: now,
+      completed_at: Some(now),
+    };
+    self.activations.lock().push(entity.clone());
+    Ok(entity)
+  }
+
+  async fn log_error(
+    &self,
+    user_id: Option<Uuid>,
+    session_id: Option<Uuid>,
+    error_code: Option<&str>,
+    error_type: &str,
+    message: &str,
+    stack_trace: Option<&str>,
+    context: Option<serde_json::Value>,
+    source_file: Option<&str>,
+    source_line: Option<i32>,
+    source_function: Option<&str>,
+    severity: Severity,
+  ) -> Result<ErrorLogEntity> {
+    let now = Utc::now();
+    let entity = ErrorLogEntity {
+      id: Uuid::new_v4(),
+      user_id,
+      session_id,
+      error_code: error_code.map(str::to_string),
+      error_type: error_type.to_string(),
+      message: message.to_string(),
+      stack_trace: stack_trace.map(str::to_string),
+      context,
+      source_file: source_file.map(str::to_string),
+      source_line,
+      source_function: source_function.map(str::to_string),
+      severity,
+      is_resolved: Some(false),
+      resolved_at: None,
+      resolution_notes: None,
+      occurrence_count: Some(1),
+      first_occurred_at: now,
+      last_occurred_at: now,
+      created_at: now,
+      fingerprint: Some(error_fingerprint(
+        error_type,
+        message,
+        source_file,
+        source_function,
+      )),
+      regression_count: 0,
+      reopened_at: None,
+      request_id: current_request_id(),
+    };
+    self.errors.lock().push(entity.clone());
+    Ok(entity)
+  }
+}
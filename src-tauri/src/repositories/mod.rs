@@ -3,20 +3,45 @@
 //! This module provides database access abstractions following the repository pattern.
 //! Repositories encapsulate data access logic and provide a clean API for services.
 
+mod alert_repository;
 mod app_repository;
 mod audit_repository;
 mod automation_repository;
 mod browser_tab_repository;
+mod declarative_profile_repository;
+mod focus_session_repository;
+mod folder_sync_repository;
+mod json_filter;
 mod monitor_repository;
+mod profile_file_repository;
 mod profile_repository;
+mod profile_snapshot_repository;
+mod storage_repository;
 mod subscription_repository;
+mod supabase_audit_repository;
+mod team_repository;
+mod traits;
 mod user_settings_repository;
 
+#[cfg(test)]
+pub mod memory;
+
+pub use alert_repository::AlertRepository;
 pub use app_repository::AppRepository;
 pub use audit_repository::AuditRepository;
 pub use automation_repository::AutomationRepository;
 pub use browser_tab_repository::BrowserTabRepository;
+pub use declarative_profile_repository::DeclarativeProfileRepository;
+pub use focus_session_repository::FocusSessionRepository;
+pub use folder_sync_repository::FolderSyncRepository;
+pub use json_filter::JsonFilter;
 pub use monitor_repository::MonitorRepository;
-pub use profile_repository::ProfileRepository;
+pub use profile_file_repository::ProfileFileRepository;
+pub use profile_repository::{ProfilePart, ProfileRepository};
+pub use profile_snapshot_repository::{ProfileRows, ProfileSnapshotRepository};
+pub use storage_repository::StorageRepository;
 pub use subscription_repository::SubscriptionRepository;
+pub use supabase_audit_repository::SupabaseAuditRepository;
+pub use team_repository::{TeamRepository, TeamSnapshot};
+pub use traits::{AuditStore, ProfileStore};
 pub use user_settings_repository::UserSettingsRepository;
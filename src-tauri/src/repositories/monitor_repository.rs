@@ -3,7 +3,7 @@
 use crate::error::{Result, SmoothieError};
 use crate::models::entities::MonitorEntity;
 use chrono::Utc;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
 pub struct MonitorRepository<'a> {
@@ -21,7 +21,8 @@ impl<'a> MonitorRepository<'a> {
       r#"
             SELECT id, profile_id, name, resolution, orientation, is_primary,
                    x, y, width, height, display_index, brand, model, refresh_rate,
-                   scale_factor, is_builtin, color_depth, created_at, updated_at
+                   scale_factor, is_builtin, color_depth, created_at, updated_at,
+                   fingerprint, captured_at, blanked
             FROM monitors
             WHERE profile_id = $1
             ORDER BY display_index
@@ -30,7 +31,7 @@ impl<'a> MonitorRepository<'a> {
     .bind(profile_id)
     .fetch_all(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    .map_err(SmoothieError::from)
   }
 
   /// Find a monitor by ID
@@ -39,7 +40,8 @@ impl<'a> MonitorRepository<'a> {
       r#"
             SELECT id, profile_id, name, resolution, orientation, is_primary,
                    x, y, width, height, display_index, brand, model, refresh_rate,
-                   scale_factor, is_builtin, color_depth, created_at, updated_at
+                   scale_factor, is_builtin, color_depth, created_at, updated_at,
+                   fingerprint, captured_at, blanked
             FROM monitors
             WHERE id = $1
             "#,
@@ -47,7 +49,7 @@ impl<'a> MonitorRepository<'a> {
     .bind(id)
     .fetch_optional(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    .map_err(SmoothieError::from)
   }
 
   /// Create a new monitor with full metadata
@@ -107,16 +109,65 @@ impl<'a> MonitorRepository<'a> {
     scale_factor: Option<f64>,
     is_builtin: Option<bool>,
     color_depth: Option<i32>,
+  ) -> Result<MonitorEntity> {
+    let mut conn = self.pool.acquire().await.map_err(SmoothieError::from)?;
+    Self::create_with_metadata_in(
+      &mut conn,
+      profile_id,
+      name,
+      resolution,
+      orientation,
+      is_primary,
+      x,
+      y,
+      width,
+      height,
+      display_index,
+      brand,
+      model,
+      refresh_rate,
+      scale_factor,
+      is_builtin,
+      color_depth,
+    )
+    .await
+  }
+
+  /// [`create_with_metadata`](Self::create_with_metadata) on the caller's
+  /// connection, so it can be part of a larger transaction
+  #[allow(clippy::too_many_arguments)]
+  pub async fn create_with_metadata_in(
+    conn: &mut PgConnection,
+    profile_id: Uuid,
+    name: &str,
+    resolution: &str,
+    orientation: &str,
+    is_primary: bool,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    display_index: i32,
+    brand: Option<&str>,
+    model: Option<&str>,
+    refresh_rate: Option<i32>,
+    scale_factor: Option<f64>,
+    is_builtin: Option<bool>,
+    color_depth: Option<i32>,
   ) -> Result<MonitorEntity> {
     let id = Uuid::new_v4();
     let now = Utc::now();
 
-    sqlx::query(
+    sqlx::query_as::<_, MonitorEntity>(
       r#"
             INSERT INTO monitors (id, profile_id, name, resolution, orientation, is_primary,
                    x, y, width, height, display_index, brand, model, refresh_rate,
                    scale_factor, is_builtin, color_depth, created_at, updated_at)
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $18)
+            RETURNING id, profile_id, name, resolution, orientation, is_primary,
+                   x, y, width, height, display_index, brand, model, refresh_rate,
+                   scale_factor, is_builtin, color_depth, created_at, updated_at,
+                   fingerprint, captured_at, blanked
             "#,
     )
     .bind(id)
@@ -137,14 +188,86 @@ impl<'a> MonitorRepository<'a> {
     .bind(is_builtin)
     .bind(color_depth)
     .bind(now)
-    .execute(self.pool)
+    .fetch_one(conn)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)
+  }
 
-    self
-      .find_by_id(id)
-      .await?
-      .ok_or_else(|| SmoothieError::NotFound("Monitor not found after creation".into()))
+  /// Save a captured monitor, or update the geometry and metadata of the one
+  /// already saved for the same display (same profile and fingerprint)
+  pub async fn upsert_captured(&self, monitor: &MonitorEntity) -> Result<MonitorEntity> {
+    sqlx::query_as::<_, MonitorEntity>(
+      r#"
+            INSERT INTO monitors (id, profile_id, name, resolution, orientation, is_primary,
+                   x, y, width, height, display_index, brand, model, refresh_rate,
+                   scale_factor, is_builtin, color_depth, created_at, updated_at,
+                   fingerprint, captured_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                    $18, $18, $19, $18)
+            ON CONFLICT (profile_id, fingerprint) DO UPDATE
+              SET name = EXCLUDED.name, resolution = EXCLUDED.resolution,
+                  orientation = EXCLUDED.orientation, is_primary = EXCLUDED.is_primary,
+                  x = EXCLUDED.x, y = EXCLUDED.y, width = EXCLUDED.width,
+                  height = EXCLUDED.height, display_index = EXCLUDED.display_index,
+                  brand = EXCLUDED.brand, model = EXCLUDED.model,
+                  refresh_rate = EXCLUDED.refresh_rate, scale_factor = EXCLUDED.scale_factor,
+                  is_builtin = EXCLUDED.is_builtin, updated_at = EXCLUDED.updated_at,
+                  captured_at = EXCLUDED.captured_at
+            RETURNING id, profile_id, name, resolution, orientation, is_primary,
+                   x, y, width, height, display_index, brand, model, refresh_rate,
+                   scale_factor, is_builtin, color_depth, created_at, updated_at,
+                   fingerprint, captured_at, blanked
+            "#,
+    )
+    .bind(monitor.id)
+    .bind(monitor.profile_id)
+    .bind(&monitor.name)
+    .bind(&monitor.resolution)
+    .bind(&monitor.orientation)
+    .bind(monitor.is_primary)
+    .bind(monitor.x)
+    .bind(monitor.y)
+    .bind(monitor.width)
+    .bind(monitor.height)
+    .bind(monitor.display_index)
+    .bind(&monitor.brand)
+    .bind(&monitor.model)
+    .bind(monitor.refresh_rate)
+    .bind(monitor.scale_factor)
+    .bind(monitor.is_builtin)
+    .bind(monitor.color_depth)
+    .bind(monitor.captured_at.unwrap_or_else(Utc::now))
+    .bind(&monitor.fingerprint)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Delete the profile's fingerprinted monitors that its most recent capture
+  /// didn't include. Monitors added by hand have no fingerprint and are left
+  /// alone, as is everything while the profile has never been captured.
+  pub async fn prune_stale(&self, profile_id: Uuid) -> Result<Vec<MonitorEntity>> {
+    sqlx::query_as::<_, MonitorEntity>(
+      r#"
+            WITH latest AS (
+              SELECT MAX(captured_at) AS captured_at FROM monitors WHERE profile_id = $1
+            )
+            DELETE FROM monitors m
+            USING latest
+            WHERE m.profile_id = $1
+              AND m.fingerprint IS NOT NULL
+              AND latest.captured_at IS NOT NULL
+              AND (m.captured_at IS NULL OR m.captured_at < latest.captured_at)
+            RETURNING m.id, m.profile_id, m.name, m.resolution, m.orientation, m.is_primary,
+                   m.x, m.y, m.width, m.height, m.display_index, m.brand, m.model,
+                   m.refresh_rate, m.scale_factor, m.is_builtin, m.color_depth,
+                   m.created_at, m.updated_at, m.fingerprint, m.captured_at, m.blanked
+            "#,
+    )
+    .bind(profile_id)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
   }
 
   /// Update monitor position
@@ -169,7 +292,23 @@ impl<'a> MonitorRepository<'a> {
     .bind(id)
     .execute(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
+
+    self
+      .find_by_id(id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Monitor not found".into()))
+  }
+
+  /// Mark a monitor to be kept dark while its profile is active
+  pub async fn set_blanked(&self, id: Uuid, blanked: bool) -> Result<MonitorEntity> {
+    sqlx::query("UPDATE monitors SET blanked = $1, updated_at = $2 WHERE id = $3")
+      .bind(blanked)
+      .bind(Utc::now())
+      .bind(id)
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
 
     self
       .find_by_id(id)
@@ -183,7 +322,7 @@ impl<'a> MonitorRepository<'a> {
       .bind(id)
       .execute(self.pool)
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
 
     Ok(result.rows_affected() > 0)
   }
@@ -195,7 +334,7 @@ impl<'a> MonitorRepository<'a> {
         .bind(profile_id)
         .fetch_one(self.pool)
         .await
-        .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+        .map_err(SmoothieError::from)?;
 
     Ok(count)
   }
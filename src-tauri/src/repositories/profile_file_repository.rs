@@ -0,0 +1,144 @@
+// Profile file repository - database operations for documents/folders opened with a profile
+
+use crate::error::{Result, SmoothieError};
+use crate::models::entities::ProfileFileEntity;
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct ProfileFileRepository<'a> {
+  pool: &'a PgPool,
+}
+
+impl<'a> ProfileFileRepository<'a> {
+  pub fn new(pool: &'a PgPool) -> Self {
+    Self { pool }
+  }
+
+  /// Find all files for a profile
+  pub async fn find_by_profile_id(&self, profile_id: Uuid) -> Result<Vec<ProfileFileEntity>> {
+    sqlx::query_as::<_, ProfileFileEntity>(
+      r#"
+            SELECT id, profile_id, path, target_app, open_on_activate, open_order, created_at, updated_at
+            FROM profile_files
+            WHERE profile_id = $1
+            ORDER BY open_order
+            "#,
+    )
+    .bind(profile_id)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Find files that should be opened when the profile is activated
+  pub async fn find_openable(&self, profile_id: Uuid) -> Result<Vec<ProfileFileEntity>> {
+    sqlx::query_as::<_, ProfileFileEntity>(
+      r#"
+            SELECT id, profile_id, path, target_app, open_on_activate, open_order, created_at, updated_at
+            FROM profile_files
+            WHERE profile_id = $1 AND open_on_activate = true
+            ORDER BY open_order
+            "#,
+    )
+    .bind(profile_id)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Find a profile file by ID
+  pub async fn find_by_id(&self, id: Uuid) -> Result<Option<ProfileFileEntity>> {
+    sqlx::query_as::<_, ProfileFileEntity>(
+      r#"
+            SELECT id, profile_id, path, target_app, open_on_activate, open_order, created_at, updated_at
+            FROM profile_files
+            WHERE id = $1
+            "#,
+    )
+    .bind(id)
+    .fetch_optional(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Create a new profile file
+  pub async fn create(
+    &self,
+    profile_id: Uuid,
+    path: &str,
+    target_app: Option<&str>,
+    open_on_activate: bool,
+    open_order: i32,
+  ) -> Result<ProfileFileEntity> {
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+
+    sqlx::query(
+      r#"
+            INSERT INTO profile_files (id, profile_id, path, target_app, open_on_activate, open_order, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+            "#,
+    )
+    .bind(id)
+    .bind(profile_id)
+    .bind(path)
+    .bind(target_app)
+    .bind(open_on_activate)
+    .bind(open_order)
+    .bind(now)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    self
+      .find_by_id(id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile file not found after creation".into()))
+  }
+
+  /// Update a profile file. An empty `target_app` clears the override.
+  pub async fn update(
+    &self,
+    id: Uuid,
+    path: Option<&str>,
+    target_app: Option<&str>,
+    open_on_activate: Option<bool>,
+  ) -> Result<ProfileFileEntity> {
+    let now = Utc::now();
+    sqlx::query(
+      r#"
+            UPDATE profile_files
+            SET path = COALESCE($1, path),
+                target_app = CASE WHEN $2::text IS NULL THEN target_app ELSE NULLIF($2, '') END,
+                open_on_activate = COALESCE($3, open_on_activate),
+                updated_at = $4
+            WHERE id = $5
+            "#,
+    )
+    .bind(path)
+    .bind(target_app)
+    .bind(open_on_activate)
+    .bind(now)
+    .bind(id)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    self
+      .find_by_id(id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile file not found".into()))
+  }
+
+  /// Delete a profile file
+  pub async fn delete(&self, id: Uuid) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM profile_files WHERE id = $1")
+      .bind(id)
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    Ok(result.rows_affected() > 0)
+  }
+}
@@ -1,12 +1,57 @@
 // Profile repository - database operations for profiles
 
+use super::traits::{ProfileChildCounts, ProfileStore};
+use super::{AppRepository, BrowserTabRepository, MonitorRepository};
 use crate::error::{Result, SmoothieError};
-use crate::models::entities::ProfileEntity;
+use crate::models::entities::{
+  AppEntity, BrowserTabEntity, MonitorEntity, ProfileBlockedAppEntity, ProfileChecklistItemEntity,
+  ProfileDeactivationActionEntity, ProfileEntity,
+};
+use crate::models::enums::{BlockedAppAction, DeactivationActionType, ProfileType};
+use async_trait::async_trait;
 use chrono::Utc;
+use sqlx::types::Json;
 use sqlx::PgPool;
 use tracing::{error, info, instrument, warn};
 use uuid::Uuid;
 
+/// Rows saved as part of a profile, which its lock covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilePart {
+  Monitor,
+  Window,
+  App,
+  BrowserTab,
+  File,
+  AutomationRule,
+}
+
+impl ProfilePart {
+  fn table(self) -> &'static str {
+    match self {
+      ProfilePart::Monitor => "monitors",
+      ProfilePart::Window => "windows",
+      ProfilePart::App => "apps",
+      ProfilePart::BrowserTab => "browser_tabs",
+      ProfilePart::File => "profile_files",
+      ProfilePart::AutomationRule => "automation_rules",
+    }
+  }
+}
+
+/// A profile's monitors, apps and browser tabs
+pub struct ProfileChildren {
+  pub monitors: Vec<MonitorEntity>,
+  pub apps: Vec<AppEntity>,
+  pub browser_tabs: Vec<BrowserTabEntity>,
+}
+
+type ChildrenRow = (
+  Json<Vec<MonitorEntity>>,
+  Json<Vec<AppEntity>>,
+  Json<Vec<BrowserTabEntity>>,
+);
+
 pub struct ProfileRepository<'a> {
   pool: &'a PgPool,
 }
@@ -16,6 +61,57 @@ impl<'a> ProfileRepository<'a> {
     Self { pool }
   }
 
+  /// The user's active profile, if any
+  pub async fn find_active_id(&self, user_id: Uuid) -> Result<Option<Uuid>> {
+    sqlx::query_scalar("SELECT id FROM profiles WHERE user_id = $1 AND is_active LIMIT 1")
+      .bind(user_id)
+      .fetch_optional(self.pool)
+      .await
+      .map_err(SmoothieError::from)
+  }
+
+  /// Clear `is_active` on every profile but the most recently activated one
+  /// of each user, returning the ids cleared
+  pub async fn deactivate_superseded(&self) -> Result<Vec<Uuid>> {
+    sqlx::query_scalar(
+      r#"
+      UPDATE profiles p
+      SET is_active = false
+      WHERE p.is_active AND EXISTS (
+        SELECT 1 FROM profiles o
+        WHERE o.user_id = p.user_id AND o.is_active AND o.id <> p.id
+          AND (COALESCE(o.last_activated_at, '-infinity'), o.id)
+            > (COALESCE(p.last_activated_at, '-infinity'), p.id)
+      )
+      RETURNING p.id
+      "#,
+    )
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// How many of the user's profiles lay out each number of monitors, as
+  /// (monitors, profiles) pairs in ascending monitor count
+  pub async fn count_by_monitor_count(&self, user_id: Uuid) -> Result<Vec<(i64, i64)>> {
+    sqlx::query_as(
+      r#"
+      SELECT monitors, COUNT(*) AS profiles
+      FROM (
+        SELECT (SELECT COUNT(*) FROM monitors m WHERE m.profile_id = p.id) AS monitors
+        FROM profiles p
+        WHERE p.user_id = $1
+      ) counts
+      GROUP BY monitors
+      ORDER BY monitors
+      "#,
+    )
+    .bind(user_id)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
   /// Find all profiles for a user
   #[instrument(skip(self), fields(user_id = %user_id))]
   pub async fn find_by_user_id(&self, user_id: Uuid) -> Result<Vec<ProfileEntity>> {
@@ -26,7 +122,10 @@ impl<'a> ProfileRepository<'a> {
       r#"
             SELECT id, user_id, name, description, type, is_active,
                    created_at, updated_at, last_used, last_activated_at,
-                   activation_count, is_favorite, color, icon, sort_order
+                   activation_count, is_favorite, color, icon, sort_order,
+                   low_power_battery_threshold, local_only,
+                   required_monitor_count, monitor_signature, notes,
+                   include_virtual_displays, is_locked
             FROM profiles
             WHERE user_id = $1
             ORDER BY COALESCE(sort_order, 0), updated_at DESC
@@ -56,7 +155,7 @@ impl<'a> ProfileRepository<'a> {
       }
     }
 
-    result.map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    result.map_err(SmoothieError::from)
   }
 
   /// Find a profile by ID
@@ -69,7 +168,10 @@ impl<'a> ProfileRepository<'a> {
       r#"
             SELECT id, user_id, name, description, type, is_active,
                    created_at, updated_at, last_used, last_activated_at,
-                   activation_count, is_favorite, color, icon, sort_order
+                   activation_count, is_favorite, color, icon, sort_order,
+                   low_power_battery_threshold, local_only,
+                   required_monitor_count, monitor_signature, notes,
+                   include_virtual_displays, is_locked
             FROM profiles
             WHERE id = $1
             "#,
@@ -106,7 +208,7 @@ impl<'a> ProfileRepository<'a> {
       }
     }
 
-    result.map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    result.map_err(SmoothieError::from)
   }
 
   /// Find favorite profiles for a user
@@ -119,7 +221,10 @@ impl<'a> ProfileRepository<'a> {
       r#"
             SELECT id, user_id, name, description, type, is_active,
                    created_at, updated_at, last_used, last_activated_at,
-                   activation_count, is_favorite, color, icon, sort_order
+                   activation_count, is_favorite, color, icon, sort_order,
+                   low_power_battery_threshold, local_only,
+                   required_monitor_count, monitor_signature, notes,
+                   include_virtual_displays, is_locked
             FROM profiles
             WHERE user_id = $1 AND is_favorite = true
             ORDER BY COALESCE(sort_order, 0), updated_at DESC
@@ -149,7 +254,7 @@ impl<'a> ProfileRepository<'a> {
       }
     }
 
-    result.map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    result.map_err(SmoothieError::from)
   }
 
   /// Find most used profiles for a user
@@ -162,7 +267,10 @@ impl<'a> ProfileRepository<'a> {
       r#"
             SELECT id, user_id, name, description, type, is_active,
                    created_at, updated_at, last_used, last_activated_at,
-                   activation_count, is_favorite, color, icon, sort_order
+                   activation_count, is_favorite, color, icon, sort_order,
+                   low_power_battery_threshold, local_only,
+                   required_monitor_count, monitor_signature, notes,
+                   include_virtual_displays, is_locked
             FROM profiles
             WHERE user_id = $1
             ORDER BY COALESCE(activation_count, 0) DESC
@@ -196,7 +304,7 @@ impl<'a> ProfileRepository<'a> {
       }
     }
 
-    result.map_err(|e| SmoothieError::DatabaseError(e.to_string()))
+    result.map_err(SmoothieError::from)
   }
 
   /// Create a new profile
@@ -206,7 +314,7 @@ impl<'a> ProfileRepository<'a> {
     user_id: Uuid,
     name: &str,
     description: Option<&str>,
-    profile_type: &str,
+    profile_type: ProfileType,
   ) -> Result<ProfileEntity> {
     info!("Creating new profile");
     let id = Uuid::new_v4();
@@ -249,7 +357,7 @@ impl<'a> ProfileRepository<'a> {
           duration_ms = duration.as_millis(),
           "Failed to insert profile"
         );
-        return Err(SmoothieError::DatabaseError(e.to_string()));
+        return Err(SmoothieError::from(e));
       }
     }
 
@@ -332,7 +440,7 @@ impl<'a> ProfileRepository<'a> {
           duration_ms = duration.as_millis(),
           "Failed to update profile"
         );
-        return Err(SmoothieError::DatabaseError(e.to_string()));
+        return Err(SmoothieError::from(e));
       }
     }
 
@@ -404,7 +512,7 @@ impl<'a> ProfileRepository<'a> {
     .bind(id)
     .execute(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     self
       .find_by_id(id)
@@ -445,7 +553,7 @@ impl<'a> ProfileRepository<'a> {
           duration_ms = duration.as_millis(),
           "Failed to update profile favorite status"
         );
-        return Err(SmoothieError::DatabaseError(e.to_string()));
+        return Err(SmoothieError::from(e));
       }
     }
 
@@ -481,6 +589,181 @@ impl<'a> ProfileRepository<'a> {
     }
   }
 
+  /// Set or clear the battery percentage below which heavy activation steps are skipped
+  #[instrument(skip(self), fields(profile_id = %id))]
+  pub async fn set_low_power_threshold(
+    &self,
+    id: Uuid,
+    threshold: Option<i32>,
+  ) -> Result<ProfileEntity> {
+    info!("Setting profile low-power threshold");
+
+    let result = sqlx::query(
+      "UPDATE profiles SET low_power_battery_threshold = $1, updated_at = $2 WHERE id = $3",
+    )
+    .bind(threshold)
+    .bind(Utc::now())
+    .bind(id)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    if result.rows_affected() == 0 {
+      return Err(SmoothieError::NotFound("Profile not found".into()));
+    }
+
+    self
+      .find_by_id(id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))
+  }
+
+  /// Replace a profile's notes; `None` clears them
+  pub async fn set_notes(&self, id: Uuid, notes: Option<&str>) -> Result<()> {
+    let result = sqlx::query("UPDATE profiles SET notes = $1, updated_at = $2 WHERE id = $3")
+      .bind(notes)
+      .bind(Utc::now())
+      .bind(id)
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    if result.rows_affected() == 0 {
+      return Err(SmoothieError::NotFound("Profile not found".into()));
+    }
+    Ok(())
+  }
+
+  /// Lock a profile against edits, or unlock it
+  #[instrument(skip(self), fields(profile_id = %id))]
+  pub async fn set_locked(&self, id: Uuid, locked: bool) -> Result<ProfileEntity> {
+    info!("Setting profile lock");
+
+    let result = sqlx::query("UPDATE profiles SET is_locked = $1 WHERE id = $2")
+      .bind(locked)
+      .bind(id)
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    if result.rows_affected() == 0 {
+      return Err(SmoothieError::NotFound("Profile not found".into()));
+    }
+
+    self
+      .find_by_id(id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))
+  }
+
+  /// Name of the profile if it's locked
+  pub async fn find_locked_name(&self, id: Uuid) -> Result<Option<String>> {
+    sqlx::query_scalar("SELECT name FROM profiles WHERE id = $1 AND is_locked")
+      .bind(id)
+      .fetch_optional(self.pool)
+      .await
+      .map_err(SmoothieError::from)
+  }
+
+  /// Name of the profile owning row `id` of `part` if that profile is locked
+  pub async fn find_locked_owner_name(
+    &self,
+    part: ProfilePart,
+    id: Uuid,
+  ) -> Result<Option<String>> {
+    sqlx::query_scalar(&format!(
+      "SELECT p.name FROM profiles p JOIN {} t ON t.profile_id = p.id WHERE t.id = $1 AND p.is_locked",
+      part.table()
+    ))
+    .bind(id)
+    .fetch_optional(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Count Sidecar, AirPlay and virtual displays for a profile, or stop to
+  #[instrument(skip(self), fields(profile_id = %id))]
+  pub async fn set_include_virtual_displays(
+    &self,
+    id: Uuid,
+    include: bool,
+  ) -> Result<ProfileEntity> {
+    info!("Setting profile virtual display flag");
+
+    let result = sqlx::query(
+      "UPDATE profiles SET include_virtual_displays = $1, updated_at = $2 WHERE id = $3",
+    )
+    .bind(include)
+    .bind(Utc::now())
+    .bind(id)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    if result.rows_affected() == 0 {
+      return Err(SmoothieError::NotFound("Profile not found".into()));
+    }
+
+    self
+      .find_by_id(id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))
+  }
+
+  /// Keep a profile out of cloud sync, or let it sync again
+  #[instrument(skip(self), fields(profile_id = %id))]
+  pub async fn set_local_only(&self, id: Uuid, local_only: bool) -> Result<ProfileEntity> {
+    info!("Setting profile local-only flag");
+
+    let result = sqlx::query("UPDATE profiles SET local_only = $1, updated_at = $2 WHERE id = $3")
+      .bind(local_only)
+      .bind(Utc::now())
+      .bind(id)
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    if result.rows_affected() == 0 {
+      return Err(SmoothieError::NotFound("Profile not found".into()));
+    }
+
+    self
+      .find_by_id(id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))
+  }
+
+  /// Bind a profile to a monitor count and/or display signature, or clear the binding
+  #[instrument(skip(self), fields(profile_id = %id))]
+  pub async fn set_monitor_binding(
+    &self,
+    id: Uuid,
+    required_monitor_count: Option<i32>,
+    monitor_signature: Option<&str>,
+  ) -> Result<ProfileEntity> {
+    info!("Setting profile monitor binding");
+
+    let result = sqlx::query(
+      "UPDATE profiles SET required_monitor_count = $1, monitor_signature = $2, updated_at = $3 WHERE id = $4",
+    )
+    .bind(required_monitor_count)
+    .bind(monitor_signature)
+    .bind(Utc::now())
+    .bind(id)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    if result.rows_affected() == 0 {
+      return Err(SmoothieError::NotFound("Profile not found".into()));
+    }
+
+    self
+      .find_by_id(id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))
+  }
+
   /// Delete a profile
   #[instrument(skip(self), fields(profile_id = %id))]
   pub async fn delete(&self, id: Uuid) -> Result<bool> {
@@ -491,7 +774,7 @@ impl<'a> ProfileRepository<'a> {
       .bind(id)
       .execute(self.pool)
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
 
     let duration = start.elapsed();
     let deleted = result.rows_affected() > 0;
@@ -541,7 +824,7 @@ impl<'a> ProfileRepository<'a> {
           error = %e,
           "Failed to deactivate other profiles"
         );
-        return Err(SmoothieError::DatabaseError(e.to_string()));
+        return Err(SmoothieError::from(e));
       }
     }
 
@@ -581,7 +864,7 @@ impl<'a> ProfileRepository<'a> {
           duration_ms = duration.as_millis(),
           "Failed to activate profile"
         );
-        return Err(SmoothieError::DatabaseError(e.to_string()));
+        return Err(SmoothieError::from(e));
       }
     }
 
@@ -623,7 +906,7 @@ impl<'a> ProfileRepository<'a> {
       .bind(profile_id)
       .fetch_all(self.pool)
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
 
     Ok(tags.into_iter().map(|(tag,)| tag).collect())
   }
@@ -633,15 +916,566 @@ impl<'a> ProfileRepository<'a> {
     self.get_tags(profile_id).await
   }
 
-  /// Add a tag to a profile
-  pub async fn add_tag(&self, profile_id: Uuid, tag: &str) -> Result<()> {
-    sqlx::query("INSERT OR IGNORE INTO profile_tags (profile_id, tag) VALUES ($1, $2)")
+  /// Give the listed profiles `sort_order` 0, 1, 2... in list order, in a single
+  /// statement; profiles left out keep their relative order after them.
+  /// Returns `false`, changing nothing, if an id isn't one of the user's profiles.
+  pub async fn reorder(&self, user_id: Uuid, ids: &[Uuid]) -> Result<bool> {
+    let result = sqlx::query(
+      r#"
+      WITH listed AS (
+        SELECT id, position FROM unnest($2::uuid[]) WITH ORDINALITY AS l(id, position)
+      ), ranked AS (
+        SELECT p.id,
+               (ROW_NUMBER() OVER (
+                 ORDER BY l.position NULLS LAST, COALESCE(p.sort_order, 0), p.updated_at DESC
+               ) - 1)::int AS sort_order
+        FROM profiles p
+        LEFT JOIN listed l ON l.id = p.id
+        WHERE p.user_id = $1
+      )
+      UPDATE profiles p
+      SET sort_order = r.sort_order
+      FROM ranked r
+      WHERE p.id = r.id
+        AND (SELECT COUNT(*) FROM profiles WHERE user_id = $1 AND id = ANY($2)) = cardinality($2)
+      "#,
+    )
+    .bind(user_id)
+    .bind(ids)
+    .execute(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    Ok(ids.is_empty() || result.rows_affected() > 0)
+  }
+
+  /// Replace a profile's tags with `tags` in a single statement
+  pub async fn set_tags(&self, profile_id: Uuid, tags: &[String]) -> Result<()> {
+    let (exists,): (bool,) = sqlx::query_as(
+      r#"
+      WITH profile AS (
+        SELECT id FROM profiles WHERE id = $1
+      ), removed AS (
+        DELETE FROM profile_tags
+        WHERE profile_id = (SELECT id FROM profile) AND tag <> ALL($2)
+      ), added AS (
+        INSERT INTO profile_tags (profile_id, tag)
+        SELECT profile.id, tag FROM profile, unnest($2::text[]) AS tag
+        ON CONFLICT DO NOTHING
+      )
+      SELECT EXISTS (SELECT 1 FROM profile)
+      "#,
+    )
+    .bind(profile_id)
+    .bind(tags)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    if !exists {
+      return Err(SmoothieError::NotFound("Profile not found".into()));
+    }
+    Ok(())
+  }
+
+  /// Monitors, apps and browser tabs in one round-trip, each aggregated to JSON
+  /// and ordered as their own repositories order them
+  pub async fn find_children(&self, profile_id: Uuid) -> Result<ProfileChildren> {
+    let (monitors, apps, browser_tabs): ChildrenRow = sqlx::query_as(
+      r#"
+      SELECT
+        (SELECT COALESCE(json_agg(m ORDER BY m.display_index), '[]'::json)
+           FROM (SELECT id, profile_id, name, resolution, orientation, is_primary,
+                        x, y, width, height, display_index, brand, model, refresh_rate,
+                        scale_factor, is_builtin, color_depth, created_at, updated_at,
+                        fingerprint, captured_at, blanked
+                 FROM monitors WHERE profile_id = $1) m),
+        (SELECT COALESCE(json_agg(a ORDER BY COALESCE(a.order_index, 0), a.name), '[]'::json)
+           FROM (SELECT id, profile_id, name, bundle_id, exe_path, launch_on_activate,
+                        monitor_preference, created_at, updated_at, icon_path, launch_args,
+                        working_directory, startup_delay_ms, order_index
+                 FROM apps WHERE profile_id = $1) a),
+        (SELECT COALESCE(json_agg(t ORDER BY t.tab_order), '[]'::json)
+           FROM (SELECT id, profile_id, url, browser, monitor_id, tab_order, favicon,
+                        created_at, updated_at
+                 FROM browser_tabs WHERE profile_id = $1) t)
+      "#,
+    )
+    .bind(profile_id)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    Ok(ProfileChildren {
+      monitors: monitors.0,
+      apps: apps.0,
+      browser_tabs: browser_tabs.0,
+    })
+  }
+
+  /// A profile's deactivation actions in execution order
+  pub async fn find_deactivation_actions(
+    &self,
+    profile_id: Uuid,
+  ) -> Result<Vec<ProfileDeactivationActionEntity>> {
+    sqlx::query_as::<_, ProfileDeactivationActionEntity>(
+      "SELECT * FROM profile_deactivation_actions WHERE profile_id = $1 ORDER BY position",
+    )
+    .bind(profile_id)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Replace a profile's deactivation actions; positions follow the slice order
+  pub async fn replace_deactivation_actions(
+    &self,
+    profile_id: Uuid,
+    actions: &[(DeactivationActionType, serde_json::Value)],
+  ) -> Result<Vec<ProfileDeactivationActionEntity>> {
+    let mut tx = self.pool.begin().await.map_err(SmoothieError::from)?;
+
+    sqlx::query("DELETE FROM profile_deactivation_actions WHERE profile_id = $1")
       .bind(profile_id)
-      .bind(tag)
-      .execute(self.pool)
+      .execute(&mut *tx)
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
 
-    Ok(())
+    let mut inserted = Vec::with_capacity(actions.len());
+    for (position, (action_type, config)) in actions.iter().enumerate() {
+      let entity = sqlx::query_as::<_, ProfileDeactivationActionEntity>(
+        r#"
+        INSERT INTO profile_deactivation_actions (profile_id, position, action_type, config)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+      )
+      .bind(profile_id)
+      .bind(position as i32)
+      .bind(action_type)
+      .bind(config)
+      .fetch_one(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+      inserted.push(entity);
+    }
+
+    tx.commit().await.map_err(SmoothieError::from)?;
+
+    Ok(inserted)
+  }
+
+  /// Apps the profile keeps closed, by name
+  pub async fn find_blocked_apps(&self, profile_id: Uuid) -> Result<Vec<ProfileBlockedAppEntity>> {
+    sqlx::query_as::<_, ProfileBlockedAppEntity>(
+      "SELECT * FROM profile_blocked_apps WHERE profile_id = $1 ORDER BY app_name, bundle_id",
+    )
+    .bind(profile_id)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Replace a profile's blocked apps
+  pub async fn replace_blocked_apps(
+    &self,
+    profile_id: Uuid,
+    apps: &[(String, String, BlockedAppAction)],
+  ) -> Result<Vec<ProfileBlockedAppEntity>> {
+    let mut tx = self.pool.begin().await.map_err(SmoothieError::from)?;
+
+    sqlx::query("DELETE FROM profile_blocked_apps WHERE profile_id = $1")
+      .bind(profile_id)
+      .execute(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    let mut inserted = Vec::with_capacity(apps.len());
+    for (bundle_id, app_name, action) in apps {
+      let entity = sqlx::query_as::<_, ProfileBlockedAppEntity>(
+        r#"
+        INSERT INTO profile_blocked_apps (profile_id, bundle_id, app_name, action)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+      )
+      .bind(profile_id)
+      .bind(bundle_id)
+      .bind(app_name)
+      .bind(action)
+      .fetch_one(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+      inserted.push(entity);
+    }
+
+    tx.commit().await.map_err(SmoothieError::from)?;
+
+    Ok(inserted)
+  }
+
+  /// The profile's checklist, in order
+  pub async fn find_checklist_items(
+    &self,
+    profile_id: Uuid,
+  ) -> Result<Vec<ProfileChecklistItemEntity>> {
+    sqlx::query_as::<_, ProfileChecklistItemEntity>(
+      "SELECT * FROM profile_checklist_items WHERE profile_id = $1 ORDER BY position",
+    )
+    .bind(profile_id)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Replace a profile's checklist. Items passed with an id keep it, so
+  /// completions logged against them still apply.
+  pub async fn replace_checklist_items(
+    &self,
+    profile_id: Uuid,
+    items: &[(Option<Uuid>, String)],
+  ) -> Result<Vec<ProfileChecklistItemEntity>> {
+    let mut tx = self.pool.begin().await.map_err(SmoothieError::from)?;
+
+    let kept: Vec<Uuid> = items.iter().filter_map(|(id, _)| *id).collect();
+    sqlx::query("DELETE FROM profile_checklist_items WHERE profile_id = $1 AND id <> ALL($2)")
+      .bind(profile_id)
+      .bind(&kept)
+      .execute(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    let mut saved = Vec::with_capacity(items.len());
+    for (position, (id, label)) in items.iter().enumerate() {
+      let entity = sqlx::query_as::<_, ProfileChecklistItemEntity>(
+        r#"
+        INSERT INTO profile_checklist_items (id, profile_id, position, label)
+        VALUES (COALESCE($1, uuid_generate_v4()), $2, $3, $4)
+        ON CONFLICT (id) DO UPDATE
+          SET position = EXCLUDED.position, label = EXCLUDED.label
+          WHERE profile_checklist_items.profile_id = EXCLUDED.profile_id
+        RETURNING *
+        "#,
+      )
+      .bind(id)
+      .bind(profile_id)
+      .bind(position as i32)
+      .bind(label)
+      .fetch_one(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+      saved.push(entity);
+    }
+
+    tx.commit().await.map_err(SmoothieError::from)?;
+
+    Ok(saved)
+  }
+}
+
+#[async_trait]
+impl ProfileStore for ProfileRepository<'_> {
+  async fn find_by_user_id(&self, user_id: Uuid) -> Result<Vec<ProfileEntity>> {
+    ProfileRepository::find_by_user_id(self, user_id).await
+  }
+
+  async fn find_by_id(&self, id: Uuid) -> Result<Option<ProfileEntity>> {
+    ProfileRepository::find_by_id(self, id).await
+  }
+
+  async fn find_favorites(&self, user_id: Uuid) -> Result<Vec<ProfileEntity>> {
+    ProfileRepository::find_favorites(self, user_id).await
+  }
+
+  async fn find_most_used(&self, user_id: Uuid, limit: i64) -> Result<Vec<ProfileEntity>> {
+    ProfileRepository::find_most_used(self, user_id, limit).await
+  }
+
+  async fn create(
+    &self,
+    user_id: Uuid,
+    name: &str,
+    description: Option<&str>,
+    profile_type: ProfileType,
+  ) -> Result<ProfileEntity> {
+    ProfileRepository::create(self, user_id, name, description, profile_type).await
+  }
+
+  async fn set_favorite(&self, id: Uuid, is_favorite: bool) -> Result<ProfileEntity> {
+    ProfileRepository::set_favorite(self, id, is_favorite).await
+  }
+
+  async fn activate(&self, id: Uuid, user_id: Uuid) -> Result<ProfileEntity> {
+    ProfileRepository::activate(self, id, user_id).await
+  }
+
+  async fn find_tags(&self, profile_id: Uuid) -> Result<Vec<String>> {
+    ProfileRepository::find_tags(self, profile_id).await
+  }
+
+  async fn set_tags(&self, profile_id: Uuid, tags: &[String]) -> Result<()> {
+    ProfileRepository::set_tags(self, profile_id, tags).await
+  }
+
+  async fn set_notes(&self, id: Uuid, notes: Option<&str>) -> Result<()> {
+    ProfileRepository::set_notes(self, id, notes).await
+  }
+
+  async fn count_children(&self, profile_id: Uuid) -> Result<ProfileChildCounts> {
+    Ok(ProfileChildCounts {
+      monitors: MonitorRepository::new(self.pool)
+        .count_by_profile_id(profile_id)
+        .await?,
+      apps: AppRepository::new(self.pool)
+        .count_by_profile_id(profile_id)
+        .await?,
+      browser_tabs: BrowserTabRepository::new(self.pool)
+        .count_by_profile_id(profile_id)
+        .await?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+
+  #[tokio::test]
+  async fn test_create_and_find_profile() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    let repo = ProfileRepository::new(test_db.pool());
+
+    let created = repo
+      .create(user_id, "Focus", Some("Deep work"), ProfileType::Work)
+      .await
+      .unwrap();
+    repo
+      .set_tags(created.id, &["coding".to_string(), "coding".to_string()])
+      .await
+      .unwrap();
+
+    let found = repo.find_by_id(created.id).await.unwrap().unwrap();
+    assert_eq!(found.name, "Focus");
+    assert_eq!(found.profile_type, ProfileType::Work);
+    assert_eq!(repo.find_tags(created.id).await.unwrap(), vec!["coding"]);
+    assert_eq!(repo.find_by_user_id(user_id).await.unwrap().len(), 1);
+  }
+
+  #[tokio::test]
+  async fn test_activate_deactivates_other_profiles() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    let repo = ProfileRepository::new(test_db.pool());
+
+    let first = repo
+      .create(user_id, "First", None, ProfileType::Work)
+      .await
+      .unwrap();
+    let second = repo
+      .create(user_id, "Second", None, ProfileType::Work)
+      .await
+      .unwrap();
+
+    repo.activate(first.id, user_id).await.unwrap();
+    let activated = repo.activate(second.id, user_id).await.unwrap();
+    repo.activate(second.id, user_id).await.unwrap();
+
+    assert!(activated.is_active);
+    assert!(activated.last_activated_at.is_some());
+    assert!(!repo.find_by_id(first.id).await.unwrap().unwrap().is_active);
+
+    let most_used = repo.find_most_used(user_id, 1).await.unwrap();
+    assert_eq!(most_used[0].id, second.id);
+    assert_eq!(most_used[0].activation_count, Some(2));
+  }
+
+  #[tokio::test]
+  async fn test_find_children_in_one_query() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    let repo = ProfileRepository::new(test_db.pool());
+    let profile = repo
+      .create(user_id, "Desk", None, ProfileType::Work)
+      .await
+      .unwrap();
+
+    let empty = repo.find_children(profile.id).await.unwrap();
+    assert!(empty.monitors.is_empty() && empty.apps.is_empty() && empty.browser_tabs.is_empty());
+
+    let monitors = MonitorRepository::new(test_db.pool());
+    for index in [1, 0] {
+      monitors
+        .create(
+          profile.id,
+          &format!("Display {}", index),
+          "2560x1440",
+          "landscape",
+          index == 0,
+          index * 2560,
+          0,
+          2560,
+          1440,
+          index,
+        )
+        .await
+        .unwrap();
+    }
+    AppRepository::new(test_db.pool())
+      .create(
+        profile.id,
+        "Slack",
+        "com.tinyspeck.slackmacgap",
+        None,
+        true,
+        None,
+        None,
+        None,
+      )
+      .await
+      .unwrap();
+    let tabs = BrowserTabRepository::new(test_db.pool());
+    tabs
+      .create(profile.id, "https://b.example", "chrome", None, 1, None)
+      .await
+      .unwrap();
+    tabs
+      .create(profile.id, "https://a.example", "chrome", None, 0, None)
+      .await
+      .unwrap();
+
+    let children = repo.find_children(profile.id).await.unwrap();
+    assert_eq!(children.monitors.len(), 2);
+    assert_eq!(children.monitors[0].name, "Display 0");
+    assert!(children.monitors[0].created_at.is_some());
+    assert_eq!(children.apps[0].bundle_id, "com.tinyspeck.slackmacgap");
+    assert_eq!(
+      children
+        .browser_tabs
+        .iter()
+        .map(|t| t.url.as_str())
+        .collect::<Vec<_>>(),
+      vec!["https://a.example", "https://b.example"]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_favorites_update_and_delete() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    let repo = ProfileRepository::new(test_db.pool());
+
+    let profile = repo
+      .create(user_id, "Focus", None, ProfileType::Work)
+      .await
+      .unwrap();
+    repo
+      .create(user_id, "Other", None, ProfileType::Work)
+      .await
+      .unwrap();
+
+    repo.set_favorite(profile.id, true).await.unwrap();
+    let favorites = repo.find_favorites(user_id).await.unwrap();
+    assert_eq!(favorites.len(), 1);
+    assert_eq!(favorites[0].id, profile.id);
+
+    let updated = repo
+      .update_extended(
+        profile.id,
+        Some("Deep Focus"),
+        None,
+        None,
+        Some("#22C55E"),
+        Some("brain"),
+        Some(3),
+      )
+      .await
+      .unwrap();
+    assert_eq!(updated.name, "Deep Focus");
+    assert_eq!(updated.color.as_deref(), Some("#22C55E"));
+    assert_eq!(updated.sort_order, Some(3));
+
+    assert!(repo.delete(profile.id).await.unwrap());
+    assert!(!repo.delete(profile.id).await.unwrap());
+    assert!(repo.find_by_id(profile.id).await.unwrap().is_none());
+  }
+
+  #[tokio::test]
+  async fn test_set_tags_replaces_and_rejects_unknown_profiles() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    let repo = ProfileRepository::new(test_db.pool());
+    let profile = repo
+      .create(user_id, "Focus", None, ProfileType::Work)
+      .await
+      .unwrap();
+    let tags = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+    repo
+      .set_tags(profile.id, &tags(&["coding", "music"]))
+      .await
+      .unwrap();
+    repo
+      .set_tags(profile.id, &tags(&["music", "focus", "focus"]))
+      .await
+      .unwrap();
+    let mut found = repo.find_tags(profile.id).await.unwrap();
+    found.sort();
+    assert_eq!(found, vec!["focus", "music"]);
+
+    repo.set_tags(profile.id, &[]).await.unwrap();
+    assert!(repo.find_tags(profile.id).await.unwrap().is_empty());
+
+    let missing = Uuid::new_v4();
+    assert!(matches!(
+      repo.set_tags(missing, &tags(&["coding"])).await,
+      Err(SmoothieError::NotFound(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn test_reorder_puts_listed_profiles_first() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    let other_user = test_db.seed_user().await;
+    let repo = ProfileRepository::new(test_db.pool());
+    let mut ids = Vec::new();
+    for name in ["Work", "Gaming", "Focus"] {
+      ids.push(
+        repo
+          .create(user_id, name, None, ProfileType::Work)
+          .await
+          .unwrap()
+          .id,
+      );
+    }
+    let names = || async {
+      repo
+        .find_by_user_id(user_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| (p.name, p.sort_order))
+        .collect::<Vec<_>>()
+    };
+
+    assert!(repo.reorder(user_id, &[ids[2], ids[0]]).await.unwrap());
+    assert_eq!(
+      names().await,
+      [
+        ("Focus".to_string(), Some(0)),
+        ("Work".to_string(), Some(1)),
+        ("Gaming".to_string(), Some(2)),
+      ]
+    );
+
+    // Someone else's profile in the list rejects the whole reorder
+    let foreign = repo
+      .create(other_user, "Theirs", None, ProfileType::Work)
+      .await
+      .unwrap();
+    assert!(!repo.reorder(user_id, &[ids[1], foreign.id]).await.unwrap());
+    assert_eq!(names().await[0].0, "Focus");
   }
 }
@@ -0,0 +1,357 @@
+// Profile snapshot repository - a profile and everything set up under it, as
+// the rows stored, so a snapshot restores it exactly
+
+use crate::error::{Result, SmoothieError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+/// A table holding part of a profile's setup
+struct PartTable {
+  name: &'static str,
+  /// Selects the profile's rows, given its id as `$1`
+  owned_by: &'static str,
+  /// Rows are matched by id and updated in place, so rows referencing them
+  /// (windows, execution history) survive; other tables are rewritten
+  keyed: bool,
+  /// Columns recording use rather than setup, left as they are
+  usage: &'static [&'static str],
+}
+
+/// Parents before children, the order rows are written in
+const PART_TABLES: &[PartTable] = &[
+  PartTable {
+    name: "profile_tags",
+    owned_by: "profile_id = $1",
+    keyed: false,
+    usage: &[],
+  },
+  PartTable {
+    name: "monitors",
+    owned_by: "profile_id = $1",
+    keyed: true,
+    usage: &[],
+  },
+  PartTable {
+    name: "apps",
+    owned_by: "profile_id = $1",
+    keyed: true,
+    usage: &[],
+  },
+  PartTable {
+    name: "windows",
+    owned_by: "profile_id = $1",
+    keyed: true,
+    usage: &[],
+  },
+  PartTable {
+    name: "browser_tabs",
+    owned_by: "profile_id = $1",
+    keyed: true,
+    usage: &[],
+  },
+  PartTable {
+    name: "profile_files",
+    owned_by: "profile_id = $1",
+    keyed: true,
+    usage: &[],
+  },
+  PartTable {
+    name: "automation_rules",
+    owned_by: "profile_id = $1",
+    keyed: true,
+    usage: &["trigger_count", "last_triggered_at"],
+  },
+  PartTable {
+    name: "rule_actions",
+    owned_by: "rule_id IN (SELECT id FROM automation_rules WHERE profile_id = $1)",
+    keyed: false,
+    usage: &[],
+  },
+  PartTable {
+    name: "profile_deactivation_actions",
+    owned_by: "profile_id = $1",
+    keyed: false,
+    usage: &[],
+  },
+  PartTable {
+    name: "profile_blocked_apps",
+    owned_by: "profile_id = $1",
+    keyed: false,
+    usage: &[],
+  },
+  PartTable {
+    name: "profile_checklist_items",
+    owned_by: "profile_id = $1",
+    keyed: true,
+    usage: &[],
+  },
+];
+
+/// Profile columns recording use rather than setup, left as they are
+const PROFILE_USAGE: &[&str] = &[
+  "is_active",
+  "last_used",
+  "last_activated_at",
+  "activation_count",
+];
+
+/// A profile's row and the rows of its parts, each as the JSON of the stored row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileRows {
+  pub profile: Value,
+  /// By table name
+  pub parts: BTreeMap<String, Vec<Value>>,
+}
+
+impl ProfileRows {
+  /// The profile's id, as the rows have it
+  pub fn profile_id(&self) -> Option<Uuid> {
+    row_id(&self.profile)
+  }
+
+  /// The same rows under another profile id, for a copy already known here
+  /// by a different id
+  pub fn with_profile_id(mut self, profile_id: Uuid) -> Self {
+    let old = self.profile.get("id").cloned();
+    let new = Value::String(profile_id.to_string());
+    self.profile["id"] = new.clone();
+    for row in self.parts.values_mut().flatten() {
+      if row.get("profile_id").is_some() && row.get("profile_id") == old.as_ref() {
+        row["profile_id"] = new.clone();
+      }
+    }
+    self
+  }
+
+  /// The rows without what differs from one machine to the next - the owner,
+  /// usage columns and `updated_at`, which starting a profile touches - and
+  /// in a stable order, to compare copies by
+  pub fn setup(&self) -> Value {
+    let strip = |row: &Value, usage: &[&str]| {
+      let mut row = row.clone();
+      if let Some(fields) = row.as_object_mut() {
+        fields.remove("user_id");
+        fields.remove("updated_at");
+        fields.retain(|column, _| !usage.contains(&column.as_str()));
+      }
+      row
+    };
+    let parts: serde_json::Map<String, Value> = PART_TABLES
+      .iter()
+      .map(|table| {
+        let mut rows: Vec<Value> = saved_rows(self, table)
+          .iter()
+          .map(|row| strip(row, table.usage))
+          .collect();
+        rows.sort_by_cached_key(|row| row.to_string());
+        (table.name.to_string(), Value::Array(rows))
+      })
+      .collect();
+    serde_json::json!({
+      "profile": strip(&self.profile, PROFILE_USAGE),
+      "parts": parts,
+    })
+  }
+
+  /// When the profile or any of its rows last changed
+  pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+    std::iter::once(&self.profile)
+      .chain(self.parts.values().flatten())
+      .filter_map(|row| row.get("updated_at")?.as_str())
+      .filter_map(|at| DateTime::parse_from_rfc3339(at).ok())
+      .map(|at| at.with_timezone(&Utc))
+      .max()
+  }
+}
+
+pub struct ProfileSnapshotRepository<'a> {
+  pool: &'a PgPool,
+}
+
+impl<'a> ProfileSnapshotRepository<'a> {
+  pub fn new(pool: &'a PgPool) -> Self {
+    Self { pool }
+  }
+
+  /// Every row of the profile, read in one transaction so the parts agree
+  pub async fn export(&self, profile_id: Uuid) -> Result<ProfileRows> {
+    let mut tx = self.pool.begin().await.map_err(SmoothieError::from)?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ")
+      .execute(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+
+    let profile: Option<(Value,)> =
+      sqlx::query_as("SELECT to_jsonb(p) FROM profiles p WHERE id = $1")
+        .bind(profile_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(SmoothieError::from)?;
+    let (profile,) = profile.ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?;
+
+    let mut parts = BTreeMap::new();
+    for table in PART_TABLES {
+      let rows: Vec<(Value,)> = sqlx::query_as(&format!(
+        "SELECT to_jsonb(t) FROM {} t WHERE {}",
+        table.name, table.owned_by
+      ))
+      .bind(profile_id)
+      .fetch_all(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+      parts.insert(
+        table.name.to_string(),
+        rows.into_iter().map(|(row,)| row).collect(),
+      );
+    }
+
+    tx.commit().await.map_err(SmoothieError::from)?;
+    Ok(ProfileRows { profile, parts })
+  }
+
+  /// Put a profile of `user_id` back the way `rows` has it, in one transaction.
+  /// Rows added since are removed, and the profile is recreated if it was
+  /// deleted; returns whether it was. Fails if the id belongs to another user.
+  pub async fn restore(&self, user_id: Uuid, rows: &ProfileRows) -> Result<bool> {
+    let profile_id = rows
+      .profile
+      .get("id")
+      .and_then(Value::as_str)
+      .and_then(|id| Uuid::parse_str(id).ok())
+      .ok_or_else(|| SmoothieError::ValidationError("Snapshot profile has no id".into()))?;
+    let mut profile = rows.profile.clone();
+    profile["user_id"] = Value::String(user_id.to_string());
+
+    let mut tx = self.pool.begin().await.map_err(SmoothieError::from)?;
+    let columns = table_columns(&mut tx).await?;
+
+    let profile_columns = restored_columns(&columns, "profiles", &profile, PROFILE_USAGE);
+    let recreated: Option<(bool,)> = sqlx::query_as(&format!(
+      r#"
+      INSERT INTO profiles ({cols})
+      SELECT {cols} FROM jsonb_populate_record(NULL::profiles, $1)
+      ON CONFLICT (id) DO UPDATE SET {updates}
+        WHERE profiles.user_id = EXCLUDED.user_id
+      RETURNING (xmax = 0)
+      "#,
+      cols = profile_columns.join(", "),
+      updates = updates(&profile_columns),
+    ))
+    .bind(&profile)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(SmoothieError::from)?;
+    let (recreated,) =
+      recreated.ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?;
+
+    // Children go first, so a removed monitor or app takes its windows with it
+    // before the saved ones are written back
+    for table in PART_TABLES.iter().rev() {
+      let saved = saved_rows(rows, table);
+      let query = match table.keyed {
+        true => format!(
+          "DELETE FROM {} WHERE {} AND id <> ALL($2)",
+          table.name, table.owned_by
+        ),
+        false => format!("DELETE FROM {} WHERE {}", table.name, table.owned_by),
+      };
+      let kept: Vec<Uuid> = saved.iter().filter_map(row_id).collect();
+      let query = sqlx::query(&query).bind(profile_id);
+      let query = match table.keyed {
+        true => query.bind(&kept),
+        false => query,
+      };
+      query.execute(&mut *tx).await.map_err(SmoothieError::from)?;
+    }
+
+    for table in PART_TABLES {
+      let saved = saved_rows(rows, table);
+      let Some(first) = saved.first() else {
+        continue;
+      };
+      let cols = restored_columns(&columns, table.name, first, table.usage);
+      let conflict = match table.keyed {
+        true => format!("ON CONFLICT (id) DO UPDATE SET {}", updates(&cols)),
+        false => String::new(),
+      };
+      sqlx::query(&format!(
+        "INSERT INTO {table} ({cols}) SELECT {cols} FROM jsonb_populate_recordset(NULL::{table}, $1) {conflict}",
+        table = table.name,
+        cols = cols.join(", "),
+        conflict = conflict,
+      ))
+      .bind(Value::Array(saved.to_vec()))
+      .execute(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+    }
+
+    tx.commit().await.map_err(SmoothieError::from)?;
+    Ok(recreated)
+  }
+}
+
+/// Columns of the tables a snapshot touches, as the schema has them now
+async fn table_columns(conn: &mut PgConnection) -> Result<HashMap<String, Vec<String>>> {
+  let tables: Vec<&str> = std::iter::once("profiles")
+    .chain(PART_TABLES.iter().map(|t| t.name))
+    .collect();
+  let rows: Vec<(String, String)> = sqlx::query_as(
+    r#"
+    SELECT table_name::text, column_name::text FROM information_schema.columns
+    WHERE table_schema = current_schema() AND table_name = ANY($1)
+    ORDER BY ordinal_position
+    "#,
+  )
+  .bind(&tables)
+  .fetch_all(conn)
+  .await
+  .map_err(SmoothieError::from)?;
+
+  let mut columns: HashMap<String, Vec<String>> = HashMap::new();
+  for (table, column) in rows {
+    columns.entry(table).or_default().push(column);
+  }
+  Ok(columns)
+}
+
+/// The columns to write for `table`: those the saved row has, so columns added
+/// since the snapshot keep their defaults, less the ones left as they are
+fn restored_columns(
+  columns: &HashMap<String, Vec<String>>,
+  table: &str,
+  row: &Value,
+  usage: &[&str],
+) -> Vec<String> {
+  columns
+    .get(table)
+    .into_iter()
+    .flatten()
+    .filter(|c| row.get(c.as_str()).is_some() && !usage.contains(&c.as_str()))
+    .map(|c| format!("\"{}\"", c))
+    .collect()
+}
+
+fn updates(cols: &[String]) -> String {
+  cols
+    .iter()
+    .filter(|c| c.as_str() != "\"id\"")
+    .map(|c| format!("{} = EXCLUDED.{}", c, c))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+fn saved_rows<'r>(rows: &'r ProfileRows, table: &PartTable) -> &'r [Value] {
+  rows.parts.get(table.name).map_or(&[], Vec::as_slice)
+}
+
+fn row_id(row: &Value) -> Option<Uuid> {
+  row
+    .get("id")
+    .and_then(Value::as_str)
+    .and_then(|id| Uuid::parse_str(id).ok())
+}
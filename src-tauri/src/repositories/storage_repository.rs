@@ -0,0 +1,42 @@
+// Storage repository - which stored blobs the database still refers to
+
+use crate::error::{Result, SmoothieError};
+use crate::storage::BLOB_REF_PREFIX;
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+pub struct StorageRepository<'a> {
+  pool: &'a PgPool,
+}
+
+impl<'a> StorageRepository<'a> {
+  pub fn new(pool: &'a PgPool) -> Self {
+    Self { pool }
+  }
+
+  /// Every blob reference held by app icons, profile icons, tab favicons and
+  /// feedback attachments
+  pub async fn find_blob_references(&self) -> Result<HashSet<String>> {
+    let references: Vec<String> = sqlx::query_scalar(
+      r#"
+            SELECT icon_path FROM apps WHERE icon_path LIKE $1
+            UNION
+            SELECT icon FROM profiles WHERE icon LIKE $1
+            UNION
+            SELECT favicon FROM browser_tabs WHERE favicon LIKE $1
+            UNION
+            SELECT attachment
+            FROM feedback, jsonb_array_elements_text(
+              CASE WHEN jsonb_typeof(metadata->'attachments') = 'array'
+                   THEN metadata->'attachments' ELSE '[]'::jsonb END
+            ) AS attachment
+            "#,
+    )
+    .bind(format!("{}%", BLOB_REF_PREFIX))
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)?;
+
+    Ok(references.into_iter().collect())
+  }
+}
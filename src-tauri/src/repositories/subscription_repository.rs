@@ -67,7 +67,7 @@ impl<'a> SubscriptionRepository<'a> {
 
     result.map_err(|e| {
       error!("Database error finding subscription: {}", e);
-      SmoothieError::DatabaseError(e.to_string())
+      SmoothieError::from(e)
     })
   }
 
@@ -140,7 +140,7 @@ impl<'a> SubscriptionRepository<'a> {
 
     result.map_err(|e| {
       error!("Database error upserting subscription: {}", e);
-      SmoothieError::DatabaseError(e.to_string())
+      SmoothieError::from(e)
     })
   }
 
@@ -177,7 +177,7 @@ impl<'a> SubscriptionRepository<'a> {
 
     result.map(|_| ()).map_err(|e| {
       error!("Database error deleting subscription: {}", e);
-      SmoothieError::DatabaseError(e.to_string())
+      SmoothieError::from(e)
     })
   }
 }
@@ -0,0 +1,276 @@
+//! Team repository - local mirror of a user's teams and their profile libraries
+
+use crate::error::{Result, SmoothieError};
+use crate::models::entities::{
+  OrganizationEntity, TeamEntity, TeamLibraryProfileEntity, TeamMemberEntity, TeamMembershipRow,
+};
+use crate::models::TeamRole;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Everything a user can see of their teams, as fetched from Supabase
+#[derive(Debug, Default)]
+pub struct TeamSnapshot {
+  pub organizations: Vec<OrganizationEntity>,
+  pub teams: Vec<TeamEntity>,
+  /// The user's own memberships
+  pub members: Vec<TeamMemberEntity>,
+  pub library: Vec<TeamLibraryProfileEntity>,
+}
+
+pub struct TeamRepository<'a> {
+  pool: &'a PgPool,
+}
+
+impl<'a> TeamRepository<'a> {
+  pub fn new(pool: &'a PgPool) -> Self {
+    Self { pool }
+  }
+
+  /// Replace the user's memberships and the libraries of their teams with
+  /// `snapshot`. Library entries of teams nobody here belongs to any more go too.
+  pub async fn replace_for_user(&self, user_id: Uuid, snapshot: &TeamSnapshot) -> Result<()> {
+    let mut tx = self.pool.begin().await.map_err(SmoothieError::from)?;
+
+    for org in &snapshot.organizations {
+      sqlx::query(
+        r#"
+        INSERT INTO organizations (id, name, created_at) VALUES ($1, $2, $3)
+        ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name
+        "#,
+      )
+      .bind(org.id)
+      .bind(&org.name)
+      .bind(org.created_at)
+      .execute(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+    }
+    for team in &snapshot.teams {
+      sqlx::query(
+        r#"
+        INSERT INTO teams (id, organization_id, name, created_at) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (id) DO UPDATE
+          SET organization_id = EXCLUDED.organization_id, name = EXCLUDED.name
+        "#,
+      )
+      .bind(team.id)
+      .bind(team.organization_id)
+      .bind(&team.name)
+      .bind(team.created_at)
+      .execute(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+    }
+
+    sqlx::query("DELETE FROM team_members WHERE user_id = $1")
+      .bind(user_id)
+      .execute(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+    for member in snapshot.members.iter().filter(|m| m.user_id == user_id) {
+      sqlx::query(
+        "INSERT INTO team_members (team_id, user_id, role, joined_at) VALUES ($1, $2, $3, $4)",
+      )
+      .bind(member.team_id)
+      .bind(member.user_id)
+      .bind(member.role)
+      .bind(member.joined_at)
+      .execute(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+    }
+
+    let team_ids: Vec<Uuid> = snapshot.teams.iter().map(|t| t.id).collect();
+    sqlx::query(
+      r#"
+      DELETE FROM team_library_profiles
+      WHERE team_id = ANY($1)
+         OR team_id NOT IN (SELECT team_id FROM team_members)
+      "#,
+    )
+    .bind(&team_ids)
+    .execute(&mut *tx)
+    .await
+    .map_err(SmoothieError::from)?;
+    for entry in &snapshot.library {
+      Self::insert_library_profile_in(&mut tx, entry).await?;
+    }
+
+    tx.commit().await.map_err(SmoothieError::from)?;
+    Ok(())
+  }
+
+  /// Teams the user belongs to, with their role in each
+  pub async fn find_teams(&self, user_id: Uuid) -> Result<Vec<TeamMembershipRow>> {
+    sqlx::query_as::<_, TeamMembershipRow>(
+      r#"
+      SELECT t.id AS team_id, t.name AS team_name,
+             o.id AS organization_id, o.name AS organization_name, m.role
+      FROM team_members m
+      JOIN teams t ON t.id = m.team_id
+      JOIN organizations o ON o.id = t.organization_id
+      WHERE m.user_id = $1
+      ORDER BY o.name, t.name
+      "#,
+    )
+    .bind(user_id)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// The user's role in a team, `None` if they aren't a member
+  pub async fn find_role(&self, team_id: Uuid, user_id: Uuid) -> Result<Option<TeamRole>> {
+    sqlx::query_scalar("SELECT role FROM team_members WHERE team_id = $1 AND user_id = $2")
+      .bind(team_id)
+      .bind(user_id)
+      .fetch_optional(self.pool)
+      .await
+      .map_err(SmoothieError::from)
+  }
+
+  /// A team's library, newest first
+  pub async fn find_library(&self, team_id: Uuid) -> Result<Vec<TeamLibraryProfileEntity>> {
+    sqlx::query_as::<_, TeamLibraryProfileEntity>(
+      r#"
+      SELECT id, team_id, name, published_by, published_at, document
+      FROM team_library_profiles
+      WHERE team_id = $1
+      ORDER BY published_at DESC
+      "#,
+    )
+    .bind(team_id)
+    .fetch_all(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  pub async fn find_library_profile(&self, id: Uuid) -> Result<Option<TeamLibraryProfileEntity>> {
+    sqlx::query_as::<_, TeamLibraryProfileEntity>(
+      r#"
+      SELECT id, team_id, name, published_by, published_at, document
+      FROM team_library_profiles
+      WHERE id = $1
+      "#,
+    )
+    .bind(id)
+    .fetch_optional(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  pub async fn insert_library_profile(&self, entry: &TeamLibraryProfileEntity) -> Result<()> {
+    let mut tx = self.pool.begin().await.map_err(SmoothieError::from)?;
+    Self::insert_library_profile_in(&mut tx, entry).await?;
+    tx.commit().await.map_err(SmoothieError::from)
+  }
+
+  async fn insert_library_profile_in(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    entry: &TeamLibraryProfileEntity,
+  ) -> Result<()> {
+    sqlx::query(
+      r#"
+      INSERT INTO team_library_profiles (id, team_id, name, published_by, published_at, document)
+      VALUES ($1, $2, $3, $4, $5, $6)
+      ON CONFLICT (id) DO UPDATE
+        SET name = EXCLUDED.name, document = EXCLUDED.document, synced_at = NOW()
+      "#,
+    )
+    .bind(entry.id)
+    .bind(entry.team_id)
+    .bind(&entry.name)
+    .bind(entry.published_by)
+    .bind(entry.published_at)
+    .bind(&entry.document)
+    .execute(&mut **tx)
+    .await
+    .map_err(SmoothieError::from)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use chrono::Utc;
+
+  #[tokio::test]
+  async fn test_replace_for_user_mirrors_memberships_and_libraries() {
+    let test_db = test_db_or_skip!();
+    let pool = test_db.pool();
+    let user_id = test_db.seed_user().await;
+
+    let now = Utc::now();
+    let org = OrganizationEntity {
+      id: Uuid::new_v4(),
+      name: "Acme".into(),
+      created_at: now,
+    };
+    let team = |name: &str| TeamEntity {
+      id: Uuid::new_v4(),
+      organization_id: org.id,
+      name: name.into(),
+      created_at: now,
+    };
+    let (design, sales) = (team("Design"), team("Sales"));
+    let entry = |team_id| TeamLibraryProfileEntity {
+      id: Uuid::new_v4(),
+      team_id,
+      name: "Onboarding".into(),
+      published_by: Uuid::new_v4(),
+      published_at: now,
+      document: serde_json::json!({ "format": 1 }),
+    };
+    let repo = TeamRepository::new(pool);
+
+    let snapshot = TeamSnapshot {
+      organizations: vec![org.clone()],
+      teams: vec![design.clone(), sales.clone()],
+      members: vec![
+        TeamMemberEntity {
+          team_id: design.id,
+          user_id,
+          role: TeamRole::Admin,
+          joined_at: now,
+        },
+        TeamMemberEntity {
+          team_id: sales.id,
+          user_id,
+          role: TeamRole::Member,
+          joined_at: now,
+        },
+      ],
+      library: vec![entry(design.id), entry(sales.id)],
+    };
+    repo.replace_for_user(user_id, &snapshot).await.unwrap();
+
+    let teams = repo.find_teams(user_id).await.unwrap();
+    assert_eq!(
+      teams
+        .iter()
+        .map(|t| t.team_name.as_str())
+        .collect::<Vec<_>>(),
+      ["Design", "Sales"]
+    );
+    assert_eq!(
+      repo.find_role(sales.id, user_id).await.unwrap(),
+      Some(TeamRole::Member)
+    );
+    assert_eq!(repo.find_library(sales.id).await.unwrap().len(), 1);
+
+    // Leaving Sales drops its library along with the membership
+    let snapshot = TeamSnapshot {
+      organizations: vec![org],
+      teams: vec![design.clone()],
+      members: vec![snapshot.members[0].clone()],
+      library: vec![],
+    };
+    repo.replace_for_user(user_id, &snapshot).await.unwrap();
+    assert_eq!(repo.find_role(sales.id, user_id).await.unwrap(), None);
+    assert!(repo.find_library(sales.id).await.unwrap().is_empty());
+    assert!(repo.find_library(design.id).await.unwrap().is_empty());
+  }
+}
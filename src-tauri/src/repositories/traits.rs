@@ -0,0 +1,134 @@
+// Repository traits - the storage operations services depend on
+//
+// The Postgres repositories implement these directly; tests swap in the
+// in-memory fakes from `repositories::memory` so service logic can be
+// exercised without a database.
+
+use crate::error::Result;
+use crate::models::entities::*;
+use crate::models::enums::{ActivationSource, ActivityStatus, ProfileType, Severity};
+use async_trait::async_trait;
+use uuid::Uuid;
+
+/// Number of monitors, apps and browser tabs attached to a profile
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProfileChildCounts {
+  pub monitors: i64,
+  pub apps: i64,
+  pub browser_tabs: i64,
+}
+
+/// Profile storage
+#[async_trait]
+pub trait ProfileStore: Send + Sync {
+  async fn find_by_user_id(&self, user_id: Uuid) -> Result<Vec<ProfileEntity>>;
+
+  async fn find_by_id(&self, id: Uuid) -> Result<Option<ProfileEntity>>;
+
+  async fn find_favorites(&self, user_id: Uuid) -> Result<Vec<ProfileEntity>>;
+
+  async fn find_most_used(&self, user_id: Uuid, limit: i64) -> Result<Vec<ProfileEntity>>;
+
+  async fn create(
+    &self,
+    user_id: Uuid,
+    name: &str,
+    description: Option<&str>,
+    profile_type: ProfileType,
+  ) -> Result<ProfileEntity>;
+
+  async fn set_favorite(&self, id: Uuid, is_favorite: bool) -> Result<ProfileEntity>;
+
+  /// Mark a profile active, deactivating the user's other profiles
+  async fn activate(&self, id: Uuid, user_id: Uuid) -> Result<ProfileEntity>;
+
+  async fn find_tags(&self, profile_id: Uuid) -> Result<Vec<String>>;
+
+  /// Replace every tag on a profile
+  async fn set_tags(&self, profile_id: Uuid, tags: &[String]) -> Result<()>;
+
+  async fn set_notes(&self, id: Uuid, notes: Option<&str>) -> Result<()>;
+
+  async fn count_children(&self, profile_id: Uuid) -> Result<ProfileChildCounts>;
+}
+
+/// Audit log storage (activity, system events, sessions, activations, errors)
+#[async_trait]
+pub trait AuditStore: Send + Sync {
+  async fn log_activity(
+    &self,
+    user_id: Uuid,
+    session_id: Option<Uuid>,
+    action: &str,
+    entity_type: Option<&str>,
+    entity_id: Option<Uuid>,
+    entity_name: Option<&str>,
+    details: Option<serde_json::Value>,
+    status: ActivityStatus,
+    error_message: Option<&str>,
+    duration_ms: Option<i32>,
+  ) -> Result<ActivityLogEntity>;
+
+  async fn log_system_event(
+    &self,
+    event_type: &str,
+    severity: Severity,
+    source: &str,
+    message: &str,
+    details: Option<serde_json::Value>,
+    stack_trace: Option<&str>,
+    os_info: Option<serde_json::Value>,
+    app_version: Option<&str>,
+  ) -> Result<SystemEventEntity>;
+
+  async fn start_session(
+    &self,
+    user_id: Uuid,
+    device_id: Option<&str>,
+    device_name: Option<&str>,
+    device_type: Option<&str>,
+    os_name: Option<&str>,
+    os_version: Option<&str>,
+    app_version: Option<&str>,
+    metadata: Option<serde_json::Value>,
+  ) -> Result<SessionEntity>;
+
+  async fn end_session(&self, session_id: Uuid, reason: &str) -> Result<SessionEntity>;
+
+  #[allow(clippy::too_many_arguments)]
+  async fn record_profile_activation(
+    &self,
+    user_id: Uuid,
+    profile_id: Uuid,
+    session_id: Option<Uuid>,
+    activation_source: ActivationSource,
+    previous_profile_id: Option<Uuid>,
+    monitors_detected: Option<i32>,
+    monitors_applied: Option<i32>,
+    apps_detected: Option<i32>,
+    apps_launched: Option<i32>,
+    apps_failed: Option<i32>,
+    tabs_detected: Option<i32>,
+    tabs_opened: Option<i32>,
+    windows_restored: Option<i32>,
+    duration_ms: Option<i32>,
+    success: bool,
+    error_message: Option<&str>,
+    metadata: Option<serde_json::Value>,
+  ) -> Result<ProfileActivationEntity>;
+
+  async fn log_error(
+    &self,
+    user_id: Option<Uuid>,
+    session_id: Option<Uuid>,
+    error_code: Option<&str>,
+    error_type: &str,
+    message: &str,
+    stack_trace: Option<&str>,
+    context: Option<serde_json::Value>,
+    source_file: Option<&str>,
+    source_line: Option<i32>,
+    source_function: Option<&str>,
+    severity: Severity,
+  ) -> Result<ErrorLogEntity>;
+}
@@ -2,6 +2,8 @@
 
 use crate::error::{Result, SmoothieError};
 use crate::models::entities::UserSettingsEntity;
+use crate::models::enums::{ReportFormat, SyncScope};
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -18,11 +20,11 @@ impl<'a> UserSettingsRepository<'a> {
   pub async fn get_or_create(&self, user_id: Uuid) -> Result<UserSettingsEntity> {
     // Try to find existing settings
     let existing =
-      sqlx::query_as::<_, UserSettingsEntity>(r#"SELECT * FROM user_settings WHERE user_id = ?"#)
-        .bind(user_id.to_string())
+      sqlx::query_as::<_, UserSettingsEntity>(r#"SELECT * FROM user_settings WHERE user_id = $1"#)
+        .bind(user_id)
         .fetch_optional(self.pool)
         .await
-        .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+        .map_err(SmoothieError::from)?;
 
     if let Some(settings) = existing {
       return Ok(settings);
@@ -32,15 +34,15 @@ impl<'a> UserSettingsRepository<'a> {
     let settings = sqlx::query_as::<_, UserSettingsEntity>(
       r#"
       INSERT INTO user_settings (id, user_id)
-      VALUES (?, ?)
+      VALUES ($1, $2)
       RETURNING *
       "#,
     )
-    .bind(Uuid::new_v4().to_string())
-    .bind(user_id.to_string())
+    .bind(Uuid::new_v4())
+    .bind(user_id)
     .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(settings)
   }
@@ -57,37 +59,325 @@ impl<'a> UserSettingsRepository<'a> {
     auto_activate_time: Option<String>,
     keyboard_shortcut: Option<String>,
     notifications_enabled: Option<bool>,
+    log_retention_days: Option<i32>,
+    auto_cleanup_logs: Option<bool>,
   ) -> Result<UserSettingsEntity> {
     let settings = sqlx::query_as::<_, UserSettingsEntity>(
       r#"
       UPDATE user_settings
       SET
-        theme = COALESCE(?, theme),
-        auto_restore = COALESCE(?, auto_restore),
-        monitor_detection = COALESCE(?, monitor_detection),
-        animations_enabled = COALESCE(?, animations_enabled),
-        cloud_sync = COALESCE(?, cloud_sync),
-        auto_activate_time = COALESCE(?, auto_activate_time),
-        keyboard_shortcut = COALESCE(?, keyboard_shortcut),
-        notifications_enabled = COALESCE(?, notifications_enabled),
+        theme = COALESCE($1, theme),
+        auto_restore = COALESCE($2, auto_restore),
+        monitor_detection = COALESCE($3, monitor_detection),
+        animations_enabled = COALESCE($4, animations_enabled),
+        cloud_sync = COALESCE($5, cloud_sync),
+        auto_activate_time = COALESCE($6, auto_activate_time),
+        keyboard_shortcut = COALESCE($7, keyboard_shortcut),
+        notifications_enabled = COALESCE($8, notifications_enabled),
+        log_retention_days = COALESCE($9, log_retention_days),
+        auto_cleanup_logs = COALESCE($10, auto_cleanup_logs),
         updated_at = CURRENT_TIMESTAMP
-      WHERE user_id = ?
+      WHERE user_id = $11
       RETURNING *
       "#,
     )
     .bind(theme)
-    .bind(auto_restore.map(|b| if b { 1 } else { 0 }))
-    .bind(monitor_detection.map(|b| if b { 1 } else { 0 }))
-    .bind(animations_enabled.map(|b| if b { 1 } else { 0 }))
-    .bind(cloud_sync.map(|b| if b { 1 } else { 0 }))
+    .bind(auto_restore)
+    .bind(monitor_detection)
+    .bind(animations_enabled)
+    .bind(cloud_sync)
     .bind(auto_activate_time)
     .bind(keyboard_shortcut)
-    .bind(notifications_enabled.map(|b| if b { 1 } else { 0 }))
-    .bind(user_id.to_string())
+    .bind(notifications_enabled)
+    .bind(log_retention_days)
+    .bind(auto_cleanup_logs)
+    .bind(user_id)
     .fetch_one(self.pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     Ok(settings)
   }
+
+  /// Set or clear the location used for sunrise/sunset schedules
+  pub async fn set_location(
+    &self,
+    user_id: Uuid,
+    location: Option<(f64, f64)>,
+  ) -> Result<UserSettingsEntity> {
+    sqlx::query_as::<_, UserSettingsEntity>(
+      r#"
+      UPDATE user_settings
+      SET latitude = $1, longitude = $2, updated_at = CURRENT_TIMESTAMP
+      WHERE user_id = $3
+      RETURNING *
+      "#,
+    )
+    .bind(location.map(|(latitude, _)| latitude))
+    .bind(location.map(|(_, longitude)| longitude))
+    .bind(user_id)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Replace the default profiles picked by monitor count
+  pub async fn set_monitor_profile_mappings(
+    &self,
+    user_id: Uuid,
+    mappings: serde_json::Value,
+  ) -> Result<UserSettingsEntity> {
+    sqlx::query_as::<_, UserSettingsEntity>(
+      r#"
+      UPDATE user_settings
+      SET monitor_profile_mappings = $1, updated_at = CURRENT_TIMESTAMP
+      WHERE user_id = $2
+      RETURNING *
+      "#,
+    )
+    .bind(mappings)
+    .bind(user_id)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Replace the per-profile shortcuts, keyed by profile ID
+  pub async fn set_keyboard_shortcuts(
+    &self,
+    user_id: Uuid,
+    shortcuts: serde_json::Value,
+  ) -> Result<UserSettingsEntity> {
+    sqlx::query_as::<_, UserSettingsEntity>(
+      r#"
+      UPDATE user_settings
+      SET keyboard_shortcuts = $1, updated_at = CURRENT_TIMESTAMP
+      WHERE user_id = $2
+      RETURNING *
+      "#,
+    )
+    .bind(shortcuts)
+    .bind(user_id)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Set what cloud sync uploads
+  pub async fn set_sync_scope(
+    &self,
+    user_id: Uuid,
+    scope: SyncScope,
+  ) -> Result<UserSettingsEntity> {
+    sqlx::query_as::<_, UserSettingsEntity>(
+      r#"
+      UPDATE user_settings
+      SET sync_scope = $1, updated_at = CURRENT_TIMESTAMP
+      WHERE user_id = $2
+      RETURNING *
+      "#,
+    )
+    .bind(scope)
+    .bind(user_id)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// Turn the Monday usage report on or off and pick its format
+  pub async fn set_weekly_report(
+    &self,
+    user_id: Uuid,
+    enabled: bool,
+    format: ReportFormat,
+  ) -> Result<UserSettingsEntity> {
+    sqlx::query_as::<_, UserSettingsEntity>(
+      r#"
+      UPDATE user_settings
+      SET weekly_report_enabled = $1, weekly_report_format = $2,
+          updated_at = CURRENT_TIMESTAMP
+      WHERE user_id = $3
+      RETURNING *
+      "#,
+    )
+    .bind(enabled)
+    .bind(format)
+    .bind(user_id)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  /// How many daily profile snapshots to keep
+  pub async fn set_snapshots_kept(&self, user_id: Uuid, kept: i32) -> Result<UserSettingsEntity> {
+    sqlx::query_as::<_, UserSettingsEntity>(
+      r#"
+      UPDATE user_settings
+      SET snapshots_kept = $1, updated_at = CURRENT_TIMESTAMP
+      WHERE user_id = $2
+      RETURNING *
+      "#,
+    )
+    .bind(kept)
+    .bind(user_id)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  pub async fn set_declarative_watch_path(
+    &self,
+    user_id: Uuid,
+    path: Option<&str>,
+  ) -> Result<UserSettingsEntity> {
+    sqlx::query_as::<_, UserSettingsEntity>(
+      r#"
+      UPDATE user_settings
+      SET declarative_watch_path = $1, updated_at = CURRENT_TIMESTAMP
+      WHERE user_id = $2
+      RETURNING *
+      "#,
+    )
+    .bind(path)
+    .bind(user_id)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  pub async fn set_login_item(
+    &self,
+    user_id: Uuid,
+    launch_at_login: bool,
+    background_agent: bool,
+  ) -> Result<UserSettingsEntity> {
+    sqlx::query_as::<_, UserSettingsEntity>(
+      r#"
+      UPDATE user_settings
+      SET launch_at_login = $1, background_agent = $2, updated_at = CURRENT_TIMESTAMP
+      WHERE user_id = $3
+      RETURNING *
+      "#,
+    )
+    .bind(launch_at_login)
+    .bind(background_agent)
+    .bind(user_id)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  pub async fn set_diagnostic_uploads(
+    &self,
+    user_id: Uuid,
+    enabled: bool,
+  ) -> Result<UserSettingsEntity> {
+    sqlx::query_as::<_, UserSettingsEntity>(
+      r#"
+      UPDATE user_settings
+      SET diagnostic_uploads = $1, updated_at = CURRENT_TIMESTAMP
+      WHERE user_id = $2
+      RETURNING *
+      "#,
+    )
+    .bind(enabled)
+    .bind(user_id)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  pub async fn set_telemetry_enabled(
+    &self,
+    user_id: Uuid,
+    enabled: bool,
+  ) -> Result<UserSettingsEntity> {
+    sqlx::query_as::<_, UserSettingsEntity>(
+      r#"
+      UPDATE user_settings
+      SET telemetry_enabled = $1, updated_at = CURRENT_TIMESTAMP
+      WHERE user_id = $2
+      RETURNING *
+      "#,
+    )
+    .bind(enabled)
+    .bind(user_id)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  pub async fn set_displayplacer_path(
+    &self,
+    user_id: Uuid,
+    path: Option<&str>,
+  ) -> Result<UserSettingsEntity> {
+    sqlx::query_as::<_, UserSettingsEntity>(
+      r#"
+      UPDATE user_settings
+      SET displayplacer_path = $1, updated_at = CURRENT_TIMESTAMP
+      WHERE user_id = $2
+      RETURNING *
+      "#,
+    )
+    .bind(path)
+    .bind(user_id)
+    .fetch_one(self.pool)
+    .await
+    .map_err(SmoothieError::from)
+  }
+
+  pub async fn mark_telemetry_sent(&self, user_id: Uuid, sent_at: DateTime<Utc>) -> Result<()> {
+    sqlx::query("UPDATE user_settings SET telemetry_sent_at = $1 WHERE user_id = $2")
+      .bind(sent_at)
+      .bind(user_id)
+      .execute(self.pool)
+      .await
+      .map_err(SmoothieError::from)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+
+  #[tokio::test]
+  async fn test_get_or_create_and_update() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    let repo = UserSettingsRepository::new(test_db.pool());
+
+    let created = repo.get_or_create(user_id).await.unwrap();
+    assert_eq!(created.log_retention_days, 30);
+    assert_eq!(repo.get_or_create(user_id).await.unwrap().id, created.id);
+
+    let updated = repo
+      .update(
+        user_id,
+        Some("light".into()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(false),
+        Some(90),
+        Some(false),
+      )
+      .await
+      .unwrap();
+    assert_eq!(updated.theme, "light");
+    assert!(!updated.notifications_enabled);
+    assert!(updated.auto_restore);
+    assert_eq!(updated.log_retention_days, 90);
+    assert!(!updated.auto_cleanup_logs);
+
+    assert_eq!(created.sync_scope, SyncScope::Profiles);
+    let scoped = repo.set_sync_scope(user_id, SyncScope::All).await.unwrap();
+    assert_eq!(scoped.sync_scope, SyncScope::All);
+    assert_eq!(scoped.theme, "light");
+  }
 }
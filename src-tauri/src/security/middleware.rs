@@ -0,0 +1,396 @@
+// Command middleware - per-command token-bucket rate limiting, team roles and
+// read-only mode
+//
+// Every command goes through `RATE_LIMITER.check` (see `instrumented!`), but only
+// commands with a configured limit are throttled. The defaults cover the audit
+// endpoints a runaway frontend loop can hammer; `SMOOTHIE_RATE_LIMITS` overrides
+// them, e.g. `log_activity=50/20,log_error=off`.
+//
+// Team commands also need a role in the team they act on. The caller's role
+// only becomes known once the service has looked up the membership, so the
+// service calls `authorize_team` rather than `instrumented!`.
+//
+// On kiosk and shared machines whose profiles are managed elsewhere,
+// `SMOOTHIE_READ_ONLY=1` turns away every command that isn't on an allowlist of
+// ones that leave profiles and settings alone, so a new command stays blocked
+// until it's vetted. Activating, opening and reading all keep working, and so
+// does declarative sync, which is how centrally managed profiles arrive.
+
+use crate::error::{Result, SmoothieError};
+use crate::models::TeamRole;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+pub const RATE_LIMITS_ENV: &str = "SMOOTHIE_RATE_LIMITS";
+pub const READ_ONLY_ENV: &str = "SMOOTHIE_READ_ONLY";
+
+/// Commands that leave profiles and settings alone, the only ones read-only
+/// mode lets through. Anything missing, new commands included, is refused.
+const READ_ONLY_COMMANDS: &[&str] = &[
+  // Reads, previews and exports
+  "check_display_permission",
+  "check_for_updates",
+  "export_profile_as_script",
+  "export_sanitized_logs",
+  "generate_usage_report",
+  "get_activation_checklist",
+  "get_activation_report",
+  "get_active_alerts",
+  "get_active_focus_session",
+  "get_activity_logs",
+  "get_alert_rules",
+  "get_api_version",
+  "get_app_launches",
+  "get_app_metrics",
+  "get_apps",
+  "get_automation_executions",
+  "get_automation_pause",
+  "get_background_tasks",
+  "get_blob",
+  "get_bluetooth_devices",
+  "get_browser_tabs",
+  "get_command_stats",
+  "get_connected_monitors",
+  "get_daily_stats",
+  "get_dashboard_stats",
+  "get_db_connection_status",
+  "get_error_groups",
+  "get_error_logs",
+  "get_favorite_profiles",
+  "get_feedback",
+  "get_fixes",
+  "get_focus_sessions",
+  "get_folder_sync_status",
+  "get_health",
+  "get_installed_apps",
+  "get_interrupted_activations",
+  "get_layout_preview",
+  "get_log_summary",
+  "get_missing_apps",
+  "get_monitor_changes",
+  "get_monitors",
+  "get_most_used_profiles",
+  "get_profile",
+  "get_profile_activations",
+  "get_profile_blocked_apps",
+  "get_profile_checklist",
+  "get_profile_deactivation_actions",
+  "get_profile_edit_state",
+  "get_profile_files",
+  "get_profile_focus_time",
+  "get_profile_history",
+  "get_profiles",
+  "get_release_notes",
+  "get_rule_actions",
+  "get_rules",
+  "get_running_apps",
+  "get_sessions",
+  "get_startup_status",
+  "get_storage_usage",
+  "get_subscription",
+  "get_sync_preview",
+  "get_system_events",
+  "get_team_library",
+  "get_teams",
+  "get_telemetry_payload_preview",
+  "get_usb_devices",
+  "get_user_preferences",
+  "get_user_settings",
+  "get_visible_windows",
+  "get_windows",
+  "list_snapshots",
+  "preview_bookmark_import",
+  "resolve_profile_for_monitors",
+  "simulate_trigger",
+  "test_rule",
+  "validate_shortcut",
+  // Activating and opening profiles
+  "activate_profile",
+  "start_profile",
+  "apply_monitor_layout",
+  "launch_apps",
+  "open_files",
+  "open_tabs",
+  "evaluate_rules",
+  "resume_interrupted_activation",
+  "rollback_interrupted_activation",
+  "discard_interrupted_activation",
+  "release_blanked_displays",
+  "capture_current_layout",
+  "share_profile",
+  // Managed centrally
+  "sync_from_declarative",
+  "sync_teams",
+  // The running session, logs, feedback and maintenance
+  "cancel_focus_session",
+  "cleanup_old_logs",
+  "collect_storage_garbage",
+  "download_update",
+  "end_session",
+  "log_activity",
+  "log_error",
+  "log_system_event",
+  "record_app_launch",
+  "record_automation_execution",
+  "record_monitor_change",
+  "record_profile_activation",
+  "request_display_permission",
+  "resolve_error",
+  "set_checklist_item_completed",
+  "set_log_level",
+  "start_focus_session",
+  "start_session",
+  "store_blob",
+  "submit_feedback",
+];
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// (command, burst, sustained calls per second)
+const DEFAULT_LIMITS: [(&str, u32, f64); 7] = [
+  ("log_activity", 30, 10.0),
+  ("log_system_event", 30, 10.0),
+  ("log_error", 20, 5.0),
+  ("record_profile_activation", 20, 5.0),
+  ("record_monitor_change", 20, 5.0),
+  ("record_app_launch", 20, 5.0),
+  ("record_automation_execution", 20, 5.0),
+];
+
+/// Role each team command requires in the team it acts on
+const TEAM_COMMAND_ROLES: [(&str, TeamRole); 3] = [
+  ("get_team_library", TeamRole::Member),
+  ("clone_team_profile", TeamRole::Member),
+  ("publish_team_profile", TeamRole::Admin),
+];
+
+lazy_static! {
+  pub static ref RATE_LIMITER: RateLimiter = RateLimiter::with_defaults();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+  /// Calls allowed back to back before throttling starts
+  pub burst: u32,
+  /// Tokens refilled per second
+  pub per_second: f64,
+}
+
+struct Bucket {
+  tokens: f64,
+  refilled_at: Instant,
+}
+
+#[derive(Default)]
+pub struct RateLimiter {
+  limits: Mutex<HashMap<String, RateLimit>>,
+  buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+  pub fn with_defaults() -> Self {
+    let limiter = Self::default();
+    for (command, burst, per_second) in DEFAULT_LIMITS {
+      limiter.set_limit(command, Some(RateLimit { burst, per_second }));
+    }
+    limiter
+  }
+
+  /// Set or clear (`None`) the limit for a command
+  pub fn set_limit(&self, command: &str, limit: Option<RateLimit>) {
+    match limit {
+      Some(limit) => self.limits.lock().insert(command.to_string(), limit),
+      None => self.limits.lock().remove(command),
+    };
+    // Start over with a full bucket under the new limit
+    self.buckets.lock().remove(command);
+  }
+
+  /// Apply overrides in `command=burst/per_second` form, comma separated.
+  /// `command=off` removes a limit.
+  pub fn configure(&self, spec: &str) -> Result<()> {
+    for (command, limit) in parse_limits(spec)? {
+      self.set_limit(&command, limit);
+    }
+    Ok(())
+  }
+
+  /// Take a token for `command`, or fail with `RateLimited` if its bucket is empty
+  pub fn check(&self, command: &str) -> Result<()> {
+    let Some(limit) = self.limits.lock().get(command).copied() else {
+      return Ok(());
+    };
+
+    let now = Instant::now();
+    let mut buckets = self.buckets.lock();
+    let bucket = buckets.entry(command.to_string()).or_insert(Bucket {
+      tokens: limit.burst as f64,
+      refilled_at: now,
+    });
+    let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+    bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(limit.burst as f64);
+    bucket.refilled_at = now;
+
+    if bucket.tokens >= 1.0 {
+      bucket.tokens -= 1.0;
+      return Ok(());
+    }
+
+    let retry_after_ms = ((1.0 - bucket.tokens) / limit.per_second * 1000.0).ceil() as u64;
+    Err(SmoothieError::RateLimited(format!(
+      "{} exceeded {} calls/s, retry in {}ms",
+      command, limit.per_second, retry_after_ms
+    )))
+  }
+}
+
+/// Turn read-only mode on or off for the rest of the run
+pub fn set_read_only(read_only: bool) {
+  READ_ONLY.store(read_only, Ordering::Relaxed);
+  tracing::info!(read_only, "Read-only mode set");
+}
+
+pub fn is_read_only() -> bool {
+  READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// Whether `command` may change profiles or settings, which is assumed of
+/// every command not known to be read-only
+pub fn is_mutation(command: &str) -> bool {
+  !READ_ONLY_COMMANDS.contains(&command)
+}
+
+/// Fail with `ReadOnly` if `command` would change something in read-only mode
+pub fn check_read_only(command: &str) -> Result<()> {
+  if is_read_only() && is_mutation(command) {
+    return Err(SmoothieError::ReadOnly(format!(
+      "{} is unavailable; profiles on this machine are managed centrally",
+      command
+    )));
+  }
+  Ok(())
+}
+
+/// Check that a caller with `role` in a team (`None` when not a member) may run
+/// `command` against it. Commands without a configured role are refused.
+/// `role` comes from the local copy of the team, so this only fails early;
+/// Supabase's row-level security policies enforce the same roles.
+pub fn authorize_team(command: &str, role: Option<TeamRole>) -> Result<()> {
+  let Some(required) = TEAM_COMMAND_ROLES
+    .iter()
+    .find(|(name, _)| *name == command)
+    .map(|(_, role)| *role)
+  else {
+    return Err(SmoothieError::Forbidden(format!(
+      "{} has no team role configured",
+      command
+    )));
+  };
+  match role {
+    Some(role) if role.grants(required) => Ok(()),
+    Some(role) => Err(SmoothieError::Forbidden(format!(
+      "{} requires the {} role, not {}",
+      command, required, role
+    ))),
+    None => Err(SmoothieError::Forbidden("Not a member of this team".into())),
+  }
+}
+
+fn parse_limits(spec: &str) -> Result<Vec<(String, Option<RateLimit>)>> {
+  let invalid = |entry: &str| {
+    SmoothieError::ValidationError(format!(
+      "Invalid rate limit '{}', expected command=burst/per_second or command=off",
+      entry
+    ))
+  };
+
+  spec
+    .split(',')
+    .map(str::trim)
+    .filter(|entry| !entry.is_empty())
+    .map(|entry| {
+      let (command, value) = entry.split_once('=').ok_or_else(|| invalid(entry))?;
+      if value.trim() == "off" {
+        return Ok((command.trim().to_string(), None));
+      }
+      let (burst, per_second) = value.split_once('/').ok_or_else(|| invalid(entry))?;
+      let burst: u32 = burst.trim().parse().map_err(|_| invalid(entry))?;
+      let per_second: f64 = per_second.trim().parse().map_err(|_| invalid(entry))?;
+      if burst == 0 || !per_second.is_finite() || per_second <= 0.0 {
+        return Err(invalid(entry));
+      }
+      Ok((
+        command.trim().to_string(),
+        Some(RateLimit { burst, per_second }),
+      ))
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_bucket_allows_burst_then_rejects() {
+    let limiter = RateLimiter::default();
+    limiter
+      .configure("log_activity=3/0.5, log_error=off")
+      .unwrap();
+
+    for _ in 0..3 {
+      assert!(limiter.check("log_activity").is_ok());
+    }
+    assert!(matches!(
+      limiter.check("log_activity"),
+      Err(SmoothieError::RateLimited(_))
+    ));
+    assert!(limiter.check("get_profiles").is_ok());
+
+    assert!(limiter.configure("log_activity=0/1").is_err());
+    assert!(limiter.configure("log_activity").is_err());
+  }
+
+  #[test]
+  fn test_team_commands_need_their_role() {
+    assert!(authorize_team("publish_team_profile", Some(TeamRole::Admin)).is_ok());
+    assert!(authorize_team("clone_team_profile", Some(TeamRole::Admin)).is_ok());
+    assert!(authorize_team("clone_team_profile", Some(TeamRole::Member)).is_ok());
+    assert!(matches!(
+      authorize_team("publish_team_profile", Some(TeamRole::Member)),
+      Err(SmoothieError::Forbidden(_))
+    ));
+    assert!(authorize_team("get_team_library", None).is_err());
+    assert!(authorize_team("delete_team", Some(TeamRole::Admin)).is_err());
+  }
+
+  #[test]
+  fn test_read_only_mode_covers_mutations_only() {
+    for command in [
+      "update_profile",
+      "set_profile_locked",
+      "capture_monitor_layout",
+      "undo_last_change",
+      "apply_fix",
+      "sync_folder_now",
+      "recover_offscreen_windows",
+      "a_command_added_later",
+    ] {
+      assert!(is_mutation(command), "{}", command);
+    }
+    for command in [
+      "get_profiles",
+      "start_profile",
+      "activate_profile",
+      "resume_interrupted_activation",
+      "sync_from_declarative",
+      "set_checklist_item_completed",
+      "log_activity",
+    ] {
+      assert!(!is_mutation(command), "{}", command);
+    }
+  }
+}
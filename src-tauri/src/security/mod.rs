@@ -1,2 +1,3 @@
 // Security module - authentication, authorization, and access control
-// Placeholder for future security features
+
+pub mod middleware;
@@ -0,0 +1,946 @@
+// Profile activation pipeline
+//
+// Starting a profile runs a sequence of typed steps - monitor layout, blanked
+// displays, apps, browser tabs, files. Steps can be switched off or reordered per run, and when
+// one fails the steps that already ran are rolled back, newest first. Steps
+// that only open things (tabs, files) fail on their own without that. A run can
+// also keep a journal on disk (see `activation_journal`) so it can be resumed or
+// rolled back after the app goes down part way through.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  logging::{ACTIVATION_DURATION, METRICS},
+  models::{ProfileId, UserId},
+  services::{
+    activation_journal::{ActivationJournal, JournalStepStatus},
+    app_service::LaunchResult,
+    browser_service::OpenTabResult,
+    deactivation_service,
+    environment_service::PowerState,
+    file_service::OpenFileResult,
+    monitor_service::MonitorLayoutResult,
+    AppService, BrowserService, FileService, MonitorService, ProfileService, SystemBackend,
+    SystemMonitor,
+  },
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The steps a profile start is made of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivationStepKind {
+  MonitorLayout,
+  BlankDisplays,
+  Apps,
+  BrowserTabs,
+  Files,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+  Completed,
+  Skipped,
+  Failed,
+  RolledBack,
+  RollbackFailed,
+}
+
+/// How one step went
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepReport {
+  pub step: ActivationStepKind,
+  pub status: StepStatus,
+  pub message: String,
+  pub duration_ms: i64,
+}
+
+/// What the steps produced
+#[derive(Debug, Default)]
+pub struct ActivationOutput {
+  pub monitor_layout: Option<MonitorLayoutResult>,
+  pub apps_launched: Vec<LaunchResult>,
+  pub tabs_opened: Vec<OpenTabResult>,
+  pub files_opened: Vec<OpenFileResult>,
+}
+
+/// A single stage of starting a profile
+#[async_trait]
+pub trait ActivationStep: Send {
+  fn kind(&self) -> ActivationStepKind;
+
+  /// Note what the system looks like before `execute` changes it
+  fn capture(&mut self) {}
+
+  /// What `rollback` needs, kept in the activation journal
+  fn undo_state(&self) -> Value {
+    Value::Null
+  }
+
+  /// Take back the state from `undo_state` of a run the app didn't finish
+  fn restore(&mut self, _state: Value) -> Result<()> {
+    Ok(())
+  }
+
+  /// Whether a failure here stops the run and rolls back the earlier steps.
+  /// Steps that leave nothing to undo can fail without taking them down.
+  fn critical(&self) -> bool {
+    true
+  }
+
+  /// Do the step's work, recording the results in `output`
+  async fn execute(&mut self, output: &mut ActivationOutput) -> Result<()>;
+
+  /// Undo what `execute` did after a later step failed
+  async fn rollback(&mut self) -> Result<()> {
+    Ok(())
+  }
+
+  /// One-line summary of what `execute` did
+  fn report(&self) -> String;
+}
+
+/// Outcome of running a pipeline; `error` is set when a critical step failed
+/// and the earlier ones were rolled back
+pub struct PipelineRun {
+  pub output: ActivationOutput,
+  pub steps: Vec<StepReport>,
+  pub error: Option<SmoothieError>,
+}
+
+pub struct ActivationPipeline {
+  steps: Vec<Box<dyn ActivationStep>>,
+  skipped: HashMap<ActivationStepKind, String>,
+  journal: Option<ActivationJournal>,
+}
+
+impl ActivationPipeline {
+  pub fn new(steps: Vec<Box<dyn ActivationStep>>) -> Self {
+    Self {
+      steps,
+      skipped: HashMap::new(),
+      journal: None,
+    }
+  }
+
+  /// The standard steps for starting a profile. Monitors go first so apps open
+  /// on the right screens, and files last, once their apps are up.
+  pub fn for_profile(
+    db: Arc<Database>,
+    system: Arc<dyn SystemBackend>,
+    profile_id: &str,
+    user_id: &str,
+  ) -> Self {
+    Self::new(vec![
+      Box::new(MonitorLayoutStep::new(
+        db.clone(),
+        system.clone(),
+        profile_id,
+      )),
+      Box::new(BlankDisplaysStep::new(
+        db.clone(),
+        system.clone(),
+        profile_id,
+      )),
+      Box::new(LaunchAppsStep::new(db.clone(), system, profile_id, user_id)),
+      Box::new(OpenTabsStep::new(db.clone(), profile_id)),
+      Box::new(OpenFilesStep::new(db, profile_id)),
+    ])
+  }
+
+  /// `for_profile`, leaving out app launches and browser tabs while the
+  /// battery is below the profile's low-power threshold. The power state is
+  /// returned when that happened.
+  pub async fn for_start(
+    db: Arc<Database>,
+    system: Arc<dyn SystemBackend>,
+    profile_id: ProfileId,
+    user_id: UserId,
+  ) -> Result<(Self, Option<PowerState>)> {
+    let low_power = ProfileService::low_power_state(&db, profile_id).await?;
+    let mut pipeline = Self::for_profile(db, system, &profile_id.to_string(), &user_id.to_string());
+    if let Some(power) = &low_power {
+      tracing::info!(
+        battery_percent = ?power.battery_percent,
+        "On low battery, skipping app launches and browser tabs"
+      );
+      pipeline = pipeline
+        .skip(ActivationStepKind::Apps, "Skipped on low battery")
+        .skip(ActivationStepKind::BrowserTabs, "Skipped on low battery");
+    }
+    Ok((pipeline, low_power))
+  }
+
+  /// Run the listed steps in the given order; the rest are skipped
+  pub fn order(mut self, order: &[ActivationStepKind]) -> Self {
+    let requested: HashSet<_> = order.iter().copied().collect();
+    for step in &self.steps {
+      if !requested.contains(&step.kind()) {
+        self
+          .skipped
+          .entry(step.kind())
+          .or_insert_with(|| "Not requested".to_string());
+      }
+    }
+    let position = |kind| order.iter().position(|k| *k == kind).unwrap_or(order.len());
+    self.steps.sort_by_key(|step| position(step.kind()));
+    self
+  }
+
+  /// Leave a step out of this run
+  pub fn skip(mut self, kind: ActivationStepKind, reason: impl Into<String>) -> Self {
+    self.skipped.insert(kind, reason.into());
+    self
+  }
+
+  /// Keep `journal` up to date while the run goes, removing it at the end
+  pub fn journaled(mut self, journal: ActivationJournal) -> Self {
+    self.journal = Some(journal);
+    self
+  }
+
+  /// Run the steps in order, stopping at the first critical failure and
+  /// rolling back the steps that completed before it
+  pub async fn run(self) -> PipelineRun {
+    let run_started = Instant::now();
+    let mut output = ActivationOutput::default();
+    let mut reports = Vec::with_capacity(self.steps.len());
+    let mut completed: Vec<(usize, Box<dyn ActivationStep>)> = Vec::new();
+    let mut error = None;
+
+    let mut journal = self.journal;
+    if let Some(journal) = &mut journal {
+      let plan = self
+        .steps
+        .iter()
+        .map(|step| (step.kind(), self.skipped.contains_key(&step.kind())))
+        .collect();
+      journal.plan(plan).await;
+    }
+
+    for (index, mut step) in self.steps.into_iter().enumerate() {
+      let kind = step.kind();
+      if let Some(reason) = self.skipped.get(&kind) {
+        reports.push(StepReport {
+          step: kind,
+          status: StepStatus::Skipped,
+          message: reason.clone(),
+          duration_ms: 0,
+        });
+        continue;
+      }
+      if error.is_some() {
+        reports.push(StepReport {
+          step: kind,
+          status: StepStatus::Skipped,
+          message: "Not run after an earlier step failed".to_string(),
+          duration_ms: 0,
+        });
+        continue;
+      }
+
+      let started = Instant::now();
+      step.capture();
+      if let Some(journal) = &mut journal {
+        journal
+          .mark(index, JournalStepStatus::Running, step.undo_state())
+          .await;
+      }
+      match step.execute(&mut output).await {
+        Ok(()) => {
+          if let Some(journal) = &mut journal {
+            journal
+              .mark(index, JournalStepStatus::Completed, step.undo_state())
+              .await;
+          }
+          reports.push(StepReport {
+            step: kind,
+            status: StepStatus::Completed,
+            message: step.report(),
+            duration_ms: started.elapsed().as_millis() as i64,
+          });
+          completed.push((reports.len() - 1, step));
+        }
+        Err(e) => {
+          tracing::warn!(step = ?kind, "Activation step failed: {}", e);
+          reports.push(StepReport {
+            step: kind,
+            status: StepStatus::Failed,
+            message: e.to_string(),
+            duration_ms: started.elapsed().as_millis() as i64,
+          });
+          if step.critical() {
+            error = Some(e);
+          }
+        }
+      }
+    }
+
+    if error.is_some() {
+      for (index, mut step) in completed.into_iter().rev() {
+        let report = &mut reports[index];
+        match step.rollback().await {
+          Ok(()) => report.status = StepStatus::RolledBack,
+          Err(e) => {
+            tracing::warn!(step = ?report.step, "Activation step rollback failed: {}", e);
+            report.status = StepStatus::RollbackFailed;
+            report.message = format!("{}; rollback failed: {}", report.message, e);
+          }
+        }
+      }
+    }
+
+    if let Some(journal) = &journal {
+      journal.remove().await;
+    }
+
+    METRICS.observe(ACTIVATION_DURATION, run_started.elapsed());
+    PipelineRun {
+      output,
+      steps: reports,
+      error,
+    }
+  }
+
+  /// Roll back the steps an interrupted run started, newest first, using the
+  /// undo state its journal kept
+  pub async fn rollback_journal(mut self, journal: &ActivationJournal) -> Vec<StepReport> {
+    let mut reports = Vec::new();
+    for entry in journal.steps.iter().rev() {
+      if !matches!(
+        entry.status,
+        JournalStepStatus::Running | JournalStepStatus::Completed
+      ) {
+        continue;
+      }
+      let Some(position) = self.steps.iter().position(|s| s.kind() == entry.step) else {
+        continue;
+      };
+      let mut step = self.steps.remove(position);
+
+      let started = Instant::now();
+      let rolled_back = match step.restore(entry.undo.clone()) {
+        Ok(()) => step.rollback().await,
+        Err(e) => Err(e),
+      };
+      let (status, message) = match rolled_back {
+        Ok(()) => (StepStatus::RolledBack, "Rolled back".to_string()),
+        Err(e) => {
+          tracing::warn!(step = ?entry.step, "Activation step rollback failed: {}", e);
+          (
+            StepStatus::RollbackFailed,
+            format!("Rollback failed: {}", e),
+          )
+        }
+      };
+      reports.push(StepReport {
+        step: entry.step,
+        status,
+        message,
+        duration_ms: started.elapsed().as_millis() as i64,
+      });
+    }
+    reports
+  }
+}
+
+/// Applies the profile's monitor layout, putting the previous one back on rollback
+pub struct MonitorLayoutStep {
+  db: Arc<Database>,
+  system: Arc<dyn SystemBackend>,
+  profile_id: String,
+  previous: Vec<SystemMonitor>,
+  summary: String,
+}
+
+impl MonitorLayoutStep {
+  pub fn new(db: Arc<Database>, system: Arc<dyn SystemBackend>, profile_id: &str) -> Self {
+    Self {
+      db,
+      system,
+      profile_id: profile_id.to_string(),
+      previous: Vec::new(),
+      summary: String::new(),
+    }
+  }
+}
+
+#[async_trait]
+impl ActivationStep for MonitorLayoutStep {
+  fn kind(&self) -> ActivationStepKind {
+    ActivationStepKind::MonitorLayout
+  }
+
+  fn capture(&mut self) {
+    self.previous = self.system.get_monitors();
+  }
+
+  fn undo_state(&self) -> Value {
+    serde_json::to_value(&self.previous).unwrap_or_default()
+  }
+
+  fn restore(&mut self, state: Value) -> Result<()> {
+    self.previous = serde_json::from_value(state)?;
+    Ok(())
+  }
+
+  async fn execute(&mut self, output: &mut ActivationOutput) -> Result<()> {
+    // A layout that can't be loaded or applied is reported, not fatal. Only
+    // an applied layout needs the previous one put back.
+    let layout =
+      match MonitorService::get_layout_to_apply(&self.db, self.system.as_ref(), &self.profile_id)
+        .await
+      {
+        Ok(monitors) if !monitors.is_empty() => {
+          tracing::info!("Applying monitor layout with {} monitors", monitors.len());
+          MonitorService::apply_layout(self.system.as_ref(), monitors).await
+        }
+        Ok(_) => {
+          tracing::info!("No monitors configured for this profile");
+          self.previous.clear();
+          MonitorLayoutResult::skipped("No monitor layout configured for this profile")
+        }
+        Err(e) => {
+          tracing::warn!("Failed to get profile monitors: {:?}", e);
+          self.previous.clear();
+          MonitorLayoutResult::skipped(format!("Failed to load monitor layout: {}", e))
+        }
+      };
+    self.summary = layout.message.clone();
+    output.monitor_layout = Some(layout);
+    Ok(())
+  }
+
+  async fn rollback(&mut self) -> Result<()> {
+    if self.previous.is_empty() {
+      return Ok(());
+    }
+    let restored = MonitorService::apply_layout(self.system.as_ref(), self.previous.clone()).await;
+    if restored.applied {
+      Ok(())
+    } else {
+      Err(SmoothieError::SystemError(restored.message))
+    }
+  }
+
+  fn report(&self) -> String {
+    self.summary.clone()
+  }
+}
+
+/// Make `target` exactly the set of blanked displays, returning how many
+/// displays couldn't be switched
+fn set_blanked_displays(system: &dyn SystemBackend, target: &HashSet<u32>) -> usize {
+  let current: HashSet<u32> = system.blanked_displays().into_iter().collect();
+  let mut failed = 0;
+  for display_id in current.difference(target) {
+    if let Err(e) = system.unblank_display(*display_id) {
+      tracing::warn!(display_id, "Failed to unblank display: {}", e);
+      failed += 1;
+    }
+  }
+  for display_id in target.difference(&current) {
+    if let Err(e) = system.blank_display(*display_id) {
+      tracing::warn!(display_id, "Failed to blank display: {}", e);
+      failed += 1;
+    }
+  }
+  failed
+}
+
+/// Blank `target` out of `live_count` connected displays, leaving every
+/// display lit if it would cover them all. Returns the step summary.
+fn blank_displays(system: &dyn SystemBackend, live_count: usize, target: &HashSet<u32>) -> String {
+  if !target.is_empty() && target.len() >= live_count {
+    set_blanked_displays(system, &HashSet::new());
+    return "Left all displays on: blanking would cover every screen".to_string();
+  }
+
+  match (target.len(), set_blanked_displays(system, target)) {
+    (0, 0) => "No displays to blank".to_string(),
+    (n, 0) => format!("Blanked {} of {} displays", n, live_count),
+    (_, failed) => format!("Blanked displays with {} failures", failed),
+  }
+}
+
+/// Blanks the displays the profile keeps dark and gives back any an earlier
+/// profile blanked. The last lit display is never blanked, so a profile saved
+/// on a bigger desk can't leave every screen black.
+pub struct BlankDisplaysStep {
+  db: Arc<Database>,
+  system: Arc<dyn SystemBackend>,
+  profile_id: String,
+  previous: HashSet<u32>,
+  summary: String,
+}
+
+impl BlankDisplaysStep {
+  pub fn new(db: Arc<Database>, system: Arc<dyn SystemBackend>, profile_id: &str) -> Self {
+    Self {
+      db,
+      system,
+      profile_id: profile_id.to_string(),
+      previous: HashSet::new(),
+      summary: String::new(),
+    }
+  }
+}
+
+#[async_trait]
+impl ActivationStep for BlankDisplaysStep {
+  fn kind(&self) -> ActivationStepKind {
+    ActivationStepKind::BlankDisplays
+  }
+
+  fn capture(&mut self) {
+    self.previous = self.system.blanked_displays().into_iter().collect();
+  }
+
+  fn undo_state(&self) -> Value {
+    serde_json::to_value(&self.previous).unwrap_or_default()
+  }
+
+  fn restore(&mut self, state: Value) -> Result<()> {
+    self.previous = serde_json::from_value(state)?;
+    Ok(())
+  }
+
+  async fn execute(&mut self, _output: &mut ActivationOutput) -> Result<()> {
+    let live = self.system.get_monitors();
+    let target = MonitorService::displays_to_blank(&self.db, &self.profile_id, &live).await?;
+    self.summary = blank_displays(
+      self.system.as_ref(),
+      live.len(),
+      &target.into_iter().collect(),
+    );
+    Ok(())
+  }
+
+  async fn rollback(&mut self) -> Result<()> {
+    match set_blanked_displays(self.system.as_ref(), &self.previous) {
+      0 => Ok(()),
+      failed => Err(SmoothieError::SystemError(format!(
+        "{} displays couldn't be restored",
+        failed
+      ))),
+    }
+  }
+
+  fn report(&self) -> String {
+    self.summary.clone()
+  }
+}
+
+/// Launches the profile's apps, quitting the ones it started on rollback
+pub struct LaunchAppsStep {
+  db: Arc<Database>,
+  system: Arc<dyn SystemBackend>,
+  profile_id: String,
+  user_id: String,
+  already_running: HashSet<String>,
+  launched: Vec<LaunchResult>,
+  finished: bool,
+  /// Restored from a run that went down mid-launch, so any of the profile's
+  /// apps may have been started
+  launched_unknown: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LaunchAppsUndo {
+  already_running: HashSet<String>,
+  /// Bundle ids of the apps started, once the launches finished
+  launched: Option<Vec<String>>,
+}
+
+impl LaunchAppsStep {
+  pub fn new(
+    db: Arc<Database>,
+    system: Arc<dyn SystemBackend>,
+    profile_id: &str,
+    user_id: &str,
+  ) -> Self {
+    Self {
+      db,
+      system,
+      profile_id: profile_id.to_string(),
+      user_id: user_id.to_string(),
+      already_running: HashSet::new(),
+      launched: Vec::new(),
+      finished: false,
+      launched_unknown: false,
+    }
+  }
+}
+
+#[async_trait]
+impl ActivationStep for LaunchAppsStep {
+  fn kind(&self) -> ActivationStepKind {
+    ActivationStepKind::Apps
+  }
+
+  fn capture(&mut self) {
+    self.already_running = self
+      .system
+      .get_running_apps()
+      .into_iter()
+      .map(|app| app.bundle_id)
+      .collect();
+  }
+
+  fn undo_state(&self) -> Value {
+    let undo = LaunchAppsUndo {
+      already_running: self.already_running.clone(),
+      launched: self.finished.then(|| {
+        self
+          .launched
+          .iter()
+          .filter(|result| result.success)
+          .map(|result| result.bundle_id.clone())
+          .collect()
+      }),
+    };
+    serde_json::to_value(undo).unwrap_or_default()
+  }
+
+  fn restore(&mut self, state: Value) -> Result<()> {
+    let undo: LaunchAppsUndo = serde_json::from_value(state)?;
+    self.already_running = undo.already_running;
+    self.launched_unknown = undo.launched.is_none();
+    self.launched = undo
+      .launched
+      .unwrap_or_default()
+      .into_iter()
+      .map(|bundle_id| LaunchResult {
+        name: bundle_id.clone(),
+        bundle_id,
+        success: true,
+        message: String::new(),
+        window_positioned: false,
+        app_id: None,
+        failure_code: None,
+      })
+      .collect();
+    Ok(())
+  }
+
+  async fn execute(&mut self, output: &mut ActivationOutput) -> Result<()> {
+    self.launched = AppService::launch_profile_apps(
+      &self.db,
+      self.system.clone(),
+      &self.profile_id,
+      &self.user_id,
+    )
+    .await?;
+    self.finished = true;
+    output.apps_launched = self.launched.clone();
+    Ok(())
+  }
+
+  async fn rollback(&mut self) -> Result<()> {
+    let mut started: HashSet<String> = self
+      .launched
+      .iter()
+      .filter(|result| result.success)
+      .map(|result| result.bundle_id.clone())
+      .collect();
+    if self.launched_unknown {
+      let apps = AppService::get_launchable_apps(&self.db, &self.profile_id).await?;
+      started.extend(apps.into_iter().map(|app| app.bundle_id));
+    }
+    // Apps the user already had open stay open
+    for bundle_id in started.difference(&self.already_running) {
+      deactivation_service::quit_app(bundle_id).await?;
+    }
+    Ok(())
+  }
+
+  fn report(&self) -> String {
+    let succeeded = self.launched.iter().filter(|r| r.success).count();
+    format!("Launched {} of {} apps", succeeded, self.launched.len())
+  }
+}
+
+/// Opens the profile's browser tabs. Rollback leaves them open: there's no
+/// telling them apart from tabs the user already had.
+pub struct OpenTabsStep {
+  db: Arc<Database>,
+  profile_id: String,
+  opened: Vec<OpenTabResult>,
+}
+
+impl OpenTabsStep {
+  pub fn new(db: Arc<Database>, profile_id: &str) -> Self {
+    Self {
+      db,
+      profile_id: profile_id.to_string(),
+      opened: Vec::new(),
+    }
+  }
+}
+
+#[async_trait]
+impl ActivationStep for OpenTabsStep {
+  fn kind(&self) -> ActivationStepKind {
+    ActivationStepKind::BrowserTabs
+  }
+
+  fn critical(&self) -> bool {
+    false
+  }
+
+  async fn execute(&mut self, output: &mut ActivationOutput) -> Result<()> {
+    self.opened = BrowserService::open_profile_tabs(&self.db, &self.profile_id).await?;
+    output.tabs_opened = self.opened.clone();
+    Ok(())
+  }
+
+  fn report(&self) -> String {
+    let succeeded = self.opened.iter().filter(|r| r.success).count();
+    format!("Opened {} of {} tabs", succeeded, self.opened.len())
+  }
+}
+
+/// Opens the profile's documents and folders
+pub struct OpenFilesStep {
+  db: Arc<Database>,
+  profile_id: String,
+  opened: Vec<OpenFileResult>,
+}
+
+impl OpenFilesStep {
+  pub fn new(db: Arc<Database>, profile_id: &str) -> Self {
+    Self {
+      db,
+      profile_id: profile_id.to_string(),
+      opened: Vec::new(),
+    }
+  }
+}
+
+#[async_trait]
+impl ActivationStep for OpenFilesStep {
+  fn kind(&self) -> ActivationStepKind {
+    ActivationStepKind::Files
+  }
+
+  fn critical(&self) -> bool {
+    false
+  }
+
+  async fn execute(&mut self, output: &mut ActivationOutput) -> Result<()> {
+    self.opened = FileService::open_profile_files(&self.db, &self.profile_id).await?;
+    output.files_opened = self.opened.clone();
+    Ok(())
+  }
+
+  fn report(&self) -> String {
+    let succeeded = self.opened.iter().filter(|r| r.success).count();
+    format!("Opened {} of {} files", succeeded, self.opened.len())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::services::system_backend::MockSystemBackend;
+  use parking_lot::Mutex;
+
+  /// Records execute/rollback calls into a shared log
+  struct FakeStep {
+    kind: ActivationStepKind,
+    fail: bool,
+    critical: bool,
+    log: Arc<Mutex<Vec<String>>>,
+  }
+
+  #[async_trait]
+  impl ActivationStep for FakeStep {
+    fn kind(&self) -> ActivationStepKind {
+      self.kind
+    }
+
+    fn critical(&self) -> bool {
+      self.critical
+    }
+
+    async fn execute(&mut self, _output: &mut ActivationOutput) -> Result<()> {
+      self.log.lock().push(format!("execute {:?}", self.kind));
+      if self.fail {
+        return Err(SmoothieError::SystemError("boom".into()));
+      }
+      Ok(())
+    }
+
+    async fn rollback(&mut self) -> Result<()> {
+      self.log.lock().push(format!("rollback {:?}", self.kind));
+      Ok(())
+    }
+
+    fn report(&self) -> String {
+      format!("{:?} done", self.kind)
+    }
+  }
+
+  fn pipeline(
+    failing: Option<ActivationStepKind>,
+  ) -> (ActivationPipeline, Arc<Mutex<Vec<String>>>) {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let steps = [
+      ActivationStepKind::MonitorLayout,
+      ActivationStepKind::Apps,
+      ActivationStepKind::BrowserTabs,
+      ActivationStepKind::Files,
+    ]
+    .into_iter()
+    .map(|kind| {
+      Box::new(FakeStep {
+        kind,
+        fail: failing == Some(kind),
+        critical: kind != ActivationStepKind::Files,
+        log: log.clone(),
+      }) as Box<dyn ActivationStep>
+    })
+    .collect();
+    (ActivationPipeline::new(steps), log)
+  }
+
+  fn statuses(run: &PipelineRun) -> Vec<(ActivationStepKind, StepStatus)> {
+    run.steps.iter().map(|r| (r.step, r.status)).collect()
+  }
+
+  #[tokio::test]
+  async fn test_steps_run_in_requested_order_with_toggles() {
+    use ActivationStepKind::*;
+    let (pipeline, log) = pipeline(None);
+
+    let run = pipeline
+      .order(&[Files, MonitorLayout, Apps])
+      .skip(Apps, "On low battery")
+      .run()
+      .await;
+
+    assert!(run.error.is_none());
+    assert_eq!(*log.lock(), vec!["execute Files", "execute MonitorLayout"]);
+    assert_eq!(
+      statuses(&run),
+      vec![
+        (Files, StepStatus::Completed),
+        (MonitorLayout, StepStatus::Completed),
+        (Apps, StepStatus::Skipped),
+        (BrowserTabs, StepStatus::Skipped),
+      ]
+    );
+    assert_eq!(run.steps[2].message, "On low battery");
+  }
+
+  #[tokio::test]
+  async fn test_journal_rollback_undoes_started_steps_newest_first() {
+    use crate::services::activation_journal::JournalStep;
+    use ActivationStepKind::*;
+    let (pipeline, log) = pipeline(None);
+    let mut journal = ActivationJournal::new(
+      ProfileId::from(uuid::Uuid::nil()),
+      UserId::from(uuid::Uuid::nil()),
+    );
+    journal.steps = [
+      (MonitorLayout, JournalStepStatus::Completed),
+      (Apps, JournalStepStatus::Running),
+      (BrowserTabs, JournalStepStatus::Pending),
+      (Files, JournalStepStatus::Pending),
+    ]
+    .into_iter()
+    .map(|(step, status)| JournalStep {
+      step,
+      status,
+      undo: Value::Null,
+    })
+    .collect();
+
+    let reports = pipeline.rollback_journal(&journal).await;
+
+    assert_eq!(*log.lock(), vec!["rollback Apps", "rollback MonitorLayout"]);
+    assert_eq!(
+      reports
+        .iter()
+        .map(|r| (r.step, r.status))
+        .collect::<Vec<_>>(),
+      vec![
+        (Apps, StepStatus::RolledBack),
+        (MonitorLayout, StepStatus::RolledBack),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_blank_displays_never_covers_every_screen() {
+    let system = MockSystemBackend::with_sample_layout();
+
+    let summary = blank_displays(&system, 2, &HashSet::from([2]));
+    assert_eq!(system.blanked_displays(), vec![2]);
+    assert_eq!(summary, "Blanked 1 of 2 displays");
+
+    let summary = blank_displays(&system, 2, &HashSet::from([1, 2]));
+    assert!(system.blanked_displays().is_empty());
+    assert_eq!(
+      summary,
+      "Left all displays on: blanking would cover every screen"
+    );
+  }
+
+  #[tokio::test]
+  async fn test_failure_rolls_back_completed_steps_newest_first() {
+    use ActivationStepKind::*;
+    let (pipeline, log) = pipeline(Some(BrowserTabs));
+
+    let run = pipeline.run().await;
+
+    assert!(matches!(run.error, Some(SmoothieError::SystemError(_))));
+    assert_eq!(
+      *log.lock(),
+      vec![
+        "execute MonitorLayout",
+        "execute Apps",
+        "execute BrowserTabs",
+        "rollback Apps",
+        "rollback MonitorLayout",
+      ]
+    );
+    assert_eq!(
+      statuses(&run),
+      vec![
+        (MonitorLayout, StepStatus::RolledBack),
+        (Apps, StepStatus::RolledBack),
+        (BrowserTabs, StepStatus::Failed),
+        (Files, StepStatus::Skipped),
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_non_critical_failure_keeps_earlier_steps() {
+    use ActivationStepKind::*;
+    let (pipeline, log) = pipeline(Some(Files));
+
+    let run = pipeline.run().await;
+
+    assert!(run.error.is_none());
+    assert!(!log.lock().iter().any(|entry| entry.starts_with("rollback")));
+    assert_eq!(
+      statuses(&run),
+      vec![
+        (MonitorLayout, StepStatus::Completed),
+        (Apps, StepStatus::Completed),
+        (BrowserTabs, StepStatus::Completed),
+        (Files, StepStatus::Failed),
+      ]
+    );
+  }
+}
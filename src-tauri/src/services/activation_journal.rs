@@ -0,0 +1,354 @@
+// Write-ahead journal for profile activations
+//
+// A journaled activation writes its plan to the app data directory before the
+// first step runs, then marks each step running and completed as it goes,
+// together with what the step needs to undo itself. The file is removed when
+// the run ends, whether it succeeded or rolled back, so a journal left by an
+// earlier launch means the app went down mid-activation. The user can then
+// resume the steps that hadn't finished or roll back the ones that had, rather
+// than being left with a half-arranged desktop.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::{ProfileId, UserId},
+  services::{
+    activation::{ActivationPipeline, ActivationStepKind, PipelineRun, StepReport},
+    environment_service::PowerState,
+    ProfileService, SystemBackend,
+  },
+  utils::paths,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use uuid::Uuid;
+
+/// Version of the journal file; newer ones are left alone
+const JOURNAL_FORMAT: u32 = 1;
+
+/// Tells this launch's journals, which may belong to a running activation,
+/// from ones an earlier launch left behind
+static LAUNCH_ID: OnceLock<Uuid> = OnceLock::new();
+
+fn launch_id() -> Uuid {
+  *LAUNCH_ID.get_or_init(Uuid::new_v4)
+}
+
+fn journal_dir() -> PathBuf {
+  paths::app_data_dir().join("activation-journal")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalStepStatus {
+  Pending,
+  Running,
+  Completed,
+  Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalStep {
+  pub step: ActivationStepKind,
+  pub status: JournalStepStatus,
+  /// From `ActivationStep::undo_state`, once the step has started
+  #[serde(default)]
+  pub undo: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivationJournal {
+  format: u32,
+  launch_id: Uuid,
+  pub id: Uuid,
+  pub profile_id: ProfileId,
+  pub user_id: UserId,
+  pub started_at: DateTime<Utc>,
+  pub steps: Vec<JournalStep>,
+  #[serde(skip)]
+  dir: PathBuf,
+}
+
+impl ActivationJournal {
+  pub fn new(profile_id: ProfileId, user_id: UserId) -> Self {
+    Self::in_dir(journal_dir(), profile_id, user_id)
+  }
+
+  fn in_dir(dir: PathBuf, profile_id: ProfileId, user_id: UserId) -> Self {
+    Self {
+      format: JOURNAL_FORMAT,
+      launch_id: launch_id(),
+      id: Uuid::new_v4(),
+      profile_id,
+      user_id,
+      started_at: Utc::now(),
+      steps: Vec::new(),
+      dir,
+    }
+  }
+
+  fn path(&self) -> PathBuf {
+    self.dir.join(format!("{}.json", self.id))
+  }
+
+  /// Lay out the steps a run is about to take. Steps it leaves out keep the
+  /// entry from an interrupted run they completed in, so a resumed activation
+  /// can still roll them back.
+  pub(crate) async fn plan(&mut self, steps: Vec<(ActivationStepKind, bool)>) {
+    let earlier = std::mem::take(&mut self.steps);
+    self.launch_id = launch_id();
+    self.steps = steps
+      .into_iter()
+      .map(|(step, skipped)| {
+        let completed = earlier
+          .iter()
+          .find(|e| e.step == step && e.status == JournalStepStatus::Completed);
+        match completed {
+          Some(entry) if skipped => entry.clone(),
+          _ => JournalStep {
+            step,
+            status: if skipped {
+              JournalStepStatus::Skipped
+            } else {
+              JournalStepStatus::Pending
+            },
+            undo: Value::Null,
+          },
+        }
+      })
+      .collect();
+    self.save().await;
+  }
+
+  /// Record that the step at `index` started or finished
+  pub(crate) async fn mark(&mut self, index: usize, status: JournalStepStatus, undo: Value) {
+    if let Some(entry) = self.steps.get_mut(index) {
+      entry.status = status;
+      entry.undo = undo;
+    }
+    self.save().await;
+  }
+
+  /// A journal that can't be written doesn't stop the activation; it just
+  /// can't be recovered if the app goes down
+  async fn save(&self) {
+    let written = async {
+      tokio::fs::create_dir_all(&self.dir).await?;
+      paths::write_atomically(&self.path(), &serde_json::to_vec_pretty(self)?).await
+    }
+    .await;
+    if let Err(e) = written {
+      tracing::warn!(journal = %self.id, "Failed to write activation journal: {}", e);
+    }
+  }
+
+  pub(crate) async fn remove(&self) {
+    match tokio::fs::remove_file(self.path()).await {
+      Ok(()) => {}
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+      Err(e) => {
+        tracing::warn!(journal = %self.id, "Failed to remove activation journal: {}", e);
+      }
+    }
+  }
+
+  fn kinds(&self, status: JournalStepStatus) -> Vec<ActivationStepKind> {
+    self
+      .steps
+      .iter()
+      .filter(|s| s.status == status)
+      .map(|s| s.step)
+      .collect()
+  }
+}
+
+/// An activation an earlier launch didn't finish
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterruptedActivationDto {
+  pub id: Uuid,
+  pub profile_id: ProfileId,
+  /// Missing when the profile has been deleted since
+  pub profile_name: Option<String>,
+  pub started_at: DateTime<Utc>,
+  pub completed: Vec<ActivationStepKind>,
+  /// The step that was running when the app went down
+  pub interrupted: Option<ActivationStepKind>,
+  pub pending: Vec<ActivationStepKind>,
+}
+
+/// Journals from earlier launches in `dir`, oldest first. Unreadable files
+/// are skipped so one bad journal doesn't hide the rest.
+async fn interrupted_in(dir: &Path) -> Result<Vec<ActivationJournal>> {
+  let mut entries = match tokio::fs::read_dir(dir).await {
+    Ok(entries) => entries,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(e) => return Err(e.into()),
+  };
+
+  let mut journals = Vec::new();
+  while let Some(entry) = entries.next_entry().await? {
+    let path = entry.path();
+    if path.extension().and_then(|e| e.to_str()) != Some("json") {
+      continue;
+    }
+    let journal = tokio::fs::read(&path)
+      .await
+      .map_err(SmoothieError::from)
+      .and_then(|data| Ok(serde_json::from_slice::<ActivationJournal>(&data)?));
+    match journal {
+      Ok(mut journal) if journal.format <= JOURNAL_FORMAT && journal.launch_id != launch_id() => {
+        journal.dir = dir.to_path_buf();
+        journals.push(journal);
+      }
+      Ok(_) => {}
+      Err(e) => {
+        tracing::warn!(path = %path.display(), "Skipping unreadable activation journal: {}", e)
+      }
+    }
+  }
+  journals.sort_by_key(|j| j.started_at);
+  Ok(journals)
+}
+
+async fn find_interrupted(dir: &Path, id: Uuid) -> Result<ActivationJournal> {
+  interrupted_in(dir)
+    .await?
+    .into_iter()
+    .find(|j| j.id == id)
+    .ok_or_else(|| SmoothieError::NotFound("Interrupted activation not found".into()))
+}
+
+pub struct ActivationJournalService;
+
+impl ActivationJournalService {
+  /// Activations an earlier launch left half done, oldest first
+  pub async fn list_interrupted(db: &Database) -> Result<Vec<InterruptedActivationDto>> {
+    let mut interrupted = Vec::new();
+    for journal in interrupted_in(&journal_dir()).await? {
+      let profile_name = ProfileService::get_profile(db, journal.profile_id)
+        .await
+        .ok()
+        .map(|p| p.name);
+      interrupted.push(InterruptedActivationDto {
+        id: journal.id,
+        profile_id: journal.profile_id,
+        profile_name,
+        started_at: journal.started_at,
+        completed: journal.kinds(JournalStepStatus::Completed),
+        interrupted: journal.kinds(JournalStepStatus::Running).first().copied(),
+        pending: journal.kinds(JournalStepStatus::Pending),
+      });
+    }
+    Ok(interrupted)
+  }
+
+  /// Run the steps an interrupted activation hadn't finished, including the
+  /// one it was in the middle of. The journal carries on with the new run.
+  pub async fn resume(
+    db: Arc<Database>,
+    system: Arc<dyn SystemBackend>,
+    id: Uuid,
+  ) -> Result<(ProfileId, PipelineRun, Option<PowerState>)> {
+    let journal = find_interrupted(&journal_dir(), id).await?;
+    let profile_id = journal.profile_id;
+    let order: Vec<_> = journal.steps.iter().map(|s| s.step).collect();
+
+    let (mut pipeline, low_power) =
+      ActivationPipeline::for_start(db, system, profile_id, journal.user_id).await?;
+    pipeline = pipeline.order(&order);
+    for entry in &journal.steps {
+      match entry.status {
+        JournalStepStatus::Completed => {
+          pipeline = pipeline.skip(entry.step, "Completed before the interruption")
+        }
+        JournalStepStatus::Skipped => {
+          pipeline = pipeline.skip(entry.step, "Skipped in the interrupted run")
+        }
+        JournalStepStatus::Pending | JournalStepStatus::Running => {}
+      }
+    }
+
+    let run = pipeline.journaled(journal).run().await;
+    Ok((profile_id, run, low_power))
+  }
+
+  /// Undo the steps an interrupted activation got through, newest first
+  pub async fn rollback(
+    db: Arc<Database>,
+    system: Arc<dyn SystemBackend>,
+    id: Uuid,
+  ) -> Result<Vec<StepReport>> {
+    let journal = find_interrupted(&journal_dir(), id).await?;
+    let reports = ActivationPipeline::for_profile(
+      db,
+      system,
+      &journal.profile_id.to_string(),
+      &journal.user_id.to_string(),
+    )
+    .rollback_journal(&journal)
+    .await;
+    journal.remove().await;
+    Ok(reports)
+  }
+
+  /// Forget an interrupted activation, leaving the desktop as it is
+  pub async fn discard(id: Uuid) -> Result<()> {
+    find_interrupted(&journal_dir(), id).await?.remove().await;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_journals_from_this_launch_are_not_interrupted() {
+    let dir = std::env::temp_dir().join(format!("smoothie-journal-{}", Uuid::new_v4()));
+    let mut current = ActivationJournal::in_dir(
+      dir.clone(),
+      ProfileId::from(Uuid::nil()),
+      UserId::from(Uuid::nil()),
+    );
+    current
+      .plan(vec![
+        (ActivationStepKind::MonitorLayout, false),
+        (ActivationStepKind::Apps, false),
+      ])
+      .await;
+    current
+      .mark(0, JournalStepStatus::Completed, serde_json::json!([]))
+      .await;
+
+    // Written by an earlier launch that went down while launching apps
+    let mut earlier = current.clone();
+    earlier.id = Uuid::new_v4();
+    earlier.launch_id = Uuid::new_v4();
+    earlier
+      .mark(1, JournalStepStatus::Running, Value::Null)
+      .await;
+
+    let interrupted = interrupted_in(&dir).await.unwrap();
+    assert_eq!(interrupted.len(), 1);
+    assert_eq!(interrupted[0].id, earlier.id);
+    assert_eq!(
+      interrupted[0].kinds(JournalStepStatus::Completed),
+      vec![ActivationStepKind::MonitorLayout]
+    );
+    assert_eq!(
+      interrupted[0].kinds(JournalStepStatus::Running),
+      vec![ActivationStepKind::Apps]
+    );
+
+    earlier.remove().await;
+    current.remove().await;
+    assert!(interrupted_in(&dir).await.unwrap().is_empty());
+    let _ = std::fs::remove_dir(&dir);
+  }
+}
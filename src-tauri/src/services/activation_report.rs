@@ -0,0 +1,321 @@
+//! Post-mortems of failed activations
+//!
+//! A failed start shows up as one error line, which rarely says why. When a
+//! step fails, a report is kept with the activation: how every step went,
+//! what osascript and displayplacer printed while the run went on, whether
+//! Screen Recording is granted, where the tools were found, and the fixes
+//! that all of that points to.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::ProfileId,
+  repositories::AuditRepository,
+  services::{
+    activation::{ActivationStepKind, PipelineRun, StepReport, StepStatus},
+    fix_service::FailureCode,
+    health_service::{HealthService, HealthStatus, PermissionHealth, ToolHealth},
+    system_service::{SystemService, ToolFailure},
+    SystemBackend,
+  },
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// AppleScript errors for a missing Accessibility grant
+const ACCESSIBILITY_ERRORS: [&str; 3] = ["-1719", "-25211", "assistive access"];
+/// AppleScript errors for a missing Automation grant
+const AUTOMATION_ERRORS: [&str; 2] = ["-1743", "Not authorized to send Apple events"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivationReportDto {
+  pub activation_id: String,
+  pub profile_id: String,
+  pub failed_step: Option<ActivationStepKind>,
+  pub error: String,
+  pub steps: Vec<StepReport>,
+  /// What external tools printed when they failed during the run
+  pub tool_output: Vec<ToolFailure>,
+  pub permissions: PermissionHealth,
+  pub tools: Vec<ToolHealth>,
+  pub suggested_fixes: Vec<String>,
+  /// Known failures `get_fixes` has actions for
+  #[serde(default)]
+  pub failure_codes: Vec<FailureCode>,
+  pub created_at: String,
+}
+
+pub struct ActivationReportService;
+
+impl ActivationReportService {
+  /// Mark the profile's latest activation as failed and keep a report of
+  /// `run` with it. `started_at` is when the run began, to pick out the tool
+  /// output it caused. Nothing is recorded for a run that didn't fail, and
+  /// failing to record doesn't change the outcome of the run.
+  pub async fn record_failure(
+    db: &Database,
+    system: &dyn SystemBackend,
+    profile_id: ProfileId,
+    run: &PipelineRun,
+    started_at: DateTime<Utc>,
+  ) -> Option<ActivationReportDto> {
+    let error = run.error.as_ref()?;
+    match Self::store(db, system, profile_id, run, &error.to_string(), started_at).await {
+      Ok(report) => report,
+      Err(e) => {
+        tracing::warn!(profile_id = %profile_id, "Could not store the activation report: {}", e);
+        None
+      }
+    }
+  }
+
+  async fn store(
+    db: &Database,
+    system: &dyn SystemBackend,
+    profile_id: ProfileId,
+    run: &PipelineRun,
+    error: &str,
+    started_at: DateTime<Utc>,
+  ) -> Result<Option<ActivationReportDto>> {
+    let repo = AuditRepository::new(db.pool());
+    let Some(activation) = repo
+      .get_latest_profile_activation(profile_id.as_uuid())
+      .await?
+    else {
+      tracing::warn!(profile_id = %profile_id, "No activation to attach the failure report to");
+      return Ok(None);
+    };
+
+    let failed_step = run
+      .steps
+      .iter()
+      .find(|r| r.status == StepStatus::Failed)
+      .map(|r| r.step);
+    let tool_output = SystemService::tool_failures_since(started_at);
+    let permissions = HealthService::check_permissions(system);
+    let tools = HealthService::check_tools();
+    let suggested_fixes = suggested_fixes(
+      failed_step,
+      error,
+      &tool_output,
+      permissions.screen_recording,
+      &tools,
+    );
+    let failure_codes = failure_codes(failed_step, permissions.screen_recording, &tools);
+
+    let mut report = ActivationReportDto {
+      activation_id: activation.id.to_string(),
+      profile_id: profile_id.to_string(),
+      failed_step,
+      error: error.to_string(),
+      steps: run.steps.clone(),
+      tool_output,
+      permissions,
+      tools,
+      suggested_fixes,
+      failure_codes,
+      created_at: String::new(),
+    };
+
+    let rolled_back = run.steps.iter().any(|r| r.status == StepStatus::RolledBack);
+    repo
+      .fail_profile_activation(activation.id, error, rolled_back)
+      .await?;
+    let stored = repo
+      .save_activation_report(
+        activation.id,
+        activation.profile_id,
+        serde_json::to_value(&report)?,
+      )
+      .await?;
+    report.created_at = stored.created_at.to_rfc3339();
+
+    tracing::info!(
+      activation_id = %activation.id,
+      failed_step = ?failed_step,
+      "Stored activation failure report"
+    );
+    Ok(Some(report))
+  }
+
+  /// The report kept for a failed activation
+  pub async fn get(db: &Database, activation_id: Uuid) -> Result<ActivationReportDto> {
+    let stored = AuditRepository::new(db.pool())
+      .get_activation_report(activation_id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("No report for this activation".into()))?;
+    let mut report: ActivationReportDto = serde_json::from_value(stored.report)?;
+    report.created_at = stored.created_at.to_rfc3339();
+    Ok(report)
+  }
+}
+
+fn displayplacer_missing(tools: &[ToolHealth]) -> bool {
+  tools
+    .iter()
+    .any(|t| t.name == "displayplacer" && t.status != HealthStatus::Ok)
+}
+
+fn on_displays(failed_step: Option<ActivationStepKind>) -> bool {
+  matches!(
+    failed_step,
+    Some(ActivationStepKind::MonitorLayout | ActivationStepKind::BlankDisplays)
+  )
+}
+
+/// The known failures behind a failed step
+fn failure_codes(
+  failed_step: Option<ActivationStepKind>,
+  screen_recording: bool,
+  tools: &[ToolHealth],
+) -> Vec<FailureCode> {
+  let mut codes = Vec::new();
+  if on_displays(failed_step) && !screen_recording {
+    codes.push(FailureCode::ScreenRecordingDenied);
+  }
+  if failed_step == Some(ActivationStepKind::MonitorLayout) && displayplacer_missing(tools) {
+    codes.push(FailureCode::DisplayplacerMissing);
+  }
+  codes
+}
+
+/// What to try, from what failed and what the system looked like
+fn suggested_fixes(
+  failed_step: Option<ActivationStepKind>,
+  error: &str,
+  tool_output: &[ToolFailure],
+  screen_recording: bool,
+  tools: &[ToolHealth],
+) -> Vec<String> {
+  let printed: Vec<&str> = std::iter::once(error)
+    .chain(tool_output.iter().map(|f| f.stderr.as_str()))
+    .collect();
+  let mentions = |needles: &[&str]| {
+    printed
+      .iter()
+      .any(|text| needles.iter().any(|needle| text.contains(needle)))
+  };
+  let mut fixes = Vec::new();
+  if on_displays(failed_step) && !screen_recording {
+    fixes.push(
+      "Allow Smoothie under System Settings > Privacy & Security > Screen Recording".to_string(),
+    );
+  }
+  if failed_step == Some(ActivationStepKind::MonitorLayout) {
+    if displayplacer_missing(tools) {
+      fixes.push(
+        "Install displayplacer (brew install jakehilborn/jakehilborn/displayplacer) or set its path in Settings"
+          .to_string(),
+      );
+    } else if tool_output.iter().any(|f| f.tool == "displayplacer") {
+      fixes.push(
+        "Connect the displays the profile was saved with, or save its layout again".to_string(),
+      );
+    }
+  }
+  if mentions(&ACCESSIBILITY_ERRORS) {
+    fixes.push(
+      "Allow Smoothie under System Settings > Privacy & Security > Accessibility".to_string(),
+    );
+  }
+  if mentions(&AUTOMATION_ERRORS) {
+    fixes.push(
+      "Allow Smoothie to control the apps under System Settings > Privacy & Security > Automation"
+        .to_string(),
+    );
+  }
+  if failed_step == Some(ActivationStepKind::Apps) && fixes.is_empty() {
+    fixes.push("Check that the profile's apps are still installed".to_string());
+  }
+  if fixes.is_empty() {
+    fixes.push(
+      "Activate the profile again; if it keeps failing, export the logs from Diagnostics"
+        .to_string(),
+    );
+  }
+  fixes
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use crate::models::ActivationSource;
+  use crate::services::activation::ActivationOutput;
+  use crate::services::system_backend::MockSystemBackend;
+  use crate::services::ProfileService;
+
+  #[tokio::test]
+  async fn test_failed_run_is_reported_with_its_activation() {
+    let test_db = test_db_or_skip!();
+    let (user_id, profile_id) = test_db.seed_user_and_profile("Desk").await;
+    let db = &test_db.db;
+
+    let started_at = Utc::now();
+    ProfileService::activate_profile(
+      db,
+      profile_id.into(),
+      user_id.into(),
+      ActivationSource::Manual,
+    )
+    .await
+    .unwrap();
+    let step = |step, status| StepReport {
+      step,
+      status,
+      message: String::new(),
+      duration_ms: 0,
+    };
+    let run = PipelineRun {
+      output: ActivationOutput::default(),
+      steps: vec![
+        step(ActivationStepKind::MonitorLayout, StepStatus::Failed),
+        step(ActivationStepKind::Apps, StepStatus::Skipped),
+      ],
+      error: Some(SmoothieError::SystemError(
+        "Failed to apply monitor layout".into(),
+      )),
+    };
+
+    // No Screen Recording grant in the mock
+    let system = MockSystemBackend::default();
+    let report =
+      ActivationReportService::record_failure(db, &system, profile_id.into(), &run, started_at)
+        .await
+        .unwrap();
+    assert_eq!(report.failed_step, Some(ActivationStepKind::MonitorLayout));
+    assert!(report.suggested_fixes[0].contains("Screen Recording"));
+    assert_eq!(report.failure_codes[0], FailureCode::ScreenRecordingDenied);
+
+    let activation_id = Uuid::parse_str(&report.activation_id).unwrap();
+    let stored = ActivationReportService::get(db, activation_id)
+      .await
+      .unwrap();
+    assert_eq!(stored.steps.len(), 2);
+    assert_eq!(stored.error, report.error);
+    let (success,): (bool,) =
+      sqlx::query_as("SELECT success FROM profile_activations WHERE id = $1")
+        .bind(activation_id)
+        .fetch_one(test_db.pool())
+        .await
+        .unwrap();
+    assert!(!success);
+
+    let tool_output = [ToolFailure {
+      tool: "osascript".into(),
+      exit_code: Some(1),
+      stderr: "execution error: Not authorized to send Apple events to Finder. (-1743)".into(),
+      failed_at: Utc::now(),
+    }];
+    assert_eq!(
+      suggested_fixes(Some(ActivationStepKind::Apps), "", &tool_output, true, &[]).len(),
+      1
+    );
+    assert!(
+      suggested_fixes(Some(ActivationStepKind::Files), "", &[], true, &[])[0]
+        .starts_with("Activate the profile again")
+    );
+  }
+}
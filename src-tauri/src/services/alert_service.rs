@@ -0,0 +1,380 @@
+//! Alert Service
+//!
+//! Evaluates alert rules (error bursts, activation failure rates) against the
+//! audit tables. A background task runs the rules every minute; when one is
+//! breached an alert row is opened, a system event is written and a native
+//! notification is shown. The alert resolves itself once the metric drops
+//! back under the threshold.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::dto::{AlertDto, AlertRuleDto},
+  models::entities::{AlertEntity, AlertRuleEntity},
+  models::Severity,
+  repositories::AlertRepository,
+  services::{SystemBackend, UserSettingsService, AUDIT_SERVICE},
+  state::AppState,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const EVALUATION_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Metrics a rule can watch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AlertMetric {
+  /// Critical error occurrences in the window
+  CriticalErrors,
+  /// Error occurrences of any severity in the window
+  ErrorCount,
+  /// Share of failed profile activations in the window (0.0 - 1.0)
+  ActivationFailureRate,
+}
+
+impl AlertMetric {
+  fn parse(metric: &str) -> Option<Self> {
+    match metric {
+      "critical_errors" => Some(Self::CriticalErrors),
+      "error_count" => Some(Self::ErrorCount),
+      "activation_failure_rate" => Some(Self::ActivationFailureRate),
+      _ => None,
+    }
+  }
+}
+
+/// What to do with a rule after measuring it
+#[derive(Debug, PartialEq)]
+enum Decision {
+  Fire,
+  Resolve(Uuid),
+  Nothing,
+}
+
+fn decide(
+  breached: bool,
+  latest: Option<&AlertEntity>,
+  cooldown: Duration,
+  now: DateTime<Utc>,
+) -> Decision {
+  let open = latest.filter(|a| a.resolved_at.is_none());
+  match (breached, open) {
+    (true, Some(_)) => Decision::Nothing,
+    // Don't re-fire a rule that flapped back over the threshold right after resolving
+    (true, None) => match latest {
+      Some(previous) if now - previous.triggered_at < cooldown => Decision::Nothing,
+      _ => Decision::Fire,
+    },
+    (false, Some(alert)) => Decision::Resolve(alert.id),
+    (false, None) => Decision::Nothing,
+  }
+}
+
+pub struct AlertService;
+
+impl AlertService {
+  /// Get the user's alert rules, creating the defaults on first use
+  pub async fn get_rules(db: &Database, user_id: &str) -> Result<Vec<AlertRuleDto>> {
+    let user_uuid = parse_uuid(user_id)?;
+    let repo = AlertRepository::new(db.pool());
+    repo.ensure_default_rules(user_uuid).await?;
+
+    let rules = repo.find_rules(user_uuid).await?;
+    Ok(rules.into_iter().map(AlertRuleDto::from).collect())
+  }
+
+  pub async fn update_rule(
+    db: &Database,
+    rule_id: &str,
+    is_enabled: Option<bool>,
+    threshold: Option<f64>,
+    window_minutes: Option<i32>,
+    cooldown_minutes: Option<i32>,
+  ) -> Result<AlertRuleDto> {
+    let rule_uuid = parse_uuid(rule_id)?;
+    if threshold.is_some_and(|t| !t.is_finite() || t < 0.0) {
+      return Err(SmoothieError::ValidationError(
+        "Threshold must be a non-negative number".into(),
+      ));
+    }
+    if window_minutes.is_some_and(|w| !(1..=24 * 60).contains(&w)) {
+      return Err(SmoothieError::ValidationError(
+        "Window must be between 1 minute and 24 hours".into(),
+      ));
+    }
+    if cooldown_minutes.is_some_and(|c| c < 0) {
+      return Err(SmoothieError::ValidationError(
+        "Cooldown cannot be negative".into(),
+      ));
+    }
+
+    let rule = AlertRepository::new(db.pool())
+      .update_rule(
+        rule_uuid,
+        is_enabled,
+        threshold,
+        window_minutes,
+        cooldown_minutes,
+      )
+      .await?;
+    Ok(AlertRuleDto::from(rule))
+  }
+
+  /// Alerts that are currently firing
+  pub async fn get_active_alerts(db: &Database, user_id: &str) -> Result<Vec<AlertDto>> {
+    let user_uuid = parse_uuid(user_id)?;
+    let repo = AlertRepository::new(db.pool());
+    let rules = repo.find_rules(user_uuid).await?;
+
+    let alerts = repo.find_active_alerts(user_uuid).await?;
+    Ok(
+      alerts
+        .into_iter()
+        .filter_map(|alert| {
+          let rule = rules.iter().find(|r| r.id == alert.rule_id)?;
+          Some(AlertDto::from_entity(alert, rule))
+        })
+        .collect(),
+    )
+  }
+
+  /// Evaluate every enabled rule once, returning the alerts that fired
+  pub async fn evaluate(
+    db: &Database,
+    system: &dyn SystemBackend,
+    user_id: Uuid,
+  ) -> Result<Vec<AlertDto>> {
+    let repo = AlertRepository::new(db.pool());
+    repo.ensure_default_rules(user_id).await?;
+    let notify = UserSettingsService::get_settings(db, user_id)
+      .await
+      .map(|s| s.notifications_enabled)
+      .unwrap_or(true);
+
+    let now = Utc::now();
+    let mut fired = Vec::new();
+    for rule in repo.find_rules(user_id).await? {
+      if !rule.is_enabled {
+        continue;
+      }
+      let Some(metric) = AlertMetric::parse(&rule.metric) else {
+        tracing::warn!(rule = %rule.name, metric = %rule.metric, "Unknown alert metric");
+        continue;
+      };
+
+      let since = now - Duration::minutes(rule.window_minutes as i64);
+      let value = match metric {
+        AlertMetric::CriticalErrors => Some(
+          repo
+            .count_errors_since(since, Some(Severity::Critical))
+            .await? as f64,
+        ),
+        AlertMetric::ErrorCount => Some(repo.count_errors_since(since, None).await? as f64),
+        AlertMetric::ActivationFailureRate => {
+          let (total, failed) = repo.activation_counts_since(user_id, since).await?;
+          // Too few activations to say anything about the rate
+          (total > 0 && total >= rule.min_samples as i64).then(|| failed as f64 / total as f64)
+        }
+      };
+      let breached = value.is_some_and(|v| v > rule.threshold);
+
+      let latest = repo.find_latest_alert(rule.id).await?;
+      let cooldown = Duration::minutes(rule.cooldown_minutes as i64);
+      match decide(breached, latest.as_ref(), cooldown, now) {
+        Decision::Fire => {
+          let value = value.unwrap_or_default();
+          let message = describe(metric, &rule, value);
+          let alert = repo.create_alert(&rule, value, &message).await?;
+          Self::announce(db, system, &rule, &alert, notify).await;
+          fired.push(AlertDto::from_entity(alert, &rule));
+        }
+        Decision::Resolve(alert_id) => {
+          repo.resolve_alert(alert_id).await?;
+          let _ = AUDIT_SERVICE
+            .log_system_event(
+              db,
+              "alert_resolved",
+              Severity::Info,
+              "AlertService",
+              &format!("Alert '{}' resolved", rule.name),
+              Some(json!({ "rule_id": rule.id, "alert_id": alert_id })),
+              None,
+            )
+            .await;
+        }
+        Decision::Nothing => {}
+      }
+    }
+
+    Ok(fired)
+  }
+
+  /// Evaluate alert rules every minute in the background
+  pub fn spawn_alert_monitor(state: Arc<AppState>, user_id: Uuid) {
+    let monitor_state = state.clone();
+    state.tasks.spawn("alert_monitor", move || {
+      let state = monitor_state.clone();
+      async move {
+        let mut ticker = tokio::time::interval(EVALUATION_INTERVAL);
+        loop {
+          ticker.tick().await;
+          if let Err(e) = Self::evaluate(&state.db, state.system.as_ref(), user_id).await {
+            tracing::warn!("Alert evaluation failed: {}", e);
+          }
+        }
+      }
+    });
+  }
+
+  async fn announce(
+    db: &Database,
+    system: &dyn SystemBackend,
+    rule: &AlertRuleEntity,
+    alert: &AlertEntity,
+    notify: bool,
+  ) {
+    tracing::warn!(rule = %rule.name, value = alert.value, "Alert triggered");
+
+    let severity = if rule.severity == Severity::Critical {
+      Severity::Critical
+    } else {
+      Severity::Warning
+    };
+    let _ = AUDIT_SERVICE
+      .log_system_event(
+        db,
+        "alert_triggered",
+        severity,
+        "AlertService",
+        &alert.message,
+        Some(json!({
+          "rule_id": rule.id,
+          "alert_id": alert.id,
+          "metric": rule.metric,
+          "value": alert.value,
+          "threshold": alert.threshold,
+        })),
+        None,
+      )
+      .await;
+
+    if notify {
+      if let Err(e) = system.show_notification(&format!("Smoothie: {}", rule.name), &alert.message)
+      {
+        tracing::warn!("Failed to show alert notification: {}", e);
+      }
+    }
+  }
+}
+
+fn describe(metric: AlertMetric, rule: &AlertRuleEntity, value: f64) -> String {
+  match metric {
+    AlertMetric::CriticalErrors => format!(
+      "{} critical errors in the last {} minutes (threshold {})",
+      value, rule.window_minutes, rule.threshold
+    ),
+    AlertMetric::ErrorCount => format!(
+      "{} errors in the last {} minutes (threshold {})",
+      value, rule.window_minutes, rule.threshold
+    ),
+    AlertMetric::ActivationFailureRate => format!(
+      "{:.0}% of profile activations failed in the last {} minutes (threshold {:.0}%)",
+      value * 100.0,
+      rule.window_minutes,
+      rule.threshold * 100.0
+    ),
+  }
+}
+
+fn parse_uuid(s: &str) -> Result<Uuid> {
+  Uuid::parse_str(s).map_err(|_| SmoothieError::ValidationError(format!("Invalid UUID: {}", s)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use crate::services::system_backend::MockSystemBackend;
+
+  fn alert(triggered_minutes_ago: i64, resolved: bool) -> AlertEntity {
+    let triggered_at = Utc::now() - Duration::minutes(triggered_minutes_ago);
+    AlertEntity {
+      id: Uuid::new_v4(),
+      rule_id: Uuid::new_v4(),
+      user_id: Uuid::new_v4(),
+      value: 6.0,
+      threshold: 5.0,
+      message: "6 critical errors".into(),
+      triggered_at,
+      resolved_at: resolved.then_some(triggered_at),
+    }
+  }
+
+  #[test]
+  fn test_decide_respects_open_alerts_and_cooldown() {
+    let now = Utc::now();
+    let cooldown = Duration::minutes(30);
+
+    assert_eq!(decide(true, None, cooldown, now), Decision::Fire);
+    assert_eq!(
+      decide(true, Some(&alert(5, false)), cooldown, now),
+      Decision::Nothing
+    );
+    assert_eq!(
+      decide(true, Some(&alert(5, true)), cooldown, now),
+      Decision::Nothing
+    );
+    assert_eq!(
+      decide(true, Some(&alert(45, true)), cooldown, now),
+      Decision::Fire
+    );
+
+    let open = alert(5, false);
+    assert_eq!(
+      decide(false, Some(&open), cooldown, now),
+      Decision::Resolve(open.id)
+    );
+    assert_eq!(decide(false, None, cooldown, now), Decision::Nothing);
+  }
+
+  #[tokio::test]
+  async fn test_critical_error_burst_fires_once() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    sqlx::query(
+      "INSERT INTO error_logs (error_type, message, severity) \
+       SELECT 'panic', 'crash ' || n, 'critical' FROM generate_series(1, 6) AS n",
+    )
+    .execute(test_db.pool())
+    .await
+    .unwrap();
+
+    let system = MockSystemBackend::default();
+    let fired = AlertService::evaluate(&test_db.db, &system, user_id)
+      .await
+      .unwrap();
+    assert_eq!(fired.len(), 1);
+    assert_eq!(fired[0].rule_name, "Critical error burst");
+    assert_eq!(system.notifications().len(), 1);
+
+    // Still breached, but already open
+    let again = AlertService::evaluate(&test_db.db, &system, user_id)
+      .await
+      .unwrap();
+    assert!(again.is_empty());
+
+    let active = AlertService::get_active_alerts(&test_db.db, &user_id.to_string())
+      .await
+      .unwrap();
+    assert_eq!(active.len(), 1);
+
+    let (events,): (i64,) =
+      sqlx::query_as("SELECT COUNT(*) FROM system_events WHERE event_type = 'alert_triggered'")
+        .fetch_one(test_db.pool())
+        .await
+        .unwrap();
+    assert_eq!(events, 1);
+  }
+}
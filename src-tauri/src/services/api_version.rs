@@ -0,0 +1,103 @@
+//! Versioning for the commands the frontend calls
+//!
+//! The frontend and backend ship together, but a cached or stale webview can
+//! end up talking to a newer backend. On startup the frontend sends the
+//! version it was built against and gets back whether the two can work
+//! together, plus the optional features this backend has, so it can hide
+//! what's missing instead of failing on a renamed field.
+//!
+//! `API_VERSION` goes up when a command or DTO changes in a way an existing
+//! frontend would misread - a field renamed, removed or changing type.
+//! Additions don't bump it; they get a capability instead.
+
+use crate::security::middleware;
+use serde::Serialize;
+
+/// Version of the command API this backend serves
+pub const API_VERSION: u32 = 1;
+
+/// Oldest frontend API version this backend still serves correctly
+pub const MIN_CLIENT_API_VERSION: u32 = 1;
+
+/// Features added without a version bump, for frontends to check before
+/// showing them
+pub const CAPABILITIES: &[&str] = &[
+  "activation_reports",
+  "app_substitutes",
+  "blocked_apps",
+  "declarative_sync",
+  "deactivation_actions",
+  "edit_history",
+  "fix_actions",
+  "offline_mode",
+  "offline_profiles",
+  "profile_checklists",
+  "profile_locks",
+  "profile_notes",
+  "profile_sharing",
+  "read_only_mode",
+  "shortcut_validation",
+  "snapshots",
+  "virtual_displays",
+];
+
+/// What the backend serves and whether it suits the asking frontend
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiVersionDto {
+  pub version: u32,
+  pub min_client_version: u32,
+  pub app_version: String,
+  /// Unset when the frontend didn't say which version it expects
+  pub compatible: Option<bool>,
+  pub capabilities: Vec<String>,
+  /// Edits are turned away; only activation and reads work
+  pub read_only: bool,
+}
+
+pub struct ApiVersion;
+
+impl ApiVersion {
+  /// Describe this backend to a frontend built against `client_version`
+  pub fn negotiate(client_version: Option<u32>) -> ApiVersionDto {
+    let compatible = client_version.map(|v| (MIN_CLIENT_API_VERSION..=API_VERSION).contains(&v));
+    if compatible == Some(false) {
+      tracing::warn!(
+        client_version = ?client_version,
+        api_version = API_VERSION,
+        "Frontend expects an API version this backend doesn't serve"
+      );
+    }
+
+    ApiVersionDto {
+      version: API_VERSION,
+      min_client_version: MIN_CLIENT_API_VERSION,
+      app_version: env!("CARGO_PKG_VERSION").to_string(),
+      compatible,
+      capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+      read_only: middleware::is_read_only(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_negotiate_accepts_only_served_versions() {
+    assert_eq!(ApiVersion::negotiate(None).compatible, None);
+    assert_eq!(
+      ApiVersion::negotiate(Some(API_VERSION)).compatible,
+      Some(true)
+    );
+    assert_eq!(
+      ApiVersion::negotiate(Some(API_VERSION + 1)).compatible,
+      Some(false)
+    );
+    assert_eq!(
+      ApiVersion::negotiate(Some(MIN_CLIENT_API_VERSION - 1)).compatible,
+      Some(false)
+    );
+  }
+}
@@ -0,0 +1,375 @@
+//! Per-profile app blocklists
+//!
+//! A profile can list apps it keeps closed, each either hidden or quit. The
+//! running-app watcher samples the running apps every few seconds and acts on
+//! blocked apps that weren't running at the previous sample, so an app is
+//! handled once per launch rather than on every poll. On a profile switch
+//! everything running counts as new, which clears out the incoming profile's
+//! blocked apps. The user can let an app through with an override, which
+//! lasts until it runs out or the profile changes.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::dto::{BlockedAppDto, BlockedAppOverrideDto, BlockedAppRequest},
+  models::entities::ProfileBlockedAppEntity,
+  models::{ActivityStatus, BlockedAppAction, ProfileId, UserId},
+  repositories::ProfileRepository,
+  services::{profile_lock::ProfileLock, RunningApp, UserSettingsService, AUDIT_SERVICE},
+  state::AppState,
+  utils::validation,
+};
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+const RUNNING_APP_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Most apps one profile can block
+const MAX_BLOCKED_APPS: usize = 100;
+
+/// Longest a blocked app can be let through for
+const MAX_OVERRIDE_MINUTES: i64 = 24 * 60;
+
+/// Running apps seen at the previous sample, and under which profile
+#[derive(Debug, Default)]
+pub struct LaunchDetector {
+  profile_id: Option<Uuid>,
+  seen: HashSet<u32>,
+}
+
+impl LaunchDetector {
+  /// Note the active profile and running apps. Returns the apps that
+  /// launched since the last call, or all of them when the profile changed.
+  pub fn observe<'a>(
+    &mut self,
+    profile_id: Option<Uuid>,
+    running: &'a [RunningApp],
+  ) -> Vec<&'a RunningApp> {
+    if profile_id != self.profile_id {
+      self.profile_id = profile_id;
+      self.seen.clear();
+    }
+    let launched = running
+      .iter()
+      .filter(|app| !self.seen.contains(&app.pid))
+      .collect();
+    self.seen = running.iter().map(|app| app.pid).collect();
+    launched
+  }
+}
+
+pub struct AppBlocklistService;
+
+impl AppBlocklistService {
+  pub async fn get_blocked_apps(
+    db: &Database,
+    profile_id: ProfileId,
+  ) -> Result<Vec<BlockedAppDto>> {
+    let apps = ProfileRepository::new(db.pool())
+      .find_blocked_apps(profile_id.as_uuid())
+      .await?;
+    Ok(apps.into_iter().map(BlockedAppDto::from).collect())
+  }
+
+  /// Replace the apps a profile keeps closed
+  pub async fn set_blocked_apps(
+    db: &Database,
+    profile_id: ProfileId,
+    apps: Vec<BlockedAppRequest>,
+  ) -> Result<Vec<BlockedAppDto>> {
+    if apps.len() > MAX_BLOCKED_APPS {
+      return Err(SmoothieError::ValidationError(format!(
+        "A profile can block at most {} apps",
+        MAX_BLOCKED_APPS
+      )));
+    }
+    let mut bundle_ids = HashSet::new();
+    for app in &apps {
+      validation::validate(app)?;
+      if !bundle_ids.insert(app.bundle_id.trim().to_lowercase()) {
+        return Err(SmoothieError::ValidationError(format!(
+          "{} is listed more than once",
+          app.bundle_id
+        )));
+      }
+    }
+
+    let profile_uuid = profile_id.as_uuid();
+    let repo = ProfileRepository::new(db.pool());
+    repo
+      .find_by_id(profile_uuid)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?;
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
+
+    let apps: Vec<_> = apps
+      .into_iter()
+      .map(|app| {
+        (
+          app.bundle_id.trim().to_string(),
+          app.app_name.trim().to_string(),
+          app.action,
+        )
+      })
+      .collect();
+    let stored = repo.replace_blocked_apps(profile_uuid, &apps).await?;
+
+    tracing::info!(profile_id = %profile_id, count = stored.len(), "Blocked apps updated");
+
+    Ok(stored.into_iter().map(BlockedAppDto::from).collect())
+  }
+
+  /// Let a blocked app run under the active profile, for `duration_minutes`
+  /// or until another profile is activated
+  pub async fn allow_app(
+    state: &AppState,
+    user_id: UserId,
+    bundle_id: &str,
+    duration_minutes: Option<i64>,
+  ) -> Result<BlockedAppOverrideDto> {
+    validation::validate_field("bundleId", validation::not_blank(bundle_id))?;
+    if duration_minutes.is_some_and(|m| !(1..=MAX_OVERRIDE_MINUTES).contains(&m)) {
+      return Err(SmoothieError::ValidationError(format!(
+        "Override must last between 1 and {} minutes",
+        MAX_OVERRIDE_MINUTES
+      )));
+    }
+    let profile_id = ProfileRepository::new(state.db.pool())
+      .find_active_id(user_id.as_uuid())
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("No profile is active".into()))?;
+
+    let bundle_id = bundle_id.trim();
+    let until = state.blocked_app_overrides.allow(
+      profile_id,
+      bundle_id,
+      duration_minutes.map(chrono::Duration::minutes),
+    );
+    tracing::info!(bundle_id = %bundle_id, until = ?until, "Blocked app allowed");
+
+    Ok(BlockedAppOverrideDto {
+      profile_id: profile_id.to_string(),
+      bundle_id: bundle_id.to_string(),
+      until: until.map(|dt| dt.to_rfc3339()),
+    })
+  }
+
+  /// Hide or quit the blocked apps among `launched`, returning the ones handled
+  pub async fn enforce(
+    state: &AppState,
+    profile_id: Uuid,
+    launched: &[&RunningApp],
+  ) -> Result<Vec<(RunningApp, BlockedAppAction)>> {
+    let repo = ProfileRepository::new(state.db.pool());
+    let blocked = repo.find_blocked_apps(profile_id).await?;
+    let targets: Vec<(RunningApp, &ProfileBlockedAppEntity)> = launched
+      .iter()
+      .filter_map(|app| {
+        let entry = blocked
+          .iter()
+          .find(|b| b.bundle_id.eq_ignore_ascii_case(&app.bundle_id))?;
+        let allowed = state
+          .blocked_app_overrides
+          .is_allowed(profile_id, &app.bundle_id);
+        (!allowed).then(|| ((*app).clone(), entry))
+      })
+      .collect();
+    if targets.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    let profile = repo.find_by_id(profile_id).await?;
+    let profile_name = profile.as_ref().map_or("this profile", |p| p.name.as_str());
+    let notify = match &profile {
+      Some(profile) => UserSettingsService::get_settings(&state.db, profile.user_id)
+        .await
+        .map(|s| s.notifications_enabled)
+        .unwrap_or(true),
+      None => true,
+    };
+
+    let mut handled = Vec::new();
+    for (app, entry) in targets {
+      let system = state.system.clone();
+      let target = app.clone();
+      let action = entry.action;
+      let result = tokio::task::spawn_blocking(move || match action {
+        BlockedAppAction::Hide => system.hide_app(&target),
+        BlockedAppAction::Quit => system.quit_app(&target),
+      })
+      .await
+      .map_err(|e| SmoothieError::SystemError(e.to_string()))
+      .and_then(|result| result);
+      if let Err(e) = result {
+        tracing::warn!(bundle_id = %app.bundle_id, "Failed to {} blocked app: {}", action, e);
+        continue;
+      }
+      tracing::info!(bundle_id = %app.bundle_id, action = %action, "Blocked app handled");
+
+      if notify {
+        let verb = match action {
+          BlockedAppAction::Hide => "hidden",
+          BlockedAppAction::Quit => "closed",
+        };
+        let body = format!(
+          "{} is blocked while \"{}\" is active. Allow it in Smoothie to keep using it.",
+          app.name, profile_name
+        );
+        if let Err(e) = state
+          .system
+          .show_notification(&format!("Smoothie: {} {}", app.name, verb), &body)
+        {
+          tracing::warn!("Failed to show blocked app notification: {}", e);
+        }
+      }
+
+      if let Some(profile) = &profile {
+        let _ = AUDIT_SERVICE
+          .log_activity(
+            &state.db,
+            &profile.user_id.to_string(),
+            "app_blocked",
+            Some("profile"),
+            Some(&profile.id.to_string()),
+            Some(&profile.name),
+            Some(json!({
+              "bundle_id": app.bundle_id,
+              "app_name": app.name,
+              "action": action,
+            })),
+            ActivityStatus::Success,
+            None,
+            None,
+          )
+          .await;
+      }
+      handled.push((app, action));
+    }
+
+    Ok(handled)
+  }
+
+  /// Watch running apps and keep the active profile's blocked apps closed
+  pub fn spawn_watcher(state: Arc<AppState>, user_id: Uuid) {
+    let watcher_state = state.clone();
+    state.tasks.spawn("running_app_watcher", move || {
+      Self::watch(watcher_state.clone(), user_id)
+    });
+  }
+
+  async fn watch(state: Arc<AppState>, user_id: Uuid) {
+    let mut ticker = tokio::time::interval(RUNNING_APP_POLL_INTERVAL);
+    let mut detector = LaunchDetector::default();
+    loop {
+      ticker.tick().await;
+      let profile_id = match ProfileRepository::new(state.db.pool())
+        .find_active_id(user_id)
+        .await
+      {
+        Ok(profile_id) => profile_id,
+        Err(e) => {
+          tracing::warn!("Failed to read the active profile: {}", e);
+          continue;
+        }
+      };
+      if profile_id != detector.profile_id {
+        state.blocked_app_overrides.retain_profile(profile_id);
+      }
+
+      let system = state.system.clone();
+      let running = tokio::task::spawn_blocking(move || system.get_running_apps())
+        .await
+        .unwrap_or_default();
+      let launched = detector.observe(profile_id, &running);
+      let Some(profile_id) = profile_id else {
+        continue;
+      };
+      if launched.is_empty() {
+        continue;
+      }
+      if let Err(e) = Self::enforce(&state, profile_id, &launched).await {
+        tracing::warn!("Blocked app enforcement failed: {}", e);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use crate::services::system_backend::MockSystemBackend;
+  use crate::services::SystemBackend;
+
+  #[test]
+  fn test_launch_detector_reports_new_apps_and_everything_on_profile_change() {
+    let system = MockSystemBackend::with_sample_layout();
+    let running = system.get_running_apps();
+    let (work, home) = (Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+    let mut detector = LaunchDetector::default();
+
+    assert_eq!(detector.observe(work, &running).len(), 2);
+    assert!(detector.observe(work, &running).is_empty());
+
+    let safari_only = &running[..1];
+    assert!(detector.observe(work, safari_only).is_empty());
+    let relaunched = detector.observe(work, &running);
+    assert_eq!(relaunched.len(), 1);
+    assert_eq!(relaunched[0].name, "Terminal");
+
+    assert_eq!(detector.observe(home, &running).len(), 2);
+  }
+
+  #[tokio::test]
+  async fn test_blocked_apps_are_handled_unless_allowed() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+    let (profile_id,): (Uuid,) = sqlx::query_as(
+      "INSERT INTO profiles (user_id, name, type, is_active) VALUES ($1, 'Work', 'work', true) RETURNING id",
+    )
+    .bind(user_id)
+    .fetch_one(test_db.pool())
+    .await
+    .unwrap();
+
+    let block = |bundle_id: &str, action| BlockedAppRequest {
+      bundle_id: bundle_id.into(),
+      app_name: bundle_id.trim_start_matches("com.apple.").into(),
+      action,
+    };
+    AppBlocklistService::set_blocked_apps(
+      &test_db.db,
+      profile_id.into(),
+      vec![
+        block("com.apple.Safari", BlockedAppAction::Hide),
+        block("com.apple.Terminal", BlockedAppAction::Quit),
+      ],
+    )
+    .await
+    .unwrap();
+
+    let system = Arc::new(MockSystemBackend::with_sample_layout());
+    let state = AppState::with_system_backend(Arc::new(test_db.db.clone()), system.clone());
+    AppBlocklistService::allow_app(&state, user_id.into(), "com.apple.terminal", None)
+      .await
+      .unwrap();
+
+    let running = system.get_running_apps();
+    let launched: Vec<_> = running.iter().collect();
+    let handled = AppBlocklistService::enforce(&state, profile_id, &launched)
+      .await
+      .unwrap();
+    assert_eq!(handled.len(), 1);
+    assert_eq!(handled[0].1, BlockedAppAction::Hide);
+
+    let running = system.get_running_apps();
+    assert!(running
+      .iter()
+      .any(|app| app.name == "Safari" && app.is_hidden));
+    assert!(running.iter().any(|app| app.name == "Terminal"));
+    assert_eq!(system.notifications().len(), 1);
+  }
+}
@@ -0,0 +1,173 @@
+//! Whether a profile's apps are still installed
+//!
+//! Apps get uninstalled, renamed or swapped for a beta long after they were
+//! added to a profile, and `open -b` for an app that's gone fails without a
+//! word. Before launching, each app is looked up by its bundle path, then in
+//! the installed-app index; a missing one is reported with the installed
+//! apps most like it, so it can be replaced in one call.
+
+use crate::models::AppDto;
+use crate::services::InstalledApp;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Most substitutes suggested for a missing app
+const MAX_SUGGESTIONS: usize = 3;
+
+/// A profile app that isn't installed any more
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingAppDto {
+  pub app_id: String,
+  pub name: String,
+  pub bundle_id: String,
+  /// Installed apps most like the missing one, best match first
+  pub suggestions: Vec<InstalledApp>,
+}
+
+/// Whether the app's bundle is still where it was saved, which needs no index
+pub fn bundle_exists(app: &AppDto) -> bool {
+  app
+    .exe_path
+    .as_deref()
+    .is_some_and(|path| Path::new(path).exists())
+}
+
+/// Whether `installed` has the app, by bundle ID or bundle path. An empty
+/// index means the scan found nothing, not that nothing is installed.
+pub fn is_installed(app: &AppDto, installed: &[InstalledApp]) -> bool {
+  installed.is_empty()
+    || installed
+      .iter()
+      .any(|i| i.bundle_id == app.bundle_id || app.exe_path.as_deref() == Some(i.path.as_str()))
+}
+
+fn words(name: &str) -> HashSet<String> {
+  name
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|w| !w.is_empty())
+    .map(str::to_lowercase)
+    .collect()
+}
+
+/// The vendor part of a bundle ID, "com.google" for "com.google.Chrome"
+fn vendor(bundle_id: &str) -> Option<&str> {
+  let mut dots = bundle_id.match_indices('.');
+  let (second, _) = dots.nth(1)?;
+  Some(&bundle_id[..second])
+}
+
+/// How much `candidate` looks like a replacement for `app`; 0 when not at all
+fn similarity(app: &AppDto, candidate: &InstalledApp) -> usize {
+  let shared = words(&app.name)
+    .intersection(&words(&candidate.name))
+    .count();
+  let same_vendor = vendor(&app.bundle_id).is_some_and(|v| vendor(&candidate.bundle_id) == Some(v));
+  let prefix = candidate
+    .bundle_id
+    .to_lowercase()
+    .starts_with(&app.bundle_id.to_lowercase());
+  shared * 2 + usize::from(same_vendor) + usize::from(prefix) * 2
+}
+
+/// Installed apps that could stand in for `app`, best first
+pub fn suggest_substitutes(app: &AppDto, installed: &[InstalledApp]) -> Vec<InstalledApp> {
+  let mut scored: Vec<(usize, &InstalledApp)> = installed
+    .iter()
+    .filter(|i| i.bundle_id != app.bundle_id)
+    .map(|i| (similarity(app, i), i))
+    .filter(|(score, _)| *score > 0)
+    .collect();
+  scored.sort_by(|(a, a_app), (b, b_app)| b.cmp(a).then_with(|| a_app.name.cmp(&b_app.name)));
+  scored
+    .into_iter()
+    .take(MAX_SUGGESTIONS)
+    .map(|(_, i)| i.clone())
+    .collect()
+}
+
+/// The apps of `apps` that aren't installed, with what could replace them
+pub fn find_missing(apps: &[AppDto], installed: &[InstalledApp]) -> Vec<MissingAppDto> {
+  apps
+    .iter()
+    .filter(|app| !bundle_exists(app) && !is_installed(app, installed))
+    .map(|app| MissingAppDto {
+      app_id: app.id.clone(),
+      name: app.name.clone(),
+      bundle_id: app.bundle_id.clone(),
+      suggestions: suggest_substitutes(app, installed),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn installed(name: &str, bundle_id: &str) -> InstalledApp {
+    InstalledApp {
+      name: name.to_string(),
+      bundle_id: bundle_id.to_string(),
+      path: format!("/Applications/{}.app", name),
+      version: None,
+      category: None,
+    }
+  }
+
+  fn app(name: &str, bundle_id: &str) -> AppDto {
+    AppDto {
+      id: bundle_id.to_string(),
+      profile_id: String::new(),
+      name: name.to_string(),
+      bundle_id: bundle_id.to_string(),
+      exe_path: Some(format!("/Applications/{}.app", name)),
+      launch_on_activate: true,
+      monitor_preference: None,
+      created_at: String::new(),
+      updated_at: None,
+      icon_path: None,
+      launch_args: None,
+      working_directory: None,
+      startup_delay_ms: 0,
+      order_index: 0,
+    }
+  }
+
+  #[test]
+  fn test_missing_apps_get_close_installed_matches() {
+    let index = vec![
+      installed("Safari", "com.apple.Safari"),
+      installed("Google Chrome Beta", "com.google.Chrome.beta"),
+      installed("Google Drive", "com.google.drivefs"),
+      installed(
+        "Visual Studio Code - Insiders",
+        "com.microsoft.VSCodeInsiders",
+      ),
+    ];
+    let apps = vec![
+      app("Safari", "com.apple.Safari"),
+      app("Google Chrome", "com.google.Chrome"),
+      app("Visual Studio Code", "com.microsoft.VSCode"),
+      app("Tweetbot", "com.tapbots.Tweetbot3Mac"),
+    ];
+
+    let missing = find_missing(&apps, &index);
+    let names: Vec<_> = missing.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, ["Google Chrome", "Visual Studio Code", "Tweetbot"]);
+
+    let suggested = |i: usize| -> Vec<&str> {
+      missing[i]
+        .suggestions
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect()
+    };
+    assert_eq!(suggested(0), ["Google Chrome Beta", "Google Drive"]);
+    assert_eq!(suggested(1), ["Visual Studio Code - Insiders"]);
+    assert!(suggested(2).is_empty());
+
+    // A failed scan doesn't make every app missing
+    assert!(find_missing(&apps, &[]).is_empty());
+  }
+}
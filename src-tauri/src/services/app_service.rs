@@ -3,12 +3,32 @@
 use crate::{
   db::Database,
   error::{Result, SmoothieError},
-  models::dto::AppDto,
-  repositories::AppRepository,
+  logging::{APP_LAUNCH_DURATION, METRICS},
+  models::dto::{AppDto, CreateAppRequest},
+  models::ActivityStatus,
+  repositories::{AppRepository, ProfilePart},
+  services::app_presence::{self, MissingAppDto},
+  services::audit_backend::audit_store,
+  services::change_log::{ChangeLog, ChangeSubject},
+  services::fix_service::FailureCode,
+  services::profile_lock::ProfileLock,
+  services::window_service::{WindowRect, WindowService},
+  services::{InstalledApp, SystemBackend, SystemWindow},
+  utils::validation,
 };
+use std::collections::HashMap;
 use std::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+/// How long to wait for a launched app to show its first window
+const WINDOW_WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+const WINDOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How far a moved window may land from its target (snapping, minimum sizes)
+/// and still count as positioned
+const FRAME_TOLERANCE: i32 = 8;
+
 /// Helper to parse UUID from string
 fn parse_uuid(s: &str) -> Result<Uuid> {
   Uuid::parse_str(s).map_err(|_| SmoothieError::ValidationError(format!("Invalid UUID: {}", s)))
@@ -21,8 +41,50 @@ pub struct AppService;
 #[serde(rename_all = "camelCase")]
 pub struct LaunchResult {
   pub name: String,
+  pub bundle_id: String,
   pub success: bool,
   pub message: String,
+  /// Whether the app's windows appeared and were moved to their saved frames
+  pub window_positioned: bool,
+  /// The profile app launched, when it was one
+  pub app_id: Option<String>,
+  /// Set when the launch failed in a way `get_fixes` has actions for
+  pub failure_code: Option<FailureCode>,
+}
+
+impl LaunchResult {
+  /// The result for an app that wasn't launched because it's gone
+  fn not_installed(missing: &MissingAppDto) -> Self {
+    let mut message = format!("{} isn't installed", missing.name);
+    if !missing.suggestions.is_empty() {
+      let names: Vec<_> = missing
+        .suggestions
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect();
+      message.push_str(&format!("; similar installed apps: {}", names.join(", ")));
+    }
+    Self {
+      name: missing.name.clone(),
+      bundle_id: missing.bundle_id.clone(),
+      success: false,
+      message,
+      window_positioned: false,
+      app_id: Some(missing.app_id.clone()),
+      failure_code: Some(FailureCode::AppNotInstalled),
+    }
+  }
+}
+
+/// Outcome of waiting for a launched app's windows and placing them
+#[derive(Debug, Clone, Default)]
+pub struct WindowHandshake {
+  /// Process owning the windows, once any appeared
+  pub pid: Option<u32>,
+  /// Time from the start of the wait until the first window appeared
+  pub ready_after: Option<Duration>,
+  /// Every window that was moved is now at its target frame
+  pub positioned: bool,
 }
 
 impl AppService {
@@ -30,47 +92,43 @@ impl AppService {
     db: &Database,
     profile_id: &str,
     user_id: &str,
-    name: String,
-    bundle_id: String,
-    exe_path: Option<String>,
-    launch_on_activate: bool,
-    monitor_preference: Option<i32>,
-    startup_delay_ms: Option<i32>,
-    order_index: Option<i32>,
+    req: CreateAppRequest,
   ) -> Result<AppDto> {
+    validation::validate(&req)?;
     let profile_uuid = parse_uuid(profile_id)?;
     let user_uuid = parse_uuid(user_id)?;
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
     let repo = AppRepository::new(db.pool());
 
     let entity = repo
       .create(
         profile_uuid,
-        &name,
-        &bundle_id,
-        exe_path.as_deref(),
-        launch_on_activate,
-        monitor_preference,
-        startup_delay_ms,
-        order_index,
+        &req.name,
+        &req.bundle_id,
+        req.exe_path.as_deref(),
+        req.launch_on_activate,
+        req.monitor_preference,
+        req.startup_delay_ms,
+        req.order_index,
       )
       .await?;
 
     // Log the app creation activity
-    let audit_repo = crate::repositories::AuditRepository::new(db.pool());
-    let _ = audit_repo
+    let audit = audit_store(db);
+    let _ = audit
       .log_activity(
         user_uuid,
         None, // session_id
         "app_created",
         Some("app"),
         Some(entity.id),
-        Some(&name),
+        Some(&req.name),
         Some(serde_json::json!({
-          "bundle_id": bundle_id,
+          "bundle_id": req.bundle_id,
           "profile_id": profile_id,
-          "launch_on_activate": launch_on_activate
+          "launch_on_activate": req.launch_on_activate
         })),
-        "success",
+        ActivityStatus::Success,
         None,
         None,
       )
@@ -101,21 +159,129 @@ impl AppService {
     launch_on_activate: Option<bool>,
   ) -> Result<AppDto> {
     let app_uuid = parse_uuid(app_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::App, app_uuid).await?;
     let repo = AppRepository::new(db.pool());
+    let before = repo
+      .find_by_id(app_uuid)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("App not found".into()))?;
 
     let entity = repo.update(app_uuid, launch_on_activate).await?;
-    Ok(AppDto::from(entity))
+    let subject = ChangeSubject {
+      entity_type: "app",
+      entity_id: app_uuid,
+      profile_id: entity.profile_id,
+    };
+    let updated = AppDto::from(entity);
+    ChangeLog::record_update(db, subject, &AppDto::from(before), &updated).await;
+    Ok(updated)
+  }
+
+  /// Put the profile's apps in the order of `app_ids` and return the new ordering
+  pub async fn reorder_apps(
+    db: &Database,
+    profile_id: &str,
+    app_ids: &[String],
+  ) -> Result<Vec<AppDto>> {
+    let profile_uuid = parse_uuid(profile_id)?;
+    let ids = app_ids
+      .iter()
+      .map(|id| parse_uuid(id))
+      .collect::<Result<Vec<_>>>()?;
+    validation::validate_field("app_ids", validation::distinct_ids(&ids))?;
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
+
+    if !AppRepository::new(db.pool())
+      .reorder(profile_uuid, &ids)
+      .await?
+    {
+      return Err(SmoothieError::NotFound(
+        "App not found in this profile".into(),
+      ));
+    }
+    Self::get_apps(db, profile_id).await
+  }
+
+  /// Scan for installed apps off the async runtime
+  pub async fn scan_installed_apps(system: Arc<dyn SystemBackend>) -> Vec<InstalledApp> {
+    tokio::task::spawn_blocking(move || system.get_installed_apps())
+      .await
+      .unwrap_or_default()
+  }
+
+  /// The profile's apps that aren't in `installed`, with suggested substitutes
+  pub async fn get_missing_apps(
+    db: &Database,
+    profile_id: &str,
+    installed: &[InstalledApp],
+  ) -> Result<Vec<MissingAppDto>> {
+    let apps = Self::get_apps(db, profile_id).await?;
+    Ok(app_presence::find_missing(&apps, installed))
+  }
+
+  /// Swap a profile app for the installed app with `bundle_id`. The app keeps
+  /// its place, launch settings and saved windows.
+  pub async fn replace_app(
+    db: &Database,
+    app_id: &str,
+    bundle_id: &str,
+    installed: &[InstalledApp],
+  ) -> Result<AppDto> {
+    let app_uuid = parse_uuid(app_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::App, app_uuid).await?;
+    let replacement = installed
+      .iter()
+      .find(|i| i.bundle_id == bundle_id)
+      .ok_or_else(|| SmoothieError::NotFound(format!("{} isn't installed", bundle_id)))?;
+    let repo = AppRepository::new(db.pool());
+    let before = repo
+      .find_by_id(app_uuid)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("App not found".into()))?;
+
+    let entity = repo
+      .replace(
+        app_uuid,
+        &replacement.name,
+        &replacement.bundle_id,
+        Some(&replacement.path),
+      )
+      .await?;
+    tracing::info!(
+      from = %before.bundle_id,
+      to = %entity.bundle_id,
+      "Replaced profile app"
+    );
+
+    let subject = ChangeSubject {
+      entity_type: "app",
+      entity_id: app_uuid,
+      profile_id: entity.profile_id,
+    };
+    let updated = AppDto::from(entity);
+    ChangeLog::record_update(db, subject, &AppDto::from(before), &updated).await;
+    Ok(updated)
   }
 
   pub async fn delete_app(db: &Database, app_id: &str) -> Result<()> {
     let app_uuid = parse_uuid(app_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::App, app_uuid).await?;
     let repo = AppRepository::new(db.pool());
+    let existing = repo.find_by_id(app_uuid).await?;
 
     let deleted = repo.delete(app_uuid).await?;
     if !deleted {
       return Err(SmoothieError::NotFound("App not found".into()));
     }
 
+    if let Some(app) = existing {
+      let subject = ChangeSubject {
+        entity_type: "app",
+        entity_id: app_uuid,
+        profile_id: app.profile_id,
+      };
+      ChangeLog::record_delete(db, subject, &AppDto::from(app)).await;
+    }
     Ok(())
   }
 
@@ -129,29 +295,155 @@ impl AppService {
     match result {
       Ok(_) => LaunchResult {
         name: name.to_string(),
+        bundle_id: bundle_id.to_string(),
         success: true,
         message: format!("Launched {}", name),
+        window_positioned: false,
+        app_id: None,
+        failure_code: None,
       },
       Err(e) => {
         tracing::error!("Failed to launch {}: {}", name, e);
         LaunchResult {
           name: name.to_string(),
+          bundle_id: bundle_id.to_string(),
           success: false,
           message: format!("Failed to launch: {}", e),
+          window_positioned: false,
+          app_id: None,
+          failure_code: None,
         }
       }
     }
   }
 
-  /// Launch all launchable apps for a profile
+  /// Wait for an app's windows to appear, move them to `targets` in order and
+  /// read them back to confirm they landed there.
+  ///
+  /// Gives up after `timeout` without windows. With no targets there's nothing
+  /// to place, so `positioned` stays false.
+  pub async fn position_launched_windows(
+    system: Arc<dyn SystemBackend>,
+    bundle_id: &str,
+    targets: &[WindowRect],
+    timeout: Duration,
+  ) -> WindowHandshake {
+    let started = Instant::now();
+    let windows = loop {
+      let windows = app_windows(system.clone(), bundle_id).await;
+      if !windows.is_empty() {
+        break windows;
+      }
+      if started.elapsed() >= timeout {
+        tracing::warn!(bundle_id = %bundle_id, "No window appeared after launch");
+        return WindowHandshake::default();
+      }
+      tokio::time::sleep(WINDOW_POLL_INTERVAL).await;
+    };
+    let mut handshake = WindowHandshake {
+      pid: Some(windows[0].pid),
+      ready_after: Some(started.elapsed()),
+      positioned: false,
+    };
+    if targets.is_empty() {
+      return handshake;
+    }
+
+    let moves: Vec<(SystemWindow, WindowRect)> =
+      windows.into_iter().zip(targets.iter().copied()).collect();
+    for (window, rect) in &moves {
+      let system = system.clone();
+      let (window, rect) = (window.clone(), *rect);
+      let moved = tokio::task::spawn_blocking(move || {
+        system.move_window(&window, rect.x, rect.y, rect.width, rect.height)
+      })
+      .await
+      .map_err(|e| SmoothieError::SystemError(format!("Window move failed: {}", e)))
+      .and_then(|r| r);
+      if let Err(e) = moved {
+        tracing::warn!(bundle_id = %bundle_id, error = %e, "Could not position window");
+        return handshake;
+      }
+    }
+
+    let placed = app_windows(system, bundle_id).await;
+    handshake.positioned = moves.iter().all(|(window, rect)| {
+      placed
+        .iter()
+        .any(|w| w.window_id == window.window_id && lands_on(w, rect))
+    });
+    handshake
+  }
+
+  /// Apply zoom hints to an app's windows, paired with them in the order
+  /// `position_launched_windows` moves them. Returns how many were zoomed.
+  pub async fn zoom_launched_windows(
+    system: Arc<dyn SystemBackend>,
+    bundle_id: &str,
+    zooms: &[Option<i32>],
+  ) -> usize {
+    if zooms.iter().all(Option::is_none) {
+      return 0;
+    }
+    let windows = app_windows(system.clone(), bundle_id).await;
+    let mut zoomed = 0;
+    for (window, steps) in windows.into_iter().zip(zooms.iter().copied()) {
+      let Some(steps) = steps else {
+        continue;
+      };
+      let system = system.clone();
+      let result = tokio::task::spawn_blocking(move || system.zoom_window(&window, steps))
+        .await
+        .map_err(|e| SmoothieError::SystemError(format!("Window zoom failed: {}", e)))
+        .and_then(|r| r);
+      match result {
+        Ok(()) => zoomed += 1,
+        Err(e) => tracing::warn!(bundle_id = %bundle_id, error = %e, "Could not zoom window"),
+      }
+    }
+    zoomed
+  }
+
+  /// Launch all launchable apps for a profile, placing each app's windows at
+  /// their saved frames once they appear
   pub async fn launch_profile_apps(
     db: &Database,
+    system: Arc<dyn SystemBackend>,
     profile_id: &str,
     user_id: &str,
   ) -> Result<Vec<LaunchResult>> {
     let apps = Self::get_launchable_apps(db, profile_id).await?;
     let mut results = Vec::new();
 
+    // Apps whose saved bundle is gone are looked up in the installed-app
+    // index, which is slow to build, so only then
+    let missing = if apps.iter().all(app_presence::bundle_exists) {
+      Vec::new()
+    } else {
+      let installed = Self::scan_installed_apps(system.clone()).await;
+      app_presence::find_missing(&apps, &installed)
+    };
+
+    let mut saved_frames: HashMap<String, Vec<WindowRect>> = HashMap::new();
+    let mut saved_zooms: HashMap<String, Vec<Option<i32>>> = HashMap::new();
+    for window in
+      WindowService::get_windows_on_live_displays(db, system.clone(), profile_id).await?
+    {
+      saved_frames
+        .entry(window.app_id.clone())
+        .or_default()
+        .push(WindowRect {
+          x: window.x,
+          y: window.y,
+          width: window.width,
+          height: window.height,
+        });
+      saved_zooms
+        .entry(window.app_id)
+        .or_default()
+        .push(window.zoom_steps);
+    }
+
     let profile_uuid = parse_uuid(profile_id)?;
     let user_uuid = parse_uuid(user_id)?;
     let audit_repo = crate::repositories::AuditRepository::new(db.pool());
@@ -165,7 +457,33 @@ impl AppService {
 
     for app in apps {
       let app_uuid = parse_uuid(&app.id)?;
-      let result = Self::launch_app_by_bundle_id(&app.bundle_id, &app.name);
+      let mut result = match missing.iter().find(|m| m.app_id == app.id) {
+        Some(missing) => {
+          tracing::warn!(app = %app.name, "App isn't installed; not launching it");
+          LaunchResult::not_installed(missing)
+        }
+        None => Self::launch_app_by_bundle_id(&app.bundle_id, &app.name),
+      };
+      result.app_id = Some(app.id.clone());
+      let handshake = if result.success {
+        let targets = saved_frames.get(&app.id).map_or(&[][..], Vec::as_slice);
+        Self::position_launched_windows(
+          system.clone(),
+          &app.bundle_id,
+          targets,
+          WINDOW_WAIT_TIMEOUT,
+        )
+        .await
+      } else {
+        WindowHandshake::default()
+      };
+      result.window_positioned = handshake.positioned;
+      if let (Some(_), Some(zooms)) = (handshake.pid, saved_zooms.get(&app.id)) {
+        Self::zoom_launched_windows(system.clone(), &app.bundle_id, zooms).await;
+      }
+      if let Some(ready_after) = handshake.ready_after {
+        METRICS.observe(APP_LAUNCH_DURATION, ready_after);
+      }
 
       // Log the app launch
       let _ = audit_repo
@@ -183,9 +501,9 @@ impl AppService {
           } else {
             Some(&result.message)
           },
-          None,  // pid - could be captured if needed
-          None,  // launch_duration_ms - could be measured
-          false, // window_positioned - will be set when windows are positioned
+          handshake.pid.map(|pid| pid as i32),
+          handshake.ready_after.map(|d| d.as_millis() as i32),
+          handshake.positioned,
         )
         .await;
 
@@ -197,3 +515,87 @@ impl AppService {
     Ok(results)
   }
 }
+
+/// On-screen, normal-layer windows of the app with `bundle_id`
+async fn app_windows(system: Arc<dyn SystemBackend>, bundle_id: &str) -> Vec<SystemWindow> {
+  let bundle_id = bundle_id.to_string();
+  tokio::task::spawn_blocking(move || {
+    system
+      .get_windows()
+      .into_iter()
+      .filter(|w| w.bundle_id == bundle_id && w.layer == 0 && w.is_visible())
+      .collect()
+  })
+  .await
+  .unwrap_or_default()
+}
+
+fn lands_on(window: &SystemWindow, rect: &WindowRect) -> bool {
+  (window.x - rect.x).abs() <= FRAME_TOLERANCE
+    && (window.y - rect.y).abs() <= FRAME_TOLERANCE
+    && (window.width - rect.width).abs() <= FRAME_TOLERANCE
+    && (window.height - rect.height).abs() <= FRAME_TOLERANCE
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::services::system_backend::MockSystemBackend;
+
+  #[tokio::test]
+  async fn test_launch_handshake_positions_windows() {
+    let system = Arc::new(MockSystemBackend::with_sample_layout());
+    let target = WindowRect {
+      x: 200,
+      y: 100,
+      width: 900,
+      height: 600,
+    };
+
+    let handshake = AppService::position_launched_windows(
+      system.clone(),
+      "com.apple.Terminal",
+      &[target],
+      Duration::from_millis(50),
+    )
+    .await;
+    assert!(handshake.positioned);
+    assert_eq!(handshake.pid, Some(502));
+    let terminal = system
+      .get_windows()
+      .into_iter()
+      .find(|w| w.bundle_id == "com.apple.Terminal")
+      .unwrap();
+    assert!(lands_on(&terminal, &target));
+
+    // Windows appeared but there was nowhere saved to put them
+    let handshake = AppService::position_launched_windows(
+      system.clone(),
+      "com.apple.Safari",
+      &[],
+      Duration::ZERO,
+    )
+    .await;
+    assert_eq!(handshake.pid, Some(501));
+    assert!(!handshake.positioned);
+
+    let zoomed =
+      AppService::zoom_launched_windows(system.clone(), "com.apple.Safari", &[Some(3)]).await;
+    assert_eq!(zoomed, 1);
+    assert_eq!(system.zoomed_windows(), vec![(101, 3)]);
+    let zoomed =
+      AppService::zoom_launched_windows(system.clone(), "com.apple.Safari", &[None]).await;
+    assert_eq!(zoomed, 0);
+
+    // The app never showed a window
+    let handshake = AppService::position_launched_windows(
+      system,
+      "com.example.Missing",
+      &[target],
+      Duration::from_millis(50),
+    )
+    .await;
+    assert!(handshake.pid.is_none());
+    assert!(!handshake.positioned);
+  }
+}
@@ -0,0 +1,283 @@
+// Audit backend selection
+//
+// Audit writes always go to the local database through the write queue, which
+// holds the sessions and activations other local tables reference, and every
+// read comes from there. With `SMOOTHIE_AUDIT_BACKEND=supabase` each record is
+// also appended to the Supabase project's `audit_events` table as the
+// signed-in user, for an audit trail that lives off the machine.
+
+use crate::{
+  db::{supabase::SupabaseClient, Database},
+  error::{Result, SmoothieError},
+  models::entities::{
+    ActivityLogEntity, AuditEvent, ErrorLogEntity, ProfileActivationEntity, SessionEntity,
+    SystemEventEntity,
+  },
+  models::enums::{ActivationSource, ActivityStatus, Severity},
+  repositories::{AuditStore, SupabaseAuditRepository},
+  services::audit_queue::QueuedAuditStore,
+};
+use async_trait::async_trait;
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+pub const AUDIT_BACKEND_ENV: &str = "SMOOTHIE_AUDIT_BACKEND";
+
+static SUPABASE_AUDIT: OnceLock<SupabaseAuditRepository> = OnceLock::new();
+
+pub enum AuditBackend {
+  Postgres,
+  Supabase(SupabaseAuditRepository),
+}
+
+impl AuditBackend {
+  /// The backend named by `SMOOTHIE_AUDIT_BACKEND`, Postgres when unset.
+  /// Supabase needs a signed-in user so its rows are scoped by policy.
+  pub fn from_env() -> Result<Self> {
+    let name = std::env::var(AUDIT_BACKEND_ENV).unwrap_or_default();
+    match name.trim().to_ascii_lowercase().as_str() {
+      "" | "postgres" => Ok(Self::Postgres),
+      "supabase" => SupabaseClient::from_env()
+        .filter(SupabaseClient::is_signed_in)
+        .map(|client| Self::Supabase(SupabaseAuditRepository::new(client)))
+        .ok_or_else(|| {
+          SmoothieError::ValidationError(format!(
+            "{}=supabase requires {}, {} and {}",
+            AUDIT_BACKEND_ENV,
+            crate::db::supabase::SUPABASE_URL_ENV,
+            crate::db::supabase::SUPABASE_KEY_ENV,
+            crate::db::supabase::SUPABASE_REFRESH_TOKEN_ENV
+          ))
+        }),
+      other => Err(SmoothieError::ValidationError(format!(
+        "Unknown audit backend: {}",
+        other
+      ))),
+    }
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Postgres => "postgres",
+      Self::Supabase(_) => "supabase",
+    }
+  }
+
+  /// Make this the backend [`audit_store`] hands out; only the first call counts
+  pub fn install(self) {
+    tracing::info!(backend = self.name(), "Audit backend selected");
+    if let Self::Supabase(repo) = self {
+      let _ = SUPABASE_AUDIT.set(repo);
+    }
+  }
+}
+
+/// The store audit writes should go to
+pub fn audit_store(db: &Database) -> Box<dyn AuditStore + '_> {
+  let local = QueuedAuditStore::new(db.pool());
+  match SUPABASE_AUDIT.get() {
+    Some(remote) => Box::new(MirroredAuditStore { local, remote }),
+    None => Box::new(local),
+  }
+}
+
+/// The Supabase audit copy, when it is the selected backend
+pub fn supabase_audit() -> Option<&'static SupabaseAuditRepository> {
+  SUPABASE_AUDIT.get()
+}
+
+/// Writes to Postgres, then appends what was written to Supabase in the
+/// background. A failed append is logged; the local record stands.
+struct MirroredAuditStore<'a> {
+  local: QueuedAuditStore<'a>,
+  remote: &'static SupabaseAuditRepository,
+}
+
+impl MirroredAuditStore<'_> {
+  fn mirror(&self, event: AuditEvent) {
+    let remote = self.remote.clone();
+    tokio::spawn(async move {
+      if let Err(e) = remote.append(event).await {
+        tracing::warn!("Failed to copy audit event to Supabase: {}", e);
+      }
+    });
+  }
+}
+
+#[async_trait]
+impl AuditStore for MirroredAuditStore<'_> {
+  async fn log_activity(
+    &self,
+    user_id: Uuid,
+    session_id: Option<Uuid>,
+    action: &str,
+    entity_type: Option<&str>,
+    entity_id: Option<Uuid>,
+    entity_name: Option<&str>,
+    details: Option<serde_json::Value>,
+    status: ActivityStatus,
+    error_message: Option<&str>,
+    duration_ms: Option<i32>,
+  ) -> Result<ActivityLogEntity> {
+    let entity = self
+      .local
+      .log_activity(
+        user_id,
+        session_id,
+        action,
+        entity_type,
+        entity_id,
+        entity_name,
+        details,
+        status,
+        error_message,
+        duration_ms,
+      )
+      .await?;
+    self.mirror(AuditEvent::Activity(entity.clone()));
+    Ok(entity)
+  }
+
+  async fn log_system_event(
+    &self,
+    event_type: &str,
+    severity: Severity,
+    source: &str,
+    message: &str,
+    details: Option<serde_json::Value>,
+    stack_trace: Option<&str>,
+    os_info: Option<serde_json::Value>,
+    app_version: Option<&str>,
+  ) -> Result<SystemEventEntity> {
+    let entity = self
+      .local
+      .log_system_event(
+        event_type,
+        severity,
+        source,
+        message,
+        details,
+        stack_trace,
+        os_info,
+        app_version,
+      )
+      .await?;
+    self.mirror(AuditEvent::SystemEvent(entity.clone()));
+    Ok(entity)
+  }
+
+  async fn start_session(
+    &self,
+    user_id: Uuid,
+    device_id: Option<&str>,
+    device_name: Option<&str>,
+    device_type: Option<&str>,
+    os_name: Option<&str>,
+    os_version: Option<&str>,
+    app_version: Option<&str>,
+    metadata: Option<serde_json::Value>,
+  ) -> Result<SessionEntity> {
+    let entity = self
+      .local
+      .start_session(
+        user_id,
+        device_id,
+        device_name,
+        device_type,
+        os_name,
+        os_version,
+        app_version,
+        metadata,
+      )
+      .await?;
+    self.mirror(AuditEvent::Session(entity.clone()));
+    Ok(entity)
+  }
+
+  async fn end_session(&self, session_id: Uuid, reason: &str) -> Result<SessionEntity> {
+    let entity = self.local.end_session(session_id, reason).await?;
+    self.mirror(AuditEvent::Session(entity.clone()));
+    Ok(entity)
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  async fn record_profile_activation(
+    &self,
+    user_id: Uuid,
+    profile_id: Uuid,
+    session_id: Option<Uuid>,
+    activation_source: ActivationSource,
+    previous_profile_id: Option<Uuid>,
+    monitors_detected: Option<i32>,
+    monitors_applied: Option<i32>,
+    apps_detected: Option<i32>,
+    apps_launched: Option<i32>,
+    apps_failed: Option<i32>,
+    tabs_detected: Option<i32>,
+    tabs_opened: Option<i32>,
+    windows_restored: Option<i32>,
+    duration_ms: Option<i32>,
+    success: bool,
+    error_message: Option<&str>,
+    metadata: Option<serde_json::Value>,
+  ) -> Result<ProfileActivationEntity> {
+    let entity = self
+      .local
+      .record_profile_activation(
+        user_id,
+        profile_id,
+        session_id,
+        activation_source,
+        previous_profile_id,
+        monitors_detected,
+        monitors_applied,
+        apps_detected,
+        apps_launched,
+        apps_failed,
+        tabs_detected,
+        tabs_opened,
+        windows_restored,
+        duration_ms,
+        success,
+        error_message,
+        metadata,
+      )
+      .await?;
+    self.mirror(AuditEvent::ProfileActivation(entity.clone()));
+    Ok(entity)
+  }
+
+  async fn log_error(
+    &self,
+    user_id: Option<Uuid>,
+    session_id: Option<Uuid>,
+    error_code: Option<&str>,
+    error_type: &str,
+    message: &str,
+    stack_trace: Option<&str>,
+    context: Option<serde_json::Value>,
+    source_file: Option<&str>,
+    source_line: Option<i32>,
+    source_function: Option<&str>,
+    severity: Severity,
+  ) -> Result<ErrorLogEntity> {
+    let entity = self
+      .local
+      .log_error(
+        user_id,
+        session_id,
+        error_code,
+        error_type,
+        message,
+        stack_trace,
+        context,
+        source_file,
+        source_line,
+        source_function,
+        severity,
+      )
+      .await?;
+    self.mirror(AuditEvent::Error(entity.clone()));
+    Ok(entity)
+  }
+}
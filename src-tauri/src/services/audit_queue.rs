@@ -0,0 +1,654 @@
+//! Audit Write Queue
+//!
+//! Activity logs and system events are written on the hot path of most
+//! handlers. Instead of awaiting one INSERT per entry, they are pushed onto a
+//! bounded in-memory queue and a background task writes them in multi-row
+//! batches, either when a batch fills up or on a short interval.
+//!
+//! Entities are built (id and timestamp included) before they are queued, so
+//! callers still get a complete record back immediately. When the queue is
+//! full the configured [`OverflowPolicy`] applies; error and critical system
+//! events always wait for space rather than being dropped.
+
+use crate::error::Result;
+use crate::logging::current_request_id;
+use crate::models::entities::{
+  ActivityLogEntity, ErrorLogEntity, ProfileActivationEntity, SessionEntity, SystemEventEntity,
+};
+use crate::models::enums::{ActivationSource, ActivityStatus, Severity};
+use crate::repositories::{AuditRepository, AuditStore};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+static AUDIT_QUEUE: OnceLock<AuditQueue> = OnceLock::new();
+
+/// What to do with a new entry when the queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+  /// Discard the entry and count it as dropped
+  DropNewest,
+  /// Wait for the writer to make room
+  Block,
+}
+
+/// What became of an entry passed to [`AuditQueue::push`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+  Queued,
+  /// Discarded under [`OverflowPolicy::DropNewest`]
+  Dropped,
+  /// The writer has stopped; the caller has to write the entry itself
+  Closed,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditQueueConfig {
+  pub capacity: usize,
+  pub batch_size: usize,
+  pub flush_interval: Duration,
+  pub overflow: OverflowPolicy,
+}
+
+impl Default for AuditQueueConfig {
+  fn default() -> Self {
+    Self {
+      capacity: 1024,
+      batch_size: 100,
+      flush_interval: Duration::from_millis(500),
+      overflow: OverflowPolicy::DropNewest,
+    }
+  }
+}
+
+/// Counters since the queue was started
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditQueueStats {
+  pub queued: u64,
+  pub written: u64,
+  pub dropped: u64,
+  pub failed: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+  queued: AtomicU64,
+  written: AtomicU64,
+  dropped: AtomicU64,
+  failed: AtomicU64,
+}
+
+#[derive(Debug, Clone)]
+pub enum AuditRecord {
+  Activity(ActivityLogEntity),
+  SystemEvent(SystemEventEntity),
+}
+
+impl AuditRecord {
+  /// Entries that must not be lost to overflow
+  fn is_critical(&self) -> bool {
+    matches!(self, AuditRecord::SystemEvent(e) if e.severity.is_error())
+  }
+}
+
+enum Command {
+  Record(Box<AuditRecord>),
+  Flush(oneshot::Sender<()>),
+  Shutdown(oneshot::Sender<()>),
+}
+
+pub struct AuditQueue {
+  sender: mpsc::Sender<Command>,
+  overflow: OverflowPolicy,
+  counters: Arc<Counters>,
+  runtime: tokio::runtime::Handle,
+}
+
+impl AuditQueue {
+  /// Create a queue and spawn its writer on the current Tokio runtime
+  pub fn new(pool: PgPool, config: AuditQueueConfig) -> Self {
+    let (sender, receiver) = mpsc::channel(config.capacity.max(1));
+    let counters = Arc::new(Counters::default());
+
+    let writer = Writer {
+      pool,
+      batch_size: config.batch_size.max(1),
+      counters: counters.clone(),
+      activities: Vec::new(),
+      events: Vec::new(),
+    };
+    tokio::spawn(writer.run(receiver, config.flush_interval));
+
+    Self {
+      sender,
+      overflow: config.overflow,
+      counters,
+      runtime: tokio::runtime::Handle::current(),
+    }
+  }
+
+  /// Start the application-wide queue; later calls return the existing one
+  pub fn start(pool: PgPool, config: AuditQueueConfig) -> &'static AuditQueue {
+    AUDIT_QUEUE.get_or_init(|| {
+      tracing::info!(
+        capacity = config.capacity,
+        batch_size = config.batch_size,
+        "Audit write queue started"
+      );
+      AuditQueue::new(pool, config)
+    })
+  }
+
+  /// The application-wide queue, if it has been started
+  pub fn global() -> Option<&'static AuditQueue> {
+    AUDIT_QUEUE.get()
+  }
+
+  /// Queue an entry for writing
+  pub async fn push(&self, record: AuditRecord) -> PushOutcome {
+    let command = Command::Record(Box::new(record));
+    let accepted = match self.sender.try_send(command) {
+      Ok(()) => true,
+      Err(mpsc::error::TrySendError::Full(Command::Record(record))) => {
+        if self.overflow == OverflowPolicy::Block || record.is_critical() {
+          self.sender.send(Command::Record(record)).await.is_ok()
+        } else {
+          let dropped = self.counters.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+          if dropped == 1 || dropped % 100 == 0 {
+            tracing::warn!(dropped, "Audit queue full, dropping entries");
+          }
+          return PushOutcome::Dropped;
+        }
+      }
+      Err(_) => false,
+    };
+
+    if accepted {
+      self.counters.queued.fetch_add(1, Ordering::Relaxed);
+      PushOutcome::Queued
+    } else {
+      PushOutcome::Closed
+    }
+  }
+
+  /// Wait until everything queued so far has been written
+  pub async fn flush(&self) {
+    let (done, wait) = oneshot::channel();
+    if self.sender.send(Command::Flush(done)).await.is_ok() {
+      let _ = wait.await;
+    }
+  }
+
+  /// Write out pending entries and stop the writer
+  pub async fn shutdown(&self) {
+    let (done, wait) = oneshot::channel();
+    if self.sender.send(Command::Shutdown(done)).await.is_ok() {
+      let _ = wait.await;
+    }
+  }
+
+  /// Queue an entry from synchronous code such as the app exit hook
+  pub fn push_blocking(&'static self, record: AuditRecord) -> PushOutcome {
+    let runtime = self.runtime.clone();
+    std::thread::spawn(move || runtime.block_on(self.push(record)))
+      .join()
+      .unwrap_or(PushOutcome::Closed)
+  }
+
+  /// Shut down from synchronous code such as the app exit hook
+  pub fn shutdown_blocking(&'static self, timeout: Duration) {
+    let runtime = self.runtime.clone();
+    let flushed = std::thread::spawn(move || {
+      runtime.block_on(async { tokio::time::timeout(timeout, self.shutdown()).await.is_ok() })
+    })
+    .join()
+    .unwrap_or(false);
+
+    let stats = self.stats();
+    if flushed {
+      tracing::info!(?stats, "Audit queue flushed on shutdown");
+    } else {
+      tracing::warn!(
+        ?stats,
+        "Audit queue did not finish flushing before shutdown"
+      );
+    }
+  }
+
+  pub fn stats(&self) -> AuditQueueStats {
+    AuditQueueStats {
+      queued: self.counters.queued.load(Ordering::Relaxed),
+      written: self.counters.written.load(Ordering::Relaxed),
+      dropped: self.counters.dropped.load(Ordering::Relaxed),
+      failed: self.counters.failed.load(Ordering::Relaxed),
+    }
+  }
+}
+
+struct Writer {
+  pool: PgPool,
+  batch_size: usize,
+  counters: Arc<Counters>,
+  activities: Vec<ActivityLogEntity>,
+  events: Vec<SystemEventEntity>,
+}
+
+impl Writer {
+  async fn run(mut self, mut receiver: mpsc::Receiver<Command>, flush_interval: Duration) {
+    let mut ticker = tokio::time::interval(flush_interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+      tokio::select! {
+        command = receiver.recv() => match command {
+          Some(Command::Record(record)) => {
+            self.add(*record);
+            if self.activities.len() >= self.batch_size || self.events.len() >= self.batch_size {
+              self.write().await;
+            }
+          }
+          Some(Command::Flush(done)) => {
+            self.write().await;
+            let _ = done.send(());
+          }
+          Some(Command::Shutdown(done)) => {
+            // Refuse new entries, but keep whatever is already buffered in the channel
+            receiver.close();
+            let mut waiting = vec![done];
+            while let Some(command) = receiver.recv().await {
+              match command {
+                Command::Record(record) => self.add(*record),
+                Command::Flush(done) | Command::Shutdown(done) => waiting.push(done),
+              }
+            }
+            self.write().await;
+            for done in waiting {
+              let _ = done.send(());
+            }
+            break;
+          }
+          None => {
+            self.write().await;
+            break;
+          }
+        },
+        _ = ticker.tick() => self.write().await,
+      }
+    }
+  }
+
+  fn add(&mut self, record: AuditRecord) {
+    match record {
+      AuditRecord::Activity(log) => self.activities.push(log),
+      AuditRecord::SystemEvent(event) => self.events.push(event),
+    }
+  }
+
+  async fn write(&mut self) {
+    let repo = AuditRepository::new(&self.pool);
+
+    let activities = std::mem::take(&mut self.activities);
+    if !activities.is_empty() {
+      if let Err(e) = repo.insert_activity_logs(&activities).await {
+        // One bad row (e.g. an unknown user) fails the whole statement, so
+        // fall back to row-by-row inserts to keep the rest of the batch
+        tracing::warn!(
+          entries = activities.len(),
+          "Activity log batch failed, retrying rows: {}",
+          e
+        );
+        for log in &activities {
+          let result = repo.insert_activity_logs(std::slice::from_ref(log)).await;
+          self.record(result, "activity_logs");
+        }
+      } else {
+        self
+          .counters
+          .written
+          .fetch_add(activities.len() as u64, Ordering::Relaxed);
+      }
+    }
+
+    let events = std::mem::take(&mut self.events);
+    if !events.is_empty() {
+      if let Err(e) = repo.insert_system_events(&events).await {
+        tracing::warn!(
+          entries = events.len(),
+          "System event batch failed, retrying rows: {}",
+          e
+        );
+        for event in &events {
+          let result = repo.insert_system_events(std::slice::from_ref(event)).await;
+          self.record(result, "system_events");
+        }
+      } else {
+        self
+          .counters
+          .written
+          .fetch_add(events.len() as u64, Ordering::Relaxed);
+      }
+    }
+  }
+
+  fn record(&self, result: Result<u64>, table: &str) {
+    match result {
+      Ok(written) => {
+        self.counters.written.fetch_add(written, Ordering::Relaxed);
+      }
+      Err(e) => {
+        self.counters.failed.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!(
+          table,
+          "Dropping audit entry that could not be written: {}",
+          e
+        );
+      }
+    }
+  }
+}
+
+/// `AuditStore` that queues activity logs and system events when the
+/// application-wide [`AuditQueue`] is running and writes everything else
+/// straight through to Postgres. An entry the queue drops on overflow is not
+/// written; one it can't take because the writer stopped is written inline.
+pub struct QueuedAuditStore<'a> {
+  repo: AuditRepository<'a>,
+  queue: Option<&'static AuditQueue>,
+}
+
+impl<'a> QueuedAuditStore<'a> {
+  pub fn new(pool: &'a PgPool) -> Self {
+    Self {
+      repo: AuditRepository::new(pool),
+      queue: AuditQueue::global(),
+    }
+  }
+}
+
+#[async_trait]
+impl AuditStore for QueuedAuditStore<'_> {
+  async fn log_activity(
+    &self,
+    user_id: Uuid,
+    session_id: Option<Uuid>,
+    action: &str,
+    entity_type: Option<&str>,
+    entity_id: Option<Uuid>,
+    entity_name: Option<&str>,
+    details: Option<serde_json::Value>,
+    status: ActivityStatus,
+    error_message: Option<&str>,
+    duration_ms: Option<i32>,
+  ) -> Result<ActivityLogEntity> {
+    let Some(queue) = self.queue else {
+      return self
+        .repo
+        .log_activity(
+          user_id,
+          session_id,
+          action,
+          entity_type,
+          entity_id,
+          entity_name,
+          details,
+          status,
+          error_message,
+          duration_ms,
+        )
+        .await;
+    };
+
+    let entity = ActivityLogEntity {
+      id: Uuid::new_v4(),
+      user_id,
+      session_id,
+      action: action.to_string(),
+      entity_type: entity_type.map(str::to_string),
+      entity_id,
+      entity_name: entity_name.map(str::to_string),
+      details,
+      ip_address: None,
+      user_agent: None,
+      status,
+      error_message: error_message.map(str::to_string),
+      duration_ms,
+      request_id: current_request_id(),
+      created_at: Utc::now(),
+    };
+    if queue.push(AuditRecord::Activity(entity.clone())).await == PushOutcome::Closed {
+      self
+        .repo
+        .insert_activity_logs(std::slice::from_ref(&entity))
+        .await?;
+    }
+    Ok(entity)
+  }
+
+  async fn log_system_event(
+    &self,
+    event_type: &str,
+    severity: Severity,
+    source: &str,
+    message: &str,
+    details: Option<serde_json::Value>,
+    stack_trace: Option<&str>,
+    os_info: Option<serde_json::Value>,
+    app_version: Option<&str>,
+  ) -> Result<SystemEventEntity> {
+    let Some(queue) = self.queue else {
+      return self
+        .repo
+        .log_system_event(
+          event_type,
+          severity,
+          source,
+          message,
+          details,
+          stack_trace,
+          os_info,
+          app_version,
+        )
+        .await;
+    };
+
+    let entity = SystemEventEntity {
+      id: Uuid::new_v4(),
+      event_type: event_type.to_string(),
+      severity,
+      source: source.to_string(),
+      message: message.to_string(),
+      details,
+      stack_trace: stack_trace.map(str::to_string),
+      os_info,
+      app_version: app_version.map(str::to_string),
+      request_id: current_request_id(),
+      created_at: Utc::now(),
+    };
+    if queue.push(AuditRecord::SystemEvent(entity.clone())).await == PushOutcome::Closed {
+      self
+        .repo
+        .insert_system_events(std::slice::from_ref(&entity))
+        .await?;
+    }
+    Ok(entity)
+  }
+
+  async fn start_session(
+    &self,
+    user_id: Uuid,
+    device_id: Option<&str>,
+    device_name: Option<&str>,
+    device_type: Option<&str>,
+    os_name: Option<&str>,
+    os_version: Option<&str>,
+    app_version: Option<&str>,
+    metadata: Option<serde_json::Value>,
+  ) -> Result<SessionEntity> {
+    self
+      .repo
+      .start_session(
+        user_id,
+        device_id,
+        device_name,
+        device_type,
+        os_name,
+        os_version,
+        app_version,
+        metadata,
+      )
+      .await
+  }
+
+  async fn end_session(&self, session_id: Uuid, reason: &str) -> Result<SessionEntity> {
+    self.repo.end_session(session_id, reason).await
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  async fn record_profile_activation(
+    &self,
+    user_id: Uuid,
+    profile_id: Uuid,
+    session_id: Option<Uuid>,
+    activation_source: ActivationSource,
+    previous_profile_id: Option<Uuid>,
+    monitors_detected: Option<i32>,
+    monitors_applied: Option<i32>,
+    apps_detected: Option<i32>,
+    apps_launched: Option<i32>,
+    apps_failed: Option<i32>,
+    tabs_detected: Option<i32>,
+    tabs_opened: Option<i32>,
+    windows_restored: Option<i32>,
+    duration_ms: Option<i32>,
+    success: bool,
+    error_message: Option<&str>,
+    metadata: Option<serde_json::Value>,
+  ) -> Result<ProfileActivationEntity> {
+    self
+      .repo
+      .record_profile_activation(
+        user_id,
+        profile_id,
+        session_id,
+        activation_source,
+        previous_profile_id,
+        monitors_detected,
+        monitors_applied,
+        apps_detected,
+        apps_launched,
+        apps_failed,
+        tabs_detected,
+        tabs_opened,
+        windows_restored,
+        duration_ms,
+        success,
+        error_message,
+        metadata,
+      )
+      .await
+  }
+
+  async fn log_error(
+    &self,
+    user_id: Option<Uuid>,
+    session_id: Option<Uuid>,
+    error_code: Option<&str>,
+    error_type: &str,
+    message: &str,
+    stack_trace: Option<&str>,
+    context: Option<serde_json::Value>,
+    source_file: Option<&str>,
+    source_line: Option<i32>,
+    source_function: Option<&str>,
+    severity: Severity,
+  ) -> Result<ErrorLogEntity> {
+    self
+      .repo
+      .log_error(
+        user_id,
+        session_id,
+        error_code,
+        error_type,
+        message,
+        stack_trace,
+        context,
+        source_file,
+        source_line,
+        source_function,
+        severity,
+      )
+      .await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+
+  fn activity(user_id: Uuid, action: &str) -> AuditRecord {
+    AuditRecord::Activity(ActivityLogEntity {
+      id: Uuid::new_v4(),
+      user_id,
+      session_id: None,
+      action: action.to_string(),
+      entity_type: None,
+      entity_id: None,
+      entity_name: None,
+      details: None,
+      ip_address: None,
+      user_agent: None,
+      status: ActivityStatus::Success,
+      error_message: None,
+      duration_ms: None,
+      request_id: None,
+      created_at: Utc::now(),
+    })
+  }
+
+  #[tokio::test]
+  async fn test_batches_are_written_and_bad_rows_isolated() {
+    let test_db = test_db_or_skip!();
+    let user_id = test_db.seed_user().await;
+
+    let queue = AuditQueue::new(
+      test_db.pool().clone(),
+      AuditQueueConfig {
+        flush_interval: Duration::from_secs(60),
+        ..Default::default()
+      },
+    );
+    for action in ["a", "b", "c"] {
+      assert_eq!(
+        queue.push(activity(user_id, action)).await,
+        PushOutcome::Queued
+      );
+    }
+    // Unknown user violates the foreign key and fails the first batch attempt
+    assert_eq!(
+      queue.push(activity(Uuid::new_v4(), "orphan")).await,
+      PushOutcome::Queued
+    );
+    queue.shutdown().await;
+
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM activity_logs")
+      .fetch_one(test_db.pool())
+      .await
+      .unwrap();
+    assert_eq!(count, 3);
+
+    let stats = queue.stats();
+    assert_eq!((stats.queued, stats.written, stats.failed), (4, 3, 1));
+    assert_eq!(
+      queue.push(activity(user_id, "late")).await,
+      PushOutcome::Closed
+    );
+  }
+}
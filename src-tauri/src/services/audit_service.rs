@@ -3,14 +3,39 @@
 // Migrated to use Supabase instead of local PostgreSQL
 
 use crate::{
-  db::Database, error::Result, logging::METRICS, models::dto::*, repositories::AuditRepository,
+  db::Database,
+  error::Result,
+  logging::{APP_LAUNCH_DURATION, METRICS},
+  models::dto::*,
+  models::enums::{ActivationSource, ActivityStatus, MonitorChangeType, Severity},
+  repositories::{AuditRepository, AuditStore},
+  services::{
+    audit_backend::audit_store, audit_queue::AuditQueue, monitor_summary, DiagnosticUploadService,
+    EnvironmentService, UserSettingsService,
+  },
+  state::supervisor::TaskSupervisor,
 };
 use chrono::{DateTime, Utc};
 use serde_json::json;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+/// Upper bound for log retention, in days
+pub const MAX_LOG_RETENTION_DAYS: i64 = 3650;
+
+/// Wait after startup before the first scheduled cleanup, so it doesn't compete with launch
+const LOG_MAINTENANCE_DELAY: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+const LOG_MAINTENANCE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+/// How often `daily_stats` is brought up to date; today's row lags by at most this much
+const DAILY_STATS_DELAY: std::time::Duration = std::time::Duration::from_secs(60);
+const DAILY_STATS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+/// Days covered by an error group's daily occurrences
+const ERROR_TREND_DAYS: usize = 30;
+
 /// Current session state
 #[allow(dead_code)]
 pub struct SessionState {
@@ -43,9 +68,20 @@ impl AuditService {
     db: &Database,
     user_id: &str,
     device_info: Option<serde_json::Value>,
+  ) -> Result<SessionDto> {
+    self
+      .start_session_with(&*audit_store(db), user_id, device_info)
+      .await
+  }
+
+  /// Initialize a new session in the given store
+  pub async fn start_session_with(
+    &self,
+    repo: &dyn AuditStore,
+    user_id: &str,
+    device_info: Option<serde_json::Value>,
   ) -> Result<SessionDto> {
     let user_uuid = parse_uuid(user_id)?;
-    let repo = AuditRepository::new(db.pool());
 
     let os_info = get_os_info();
     let app_version = get_app_version();
@@ -98,7 +134,7 @@ impl AuditService {
     repo
       .log_system_event(
         "session_started",
-        "info",
+        Severity::Info,
         "AuditService",
         "New session started",
         Some(json!({
@@ -119,10 +155,18 @@ impl AuditService {
 
   /// End the current session
   pub async fn end_session(&self, db: &Database, reason: &str) -> Result<Option<SessionDto>> {
+    self.end_session_with(&*audit_store(db), reason).await
+  }
+
+  /// End the current session in the given store
+  pub async fn end_session_with(
+    &self,
+    repo: &dyn AuditStore,
+    reason: &str,
+  ) -> Result<Option<SessionDto>> {
     let session_id = self.get_current_session_id().await;
 
     if let Some(sid) = session_id {
-      let repo = AuditRepository::new(db.pool());
       let session = repo.end_session(sid, reason).await?;
 
       // Clear internal state
@@ -135,7 +179,7 @@ impl AuditService {
       repo
         .log_system_event(
           "session_ended",
-          "info",
+          Severity::Info,
           "AuditService",
           &format!("Session ended: {}", reason),
           Some(json!({
@@ -167,7 +211,37 @@ impl AuditService {
     entity_id: Option<&str>,
     entity_name: Option<&str>,
     details: Option<serde_json::Value>,
-    status: &str,
+    status: ActivityStatus,
+    error_message: Option<&str>,
+    duration_ms: Option<i32>,
+  ) -> Result<ActivityLogDto> {
+    self
+      .log_activity_with(
+        &*audit_store(db),
+        user_id,
+        action,
+        entity_type,
+        entity_id,
+        entity_name,
+        details,
+        status,
+        error_message,
+        duration_ms,
+      )
+      .await
+  }
+
+  /// Log a user activity to the given store, tagged with the current session
+  pub async fn log_activity_with(
+    &self,
+    repo: &dyn AuditStore,
+    user_id: &str,
+    action: &str,
+    entity_type: Option<&str>,
+    entity_id: Option<&str>,
+    entity_name: Option<&str>,
+    details: Option<serde_json::Value>,
+    status: ActivityStatus,
     error_message: Option<&str>,
     duration_ms: Option<i32>,
   ) -> Result<ActivityLogDto> {
@@ -175,8 +249,6 @@ impl AuditService {
     let entity_uuid = entity_id.map(parse_uuid).transpose()?;
     let session_id = self.get_current_session_id().await;
 
-    let repo = AuditRepository::new(db.pool());
-
     let log = repo
       .log_activity(
         user_uuid,
@@ -193,7 +265,7 @@ impl AuditService {
       .await?;
 
     // Update metrics
-    if status == "error" {
+    if status == ActivityStatus::Failure {
       METRICS.record_error();
     }
 
@@ -212,13 +284,13 @@ impl AuditService {
     &self,
     db: &Database,
     event_type: &str,
-    severity: &str,
+    severity: Severity,
     source: &str,
     message: &str,
     details: Option<serde_json::Value>,
     stack_trace: Option<&str>,
   ) -> Result<SystemEventDto> {
-    let repo = AuditRepository::new(db.pool());
+    let repo = audit_store(db);
     let os_info = get_os_info();
     let app_version = get_app_version();
 
@@ -235,12 +307,15 @@ impl AuditService {
       )
       .await?;
 
-    if severity == "error" || severity == "critical" {
+    if severity.is_error() {
       METRICS.record_error();
     }
+    if severity == Severity::Critical {
+      DiagnosticUploadService::on_critical_error(db, None, source, message);
+    }
 
     match severity {
-      "critical" | "error" => {
+      Severity::Critical | Severity::Error => {
         tracing::error!(
           target: "system_events",
           event_type = %event_type,
@@ -248,7 +323,7 @@ impl AuditService {
           "{}", message
         );
       }
-      "warning" => {
+      Severity::Warning => {
         tracing::warn!(
           target: "system_events",
           event_type = %event_type,
@@ -256,7 +331,7 @@ impl AuditService {
           "{}", message
         );
       }
-      _ => {
+      Severity::Debug | Severity::Info => {
         tracing::info!(
           target: "system_events",
           event_type = %event_type,
@@ -276,7 +351,7 @@ impl AuditService {
     db: &Database,
     user_id: &str,
     profile_id: &str,
-    activation_source: &str,
+    activation_source: ActivationSource,
     previous_profile_id: Option<&str>,
     monitors_detected: Option<i32>,
     monitors_applied: Option<i32>,
@@ -295,8 +370,10 @@ impl AuditService {
     let profile_uuid = parse_uuid(profile_id)?;
     let prev_profile_uuid = previous_profile_id.map(parse_uuid).transpose()?;
     let session_id = self.get_current_session_id().await;
+    let metadata =
+      EnvironmentService::attach_to_metadata(metadata, EnvironmentService::capture_async().await);
 
-    let repo = AuditRepository::new(db.pool());
+    let repo = audit_store(db);
 
     let activation = repo
       .record_profile_activation(
@@ -338,7 +415,11 @@ impl AuditService {
           "apps_launched": apps_launched,
           "monitors_applied": monitors_applied,
         })),
-        if success { "success" } else { "error" },
+        if success {
+          ActivityStatus::Success
+        } else {
+          ActivityStatus::Failure
+        },
         error_message,
         duration_ms,
       )
@@ -368,13 +449,47 @@ impl AuditService {
     source_file: Option<&str>,
     source_line: Option<i32>,
     source_function: Option<&str>,
-    severity: &str,
+    severity: Severity,
+  ) -> Result<ErrorLogDto> {
+    let logged = self
+      .log_error_with(
+        &*audit_store(db),
+        user_id,
+        error_code,
+        error_type,
+        message,
+        stack_trace,
+        context,
+        source_file,
+        source_line,
+        source_function,
+        severity,
+      )
+      .await?;
+    if severity == Severity::Critical {
+      DiagnosticUploadService::on_critical_error(db, user_id, error_type, message);
+    }
+    Ok(logged)
+  }
+
+  /// Log an error to the given store, tagged with the current session
+  pub async fn log_error_with(
+    &self,
+    repo: &dyn AuditStore,
+    user_id: Option<&str>,
+    error_code: Option<&str>,
+    error_type: &str,
+    message: &str,
+    stack_trace: Option<&str>,
+    context: Option<serde_json::Value>,
+    source_file: Option<&str>,
+    source_line: Option<i32>,
+    source_function: Option<&str>,
+    severity: Severity,
   ) -> Result<ErrorLogDto> {
     let user_uuid = user_id.map(parse_uuid).transpose()?;
     let session_id = self.get_current_session_id().await;
 
-    let repo = AuditRepository::new(db.pool());
-
     let error = repo
       .log_error(
         user_uuid,
@@ -408,7 +523,7 @@ impl AuditService {
     &self,
     db: &Database,
     user_id: Option<&str>,
-    change_type: &str,
+    change_type: MonitorChangeType,
     monitors_before: Option<serde_json::Value>,
     monitors_after: Option<serde_json::Value>,
     auto_profile_activated: bool,
@@ -417,6 +532,8 @@ impl AuditService {
     let user_uuid = user_id.map(parse_uuid).transpose()?;
     let profile_uuid = activated_profile_id.map(parse_uuid).transpose()?;
     let session_id = self.get_current_session_id().await;
+    let summary =
+      monitor_summary::summarize_json(monitors_before.as_ref(), monitors_after.as_ref());
 
     let repo = AuditRepository::new(db.pool());
 
@@ -429,19 +546,23 @@ impl AuditService {
         monitors_after,
         auto_profile_activated,
         profile_uuid,
+        summary.as_deref(),
       )
       .await?;
 
     tracing::info!(
       change_type = %change_type,
       auto_activated = %auto_profile_activated,
+      summary = summary.as_deref().unwrap_or_default(),
       "Monitor change recorded"
     );
 
     Ok(MonitorChangeDto::from(change))
   }
 
-  /// Record an app launch
+  /// Record an app launch reported by the frontend. Only launches that went
+  /// through the window handshake in `AppService::launch_profile_apps` can
+  /// claim `window_positioned`, so these never do.
   #[allow(clippy::too_many_arguments)]
   pub async fn record_app_launch(
     &self,
@@ -457,12 +578,17 @@ impl AuditService {
     error_message: Option<&str>,
     pid: Option<i32>,
     launch_duration_ms: Option<i32>,
-    window_positioned: bool,
   ) -> Result<AppLaunchDto> {
     let user_uuid = parse_uuid(user_id)?;
     let profile_uuid = profile_id.map(parse_uuid).transpose()?;
     let activation_uuid = activation_id.map(parse_uuid).transpose()?;
     let app_uuid = app_id.map(parse_uuid).transpose()?;
+    if let Some(ms) = launch_duration_ms {
+      METRICS.observe(
+        APP_LAUNCH_DURATION,
+        std::time::Duration::from_millis(ms.max(0) as u64),
+      );
+    }
 
     let repo = AuditRepository::new(db.pool());
 
@@ -479,7 +605,7 @@ impl AuditService {
         error_message,
         pid,
         launch_duration_ms,
-        window_positioned,
+        false,
       )
       .await?;
 
@@ -551,7 +677,6 @@ impl AuditService {
     params: LogQueryParams,
   ) -> Result<Vec<ActivityLogDto>> {
     let user_uuid = parse_uuid(user_id)?;
-    let repo = AuditRepository::new(db.pool());
 
     let start_date = params
       .start_date
@@ -561,16 +686,20 @@ impl AuditService {
       .end_date
       .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
       .map(|dt| dt.with_timezone(&Utc));
+    let limit = params.limit.unwrap_or(50);
+    let offset = params.offset.unwrap_or(0);
 
-    let logs = repo
+    flush_pending_writes().await;
+    let logs = AuditRepository::new(db.pool())
       .get_activity_logs(
         user_uuid,
-        params.limit.unwrap_or(50),
-        params.offset.unwrap_or(0),
+        limit,
+        offset,
         params.action.as_deref(),
         params.entity_type.as_deref(),
         start_date,
         end_date,
+        params.details.as_ref(),
       )
       .await?;
 
@@ -583,13 +712,14 @@ impl AuditService {
     db: &Database,
     params: LogQueryParams,
   ) -> Result<Vec<SystemEventDto>> {
+    flush_pending_writes().await;
     let repo = AuditRepository::new(db.pool());
 
     let events = repo
       .get_system_events(
         params.limit.unwrap_or(50),
         params.offset.unwrap_or(0),
-        params.severity.as_deref(),
+        params.severity,
         params.action.as_deref(), // action used as event_type filter
       )
       .await?;
@@ -626,6 +756,62 @@ impl AuditService {
     )
   }
 
+  /// Error logs grouped by fingerprint with their daily occurrences over the
+  /// last 30 days, most recently seen first
+  pub async fn get_error_groups(
+    &self,
+    db: &Database,
+    include_resolved: bool,
+    limit: i64,
+  ) -> Result<Vec<ErrorGroupDto>> {
+    let repo = AuditRepository::new(db.pool());
+    let groups = repo.get_error_groups(include_resolved, limit).await?;
+    let fingerprints: Vec<String> = groups.iter().map(|g| g.fingerprint.clone()).collect();
+
+    let today = Utc::now().date_naive();
+    let first_day = today - chrono::Duration::days(ERROR_TREND_DAYS as i64 - 1);
+    let mut daily: HashMap<String, Vec<i64>> = HashMap::new();
+    for (fingerprint, day, occurrences) in repo
+      .get_error_daily_counts(&fingerprints, first_day)
+      .await?
+    {
+      let index = (day - first_day).num_days() as usize;
+      if let Some(count) = daily
+        .entry(fingerprint)
+        .or_insert_with(|| vec![0; ERROR_TREND_DAYS])
+        .get_mut(index)
+      {
+        *count += occurrences;
+      }
+    }
+    let mut versions: HashMap<String, Vec<String>> = repo
+      .get_error_versions(&fingerprints)
+      .await?
+      .into_iter()
+      .collect();
+
+    Ok(
+      groups
+        .into_iter()
+        .map(|group| ErrorGroupDto {
+          daily_occurrences: daily
+            .remove(&group.fingerprint)
+            .unwrap_or_else(|| vec![0; ERROR_TREND_DAYS]),
+          affected_versions: versions.remove(&group.fingerprint).unwrap_or_default(),
+          fingerprint: group.fingerprint,
+          error_type: group.error_type,
+          message: group.message,
+          severity: group.severity,
+          occurrences: group.occurrences,
+          first_seen: group.first_seen.to_rfc3339(),
+          last_seen: group.last_seen.to_rfc3339(),
+          resolved: group.resolved,
+          regressions: group.regressions,
+        })
+        .collect(),
+    )
+  }
+
   /// Get error logs
   pub async fn get_error_logs(
     &self,
@@ -639,7 +825,7 @@ impl AuditService {
       .get_error_logs(
         params.limit.unwrap_or(50),
         params.offset.unwrap_or(0),
-        params.severity.as_deref(),
+        params.severity,
         include_resolved,
       )
       .await?;
@@ -675,47 +861,32 @@ impl AuditService {
     user_id: &str,
   ) -> Result<DashboardStatsDto> {
     let user_uuid = parse_uuid(user_id)?;
+    flush_pending_writes().await;
     let repo = AuditRepository::new(db.pool());
 
-    let total_activations = repo.count_activity_logs(user_uuid).await?;
-    let total_activations_today = repo.get_activations_today(user_uuid).await?;
-    let total_activations_week = repo.get_activations_this_week(user_uuid).await?;
-    let unresolved_errors = repo.count_unresolved_errors().await?;
-    let most_used = repo.get_most_used_profile(user_uuid).await?;
-    let last_activation = repo.get_last_activation(user_uuid).await?;
-
-    let active_session = repo.get_active_session(user_uuid).await?;
-    let session_duration = active_session
-      .as_ref()
-      .map(|s| (Utc::now() - s.started_at).num_minutes());
-
-    // Get profile count
-    let (total_profiles,): (i64,) =
-      sqlx::query_as("SELECT COUNT(*) FROM profiles WHERE user_id = $1")
-        .bind(user_uuid)
-        .fetch_one(db.pool())
-        .await
-        .unwrap_or((0,));
-
-    // Get total error count
-    let (total_errors,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM error_logs")
-      .fetch_one(db.pool())
-      .await
-      .unwrap_or((0,));
+    let (counts, highlights) = tokio::try_join!(
+      repo.get_dashboard_counts(user_uuid),
+      repo.get_dashboard_highlights(user_uuid)
+    )?;
+    let session_duration = highlights
+      .session_started_at
+      .map(|started_at| (Utc::now() - started_at).num_minutes());
 
     Ok(DashboardStatsDto {
-      total_profiles,
-      total_activations,
-      total_activations_today,
-      total_activations_week,
-      total_errors,
-      unresolved_errors,
-      active_session_id: active_session.as_ref().map(|s| s.id.to_string()),
+      total_profiles: counts.total_profiles,
+      total_activations: counts.total_activity_logs,
+      total_activations_today: counts.activations_today,
+      total_activations_week: counts.activations_week,
+      total_errors: counts.total_errors,
+      unresolved_errors: counts.unresolved_errors,
+      active_session_id: highlights.active_session_id.map(|id| id.to_string()),
       session_duration_seconds: session_duration,
-      most_used_profile_id: most_used.as_ref().map(|(id, _, _)| id.to_string()),
-      most_used_profile_name: most_used.as_ref().map(|(_, name, _)| name.clone()),
-      most_used_profile_count: most_used.map(|(_, _, count)| count).unwrap_or(0),
-      last_activation_at: last_activation.map(|dt| dt.to_rfc3339()),
+      most_used_profile_id: highlights.most_used_profile_id.map(|id| id.to_string()),
+      most_used_profile_name: highlights.most_used_profile_name,
+      most_used_profile_count: highlights.most_used_profile_count,
+      last_activation_at: counts.last_activation_at.map(|dt| dt.to_rfc3339()),
+      focus_sessions_week: counts.focus_sessions_week,
+      focus_minutes_week: counts.focus_minutes_week,
       uptime_seconds: METRICS.get_uptime_secs(),
     })
   }
@@ -723,47 +894,107 @@ impl AuditService {
   /// Get log summary for analytics
   pub async fn get_log_summary(&self, db: &Database, user_id: &str) -> Result<LogSummaryDto> {
     let user_uuid = parse_uuid(user_id)?;
+    flush_pending_writes().await;
     let repo = AuditRepository::new(db.pool());
 
-    let total_activity_logs = repo.count_activity_logs(user_uuid).await?;
+    let (totals, actions_by_type, errors_by_severity, activations_by_source) = tokio::try_join!(
+      repo.get_log_totals(user_uuid),
+      repo.get_actions_by_type(user_uuid),
+      repo.get_errors_by_severity(),
+      repo.get_activations_by_source(user_uuid)
+    )?;
 
-    let (total_system_events,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM system_events")
-      .fetch_one(db.pool())
-      .await
-      .unwrap_or((0,));
+    Ok(LogSummaryDto {
+      total_activity_logs: totals.total_activity_logs,
+      total_system_events: totals.total_system_events,
+      total_profile_activations: totals.total_profile_activations,
+      total_error_logs: totals.total_error_logs,
+      total_sessions: totals.total_sessions,
+      actions_by_type,
+      errors_by_severity,
+      activations_by_source,
+    })
+  }
 
-    let (total_profile_activations,): (i64,) =
-      sqlx::query_as("SELECT COUNT(*) FROM profile_activations WHERE user_id = $1")
-        .bind(user_uuid)
-        .fetch_one(db.pool())
-        .await
-        .unwrap_or((0,));
+  /// Roll raw logs up into `daily_stats`, starting from the last day already
+  /// rolled up since that one may have been partial
+  pub async fn refresh_daily_stats(&self, db: &Database, user_id: Uuid) -> Result<u64> {
+    flush_pending_writes().await;
+    let repo = AuditRepository::new(db.maintenance_pool());
 
-    let (total_error_logs,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM error_logs")
-      .fetch_one(db.pool())
-      .await
-      .unwrap_or((0,));
+    let since = repo.last_daily_stats_day(user_id).await?;
+    let rows = repo.refresh_daily_stats(user_id, since).await?;
 
-    let (total_sessions,): (i64,) =
-      sqlx::query_as("SELECT COUNT(*) FROM sessions WHERE user_id = $1")
-        .bind(user_uuid)
-        .fetch_one(db.pool())
-        .await
-        .unwrap_or((0,));
+    tracing::debug!(user_id = %user_id, since = ?since, rows, "Daily stats refreshed");
+    Ok(rows)
+  }
+
+  /// Get rolled-up daily statistics for the last `days` days, including today
+  pub async fn get_daily_stats(
+    &self,
+    db: &Database,
+    user_id: &str,
+    days: i64,
+    profile_id: Option<&str>,
+  ) -> Result<Vec<DailyStatsDto>> {
+    if !(1..=MAX_LOG_RETENTION_DAYS).contains(&days) {
+      return Err(crate::error::SmoothieError::ValidationError(format!(
+        "Days must be between 1 and {}",
+        MAX_LOG_RETENTION_DAYS
+      )));
+    }
+    let user_uuid = parse_uuid(user_id)?;
+    let profile_uuid = profile_id.map(parse_uuid).transpose()?;
+    let repo = AuditRepository::new(db.pool());
 
-    let actions_by_type = repo.get_actions_by_type(user_uuid).await?;
-    let errors_by_severity = repo.get_errors_by_severity().await?;
-    let activations_by_source = repo.get_activations_by_source(user_uuid).await?;
+    let since = Utc::now().date_naive() - chrono::Duration::days(days - 1);
+    let rows = repo.get_daily_stats(user_uuid, since, profile_uuid).await?;
 
-    Ok(LogSummaryDto {
-      total_activity_logs,
-      total_system_events,
-      total_profile_activations,
-      total_error_logs,
-      total_sessions,
-      actions_by_type,
-      errors_by_severity,
-      activations_by_source,
+    Ok(rows.into_iter().map(DailyStatsDto::from).collect())
+  }
+
+  /// Per-app focus time under a profile over the last `days` days
+  pub async fn get_focus_time(
+    &self,
+    db: &Database,
+    user_id: &str,
+    profile_id: &str,
+    days: i64,
+  ) -> Result<ProfileFocusTimeDto> {
+    if !(1..=MAX_LOG_RETENTION_DAYS).contains(&days) {
+      return Err(crate::error::SmoothieError::ValidationError(format!(
+        "Days must be between 1 and {}",
+        MAX_LOG_RETENTION_DAYS
+      )));
+    }
+    let user_uuid = parse_uuid(user_id)?;
+    let profile_uuid = parse_uuid(profile_id)?;
+    let since = Utc::now() - chrono::Duration::days(days);
+    let rows = AuditRepository::new(db.pool())
+      .get_focus_breakdown(user_uuid, profile_uuid, since)
+      .await?;
+
+    let total_focus_ms: i64 = rows.iter().map(|row| row.focus_ms).sum();
+    let apps = rows
+      .into_iter()
+      .map(|row| AppFocusTimeDto {
+        share: if total_focus_ms > 0 {
+          row.focus_ms as f64 / total_focus_ms as f64
+        } else {
+          0.0
+        },
+        bundle_id: row.bundle_id,
+        app_name: row.app_name,
+        focus_ms: row.focus_ms,
+        sessions: row.sessions,
+      })
+      .collect();
+
+    Ok(ProfileFocusTimeDto {
+      profile_id: profile_id.to_string(),
+      days,
+      total_focus_ms,
+      apps,
     })
   }
 
@@ -784,13 +1015,105 @@ impl AuditService {
     Ok(ErrorLogDto::from(error))
   }
 
-  /// Cleanup old logs
-  pub async fn cleanup_old_logs(&self, db: &Database, days: i64) -> Result<()> {
-    let repo = AuditRepository::new(db.pool());
-    repo.cleanup_old_logs(days).await?;
+  /// Delete logs older than `days`, optionally running ANALYZE afterwards
+  pub async fn cleanup_old_logs(
+    &self,
+    db: &Database,
+    days: i64,
+    analyze: bool,
+  ) -> Result<LogCleanupDto> {
+    if !(1..=MAX_LOG_RETENTION_DAYS).contains(&days) {
+      return Err(crate::error::SmoothieError::ValidationError(format!(
+        "Retention must be between 1 and {} days",
+        MAX_LOG_RETENTION_DAYS
+      )));
+    }
+
+    let repo = AuditRepository::new(db.maintenance_pool());
+    let cutoff = Utc::now() - chrono::Duration::days(days);
+    let deleted = repo.cleanup_old_logs(cutoff).await?;
+
+    let mut result = LogCleanupDto {
+      retention_days: days,
+      ..Default::default()
+    };
+    for (table, count) in deleted {
+      match table {
+        "activity_logs" => result.activity_logs = count,
+        "system_events" => result.system_events = count,
+        "error_logs" => result.error_logs = count,
+        "sessions" => result.sessions = count,
+        "app_launches" => result.app_launches = count,
+        "monitor_changes" => result.monitor_changes = count,
+        "automation_executions" => result.automation_executions = count,
+        "app_focus_events" => result.app_focus_events = count,
+        _ => {}
+      }
+      result.total_deleted += count;
+    }
+
+    if analyze {
+      repo.analyze_log_tables().await?;
+      result.analyzed = true;
+    }
+
+    tracing::info!(
+      days = %days,
+      deleted = result.total_deleted,
+      analyzed = result.analyzed,
+      "Old logs cleaned up"
+    );
+    Ok(result)
+  }
+
+  /// Prune logs once a day according to the user's retention settings
+  pub fn spawn_log_maintenance(tasks: &TaskSupervisor, db: Arc<Database>, user_id: Uuid) {
+    tasks.spawn("log_maintenance", move || {
+      let db = db.clone();
+      async move {
+        let first_run = tokio::time::Instant::now() + LOG_MAINTENANCE_DELAY;
+        let mut ticker = tokio::time::interval_at(first_run, LOG_MAINTENANCE_INTERVAL);
+        loop {
+          ticker.tick().await;
+
+          let settings = match UserSettingsService::get_settings(&db, user_id).await {
+            Ok(settings) => settings,
+            Err(e) => {
+              tracing::warn!("Skipping log maintenance, could not load settings: {}", e);
+              continue;
+            }
+          };
+          if !settings.auto_cleanup_logs {
+            tracing::debug!("Automatic log cleanup disabled in settings");
+            continue;
+          }
+
+          if let Err(e) = AUDIT_SERVICE
+            .cleanup_old_logs(&db, settings.log_retention_days as i64, true)
+            .await
+          {
+            tracing::warn!("Scheduled log cleanup failed: {}", e);
+          }
+        }
+      }
+    });
+  }
 
-    tracing::info!(days = %days, "Old logs cleaned up");
-    Ok(())
+  /// Keep `daily_stats` current so analytics reads don't scan the log tables
+  pub fn spawn_daily_stats_rollup(tasks: &TaskSupervisor, db: Arc<Database>, user_id: Uuid) {
+    tasks.spawn("daily_stats_rollup", move || {
+      let db = db.clone();
+      async move {
+        let first_run = tokio::time::Instant::now() + DAILY_STATS_DELAY;
+        let mut ticker = tokio::time::interval_at(first_run, DAILY_STATS_INTERVAL);
+        loop {
+          ticker.tick().await;
+          if let Err(e) = AUDIT_SERVICE.refresh_daily_stats(&db, user_id).await {
+            tracing::warn!("Daily stats rollup failed: {}", e);
+          }
+        }
+      }
+    });
   }
 }
 
@@ -809,6 +1132,13 @@ fn parse_uuid(s: &str) -> Result<Uuid> {
     .map_err(|_| crate::error::SmoothieError::ValidationError(format!("Invalid UUID: {}", s)))
 }
 
+/// Make queued activity logs and system events visible to the reads that follow
+async fn flush_pending_writes() {
+  if let Some(queue) = AuditQueue::global() {
+    queue.flush().await;
+  }
+}
+
 fn get_os_info() -> Option<serde_json::Value> {
   Some(json!({
     "name": std::env::consts::OS,
@@ -825,3 +1155,95 @@ fn get_app_version() -> Option<String> {
 lazy_static::lazy_static! {
   pub static ref AUDIT_SERVICE: AuditService = AuditService::new();
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::repositories::memory::InMemoryAuditStore;
+
+  const USER_ID: &str = "00000000-0000-0000-0000-000000000001";
+
+  #[tokio::test]
+  async fn test_session_lifecycle_tags_activity() {
+    let service = AuditService::new();
+    let store = InMemoryAuditStore::default();
+
+    let session = service
+      .start_session_with(&store, USER_ID, Some(json!({ "device_name": "MacBook" })))
+      .await
+      .unwrap();
+    assert_eq!(session.device_name.as_deref(), Some("MacBook"));
+
+    service
+      .log_activity_with(
+        &store,
+        USER_ID,
+        "profile_viewed",
+        Some("profile"),
+        None,
+        None,
+        None,
+        ActivityStatus::Success,
+        None,
+        None,
+      )
+      .await
+      .unwrap();
+    let session_id = Uuid::parse_str(&session.id).unwrap();
+    assert_eq!(store.activities.lock()[0].session_id, Some(session_id));
+
+    let ended = service
+      .end_session_with(&store, "app_closed")
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(ended.end_reason.as_deref(), Some("app_closed"));
+    assert_eq!(service.get_current_session_id().await, None);
+
+    let events: Vec<String> = store
+      .system_events
+      .lock()
+      .iter()
+      .map(|e| e.event_type.clone())
+      .collect();
+    assert_eq!(events, vec!["session_started", "session_ended"]);
+  }
+
+  #[tokio::test]
+  async fn test_end_session_without_session() {
+    let service = AuditService::new();
+    let store = InMemoryAuditStore::default();
+
+    assert!(service
+      .end_session_with(&store, "app_closed")
+      .await
+      .unwrap()
+      .is_none());
+    assert!(store.system_events.lock().is_empty());
+  }
+
+  #[tokio::test]
+  async fn test_log_error_rejects_invalid_user() {
+    let service = AuditService::new();
+    let store = InMemoryAuditStore::default();
+
+    let result = service
+      .log_error_with(
+        &store,
+        Some("not-a-uuid"),
+        None,
+        "panic",
+        "boom",
+        None,
+        None,
+        None,
+        None,
+        None,
+        Severity::Error,
+      )
+      .await;
+
+    assert!(result.is_err());
+    assert!(store.errors.lock().is_empty());
+  }
+}
@@ -2,10 +2,27 @@ use crate::{
   db::Database,
   error::{Result, SmoothieError},
   logging::METRICS,
-  models::dto::AutomationRuleDto,
-  repositories::AutomationRepository,
+  models::{
+    dto::{AutomationRuleDto, RuleActionDto, RuleActionRequest},
+    entities::{AutomationRuleEntity, RuleActionEntity},
+  },
+  repositories::{AutomationRepository, JsonFilter, ProfilePart},
+  services::{
+    device_service::parse_device_id, environment_service::PowerState, profile_lock::ProfileLock,
+    resource_guard::ThrottledTicker, rule_action_service::RuleAction, BluetoothDevice,
+    EnvironmentService, RuleActionService, SystemBackend, UsbDevice, UserSettingsService,
+    AUDIT_SERVICE,
+  },
+  state::AppState,
+  utils::{
+    rule_conditions::{Condition, ConditionContext, ConditionResult},
+    sun::sun_times,
+  },
 };
-use chrono::{Datelike, Timelike, Utc};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use uuid::Uuid;
 
 /// Helper to parse UUID from string
@@ -13,6 +30,478 @@ fn parse_uuid(s: &str) -> Result<Uuid> {
   Uuid::parse_str(s).map_err(|_| SmoothieError::ValidationError(format!("Invalid UUID: {}", s)))
 }
 
+fn parse_conditions(conditions: &serde_json::Value) -> Result<Condition> {
+  Condition::parse(conditions)
+    .map_err(|e| SmoothieError::ValidationError(format!("Invalid rule conditions: {}", e)))
+}
+
+/// A rule's conditions checked against the machine as it is right now
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleTestResult {
+  pub rule_id: String,
+  /// True when every condition passes, or the rule has none
+  pub passed: bool,
+  pub context: ConditionContext,
+  pub conditions: Option<ConditionResult>,
+  /// Set while the rule's cooldown keeps it from acting again
+  pub cooling_down_until: Option<String>,
+  /// What the engine would decide for this rule if its trigger fired now
+  pub outcome: RuleOutcome,
+}
+
+/// A rule as the engine would treat it for a synthetic trigger
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedRule {
+  #[serde(flatten)]
+  pub decision: RuleDecision,
+  pub profile_id: String,
+  pub conditions: Option<ConditionResult>,
+  /// What the rule would do; empty unless it was chosen
+  pub actions: Vec<serde_json::Value>,
+}
+
+/// Outcome of `simulate_trigger`; nothing is executed or recorded
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationResult {
+  pub trigger_type: String,
+  /// Local time the trigger was simulated at
+  pub at: String,
+  pub context: ConditionContext,
+  pub rules: Vec<SimulatedRule>,
+}
+
+/// Rule type fired by the periodic `evaluate_rules` check. Its trigger config is
+/// `{"time": "HH:MM"}` or `{"sun": "sunrise" | "sunset", "offsetMinutes": -30}`, with
+/// optional `weekdays`; a config with neither fires on every check.
+pub const SCHEDULE_TRIGGER: &str = "schedule";
+/// How late a missed schedule may still fire, e.g. after the Mac wakes from sleep
+const SCHEDULE_GRACE_MINUTES: i64 = 60;
+/// Sun-relative schedules may be shifted by up to 12 hours either way
+const MAX_SUN_OFFSET_MINUTES: i64 = 12 * 60;
+/// Rule type fired when the machine switches between battery and AC power.
+/// Its trigger config is `{"source": "battery" | "ac"}`.
+pub const POWER_SOURCE_TRIGGER: &str = "power_source";
+/// How often the power watcher polls `pmset`
+const POWER_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+/// Rule type fired when a USB or Thunderbolt device is plugged in or out. Its
+/// trigger config is `{"vendorId": "0x2188", "productId": "0x0747", "event": "connected"}`;
+/// `productId` is optional and `event` ("connected" or "disconnected") defaults to connected.
+pub const USB_DEVICE_TRIGGER: &str = "usb_device";
+/// Rule type fired when a Bluetooth device connects or disconnects. Its trigger
+/// config is `{"address": "AC:90:85:12:34:56", "event": "connected"}` or names the
+/// device with `{"name": "AirPods Pro"}` instead; `event` defaults to connected.
+pub const BLUETOOTH_DEVICE_TRIGGER: &str = "bluetooth_device";
+/// How often the device watcher lists attached devices; `system_profiler` is slow
+const DEVICE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+/// Device trigger events
+const DEVICE_EVENTS: [&str; 2] = ["connected", "disconnected"];
+
+/// Longest allowed cooldown, one week
+const MAX_COOLDOWN_MINUTES: i32 = 7 * 24 * 60;
+/// Longest allowed snooze, one week
+const MAX_SNOOZE_HOURS: i64 = 7 * 24;
+
+/// A daily window, in local time, during which a rule may act.
+/// `end` before `start` wraps past midnight.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveHoursWindow {
+  /// "HH:MM"
+  pub start: String,
+  /// "HH:MM", exclusive
+  pub end: String,
+  /// 1 = Monday ... 7 = Sunday, counted from the day the window starts; empty means every day
+  #[serde(default)]
+  pub weekdays: Vec<u32>,
+}
+
+impl ActiveHoursWindow {
+  fn bounds(&self) -> Result<(NaiveTime, NaiveTime)> {
+    let parse = |value: &str| {
+      NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| {
+        SmoothieError::ValidationError(format!("Invalid time \"{}\", expected HH:MM", value))
+      })
+    };
+    Ok((parse(&self.start)?, parse(&self.end)?))
+  }
+
+  fn validate(&self) -> Result<()> {
+    let (start, end) = self.bounds()?;
+    if start == end {
+      return Err(SmoothieError::ValidationError(
+        "An active hours window must not start and end at the same time".into(),
+      ));
+    }
+    if self.weekdays.iter().any(|day| !(1..=7).contains(day)) {
+      return Err(SmoothieError::ValidationError(
+        "Weekdays must be between 1 (Monday) and 7 (Sunday)".into(),
+      ));
+    }
+    Ok(())
+  }
+
+  fn contains(&self, at: NaiveDateTime) -> bool {
+    let Ok((start, end)) = self.bounds() else {
+      return false;
+    };
+    let on_day = |date: chrono::NaiveDate| {
+      self.weekdays.is_empty() || self.weekdays.contains(&date.weekday().number_from_monday())
+    };
+    let time = at.time();
+    if start < end {
+      start <= time && time < end && on_day(at.date())
+    } else {
+      (time >= start && on_day(at.date()))
+        || (time < end && at.date().pred_opt().is_some_and(on_day))
+    }
+  }
+}
+
+fn parse_active_hours(active_hours: &serde_json::Value) -> Result<Vec<ActiveHoursWindow>> {
+  let windows: Vec<ActiveHoursWindow> = serde_json::from_value(active_hours.clone())
+    .map_err(|e| SmoothieError::ValidationError(format!("Invalid active hours: {}", e)))?;
+  for window in &windows {
+    window.validate()?;
+  }
+  Ok(windows)
+}
+
+/// Whether a rule's active hours allow it to act at `at`; rules without any always may
+fn within_active_hours(rule: &AutomationRuleEntity, at: NaiveDateTime) -> bool {
+  match rule.active_hours.as_ref().map(parse_active_hours) {
+    None => true,
+    Some(Ok(windows)) => windows.iter().any(|window| window.contains(at)),
+    Some(Err(_)) => false,
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SunEvent {
+  Sunrise,
+  Sunset,
+}
+
+/// When a schedule rule fires: a fixed local time, or a time relative to sunrise or sunset
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Schedule {
+  time: Option<String>,
+  sun: Option<SunEvent>,
+  #[serde(default)]
+  offset_minutes: i64,
+  /// 1 = Monday ... 7 = Sunday; empty means every day
+  #[serde(default)]
+  weekdays: Vec<u32>,
+}
+
+impl Schedule {
+  /// The schedule in a rule's trigger config; `None` for configs that name no time
+  fn parse(trigger_config: &serde_json::Value) -> Result<Option<Self>> {
+    if trigger_config.get("time").is_none() && trigger_config.get("sun").is_none() {
+      return Ok(None);
+    }
+    let schedule: Self = serde_json::from_value(trigger_config.clone())
+      .map_err(|e| SmoothieError::ValidationError(format!("Invalid schedule: {}", e)))?;
+
+    match (&schedule.time, schedule.sun) {
+      (Some(time), None) => {
+        NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| {
+          SmoothieError::ValidationError(format!("Invalid time \"{}\", expected HH:MM", time))
+        })?;
+      }
+      (None, Some(_)) => {}
+      _ => {
+        return Err(SmoothieError::ValidationError(
+          "A schedule needs either a \"time\" or a \"sun\" event, not both".into(),
+        ))
+      }
+    }
+    if schedule.offset_minutes.abs() > MAX_SUN_OFFSET_MINUTES {
+      return Err(SmoothieError::ValidationError(format!(
+        "Schedule offset must be within {} minutes",
+        MAX_SUN_OFFSET_MINUTES
+      )));
+    }
+    if schedule.weekdays.iter().any(|day| !(1..=7).contains(day)) {
+      return Err(SmoothieError::ValidationError(
+        "Weekdays must be between 1 (Monday) and 7 (Sunday)".into(),
+      ));
+    }
+    Ok(Some(schedule))
+  }
+
+  /// When the schedule fires on `date`, if it does; sun events need a location
+  fn occurrence(&self, date: NaiveDate, location: Option<(f64, f64)>) -> Option<DateTime<Local>> {
+    if !self.weekdays.is_empty() && !self.weekdays.contains(&date.weekday().number_from_monday()) {
+      return None;
+    }
+    let base = match (&self.time, self.sun) {
+      (Some(time), _) => {
+        let time = NaiveTime::parse_from_str(time, "%H:%M").ok()?;
+        date.and_time(time).and_local_timezone(Local).earliest()?
+      }
+      (None, Some(event)) => {
+        let (latitude, longitude) = location?;
+        let (sunrise, sunset) = sun_times(date, latitude, longitude)?;
+        match event {
+          SunEvent::Sunrise => sunrise,
+          SunEvent::Sunset => sunset,
+        }
+        .with_timezone(&Local)
+      }
+      (None, None) => return None,
+    };
+    Some(base + chrono::Duration::minutes(self.offset_minutes))
+  }
+}
+
+/// Whether a schedule rule is due at `now`: its latest occurrence passed within
+/// the grace period and the rule hasn't fired since. Rules without a schedule
+/// are always due.
+fn schedule_due(
+  rule: &AutomationRuleEntity,
+  now: DateTime<Local>,
+  location: Option<(f64, f64)>,
+) -> bool {
+  let schedule = match Schedule::parse(&rule.trigger_config) {
+    Ok(Some(schedule)) => schedule,
+    Ok(None) => return true,
+    Err(_) => return false,
+  };
+  let today = now.date_naive();
+  // An offset can push yesterday's occurrence past midnight
+  [today.pred_opt(), Some(today)]
+    .into_iter()
+    .flatten()
+    .filter_map(|date| schedule.occurrence(date, location))
+    .any(|at| {
+      at <= now
+        && now - at < chrono::Duration::minutes(SCHEDULE_GRACE_MINUTES)
+        && rule
+          .last_triggered_at
+          .map_or(true, |last| last < at.with_timezone(&Utc))
+    })
+}
+
+fn uses_sun(rules: &[AutomationRuleEntity]) -> bool {
+  rules.iter().any(|rule| {
+    matches!(
+      Schedule::parse(&rule.trigger_config),
+      Ok(Some(Schedule { sun: Some(_), .. }))
+    )
+  })
+}
+
+/// Why a matching rule did or didn't act
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleOutcome {
+  Chosen,
+  Snoozed,
+  OutsideActiveHours,
+  CoolingDown,
+  ConditionsNotMet,
+  InvalidConditions,
+  /// A higher-priority rule with `stop_after_match` was chosen
+  Superseded,
+}
+
+/// One rule's outcome, stored in `trigger_details` of each execution
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleDecision {
+  pub rule_id: String,
+  pub priority: i32,
+  pub outcome: RuleOutcome,
+}
+
+fn cooling_down_until(rule: &AutomationRuleEntity) -> Option<DateTime<Utc>> {
+  if rule.cooldown_minutes <= 0 {
+    return None;
+  }
+  rule
+    .last_triggered_at
+    .map(|at| at + chrono::Duration::minutes(rule.cooldown_minutes as i64))
+}
+
+/// Decide which of `rules`, highest priority first, act on a trigger fired at `now`.
+/// `context` is only needed when some rule has conditions.
+fn choose_rules(
+  rules: &[AutomationRuleEntity],
+  now: DateTime<Local>,
+  context: Option<&ConditionContext>,
+) -> Vec<RuleDecision> {
+  let utc_now = now.with_timezone(&Utc);
+  let mut stopped = false;
+  rules
+    .iter()
+    .map(|rule| {
+      let outcome = if stopped {
+        RuleOutcome::Superseded
+      } else if rule.snoozed_until.is_some_and(|until| until > utc_now) {
+        RuleOutcome::Snoozed
+      } else if !within_active_hours(rule, now.naive_local()) {
+        RuleOutcome::OutsideActiveHours
+      } else if cooling_down_until(rule).is_some_and(|until| until > utc_now) {
+        RuleOutcome::CoolingDown
+      } else {
+        match rule.conditions.as_ref().map(parse_conditions) {
+          None => RuleOutcome::Chosen,
+          Some(Err(_)) => RuleOutcome::InvalidConditions,
+          Some(Ok(condition)) => match context {
+            Some(context) if condition.evaluate(context).passed => RuleOutcome::Chosen,
+            _ => RuleOutcome::ConditionsNotMet,
+          },
+        }
+      };
+      stopped |= outcome == RuleOutcome::Chosen && rule.stop_after_match;
+
+      RuleDecision {
+        rule_id: rule.id.to_string(),
+        priority: rule.priority,
+        outcome,
+      }
+    })
+    .collect()
+}
+
+/// The actions a chosen rule takes, in order
+fn planned_actions(
+  rule: &AutomationRuleEntity,
+  actions: &[RuleActionEntity],
+) -> Vec<serde_json::Value> {
+  RuleActionService::effective_actions(rule, actions)
+    .into_iter()
+    .map(|(position, action_type, config)| {
+      serde_json::json!({
+        "position": position,
+        "type": action_type,
+        "config": config,
+      })
+    })
+    .collect()
+}
+
+/// Matches the power-source rules that fire on a switch to `source` ("ac" or "battery")
+fn power_source_filter(source: &str) -> JsonFilter {
+  JsonFilter::Contains(serde_json::json!({ "source": source }))
+}
+
+/// The device event ("connected" or "disconnected") a device rule fires on
+fn device_event(rule: &AutomationRuleEntity) -> &str {
+  rule
+    .trigger_config
+    .get("event")
+    .and_then(|e| e.as_str())
+    .unwrap_or("connected")
+}
+
+/// Whether a USB rule's vendor (and product, if given) IDs match `device`
+fn matches_usb_device(rule: &AutomationRuleEntity, device: &UsbDevice) -> bool {
+  let config = &rule.trigger_config;
+  config.get("vendorId").and_then(parse_device_id) == Some(device.vendor_id)
+    && config
+      .get("productId")
+      .map_or(true, |id| parse_device_id(id) == Some(device.product_id))
+}
+
+/// Whether a Bluetooth rule's address (compared case-insensitively) or name matches `device`
+fn matches_bluetooth_device(rule: &AutomationRuleEntity, device: &BluetoothDevice) -> bool {
+  let config = &rule.trigger_config;
+  match config.get("address").and_then(|a| a.as_str()) {
+    Some(address) => device
+      .address
+      .as_deref()
+      .is_some_and(|a| a.eq_ignore_ascii_case(address)),
+    None => config.get("name").and_then(|n| n.as_str()) == Some(device.name.as_str()),
+  }
+}
+
+/// Devices in `current` but not `previous` ("connected") and the reverse ("disconnected")
+fn device_changes<T: Clone + Eq + std::hash::Hash>(
+  previous: &HashSet<T>,
+  current: &HashSet<T>,
+) -> [(&'static str, Vec<T>); 2] {
+  [
+    ("connected", current.difference(previous).cloned().collect()),
+    (
+      "disconnected",
+      previous.difference(current).cloned().collect(),
+    ),
+  ]
+}
+
+fn validate_trigger_config(rule_type: &str, trigger_config: &serde_json::Value) -> Result<()> {
+  if rule_type == SCHEDULE_TRIGGER {
+    Schedule::parse(trigger_config)?;
+  }
+  let valid_usb_ids = || {
+    trigger_config
+      .get("vendorId")
+      .and_then(parse_device_id)
+      .is_some()
+      && trigger_config
+        .get("productId")
+        .map_or(true, |id| parse_device_id(id).is_some())
+  };
+  if rule_type == USB_DEVICE_TRIGGER && !valid_usb_ids() {
+    return Err(SmoothieError::ValidationError(
+      "USB device rules need a \"vendorId\" and optional \"productId\", e.g. \"0x2188\"".into(),
+    ));
+  }
+  if rule_type == BLUETOOTH_DEVICE_TRIGGER
+    && !["address", "name"].iter().any(|key| {
+      trigger_config
+        .get(*key)
+        .and_then(|v| v.as_str())
+        .is_some_and(|v| !v.trim().is_empty())
+    })
+  {
+    return Err(SmoothieError::ValidationError(
+      "Bluetooth device rules need an \"address\" or \"name\"".into(),
+    ));
+  }
+  if rule_type == USB_DEVICE_TRIGGER || rule_type == BLUETOOTH_DEVICE_TRIGGER {
+    if let Some(event) = trigger_config.get("event") {
+      if !event.as_str().is_some_and(|e| DEVICE_EVENTS.contains(&e)) {
+        return Err(SmoothieError::ValidationError(format!(
+          "Device rule event must be one of: {}",
+          DEVICE_EVENTS.join(", ")
+        )));
+      }
+    }
+  }
+  if rule_type == POWER_SOURCE_TRIGGER
+    && !matches!(
+      trigger_config.get("source").and_then(|s| s.as_str()),
+      Some("ac" | "battery")
+    )
+  {
+    return Err(SmoothieError::ValidationError(
+      "Power source rules need a trigger config of {\"source\": \"ac\"} or {\"source\": \"battery\"}"
+        .into(),
+    ));
+  }
+  Ok(())
+}
+
+/// Stored actions of `rules`, keyed by rule id
+async fn actions_by_rule(
+  repo: &AutomationRepository<'_>,
+  rules: &[AutomationRuleEntity],
+) -> Result<HashMap<Uuid, Vec<RuleActionEntity>>> {
+  let rule_ids: Vec<Uuid> = rules.iter().map(|rule| rule.id).collect();
+  let mut grouped: HashMap<Uuid, Vec<RuleActionEntity>> = HashMap::new();
+  for action in repo.find_actions_for_rules(&rule_ids).await? {
+    grouped.entry(action.rule_id).or_default().push(action);
+  }
+  Ok(grouped)
+}
+
 pub struct AutomationService;
 
 impl AutomationService {
@@ -21,12 +510,18 @@ impl AutomationService {
     profile_id: &str,
     rule_type: String,
     trigger_config: serde_json::Value,
+    conditions: Option<serde_json::Value>,
   ) -> Result<AutomationRuleDto> {
     let profile_uuid = parse_uuid(profile_id)?;
+    validate_trigger_config(&rule_type, &trigger_config)?;
+    if let Some(conditions) = &conditions {
+      parse_conditions(conditions)?;
+    }
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
     let repo = AutomationRepository::new(db.pool());
 
     let entity = repo
-      .create(profile_uuid, &rule_type, trigger_config)
+      .create(profile_uuid, &rule_type, trigger_config, conditions)
       .await?;
 
     tracing::info!(rule_id = %entity.id, profile_id = %profile_id, "Automation rule created");
@@ -42,33 +537,654 @@ impl AutomationService {
     Ok(rules.into_iter().map(AutomationRuleDto::from).collect())
   }
 
-  pub async fn evaluate_schedule_triggers(db: &Database) -> Result<Vec<(String, String)>> {
-    let _now = Utc::now();
-    let _hour = _now.hour();
-    let _minute = _now.minute();
-    let _weekday = _now.weekday().num_days_from_monday() + 1;
+  /// Pick the schedule rules that are due now and fire them
+  pub async fn evaluate_schedule_triggers(
+    db: &Database,
+    system: &dyn SystemBackend,
+    user_id: &str,
+  ) -> Result<Vec<(String, String)>> {
+    let repo = AutomationRepository::new(db.pool());
+    let mut rules = repo.find_enabled_by_type(SCHEDULE_TRIGGER).await?;
+    let location = Self::schedule_location(db, user_id, &rules).await?;
+    let now = Local::now();
+    rules.retain(|rule| schedule_due(rule, now, location));
+    if rules.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    Self::fire_rules(
+      db,
+      system,
+      user_id,
+      SCHEDULE_TRIGGER,
+      &rules,
+      serde_json::Map::new(),
+    )
+    .await
+  }
+
+  /// The location sun-relative schedules among `rules` are computed for;
+  /// only looked up when one of them needs it
+  async fn schedule_location(
+    db: &Database,
+    user_id: &str,
+    rules: &[AutomationRuleEntity],
+  ) -> Result<Option<(f64, f64)>> {
+    if !uses_sun(rules) {
+      return Ok(None);
+    }
+    let location = UserSettingsService::resolve_location(db, parse_uuid(user_id)?).await?;
+    if location.is_none() {
+      tracing::warn!("No location set or available; sunrise/sunset rules won't fire");
+    }
+    Ok(location)
+  }
+
+  /// Fire the power-source rules watching for a switch to `current.source`
+  pub async fn evaluate_power_triggers(
+    db: &Database,
+    system: &dyn SystemBackend,
+    user_id: &str,
+    previous: &PowerState,
+    current: &PowerState,
+  ) -> Result<Vec<(String, String)>> {
+    let repo = AutomationRepository::new(db.pool());
+    let rules = repo
+      .find_enabled_by_type_matching(POWER_SOURCE_TRIGGER, &power_source_filter(&current.source))
+      .await?;
+    if rules.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    let mut event = serde_json::Map::new();
+    event.insert(
+      "power".into(),
+      serde_json::json!({ "from": previous, "to": current }),
+    );
+    Self::fire_rules(db, system, user_id, POWER_SOURCE_TRIGGER, &rules, event).await
+  }
+
+  /// Fire the USB device rules watching for `event` on any of `devices`
+  pub async fn evaluate_usb_triggers(
+    db: &Database,
+    system: &dyn SystemBackend,
+    user_id: &str,
+    event: &str,
+    devices: &[UsbDevice],
+  ) -> Result<Vec<(String, String)>> {
+    Self::fire_device_rules(
+      db,
+      system,
+      user_id,
+      USB_DEVICE_TRIGGER,
+      "usb",
+      event,
+      devices,
+      |rule| devices.iter().any(|d| matches_usb_device(rule, d)),
+    )
+    .await
+  }
+
+  /// Fire the Bluetooth device rules watching for `event` on any of `devices`
+  pub async fn evaluate_bluetooth_triggers(
+    db: &Database,
+    system: &dyn SystemBackend,
+    user_id: &str,
+    event: &str,
+    devices: &[BluetoothDevice],
+  ) -> Result<Vec<(String, String)>> {
+    Self::fire_device_rules(
+      db,
+      system,
+      user_id,
+      BLUETOOTH_DEVICE_TRIGGER,
+      "bluetooth",
+      event,
+      devices,
+      |rule| devices.iter().any(|d| matches_bluetooth_device(rule, d)),
+    )
+    .await
+  }
 
+  /// Fire the `trigger_type` rules for `event` that `matches` accepts, noting the
+  /// changed devices under `details_key` in the trigger details
+  #[allow(clippy::too_many_arguments)]
+  async fn fire_device_rules<D: Serialize>(
+    db: &Database,
+    system: &dyn SystemBackend,
+    user_id: &str,
+    trigger_type: &str,
+    details_key: &str,
+    event: &str,
+    devices: &[D],
+    matches: impl Fn(&AutomationRuleEntity) -> bool,
+  ) -> Result<Vec<(String, String)>> {
     let repo = AutomationRepository::new(db.pool());
-    let rules = repo.find_enabled_by_type("schedule").await?;
+    let rules: Vec<_> = repo
+      .find_enabled_by_type(trigger_type)
+      .await?
+      .into_iter()
+      .filter(|rule| device_event(rule) == event && matches(rule))
+      .collect();
+    if rules.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    let mut details = serde_json::Map::new();
+    details.insert(
+      details_key.into(),
+      serde_json::json!({ "event": event, "devices": devices }),
+    );
+    Self::fire_rules(db, system, user_id, trigger_type, &rules, details).await
+  }
+
+  /// Choose among `rules`, run the chosen ones' actions and record an execution
+  /// for each. Every execution's `trigger_details` holds `event` plus the
+  /// decision made for every rule; `actions_taken` holds each action's result.
+  async fn fire_rules(
+    db: &Database,
+    system: &dyn SystemBackend,
+    user_id: &str,
+    trigger_type: &str,
+    rules: &[AutomationRuleEntity],
+    mut event: serde_json::Map<String, serde_json::Value>,
+  ) -> Result<Vec<(String, String)>> {
+    // Only gathered when some rule actually has conditions
+    let context = if rules.iter().any(|rule| rule.conditions.is_some()) {
+      Some(Self::condition_context(system).await)
+    } else {
+      None
+    };
+    let decisions = choose_rules(rules, Local::now(), context.as_ref());
+    event.insert("decisions".into(), serde_json::to_value(&decisions)?);
+    let trigger_details = serde_json::Value::Object(event);
+    let actions = if decisions.iter().any(|d| d.outcome == RuleOutcome::Chosen) {
+      actions_by_rule(&AutomationRepository::new(db.pool()), rules).await?
+    } else {
+      HashMap::new()
+    };
 
     let mut triggered = Vec::new();
+    for (rule, decision) in rules.iter().zip(&decisions) {
+      match decision.outcome {
+        RuleOutcome::Chosen => {}
+        RuleOutcome::InvalidConditions => {
+          tracing::warn!(rule_id = %rule.id, "Skipping rule with invalid conditions");
+          continue;
+        }
+        outcome => {
+          tracing::debug!(rule_id = %rule.id, ?outcome, "Skipping rule");
+          continue;
+        }
+      }
+
+      let results = RuleActionService::run_all(
+        db,
+        system,
+        user_id,
+        rule,
+        actions.get(&rule.id).map(Vec::as_slice).unwrap_or_default(),
+      )
+      .await;
+      let error_message = results
+        .iter()
+        .find(|result| !result.success)
+        .map(|result| format!("Action {} failed: {}", result.position, result.message));
+      let duration_ms: i64 = results.iter().map(|result| result.duration_ms).sum();
 
-    for rule in rules {
-      // Parse trigger config and evaluate
-      // This is a simplified version - full implementation would parse JSON
-      triggered.push((rule.id.to_string(), rule.profile_id.to_string()));
-      METRICS.record_automation_triggered();
+      // Recording the execution also starts the rule's cooldown
+      let rule_id = rule.id.to_string();
+      let profile_id = rule.profile_id.to_string();
+      if let Err(e) = AUDIT_SERVICE
+        .record_automation_execution(
+          db,
+          user_id,
+          &rule_id,
+          Some(&profile_id),
+          trigger_type,
+          Some(trigger_details.clone()),
+          error_message.is_none(),
+          error_message.as_deref(),
+          Some(serde_json::to_value(&results)?),
+          Some(duration_ms.min(i32::MAX as i64) as i32),
+        )
+        .await
+      {
+        tracing::warn!(rule_id = %rule_id, "Failed to record automation execution: {}", e);
+        // The audit write normally counts it
+        METRICS.record_automation_triggered();
+      }
+
+      triggered.push((rule_id, profile_id));
     }
 
     Ok(triggered)
   }
 
+  /// Run the rule engine against a synthetic `trigger_type` event without acting on it.
+  ///
+  /// `payload` overrides fields of the live condition context (e.g.
+  /// `{"monitorCount": 3, "onAcPower": false}`); an `at` field ("YYYY-MM-DDTHH:MM")
+  /// moves the evaluation to that local time, which also sets time, hour,
+  /// minute and weekday and is used for snoozes, active hours and cooldowns.
+  pub async fn simulate_trigger(
+    db: &Database,
+    system: &dyn SystemBackend,
+    user_id: &str,
+    trigger_type: &str,
+    payload: Option<serde_json::Value>,
+  ) -> Result<SimulationResult> {
+    let mut overrides = match payload {
+      None | Some(serde_json::Value::Null) => serde_json::Map::new(),
+      Some(serde_json::Value::Object(map)) => map,
+      Some(_) => {
+        return Err(SmoothieError::ValidationError(
+          "Simulation payload must be an object".into(),
+        ))
+      }
+    };
+
+    let at = match overrides.remove("at") {
+      None => Local::now(),
+      Some(value) => value
+        .as_str()
+        .and_then(|at| NaiveDateTime::parse_from_str(at, "%Y-%m-%dT%H:%M").ok())
+        .and_then(|at| at.and_local_timezone(Local).earliest())
+        .ok_or_else(|| {
+          SmoothieError::ValidationError(format!(
+            "Invalid \"at\" {}, expected YYYY-MM-DDTHH:MM",
+            value
+          ))
+        })?,
+    };
+
+    let mut context = Self::condition_context(system).await;
+    context.time = at.format("%H:%M").to_string();
+    context.hour = at.hour();
+    context.minute = at.minute();
+    context.weekday = at.weekday().number_from_monday();
+    if !overrides.is_empty() {
+      let mut merged = serde_json::to_value(&context)?;
+      if let serde_json::Value::Object(fields) = &mut merged {
+        fields.extend(overrides);
+      }
+      context = serde_json::from_value(merged).map_err(|e| {
+        SmoothieError::ValidationError(format!("Invalid simulation payload: {}", e))
+      })?;
+    }
+
+    let repo = AutomationRepository::new(db.pool());
+    let mut rules = if trigger_type == POWER_SOURCE_TRIGGER {
+      // Only the rules watching for the simulated power source would fire
+      let source = match context.on_ac_power {
+        Some(true) => "ac",
+        Some(false) => "battery",
+        None => "unknown",
+      };
+      repo
+        .find_enabled_by_type_matching(trigger_type, &power_source_filter(source))
+        .await?
+    } else {
+      repo.find_enabled_by_type(trigger_type).await?
+    };
+    if trigger_type == SCHEDULE_TRIGGER {
+      let location = Self::schedule_location(db, user_id, &rules).await?;
+      rules.retain(|rule| schedule_due(rule, at, location));
+    }
+    let decisions = choose_rules(&rules, at, Some(&context));
+    let actions = actions_by_rule(&repo, &rules).await?;
+
+    let rules = rules
+      .iter()
+      .zip(decisions)
+      .map(|(rule, decision)| SimulatedRule {
+        actions: if decision.outcome == RuleOutcome::Chosen {
+          planned_actions(
+            rule,
+            actions.get(&rule.id).map(Vec::as_slice).unwrap_or_default(),
+          )
+        } else {
+          Vec::new()
+        },
+        decision,
+        profile_id: rule.profile_id.to_string(),
+        conditions: rule
+          .conditions
+          .as_ref()
+          .and_then(|conditions| parse_conditions(conditions).ok())
+          .map(|condition| condition.evaluate(&context)),
+      })
+      .collect();
+
+    Ok(SimulationResult {
+      trigger_type: trigger_type.to_string(),
+      at: at.to_rfc3339(),
+      context,
+      rules,
+    })
+  }
+
+  /// Check a rule's conditions now, reporting which of them pass
+  pub async fn test_rule(
+    db: &Database,
+    system: &dyn SystemBackend,
+    rule_id: &str,
+  ) -> Result<RuleTestResult> {
+    let rule_uuid = parse_uuid(rule_id)?;
+    let repo = AutomationRepository::new(db.pool());
+
+    let rule = repo
+      .find_by_id(rule_uuid)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Rule not found".into()))?;
+    let condition = rule.conditions.as_ref().map(parse_conditions).transpose()?;
+
+    let context = Self::condition_context(system).await;
+    let outcome =
+      choose_rules(std::slice::from_ref(&rule), Local::now(), Some(&context))[0].outcome;
+    let conditions = condition.map(|c| c.evaluate(&context));
+
+    Ok(RuleTestResult {
+      rule_id: rule_id.to_string(),
+      passed: conditions.as_ref().map_or(true, |c| c.passed),
+      context,
+      conditions,
+      cooling_down_until: cooling_down_until(&rule)
+        .filter(|until| *until > Utc::now())
+        .map(|until| until.to_rfc3339()),
+      outcome,
+    })
+  }
+
+  /// Replace a rule's conditions, or remove them with `None`
+  pub async fn set_rule_conditions(
+    db: &Database,
+    rule_id: &str,
+    conditions: Option<serde_json::Value>,
+  ) -> Result<AutomationRuleDto> {
+    let rule_uuid = parse_uuid(rule_id)?;
+    if let Some(conditions) = &conditions {
+      parse_conditions(conditions)?;
+    }
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::AutomationRule, rule_uuid).await?;
+    let repo = AutomationRepository::new(db.pool());
+
+    let entity = repo.set_conditions(rule_uuid, conditions).await?;
+
+    tracing::info!(rule_id = %rule_id, "Automation rule conditions updated");
+
+    Ok(AutomationRuleDto::from(entity))
+  }
+
+  /// Set a rule's priority, stop-after-match flag and cooldown
+  pub async fn set_rule_priority(
+    db: &Database,
+    rule_id: &str,
+    priority: i32,
+    stop_after_match: bool,
+    cooldown_minutes: i32,
+  ) -> Result<AutomationRuleDto> {
+    let rule_uuid = parse_uuid(rule_id)?;
+    if !(0..=MAX_COOLDOWN_MINUTES).contains(&cooldown_minutes) {
+      return Err(SmoothieError::ValidationError(format!(
+        "Cooldown must be between 0 and {} minutes",
+        MAX_COOLDOWN_MINUTES
+      )));
+    }
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::AutomationRule, rule_uuid).await?;
+    let repo = AutomationRepository::new(db.pool());
+
+    let entity = repo
+      .set_priority(rule_uuid, priority, stop_after_match, cooldown_minutes)
+      .await?;
+
+    tracing::info!(rule_id = %rule_id, priority, "Automation rule priority updated");
+
+    Ok(AutomationRuleDto::from(entity))
+  }
+
+  /// Keep a rule from acting for the next `hours` hours; 0 wakes it now
+  pub async fn snooze_rule(db: &Database, rule_id: &str, hours: i64) -> Result<AutomationRuleDto> {
+    let rule_uuid = parse_uuid(rule_id)?;
+    if !(0..=MAX_SNOOZE_HOURS).contains(&hours) {
+      return Err(SmoothieError::ValidationError(format!(
+        "Snooze must be between 0 and {} hours",
+        MAX_SNOOZE_HOURS
+      )));
+    }
+    let until = (hours > 0).then(|| Utc::now() + chrono::Duration::hours(hours));
+    let repo = AutomationRepository::new(db.pool());
+
+    let entity = repo.snooze(rule_uuid, until).await?;
+
+    tracing::info!(rule_id = %rule_id, hours, "Automation rule snoozed");
+
+    Ok(AutomationRuleDto::from(entity))
+  }
+
+  /// Limit a rule to the given daily windows, or lift the limit with `None`
+  pub async fn set_rule_active_hours(
+    db: &Database,
+    rule_id: &str,
+    active_hours: Option<Vec<ActiveHoursWindow>>,
+  ) -> Result<AutomationRuleDto> {
+    let rule_uuid = parse_uuid(rule_id)?;
+    if let Some(windows) = &active_hours {
+      for window in windows {
+        window.validate()?;
+      }
+    }
+    let active_hours = active_hours.map(serde_json::to_value).transpose()?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::AutomationRule, rule_uuid).await?;
+    let repo = AutomationRepository::new(db.pool());
+
+    let entity = repo.set_active_hours(rule_uuid, active_hours).await?;
+
+    tracing::info!(rule_id = %rule_id, "Automation rule active hours updated");
+
+    Ok(AutomationRuleDto::from(entity))
+  }
+
+  /// Poll the power source in the background and fire power-source rules
+  /// whenever it switches between battery and AC
+  pub fn spawn_power_watcher(state: Arc<AppState>, user_id: Uuid) {
+    let watcher_state = state.clone();
+    state.tasks.spawn("power_watcher", move || {
+      Self::watch_power(watcher_state.clone(), user_id)
+    });
+  }
+
+  async fn watch_power(state: Arc<AppState>, user_id: Uuid) {
+    let mut ticker = tokio::time::interval(POWER_POLL_INTERVAL);
+    let mut previous: Option<PowerState> = None;
+    loop {
+      ticker.tick().await;
+      let Ok(current) = tokio::task::spawn_blocking(EnvironmentService::get_power_state).await
+      else {
+        continue;
+      };
+      // "unknown" readings neither fire rules nor reset the last known source
+      if current.source == "unknown" {
+        continue;
+      }
+
+      if let Some(previous) = previous.as_ref().filter(|p| p.source != current.source) {
+        tracing::info!(from = %previous.source, to = %current.source, "Power source changed");
+        // Switches while paused are dropped, not fired on resume
+        if state.automation_pause.is_paused() {
+          tracing::info!("Automation paused; not firing power-source rules");
+        } else {
+          match Self::evaluate_power_triggers(
+            &state.db,
+            state.system.as_ref(),
+            &user_id.to_string(),
+            previous,
+            &current,
+          )
+          .await
+          {
+            Ok(fired) if !fired.is_empty() => state.invalidate_profile_caches(),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Power trigger evaluation failed: {}", e),
+          }
+        }
+      }
+      previous = Some(current);
+    }
+  }
+
+  /// Watch for USB/Thunderbolt devices being plugged in or out and Bluetooth
+  /// devices connecting or disconnecting, and fire the matching device rules.
+  /// The first listing only sets the baseline.
+  pub fn spawn_device_watcher(state: Arc<AppState>, user_id: Uuid) {
+    let watcher_state = state.clone();
+    state.tasks.spawn("device_watcher", move || {
+      Self::watch_devices(watcher_state.clone(), user_id)
+    });
+  }
+
+  async fn watch_devices(state: Arc<AppState>, user_id: Uuid) {
+    let mut ticker = ThrottledTicker::new(DEVICE_POLL_INTERVAL);
+    let mut previous: Option<(HashSet<UsbDevice>, HashSet<BluetoothDevice>)> = None;
+    let user_id = user_id.to_string();
+    loop {
+      ticker.tick().await;
+      let system = state.system.clone();
+      let Ok((usb, bluetooth)) = tokio::task::spawn_blocking(move || {
+        (system.get_usb_devices(), system.get_bluetooth_devices())
+      })
+      .await
+      else {
+        continue;
+      };
+      let usb: HashSet<_> = usb.into_iter().collect();
+      let bluetooth: HashSet<_> = bluetooth.into_iter().collect();
+
+      // Devices that come and go while paused fire nothing, even after resuming
+      let paused = state.automation_pause.is_paused();
+      if let Some((previous_usb, previous_bluetooth)) = previous.as_ref().filter(|_| !paused) {
+        for (event, devices) in device_changes(previous_usb, &usb) {
+          if devices.is_empty() {
+            continue;
+          }
+          tracing::info!(event, count = devices.len(), "USB devices changed");
+          let fired = Self::evaluate_usb_triggers(
+            &state.db,
+            state.system.as_ref(),
+            &user_id,
+            event,
+            &devices,
+          )
+          .await;
+          match fired {
+            Ok(fired) if !fired.is_empty() => state.invalidate_profile_caches(),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("USB trigger evaluation failed: {}", e),
+          }
+        }
+        for (event, devices) in device_changes(previous_bluetooth, &bluetooth) {
+          if devices.is_empty() {
+            continue;
+          }
+          tracing::info!(event, count = devices.len(), "Bluetooth devices changed");
+          let fired = Self::evaluate_bluetooth_triggers(
+            &state.db,
+            state.system.as_ref(),
+            &user_id,
+            event,
+            &devices,
+          )
+          .await;
+          match fired {
+            Ok(fired) if !fired.is_empty() => state.invalidate_profile_caches(),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Bluetooth trigger evaluation failed: {}", e),
+          }
+        }
+      }
+      previous = Some((usb, bluetooth));
+    }
+  }
+
+  /// Gather the facts rule conditions are checked against
+  async fn condition_context(system: &dyn SystemBackend) -> ConditionContext {
+    let power = tokio::task::spawn_blocking(EnvironmentService::get_power_state)
+      .await
+      .unwrap_or_default();
+    let now = Local::now();
+
+    ConditionContext {
+      monitor_count: system.get_monitors().len(),
+      time: now.format("%H:%M").to_string(),
+      hour: now.hour(),
+      minute: now.minute(),
+      weekday: now.weekday().number_from_monday(),
+      on_ac_power: match power.source.as_str() {
+        "ac" => Some(true),
+        "battery" => Some(false),
+        _ => None,
+      },
+      battery_percent: power.battery_percent,
+      running_apps: system
+        .get_running_apps()
+        .into_iter()
+        .map(|app| app.bundle_id)
+        .collect(),
+    }
+  }
+
+  pub async fn get_rule_actions(db: &Database, rule_id: &str) -> Result<Vec<RuleActionDto>> {
+    let rule_uuid = parse_uuid(rule_id)?;
+    let repo = AutomationRepository::new(db.pool());
+
+    let actions = repo.find_actions(rule_uuid).await?;
+    Ok(actions.into_iter().map(RuleActionDto::from).collect())
+  }
+
+  /// Replace a rule's actions; they run in the order given. An empty list
+  /// falls back to activating the rule's profile.
+  pub async fn set_rule_actions(
+    db: &Database,
+    rule_id: &str,
+    actions: Vec<RuleActionRequest>,
+  ) -> Result<Vec<RuleActionDto>> {
+    let rule_uuid = parse_uuid(rule_id)?;
+    for (position, action) in actions.iter().enumerate() {
+      RuleAction::parse(action.action_type, &action.config).map_err(|e| match e {
+        SmoothieError::ValidationError(msg) => {
+          SmoothieError::ValidationError(format!("Action {}: {}", position, msg))
+        }
+        other => other,
+      })?;
+    }
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::AutomationRule, rule_uuid).await?;
+    let repo = AutomationRepository::new(db.pool());
+    repo
+      .find_by_id(rule_uuid)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Rule not found".into()))?;
+
+    let actions: Vec<_> = actions
+      .into_iter()
+      .map(|action| match action.config {
+        serde_json::Value::Null => (action.action_type, serde_json::json!({})),
+        config => (action.action_type, config),
+      })
+      .collect();
+    let stored = repo.replace_actions(rule_uuid, &actions).await?;
+
+    tracing::info!(rule_id = %rule_id, count = stored.len(), "Rule actions updated");
+
+    Ok(stored.into_iter().map(RuleActionDto::from).collect())
+  }
+
   pub async fn toggle_rule(
     db: &Database,
     rule_id: &str,
     enabled: bool,
   ) -> Result<AutomationRuleDto> {
     let rule_uuid = parse_uuid(rule_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::AutomationRule, rule_uuid).await?;
     let repo = AutomationRepository::new(db.pool());
 
     let entity = repo.toggle(rule_uuid, enabled).await?;
@@ -77,6 +1193,7 @@ impl AutomationService {
 
   pub async fn delete_rule(db: &Database, rule_id: &str) -> Result<()> {
     let rule_uuid = parse_uuid(rule_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::AutomationRule, rule_uuid).await?;
     let repo = AutomationRepository::new(db.pool());
 
     let deleted = repo.delete(rule_uuid).await?;
@@ -89,3 +1206,99 @@ impl AutomationService {
     Ok(())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rule(priority: i32, stop_after_match: bool) -> AutomationRuleEntity {
+    AutomationRuleEntity {
+      id: Uuid::new_v4(),
+      profile_id: Uuid::new_v4(),
+      rule_type: "schedule".to_string(),
+      trigger_config: serde_json::json!({}),
+      conditions: None,
+      priority,
+      stop_after_match,
+      cooldown_minutes: 0,
+      last_triggered_at: None,
+      snoozed_until: None,
+      active_hours: None,
+      is_enabled: true,
+      created_at: Utc::now(),
+    }
+  }
+
+  #[test]
+  fn test_choose_rules_honours_cooldown_and_stop_after_match() {
+    let now = Local::now();
+    let mut cooling = rule(30, true);
+    cooling.cooldown_minutes = 60;
+    cooling.last_triggered_at = Some(Utc::now() - chrono::Duration::minutes(10));
+    let mut unmet = rule(20, true);
+    unmet.conditions = Some(serde_json::json!({"==": [{"var": "monitor_count"}, 3]}));
+    let rules = [cooling, unmet, rule(10, true), rule(0, false)];
+
+    let context = ConditionContext {
+      monitor_count: 1,
+      ..Default::default()
+    };
+    let outcomes: Vec<_> = choose_rules(&rules, now, Some(&context))
+      .into_iter()
+      .map(|d| d.outcome)
+      .collect();
+    assert_eq!(
+      outcomes,
+      [
+        RuleOutcome::CoolingDown,
+        RuleOutcome::ConditionsNotMet,
+        RuleOutcome::Chosen,
+        RuleOutcome::Superseded,
+      ]
+    );
+  }
+
+  #[test]
+  fn test_active_hours_wrap_past_midnight() {
+    let at = |day: u32, time: &str| {
+      chrono::NaiveDate::from_ymd_opt(2026, 3, day)
+        .unwrap()
+        .and_time(NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+    };
+    // Friday night into Saturday morning; 2026-03-06 is a Friday
+    let window = ActiveHoursWindow {
+      start: "22:00".to_string(),
+      end: "06:00".to_string(),
+      weekdays: vec![5],
+    };
+    assert!(window.contains(at(6, "23:30")));
+    assert!(window.contains(at(7, "05:59")));
+    assert!(!window.contains(at(7, "06:00")));
+    assert!(!window.contains(at(7, "23:30")));
+  }
+
+  #[test]
+  fn test_schedule_due_once_within_grace() {
+    let at = |time: &str| {
+      NaiveDate::from_ymd_opt(2026, 3, 4)
+        .unwrap()
+        .and_time(NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+        .and_local_timezone(Local)
+        .unwrap()
+    };
+    let mut morning = rule(0, false);
+    morning.trigger_config = serde_json::json!({"time": "09:00"});
+    assert!(!schedule_due(&morning, at("08:59"), None));
+    assert!(schedule_due(&morning, at("09:30"), None));
+    assert!(!schedule_due(&morning, at("10:01"), None));
+    morning.last_triggered_at = Some(at("09:01").with_timezone(&Utc));
+    assert!(!schedule_due(&morning, at("09:30"), None));
+
+    // Sunset needs a location; 2026-03-04 is a Wednesday
+    let mut sunset = rule(0, false);
+    sunset.trigger_config = serde_json::json!({"sun": "sunset", "weekdays": [3]});
+    assert!(!schedule_due(&sunset, at("23:00"), None));
+    assert!(Schedule::parse(&serde_json::json!({"time": "9am"})).is_err());
+    assert!(Schedule::parse(&serde_json::json!({"time": "09:00", "sun": "sunrise"})).is_err());
+  }
+}
@@ -0,0 +1,358 @@
+//! Bookmark Import Service
+//!
+//! Reads links out of a browser's own files so they can become a profile's
+//! browser tabs: Chrome's (and other Chromium browsers') `Bookmarks` file,
+//! Firefox bookmark backups (`.json` or the compressed `.jsonlz4` in
+//! `bookmarkbackups/`), and Firefox session files (`sessionstore.jsonlz4`,
+//! `recovery.jsonlz4`). Chrome keeps its sessions in a binary format that is
+//! not supported. Only http(s) links are kept, and a link is a duplicate when
+//! the profile already has it as a tab or it appears earlier in the file.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::{
+    BookmarkImportPreviewDto, BookmarkItemDto, BookmarkSource, BrowserTabDto,
+    CreateBrowserTabRequest, ImportBookmarksRequest,
+  },
+  services::BrowserService,
+  utils::validation,
+};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Header Firefox puts in front of its lz4-compressed JSON files
+const MOZLZ4_MAGIC: &[u8] = b"mozLz40\0";
+
+pub struct BookmarkImportService;
+
+impl BookmarkImportService {
+  /// Everything an import of `path` would offer, with duplicates marked
+  pub async fn preview_bookmark_import(
+    db: &Database,
+    profile_id: &str,
+    path: &str,
+    folder: Option<&str>,
+  ) -> Result<BookmarkImportPreviewDto> {
+    let (source, links) = read_file(Path::new(path))?;
+    let existing = BrowserService::get_browser_tabs(db, profile_id).await?;
+    Ok(BookmarkImportPreviewDto {
+      source,
+      browser: default_browser(source).to_string(),
+      items: mark_duplicates(&existing, in_folder(links, folder)),
+    })
+  }
+
+  /// Create a tab for each selected, non-duplicate item, after the profile's
+  /// existing tabs
+  pub async fn import_bookmarks(
+    db: &Database,
+    profile_id: &str,
+    req: ImportBookmarksRequest,
+  ) -> Result<Vec<BrowserTabDto>> {
+    validation::validate(&req)?;
+    let (source, links) = read_file(Path::new(&req.path))?;
+    let existing = BrowserService::get_browser_tabs(db, profile_id).await?;
+    let selected: Option<HashSet<&str>> = req
+      .urls
+      .as_ref()
+      .map(|urls| urls.iter().map(|u| u.trim()).collect());
+    let browser = req
+      .browser
+      .unwrap_or_else(|| default_browser(source).to_string());
+
+    let mut tab_order = existing.iter().map(|t| t.tab_order + 1).max().unwrap_or(0);
+    let mut created = Vec::new();
+    for item in mark_duplicates(&existing, in_folder(links, req.folder.as_deref())) {
+      let wanted = selected
+        .as_ref()
+        .map_or(true, |urls| urls.contains(item.url.as_str()));
+      if item.duplicate || !wanted {
+        continue;
+      }
+      let tab = BrowserService::create_browser_tab(
+        db,
+        profile_id,
+        CreateBrowserTabRequest {
+          url: item.url,
+          browser: browser.clone(),
+          monitor_id: None,
+          tab_order,
+          favicon: None,
+        },
+      )
+      .await?;
+      created.push(tab);
+      tab_order += 1;
+    }
+
+    tracing::info!(
+      profile_id = %profile_id,
+      source = %source,
+      count = created.len(),
+      "Imported browser tabs"
+    );
+    Ok(created)
+  }
+}
+
+/// A link before duplicates are known
+struct Link {
+  title: String,
+  url: String,
+  folder: String,
+}
+
+fn default_browser(source: BookmarkSource) -> &'static str {
+  match source {
+    BookmarkSource::ChromeBookmarks => "chrome",
+    BookmarkSource::FirefoxBookmarks | BookmarkSource::FirefoxSession => "firefox",
+  }
+}
+
+fn read_file(path: &Path) -> Result<(BookmarkSource, Vec<Link>)> {
+  let bytes = std::fs::read(path)?;
+  let json = match bytes.strip_prefix(MOZLZ4_MAGIC) {
+    Some(compressed) => decompress_mozlz4(compressed)?,
+    None => bytes,
+  };
+  let value: Value = serde_json::from_slice(&json)
+    .map_err(|_| SmoothieError::ValidationError("Not a bookmarks or session file".into()))?;
+  parse(&value)
+}
+
+/// The body of a mozLz4 file: the decompressed size as a little-endian u32,
+/// then one lz4 block
+fn decompress_mozlz4(data: &[u8]) -> Result<Vec<u8>> {
+  let invalid = || SmoothieError::ValidationError("Damaged Firefox file".into());
+  let size: [u8; 4] = data.get(..4).ok_or_else(invalid)?.try_into().unwrap();
+  lz4_flex::block::decompress(&data[4..], u32::from_le_bytes(size) as usize).map_err(|_| invalid())
+}
+
+fn parse(value: &Value) -> Result<(BookmarkSource, Vec<Link>)> {
+  let mut links = Vec::new();
+  if let Some(roots) = value.get("roots").and_then(Value::as_object) {
+    for root in roots.values() {
+      walk_chrome(root, "", &mut links);
+    }
+    return Ok((BookmarkSource::ChromeBookmarks, links));
+  }
+  if let Some(windows) = value.get("windows").and_then(Value::as_array) {
+    for (i, window) in windows.iter().enumerate() {
+      session_window(window, &format!("Window {}", i + 1), &mut links);
+    }
+    return Ok((BookmarkSource::FirefoxSession, links));
+  }
+  if value.get("type").and_then(Value::as_str) == Some("text/x-moz-place-container") {
+    walk_firefox(value, "", &mut links);
+    return Ok((BookmarkSource::FirefoxBookmarks, links));
+  }
+  Err(SmoothieError::ValidationError(
+    "Not a bookmarks or session file".into(),
+  ))
+}
+
+fn str_field<'a>(node: &'a Value, key: &str) -> &'a str {
+  node.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn child_folder(parent: &str, name: &str) -> String {
+  match (parent.is_empty(), name.is_empty()) {
+    (_, true) => parent.to_string(),
+    (true, false) => name.to_string(),
+    (false, false) => format!("{} / {}", parent, name),
+  }
+}
+
+fn push_link(links: &mut Vec<Link>, title: &str, url: &str, folder: &str) {
+  let url = url.trim();
+  if validation::web_url(url).is_ok() {
+    links.push(Link {
+      title: title.to_string(),
+      url: url.to_string(),
+      folder: folder.to_string(),
+    });
+  }
+}
+
+fn walk_chrome(node: &Value, folder: &str, links: &mut Vec<Link>) {
+  match str_field(node, "type") {
+    "url" => push_link(
+      links,
+      str_field(node, "name"),
+      str_field(node, "url"),
+      folder,
+    ),
+    "folder" => {
+      let folder = child_folder(folder, str_field(node, "name"));
+      for child in node
+        .get("children")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+      {
+        walk_chrome(child, &folder, links);
+      }
+    }
+    _ => {}
+  }
+}
+
+fn walk_firefox(node: &Value, folder: &str, links: &mut Vec<Link>) {
+  match str_field(node, "type") {
+    "text/x-moz-place" => push_link(
+      links,
+      str_field(node, "title"),
+      str_field(node, "uri"),
+      folder,
+    ),
+    "text/x-moz-place-container" => {
+      let folder = child_folder(folder, str_field(node, "title"));
+      for child in node
+        .get("children")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+      {
+        walk_firefox(child, &folder, links);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// The page each tab in a session window is showing; `index` is the 1-based
+/// position in the tab's back/forward history
+fn session_window(window: &Value, folder: &str, links: &mut Vec<Link>) {
+  for tab in window
+    .get("tabs")
+    .and_then(Value::as_array)
+    .into_iter()
+    .flatten()
+  {
+    let Some(entries) = tab.get("entries").and_then(Value::as_array) else {
+      continue;
+    };
+    let index = tab.get("index").and_then(Value::as_u64).unwrap_or(1) as usize;
+    if let Some(entry) = entries.get(index.saturating_sub(1)).or(entries.last()) {
+      push_link(
+        links,
+        str_field(entry, "title"),
+        str_field(entry, "url"),
+        folder,
+      );
+    }
+  }
+}
+
+fn in_folder(links: Vec<Link>, folder: Option<&str>) -> Vec<Link> {
+  match folder.map(str::trim).filter(|f| !f.is_empty()) {
+    Some(folder) => {
+      let nested = format!("{} / ", folder);
+      links
+        .into_iter()
+        .filter(|l| l.folder == folder || l.folder.starts_with(&nested))
+        .collect()
+    }
+    None => links,
+  }
+}
+
+/// Compare URLs ignoring a trailing slash and the case of the scheme and host
+fn dedupe_key(url: &str) -> String {
+  let url = url.trim().trim_end_matches('/');
+  match url.find("://") {
+    Some(i) => {
+      let rest = &url[i + 3..];
+      let host_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+      let (origin, tail) = url.split_at(i + 3 + host_end);
+      format!("{}{}", origin.to_ascii_lowercase(), tail)
+    }
+    None => url.to_string(),
+  }
+}
+
+fn mark_duplicates(existing: &[BrowserTabDto], links: Vec<Link>) -> Vec<BookmarkItemDto> {
+  let mut seen: HashSet<String> = existing.iter().map(|t| dedupe_key(&t.url)).collect();
+  links
+    .into_iter()
+    .map(|link| BookmarkItemDto {
+      duplicate: !seen.insert(dedupe_key(&link.url)),
+      title: link.title,
+      url: link.url,
+      folder: link.folder,
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_parses_each_format_and_marks_duplicates() {
+    let chrome = json!({ "roots": {
+      "bookmark_bar": { "type": "folder", "name": "Bookmarks Bar", "children": [
+        { "type": "url", "name": "Docs", "url": "https://docs.rs/" },
+        { "type": "folder", "name": "Work", "children": [
+          { "type": "url", "name": "Tracker", "url": "https://Tracker.example.com/board" },
+          { "type": "url", "name": "Script", "url": "javascript:void(0)" },
+        ]},
+      ]},
+    }});
+    let (source, links) = parse(&chrome).unwrap();
+    assert_eq!(source, BookmarkSource::ChromeBookmarks);
+    let work = in_folder(links, Some("Bookmarks Bar / Work"));
+    assert_eq!(work.len(), 1);
+    assert_eq!(work[0].title, "Tracker");
+
+    let existing: BrowserTabDto = serde_json::from_value(json!({
+      "id": "", "profileId": "", "url": "https://tracker.example.com/board/",
+      "browser": "chrome", "tabOrder": 0, "createdAt": "",
+    }))
+    .unwrap();
+    assert!(mark_duplicates(&[existing], work)[0].duplicate);
+
+    let firefox = json!({ "type": "text/x-moz-place-container", "title": "", "children": [
+      { "type": "text/x-moz-place-container", "title": "toolbar", "children": [
+        { "type": "text/x-moz-place", "title": "MDN", "uri": "https://developer.mozilla.org" },
+      ]},
+    ]});
+    let (source, links) = parse(&firefox).unwrap();
+    assert_eq!(source, BookmarkSource::FirefoxBookmarks);
+    assert_eq!(links[0].folder, "toolbar");
+
+    let session = json!({ "windows": [{ "tabs": [
+      { "index": 2, "entries": [
+        { "url": "https://a.example.com", "title": "A" },
+        { "url": "https://b.example.com", "title": "B" },
+      ]},
+      { "index": 1, "entries": [{ "url": "https://b.example.com/", "title": "B again" }] },
+    ]}]});
+    let (source, links) = parse(&session).unwrap();
+    assert_eq!(source, BookmarkSource::FirefoxSession);
+    let items = mark_duplicates(&[], links);
+    assert_eq!(items[0].url, "https://b.example.com");
+    assert_eq!(items[0].folder, "Window 1");
+    assert!(!items[0].duplicate);
+    assert!(items[1].duplicate);
+  }
+
+  #[test]
+  fn test_reads_mozlz4_files() {
+    let body = br#"{"windows":[{"tabs":[{"index":1,"entries":[{"url":"https://example.com","title":"E"}]}]}]}"#;
+    let mut file = MOZLZ4_MAGIC.to_vec();
+    file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    file.extend_from_slice(&lz4_flex::block::compress(body));
+
+    let path = std::env::temp_dir().join(format!("smoothie-{}.jsonlz4", uuid::Uuid::new_v4()));
+    std::fs::write(&path, file).unwrap();
+    let (source, links) = read_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(source, BookmarkSource::FirefoxSession);
+    assert_eq!(links[0].url, "https://example.com");
+  }
+}
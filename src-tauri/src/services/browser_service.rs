@@ -3,8 +3,11 @@
 use crate::{
   db::Database,
   error::{Result, SmoothieError},
-  models::dto::BrowserTabDto,
-  repositories::BrowserTabRepository,
+  models::dto::{BrowserTabDto, CreateBrowserTabRequest},
+  repositories::{BrowserTabRepository, ProfilePart},
+  services::change_log::{ChangeLog, ChangeSubject},
+  services::profile_lock::ProfileLock,
+  utils::validation,
 };
 use std::process::Command;
 use uuid::Uuid;
@@ -30,29 +33,27 @@ impl BrowserService {
   pub async fn create_browser_tab(
     db: &Database,
     profile_id: &str,
-    url: String,
-    browser: String,
-    monitor_id: Option<String>,
-    tab_order: i32,
-    favicon: Option<String>,
+    req: CreateBrowserTabRequest,
   ) -> Result<BrowserTabDto> {
+    validation::validate(&req)?;
     let profile_uuid = parse_uuid(profile_id)?;
-    let monitor_uuid = match monitor_id {
+    let monitor_uuid = match req.monitor_id {
       Some(id) if !id.is_empty() && id != "00000000-0000-0000-0000-000000000000" => {
         Some(parse_uuid(&id)?)
       }
       _ => None,
     };
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
     let repo = BrowserTabRepository::new(db.pool());
 
     let entity = repo
       .create(
         profile_uuid,
-        &url,
-        &browser,
+        &req.url,
+        &req.browser,
         monitor_uuid,
-        tab_order,
-        favicon.as_deref(),
+        req.tab_order,
+        req.favicon.as_deref(),
       )
       .await?;
 
@@ -72,27 +73,77 @@ impl BrowserService {
     tab_id: &str,
     url: Option<String>,
   ) -> Result<BrowserTabDto> {
+    if let Some(url) = &url {
+      validation::validate_field("url", validation::web_url(url))?;
+    }
     let tab_uuid = parse_uuid(tab_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::BrowserTab, tab_uuid).await?;
     let repo = BrowserTabRepository::new(db.pool());
+    let before = repo
+      .find_by_id(tab_uuid)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Browser tab not found".into()))?;
 
     let entity = repo.update(tab_uuid, url.as_deref()).await?;
-    Ok(BrowserTabDto::from(entity))
+    let subject = ChangeSubject {
+      entity_type: "browser_tab",
+      entity_id: tab_uuid,
+      profile_id: entity.profile_id,
+    };
+    let updated = BrowserTabDto::from(entity);
+    ChangeLog::record_update(db, subject, &BrowserTabDto::from(before), &updated).await;
+    Ok(updated)
+  }
+
+  /// Put the profile's tabs in the order of `tab_ids` and return the new ordering
+  pub async fn reorder_browser_tabs(
+    db: &Database,
+    profile_id: &str,
+    tab_ids: &[String],
+  ) -> Result<Vec<BrowserTabDto>> {
+    let profile_uuid = parse_uuid(profile_id)?;
+    let ids = tab_ids
+      .iter()
+      .map(|id| parse_uuid(id))
+      .collect::<Result<Vec<_>>>()?;
+    validation::validate_field("tab_ids", validation::distinct_ids(&ids))?;
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
+
+    if !BrowserTabRepository::new(db.pool())
+      .reorder(profile_uuid, &ids)
+      .await?
+    {
+      return Err(SmoothieError::NotFound(
+        "Browser tab not found in this profile".into(),
+      ));
+    }
+    Self::get_browser_tabs(db, profile_id).await
   }
 
   pub async fn delete_browser_tab(db: &Database, tab_id: &str) -> Result<()> {
     let tab_uuid = parse_uuid(tab_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::BrowserTab, tab_uuid).await?;
     let repo = BrowserTabRepository::new(db.pool());
+    let existing = repo.find_by_id(tab_uuid).await?;
 
     let deleted = repo.delete(tab_uuid).await?;
     if !deleted {
       return Err(SmoothieError::NotFound("Browser tab not found".into()));
     }
 
+    if let Some(tab) = existing {
+      let subject = ChangeSubject {
+        entity_type: "browser_tab",
+        entity_id: tab_uuid,
+        profile_id: tab.profile_id,
+      };
+      ChangeLog::record_delete(db, subject, &BrowserTabDto::from(tab)).await;
+    }
     Ok(())
   }
 
   /// Get the bundle ID for a browser name
-  fn get_browser_bundle_id(browser: &str) -> &'static str {
+  pub(crate) fn get_browser_bundle_id(browser: &str) -> &'static str {
     match browser.to_lowercase().as_str() {
       "safari" => "com.apple.Safari",
       "chrome" | "google chrome" => "com.google.Chrome",
@@ -0,0 +1,199 @@
+// Field-level change records
+//
+// Update paths snapshot a record before and after writing it and hand both to
+// `ChangeLog::record_update`, which stores the fields that differ, old and
+// new, in the activity log's `details`. Deletes store the removed record
+// whole. Each entry is attributed to the owner of the profile it belongs to
+// and named after that profile, so "who changed the monitor arrangement of
+// the Office profile, and when" is a `history` lookup. Recorded changes also
+// feed the undo history in `edit_history`.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::{ActivityLogDto, ActivityStatus, LogQueryParams, ProfileId},
+  repositories::{JsonFilter, ProfileRepository},
+  services::{
+    audit_backend::audit_store,
+    edit_history::{Edit, EditHistory},
+    AUDIT_SERVICE,
+  },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// Changed on every write, so never worth recording
+const IGNORED_FIELDS: [&str; 1] = ["updatedAt"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+  pub field: String,
+  pub old: Value,
+  pub new: Value,
+}
+
+/// The record a change is about and the profile it belongs to
+#[derive(Debug, Clone, Copy)]
+pub struct ChangeSubject {
+  /// `profile`, `monitor`, `app` or `browser_tab`
+  pub entity_type: &'static str,
+  pub entity_id: Uuid,
+  pub profile_id: Uuid,
+}
+
+impl ChangeSubject {
+  pub fn profile(profile_id: Uuid) -> Self {
+    Self {
+      entity_type: "profile",
+      entity_id: profile_id,
+      profile_id,
+    }
+  }
+}
+
+pub struct ChangeLog;
+
+impl ChangeLog {
+  /// Log the fields that differ between two snapshots of a record as
+  /// `<entity_type>_updated`; nothing is logged when none do
+  pub async fn record_update<T: Serialize>(
+    db: &Database,
+    subject: ChangeSubject,
+    before: &T,
+    after: &T,
+  ) {
+    let changes = match (serde_json::to_value(before), serde_json::to_value(after)) {
+      (Ok(before), Ok(after)) => diff(&before, &after),
+      _ => return,
+    };
+    if changes.is_empty() {
+      return;
+    }
+    let details = json!({ "profile_id": subject.profile_id, "changes": changes });
+    EditHistory::push(Edit::Updated { subject, changes });
+    Self::record(db, subject, "updated", details).await;
+  }
+
+  /// Log a removed record in full as `<entity_type>_deleted`
+  pub async fn record_delete<T: Serialize>(db: &Database, subject: ChangeSubject, removed: &T) {
+    let Ok(removed) = serde_json::to_value(removed) else {
+      return;
+    };
+    let details = json!({ "profile_id": subject.profile_id, "deleted": removed });
+    EditHistory::push(Edit::Deleted { subject, removed });
+    Self::record(db, subject, "deleted", details).await;
+  }
+
+  /// Activity about a profile and everything in it, newest first
+  pub async fn history(
+    db: &Database,
+    profile_id: ProfileId,
+    limit: Option<i64>,
+  ) -> Result<Vec<ActivityLogDto>> {
+    let profile = ProfileRepository::new(db.pool())
+      .find_by_id(profile_id.as_uuid())
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?;
+    let params = LogQueryParams {
+      limit,
+      details: Some(JsonFilter::Contains(json!({ "profile_id": profile_id }))),
+      ..Default::default()
+    };
+    AUDIT_SERVICE
+      .get_activity_logs(db, &profile.user_id.to_string(), params)
+      .await
+  }
+
+  /// Log `<entity_type>_<verb>` without adding to the undo history
+  pub(crate) async fn record(db: &Database, subject: ChangeSubject, verb: &str, details: Value) {
+    // Failing to log a change shouldn't fail the change itself
+    let profile = match ProfileRepository::new(db.pool())
+      .find_by_id(subject.profile_id)
+      .await
+    {
+      Ok(Some(profile)) => profile,
+      Ok(None) => return,
+      Err(e) => {
+        tracing::warn!(error = %e, "Could not attribute profile change");
+        return;
+      }
+    };
+    let action = format!("{}_{}", subject.entity_type, verb);
+    if let Err(e) = audit_store(db)
+      .log_activity(
+        profile.user_id,
+        None,
+        &action,
+        Some(subject.entity_type),
+        Some(subject.entity_id),
+        Some(&profile.name),
+        Some(details),
+        ActivityStatus::Success,
+        None,
+        None,
+      )
+      .await
+    {
+      tracing::warn!(error = %e, action, "Failed to record profile change");
+    }
+  }
+}
+
+/// Top-level fields whose values differ between two JSON objects, by name
+pub fn diff(before: &Value, after: &Value) -> Vec<FieldChange> {
+  let empty = serde_json::Map::new();
+  let before = before.as_object().unwrap_or(&empty);
+  let after = after.as_object().unwrap_or(&empty);
+
+  let removed = before.keys().filter(|k| !after.contains_key(*k));
+  let mut changes: Vec<FieldChange> = after
+    .keys()
+    .chain(removed)
+    .filter(|field| !IGNORED_FIELDS.contains(&field.as_str()))
+    .filter_map(|field| {
+      let old = before.get(field).cloned().unwrap_or(Value::Null);
+      let new = after.get(field).cloned().unwrap_or(Value::Null);
+      (old != new).then(|| FieldChange {
+        field: field.clone(),
+        old,
+        new,
+      })
+    })
+    .collect();
+  changes.sort_by(|a, b| a.field.cmp(&b.field));
+  changes
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_diff_reports_changed_fields_only() {
+    let before = json!({ "x": 0, "y": 0, "width": 1920, "updatedAt": "a", "brand": "Dell" });
+    let after = json!({ "x": 1920, "y": 0, "width": 2560, "updatedAt": "b" });
+
+    assert_eq!(
+      diff(&before, &after),
+      vec![
+        FieldChange {
+          field: "brand".into(),
+          old: json!("Dell"),
+          new: Value::Null,
+        },
+        FieldChange {
+          field: "width".into(),
+          old: json!(1920),
+          new: json!(2560),
+        },
+        FieldChange {
+          field: "x".into(),
+          old: json!(0),
+          new: json!(1920),
+        },
+      ]
+    );
+    assert!(diff(&after, &after).is_empty());
+  }
+}
@@ -0,0 +1,324 @@
+//! Profile checklists
+//!
+//! A profile can carry a short list of things to check once it's active -
+//! "VPN connected?", "Timer started?". They're shown in a notification after
+//! every activation, and ticking one off is written to the activity log
+//! rather than stored on the item, so the log doubles as the record of what
+//! was confirmed and when. An item counts as done for the current activation
+//! when its latest entry since the profile was activated is a check.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::dto::{
+    ActivationChecklistDto, ChecklistItemDto, ChecklistItemRequest, ChecklistItemStatusDto,
+    LogQueryParams,
+  },
+  models::entities::ProfileEntity,
+  models::{ActivityStatus, ProfileId},
+  repositories::{JsonFilter, ProfileRepository},
+  services::{profile_lock::ProfileLock, SystemBackend, UserSettingsService, AUDIT_SERVICE},
+  utils::validation,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Most items one checklist can hold
+const MAX_CHECKLIST_ITEMS: usize = 20;
+
+const CHECKED: &str = "checklist_item_checked";
+const UNCHECKED: &str = "checklist_item_unchecked";
+
+fn parse_uuid(s: &str) -> Result<Uuid> {
+  Uuid::parse_str(s).map_err(|_| SmoothieError::ValidationError(format!("Invalid UUID: {}", s)))
+}
+
+pub struct ChecklistService;
+
+impl ChecklistService {
+  pub async fn get_items(db: &Database, profile_id: ProfileId) -> Result<Vec<ChecklistItemDto>> {
+    let items = ProfileRepository::new(db.pool())
+      .find_checklist_items(profile_id.as_uuid())
+      .await?;
+    Ok(items.into_iter().map(ChecklistItemDto::from).collect())
+  }
+
+  /// Replace a profile's checklist, in the given order
+  pub async fn set_items(
+    db: &Database,
+    profile_id: ProfileId,
+    items: Vec<ChecklistItemRequest>,
+  ) -> Result<Vec<ChecklistItemDto>> {
+    if items.len() > MAX_CHECKLIST_ITEMS {
+      return Err(SmoothieError::ValidationError(format!(
+        "A checklist can have at most {} items",
+        MAX_CHECKLIST_ITEMS
+      )));
+    }
+    for item in &items {
+      validation::validate(item)?;
+    }
+
+    let profile_uuid = profile_id.as_uuid();
+    let repo = ProfileRepository::new(db.pool());
+    repo
+      .find_by_id(profile_uuid)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?;
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
+
+    let existing: Vec<Uuid> = repo
+      .find_checklist_items(profile_uuid)
+      .await?
+      .into_iter()
+      .map(|item| item.id)
+      .collect();
+    let mut kept = Vec::new();
+    let items = items
+      .into_iter()
+      .map(|item| {
+        let id = item.id.as_deref().map(parse_uuid).transpose()?;
+        if let Some(id) = id {
+          if !existing.contains(&id) {
+            return Err(SmoothieError::NotFound("Checklist item not found".into()));
+          }
+          if kept.contains(&id) {
+            return Err(SmoothieError::ValidationError(format!(
+              "Checklist item {} is listed more than once",
+              id
+            )));
+          }
+          kept.push(id);
+        }
+        Ok((id, item.label.trim().to_string()))
+      })
+      .collect::<Result<Vec<_>>>()?;
+    let stored = repo.replace_checklist_items(profile_uuid, &items).await?;
+
+    tracing::info!(profile_id = %profile_id, count = stored.len(), "Checklist updated");
+
+    Ok(stored.into_iter().map(ChecklistItemDto::from).collect())
+  }
+
+  /// Show the checklist of a profile that was just activated. Failing to
+  /// doesn't fail the activation.
+  pub async fn present(db: &Database, system: &dyn SystemBackend, profile_id: ProfileId) {
+    let repo = ProfileRepository::new(db.pool());
+    let (profile, items) = match tokio::try_join!(
+      repo.find_by_id(profile_id.as_uuid()),
+      repo.find_checklist_items(profile_id.as_uuid())
+    ) {
+      Ok((Some(profile), items)) if !items.is_empty() => (profile, items),
+      Ok(_) => return,
+      Err(e) => {
+        tracing::warn!(error = %e, "Could not read the profile checklist");
+        return;
+      }
+    };
+
+    let notify = UserSettingsService::get_settings(db, profile.user_id)
+      .await
+      .map(|s| s.notifications_enabled)
+      .unwrap_or(true);
+    if notify {
+      let body = items
+        .iter()
+        .map(|item| format!("☐ {}", item.label))
+        .collect::<Vec<_>>()
+        .join("\n");
+      if let Err(e) =
+        system.show_notification(&format!("Smoothie: {} checklist", profile.name), &body)
+      {
+        tracing::warn!("Failed to show checklist notification: {}", e);
+      }
+    }
+
+    let _ = AUDIT_SERVICE
+      .log_activity(
+        db,
+        &profile.user_id.to_string(),
+        "checklist_presented",
+        Some("profile"),
+        Some(&profile.id.to_string()),
+        Some(&profile.name),
+        Some(json!({
+          "profile_id": profile.id,
+          "items": items.iter().map(|item| &item.label).collect::<Vec<_>>(),
+        })),
+        ActivityStatus::Success,
+        None,
+        None,
+      )
+      .await;
+  }
+
+  /// Check an item off, or take the check back, for the current activation
+  pub async fn set_item_completed(
+    db: &Database,
+    profile_id: ProfileId,
+    item_id: &str,
+    completed: bool,
+  ) -> Result<ActivationChecklistDto> {
+    let item_uuid = parse_uuid(item_id)?;
+    let repo = ProfileRepository::new(db.pool());
+    let profile = Self::find_profile(&repo, profile_id).await?;
+    let item = repo
+      .find_checklist_items(profile.id)
+      .await?
+      .into_iter()
+      .find(|item| item.id == item_uuid)
+      .ok_or_else(|| SmoothieError::NotFound("Checklist item not found".into()))?;
+
+    AUDIT_SERVICE
+      .log_activity(
+        db,
+        &profile.user_id.to_string(),
+        if completed { CHECKED } else { UNCHECKED },
+        Some("checklist_item"),
+        Some(&item.id.to_string()),
+        Some(&item.label),
+        Some(json!({ "profile_id": profile.id })),
+        ActivityStatus::Success,
+        None,
+        None,
+      )
+      .await?;
+
+    Self::activation_checklist(db, profile_id).await
+  }
+
+  /// The checklist and what's been checked off since the profile was last
+  /// activated
+  pub async fn activation_checklist(
+    db: &Database,
+    profile_id: ProfileId,
+  ) -> Result<ActivationChecklistDto> {
+    let repo = ProfileRepository::new(db.pool());
+    let profile = Self::find_profile(&repo, profile_id).await?;
+    let items = repo.find_checklist_items(profile.id).await?;
+
+    // Newest first, so the first entry per item is where it stands
+    let mut latest = HashMap::new();
+    if let (Some(activated_at), false) = (profile.last_activated_at, items.is_empty()) {
+      let params = LogQueryParams {
+        limit: Some(1000),
+        start_date: Some(activated_at.to_rfc3339()),
+        entity_type: Some("checklist_item".into()),
+        details: Some(JsonFilter::Contains(json!({ "profile_id": profile.id }))),
+        ..Default::default()
+      };
+      let logs = AUDIT_SERVICE
+        .get_activity_logs(db, &profile.user_id.to_string(), params)
+        .await?;
+      for log in logs {
+        if let Some(entity_id) = log.entity_id.clone() {
+          latest.entry(entity_id).or_insert(log);
+        }
+      }
+    }
+
+    let items = items
+      .into_iter()
+      .map(|item| {
+        let checked = latest
+          .get(&item.id.to_string())
+          .filter(|log| log.action == CHECKED);
+        ChecklistItemStatusDto {
+          id: item.id.to_string(),
+          label: item.label,
+          position: item.position,
+          completed: checked.is_some(),
+          completed_at: checked.map(|log| log.created_at.clone()),
+        }
+      })
+      .collect();
+
+    Ok(ActivationChecklistDto {
+      profile_id: profile.id.to_string(),
+      activated_at: profile.last_activated_at.map(|dt| dt.to_rfc3339()),
+      items,
+    })
+  }
+
+  async fn find_profile(
+    repo: &ProfileRepository<'_>,
+    profile_id: ProfileId,
+  ) -> Result<ProfileEntity> {
+    repo
+      .find_by_id(profile_id.as_uuid())
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use crate::models::ActivationSource;
+  use crate::services::system_backend::MockSystemBackend;
+  use crate::services::ProfileService;
+
+  #[tokio::test]
+  async fn test_checklist_is_shown_on_activation_and_tracks_checks() {
+    let test_db = test_db_or_skip!();
+    let (user_id, profile_id) = test_db.seed_user_and_profile("Client A").await;
+    let db = &test_db.db;
+
+    let item = |id: Option<&str>, label: &str| ChecklistItemRequest {
+      id: id.map(str::to_string),
+      label: label.into(),
+    };
+    let items = ChecklistService::set_items(
+      db,
+      profile_id.into(),
+      vec![item(None, "VPN connected?"), item(None, " Timer started? ")],
+    )
+    .await
+    .unwrap();
+    assert_eq!(items[1].label, "Timer started?");
+
+    ProfileService::activate_profile(
+      db,
+      profile_id.into(),
+      user_id.into(),
+      ActivationSource::Manual,
+    )
+    .await
+    .unwrap();
+    let system = MockSystemBackend::with_sample_layout();
+    ChecklistService::present(db, &system, profile_id.into()).await;
+    assert_eq!(system.notifications().len(), 1);
+
+    let status = ChecklistService::set_item_completed(db, profile_id.into(), &items[0].id, true)
+      .await
+      .unwrap();
+    assert!(status.items[0].completed);
+    assert!(!status.items[1].completed);
+
+    // Reordering keeps the check; a dropped item is gone
+    ChecklistService::set_items(
+      db,
+      profile_id.into(),
+      vec![
+        item(None, "Slack status set?"),
+        item(Some(&items[0].id), "VPN on?"),
+      ],
+    )
+    .await
+    .unwrap();
+    let status = ChecklistService::activation_checklist(db, profile_id.into())
+      .await
+      .unwrap();
+    assert_eq!(status.items.len(), 2);
+    assert_eq!(status.items[1].id, items[0].id);
+    assert!(status.items[1].completed);
+    assert!(!status.items[0].completed);
+
+    let status = ChecklistService::set_item_completed(db, profile_id.into(), &items[0].id, false)
+      .await
+      .unwrap();
+    assert!(!status.items[1].completed);
+  }
+}
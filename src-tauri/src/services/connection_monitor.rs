@@ -0,0 +1,224 @@
+//! Database connection monitor
+//!
+//! Postgres runs beside the app and can drop out from under it: the container
+//! is stopped, the laptop wakes up before the server does. The connection is
+//! pinged in the background, and whenever it goes down or comes back the new
+//! status is published for the UI (see `subscribe`) so it can show offline
+//! mode rather than a stream of database errors. Both ends of an outage are
+//! written to system_events once the database answers again, since nothing
+//! can be written while it's down.
+
+use crate::{
+  db::Database, models::Severity, services::audit_service::AUDIT_SERVICE, state::AppState,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Event the UI listens on for connection changes
+pub const CONNECTION_EVENT: &str = "db-connection-changed";
+
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+const PING_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Failed pings in a row before the database counts as offline, so one slow
+/// answer under load doesn't flip the UI into offline mode
+const FAILURES_TO_OFFLINE: u32 = 2;
+
+static STATUS: OnceLock<watch::Sender<ConnectionStatus>> = OnceLock::new();
+
+fn status_sender() -> &'static watch::Sender<ConnectionStatus> {
+  STATUS.get_or_init(|| watch::channel(ConnectionStatus::new(Utc::now())).0)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStatus {
+  pub online: bool,
+  /// When the connection last went down or came back
+  pub since: DateTime<Utc>,
+  pub checked_at: Option<DateTime<Utc>>,
+  /// Why the latest ping failed; cleared once one succeeds
+  pub last_error: Option<String>,
+  pub consecutive_failures: u32,
+  /// Outages since the app started
+  pub outages: u32,
+}
+
+impl ConnectionStatus {
+  fn new(now: DateTime<Utc>) -> Self {
+    Self {
+      online: true,
+      since: now,
+      checked_at: None,
+      last_error: None,
+      consecutive_failures: 0,
+      outages: 0,
+    }
+  }
+}
+
+/// How a ping changed the connection
+#[derive(Debug, Clone, PartialEq)]
+enum Transition {
+  Lost,
+  Restored {
+    lost_at: DateTime<Utc>,
+    error: Option<String>,
+  },
+}
+
+/// Fold a ping into `status`, returning the change it made, if any
+fn apply_ping(
+  status: &mut ConnectionStatus,
+  ping: std::result::Result<(), String>,
+  now: DateTime<Utc>,
+) -> Option<Transition> {
+  status.checked_at = Some(now);
+  match ping {
+    Ok(()) => {
+      status.consecutive_failures = 0;
+      let error = status.last_error.take();
+      if status.online {
+        return None;
+      }
+      let lost_at = status.since;
+      status.online = true;
+      status.since = now;
+      Some(Transition::Restored { lost_at, error })
+    }
+    Err(e) => {
+      status.consecutive_failures += 1;
+      status.last_error = Some(e);
+      if !status.online || status.consecutive_failures < FAILURES_TO_OFFLINE {
+        return None;
+      }
+      status.online = false;
+      status.since = now;
+      status.outages += 1;
+      Some(Transition::Lost)
+    }
+  }
+}
+
+pub struct ConnectionMonitor;
+
+impl ConnectionMonitor {
+  pub fn status() -> ConnectionStatus {
+    status_sender().borrow().clone()
+  }
+
+  pub fn is_online() -> bool {
+    status_sender().borrow().online
+  }
+
+  /// Changes to the connection status; only a drop or a recovery wakes it
+  pub fn subscribe() -> watch::Receiver<ConnectionStatus> {
+    status_sender().subscribe()
+  }
+
+  /// Ping the database in the background
+  pub fn spawn(state: Arc<AppState>) {
+    let monitor_state = state.clone();
+    state.tasks.spawn("db_connection_monitor", move || {
+      let db = monitor_state.db.clone();
+      async move {
+        let mut ticker = tokio::time::interval(PING_INTERVAL);
+        loop {
+          ticker.tick().await;
+          let ping = Self::ping(&db).await;
+          Self::record(&db, ping, Utc::now()).await;
+        }
+      }
+    });
+  }
+
+  async fn ping(db: &Database) -> std::result::Result<(), String> {
+    match tokio::time::timeout(PING_TIMEOUT, sqlx::query("SELECT 1").execute(db.pool())).await {
+      Ok(Ok(_)) => Ok(()),
+      Ok(Err(e)) => Err(e.to_string()),
+      Err(_) => Err(format!("No answer within {}ms", PING_TIMEOUT.as_millis())),
+    }
+  }
+
+  async fn record(db: &Database, ping: std::result::Result<(), String>, now: DateTime<Utc>) {
+    let mut transition = None;
+    status_sender().send_if_modified(|status| {
+      transition = apply_ping(status, ping, now);
+      transition.is_some()
+    });
+
+    match transition {
+      None => {}
+      Some(Transition::Lost) => {
+        let status = Self::status();
+        tracing::error!(
+          error = status.last_error.as_deref().unwrap_or_default(),
+          "Lost the database connection"
+        );
+      }
+      Some(Transition::Restored { lost_at, error }) => {
+        let downtime_ms = (now - lost_at).num_milliseconds();
+        tracing::info!(downtime_ms, "Database connection restored");
+        let _ = AUDIT_SERVICE
+          .log_system_event(
+            db,
+            "database_connection_lost",
+            Severity::Error,
+            "ConnectionMonitor",
+            "Lost the database connection",
+            Some(json!({ "lost_at": lost_at, "error": error })),
+            None,
+          )
+          .await;
+        let _ = AUDIT_SERVICE
+          .log_system_event(
+            db,
+            "database_connection_restored",
+            Severity::Info,
+            "ConnectionMonitor",
+            &format!("Database connection restored after {}s", downtime_ms / 1000),
+            Some(json!({ "lost_at": lost_at, "downtime_ms": downtime_ms })),
+            None,
+          )
+          .await;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_outage_needs_repeated_failures_and_ends_on_first_success() {
+    let start = Utc::now();
+    let at = |secs| start + chrono::Duration::seconds(secs);
+    let mut status = ConnectionStatus::new(start);
+
+    assert_eq!(apply_ping(&mut status, Err("refused".into()), at(10)), None);
+    assert!(status.online);
+    assert_eq!(
+      apply_ping(&mut status, Err("refused".into()), at(20)),
+      Some(Transition::Lost)
+    );
+    assert!(!status.online);
+    assert_eq!(status.outages, 1);
+    assert_eq!(apply_ping(&mut status, Err("refused".into()), at(30)), None);
+
+    assert_eq!(
+      apply_ping(&mut status, Ok(()), at(40)),
+      Some(Transition::Restored {
+        lost_at: at(20),
+        error: Some("refused".into()),
+      })
+    );
+    assert!(status.online);
+    assert_eq!((status.since, status.consecutive_failures), (at(40), 0));
+    assert_eq!(apply_ping(&mut status, Ok(()), at(50)), None);
+  }
+}
@@ -0,0 +1,509 @@
+// Profile deactivation actions - cleanup a profile runs when another one takes over
+//
+// Quitting the outgoing profile's apps, closing its tabs and putting audio back
+// keeps a switch from "work" to "personal" from leaving the work setup running.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::{
+    dto::{DeactivationActionDto, DeactivationActionRequest},
+    enums::DeactivationActionType,
+    ProfileId,
+  },
+  repositories::{AppRepository, BrowserTabRepository, ProfileRepository},
+  services::{profile_lock::ProfileLock, rule_action_service::run_with_timeout},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use uuid::Uuid;
+
+/// Key under which activation metadata records the outgoing profile's cleanup
+pub const DEACTIVATION_METADATA_KEY: &str = "deactivation";
+
+/// Each AppleScript or audio command gets this long
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct QuitAppsConfig {
+  /// Defaults to the apps saved in the profile
+  bundle_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct CloseTabsConfig {
+  /// URL prefixes to close; defaults to the tabs saved in the profile
+  urls: Option<Vec<String>>,
+  /// Limit closing to one browser
+  browser: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RestoreAudioConfig {
+  /// Output device name as shown in Sound settings
+  output_device: Option<String>,
+  volume: Option<u8>,
+  muted: Option<bool>,
+}
+
+/// A validated deactivation action, ready to run
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeactivationAction {
+  QuitApps {
+    bundle_ids: Option<Vec<String>>,
+  },
+  CloseTabs {
+    urls: Option<Vec<String>>,
+    browser: Option<String>,
+  },
+  RestoreAudio {
+    output_device: Option<String>,
+    volume: Option<u8>,
+    muted: Option<bool>,
+  },
+}
+
+/// How one deactivation action went
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeactivationResult {
+  pub position: i32,
+  pub action_type: DeactivationActionType,
+  pub success: bool,
+  pub message: String,
+  pub duration_ms: i64,
+}
+
+/// The deactivation phase of a profile switch, stored with the incoming
+/// profile's activation record
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeactivationPhase {
+  pub profile_id: String,
+  pub success: bool,
+  pub duration_ms: i64,
+  pub actions: Vec<DeactivationResult>,
+}
+
+impl DeactivationPhase {
+  /// Merge the phase into activation metadata, keeping any caller-supplied fields
+  pub fn attach_to_metadata(&self, metadata: Option<Value>) -> Option<Value> {
+    let phase = match serde_json::to_value(self) {
+      Ok(phase) => phase,
+      Err(e) => {
+        tracing::warn!("Failed to serialize deactivation phase: {}", e);
+        return metadata;
+      }
+    };
+    match metadata {
+      Some(Value::Object(mut map)) => {
+        map.insert(DEACTIVATION_METADATA_KEY.to_string(), phase);
+        Some(Value::Object(map))
+      }
+      Some(other) => Some(serde_json::json!({ "data": other, DEACTIVATION_METADATA_KEY: phase })),
+      None => Some(serde_json::json!({ DEACTIVATION_METADATA_KEY: phase })),
+    }
+  }
+}
+
+fn parse_config<T: serde::de::DeserializeOwned>(
+  action_type: DeactivationActionType,
+  config: &Value,
+) -> Result<T> {
+  let config = if config.is_null() {
+    Value::Object(Default::default())
+  } else {
+    config.clone()
+  };
+  serde_json::from_value(config).map_err(|e| {
+    SmoothieError::ValidationError(format!("Invalid {} action config: {}", action_type, e))
+  })
+}
+
+fn require_non_empty_items(items: &Option<Vec<String>>, field: &str) -> Result<()> {
+  if let Some(items) = items {
+    if items.is_empty() || items.iter().any(|item| item.trim().is_empty()) {
+      return Err(SmoothieError::ValidationError(format!(
+        "\"{}\" must list at least one non-empty value when given",
+        field
+      )));
+    }
+  }
+  Ok(())
+}
+
+/// AppleScript application name for browsers whose tabs can be scripted
+fn scriptable_browser(browser: &str) -> Option<&'static str> {
+  match browser.to_lowercase().as_str() {
+    "safari" => Some("Safari"),
+    "chrome" | "google chrome" => Some("Google Chrome"),
+    "brave" | "brave browser" => Some("Brave Browser"),
+    "edge" | "microsoft edge" => Some("Microsoft Edge"),
+    "vivaldi" => Some("Vivaldi"),
+    _ => None,
+  }
+}
+
+const SCRIPTABLE_BROWSERS: [&str; 5] = ["safari", "chrome", "brave", "edge", "vivaldi"];
+
+/// AppleScript string literals only need quotes and backslashes escaped
+fn escape(s: &str) -> String {
+  s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+async fn osascript(script: String) -> Result<String> {
+  let mut child = Command::new("osascript");
+  child.arg("-e").arg(script).kill_on_drop(true);
+  run_with_timeout(child, SCRIPT_TIMEOUT, "osascript").await
+}
+
+/// Quit an app by bundle ID. Checking first keeps AppleScript from
+/// launching an app just to quit it.
+pub(crate) async fn quit_app(bundle_id: &str) -> Result<String> {
+  osascript(format!(
+    r#"if application id "{id}" is running then tell application id "{id}" to quit"#,
+    id = escape(bundle_id)
+  ))
+  .await
+}
+
+/// Close the tabs whose URL starts with `url` in `browser`; false when the
+/// browser's tabs can't be scripted
+pub(crate) async fn close_tabs(browser: &str, url: &str) -> Result<bool> {
+  let Some(app) = scriptable_browser(browser) else {
+    return Ok(false);
+  };
+  osascript(format!(
+    r#"if application "{app}" is running then
+  tell application "{app}"
+    repeat with w in windows
+      close (every tab of w whose URL starts with "{url}")
+    end repeat
+  end tell
+end if"#,
+    app = app,
+    url = escape(url)
+  ))
+  .await?;
+  Ok(true)
+}
+
+impl DeactivationAction {
+  /// Validate a stored or submitted action config against its type
+  pub fn parse(action_type: DeactivationActionType, config: &Value) -> Result<Self> {
+    Ok(match action_type {
+      DeactivationActionType::QuitApps => {
+        let config: QuitAppsConfig = parse_config(action_type, config)?;
+        require_non_empty_items(&config.bundle_ids, "bundleIds")?;
+        DeactivationAction::QuitApps {
+          bundle_ids: config.bundle_ids,
+        }
+      }
+      DeactivationActionType::CloseTabs => {
+        let config: CloseTabsConfig = parse_config(action_type, config)?;
+        require_non_empty_items(&config.urls, "urls")?;
+        if let Some(browser) = &config.browser {
+          if scriptable_browser(browser).is_none() {
+            return Err(SmoothieError::ValidationError(format!(
+              "Tabs can't be closed in \"{}\"; supported browsers are {}",
+              browser,
+              SCRIPTABLE_BROWSERS.join(", ")
+            )));
+          }
+        }
+        DeactivationAction::CloseTabs {
+          urls: config.urls,
+          browser: config.browser,
+        }
+      }
+      DeactivationActionType::RestoreAudio => {
+        let config: RestoreAudioConfig = parse_config(action_type, config)?;
+        if config.output_device.is_none() && config.volume.is_none() && config.muted.is_none() {
+          return Err(SmoothieError::ValidationError(
+            "restore_audio action needs an \"outputDevice\", \"volume\" or \"muted\"".into(),
+          ));
+        }
+        if config.volume.is_some_and(|volume| volume > 100) {
+          return Err(SmoothieError::ValidationError(
+            "Volume must be between 0 and 100".into(),
+          ));
+        }
+        DeactivationAction::RestoreAudio {
+          output_device: config.output_device,
+          volume: config.volume,
+          muted: config.muted,
+        }
+      }
+    })
+  }
+
+  /// Run the action for the outgoing profile, returning a short description
+  async fn run(&self, db: &Database, profile_id: Uuid) -> Result<String> {
+    match self {
+      DeactivationAction::QuitApps { bundle_ids } => {
+        let bundle_ids = match bundle_ids {
+          Some(ids) => ids.clone(),
+          None => AppRepository::new(db.pool())
+            .find_by_profile_id(profile_id)
+            .await?
+            .into_iter()
+            .map(|app| app.bundle_id)
+            .collect(),
+        };
+
+        let mut failed = Vec::new();
+        for bundle_id in &bundle_ids {
+          if let Err(e) = quit_app(bundle_id).await {
+            tracing::warn!(bundle_id = %bundle_id, "Failed to quit app: {}", e);
+            failed.push(bundle_id.as_str());
+          }
+        }
+
+        if failed.is_empty() {
+          Ok(format!("Quit {} app(s)", bundle_ids.len()))
+        } else {
+          Err(SmoothieError::SystemError(format!(
+            "Failed to quit {}",
+            failed.join(", ")
+          )))
+        }
+      }
+      DeactivationAction::CloseTabs { urls, browser } => {
+        // Saved tabs close in their own browser; explicit URLs in every scriptable one
+        let targets: Vec<(String, String)> = match urls {
+          Some(urls) => {
+            let browsers = match browser {
+              Some(browser) => vec![browser.as_str()],
+              None => SCRIPTABLE_BROWSERS.to_vec(),
+            };
+            browsers
+              .into_iter()
+              .flat_map(|b| urls.iter().map(move |url| (b.to_string(), url.clone())))
+              .collect()
+          }
+          None => BrowserTabRepository::new(db.pool())
+            .find_by_profile_id(profile_id)
+            .await?
+            .into_iter()
+            .filter(|tab| {
+              browser
+                .as_ref()
+                .map_or(true, |b| b.eq_ignore_ascii_case(&tab.browser))
+            })
+            .map(|tab| (tab.browser, tab.url))
+            .collect(),
+        };
+
+        let mut closed = 0;
+        let mut skipped = Vec::new();
+        for (browser, url) in &targets {
+          if close_tabs(browser, url).await? {
+            closed += 1;
+          } else {
+            skipped.push(browser.as_str());
+          }
+        }
+
+        skipped.sort_unstable();
+        skipped.dedup();
+        if skipped.is_empty() {
+          Ok(format!("Closed tabs matching {} URL(s)", closed))
+        } else {
+          Ok(format!(
+            "Closed tabs matching {} URL(s); {} can't be scripted",
+            closed,
+            skipped.join(", ")
+          ))
+        }
+      }
+      DeactivationAction::RestoreAudio {
+        output_device,
+        volume,
+        muted,
+      } => {
+        let mut changes = Vec::new();
+        if let Some(device) = output_device {
+          // SwitchAudioSource comes from Homebrew's switchaudio-osx
+          let mut child = Command::new("SwitchAudioSource");
+          child
+            .args(["-t", "output", "-s"])
+            .arg(device)
+            .kill_on_drop(true);
+          run_with_timeout(child, SCRIPT_TIMEOUT, "SwitchAudioSource").await?;
+          changes.push(format!("output to {}", device));
+        }
+
+        let mut statements = Vec::new();
+        if let Some(volume) = volume {
+          statements.push(format!("set volume output volume {}", volume));
+          changes.push(format!("volume to {}%", volume));
+        }
+        if let Some(muted) = muted {
+          statements.push(format!("set volume output muted {}", muted));
+          changes.push(if *muted { "muted" } else { "unmuted" }.to_string());
+        }
+        if !statements.is_empty() {
+          osascript(statements.join("\n")).await?;
+        }
+
+        Ok(format!("Set {}", changes.join(", ")))
+      }
+    }
+  }
+}
+
+pub struct DeactivationService;
+
+impl DeactivationService {
+  /// A profile's deactivation actions in execution order
+  pub async fn get_actions(
+    db: &Database,
+    profile_id: ProfileId,
+  ) -> Result<Vec<DeactivationActionDto>> {
+    let profile_uuid = profile_id.as_uuid();
+    let actions = ProfileRepository::new(db.pool())
+      .find_deactivation_actions(profile_uuid)
+      .await?;
+    Ok(
+      actions
+        .into_iter()
+        .map(DeactivationActionDto::from)
+        .collect(),
+    )
+  }
+
+  /// Replace a profile's deactivation actions after validating each config
+  pub async fn set_actions(
+    db: &Database,
+    profile_id: ProfileId,
+    actions: Vec<DeactivationActionRequest>,
+  ) -> Result<Vec<DeactivationActionDto>> {
+    let profile_uuid = profile_id.as_uuid();
+    for (position, action) in actions.iter().enumerate() {
+      DeactivationAction::parse(action.action_type, &action.config).map_err(|e| match e {
+        SmoothieError::ValidationError(msg) => {
+          SmoothieError::ValidationError(format!("Action {}: {}", position, msg))
+        }
+        other => other,
+      })?;
+    }
+    let repo = ProfileRepository::new(db.pool());
+    repo
+      .find_by_id(profile_uuid)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?;
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
+
+    let actions: Vec<_> = actions
+      .into_iter()
+      .map(|action| match action.config {
+        Value::Null => (action.action_type, serde_json::json!({})),
+        config => (action.action_type, config),
+      })
+      .collect();
+    let stored = repo
+      .replace_deactivation_actions(profile_uuid, &actions)
+      .await?;
+
+    tracing::info!(profile_id = %profile_id, count = stored.len(), "Deactivation actions updated");
+
+    Ok(
+      stored
+        .into_iter()
+        .map(DeactivationActionDto::from)
+        .collect(),
+    )
+  }
+
+  /// Run the outgoing profile's cleanup one action after another. A failing
+  /// action is reported and the rest still run.
+  pub async fn run(db: &Database, profile_id: Uuid) -> Result<DeactivationPhase> {
+    let started = Instant::now();
+    let actions = ProfileRepository::new(db.pool())
+      .find_deactivation_actions(profile_id)
+      .await?;
+
+    let mut results = Vec::with_capacity(actions.len());
+    for action in actions {
+      let action_started = Instant::now();
+      let outcome = match DeactivationAction::parse(action.action_type, &action.config) {
+        Ok(parsed) => parsed.run(db, profile_id).await,
+        Err(e) => Err(e),
+      };
+      if let Err(e) = &outcome {
+        tracing::warn!(
+          profile_id = %profile_id,
+          position = action.position,
+          action_type = %action.action_type,
+          "Deactivation action failed: {}",
+          e
+        );
+      }
+
+      results.push(DeactivationResult {
+        position: action.position,
+        action_type: action.action_type,
+        success: outcome.is_ok(),
+        message: outcome.unwrap_or_else(|e| e.to_string()),
+        duration_ms: action_started.elapsed().as_millis() as i64,
+      });
+    }
+
+    Ok(DeactivationPhase {
+      profile_id: profile_id.to_string(),
+      success: results.iter().all(|result| result.success),
+      duration_ms: started.elapsed().as_millis() as i64,
+      actions: results,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_deactivation_configs_are_validated() {
+    assert_eq!(
+      DeactivationAction::parse(DeactivationActionType::QuitApps, &Value::Null).unwrap(),
+      DeactivationAction::QuitApps { bundle_ids: None }
+    );
+    assert_eq!(
+      DeactivationAction::parse(
+        DeactivationActionType::RestoreAudio,
+        &json!({"volume": 30, "muted": false})
+      )
+      .unwrap(),
+      DeactivationAction::RestoreAudio {
+        output_device: None,
+        volume: Some(30),
+        muted: Some(false),
+      }
+    );
+
+    let invalid = [
+      (DeactivationActionType::QuitApps, json!({"bundleIds": []})),
+      (
+        DeactivationActionType::CloseTabs,
+        json!({"browser": "firefox"}),
+      ),
+      (DeactivationActionType::CloseTabs, json!({"urls": [" "]})),
+      (DeactivationActionType::RestoreAudio, json!({})),
+      (DeactivationActionType::RestoreAudio, json!({"volume": 150})),
+    ];
+    for (action_type, config) in invalid {
+      assert!(
+        DeactivationAction::parse(action_type, &config).is_err(),
+        "{} {}",
+        action_type,
+        config
+      );
+    }
+  }
+}
@@ -0,0 +1,718 @@
+//! Declarative Service
+//!
+//! Profiles can be written down in a YAML or TOML file, e.g. one kept with the
+//! user's dotfiles, and `sync_from_declarative` makes the database match it:
+//! entries new to the file become profiles, changed entries are re-applied to
+//! their profile, and profiles whose entry was removed are deleted. Only
+//! profiles the file created (or adopted by name on the first sync) are ever
+//! touched. An entry is re-applied when the file changes, not when its profile
+//! is edited in the app, and only if its profile doesn't already match it. A
+//! profile's type is only set when it's created.
+//! A file can also be watched, so saving it syncs it and records the result
+//! as a system event.
+//!
+//! ```yaml
+//! profiles:
+//!   - name: Deep Work
+//!     key: deep-work          # optional, defaults to the name
+//!     type: work
+//!     tags: [focus]
+//!     monitors:
+//!       - { name: Studio Display, width: 5120, height: 2880, primary: true }
+//!     apps:
+//!       - { name: Xcode, bundle_id: com.apple.dt.Xcode }
+//!     tabs:
+//!       - { url: "https://github.com", browser: chrome }
+//! ```
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::{
+    CreateAppRequest, CreateBrowserTabRequest, CreateMonitorRequest, CreateProfileRequest,
+    ProfileId, ProfileType, Severity, UserId,
+  },
+  repositories::DeclarativeProfileRepository,
+  services::{
+    resource_guard::ThrottledTicker,
+    share_service::{ShareService, SharedProfile, SHARE_FORMAT},
+    ProfileService, UserSettingsService, AUDIT_SERVICE,
+  },
+  state::AppState,
+  utils::{crypto, markdown, validation},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Size and modification time of a file, or `None` when it's missing
+type Stamp = Option<(u64, SystemTime)>;
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeclarativeSyncReportDto {
+  /// Names of the profiles created, updated and removed, or that would be
+  /// on a dry run
+  pub created: Vec<String>,
+  pub updated: Vec<String>,
+  pub removed: Vec<String>,
+  /// Names of locked profiles the file would change, left as they are
+  pub locked: Vec<String>,
+  pub unchanged: u32,
+  pub dry_run: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeclarativeFile {
+  #[serde(default)]
+  profiles: Vec<DeclaredProfile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeclaredProfile {
+  key: Option<String>,
+  name: String,
+  description: Option<String>,
+  #[serde(rename = "type")]
+  profile_type: Option<ProfileType>,
+  #[serde(default)]
+  tags: Vec<String>,
+  /// Markdown
+  notes: Option<String>,
+  #[serde(default)]
+  monitors: Vec<DeclaredMonitor>,
+  #[serde(default)]
+  apps: Vec<DeclaredApp>,
+  #[serde(default)]
+  tabs: Vec<DeclaredTab>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeclaredMonitor {
+  name: String,
+  width: i32,
+  height: i32,
+  #[serde(default)]
+  x: i32,
+  #[serde(default)]
+  y: i32,
+  orientation: Option<String>,
+  #[serde(default)]
+  primary: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeclaredApp {
+  name: String,
+  bundle_id: String,
+  launch: Option<bool>,
+  monitor: Option<i32>,
+  startup_delay_ms: Option<i32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeclaredTab {
+  url: String,
+  browser: Option<String>,
+}
+
+/// A validated file entry, ready to apply
+struct Entry {
+  key: String,
+  profile: SharedProfile,
+  hash: String,
+}
+
+pub struct DeclarativeService;
+
+impl DeclarativeService {
+  /// Reconcile `user_id`'s profiles against the file at `path`. Nothing is
+  /// changed if any entry is invalid, or on a dry run.
+  pub async fn sync_from_declarative(
+    db: &Database,
+    user_id: UserId,
+    path: &str,
+    dry_run: bool,
+  ) -> Result<DeclarativeSyncReportDto> {
+    let path = resolve_path(path)?;
+    let source = path.to_string_lossy().into_owned();
+    let entries = parse_file(&path, &std::fs::read_to_string(&path)?)?;
+
+    let repo = DeclarativeProfileRepository::new(db.pool());
+    let user_uuid = user_id.as_uuid();
+    let mut managed: HashMap<String, _> = repo
+      .find_by_source(user_uuid, &source)
+      .await?
+      .into_iter()
+      .map(|m| (m.profile_key.clone(), m))
+      .collect();
+    let profiles = ProfileService::get_profiles(db, user_id).await?;
+    let names: HashMap<ProfileId, String> =
+      profiles.iter().map(|p| (p.id, p.name.clone())).collect();
+    let locked: HashSet<ProfileId> = profiles
+      .iter()
+      .filter(|p| p.is_locked)
+      .map(|p| p.id)
+      .collect();
+    // Profiles managed by any file can't be adopted by name
+    let taken: HashSet<ProfileId> = repo
+      .find_managed_profile_ids(user_uuid)
+      .await?
+      .into_iter()
+      .map(ProfileId::from)
+      .collect();
+
+    let mut report = DeclarativeSyncReportDto {
+      dry_run,
+      ..Default::default()
+    };
+    for entry in entries {
+      let name = entry.profile.profile.name.clone();
+      let existing = match managed.remove(&entry.key) {
+        Some(m) if m.content_hash == entry.hash => {
+          report.unchanged += 1;
+          continue;
+        }
+        Some(m) => Some(ProfileId::from(m.profile_id)),
+        None => profiles
+          .iter()
+          .find(|p| !taken.contains(&p.id) && p.name.eq_ignore_ascii_case(&name))
+          .map(|p| p.id),
+      };
+      let matches = match existing {
+        Some(profile_id) => {
+          let stored = ProfileService::get_profile_response(db, profile_id).await?;
+          applied_fields(&SharedProfile::private_copy(stored)) == applied_fields(&entry.profile)
+        }
+        None => false,
+      };
+      // Skipped without recording the entry, so it applies once unlocked
+      if existing.is_some_and(|id| !matches && locked.contains(&id)) {
+        report.locked.push(name);
+        continue;
+      }
+      if dry_run {
+        match existing {
+          Some(_) if matches => report.unchanged += 1,
+          Some(_) => report.updated.push(name),
+          None => report.created.push(name),
+        }
+        continue;
+      }
+
+      let profile_id = match existing {
+        Some(profile_id) if matches => {
+          report.unchanged += 1;
+          profile_id
+        }
+        Some(profile_id) => {
+          ShareService::replace_from_shared(db, user_id, profile_id, entry.profile).await?;
+          report.updated.push(name);
+          profile_id
+        }
+        None => {
+          let profile = ShareService::create_from_shared(db, user_id, entry.profile).await?;
+          report.created.push(name);
+          profile.id
+        }
+      };
+      repo
+        .upsert(
+          user_uuid,
+          &source,
+          &entry.key,
+          profile_id.as_uuid(),
+          &entry.hash,
+        )
+        .await?;
+    }
+
+    // Whatever is still managed lost its entry in the file
+    for removed in managed.into_values() {
+      let profile_id = ProfileId::from(removed.profile_id);
+      let name = names
+        .get(&profile_id)
+        .cloned()
+        .unwrap_or(removed.profile_key);
+      if locked.contains(&profile_id) {
+        report.locked.push(name);
+        continue;
+      }
+      if !dry_run {
+        ProfileService::delete_profile(db, profile_id).await?;
+      }
+      report.removed.push(name);
+    }
+
+    tracing::info!(
+      source = %source,
+      created = report.created.len(),
+      updated = report.updated.len(),
+      removed = report.removed.len(),
+      locked = report.locked.len(),
+      dry_run,
+      "Declarative profiles synced"
+    );
+    Ok(report)
+  }
+}
+
+impl DeclarativeService {
+  /// The absolute path of a profile file that exists and parses
+  pub fn check_file(path: &str) -> Result<String> {
+    let path = resolve_path(path)?;
+    parse_file(&path, &std::fs::read_to_string(&path)?)?;
+    Ok(path.to_string_lossy().into_owned())
+  }
+
+  /// Sync the watched profile file shortly after each save, recording what
+  /// changed, or why it couldn't be applied, as a system event
+  pub fn spawn_watcher(state: Arc<AppState>, user_id: Uuid) {
+    let watcher_state = state.clone();
+    state.tasks.spawn("declarative_watcher", move || {
+      let state = watcher_state.clone();
+      async move {
+        let mut ticker = ThrottledTicker::new(WATCH_INTERVAL);
+        let mut watched: Option<String> = None;
+        let (mut synced, mut previous): (Stamp, Stamp) = (None, None);
+        loop {
+          ticker.tick().await;
+          let path = match UserSettingsService::get_settings(&state.db, user_id).await {
+            Ok(settings) => settings.declarative_watch_path,
+            Err(e) => {
+              tracing::warn!("Failed to read declarative watch settings: {}", e);
+              continue;
+            }
+          };
+          if path != watched {
+            watched = path;
+            (synced, previous) = (None, None);
+          }
+          let Some(path) = watched.as_deref() else {
+            continue;
+          };
+
+          let current = file_stamp(Path::new(path));
+          if settled_change(&synced, &previous, &current) {
+            Self::sync_watched(&state, user_id, path).await;
+            synced = current;
+          }
+          previous = current;
+        }
+      }
+    });
+  }
+
+  async fn sync_watched(state: &AppState, user_id: Uuid, path: &str) {
+    let (event_type, severity, message, details) =
+      match Self::sync_from_declarative(&state.db, user_id.into(), path, false).await {
+        Ok(report) if report.created.len() + report.updated.len() + report.removed.len() == 0 => {
+          return;
+        }
+        Ok(report) => {
+          state.invalidate_cache(&format!("profiles_{}", user_id));
+          (
+            "declarative_sync",
+            Severity::Info,
+            format!(
+              "Profile file synced: {} created, {} updated, {} removed",
+              report.created.len(),
+              report.updated.len(),
+              report.removed.len()
+            ),
+            json!({ "path": path, "report": report }),
+          )
+        }
+        Err(e) => (
+          "declarative_sync_failed",
+          Severity::Warning,
+          format!("Profile file could not be synced: {}", e),
+          json!({ "path": path }),
+        ),
+      };
+    let _ = AUDIT_SERVICE
+      .log_system_event(
+        &state.db,
+        event_type,
+        severity,
+        "DeclarativeService",
+        &message,
+        Some(details),
+        None,
+      )
+      .await;
+  }
+}
+
+fn file_stamp(path: &Path) -> Stamp {
+  let meta = std::fs::metadata(path).ok()?;
+  Some((meta.len(), meta.modified().ok()?))
+}
+
+/// Whether the file changed since it was last synced and has looked the same
+/// for a whole interval, so a save still being written is left alone
+fn settled_change(synced: &Stamp, previous: &Stamp, current: &Stamp) -> bool {
+  current != synced && current == previous
+}
+
+/// An absolute path with `~/` expanded, so every spelling of a file is
+/// tracked as the same source
+fn resolve_path(path: &str) -> Result<PathBuf> {
+  let path = match path.trim().strip_prefix("~/") {
+    Some(rest) => dirs::home_dir()
+      .ok_or_else(|| SmoothieError::SystemError("No home directory".into()))?
+      .join(rest),
+    None => PathBuf::from(path.trim()),
+  };
+  Ok(path.canonicalize()?)
+}
+
+fn parse_file(path: &Path, text: &str) -> Result<Vec<Entry>> {
+  let extension = path
+    .extension()
+    .and_then(|e| e.to_str())
+    .unwrap_or_default()
+    .to_ascii_lowercase();
+  let file: DeclarativeFile = match extension.as_str() {
+    "yaml" | "yml" => serde_yaml::from_str(text)
+      .map_err(|e| SmoothieError::ValidationError(format!("Invalid YAML: {}", e)))?,
+    "toml" => toml::from_str(text)
+      .map_err(|e| SmoothieError::ValidationError(format!("Invalid TOML: {}", e)))?,
+    _ => {
+      return Err(SmoothieError::ValidationError(
+        "Profile files must end in .yaml, .yml or .toml".into(),
+      ))
+    }
+  };
+
+  let mut keys = HashSet::new();
+  file
+    .profiles
+    .into_iter()
+    .map(|declared| {
+      let key = declared
+        .key
+        .clone()
+        .unwrap_or_else(|| declared.name.clone())
+        .trim()
+        .to_string();
+      if !keys.insert(key.to_lowercase()) {
+        return Err(SmoothieError::ValidationError(format!(
+          "Profile \"{}\" is defined twice",
+          key
+        )));
+      }
+      let profile = shared_profile(declared);
+      validation::validate(&profile)?;
+      validation::validate(&profile.profile)?;
+      profile.monitors.iter().try_for_each(validation::validate)?;
+      profile.apps.iter().try_for_each(validation::validate)?;
+      profile
+        .browser_tabs
+        .iter()
+        .try_for_each(validation::validate)?;
+      let hash = crypto::sha256_hex(&serde_json::to_vec(&profile)?);
+      Ok(Entry { key, profile, hash })
+    })
+    .collect()
+}
+
+/// What applying `profile` sets, equal for two profiles when applying one over
+/// the other would change nothing. The type is only set on creation, and app
+/// paths, favicons and tab monitors belong to the machine, so they're left out.
+fn applied_fields(profile: &SharedProfile) -> serde_json::Value {
+  let mut tags = profile.profile.tags.clone().unwrap_or_default();
+  tags.sort();
+  tags.dedup();
+  let monitors: Vec<_> = profile
+    .monitors
+    .iter()
+    .map(|m| {
+      json!([
+        m.name,
+        m.resolution,
+        m.orientation,
+        m.is_primary,
+        m.x,
+        m.y,
+        m.width,
+        m.height,
+        m.display_index
+      ])
+    })
+    .collect();
+  let apps: Vec<_> = profile
+    .apps
+    .iter()
+    .map(|a| {
+      json!([
+        a.name,
+        a.bundle_id,
+        a.launch_on_activate,
+        a.monitor_preference,
+        a.startup_delay_ms.unwrap_or(0),
+        a.order_index.unwrap_or(0)
+      ])
+    })
+    .collect();
+  let tabs: Vec<_> = profile
+    .browser_tabs
+    .iter()
+    .map(|t| json!([t.browser, t.url, t.tab_order]))
+    .collect();
+  json!({
+    "name": profile.profile.name,
+    "description": profile.profile.description,
+    "tags": tags,
+    "notes": profile.profile.notes.as_deref().and_then(markdown::sanitize_notes),
+    "monitors": monitors,
+    "apps": apps,
+    "tabs": tabs,
+  })
+}
+
+fn shared_profile(declared: DeclaredProfile) -> SharedProfile {
+  SharedProfile {
+    format: SHARE_FORMAT,
+    profile: CreateProfileRequest {
+      name: declared.name,
+      description: declared.description,
+      profile_type: declared.profile_type.unwrap_or(ProfileType::Custom),
+      tags: Some(declared.tags),
+      notes: declared.notes,
+    },
+    monitors: declared
+      .monitors
+      .into_iter()
+      .enumerate()
+      .map(|(i, m)| CreateMonitorRequest {
+        name: m.name,
+        resolution: format!("{}x{}", m.width, m.height),
+        orientation: m.orientation.unwrap_or_else(|| "landscape".into()),
+        is_primary: m.primary,
+        x: m.x,
+        y: m.y,
+        width: m.width,
+        height: m.height,
+        display_index: i as i32,
+      })
+      .collect(),
+    apps: declared
+      .apps
+      .into_iter()
+      .enumerate()
+      .map(|(i, a)| CreateAppRequest {
+        name: a.name,
+        bundle_id: a.bundle_id,
+        exe_path: None,
+        launch_on_activate: a.launch.unwrap_or(true),
+        monitor_preference: a.monitor,
+        startup_delay_ms: a.startup_delay_ms,
+        order_index: Some(i as i32),
+      })
+      .collect(),
+    browser_tabs: declared
+      .tabs
+      .into_iter()
+      .enumerate()
+      .map(|(i, t)| CreateBrowserTabRequest {
+        url: t.url,
+        browser: t.browser.unwrap_or_else(|| "safari".into()),
+        monitor_id: None,
+        tab_order: i as i32,
+        favicon: None,
+      })
+      .collect(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use uuid::Uuid;
+
+  #[test]
+  fn test_yaml_and_toml_describe_the_same_profile() {
+    let yaml = r#"
+profiles:
+  - name: Deep Work
+    type: work
+    tags: [focus]
+    monitors:
+      - { name: Studio Display, width: 5120, height: 2880, primary: true }
+    apps:
+      - { name: Xcode, bundle_id: com.apple.dt.Xcode }
+    tabs:
+      - { url: "https://github.com", browser: chrome }
+"#;
+    let toml = r#"
+[[profiles]]
+name = "Deep Work"
+type = "work"
+tags = ["focus"]
+monitors = [{ name = "Studio Display", width = 5120, height = 2880, primary = true }]
+apps = [{ name = "Xcode", bundle_id = "com.apple.dt.Xcode" }]
+tabs = [{ url = "https://github.com", browser = "chrome" }]
+"#;
+    let from_yaml = parse_file(Path::new("profiles.yaml"), yaml).unwrap();
+    let from_toml = parse_file(Path::new("profiles.toml"), toml).unwrap();
+
+    assert_eq!(from_yaml.len(), 1);
+    assert_eq!(from_yaml[0].key, "Deep Work");
+    assert_eq!(from_yaml[0].hash, from_toml[0].hash);
+    let profile = &from_yaml[0].profile;
+    assert_eq!(profile.monitors[0].resolution, "5120x2880");
+    assert!(profile.apps[0].launch_on_activate);
+  }
+
+  #[test]
+  fn test_watcher_waits_for_a_save_to_settle() {
+    let t = SystemTime::UNIX_EPOCH;
+    let (old, new) = (Some((10, t)), Some((12, t + Duration::from_secs(1))));
+
+    assert!(!settled_change(&old, &old, &new));
+    assert!(settled_change(&old, &new, &new));
+    assert!(!settled_change(&new, &new, &new));
+    // Deleting the file is reported once it stays deleted
+    assert!(settled_change(&new, &None, &None));
+  }
+
+  #[test]
+  fn test_rejects_unknown_fields_and_repeated_keys() {
+    let typo = "profile:\n  - name: Deep Work\n";
+    assert!(parse_file(Path::new("p.yml"), typo).is_err());
+
+    let twice = "profiles:\n  - name: Deep Work\n  - name: Writing\n    key: deep work\n";
+    assert!(parse_file(Path::new("p.yml"), twice).is_err());
+
+    let bad_url = "profiles:\n  - name: Deep Work\n    tabs:\n      - url: github.com\n";
+    assert!(parse_file(Path::new("p.yml"), bad_url).is_err());
+  }
+
+  #[tokio::test]
+  async fn test_sync_creates_updates_and_removes_only_its_own_profiles() {
+    let test_db = test_db_or_skip!();
+    let user = Uuid::new_v4();
+    ProfileService::ensure_user_exists(&test_db.db, user)
+      .await
+      .unwrap();
+    let own = ProfileService::create_profile(
+      &test_db.db,
+      user.into(),
+      CreateProfileRequest {
+        name: "Gaming".into(),
+        description: None,
+        profile_type: ProfileType::Gaming,
+        tags: None,
+        notes: None,
+      },
+    )
+    .await
+    .unwrap();
+
+    let path = std::env::temp_dir().join(format!("smoothie-{}.yaml", Uuid::new_v4()));
+    let path_str = path.to_string_lossy().into_owned();
+    let sync = |dry_run| {
+      DeclarativeService::sync_from_declarative(&test_db.db, user.into(), &path_str, dry_run)
+    };
+
+    std::fs::write(&path, "profiles:\n  - name: Deep Work\n  - name: Writing\n").unwrap();
+    let plan = sync(true).await.unwrap();
+    assert_eq!(plan.created, ["Deep Work", "Writing"]);
+    assert!(ProfileService::get_profiles(&test_db.db, user.into())
+      .await
+      .unwrap()
+      .iter()
+      .all(|p| p.id == own.id));
+
+    sync(false).await.unwrap();
+    assert_eq!(sync(false).await.unwrap().unchanged, 2);
+
+    // A type is only set on creation, so changing it alone updates nothing
+    std::fs::write(
+      &path,
+      "profiles:\n  - name: Deep Work\n    type: work\n  - name: Writing\n",
+    )
+    .unwrap();
+    let report = sync(false).await.unwrap();
+    assert!(report.updated.is_empty());
+    assert_eq!(report.unchanged, 2);
+
+    std::fs::write(&path, "profiles:\n  - name: Deep Work\n    tags: [focus]\n").unwrap();
+    let report = sync(false).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(report.updated, ["Deep Work"]);
+    assert_eq!(report.removed, ["Writing"]);
+
+    let mut names: Vec<_> = ProfileService::get_profiles(&test_db.db, user.into())
+      .await
+      .unwrap()
+      .into_iter()
+      .map(|p| (p.name, p.tags))
+      .collect();
+    names.sort();
+    assert_eq!(
+      names,
+      [
+        ("Deep Work".to_string(), vec!["focus".to_string()]),
+        ("Gaming".to_string(), vec![])
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_sync_skips_locked_profiles_and_applies_the_rest() {
+    let test_db = test_db_or_skip!();
+    let user = Uuid::new_v4();
+    ProfileService::ensure_user_exists(&test_db.db, user)
+      .await
+      .unwrap();
+    let path = std::env::temp_dir().join(format!("smoothie-{}.yaml", Uuid::new_v4()));
+    let path_str = path.to_string_lossy().into_owned();
+    let sync =
+      || DeclarativeService::sync_from_declarative(&test_db.db, user.into(), &path_str, false);
+
+    std::fs::write(&path, "profiles:\n  - name: Deep Work\n  - name: Writing\n").unwrap();
+    sync().await.unwrap();
+    let profiles = ProfileService::get_profiles(&test_db.db, user.into())
+      .await
+      .unwrap();
+    let deep_work = profiles.iter().find(|p| p.name == "Deep Work").unwrap();
+    ProfileService::set_locked(&test_db.db, deep_work.id, true)
+      .await
+      .unwrap();
+
+    std::fs::write(
+      &path,
+      "profiles:\n  - name: Deep Work\n    tags: [focus]\n  - name: Writing\n    tags: [drafts]\n",
+    )
+    .unwrap();
+    let report = sync().await.unwrap();
+    assert_eq!(report.locked, ["Deep Work"]);
+    assert_eq!(report.updated, ["Writing"]);
+
+    // Not recorded as applied, nor removed while locked; it catches up once unlocked
+    std::fs::write(&path, "profiles:\n  - name: Writing\n    tags: [drafts]\n").unwrap();
+    let report = sync().await.unwrap();
+    assert_eq!(report.locked, ["Deep Work"]);
+    assert!(report.removed.is_empty());
+    ProfileService::set_locked(&test_db.db, deep_work.id, false)
+      .await
+      .unwrap();
+    let report = sync().await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(report.removed, ["Deep Work"]);
+  }
+}
@@ -0,0 +1,472 @@
+// Demo service - seeds realistic profiles, activations and logs
+//
+// Lets the dashboard and analytics views be developed and demoed without weeks
+// of real usage. Only available in debug builds, or when SMOOTHIE_DEMO_MODE=1.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::dto::{CreateMonitorRequest, CreateProfileRequest},
+  models::{ActivationSource, ActivityStatus, ProfileType, UserId},
+  repositories::{AppRepository, ProfileRepository},
+  services::{MonitorService, ProfileService},
+};
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Tag attached to every seeded profile so a re-seed can find and replace them
+pub const DEMO_TAG: &str = "demo";
+
+/// Days of history generated for activations and activity logs
+const HISTORY_DAYS: i64 = 30;
+
+struct DemoProfile {
+  name: &'static str,
+  description: &'static str,
+  profile_type: ProfileType,
+  tags: &'static [&'static str],
+  apps: &'static [(&'static str, &'static str)],
+  /// Relative weight when picking which profile is activated on a given day
+  weight: u64,
+}
+
+const DEMO_PROFILES: &[DemoProfile] = &[
+  DemoProfile {
+    name: "Deep Work",
+    description: "Editor and terminal on the external display, chat closed",
+    profile_type: ProfileType::Work,
+    tags: &["coding", "focus"],
+    apps: &[
+      ("Visual Studio Code", "com.microsoft.VSCode"),
+      ("Terminal", "com.apple.Terminal"),
+      ("Safari", "com.apple.Safari"),
+    ],
+    weight: 5,
+  },
+  DemoProfile {
+    name: "Meetings",
+    description: "Calendar, Zoom and notes side by side",
+    profile_type: ProfileType::Work,
+    tags: &["calls"],
+    apps: &[
+      ("zoom.us", "us.zoom.xos"),
+      ("Calendar", "com.apple.iCal"),
+      ("Notes", "com.apple.Notes"),
+    ],
+    weight: 3,
+  },
+  DemoProfile {
+    name: "Design Review",
+    description: "Figma full screen on the external display",
+    profile_type: ProfileType::Work,
+    tags: &["design"],
+    apps: &[
+      ("Figma", "com.figma.Desktop"),
+      ("Slack", "com.tinyspeck.slackmacgap"),
+    ],
+    weight: 2,
+  },
+  DemoProfile {
+    name: "Evening",
+    description: "Music and browsing on the laptop screen only",
+    profile_type: ProfileType::Personal,
+    tags: &["relax"],
+    apps: &[("Music", "com.apple.Music"), ("Safari", "com.apple.Safari")],
+    weight: 2,
+  },
+];
+
+const ACTIVATION_SOURCES: &[ActivationSource] = &[
+  ActivationSource::Manual,
+  ActivationSource::Manual,
+  ActivationSource::Shortcut,
+  ActivationSource::Schedule,
+  ActivationSource::MonitorChange,
+];
+
+/// Summary of what `seed_demo_data` created
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DemoSeedResult {
+  pub profiles_created: usize,
+  pub profiles_replaced: usize,
+  pub activations_created: usize,
+  pub activity_logs_created: usize,
+  pub error_logs_created: usize,
+}
+
+pub struct DemoService;
+
+impl DemoService {
+  /// Whether demo data may be seeded in this build
+  pub fn is_enabled() -> bool {
+    cfg!(debug_assertions) || std::env::var("SMOOTHIE_DEMO_MODE").as_deref() == Ok("1")
+  }
+
+  /// Seed demo profiles plus 30 days of activation history and logs.
+  ///
+  /// Profiles from a previous seed (tagged `demo`) are deleted first, along with
+  /// their history, so the command can be run repeatedly.
+  pub async fn seed_demo_data(db: &Database, user_id: UserId) -> Result<DemoSeedResult> {
+    if !Self::is_enabled() {
+      return Err(SmoothieError::ValidationError(
+        "Demo data can only be seeded in debug builds or with SMOOTHIE_DEMO_MODE=1".into(),
+      ));
+    }
+
+    let user_uuid = user_id.as_uuid();
+    let profiles_replaced = Self::clear_demo_data(db, user_uuid).await?;
+
+    let mut profile_ids = Vec::with_capacity(DEMO_PROFILES.len());
+    for demo in DEMO_PROFILES {
+      let mut tags: Vec<String> = demo.tags.iter().map(|t| t.to_string()).collect();
+      tags.push(DEMO_TAG.to_string());
+
+      let profile = ProfileService::create_profile(
+        db,
+        user_id,
+        CreateProfileRequest {
+          name: demo.name.to_string(),
+          description: Some(demo.description.to_string()),
+          profile_type: demo.profile_type,
+          tags: Some(tags),
+          notes: None,
+        },
+      )
+      .await?;
+      let profile_uuid = profile.id.as_uuid();
+
+      MonitorService::create_monitor(
+        db,
+        &profile.id.to_string(),
+        CreateMonitorRequest {
+          name: "Built-in Retina Display".to_string(),
+          resolution: "3024x1964".to_string(),
+          orientation: "Landscape".to_string(),
+          is_primary: demo.profile_type == ProfileType::Personal,
+          x: 0,
+          y: 0,
+          width: 1512,
+          height: 982,
+          display_index: 0,
+        },
+      )
+      .await?;
+      if demo.profile_type == ProfileType::Work {
+        MonitorService::create_monitor(
+          db,
+          &profile.id.to_string(),
+          CreateMonitorRequest {
+            name: "DELL U2720Q".to_string(),
+            resolution: "3840x2160".to_string(),
+            orientation: "Landscape".to_string(),
+            is_primary: true,
+            x: 1512,
+            y: -200,
+            width: 2560,
+            height: 1440,
+            display_index: 1,
+          },
+        )
+        .await?;
+      }
+
+      let app_repo = AppRepository::new(db.pool());
+      for (index, (name, bundle_id)) in demo.apps.iter().enumerate() {
+        app_repo
+          .create(
+            profile_uuid,
+            name,
+            bundle_id,
+            None,
+            true,
+            None,
+            Some(index as i32 * 250),
+            Some(index as i32),
+          )
+          .await?;
+      }
+
+      profile_ids.push((profile_uuid, demo));
+    }
+
+    let activations_created = Self::seed_activations(db, user_uuid, &profile_ids).await?;
+    let activity_logs_created = activations_created;
+    let error_logs_created = Self::seed_error_logs(db, user_uuid).await?;
+
+    tracing::info!(
+      user_id = %user_id,
+      profiles = profile_ids.len(),
+      activations = activations_created,
+      "Demo data seeded"
+    );
+
+    Ok(DemoSeedResult {
+      profiles_created: profile_ids.len(),
+      profiles_replaced,
+      activations_created,
+      activity_logs_created,
+      error_logs_created,
+    })
+  }
+
+  /// Delete profiles from a previous seed; activations cascade with them
+  async fn clear_demo_data(db: &Database, user_id: Uuid) -> Result<usize> {
+    let repo = ProfileRepository::new(db.pool());
+    let mut removed = 0;
+    for profile in repo.find_by_user_id(user_id).await? {
+      if repo
+        .find_tags(profile.id)
+        .await?
+        .iter()
+        .any(|t| t == DEMO_TAG)
+      {
+        repo.delete(profile.id).await?;
+        removed += 1;
+      }
+    }
+
+    // Seeded logs aren't tied to a profile, so match them by their demo marker
+    sqlx::query("DELETE FROM activity_logs WHERE user_id = $1 AND details @> '{\"demo\": true}'")
+      .bind(user_id)
+      .execute(db.pool())
+      .await
+      .map_err(SmoothieError::from)?;
+    sqlx::query("DELETE FROM error_logs WHERE user_id = $1 AND context @> '{\"demo\": true}'")
+      .bind(user_id)
+      .execute(db.pool())
+      .await
+      .map_err(SmoothieError::from)?;
+
+    Ok(removed)
+  }
+
+  /// One to three activations a day, backdated over the history window
+  async fn seed_activations(
+    db: &Database,
+    user_id: Uuid,
+    profiles: &[(Uuid, &DemoProfile)],
+  ) -> Result<usize> {
+    let total_weight: u64 = profiles.iter().map(|(_, p)| p.weight).sum();
+    let mut rng = DemoRng::new(0x5EED);
+    let mut previous: Option<Uuid> = None;
+    let mut created = 0;
+
+    for day in (0..HISTORY_DAYS).rev() {
+      let activations_today = 1 + rng.next(3);
+      for slot in 0..activations_today {
+        let mut pick = rng.next(total_weight);
+        let (profile_id, demo) = profiles
+          .iter()
+          .find(|(_, p)| {
+            if pick < p.weight {
+              true
+            } else {
+              pick -= p.weight;
+              false
+            }
+          })
+          .unwrap_or(&profiles[0]);
+
+        let started_at = Utc::now() - Duration::days(day) - Duration::hours(10)
+          + Duration::hours(slot as i64 * 4)
+          - Duration::minutes(rng.next(50) as i64);
+        let duration_ms = 600 + rng.next(2400) as i32;
+        let apps = demo.apps.len() as i32;
+        // Roughly one activation in twelve fails to launch an app
+        let apps_failed = i32::from(rng.next(12) == 0);
+        let success = apps_failed == 0;
+        let source = ACTIVATION_SOURCES[rng.next(ACTIVATION_SOURCES.len() as u64) as usize];
+        let monitors = if demo.profile_type == ProfileType::Work {
+          2
+        } else {
+          1
+        };
+
+        sqlx::query(
+          r#"
+          INSERT INTO profile_activations (
+            user_id, profile_id, activation_source, previous_profile_id,
+            monitors_detected, monitors_applied, apps_detected, apps_launched, apps_failed,
+            tabs_detected, tabs_opened, windows_restored, duration_ms, success,
+            error_message, metadata, started_at, completed_at
+          )
+          VALUES ($1, $2, $3, $4, $5, $5, $6, $7, $8, 0, 0, $7, $9, $10, $11, $12, $13, $14)
+          "#,
+        )
+        .bind(user_id)
+        .bind(profile_id)
+        .bind(source)
+        .bind(previous)
+        .bind(monitors)
+        .bind(apps)
+        .bind(apps - apps_failed)
+        .bind(apps_failed)
+        .bind(duration_ms)
+        .bind(success)
+        .bind((!success).then_some("App failed to launch within timeout"))
+        .bind(serde_json::json!({ "demo": true }))
+        .bind(started_at)
+        .bind(started_at + Duration::milliseconds(duration_ms as i64))
+        .execute(db.pool())
+        .await
+        .map_err(SmoothieError::from)?;
+
+        sqlx::query(
+          r#"
+          INSERT INTO activity_logs (
+            user_id, action, entity_type, entity_id, entity_name, details, status,
+            error_message, duration_ms, created_at
+          )
+          VALUES ($1, 'profile_activated', 'profile', $2, $3, $4, $5, $6, $7, $8)
+          "#,
+        )
+        .bind(user_id)
+        .bind(profile_id)
+        .bind(demo.name)
+        .bind(serde_json::json!({ "demo": true, "activation_source": source }))
+        .bind(if success {
+          ActivityStatus::Success
+        } else {
+          ActivityStatus::Failure
+        })
+        .bind((!success).then_some("App failed to launch within timeout"))
+        .bind(duration_ms)
+        .bind(started_at)
+        .execute(db.pool())
+        .await
+        .map_err(SmoothieError::from)?;
+
+        previous = Some(*profile_id);
+        created += 1;
+      }
+    }
+
+    // Keep the profile counters in line with the generated history
+    sqlx::query(
+      r#"
+      UPDATE profiles p
+      SET activation_count = stats.total,
+          last_activated_at = stats.last_at,
+          last_used = stats.last_at
+      FROM (
+        SELECT profile_id, COUNT(*) AS total, MAX(started_at) AS last_at
+        FROM profile_activations
+        WHERE user_id = $1
+        GROUP BY profile_id
+      ) stats
+      WHERE p.id = stats.profile_id
+      "#,
+    )
+    .bind(user_id)
+    .execute(db.pool())
+    .await
+    .map_err(SmoothieError::from)?;
+
+    Ok(created)
+  }
+
+  async fn seed_error_logs(db: &Database, user_id: Uuid) -> Result<usize> {
+    let errors = [
+      (
+        "app_launch",
+        "warning",
+        "Figma did not report ready within 10s",
+        3,
+      ),
+      ("display", "error", "displayplacer exited with status 1", 1),
+      (
+        "permission",
+        "info",
+        "Screen Recording permission not granted",
+        1,
+      ),
+    ];
+
+    for (index, (error_type, severity, message, occurrences)) in errors.iter().enumerate() {
+      let last = Utc::now() - Duration::days(index as i64 * 3);
+      sqlx::query(
+        r#"
+        INSERT INTO error_logs (
+          user_id, error_type, message, context, severity, occurrence_count,
+          first_occurred_at, last_occurred_at, created_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $7)
+        "#,
+      )
+      .bind(user_id)
+      .bind(error_type)
+      .bind(message)
+      .bind(serde_json::json!({ "demo": true }))
+      .bind(severity)
+      .bind(occurrences)
+      .bind(last - Duration::days(*occurrences as i64))
+      .bind(last)
+      .execute(db.pool())
+      .await
+      .map_err(SmoothieError::from)?;
+    }
+
+    Ok(errors.len())
+  }
+}
+
+/// Tiny deterministic generator so every seed produces the same history
+struct DemoRng(u64);
+
+impl DemoRng {
+  fn new(seed: u64) -> Self {
+    Self(seed)
+  }
+
+  /// Next value in `0..bound`
+  fn next(&mut self, bound: u64) -> u64 {
+    // xorshift64
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    self.0 % bound.max(1)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+
+  fn user_id() -> UserId {
+    Uuid::from_u128(1).into()
+  }
+
+  #[tokio::test]
+  async fn test_reseed_replaces_demo_data() {
+    let test_db = test_db_or_skip!();
+
+    let first = DemoService::seed_demo_data(&test_db.db, user_id())
+      .await
+      .unwrap();
+    assert_eq!(first.profiles_created, DEMO_PROFILES.len());
+    assert_eq!(first.profiles_replaced, 0);
+
+    let second = DemoService::seed_demo_data(&test_db.db, user_id())
+      .await
+      .unwrap();
+    assert_eq!(second.profiles_replaced, DEMO_PROFILES.len());
+    assert_eq!(second.activations_created, first.activations_created);
+
+    let profiles = ProfileService::get_profiles(&test_db.db, user_id())
+      .await
+      .unwrap();
+    assert_eq!(profiles.len(), DEMO_PROFILES.len());
+    assert!(profiles
+      .iter()
+      .all(|p| p.tags.contains(&DEMO_TAG.to_string())));
+
+    let (activations,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM profile_activations")
+      .fetch_one(test_db.pool())
+      .await
+      .unwrap();
+    assert_eq!(activations as usize, second.activations_created);
+  }
+}
@@ -0,0 +1,240 @@
+//! Peripheral Detection Service
+//!
+//! Lists attached USB and Thunderbolt devices and connected Bluetooth devices
+//! via `system_profiler`, so automation rules can react to a dock or other desk
+//! peripheral being plugged in, or headphones connecting. A dock's
+//! vendor/product ID is a steadier "at my desk" signal than the EDIDs of the
+//! monitors hanging off it.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::process::Command;
+
+/// A USB or Thunderbolt device currently attached to the machine
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsbDevice {
+  pub name: String,
+  pub vendor_id: u16,
+  pub product_id: u16,
+  pub serial_number: Option<String>,
+  /// Reported by the Thunderbolt bus rather than USB
+  pub is_thunderbolt: bool,
+}
+
+/// A Bluetooth device currently connected to the machine
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BluetoothDevice {
+  pub name: String,
+  /// MAC address as reported, e.g. "AC:90:85:12:34:56"
+  pub address: Option<String>,
+  /// "Headphones", "Keyboard", ...
+  pub minor_type: Option<String>,
+}
+
+/// Data types queried; SPUSBHostDataType replaced SPUSBDataType in macOS 15
+const DATA_TYPES: [&str; 3] = [
+  "SPUSBDataType",
+  "SPUSBHostDataType",
+  "SPThunderboltDataType",
+];
+
+pub struct DeviceService;
+
+impl DeviceService {
+  /// Attached USB and Thunderbolt devices; blocks while `system_profiler` runs
+  pub fn get_usb_devices() -> Vec<UsbDevice> {
+    Self::system_profiler(&DATA_TYPES)
+      .map(|report| Self::parse_usb_report(&report))
+      .unwrap_or_default()
+  }
+
+  /// Connected Bluetooth devices; blocks while `system_profiler` runs
+  pub fn get_bluetooth_devices() -> Vec<BluetoothDevice> {
+    Self::system_profiler(&["SPBluetoothDataType"])
+      .map(|report| Self::parse_bluetooth_report(&report))
+      .unwrap_or_default()
+  }
+
+  fn system_profiler(data_types: &[&str]) -> Option<Value> {
+    let output = Command::new("system_profiler")
+      .args(data_types)
+      .arg("-json")
+      .output();
+
+    match output {
+      Ok(output) if output.status.success() => {
+        match serde_json::from_slice::<Value>(&output.stdout) {
+          Ok(report) => Some(report),
+          Err(e) => {
+            tracing::warn!("Unreadable system_profiler output: {}", e);
+            None
+          }
+        }
+      }
+      Ok(output) => {
+        tracing::warn!("system_profiler exited with {}", output.status);
+        None
+      }
+      Err(e) => {
+        tracing::warn!("Failed to run system_profiler: {}", e);
+        None
+      }
+    }
+  }
+
+  /// Parse `system_profiler SPBluetoothDataType -json`, where each controller lists
+  /// `device_connected` as single-key objects mapping the device name to its details
+  fn parse_bluetooth_report(report: &Value) -> Vec<BluetoothDevice> {
+    let Some(Value::Array(controllers)) = report.get("SPBluetoothDataType") else {
+      return Vec::new();
+    };
+
+    controllers
+      .iter()
+      .filter_map(|controller| controller.get("device_connected")?.as_array())
+      .flatten()
+      .filter_map(Value::as_object)
+      .flatten()
+      .map(|(name, details)| BluetoothDevice {
+        name: name.clone(),
+        address: details
+          .get("device_address")
+          .and_then(Value::as_str)
+          .map(str::to_string),
+        minor_type: details
+          .get("device_minorType")
+          .and_then(Value::as_str)
+          .map(str::to_string),
+      })
+      .collect()
+  }
+
+  /// Parse `system_profiler -json` output, walking the nested `_items` trees
+  fn parse_usb_report(report: &Value) -> Vec<UsbDevice> {
+    let mut devices = Vec::new();
+    for data_type in DATA_TYPES {
+      if let Some(Value::Array(items)) = report.get(data_type) {
+        let is_thunderbolt = data_type == "SPThunderboltDataType";
+        for item in items {
+          Self::collect_devices(item, is_thunderbolt, &mut devices);
+        }
+      }
+    }
+    devices
+  }
+
+  fn collect_devices(item: &Value, is_thunderbolt: bool, devices: &mut Vec<UsbDevice>) {
+    let field = |keys: &[&str]| keys.iter().find_map(|key| item.get(*key));
+    let vendor_id = field(&["vendor_id", "USBDeviceKeyVendorID", "vendor_id_key"]);
+    let product_id = field(&["product_id", "USBDeviceKeyProductID", "device_id_key"]);
+
+    // Buses and hubs without IDs are skipped, but their children are still walked
+    if let (Some(vendor_id), Some(product_id)) = (
+      vendor_id.and_then(parse_device_id),
+      product_id.and_then(parse_device_id),
+    ) {
+      let name = field(&["device_name_key", "_name"])
+        .and_then(Value::as_str)
+        .unwrap_or("Unknown device");
+      devices.push(UsbDevice {
+        name: name.to_string(),
+        vendor_id,
+        product_id,
+        serial_number: field(&["serial_num", "USBDeviceKeySerialNumber", "switch_uid_key"])
+          .and_then(Value::as_str)
+          .map(str::to_string),
+        is_thunderbolt,
+      });
+    }
+
+    if let Some(Value::Array(children)) = item.get("_items") {
+      for child in children {
+        Self::collect_devices(child, is_thunderbolt, devices);
+      }
+    }
+  }
+}
+
+/// Apple's USB vendor ID, which system_profiler reports as "apple_vendor_id"
+const APPLE_VENDOR_ID: u16 = 0x05ac;
+
+/// Read a vendor/product ID given as a number, a hex string ("0x2188",
+/// optionally followed by the vendor name) or a decimal string
+pub fn parse_device_id(value: &Value) -> Option<u16> {
+  match value {
+    Value::Number(n) => n.as_u64().and_then(|n| u16::try_from(n).ok()),
+    Value::String(s) if s.starts_with("apple_vendor_id") => Some(APPLE_VENDOR_ID),
+    Value::String(s) => {
+      let token = s.split_whitespace().next()?;
+      match token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+      {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => token.parse().ok(),
+      }
+    }
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_parse_usb_report() {
+    let report = json!({
+      "SPUSBDataType": [{
+        "_name": "USB31Bus",
+        "_items": [{
+          "_name": "CalDigit TS3 Plus",
+          "vendor_id": "0x2188  (CalDigit, Inc.)",
+          "product_id": "0x0747",
+          "serial_num": "ABC123",
+          "_items": [{
+            "_name": "USB Keyboard",
+            "vendor_id": "apple_vendor_id",
+            "product_id": "0x024f"
+          }]
+        }]
+      }],
+      "SPThunderboltDataType": [{
+        "_name": "thunderbolt_device",
+        "device_name_key": "TS3 Plus",
+        "vendor_id_key": "0x3D",
+        "device_id_key": "0x11"
+      }]
+    });
+
+    let devices = DeviceService::parse_usb_report(&report);
+    assert_eq!(devices.len(), 3);
+    assert_eq!(
+      (devices[0].vendor_id, devices[0].product_id),
+      (0x2188, 0x0747)
+    );
+    assert_eq!(devices[0].serial_number.as_deref(), Some("ABC123"));
+    assert_eq!(devices[1].vendor_id, APPLE_VENDOR_ID);
+    assert_eq!(devices[2].name, "TS3 Plus");
+    assert!(devices[2].is_thunderbolt);
+
+    let bluetooth = DeviceService::parse_bluetooth_report(&json!({
+      "SPBluetoothDataType": [{
+        "device_connected": [
+          {"AirPods Pro": {"device_address": "AC:90:85:12:34:56", "device_minorType": "Headphones"}}
+        ],
+        "device_not_connected": [{"Magic Keyboard": {"device_address": "F0:B3:EC:00:00:01"}}]
+      }]
+    }));
+    assert_eq!(bluetooth.len(), 1);
+    assert_eq!(bluetooth[0].name, "AirPods Pro");
+    assert_eq!(bluetooth[0].minor_type.as_deref(), Some("Headphones"));
+
+    assert_eq!(parse_device_id(&json!(1452)), Some(1452));
+    assert_eq!(parse_device_id(&json!("4660")), Some(4660));
+    assert_eq!(parse_device_id(&json!("0x1FFFF")), None);
+  }
+}
@@ -0,0 +1,266 @@
+//! Diagnostic Upload Service
+//!
+//! With diagnostic uploads turned on, a critical error makes the app collect a
+//! bundle - the error itself, the health report, and recent errors and system
+//! events - seal it with AES-256-GCM under the support key and upload it to
+//! the `diagnostics` Storage bucket under the user's id. Support can then
+//! pull it instead of asking the user to dig up log files. Uploads are for
+//! paid subscribers only and at most one goes out an hour. The setting is read
+//! again right before each upload, so turning it off stops them at once.
+
+use crate::{
+  db::{supabase::SupabaseClient, Database},
+  error::{Result, SmoothieError},
+  models::{dto::LogQueryParams, enums::Severity, UserId},
+  repositories::{SubscriptionRepository, UserSettingsRepository},
+  services::{system_backend, HealthReport, HealthService, AUDIT_SERVICE},
+  utils::crypto,
+};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+pub const DIAGNOSTICS_BUCKET: &str = "diagnostics";
+
+/// Passphrase shared with support that bundles are sealed with
+pub const DIAGNOSTICS_KEY_ENV: &str = "SMOOTHIE_DIAGNOSTICS_KEY";
+
+/// Version of the uploaded document
+const BUNDLE_FORMAT: u32 = 1;
+
+const MIN_UPLOAD_GAP: Duration = Duration::from_secs(60 * 60);
+
+/// Recent errors and system events included with the one that triggered it
+const RECENT_LIMIT: i64 = 25;
+
+/// Subscription statuses that count as paid
+const PAID_STATUSES: [&str; 2] = ["active", "trialing"];
+
+static LAST_UPLOAD: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// What made the app collect a bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticTrigger {
+  pub source: String,
+  pub message: String,
+  pub occurred_at: DateTime<Utc>,
+}
+
+/// The sealed contents of an upload
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticBundle {
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub app_version: String,
+  pub os: serde_json::Value,
+  pub trigger: DiagnosticTrigger,
+  pub health: HealthReport,
+  pub recent_errors: serde_json::Value,
+  pub recent_events: serde_json::Value,
+}
+
+/// The document stored in the bucket; only the id and time are readable
+/// without the support key
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SealedBundle {
+  pub format: u32,
+  pub id: Uuid,
+  pub created_at: DateTime<Utc>,
+  /// Hex-encoded PBKDF2 salt for the support key
+  pub salt: String,
+  /// Hex-encoded `nonce || ciphertext` of the JSON bundle
+  pub payload: String,
+}
+
+fn seal_bundle<T: Serialize>(
+  passphrase: &str,
+  id: Uuid,
+  created_at: DateTime<Utc>,
+  bundle: &T,
+) -> Result<SealedBundle> {
+  let salt = crypto::random_salt();
+  let key = crypto::derive_key(passphrase, &salt);
+  Ok(SealedBundle {
+    format: BUNDLE_FORMAT,
+    id,
+    created_at,
+    salt: crypto::to_hex(&salt),
+    payload: crypto::to_hex(&crypto::seal(&key, &serde_json::to_vec(bundle)?)?),
+  })
+}
+
+fn object_path(user_id: Uuid, sealed: &SealedBundle) -> String {
+  format!(
+    "{}/{}-{}.json",
+    user_id,
+    sealed.created_at.format("%Y%m%dT%H%M%SZ"),
+    sealed.id
+  )
+}
+
+/// Claim the next upload slot unless one was taken within the last hour
+fn claim_upload_slot() -> bool {
+  let mut last = LAST_UPLOAD.lock();
+  if last.is_some_and(|at| at.elapsed() < MIN_UPLOAD_GAP) {
+    return false;
+  }
+  *last = Some(Instant::now());
+  true
+}
+
+pub struct DiagnosticUploadService;
+
+impl DiagnosticUploadService {
+  /// Whether `user_id` has an active paid subscription. Installs without a
+  /// subscriptions table count as unsubscribed.
+  pub async fn is_subscribed(db: &Database, user_id: Uuid) -> bool {
+    match SubscriptionRepository::new(db.pool())
+      .find_by_user_id(user_id)
+      .await
+    {
+      Ok(Some(sub)) => {
+        sub.tier != "free"
+          && sub
+            .status
+            .as_deref()
+            .is_some_and(|status| PAID_STATUSES.contains(&status))
+          && sub.current_period_end.map_or(true, |end| end > Utc::now())
+      }
+      Ok(None) => false,
+      Err(e) => {
+        tracing::debug!("Subscription lookup failed: {}", e);
+        false
+      }
+    }
+  }
+
+  /// Upload a bundle in the background after a critical error, if the user
+  /// opted in and the last upload was over an hour ago
+  pub fn on_critical_error(db: &Database, user_id: Option<&str>, source: &str, message: &str) {
+    // Errors that aren't tied to a user, such as system events, go to the
+    // default account
+    let user_id = user_id
+      .and_then(|id| Uuid::parse_str(id).ok())
+      .unwrap_or(UserId::DEFAULT.as_uuid());
+    let trigger = DiagnosticTrigger {
+      source: source.to_string(),
+      message: message.to_string(),
+      occurred_at: Utc::now(),
+    };
+    let db = db.clone();
+    tokio::spawn(async move {
+      match Self::upload(&db, user_id, trigger).await {
+        Ok(Some(id)) => {
+          let _ = AUDIT_SERVICE
+            .log_system_event(
+              &db,
+              "diagnostics_uploaded",
+              Severity::Info,
+              "DiagnosticUploadService",
+              &format!("Diagnostic bundle {} uploaded for support", id),
+              Some(json!({ "bundleId": id })),
+              None,
+            )
+            .await;
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Diagnostic upload failed: {}", e),
+      }
+    });
+  }
+
+  /// Seal and upload a bundle for `trigger`. `None` when uploads are off, the
+  /// user isn't subscribed or one went out within the last hour.
+  pub async fn upload(
+    db: &Database,
+    user_id: Uuid,
+    trigger: DiagnosticTrigger,
+  ) -> Result<Option<Uuid>> {
+    let enabled = UserSettingsRepository::new(db.pool())
+      .get_or_create(user_id)
+      .await?
+      .diagnostic_uploads;
+    if !enabled || !Self::is_subscribed(db, user_id).await || !claim_upload_slot() {
+      return Ok(None);
+    }
+
+    let passphrase = std::env::var(DIAGNOSTICS_KEY_ENV)
+      .ok()
+      .filter(|key| !key.trim().is_empty())
+      .ok_or_else(|| {
+        SmoothieError::ValidationError(format!(
+          "Diagnostic uploads require {}",
+          DIAGNOSTICS_KEY_ENV
+        ))
+      })?;
+    let client = SupabaseClient::require_env("Diagnostic uploads")?;
+
+    let bundle = Self::collect(db, user_id, trigger).await?;
+    let sealed = seal_bundle(&passphrase, bundle.id, bundle.trigger.occurred_at, &bundle)?;
+    client
+      .upload_object(DIAGNOSTICS_BUCKET, &object_path(user_id, &sealed), &sealed)
+      .await?;
+
+    tracing::info!(bundle_id = %bundle.id, "Diagnostic bundle uploaded");
+    Ok(Some(bundle.id))
+  }
+
+  async fn collect(
+    db: &Database,
+    user_id: Uuid,
+    trigger: DiagnosticTrigger,
+  ) -> Result<DiagnosticBundle> {
+    let system = system_backend::from_env();
+    let recent = || LogQueryParams {
+      limit: Some(RECENT_LIMIT),
+      ..Default::default()
+    };
+    let recent_errors = AUDIT_SERVICE.get_error_logs(db, recent(), false).await?;
+    let recent_events = AUDIT_SERVICE.get_system_events(db, recent()).await?;
+
+    Ok(DiagnosticBundle {
+      id: Uuid::new_v4(),
+      user_id,
+      app_version: env!("CARGO_PKG_VERSION").to_string(),
+      os: json!({
+        "name": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+      }),
+      trigger,
+      // Uploads run outside the app state, so background tasks aren't listed
+      health: HealthService::check(db, &*system, &[]).await,
+      recent_errors: serde_json::to_value(recent_errors)?,
+      recent_events: serde_json::to_value(recent_events)?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_sealed_bundle_opens_with_the_support_key_only() {
+    let bundle = json!({ "trigger": { "message": "Database connection lost" } });
+    let sealed = seal_bundle("support secret", Uuid::nil(), Utc::now(), &bundle).unwrap();
+    assert!(object_path(Uuid::nil(), &sealed).starts_with("00000000-0000-0000-0000-000000000000/"));
+
+    let salt = crypto::from_hex(&sealed.salt).unwrap();
+    let payload = crypto::from_hex(&sealed.payload).unwrap();
+    let opened = crypto::open(&crypto::derive_key("support secret", &salt), &payload).unwrap();
+    assert_eq!(
+      serde_json::from_slice::<serde_json::Value>(&opened).unwrap(),
+      bundle
+    );
+    assert!(crypto::open(&crypto::derive_key("guess", &salt), &payload).is_err());
+
+    assert!(claim_upload_slot());
+    assert!(!claim_upload_slot());
+  }
+}
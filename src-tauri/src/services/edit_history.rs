@@ -0,0 +1,589 @@
+// Undo and redo for profile edits
+//
+// Every change `ChangeLog` records is also pushed onto an in-memory undo stack
+// for its profile. Undoing an update writes the old field values back, and
+// undoing a delete re-creates the removed monitor, app or tab from its
+// snapshot; redo reverses that. Re-created items get a new id, so pending
+// edits that still refer to the old one are pointed at the new row. The
+// stacks live only as long as the app runs and keep the last `MAX_EDITS`
+// edits of each profile.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::{AppDto, BrowserTabDto, MonitorDto, ProfileId, UpdateProfileRequest},
+  repositories::{AppRepository, BrowserTabRepository, MonitorRepository, ProfileRepository},
+  services::change_log::{ChangeLog, ChangeSubject, FieldChange},
+  services::profile_lock::ProfileLock,
+  utils::markdown,
+};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use uuid::Uuid;
+
+/// Edits kept per profile; older ones can no longer be undone
+const MAX_EDITS: usize = 50;
+
+static STACKS: OnceLock<Mutex<HashMap<Uuid, EditStacks>>> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub enum Edit {
+  Updated {
+    subject: ChangeSubject,
+    changes: Vec<FieldChange>,
+  },
+  Deleted {
+    subject: ChangeSubject,
+    removed: Value,
+  },
+}
+
+impl Edit {
+  fn subject(&self) -> ChangeSubject {
+    match self {
+      Edit::Updated { subject, .. } | Edit::Deleted { subject, .. } => *subject,
+    }
+  }
+}
+
+#[derive(Debug, Default)]
+struct EditStacks {
+  undo: VecDeque<Edit>,
+  redo: Vec<Edit>,
+}
+
+impl EditStacks {
+  fn push_undo(&mut self, edit: Edit) {
+    self.undo.push_back(edit);
+    if self.undo.len() > MAX_EDITS {
+      self.undo.pop_front();
+    }
+  }
+
+  /// Point edits about `old` at the row that replaced it
+  fn remap(&mut self, old: Uuid, new: Uuid) {
+    for edit in self.undo.iter_mut().chain(self.redo.iter_mut()) {
+      let (Edit::Updated { subject, .. } | Edit::Deleted { subject, .. }) = edit;
+      if subject.entity_id == old {
+        subject.entity_id = new;
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+  Undo,
+  Redo,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditStateDto {
+  pub can_undo: bool,
+  pub can_redo: bool,
+}
+
+/// What an undo or redo changed
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevertedEditDto {
+  pub entity_type: String,
+  /// The item's id after the step; a restored item has a new one
+  pub entity_id: String,
+  /// Fields written back; empty when an item was restored or removed again
+  pub fields: Vec<String>,
+  /// The item was re-created (undo) or deleted again (redo)
+  pub restored: bool,
+  pub state: EditStateDto,
+}
+
+pub struct EditHistory;
+
+impl EditHistory {
+  /// Make `edit` the next one to undo. A new edit discards anything that was
+  /// waiting to be redone.
+  pub fn push(edit: Edit) {
+    let mut stacks = lock();
+    let entry = stacks.entry(edit.subject().profile_id).or_default();
+    entry.redo.clear();
+    entry.push_undo(edit);
+  }
+
+  pub fn state(profile_id: ProfileId) -> EditStateDto {
+    lock()
+      .get(&profile_id.as_uuid())
+      .map(|stacks| EditStateDto {
+        can_undo: !stacks.undo.is_empty(),
+        can_redo: !stacks.redo.is_empty(),
+      })
+      .unwrap_or_default()
+  }
+
+  /// Revert the profile's most recent edit; `None` if there is nothing to undo
+  pub async fn undo(db: &Database, profile_id: ProfileId) -> Result<Option<RevertedEditDto>> {
+    Self::step(db, profile_id, Direction::Undo).await
+  }
+
+  /// Re-apply the most recently undone edit; `None` if there is nothing to redo
+  pub async fn redo(db: &Database, profile_id: ProfileId) -> Result<Option<RevertedEditDto>> {
+    Self::step(db, profile_id, Direction::Redo).await
+  }
+
+  async fn step(
+    db: &Database,
+    profile_id: ProfileId,
+    direction: Direction,
+  ) -> Result<Option<RevertedEditDto>> {
+    let profile_uuid = profile_id.as_uuid();
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
+    let edit = {
+      let mut stacks = lock();
+      let Some(entry) = stacks.get_mut(&profile_uuid) else {
+        return Ok(None);
+      };
+      match direction {
+        Direction::Undo => entry.undo.pop_back(),
+        Direction::Redo => entry.redo.pop(),
+      }
+    };
+    let Some(edit) = edit else {
+      return Ok(None);
+    };
+
+    let applied = match Self::apply(db, &edit, direction).await {
+      Ok(applied) => applied,
+      Err(e) => {
+        // Keep the edit for another try unless what it refers to is gone
+        if !matches!(e, SmoothieError::NotFound(_)) {
+          let mut stacks = lock();
+          let entry = stacks.entry(profile_uuid).or_default();
+          match direction {
+            Direction::Undo => entry.push_undo(edit),
+            Direction::Redo => entry.redo.push(edit),
+          }
+        }
+        return Err(e);
+      }
+    };
+
+    let subject = applied.subject();
+    let (fields, restored) = match &applied {
+      Edit::Updated { changes, .. } => (changes.iter().map(|c| c.field.clone()).collect(), false),
+      Edit::Deleted { .. } => (Vec::new(), true),
+    };
+    let details = match (&applied, direction) {
+      (Edit::Updated { changes, .. }, Direction::Undo) => {
+        json!({ "profile_id": profile_uuid, "changes": reversed(changes) })
+      }
+      (Edit::Updated { changes, .. }, Direction::Redo) => {
+        json!({ "profile_id": profile_uuid, "changes": changes })
+      }
+      (Edit::Deleted { removed, .. }, Direction::Undo) => {
+        json!({ "profile_id": profile_uuid, "restored": removed })
+      }
+      (Edit::Deleted { removed, .. }, Direction::Redo) => {
+        json!({ "profile_id": profile_uuid, "deleted": removed })
+      }
+    };
+    let verb = match direction {
+      Direction::Undo => "change_undone",
+      Direction::Redo => "change_redone",
+    };
+    ChangeLog::record(db, subject, verb, details).await;
+
+    {
+      let mut stacks = lock();
+      let entry = stacks.entry(profile_uuid).or_default();
+      match direction {
+        Direction::Undo => entry.redo.push(applied),
+        Direction::Redo => entry.push_undo(applied),
+      }
+    }
+
+    Ok(Some(RevertedEditDto {
+      entity_type: subject.entity_type.to_string(),
+      entity_id: subject.entity_id.to_string(),
+      fields,
+      restored,
+      state: Self::state(profile_id),
+    }))
+  }
+
+  /// Carry out one step of `edit` and return the edit as it now stands
+  async fn apply(db: &Database, edit: &Edit, direction: Direction) -> Result<Edit> {
+    match edit {
+      Edit::Updated { subject, changes } => {
+        let values: Map<String, Value> = changes
+          .iter()
+          .map(|c| {
+            let value = match direction {
+              Direction::Undo => &c.old,
+              Direction::Redo => &c.new,
+            };
+            (c.field.clone(), value.clone())
+          })
+          .collect();
+        write_fields(db, *subject, &values).await?;
+        Ok(edit.clone())
+      }
+      Edit::Deleted { subject, removed } => match direction {
+        Direction::Undo => {
+          let new_id = recreate(db, *subject, removed).await?;
+          lock()
+            .entry(subject.profile_id)
+            .or_default()
+            .remap(subject.entity_id, new_id);
+          Ok(Edit::Deleted {
+            subject: ChangeSubject {
+              entity_id: new_id,
+              ..*subject
+            },
+            removed: removed.clone(),
+          })
+        }
+        Direction::Redo => {
+          remove(db, *subject).await?;
+          Ok(edit.clone())
+        }
+      },
+    }
+  }
+}
+
+fn lock() -> std::sync::MutexGuard<'static, HashMap<Uuid, EditStacks>> {
+  STACKS
+    .get_or_init(Default::default)
+    .lock()
+    .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn reversed(changes: &[FieldChange]) -> Vec<FieldChange> {
+  changes
+    .iter()
+    .map(|c| FieldChange {
+      field: c.field.clone(),
+      old: c.new.clone(),
+      new: c.old.clone(),
+    })
+    .collect()
+}
+
+fn as_i32(value: &Value) -> Option<i32> {
+  value.as_i64().and_then(|v| i32::try_from(v).ok())
+}
+
+/// Write recorded field values back to an item. Only the fields the edit
+/// commands can change are written; profile text fields that were empty
+/// before an edit stay as they are, since updates never clear them.
+async fn write_fields(
+  db: &Database,
+  subject: ChangeSubject,
+  values: &Map<String, Value>,
+) -> Result<()> {
+  let id = subject.entity_id;
+  match subject.entity_type {
+    "monitor" => {
+      let repo = MonitorRepository::new(db.pool());
+      let current = repo
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| SmoothieError::NotFound("Monitor not found".into()))?;
+      let field = |name: &str, current: i32| values.get(name).and_then(as_i32).unwrap_or(current);
+      repo
+        .update_position(
+          id,
+          field("x", current.x),
+          field("y", current.y),
+          field("width", current.width),
+          field("height", current.height),
+        )
+        .await?;
+      if let Some(blanked) = values.get("blanked").and_then(Value::as_bool) {
+        repo.set_blanked(id, blanked).await?;
+      }
+    }
+    "app" => {
+      let repo = AppRepository::new(db.pool());
+      let launch_on_activate = values.get("launchOnActivate").and_then(Value::as_bool);
+      let current = repo.update(id, launch_on_activate).await?;
+      // Undoing a replacement puts the previous app back
+      if let Some(bundle_id) = values.get("bundleId").and_then(Value::as_str) {
+        let name = values
+          .get("name")
+          .and_then(Value::as_str)
+          .unwrap_or(&current.name);
+        let exe_path = values
+          .get("exePath")
+          .map(Value::as_str)
+          .unwrap_or(current.exe_path.as_deref());
+        repo.replace(id, name, bundle_id, exe_path).await?;
+      }
+    }
+    "browser_tab" => {
+      let url = values.get("url").and_then(Value::as_str);
+      BrowserTabRepository::new(db.pool()).update(id, url).await?;
+    }
+    "profile" => {
+      let repo = ProfileRepository::new(db.pool());
+      let req: UpdateProfileRequest = serde_json::from_value(Value::Object(values.clone()))?;
+      repo
+        .update_extended(
+          id,
+          req.name.as_deref(),
+          req.description.as_deref(),
+          req.is_favorite,
+          req.color.as_deref(),
+          req.icon.as_deref(),
+          req.sort_order,
+        )
+        .await?;
+      if let Some(tags) = &req.tags {
+        repo.set_tags(id, tags).await?;
+      }
+      if values.contains_key("notes") {
+        let notes = req.notes.as_deref().and_then(markdown::sanitize_notes);
+        repo.set_notes(id, notes.as_deref()).await?;
+      }
+      if let Some(threshold) = values.get("lowPowerBatteryThreshold") {
+        repo.set_low_power_threshold(id, as_i32(threshold)).await?;
+      }
+      if let Some(local_only) = values.get("localOnly").and_then(Value::as_bool) {
+        repo.set_local_only(id, local_only).await?;
+      }
+      if let Some(include) = values
+        .get("includeVirtualDisplays")
+        .and_then(Value::as_bool)
+      {
+        repo.set_include_virtual_displays(id, include).await?;
+      }
+      if values.contains_key("requiredMonitorCount") || values.contains_key("monitorSignature") {
+        let current = repo
+          .find_by_id(id)
+          .await?
+          .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?;
+        let count = match values.get("requiredMonitorCount") {
+          Some(count) => as_i32(count),
+          None => current.required_monitor_count,
+        };
+        let signature = match values.get("monitorSignature") {
+          Some(signature) => signature.as_str().map(str::to_string),
+          None => current.monitor_signature,
+        };
+        repo
+          .set_monitor_binding(id, count, signature.as_deref())
+          .await?;
+      }
+    }
+    other => {
+      return Err(SmoothieError::ValidationError(format!(
+        "Changes to {} cannot be undone",
+        other
+      )))
+    }
+  }
+  Ok(())
+}
+
+/// Re-create a deleted item from its snapshot and return its new id
+async fn recreate(db: &Database, subject: ChangeSubject, removed: &Value) -> Result<Uuid> {
+  let profile_id = subject.profile_id;
+  let entity_id = match subject.entity_type {
+    "monitor" => {
+      let m: MonitorDto = serde_json::from_value(removed.clone())?;
+      let repo = MonitorRepository::new(db.pool());
+      let monitor = repo
+        .create_with_metadata(
+          profile_id,
+          &m.name,
+          &m.resolution,
+          &m.orientation,
+          m.is_primary,
+          m.x,
+          m.y,
+          m.width,
+          m.height,
+          m.display_index,
+          m.brand.as_deref(),
+          m.model.as_deref(),
+          m.refresh_rate,
+          m.scale_factor,
+          m.is_builtin,
+          m.color_depth,
+        )
+        .await?;
+      if m.blanked {
+        repo.set_blanked(monitor.id, true).await?;
+      }
+      monitor.id
+    }
+    "app" => {
+      let a: AppDto = serde_json::from_value(removed.clone())?;
+      AppRepository::new(db.pool())
+        .create(
+          profile_id,
+          &a.name,
+          &a.bundle_id,
+          a.exe_path.as_deref(),
+          a.launch_on_activate,
+          a.monitor_preference,
+          Some(a.startup_delay_ms),
+          Some(a.order_index),
+        )
+        .await?
+        .id
+    }
+    "browser_tab" => {
+      let t: BrowserTabDto = serde_json::from_value(removed.clone())?;
+      // The monitor the tab was assigned to may have gone since
+      let monitor_id = match t
+        .monitor_id
+        .as_deref()
+        .and_then(|id| Uuid::parse_str(id).ok())
+      {
+        Some(id) => MonitorRepository::new(db.pool())
+          .find_by_id(id)
+          .await?
+          .map(|m| m.id),
+        None => None,
+      };
+      BrowserTabRepository::new(db.pool())
+        .create(
+          profile_id,
+          &t.url,
+          &t.browser,
+          monitor_id,
+          t.tab_order,
+          t.favicon.as_deref(),
+        )
+        .await?
+        .id
+    }
+    other => {
+      return Err(SmoothieError::ValidationError(format!(
+        "Deleted {} cannot be restored",
+        other
+      )))
+    }
+  };
+  Ok(entity_id)
+}
+
+async fn remove(db: &Database, subject: ChangeSubject) -> Result<()> {
+  let id = subject.entity_id;
+  let deleted = match subject.entity_type {
+    "monitor" => MonitorRepository::new(db.pool()).delete(id).await?,
+    "app" => AppRepository::new(db.pool()).delete(id).await?,
+    "browser_tab" => BrowserTabRepository::new(db.pool()).delete(id).await?,
+    other => {
+      return Err(SmoothieError::ValidationError(format!(
+        "{} cannot be deleted again",
+        other
+      )))
+    }
+  };
+  if !deleted {
+    return Err(SmoothieError::NotFound("Item not found".into()));
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use crate::models::{CreateAppRequest, CreateProfileRequest, ProfileType, UserId};
+  use crate::services::{AppService, ProfileService};
+
+  fn deleted(profile_id: Uuid, entity_id: Uuid) -> Edit {
+    Edit::Deleted {
+      subject: ChangeSubject {
+        entity_type: "app",
+        entity_id,
+        profile_id,
+      },
+      removed: Value::Null,
+    }
+  }
+
+  #[test]
+  fn test_stacks_cap_undo_and_remap_restored_ids() {
+    let (profile_id, app_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let mut stacks = EditStacks::default();
+    for _ in 0..MAX_EDITS + 5 {
+      stacks.push_undo(deleted(profile_id, Uuid::new_v4()));
+    }
+    assert_eq!(stacks.undo.len(), MAX_EDITS);
+
+    stacks.push_undo(deleted(profile_id, app_id));
+    stacks.redo.push(deleted(profile_id, app_id));
+    let new_id = Uuid::new_v4();
+    stacks.remap(app_id, new_id);
+    assert_eq!(stacks.undo.back().unwrap().subject().entity_id, new_id);
+    assert_eq!(stacks.redo[0].subject().entity_id, new_id);
+  }
+
+  #[tokio::test]
+  async fn test_undo_restores_deleted_app_and_redo_removes_it() {
+    let test_db = test_db_or_skip!();
+    let db = &test_db.db;
+    let user_id = UserId::from(Uuid::new_v4());
+    let profile = ProfileService::create_profile(
+      db,
+      user_id,
+      CreateProfileRequest {
+        name: "Focus".into(),
+        description: None,
+        profile_type: ProfileType::Work,
+        tags: None,
+        notes: None,
+      },
+    )
+    .await
+    .unwrap();
+    let profile_key = profile.id.to_string();
+    let app = AppService::create_app(
+      db,
+      &profile_key,
+      &user_id.to_string(),
+      CreateAppRequest {
+        name: "Slack".into(),
+        bundle_id: "com.tinyspeck.slackmacgap".into(),
+        exe_path: None,
+        launch_on_activate: true,
+        monitor_preference: None,
+        startup_delay_ms: Some(250),
+        order_index: Some(3),
+      },
+    )
+    .await
+    .unwrap();
+    AppService::update_app(db, &app.id, Some(false))
+      .await
+      .unwrap();
+    AppService::delete_app(db, &app.id).await.unwrap();
+
+    let restored = EditHistory::undo(db, profile.id).await.unwrap().unwrap();
+    assert!(restored.restored);
+    let apps = AppService::get_apps(db, &profile_key).await.unwrap();
+    assert_eq!(apps.len(), 1);
+    assert_eq!(apps[0].id, restored.entity_id);
+    assert_eq!(apps[0].startup_delay_ms, 250);
+    assert!(!apps[0].launch_on_activate);
+
+    // The earlier update now applies to the restored row
+    let reverted = EditHistory::undo(db, profile.id).await.unwrap().unwrap();
+    assert_eq!(reverted.fields, ["launchOnActivate"]);
+    assert!(AppService::get_apps(db, &profile_key).await.unwrap()[0].launch_on_activate);
+
+    EditHistory::redo(db, profile.id).await.unwrap();
+    EditHistory::redo(db, profile.id).await.unwrap();
+    assert!(AppService::get_apps(db, &profile_key)
+      .await
+      .unwrap()
+      .is_empty());
+    assert!(!EditHistory::state(profile.id).can_redo);
+  }
+}
@@ -0,0 +1,293 @@
+//! Environment Snapshot Service
+//!
+//! Captures the context a profile activation ran in (connected monitors, OS
+//! version, power state and Wi-Fi network) so that failed activations can be
+//! debugged later from `profile_activations.metadata`.
+
+use crate::services::system_service::SystemService;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// Key under which the snapshot is stored in activation metadata
+pub const ENVIRONMENT_METADATA_KEY: &str = "environment";
+
+/// Structured snapshot of the machine state at a point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentSnapshot {
+  pub captured_at: String,
+  pub os: OsInfo,
+  pub monitors: Vec<MonitorFingerprint>,
+  pub power: PowerState,
+  pub network: NetworkInfo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OsInfo {
+  pub name: String,
+  pub version: Option<String>,
+  pub build: Option<String>,
+  pub arch: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorFingerprint {
+  pub display_id: u32,
+  pub fingerprint: String,
+  pub name: String,
+  pub resolution: String,
+  pub is_primary: bool,
+  pub is_builtin: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerState {
+  /// "ac", "battery" or "unknown"
+  pub source: String,
+  pub battery_percent: Option<u8>,
+  pub is_charging: Option<bool>,
+}
+
+impl PowerState {
+  /// Running on battery with less than `threshold_percent` charge left
+  pub fn is_low(&self, threshold_percent: i32) -> bool {
+    self.source == "battery"
+      && self
+        .battery_percent
+        .is_some_and(|percent| i32::from(percent) < threshold_percent)
+  }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkInfo {
+  pub ssid: Option<String>,
+}
+
+pub struct EnvironmentService;
+
+impl EnvironmentService {
+  /// Capture a snapshot of the current environment.
+  ///
+  /// Shells out to `sw_vers`, `pmset` and `networksetup`, and queries the
+  /// display system, so call it from a blocking context (see `capture_async`).
+  pub fn capture() -> EnvironmentSnapshot {
+    let monitors = SystemService::get_monitors()
+      .iter()
+      .map(|m| MonitorFingerprint {
+        display_id: m.display_id,
+        fingerprint: m.fingerprint(),
+        name: m.name.clone(),
+        resolution: m.resolution.clone(),
+        is_primary: m.is_primary,
+        is_builtin: m.is_builtin,
+      })
+      .collect();
+
+    EnvironmentSnapshot {
+      captured_at: chrono::Utc::now().to_rfc3339(),
+      os: Self::get_os_info(),
+      monitors,
+      power: Self::get_power_state(),
+      network: NetworkInfo {
+        ssid: Self::get_wifi_ssid(),
+      },
+    }
+  }
+
+  /// Capture a snapshot without blocking the async runtime
+  pub async fn capture_async() -> Option<EnvironmentSnapshot> {
+    match tokio::task::spawn_blocking(Self::capture).await {
+      Ok(snapshot) => Some(snapshot),
+      Err(e) => {
+        tracing::warn!("Failed to capture environment snapshot: {}", e);
+        None
+      }
+    }
+  }
+
+  /// Merge a snapshot into activation metadata, keeping any caller-supplied fields.
+  /// An existing `environment` entry is left untouched.
+  pub fn attach_to_metadata(
+    metadata: Option<serde_json::Value>,
+    snapshot: Option<EnvironmentSnapshot>,
+  ) -> Option<serde_json::Value> {
+    let Some(snapshot) = snapshot else {
+      return metadata;
+    };
+    let snapshot = match serde_json::to_value(snapshot) {
+      Ok(snapshot) => snapshot,
+      Err(e) => {
+        tracing::warn!("Failed to serialize environment snapshot: {}", e);
+        return metadata;
+      }
+    };
+
+    match metadata {
+      Some(serde_json::Value::Object(mut map)) => {
+        map.entry(ENVIRONMENT_METADATA_KEY).or_insert(snapshot);
+        Some(serde_json::Value::Object(map))
+      }
+      Some(other) => Some(serde_json::json!({
+        "data": other,
+        ENVIRONMENT_METADATA_KEY: snapshot,
+      })),
+      None => Some(serde_json::json!({ ENVIRONMENT_METADATA_KEY: snapshot })),
+    }
+  }
+
+  /// Power state from the snapshot stored in activation metadata, if any
+  pub fn power_from_metadata(metadata: Option<&serde_json::Value>) -> Option<PowerState> {
+    let power = metadata?.get(ENVIRONMENT_METADATA_KEY)?.get("power")?;
+    serde_json::from_value(power.clone()).ok()
+  }
+
+  /// Device location as (latitude, longitude) from CoreLocation, through the
+  /// `CoreLocationCLI` tool since there is no command-line API built in.
+  /// `None` when the tool is missing or location access was denied.
+  pub fn get_location() -> Option<(f64, f64)> {
+    let output = Self::run_command("CoreLocationCLI", &["-format", "%latitude %longitude"])?;
+    let mut parts = output.split_whitespace().map(str::parse::<f64>);
+    match (parts.next()?, parts.next()?) {
+      (Ok(latitude), Ok(longitude)) => Some((latitude, longitude)),
+      _ => None,
+    }
+  }
+
+  pub(crate) fn get_os_info() -> OsInfo {
+    OsInfo {
+      name: std::env::consts::OS.to_string(),
+      version: Self::run_command("sw_vers", &["-productVersion"]),
+      build: Self::run_command("sw_vers", &["-buildVersion"]),
+      arch: std::env::consts::ARCH.to_string(),
+    }
+  }
+
+  /// Current power source via `pmset`; blocks while the command runs
+  pub fn get_power_state() -> PowerState {
+    Self::run_command("pmset", &["-g", "batt"])
+      .map(|output| Self::parse_pmset_output(&output))
+      .unwrap_or_else(|| PowerState {
+        source: "unknown".to_string(),
+        ..Default::default()
+      })
+  }
+
+  fn get_wifi_ssid() -> Option<String> {
+    // The Wi-Fi interface is en0 on every Mac that has one built in
+    if let Some(output) = Self::run_command("networksetup", &["-getairportnetwork", "en0"]) {
+      if let Some(ssid) = output.strip_prefix("Current Wi-Fi Network: ") {
+        return Some(ssid.trim().to_string());
+      }
+    }
+
+    // networksetup stopped reporting the SSID on recent macOS; fall back to ipconfig
+    Self::run_command("ipconfig", &["getsummary", "en0"]).and_then(|output| {
+      output.lines().find_map(|line| {
+        line
+          .trim()
+          .strip_prefix("SSID : ")
+          .map(|ssid| ssid.trim().to_string())
+      })
+    })
+  }
+
+  /// Parse `pmset -g batt` output, e.g.
+  ///
+  /// ```text
+  /// Now drawing from 'Battery Power'
+  ///  -InternalBattery-0 (id=4653155)  82%; discharging; 5:12 remaining present: true
+  /// ```
+  fn parse_pmset_output(output: &str) -> PowerState {
+    let source = if output.contains("'AC Power'") {
+      "ac"
+    } else if output.contains("'Battery Power'") {
+      "battery"
+    } else {
+      "unknown"
+    };
+
+    let battery_line = output.lines().find(|line| line.contains("InternalBattery"));
+    let battery_percent = battery_line.and_then(|line| {
+      line
+        .split_whitespace()
+        .find_map(|part| part.strip_suffix("%;"))
+        .and_then(|pct| pct.parse::<u8>().ok())
+    });
+    let is_charging = battery_line.map(|line| {
+      line.contains("; charging;")
+        || line.contains("; charged;")
+        || line.contains("finishing charge")
+    });
+
+    PowerState {
+      source: source.to_string(),
+      battery_percent,
+      is_charging,
+    }
+  }
+
+  fn run_command(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+      return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if stdout.is_empty() {
+      None
+    } else {
+      Some(stdout)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_pmset_output() {
+    let battery = EnvironmentService::parse_pmset_output(
+      "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=4653155)\t82%; discharging; 5:12 remaining present: true",
+    );
+    assert_eq!(battery.source, "battery");
+    assert_eq!(battery.battery_percent, Some(82));
+    assert_eq!(battery.is_charging, Some(false));
+    assert!(battery.is_low(90));
+    assert!(!battery.is_low(82));
+
+    let desktop = EnvironmentService::parse_pmset_output("Now drawing from 'AC Power'");
+    assert_eq!(desktop.source, "ac");
+    assert_eq!(desktop.battery_percent, None);
+    assert!(!desktop.is_low(100));
+    assert_eq!(desktop.is_charging, None);
+  }
+
+  #[test]
+  fn test_attach_to_metadata_preserves_fields() {
+    let snapshot = EnvironmentSnapshot {
+      captured_at: "2024-01-01T00:00:00Z".to_string(),
+      os: OsInfo {
+        name: "macos".to_string(),
+        version: Some("14.4".to_string()),
+        build: None,
+        arch: "aarch64".to_string(),
+      },
+      monitors: Vec::new(),
+      power: PowerState::default(),
+      network: NetworkInfo::default(),
+    };
+
+    let merged = EnvironmentService::attach_to_metadata(
+      Some(serde_json::json!({ "trigger": "schedule" })),
+      Some(snapshot),
+    )
+    .unwrap();
+
+    assert_eq!(merged["trigger"], "schedule");
+    assert_eq!(merged[ENVIRONMENT_METADATA_KEY]["os"]["version"], "14.4");
+  }
+}
@@ -0,0 +1,188 @@
+// File service - manage documents/folders opened alongside a profile
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::dto::ProfileFileDto,
+  repositories::{ProfileFileRepository, ProfilePart},
+  services::profile_lock::ProfileLock,
+};
+use std::path::PathBuf;
+use std::process::Command;
+use uuid::Uuid;
+
+/// Helper to parse UUID from string
+fn parse_uuid(s: &str) -> Result<Uuid> {
+  Uuid::parse_str(s).map_err(|_| SmoothieError::ValidationError(format!("Invalid UUID: {}", s)))
+}
+
+/// Result of opening a profile file
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFileResult {
+  pub path: String,
+  pub target_app: Option<String>,
+  pub success: bool,
+  pub message: String,
+}
+
+pub struct FileService;
+
+impl FileService {
+  pub async fn create_file(
+    db: &Database,
+    profile_id: &str,
+    path: String,
+    target_app: Option<String>,
+    open_on_activate: bool,
+    open_order: i32,
+  ) -> Result<ProfileFileDto> {
+    let profile_uuid = parse_uuid(profile_id)?;
+    if path.trim().is_empty() {
+      return Err(SmoothieError::ValidationError(
+        "File path cannot be empty".to_string(),
+      ));
+    }
+    let target_app = target_app.filter(|app| !app.trim().is_empty());
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
+    let repo = ProfileFileRepository::new(db.pool());
+
+    let entity = repo
+      .create(
+        profile_uuid,
+        path.trim(),
+        target_app.as_deref(),
+        open_on_activate,
+        open_order,
+      )
+      .await?;
+
+    Ok(ProfileFileDto::from(entity))
+  }
+
+  pub async fn get_files(db: &Database, profile_id: &str) -> Result<Vec<ProfileFileDto>> {
+    let profile_uuid = parse_uuid(profile_id)?;
+    let repo = ProfileFileRepository::new(db.pool());
+
+    let files = repo.find_by_profile_id(profile_uuid).await?;
+    Ok(files.into_iter().map(ProfileFileDto::from).collect())
+  }
+
+  pub async fn update_file(
+    db: &Database,
+    file_id: &str,
+    path: Option<String>,
+    target_app: Option<String>,
+    open_on_activate: Option<bool>,
+  ) -> Result<ProfileFileDto> {
+    let file_uuid = parse_uuid(file_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::File, file_uuid).await?;
+    let repo = ProfileFileRepository::new(db.pool());
+
+    let entity = repo
+      .update(
+        file_uuid,
+        path.as_deref(),
+        target_app.as_deref(),
+        open_on_activate,
+      )
+      .await?;
+    Ok(ProfileFileDto::from(entity))
+  }
+
+  pub async fn delete_file(db: &Database, file_id: &str) -> Result<()> {
+    let file_uuid = parse_uuid(file_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::File, file_uuid).await?;
+    let repo = ProfileFileRepository::new(db.pool());
+
+    let deleted = repo.delete(file_uuid).await?;
+    if !deleted {
+      return Err(SmoothieError::NotFound("Profile file not found".into()));
+    }
+
+    Ok(())
+  }
+
+  /// Expand a leading `~` to the user's home directory
+  fn expand_path(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+      Some(rest) => dirs::home_dir()
+        .map(|home| home.join(rest))
+        .unwrap_or_else(|| PathBuf::from(path)),
+      None => PathBuf::from(path),
+    }
+  }
+
+  /// The `open` flag for `target_app`: `-b` for bundle identifiers, `-a` for
+  /// application names and paths
+  pub(crate) fn open_app_flag(app: &str) -> &'static str {
+    let is_bundle_id = !app.contains('/') && !app.contains(' ') && app.matches('.').count() >= 2;
+    if is_bundle_id {
+      "-b"
+    } else {
+      "-a"
+    }
+  }
+
+  /// Open a file or folder, optionally with a specific app (macOS)
+  ///
+  /// `target_app` may be a bundle identifier (`com.microsoft.Excel`) or an
+  /// application name/path (`Numbers`, `/Applications/Numbers.app`).
+  pub fn open_file(path: &str, target_app: Option<&str>) -> OpenFileResult {
+    tracing::info!("Opening file {} with {:?}", path, target_app);
+
+    let resolved = Self::expand_path(path);
+    if !resolved.exists() {
+      tracing::warn!("Profile file does not exist: {}", resolved.display());
+      return OpenFileResult {
+        path: path.to_string(),
+        target_app: target_app.map(String::from),
+        success: false,
+        message: "File not found".to_string(),
+      };
+    }
+
+    let mut command = Command::new("open");
+    if let Some(app) = target_app {
+      command.arg(Self::open_app_flag(app)).arg(app);
+    }
+
+    match command.arg(&resolved).spawn() {
+      Ok(_) => OpenFileResult {
+        path: path.to_string(),
+        target_app: target_app.map(String::from),
+        success: true,
+        message: match target_app {
+          Some(app) => format!("Opened in {}", app),
+          None => "Opened with default app".to_string(),
+        },
+      },
+      Err(e) => {
+        tracing::error!("Failed to open file {}: {}", path, e);
+        OpenFileResult {
+          path: path.to_string(),
+          target_app: target_app.map(String::from),
+          success: false,
+          message: format!("Failed to open: {}", e),
+        }
+      }
+    }
+  }
+
+  /// Open all files flagged for activation for a profile
+  pub async fn open_profile_files(db: &Database, profile_id: &str) -> Result<Vec<OpenFileResult>> {
+    let profile_uuid = parse_uuid(profile_id)?;
+    let repo = ProfileFileRepository::new(db.pool());
+    let files = repo.find_openable(profile_uuid).await?;
+    let mut results = Vec::with_capacity(files.len());
+
+    for file in files {
+      let result = Self::open_file(&file.path, file.target_app.as_deref());
+      results.push(result);
+      // Give the target app a moment to come up before the next document
+      tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    }
+
+    Ok(results)
+  }
+}
@@ -0,0 +1,151 @@
+//! Fix-it actions for failures Smoothie can recover from itself
+//!
+//! A few failures come up again and again and have one obvious remedy:
+//! displayplacer isn't installed, Screen Recording was never granted, an app
+//! in a profile has since been uninstalled. Each is named by a failure code,
+//! which activation reports and app launch results carry; `get_fixes` turns
+//! the code into actions the UI can offer and `apply_fix` carries one out.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  services::{system_service::SystemService, AppService, SystemBackend},
+};
+use serde::{Deserialize, Serialize};
+
+/// A known failure with a remedy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCode {
+  DisplayplacerMissing,
+  ScreenRecordingDenied,
+  AppNotInstalled,
+}
+
+/// Something `apply_fix` can do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FixAction {
+  InstallDisplayplacer,
+  OpenScreenRecordingSettings,
+  RemoveAppFromProfile,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixDto {
+  pub action: FixAction,
+  pub title: String,
+  pub description: String,
+  /// `apply_fix` needs the id of the profile app it's about
+  pub needs_app_id: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixResultDto {
+  pub action: FixAction,
+  /// False when the fix was started but needs the user to finish it
+  pub completed: bool,
+  pub message: String,
+}
+
+fn fix(action: FixAction, title: &str, description: &str) -> FixDto {
+  FixDto {
+    action,
+    title: title.to_string(),
+    description: description.to_string(),
+    needs_app_id: action == FixAction::RemoveAppFromProfile,
+  }
+}
+
+pub struct FixService;
+
+impl FixService {
+  /// The actions that fix `code`, most direct first
+  pub fn get_fixes(code: FailureCode) -> Vec<FixDto> {
+    match code {
+      FailureCode::DisplayplacerMissing => vec![fix(
+        FixAction::InstallDisplayplacer,
+        "Install displayplacer",
+        "Runs brew install jakehilborn/jakehilborn/displayplacer; needs Homebrew",
+      )],
+      FailureCode::ScreenRecordingDenied => vec![fix(
+        FixAction::OpenScreenRecordingSettings,
+        "Open Screen Recording settings",
+        "Allow Smoothie in the list, then restart it",
+      )],
+      FailureCode::AppNotInstalled => vec![fix(
+        FixAction::RemoveAppFromProfile,
+        "Remove the app from the profile",
+        "The profile stops launching the app; its saved windows go with it",
+      )],
+    }
+  }
+
+  /// Carry out `action`. `app_id` is the profile app for actions about one.
+  pub async fn apply_fix(
+    db: &Database,
+    system: &dyn SystemBackend,
+    action: FixAction,
+    app_id: Option<&str>,
+  ) -> Result<FixResultDto> {
+    let (completed, message) = match action {
+      FixAction::InstallDisplayplacer => {
+        let path = tokio::task::spawn_blocking(SystemService::install_displayplacer)
+          .await
+          .map_err(|e| SmoothieError::SystemError(format!("Install failed: {}", e)))??;
+        (true, format!("displayplacer installed at {}", path))
+      }
+      FixAction::OpenScreenRecordingSettings => {
+        if !system.request_display_permission() {
+          return Err(SmoothieError::SystemError(
+            "Could not open System Settings".into(),
+          ));
+        }
+        (
+          false,
+          "Allow Smoothie under Screen Recording, then restart it".to_string(),
+        )
+      }
+      FixAction::RemoveAppFromProfile => {
+        let app_id = app_id.ok_or_else(|| {
+          SmoothieError::ValidationError("Removing an app needs its appId".into())
+        })?;
+        AppService::delete_app(db, app_id).await?;
+        (true, "App removed from the profile".to_string())
+      }
+    };
+
+    tracing::info!(action = ?action, completed, "Applied fix");
+    Ok(FixResultDto {
+      action,
+      completed,
+      message,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_every_failure_code_has_a_fix() {
+    let codes = [
+      FailureCode::DisplayplacerMissing,
+      FailureCode::ScreenRecordingDenied,
+      FailureCode::AppNotInstalled,
+    ];
+    for code in codes {
+      assert!(!FixService::get_fixes(code).is_empty(), "{:?}", code);
+    }
+
+    let remove = &FixService::get_fixes(FailureCode::AppNotInstalled)[0];
+    assert!(remove.needs_app_id);
+    assert_eq!(
+      serde_json::to_value(FailureCode::ScreenRecordingDenied).unwrap(),
+      "screen_recording_denied"
+    );
+  }
+}
@@ -0,0 +1,366 @@
+//! Focus Session Service
+//!
+//! Pomodoro-style sessions bound to a profile. While one runs, a background
+//! task quits the blocked apps and closes tabs under the blocked URLs every few
+//! seconds. When the planned time is up the session completes, Do Not Disturb
+//! is switched back off and a notification is shown. Completed sessions are
+//! counted on the dashboard.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::dto::{FocusSessionDto, StartFocusSessionRequest},
+  models::entities::FocusSessionEntity,
+  models::{ActivityStatus, FocusSessionStatus, ProfileId, UserId},
+  repositories::{FocusSessionRepository, ProfileRepository},
+  services::{
+    deactivation_service::{close_tabs, quit_app},
+    rule_action_service::run_shortcut,
+    RunningApp, SystemBackend, UserSettingsService, AUDIT_SERVICE,
+  },
+  state::AppState,
+  utils::validation,
+};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+const ENFORCE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Most sessions `get_sessions` returns
+const MAX_SESSION_LIST: i64 = 200;
+
+/// Running apps whose bundle ID is on the blocklist
+fn blocked_running<'a>(running: &'a [RunningApp], blocked: &[String]) -> Vec<&'a RunningApp> {
+  running
+    .iter()
+    .filter(|app| {
+      blocked
+        .iter()
+        .any(|b| b.eq_ignore_ascii_case(&app.bundle_id))
+    })
+    .collect()
+}
+
+fn trimmed(items: &[String]) -> Vec<String> {
+  items.iter().map(|item| item.trim().to_string()).collect()
+}
+
+pub struct FocusSessionService;
+
+impl FocusSessionService {
+  /// Start a session for one of the user's profiles; only one runs at a time
+  pub async fn start(
+    db: &Database,
+    user_id: UserId,
+    req: StartFocusSessionRequest,
+  ) -> Result<FocusSessionDto> {
+    validation::validate(&req)?;
+    let user_uuid = user_id.as_uuid();
+
+    let profile = ProfileRepository::new(db.pool())
+      .find_by_id(req.profile_id.as_uuid())
+      .await?
+      .filter(|profile| profile.user_id == user_uuid)
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?;
+
+    let repo = FocusSessionRepository::new(db.pool());
+    if repo.find_active(user_uuid).await?.is_some() {
+      return Err(SmoothieError::ValidationError(
+        "A focus session is already running".into(),
+      ));
+    }
+
+    let session = repo
+      .create(
+        user_uuid,
+        profile.id,
+        req.duration_minutes,
+        &trimmed(&req.blocked_apps),
+        &trimmed(&req.blocked_urls),
+        req.dnd_shortcut.as_deref().map(str::trim),
+        req.dnd_off_shortcut.as_deref().map(str::trim),
+      )
+      .await?;
+
+    if let Some(shortcut) = &session.dnd_shortcut {
+      if let Err(e) = run_shortcut(shortcut).await {
+        tracing::warn!(shortcut = %shortcut, "Failed to turn on Do Not Disturb: {}", e);
+      }
+    }
+
+    let _ = AUDIT_SERVICE
+      .log_activity(
+        db,
+        &user_uuid.to_string(),
+        "focus_session_started",
+        Some("profile"),
+        Some(&profile.id.to_string()),
+        Some(&profile.name),
+        Some(json!({
+          "session_id": session.id,
+          "planned_minutes": session.planned_minutes,
+          "blocked_apps": session.blocked_apps,
+          "blocked_urls": session.blocked_urls,
+        })),
+        ActivityStatus::Success,
+        None,
+        None,
+      )
+      .await;
+
+    Ok(FocusSessionDto::from(session))
+  }
+
+  /// The running session, if any
+  pub async fn get_active(db: &Database, user_id: UserId) -> Result<Option<FocusSessionDto>> {
+    let session = FocusSessionRepository::new(db.pool())
+      .find_active(user_id.as_uuid())
+      .await?;
+    Ok(session.map(FocusSessionDto::from))
+  }
+
+  /// Recent sessions, newest first
+  pub async fn get_sessions(
+    db: &Database,
+    user_id: UserId,
+    profile_id: Option<ProfileId>,
+    limit: Option<i64>,
+  ) -> Result<Vec<FocusSessionDto>> {
+    let limit = limit.unwrap_or(50).clamp(1, MAX_SESSION_LIST);
+    let sessions = FocusSessionRepository::new(db.pool())
+      .find_recent(user_id.as_uuid(), profile_id.map(|id| id.as_uuid()), limit)
+      .await?;
+    Ok(sessions.into_iter().map(FocusSessionDto::from).collect())
+  }
+
+  /// End the running session before its time is up
+  pub async fn cancel(
+    db: &Database,
+    system: &dyn SystemBackend,
+    user_id: UserId,
+  ) -> Result<FocusSessionDto> {
+    let not_running = || SmoothieError::NotFound("No focus session is running".into());
+    let session = FocusSessionRepository::new(db.pool())
+      .find_active(user_id.as_uuid())
+      .await?
+      .ok_or_else(not_running)?;
+
+    let finished = Self::finish(
+      db,
+      system,
+      &session,
+      FocusSessionStatus::Cancelled,
+      Utc::now(),
+    )
+    .await?
+    .ok_or_else(not_running)?;
+    Ok(FocusSessionDto::from(finished))
+  }
+
+  /// One enforcement pass: complete the session once its time is up,
+  /// otherwise quit blocked apps and close blocked tabs
+  pub async fn enforce(db: &Database, system: Arc<dyn SystemBackend>, user_id: Uuid) -> Result<()> {
+    let repo = FocusSessionRepository::new(db.pool());
+    let Some(session) = repo.find_active(user_id).await? else {
+      return Ok(());
+    };
+
+    // Sessions that ran out while the app was closed complete on time too
+    let ends_at = session.ends_at();
+    if Utc::now() >= ends_at {
+      Self::finish(
+        db,
+        system.as_ref(),
+        &session,
+        FocusSessionStatus::Completed,
+        ends_at,
+      )
+      .await?;
+      return Ok(());
+    }
+
+    let blocked_apps = session.blocked_apps();
+    let blocked_urls = session.blocked_urls();
+    if blocked_apps.is_empty() && blocked_urls.is_empty() {
+      return Ok(());
+    }
+
+    let running = tokio::task::spawn_blocking(move || system.get_running_apps())
+      .await
+      .unwrap_or_default();
+
+    let mut quits = 0;
+    for app in blocked_running(&running, &blocked_apps) {
+      match quit_app(&app.bundle_id).await {
+        Ok(_) => {
+          tracing::info!(bundle_id = %app.bundle_id, "Quit app blocked by focus session");
+          quits += 1;
+        }
+        Err(e) => tracing::warn!(bundle_id = %app.bundle_id, "Failed to quit blocked app: {}", e),
+      }
+    }
+    if quits > 0 {
+      repo.add_blocked_app_quits(session.id, quits).await?;
+    }
+
+    // `close_tabs` skips apps that aren't scriptable browsers without running anything
+    for app in &running {
+      for url in &blocked_urls {
+        if let Err(e) = close_tabs(&app.name, url).await {
+          tracing::warn!(browser = %app.name, url = %url, "Failed to close blocked tabs: {}", e);
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Enforce the running session's blocklists in the background
+  pub fn spawn_enforcer(state: Arc<AppState>, user_id: Uuid) {
+    let enforcer_state = state.clone();
+    state.tasks.spawn("focus_session_enforcer", move || {
+      let state = enforcer_state.clone();
+      async move {
+        let mut ticker = tokio::time::interval(ENFORCE_INTERVAL);
+        loop {
+          ticker.tick().await;
+          if let Err(e) = Self::enforce(&state.db, state.system.clone(), user_id).await {
+            tracing::warn!("Focus session enforcement failed: {}", e);
+          }
+        }
+      }
+    });
+  }
+
+  /// Close the session and undo its Do Not Disturb; `None` if it had already ended
+  async fn finish(
+    db: &Database,
+    system: &dyn SystemBackend,
+    session: &FocusSessionEntity,
+    status: FocusSessionStatus,
+    ended_at: DateTime<Utc>,
+  ) -> Result<Option<FocusSessionEntity>> {
+    let Some(finished) = FocusSessionRepository::new(db.pool())
+      .finish(session.id, status, ended_at)
+      .await?
+    else {
+      return Ok(None);
+    };
+
+    if let Some(shortcut) = &finished.dnd_off_shortcut {
+      if let Err(e) = run_shortcut(shortcut).await {
+        tracing::warn!(shortcut = %shortcut, "Failed to turn off Do Not Disturb: {}", e);
+      }
+    }
+
+    let focused_minutes = (ended_at - finished.started_at).num_minutes();
+    if status == FocusSessionStatus::Completed {
+      let notify = UserSettingsService::get_settings(db, finished.user_id)
+        .await
+        .map(|s| s.notifications_enabled)
+        .unwrap_or(true);
+      if notify {
+        let body = format!(
+          "{} minutes of focus done. Time for a break.",
+          focused_minutes
+        );
+        if let Err(e) = system.show_notification("Smoothie: Focus session complete", &body) {
+          tracing::warn!("Failed to show focus session notification: {}", e);
+        }
+      }
+    }
+
+    let _ = AUDIT_SERVICE
+      .log_activity(
+        db,
+        &finished.user_id.to_string(),
+        &format!("focus_session_{}", status),
+        Some("profile"),
+        Some(&finished.profile_id.to_string()),
+        None,
+        Some(json!({
+          "session_id": finished.id,
+          "planned_minutes": finished.planned_minutes,
+          "focused_minutes": focused_minutes,
+          "blocked_app_quits": finished.blocked_app_quits,
+        })),
+        ActivityStatus::Success,
+        None,
+        None,
+      )
+      .await;
+
+    Ok(Some(finished))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use crate::services::system_backend::MockSystemBackend;
+
+  #[test]
+  fn test_blocked_running_matches_bundle_ids_case_insensitively() {
+    let running = MockSystemBackend::with_sample_layout().get_running_apps();
+    let blocked = vec![
+      "COM.APPLE.SAFARI".to_string(),
+      "com.tinyspeck.slack".to_string(),
+    ];
+
+    let names: Vec<_> = blocked_running(&running, &blocked)
+      .into_iter()
+      .map(|app| app.name.as_str())
+      .collect();
+    assert_eq!(names, ["Safari"]);
+  }
+
+  #[tokio::test]
+  async fn test_session_completes_once_its_time_is_up() {
+    let test_db = test_db_or_skip!();
+    let (user_id, profile_id) = test_db.seed_user_and_profile("Deep work").await;
+
+    let req = StartFocusSessionRequest {
+      profile_id: ProfileId::from(profile_id),
+      duration_minutes: 25,
+      blocked_apps: vec![],
+      blocked_urls: vec![" https://news.ycombinator.com".into()],
+      dnd_shortcut: None,
+      dnd_off_shortcut: None,
+    };
+    let started = FocusSessionService::start(&test_db.db, user_id.into(), req.clone())
+      .await
+      .unwrap();
+    assert_eq!(started.status, FocusSessionStatus::Active);
+    assert_eq!(started.blocked_urls, ["https://news.ycombinator.com"]);
+    assert!(
+      FocusSessionService::start(&test_db.db, user_id.into(), req)
+        .await
+        .is_err(),
+      "a second session can't start while one runs"
+    );
+
+    sqlx::query("UPDATE focus_sessions SET started_at = NOW() - INTERVAL '30 minutes'")
+      .execute(test_db.pool())
+      .await
+      .unwrap();
+    let system = Arc::new(MockSystemBackend::default());
+    FocusSessionService::enforce(&test_db.db, system.clone(), user_id)
+      .await
+      .unwrap();
+
+    let sessions = FocusSessionService::get_sessions(&test_db.db, user_id.into(), None, None)
+      .await
+      .unwrap();
+    assert_eq!(sessions[0].status, FocusSessionStatus::Completed);
+    assert_eq!(sessions[0].remaining_seconds, None);
+    assert_eq!(system.notifications().len(), 1);
+    assert!(FocusSessionService::get_active(&test_db.db, user_id.into())
+      .await
+      .unwrap()
+      .is_none());
+  }
+}
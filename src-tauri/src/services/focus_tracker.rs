@@ -0,0 +1,169 @@
+//! Foreground app time per profile
+//!
+//! macOS announces app switches through NSWorkspace notifications; like the
+//! other watchers, the tracker samples the frontmost app instead, which is
+//! accurate to the poll interval. Each stretch an app stays in front while a
+//! profile is active becomes one `app_focus_events` row, written when it ends.
+
+use crate::{
+  repositories::{AuditRepository, ProfileRepository},
+  services::{resource_guard::ThrottledTicker, RunningApp},
+  state::AppState,
+};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+const FOCUS_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Stretches shorter than this (switching past an app) aren't recorded
+const MIN_FOCUS: chrono::Duration = chrono::Duration::seconds(1);
+
+/// An app in front under a profile, since `started_at`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FocusSpan {
+  pub profile_id: Uuid,
+  pub bundle_id: String,
+  pub app_name: String,
+  pub started_at: DateTime<Utc>,
+}
+
+/// Turns samples of the frontmost app into focus spans
+#[derive(Debug, Default)]
+pub struct FocusTracker {
+  current: Option<FocusSpan>,
+}
+
+impl FocusTracker {
+  /// Note the active profile and frontmost app at `now`. Returns the span
+  /// that just ended, with its end time, when either changed.
+  pub fn observe(
+    &mut self,
+    profile_id: Option<Uuid>,
+    app: Option<&RunningApp>,
+    now: DateTime<Utc>,
+  ) -> Option<(FocusSpan, DateTime<Utc>)> {
+    let unchanged = match (&self.current, profile_id, app) {
+      (Some(span), Some(profile_id), Some(app)) => {
+        span.profile_id == profile_id && span.bundle_id == app.bundle_id
+      }
+      (None, None, _) | (None, _, None) => true,
+      _ => false,
+    };
+    if unchanged {
+      return None;
+    }
+
+    let ended = self.current.take();
+    self.current = profile_id.zip(app).map(|(profile_id, app)| FocusSpan {
+      profile_id,
+      bundle_id: app.bundle_id.clone(),
+      app_name: app.name.clone(),
+      started_at: now,
+    });
+    ended
+      .filter(|span| now - span.started_at >= MIN_FOCUS)
+      .map(|span| (span, now))
+  }
+
+  /// Sample the frontmost app in the background and record focus spans
+  pub fn spawn(state: Arc<AppState>, user_id: Uuid) {
+    let tracker_state = state.clone();
+    state.tasks.spawn("focus_tracker", move || {
+      Self::track(tracker_state.clone(), user_id)
+    });
+  }
+
+  async fn track(state: Arc<AppState>, user_id: Uuid) {
+    let mut ticker = ThrottledTicker::new(FOCUS_POLL_INTERVAL);
+    let mut tracker = Self::default();
+    loop {
+      ticker.tick().await;
+      let profile_id = match ProfileRepository::new(state.db.pool())
+        .find_active_id(user_id)
+        .await
+      {
+        Ok(profile_id) => profile_id,
+        Err(e) => {
+          tracing::debug!(
+            "Skipping focus sample, could not load the active profile: {}",
+            e
+          );
+          continue;
+        }
+      };
+      let app = match profile_id {
+        Some(_) => {
+          let system = state.system.clone();
+          tokio::task::spawn_blocking(move || system.get_frontmost_app())
+            .await
+            .ok()
+            .flatten()
+        }
+        None => None,
+      };
+
+      let Some((span, ended_at)) = tracker.observe(profile_id, app.as_ref(), Utc::now()) else {
+        continue;
+      };
+      if let Err(e) = AuditRepository::new(state.db.pool())
+        .record_app_focus(
+          user_id,
+          span.profile_id,
+          &span.bundle_id,
+          &span.app_name,
+          span.started_at,
+          ended_at,
+        )
+        .await
+      {
+        tracing::warn!(bundle_id = %span.bundle_id, "Failed to record app focus: {}", e);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::services::system_backend::MockSystemBackend;
+  use crate::services::SystemBackend;
+
+  #[test]
+  fn test_focus_spans_end_on_app_or_profile_change() {
+    let system = MockSystemBackend::with_sample_layout();
+    let safari = system.get_frontmost_app().unwrap();
+    system.set_frontmost_app("com.apple.Terminal");
+    let terminal = system.get_frontmost_app().unwrap();
+    let (work, home) = (Uuid::new_v4(), Uuid::new_v4());
+    let at = |secs: i64| DateTime::<Utc>::UNIX_EPOCH + chrono::Duration::seconds(secs);
+
+    let mut tracker = FocusTracker::default();
+    assert!(tracker.observe(Some(work), Some(&safari), at(0)).is_none());
+    assert!(tracker.observe(Some(work), Some(&safari), at(2)).is_none());
+
+    let (span, ended_at) = tracker
+      .observe(Some(work), Some(&terminal), at(10))
+      .unwrap();
+    assert_eq!(span.bundle_id, "com.apple.Safari");
+    assert_eq!((span.started_at, ended_at), (at(0), at(10)));
+
+    // Switching profiles ends the span even though Terminal stays in front
+    let (span, _) = tracker
+      .observe(Some(home), Some(&terminal), at(20))
+      .unwrap();
+    assert_eq!(
+      (span.profile_id, span.bundle_id.as_str()),
+      (work, "com.apple.Terminal")
+    );
+
+    // Too brief to count
+    assert!(tracker.observe(Some(home), Some(&safari), at(20)).is_none());
+
+    // Deactivating ends the span; nothing is tracked until a profile is active
+    let (span, _) = tracker.observe(None, Some(&safari), at(30)).unwrap();
+    assert_eq!(span.started_at, at(20));
+    assert!(tracker.observe(None, Some(&terminal), at(40)).is_none());
+  }
+}
@@ -0,0 +1,888 @@
+// Folder sync
+//
+// An alternative to cloud sync for users who'd rather not use Supabase: each
+// profile is written as an encrypted bundle into a `Smoothie Sync` directory
+// inside a folder the user picks, typically in iCloud Drive or Dropbox, and a
+// background task picks up bundles written there by other machines. A bundle
+// holds the profile's rows, as snapshots do, so windows, files, rules and app
+// paths arrive with it, under the same ids on every machine.
+//
+// Bundles are sealed with a key derived from a passphrase entered on every
+// machine; the folder's `sync.json` holds the salt and a check value so a
+// wrong passphrase is caught when sync is turned on. The derived key and the
+// folder path stay in a config file in the app data directory, never in the
+// database.
+//
+// `folder_sync_items` records the content both sides last agreed on for each
+// bundle. A side whose content differs from that has changed; when both have,
+// the more recently modified copy wins, except that an edit always beats a
+// deletion. Local-only profiles are never written to the folder.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::{entities::FolderSyncItemEntity, ProfileDto, UserId},
+  repositories::{FolderSyncRepository, ProfileRows, ProfileSnapshotRepository},
+  services::{resource_guard::ThrottledTicker, ProfileService},
+  state::AppState,
+  utils::{crypto, paths},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use uuid::Uuid;
+
+/// Version of bundles and `sync.json`; newer ones are left alone
+const FOLDER_FORMAT: u32 = 1;
+
+const SYNC_DIR: &str = "Smoothie Sync";
+const MANIFEST_FILE: &str = "sync.json";
+const BUNDLE_EXTENSION: &str = "smoothie";
+const CONFIG_FILE: &str = "folder-sync.json";
+
+/// Sealed into `sync.json` to tell whether a passphrase matches the folder's
+const CHECK_PLAINTEXT: &[u8] = b"smoothie-folder-sync";
+
+/// Content hash recorded for deleted profiles
+const DELETED_HASH: &str = "deleted";
+
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// How often the watcher looks for bundles written by other machines
+const WATCH_INTERVAL: Duration = Duration::from_secs(15);
+/// Local edits are picked up by a full pass at least this often
+const FULL_PASS_INTERVAL: Duration = Duration::from_secs(2 * 60);
+
+lazy_static::lazy_static! {
+  /// The watcher and `sync_now` must not interleave passes
+  static ref SYNC_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSyncStatusDto {
+  pub enabled: bool,
+  pub folder: Option<String>,
+}
+
+/// What one sync pass did
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSyncReportDto {
+  pub exported: u32,
+  pub imported: u32,
+  /// Local profiles deleted because another machine deleted them
+  pub deleted: u32,
+  /// Bundles changed on both sides since the last pass
+  pub conflicts: u32,
+  /// Bundles that couldn't be decrypted or parsed, e.g. from another passphrase
+  pub unreadable: u32,
+  /// Names of locked profiles another machine changed or deleted, left as
+  /// they are until unlocked
+  pub locked: Vec<String>,
+}
+
+/// Where sync writes on this machine
+#[derive(Serialize, Deserialize)]
+struct FolderSyncConfig {
+  folder: PathBuf,
+  /// Hex-encoded derived key
+  key: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FolderManifest {
+  format: u32,
+  salt: String,
+  check: String,
+}
+
+/// One profile as stored in the folder; no `profile` marks a deletion
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FolderBundle {
+  format: u32,
+  sync_id: Uuid,
+  modified_at: DateTime<Utc>,
+  profile: Option<ProfileRows>,
+}
+
+/// One side's copy of a bundle
+#[derive(Debug, Clone, PartialEq)]
+struct Side {
+  hash: String,
+  modified_at: DateTime<Utc>,
+}
+
+impl Side {
+  fn deleted(&self) -> bool {
+    self.hash == DELETED_HASH
+  }
+}
+
+#[derive(Debug, PartialEq)]
+enum Step {
+  Nothing,
+  /// Write the local copy (or its deletion) to the folder
+  Export {
+    conflict: bool,
+  },
+  /// Apply the folder's copy (or its deletion) locally
+  Import {
+    conflict: bool,
+  },
+  /// Both sides already match; record that
+  Agree,
+}
+
+/// Decide what to do for one bundle from the last agreed hash and each side's copy
+fn plan(base: Option<&str>, local: Option<&Side>, remote: Option<&Side>) -> Step {
+  let changed = |side: Option<&Side>| match (base, side) {
+    (Some(base), Some(side)) => side.hash != base,
+    (None, Some(side)) => !side.deleted(),
+    _ => false,
+  };
+
+  match (local, remote) {
+    (None, None) => Step::Nothing,
+    // The bundle file is gone from the folder: put it back
+    (Some(local), None) if !local.deleted() || base.is_some_and(|b| b != DELETED_HASH) => {
+      Step::Export { conflict: false }
+    }
+    (Some(_), None) => Step::Nothing,
+    (None, Some(_)) if changed(remote) => Step::Import { conflict: false },
+    (None, Some(_)) => Step::Nothing,
+    (Some(local), Some(remote)) => match (changed(Some(local)), changed(Some(remote))) {
+      (_, _) if local.hash == remote.hash => {
+        if base == Some(local.hash.as_str()) {
+          Step::Nothing
+        } else {
+          Step::Agree
+        }
+      }
+      (true, true) => {
+        let remote_wins = match (local.deleted(), remote.deleted()) {
+          (true, false) => true,
+          (false, true) => false,
+          _ => remote.modified_at > local.modified_at,
+        };
+        if remote_wins {
+          Step::Import { conflict: true }
+        } else {
+          Step::Export { conflict: true }
+        }
+      }
+      (true, false) => Step::Export { conflict: false },
+      (false, true) => Step::Import { conflict: false },
+      (false, false) => Step::Nothing,
+    },
+  }
+}
+
+fn config_path() -> PathBuf {
+  paths::app_data_dir().join(CONFIG_FILE)
+}
+
+fn sync_dir(folder: &Path) -> PathBuf {
+  folder.join(SYNC_DIR)
+}
+
+fn bundle_path(dir: &Path, sync_id: Uuid) -> PathBuf {
+  dir.join(format!("{}.{}", sync_id, BUNDLE_EXTENSION))
+}
+
+fn parse_key(hex: &str) -> Result<crypto::SecretKey> {
+  crypto::from_hex(hex)
+    .and_then(|bytes| bytes.try_into().ok())
+    .ok_or_else(|| SmoothieError::ValidationError("Folder sync key is corrupted".into()))
+}
+
+fn content_hash(profile: Option<&ProfileRows>) -> Result<String> {
+  match profile {
+    Some(profile) => Ok(crypto::sha256_hex(&serde_json::to_vec(&profile.setup())?)),
+    None => Ok(DELETED_HASH.to_string()),
+  }
+}
+
+/// File names, sizes and modification times in the sync directory, to tell
+/// cheaply whether another machine wrote something
+fn folder_fingerprint(dir: &Path) -> Vec<(String, u64, Option<SystemTime>)> {
+  let mut entries: Vec<_> = std::fs::read_dir(dir)
+    .into_iter()
+    .flatten()
+    .flatten()
+    .filter_map(|entry| {
+      let meta = entry.metadata().ok()?;
+      Some((
+        entry.file_name().to_string_lossy().into_owned(),
+        meta.len(),
+        meta.modified().ok(),
+      ))
+    })
+    .collect();
+  entries.sort();
+  entries
+}
+
+pub struct FolderSyncService;
+
+impl FolderSyncService {
+  /// Sync profiles through `folder`, creating its `Smoothie Sync` directory on
+  /// first use. Machines syncing through the same folder need the same passphrase.
+  pub async fn enable(
+    db: &Database,
+    user_id: UserId,
+    folder: &str,
+    passphrase: &str,
+  ) -> Result<FolderSyncReportDto> {
+    let folder = PathBuf::from(folder.trim());
+    if !folder.is_absolute() || !folder.is_dir() {
+      return Err(SmoothieError::ValidationError(
+        "Choose an existing folder to sync through".into(),
+      ));
+    }
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+      return Err(SmoothieError::ValidationError(format!(
+        "The passphrase needs at least {} characters",
+        MIN_PASSPHRASE_LEN
+      )));
+    }
+
+    let dir = sync_dir(&folder);
+    tokio::fs::create_dir_all(&dir).await?;
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let key = match tokio::fs::read(&manifest_path).await {
+      Ok(data) => {
+        let manifest: FolderManifest = serde_json::from_slice(&data)?;
+        if manifest.format > FOLDER_FORMAT {
+          return Err(SmoothieError::ValidationError(
+            "This folder was set up by a newer version of Smoothie".into(),
+          ));
+        }
+        let salt = crypto::from_hex(&manifest.salt)
+          .ok_or_else(|| SmoothieError::ValidationError("sync.json is corrupted".into()))?;
+        let passphrase = passphrase.to_string();
+        let key = tokio::task::spawn_blocking(move || crypto::derive_key(&passphrase, &salt))
+          .await
+          .map_err(|e| SmoothieError::SystemError(e.to_string()))?;
+        let check = crypto::from_hex(&manifest.check).unwrap_or_default();
+        if !crypto::open(&key, &check).is_ok_and(|plain| plain == CHECK_PLAINTEXT) {
+          return Err(SmoothieError::ValidationError(
+            "The passphrase doesn't match the one this folder was set up with".into(),
+          ));
+        }
+        key
+      }
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+        let salt = crypto::random_salt();
+        let passphrase = passphrase.to_string();
+        let key = tokio::task::spawn_blocking(move || crypto::derive_key(&passphrase, &salt))
+          .await
+          .map_err(|e| SmoothieError::SystemError(e.to_string()))?;
+        let manifest = FolderManifest {
+          format: FOLDER_FORMAT,
+          salt: crypto::to_hex(&salt),
+          check: crypto::to_hex(&crypto::seal(&key, CHECK_PLAINTEXT)?),
+        };
+        paths::write_atomically(&manifest_path, &serde_json::to_vec_pretty(&manifest)?).await?;
+        key
+      }
+      Err(e) => return Err(e.into()),
+    };
+
+    let config = FolderSyncConfig {
+      folder,
+      key: crypto::to_hex(&key),
+    };
+    Self::save_config(&config).await?;
+    // What was agreed with a previous folder says nothing about this one
+    FolderSyncRepository::new(db.pool())
+      .delete_for_user(user_id.as_uuid())
+      .await?;
+    tracing::info!(folder = %config.folder.display(), "Folder sync enabled");
+
+    Self::sync_now(db, user_id).await
+  }
+
+  /// Stop syncing; bundles already in the folder are left in place
+  pub async fn disable(db: &Database, user_id: UserId) -> Result<()> {
+    match tokio::fs::remove_file(config_path()).await {
+      Ok(()) => {}
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+      Err(e) => return Err(e.into()),
+    }
+    FolderSyncRepository::new(db.pool())
+      .delete_for_user(user_id.as_uuid())
+      .await?;
+    tracing::info!("Folder sync disabled");
+    Ok(())
+  }
+
+  pub async fn status() -> Result<FolderSyncStatusDto> {
+    let config = Self::load_config().await?;
+    Ok(FolderSyncStatusDto {
+      enabled: config.is_some(),
+      folder: config.map(|c| c.folder.display().to_string()),
+    })
+  }
+
+  /// Run one sync pass now
+  pub async fn sync_now(db: &Database, user_id: UserId) -> Result<FolderSyncReportDto> {
+    let config = Self::load_config()
+      .await?
+      .ok_or_else(|| SmoothieError::ValidationError("Folder sync is turned off".into()))?;
+    let key = parse_key(&config.key)?;
+    Self::sync_dir_with(db, user_id, &sync_dir(&config.folder), &key).await
+  }
+
+  /// Sync the user's profiles with the bundles in `dir`
+  async fn sync_dir_with(
+    db: &Database,
+    user_id: UserId,
+    dir: &Path,
+    key: &crypto::SecretKey,
+  ) -> Result<FolderSyncReportDto> {
+    let _guard = SYNC_LOCK.lock().await;
+    let mut report = FolderSyncReportDto::default();
+    tokio::fs::create_dir_all(dir).await?;
+
+    let remote = Self::read_bundles(dir, key, &mut report).await?;
+    let repo = FolderSyncRepository::new(db.pool());
+    let items: HashMap<Uuid, FolderSyncItemEntity> = repo
+      .find_by_user(user_id.as_uuid())
+      .await?
+      .into_iter()
+      .map(|item| (item.sync_id, item))
+      .collect();
+
+    let profiles = ProfileService::get_profiles(db, user_id).await?;
+    let local_only: HashSet<Uuid> = profiles
+      .iter()
+      .filter(|p| p.local_only)
+      .map(|p| p.id.as_uuid())
+      .collect();
+    let sync_ids: HashMap<Uuid, Uuid> = items
+      .values()
+      .filter_map(|item| item.profile_id.map(|p| (p, item.sync_id)))
+      .collect();
+
+    // sync_id -> (local profile, its bundle copy)
+    let mut local: HashMap<Uuid, (Option<ProfileDto>, Option<ProfileRows>, Side)> = HashMap::new();
+    for profile in profiles.into_iter().filter(|p| !p.local_only) {
+      let sync_id = sync_ids
+        .get(&profile.id.as_uuid())
+        .copied()
+        .unwrap_or_else(|| profile.id.as_uuid());
+      let (copy, modified_at) = Self::local_copy(db, &profile).await?;
+      let side = Side {
+        hash: content_hash(Some(&copy))?,
+        modified_at,
+      };
+      local.insert(sync_id, (Some(profile), Some(copy), side));
+    }
+    for item in items.values() {
+      if item.profile_id.is_none() {
+        let side = Side {
+          hash: DELETED_HASH.to_string(),
+          modified_at: if item.deleted {
+            item.modified_at
+          } else {
+            Utc::now()
+          },
+        };
+        local.insert(item.sync_id, (None, None, side));
+      }
+    }
+
+    let mut all_ids: Vec<Uuid> = local.keys().chain(remote.keys()).copied().collect();
+    all_ids.sort();
+    all_ids.dedup();
+
+    for sync_id in all_ids {
+      let item = items.get(&sync_id);
+      // Profiles switched to local-only since they were synced stay as they are on both sides
+      if item
+        .and_then(|i| i.profile_id)
+        .is_some_and(|p| local_only.contains(&p))
+      {
+        continue;
+      }
+      let (profile, copy, local_side) = match local.remove(&sync_id) {
+        Some((profile, copy, side)) => (profile, copy, Some(side)),
+        None => (None, None, None),
+      };
+      let remote_entry = remote.get(&sync_id);
+      let remote_side = remote_entry.map(|(_, side)| side);
+
+      match plan(
+        item.map(|i| i.content_hash.as_str()),
+        local_side.as_ref(),
+        remote_side,
+      ) {
+        Step::Nothing => {}
+        Step::Agree => {
+          let Some(side) = local_side.as_ref() else {
+            continue;
+          };
+          repo
+            .upsert(
+              sync_id,
+              user_id.as_uuid(),
+              profile.as_ref().map(|p| p.id.as_uuid()),
+              &side.hash,
+              side.modified_at,
+              side.deleted(),
+            )
+            .await?;
+        }
+        Step::Export { conflict } => {
+          let Some(side) = local_side.as_ref() else {
+            continue;
+          };
+          let bundle = FolderBundle {
+            format: FOLDER_FORMAT,
+            sync_id,
+            modified_at: side.modified_at,
+            profile: copy,
+          };
+          let sealed = crypto::seal(key, &serde_json::to_vec(&bundle)?)?;
+          paths::write_atomically(&bundle_path(dir, sync_id), &sealed).await?;
+          repo
+            .upsert(
+              sync_id,
+              user_id.as_uuid(),
+              profile.as_ref().map(|p| p.id.as_uuid()),
+              &side.hash,
+              side.modified_at,
+              side.deleted(),
+            )
+            .await?;
+          report.exported += 1;
+          if conflict {
+            report.conflicts += 1;
+            tracing::info!(%sync_id, "Folder sync conflict: kept this machine's copy");
+          }
+        }
+        Step::Import { conflict } => {
+          let Some((bundle, side)) = remote_entry else {
+            continue;
+          };
+          // Not recorded as agreed, so the change applies once it's unlocked
+          if let Some(existing) = profile.as_ref().filter(|p| p.is_locked) {
+            report.locked.push(existing.name.clone());
+            continue;
+          }
+          let snapshots = ProfileSnapshotRepository::new(db.pool());
+          let profile_id = match (&bundle.profile, &profile) {
+            (Some(rows), Some(existing)) => {
+              let rows = rows.clone().with_profile_id(existing.id.as_uuid());
+              snapshots.restore(user_id.as_uuid(), &rows).await?;
+              report.imported += 1;
+              Some(existing.id.as_uuid())
+            }
+            (Some(rows), None) => {
+              snapshots.restore(user_id.as_uuid(), rows).await?;
+              report.imported += 1;
+              rows.profile_id()
+            }
+            (None, Some(existing)) => {
+              ProfileService::delete_profile(db, existing.id).await?;
+              report.deleted += 1;
+              None
+            }
+            (None, None) => None,
+          };
+          repo
+            .upsert(
+              sync_id,
+              user_id.as_uuid(),
+              profile_id,
+              &side.hash,
+              side.modified_at,
+              side.deleted(),
+            )
+            .await?;
+          if conflict {
+            report.conflicts += 1;
+            tracing::info!(%sync_id, "Folder sync conflict: took the other machine's copy");
+          }
+        }
+      }
+    }
+
+    tracing::info!(
+      exported = report.exported,
+      imported = report.imported,
+      deleted = report.deleted,
+      conflicts = report.conflicts,
+      unreadable = report.unreadable,
+      locked = report.locked.len(),
+      "Folder sync pass finished"
+    );
+    Ok(report)
+  }
+
+  /// Watch the sync folder for bundles from other machines and write local edits to it
+  pub fn spawn_watcher(state: Arc<AppState>, user_id: Uuid) {
+    let watcher_state = state.clone();
+    state.tasks.spawn("folder_sync_watcher", move || {
+      let state = watcher_state.clone();
+      async move {
+        let mut ticker = ThrottledTicker::new(WATCH_INTERVAL);
+        let mut last_fingerprint = None;
+        let mut last_pass: Option<Instant> = None;
+        loop {
+          ticker.tick().await;
+          let config = match Self::load_config().await {
+            Ok(Some(config)) => config,
+            Ok(None) => continue,
+            Err(e) => {
+              tracing::warn!("Failed to read folder sync config: {}", e);
+              continue;
+            }
+          };
+          let dir = sync_dir(&config.folder);
+          let fingerprint = folder_fingerprint(&dir);
+          let due = last_pass.map_or(true, |at| at.elapsed() >= FULL_PASS_INTERVAL);
+          if !due && last_fingerprint.as_ref() == Some(&fingerprint) {
+            continue;
+          }
+
+          let result = match parse_key(&config.key) {
+            Ok(key) => Self::sync_dir_with(&state.db, user_id.into(), &dir, &key).await,
+            Err(e) => Err(e),
+          };
+          if let Err(e) = result {
+            tracing::warn!("Folder sync failed: {}", e);
+          }
+          last_pass = Some(Instant::now());
+          last_fingerprint = Some(folder_fingerprint(&dir));
+        }
+      }
+    });
+  }
+
+  /// Decrypt every readable bundle in `dir`, keyed by sync id
+  async fn read_bundles(
+    dir: &Path,
+    key: &crypto::SecretKey,
+    report: &mut FolderSyncReportDto,
+  ) -> Result<HashMap<Uuid, (FolderBundle, Side)>> {
+    let mut bundles = HashMap::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+      let path = entry.path();
+      if path.extension().and_then(|e| e.to_str()) != Some(BUNDLE_EXTENSION) {
+        continue;
+      }
+      let bundle = tokio::fs::read(&path)
+        .await
+        .map_err(SmoothieError::from)
+        .and_then(|sealed| crypto::open(key, &sealed))
+        .and_then(|json| Ok(serde_json::from_slice::<FolderBundle>(&json)?));
+      match bundle {
+        Ok(bundle) if bundle.format <= FOLDER_FORMAT => {
+          let side = Side {
+            hash: content_hash(bundle.profile.as_ref())?,
+            modified_at: bundle.modified_at,
+          };
+          bundles.insert(bundle.sync_id, (bundle, side));
+        }
+        Ok(_) => {
+          tracing::warn!(path = %path.display(), "Skipping bundle from a newer version");
+        }
+        Err(e) => {
+          tracing::warn!(path = %path.display(), "Unreadable folder sync bundle: {}", e);
+          report.unreadable += 1;
+        }
+      }
+    }
+    Ok(bundles)
+  }
+
+  /// A profile's bundle copy and when it or any of its items last changed
+  async fn local_copy(db: &Database, profile: &ProfileDto) -> Result<(ProfileRows, DateTime<Utc>)> {
+    let rows = ProfileSnapshotRepository::new(db.pool())
+      .export(profile.id.as_uuid())
+      .await?;
+    let modified_at = rows.updated_at().unwrap_or_else(Utc::now);
+    Ok((rows, modified_at))
+  }
+
+  async fn load_config() -> Result<Option<FolderSyncConfig>> {
+    match tokio::fs::read(config_path()).await {
+      Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+      Err(e) => Err(e.into()),
+    }
+  }
+
+  async fn save_config(config: &FolderSyncConfig) -> Result<()> {
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+      tokio::fs::create_dir_all(parent).await?;
+    }
+    paths::write_atomically(&path, &serde_json::to_vec_pretty(config)?).await?;
+    // The derived key opens every bundle; keep it readable by this user only
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      tokio::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).await?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use crate::models::{
+    ActivationSource, CreateAppRequest, CreateMonitorRequest, CreateProfileRequest, ProfileType,
+    UpdateProfileRequest,
+  };
+  use crate::services::{window_service::WindowService, AppService, MonitorService};
+
+  fn side(hash: &str, minute: u32) -> Side {
+    Side {
+      hash: hash.into(),
+      modified_at: format!("2026-10-01T10:{:02}:00Z", minute).parse().unwrap(),
+    }
+  }
+
+  #[test]
+  fn test_plan_resolves_conflicts_by_modification_time() {
+    let (a, b, c) = (side("a", 0), side("b", 5), side("c", 9));
+
+    // Only one side moved on from the agreed copy
+    assert_eq!(
+      plan(Some("a"), Some(&b), Some(&a)),
+      Step::Export { conflict: false }
+    );
+    assert_eq!(
+      plan(Some("a"), Some(&a), Some(&b)),
+      Step::Import { conflict: false }
+    );
+    assert_eq!(plan(Some("a"), Some(&a), Some(&a)), Step::Nothing);
+
+    // Both did: the later edit wins
+    assert_eq!(
+      plan(Some("a"), Some(&c), Some(&b)),
+      Step::Export { conflict: true }
+    );
+    assert_eq!(
+      plan(Some("a"), Some(&b), Some(&c)),
+      Step::Import { conflict: true }
+    );
+
+    // An edit beats a deletion, however old
+    let deleted = side(DELETED_HASH, 59);
+    assert_eq!(
+      plan(Some("a"), Some(&deleted), Some(&b)),
+      Step::Import { conflict: true }
+    );
+
+    // New on either side, or removed from the folder by hand
+    assert_eq!(plan(None, Some(&a), None), Step::Export { conflict: false });
+    assert_eq!(plan(None, None, Some(&a)), Step::Import { conflict: false });
+    assert_eq!(
+      plan(Some("a"), Some(&a), None),
+      Step::Export { conflict: false }
+    );
+    assert_eq!(plan(None, None, Some(&deleted)), Step::Nothing);
+  }
+
+  #[tokio::test]
+  async fn test_profiles_round_trip_between_machines() {
+    // Each machine has its own database
+    let (laptop_db, desktop_db) = (test_db_or_skip!(), test_db_or_skip!());
+    let laptop = (&laptop_db.db, UserId::from(Uuid::new_v4()));
+    let desktop = (&desktop_db.db, UserId::from(Uuid::new_v4()));
+    for (db, user) in [laptop, desktop] {
+      ProfileService::ensure_user_exists(db, user.as_uuid())
+        .await
+        .unwrap();
+    }
+    let dir = std::env::temp_dir().join(format!("smoothie-folder-sync-{}", Uuid::new_v4()));
+    let key = crypto::derive_key("correct horse", &crypto::random_salt());
+    let sync = |(db, user)| FolderSyncService::sync_dir_with(db, user, &dir, &key);
+
+    let profile = ProfileService::create_profile(
+      laptop.0,
+      laptop.1,
+      CreateProfileRequest {
+        name: "Deep work".into(),
+        description: None,
+        profile_type: ProfileType::Work,
+        tags: None,
+        notes: None,
+      },
+    )
+    .await
+    .unwrap();
+    let monitor = MonitorService::create_monitor(
+      laptop.0,
+      &profile.id.to_string(),
+      CreateMonitorRequest {
+        name: "Desk".into(),
+        resolution: "2560x1440".into(),
+        orientation: "landscape".into(),
+        is_primary: true,
+        x: 0,
+        y: 0,
+        width: 2560,
+        height: 1440,
+        display_index: 0,
+      },
+    )
+    .await
+    .unwrap();
+    let terminal = AppService::create_app(
+      laptop.0,
+      &profile.id.to_string(),
+      &laptop.1.to_string(),
+      CreateAppRequest {
+        name: "Terminal".into(),
+        bundle_id: "com.apple.Terminal".into(),
+        exe_path: Some("/System/Applications/Utilities/Terminal.app".into()),
+        launch_on_activate: true,
+        monitor_preference: None,
+        startup_delay_ms: None,
+        order_index: None,
+      },
+    )
+    .await
+    .unwrap();
+    sqlx::query(
+      r#"
+      INSERT INTO windows (id, profile_id, app_id, monitor_id, x, y, width, height, state)
+      VALUES (uuid_generate_v4(), $1, $2::uuid, $3::uuid, 0, 0, 1280, 1440, 'normal')
+      "#,
+    )
+    .bind(profile.id.as_uuid())
+    .bind(&terminal.id)
+    .bind(&monitor.id)
+    .execute(laptop.0.pool())
+    .await
+    .unwrap();
+
+    assert_eq!(sync(laptop).await.unwrap().exported, 1);
+    assert_eq!(sync(desktop).await.unwrap().imported, 1);
+    let copies = ProfileService::get_profiles(desktop.0, desktop.1)
+      .await
+      .unwrap();
+    assert_eq!(copies.len(), 1);
+    assert_eq!(
+      (copies[0].name.as_str(), copies[0].app_count),
+      ("Deep work", 1)
+    );
+
+    // The window layout and app paths come along
+    let apps = AppService::get_apps(desktop.0, &copies[0].id.to_string())
+      .await
+      .unwrap();
+    assert_eq!(apps[0].exe_path, terminal.exe_path);
+    let windows = WindowService::get_windows(desktop.0, &copies[0].id.to_string())
+      .await
+      .unwrap();
+    assert_eq!(windows.len(), 1);
+    assert_eq!(
+      (windows[0].monitor_id.as_str(), windows[0].width),
+      (monitor.id.as_str(), 1280)
+    );
+
+    // Nothing changed, nothing to do, even after starting the profile on
+    // either machine; a deletion then travels back
+    ProfileService::activate_profile(desktop.0, copies[0].id, desktop.1, ActivationSource::Manual)
+      .await
+      .unwrap();
+    for machine in [desktop, laptop] {
+      let settled = sync(machine).await.unwrap();
+      assert_eq!((settled.exported, settled.imported), (0, 0));
+    }
+    ProfileService::delete_profile(desktop.0, copies[0].id)
+      .await
+      .unwrap();
+    assert_eq!(sync(desktop).await.unwrap().exported, 1);
+    assert_eq!(sync(laptop).await.unwrap().deleted, 1);
+    assert!(ProfileService::get_profiles(laptop.0, laptop.1)
+      .await
+      .unwrap()
+      .is_empty());
+
+    let _ = std::fs::remove_dir_all(&dir);
+  }
+
+  #[tokio::test]
+  async fn test_locked_profiles_are_skipped_until_unlocked() {
+    let (laptop_db, desktop_db) = (test_db_or_skip!(), test_db_or_skip!());
+    let laptop = (&laptop_db.db, UserId::from(Uuid::new_v4()));
+    let desktop = (&desktop_db.db, UserId::from(Uuid::new_v4()));
+    for (db, user) in [laptop, desktop] {
+      ProfileService::ensure_user_exists(db, user.as_uuid())
+        .await
+        .unwrap();
+    }
+    let dir = std::env::temp_dir().join(format!("smoothie-folder-sync-{}", Uuid::new_v4()));
+    let key = crypto::derive_key("correct horse", &crypto::random_salt());
+    let sync = |(db, user)| FolderSyncService::sync_dir_with(db, user, &dir, &key);
+
+    let mut originals = Vec::new();
+    for name in ["Deep work", "Meetings"] {
+      let request = CreateProfileRequest {
+        name: name.into(),
+        description: None,
+        profile_type: ProfileType::Work,
+        tags: None,
+        notes: None,
+      };
+      originals.push(
+        ProfileService::create_profile(laptop.0, laptop.1, request)
+          .await
+          .unwrap(),
+      );
+    }
+    sync(laptop).await.unwrap();
+    assert_eq!(sync(desktop).await.unwrap().imported, 2);
+    let copies = ProfileService::get_profiles(desktop.0, desktop.1)
+      .await
+      .unwrap();
+    let deep_work = copies.iter().find(|p| p.name == "Deep work").unwrap();
+    ProfileService::set_locked(desktop.0, deep_work.id, true)
+      .await
+      .unwrap();
+
+    for original in &originals {
+      ProfileService::update_profile_extended(
+        laptop.0,
+        original.id,
+        UpdateProfileRequest {
+          description: Some("Edited on the laptop".into()),
+          ..Default::default()
+        },
+      )
+      .await
+      .unwrap();
+    }
+    assert_eq!(sync(laptop).await.unwrap().exported, 2);
+    let report = sync(desktop).await.unwrap();
+    assert_eq!(report.imported, 1);
+    assert_eq!(report.locked, ["Deep work"]);
+
+    ProfileService::set_locked(desktop.0, deep_work.id, false)
+      .await
+      .unwrap();
+    let report = sync(desktop).await.unwrap();
+    assert_eq!(report.imported, 1);
+    assert!(report.locked.is_empty());
+    let copy = ProfileService::get_profile(desktop.0, deep_work.id)
+      .await
+      .unwrap();
+    assert_eq!(copy.description.as_deref(), Some("Edited on the laptop"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+  }
+}
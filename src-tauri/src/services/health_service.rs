@@ -0,0 +1,351 @@
+//! Health Service
+//!
+//! Aggregates the status of the subsystems Smoothie depends on (database,
+//! schema, macOS permissions, background components and external tools) into
+//! one report for the diagnostics screen.
+
+use crate::db::{migrations, Database};
+use crate::services::audit_backend::supabase_audit;
+use crate::services::resource_guard::{ResourceGuard, ThrottleStatus};
+use crate::services::system_backend::SystemBackend;
+use crate::services::system_service::SystemService;
+use crate::state::supervisor::{BackgroundTaskStatus, TaskState};
+use crate::utils::paths::is_executable;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Database round trips slower than this mark the database as degraded
+const SLOW_QUERY_MS: u128 = 500;
+
+/// Directories checked in addition to PATH - GUI apps on macOS start with a minimal PATH
+const EXTRA_BIN_DIRS: [&str; 4] = ["/opt/homebrew/bin", "/usr/local/bin", "/usr/bin", "/bin"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+  Ok,
+  /// Not present in this build or not set up; doesn't affect the overall status
+  NotConfigured,
+  Degraded,
+  Down,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+  pub status: HealthStatus,
+  pub checked_at: String,
+  pub database: DatabaseHealth,
+  pub migrations: MigrationHealth,
+  pub permissions: PermissionHealth,
+  pub watchers: ComponentHealth,
+  pub sync: ComponentHealth,
+  pub tools: Vec<ToolHealth>,
+  /// How much background polling is currently slowed down
+  pub throttle: ThrottleStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseHealth {
+  pub status: HealthStatus,
+  pub latency_ms: Option<u64>,
+  pub pool_size: u32,
+  pub idle_connections: usize,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationHealth {
+  pub status: HealthStatus,
+  pub current_version: Option<i32>,
+  pub expected_version: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionHealth {
+  pub status: HealthStatus,
+  pub screen_recording: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentHealth {
+  pub status: HealthStatus,
+  pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolHealth {
+  pub name: String,
+  pub status: HealthStatus,
+  pub path: Option<String>,
+  /// What stops working when the tool is missing
+  pub used_for: String,
+}
+
+pub struct HealthService;
+
+impl HealthService {
+  /// Run every check and build the report. `tasks` are the supervised
+  /// background tasks, as from `TaskSupervisor::statuses`.
+  pub async fn check(
+    db: &Database,
+    system: &dyn SystemBackend,
+    tasks: &[BackgroundTaskStatus],
+  ) -> HealthReport {
+    let database = Self::check_database(db).await;
+    let migrations = Self::check_migrations(db).await;
+
+    let permissions = Self::check_permissions(system);
+
+    let watchers = check_watchers(tasks);
+    let sync = Self::check_sync().await;
+
+    let tools = Self::check_tools();
+
+    let status = overall_status(
+      [
+        database.status,
+        migrations.status,
+        permissions.status,
+        watchers.status,
+        sync.status,
+      ]
+      .into_iter()
+      .chain(tools.iter().map(|t| t.status)),
+    );
+
+    HealthReport {
+      status,
+      checked_at: chrono::Utc::now().to_rfc3339(),
+      database,
+      migrations,
+      permissions,
+      watchers,
+      sync,
+      tools,
+      throttle: ResourceGuard::status(),
+    }
+  }
+
+  async fn check_database(db: &Database) -> DatabaseHealth {
+    let pool = db.pool();
+    let start = Instant::now();
+    let result = sqlx::query("SELECT 1").execute(pool).await;
+    let elapsed = start.elapsed().as_millis();
+
+    let (status, latency_ms, error) = match result {
+      Ok(_) if elapsed > SLOW_QUERY_MS => (HealthStatus::Degraded, Some(elapsed as u64), None),
+      Ok(_) => (HealthStatus::Ok, Some(elapsed as u64), None),
+      Err(e) => {
+        tracing::warn!("Health check could not reach the database: {}", e);
+        (HealthStatus::Down, None, Some(e.to_string()))
+      }
+    };
+
+    DatabaseHealth {
+      status,
+      latency_ms,
+      pool_size: pool.size(),
+      idle_connections: pool.num_idle(),
+      error,
+    }
+  }
+
+  /// Reachability of the Supabase audit log, the only data sent off the machine
+  async fn check_sync() -> ComponentHealth {
+    let Some(remote) = supabase_audit() else {
+      return ComponentHealth {
+        status: HealthStatus::NotConfigured,
+        detail: Some("Sync is not enabled".into()),
+      };
+    };
+
+    match remote.count_events().await {
+      Ok(count) => ComponentHealth {
+        status: HealthStatus::Ok,
+        detail: Some(format!("{} audit events in Supabase", count)),
+      },
+      Err(e) => {
+        tracing::warn!("Health check could not reach Supabase: {}", e);
+        ComponentHealth {
+          status: HealthStatus::Degraded,
+          detail: Some(e.to_string()),
+        }
+      }
+    }
+  }
+
+  async fn check_migrations(db: &Database) -> MigrationHealth {
+    let current_version = match migrations::applied_version(db.pool()).await {
+      Ok(version) => Some(version),
+      Err(e) => {
+        tracing::warn!("Health check could not read the schema version: {}", e);
+        None
+      }
+    };
+
+    let status = match current_version {
+      Some(version) if version >= migrations::SCHEMA_VERSION => HealthStatus::Ok,
+      Some(_) => HealthStatus::Degraded,
+      None => HealthStatus::Down,
+    };
+
+    MigrationHealth {
+      status,
+      current_version,
+      expected_version: migrations::SCHEMA_VERSION,
+    }
+  }
+
+  pub(crate) fn check_permissions(system: &dyn SystemBackend) -> PermissionHealth {
+    let screen_recording = system.check_display_permission();
+    PermissionHealth {
+      status: if screen_recording {
+        HealthStatus::Ok
+      } else {
+        HealthStatus::Degraded
+      },
+      screen_recording,
+    }
+  }
+
+  pub(crate) fn check_tools() -> Vec<ToolHealth> {
+    let displayplacer = SystemService::find_displayplacer().ok();
+    vec![
+      tool_health("displayplacer", displayplacer, "Applying monitor layouts"),
+      tool_health(
+        "osascript",
+        find_executable("osascript"),
+        "Window placement and admin prompts",
+      ),
+      tool_health(
+        "open",
+        find_executable("open"),
+        "Launching apps, tabs and files",
+      ),
+    ]
+  }
+}
+
+fn tool_health(name: &str, path: Option<String>, used_for: &str) -> ToolHealth {
+  ToolHealth {
+    name: name.to_string(),
+    status: if path.is_some() {
+      HealthStatus::Ok
+    } else {
+      HealthStatus::Degraded
+    },
+    path,
+    used_for: used_for.to_string(),
+  }
+}
+
+fn find_executable(name: &str) -> Option<String> {
+  let path_var = std::env::var_os("PATH").unwrap_or_default();
+  std::env::split_paths(&path_var)
+    .chain(EXTRA_BIN_DIRS.iter().map(PathBuf::from))
+    .map(|dir| dir.join(name))
+    .find(|candidate| is_executable(candidate))
+    .map(|candidate| candidate.to_string_lossy().into_owned())
+}
+
+/// Down when a background task was given up on, degraded while one is
+/// waiting to restart
+fn check_watchers(tasks: &[BackgroundTaskStatus]) -> ComponentHealth {
+  if tasks.is_empty() {
+    return ComponentHealth {
+      status: HealthStatus::NotConfigured,
+      detail: Some("No background watchers are running".into()),
+    };
+  }
+
+  let names = |state| {
+    tasks
+      .iter()
+      .filter(|task| task.state == state)
+      .map(|task| task.name.as_str())
+      .collect::<Vec<_>>()
+  };
+  let failed = names(TaskState::Failed);
+  let restarting = names(TaskState::Restarting);
+  let (status, detail) = if !failed.is_empty() {
+    (
+      HealthStatus::Down,
+      format!("Stopped: {}", failed.join(", ")),
+    )
+  } else if !restarting.is_empty() {
+    (
+      HealthStatus::Degraded,
+      format!("Restarting: {}", restarting.join(", ")),
+    )
+  } else {
+    let running = names(TaskState::Running).len();
+    (HealthStatus::Ok, format!("{} running", running))
+  };
+
+  ComponentHealth {
+    status,
+    detail: Some(detail),
+  }
+}
+
+/// Worst status of all checks; components that aren't configured are ignored
+fn overall_status(statuses: impl IntoIterator<Item = HealthStatus>) -> HealthStatus {
+  statuses
+    .into_iter()
+    .filter(|s| *s != HealthStatus::NotConfigured)
+    .max()
+    .unwrap_or(HealthStatus::Ok)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_overall_status_takes_worst_configured_check() {
+    use HealthStatus::*;
+
+    assert_eq!(overall_status([Ok, NotConfigured, Ok]), Ok);
+    assert_eq!(overall_status([Ok, Degraded, NotConfigured]), Degraded);
+    assert_eq!(overall_status([Degraded, Down, Ok]), Down);
+    assert_eq!(overall_status([]), Ok);
+  }
+
+  #[test]
+  fn test_watcher_health_reports_stopped_and_restarting_tasks() {
+    let task = |name: &str, state| BackgroundTaskStatus {
+      name: name.to_string(),
+      state,
+      restarts: 0,
+      started_at: chrono::Utc::now(),
+      finished_at: None,
+      last_panic: None,
+      last_panic_at: None,
+    };
+
+    assert_eq!(check_watchers(&[]).status, HealthStatus::NotConfigured);
+
+    let mut tasks = vec![
+      task("focus_tracker", TaskState::Running),
+      task("startup_sync", TaskState::Completed),
+    ];
+    let health = check_watchers(&tasks);
+    assert_eq!(health.status, HealthStatus::Ok);
+    assert_eq!(health.detail.as_deref(), Some("1 running"));
+
+    tasks.push(task("device_watcher", TaskState::Restarting));
+    assert_eq!(check_watchers(&tasks).status, HealthStatus::Degraded);
+
+    tasks.push(task("power_watcher", TaskState::Failed));
+    let health = check_watchers(&tasks);
+    assert_eq!(health.status, HealthStatus::Down);
+    assert_eq!(health.detail.as_deref(), Some("Stopped: power_watcher"));
+  }
+}
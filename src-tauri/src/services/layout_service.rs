@@ -0,0 +1,231 @@
+// Layout service - builds preview data for a profile's monitor and window layout
+
+use crate::{
+  db::Database,
+  error::Result,
+  models::dto::{AppDto, MonitorDto},
+  services::{
+    window_service::{WindowDto, WindowService},
+    AppService, MonitorService,
+  },
+};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Colors assigned to monitors, in display order
+const MONITOR_COLORS: &[&str] = &["#3B82F6", "#8B5CF6", "#06B6D4", "#6366F1", "#0EA5E9"];
+
+/// Colors assigned to windows, picked per app so an app keeps its color across monitors
+const WINDOW_COLORS: &[&str] = &[
+  "#F97316", "#22C55E", "#EAB308", "#EF4444", "#EC4899", "#14B8A6", "#A855F7", "#84CC16",
+];
+
+/// A rectangle on the unit canvas (all coordinates in 0.0..=1.0)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewRect {
+  pub id: String,
+  pub label: String,
+  pub sublabel: Option<String>,
+  pub color: String,
+  pub x: f64,
+  pub y: f64,
+  pub width: f64,
+  pub height: f64,
+  /// Monitor the rect belongs to (windows only)
+  pub monitor_id: Option<String>,
+  pub is_primary: bool,
+}
+
+/// Mini-map of what activating a profile will do
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutPreview {
+  pub profile_id: String,
+  /// Canvas size on the unit scale; the longer side is always 1.0
+  pub canvas_width: f64,
+  pub canvas_height: f64,
+  pub monitors: Vec<PreviewRect>,
+  pub windows: Vec<PreviewRect>,
+}
+
+pub struct LayoutService;
+
+impl LayoutService {
+  /// Build the layout preview for a profile from its saved monitors and windows
+  pub async fn get_layout_preview(db: &Database, profile_id: &str) -> Result<LayoutPreview> {
+    let monitors = MonitorService::get_monitors(db, profile_id).await?;
+    let windows = WindowService::get_windows(db, profile_id).await?;
+    let apps = AppService::get_apps(db, profile_id).await?;
+
+    Ok(Self::build_preview(profile_id, &monitors, &windows, &apps))
+  }
+
+  /// Scale monitors and windows (global desktop coordinates) onto a unit canvas.
+  ///
+  /// The scale is uniform so the relative proportions of the displays are kept;
+  /// windows are clipped to the canvas.
+  pub fn build_preview(
+    profile_id: &str,
+    monitors: &[MonitorDto],
+    windows: &[WindowDto],
+    apps: &[AppDto],
+  ) -> LayoutPreview {
+    let empty = LayoutPreview {
+      profile_id: profile_id.to_string(),
+      canvas_width: 1.0,
+      canvas_height: 1.0,
+      monitors: Vec::new(),
+      windows: Vec::new(),
+    };
+    if monitors.is_empty() {
+      return empty;
+    }
+
+    let min_x = monitors.iter().map(|m| m.x).min().unwrap_or(0) as f64;
+    let min_y = monitors.iter().map(|m| m.y).min().unwrap_or(0) as f64;
+    let max_x = monitors.iter().map(|m| m.x + m.width).max().unwrap_or(0) as f64;
+    let max_y = monitors.iter().map(|m| m.y + m.height).max().unwrap_or(0) as f64;
+    let span = (max_x - min_x).max(max_y - min_y);
+    if span <= 0.0 {
+      return empty;
+    }
+
+    let canvas_width = (max_x - min_x) / span;
+    let canvas_height = (max_y - min_y) / span;
+    let scale = |x: i32, y: i32, width: i32, height: i32| {
+      let left = ((x as f64 - min_x) / span).clamp(0.0, canvas_width);
+      let top = ((y as f64 - min_y) / span).clamp(0.0, canvas_height);
+      let right = (((x + width) as f64 - min_x) / span).clamp(0.0, canvas_width);
+      let bottom = (((y + height) as f64 - min_y) / span).clamp(0.0, canvas_height);
+      (left, top, right - left, bottom - top)
+    };
+
+    let monitor_rects = monitors
+      .iter()
+      .enumerate()
+      .map(|(index, m)| {
+        let (x, y, width, height) = scale(m.x, m.y, m.width, m.height);
+        PreviewRect {
+          id: m.id.clone(),
+          label: m.name.clone(),
+          sublabel: Some(m.resolution.clone()),
+          color: MONITOR_COLORS[index % MONITOR_COLORS.len()].to_string(),
+          x,
+          y,
+          width,
+          height,
+          monitor_id: None,
+          is_primary: m.is_primary,
+        }
+      })
+      .collect();
+
+    let app_names: HashMap<&str, &str> = apps
+      .iter()
+      .map(|a| (a.id.as_str(), a.name.as_str()))
+      .collect();
+    let mut app_colors: HashMap<&str, &str> = HashMap::new();
+
+    let window_rects = windows
+      .iter()
+      .filter_map(|w| {
+        let (x, y, width, height) = scale(w.x, w.y, w.width, w.height);
+        // Windows entirely outside the saved monitors have nothing to draw
+        if width <= 0.0 || height <= 0.0 {
+          return None;
+        }
+        let next_color = WINDOW_COLORS[app_colors.len() % WINDOW_COLORS.len()];
+        let color = *app_colors.entry(w.app_id.as_str()).or_insert(next_color);
+
+        Some(PreviewRect {
+          id: w.id.clone(),
+          label: app_names
+            .get(w.app_id.as_str())
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| "Unknown app".to_string()),
+          sublabel: Some(format!("{}x{}", w.width, w.height)),
+          color: color.to_string(),
+          x,
+          y,
+          width,
+          height,
+          monitor_id: Some(w.monitor_id.clone()),
+          is_primary: false,
+        })
+      })
+      .collect();
+
+    LayoutPreview {
+      profile_id: profile_id.to_string(),
+      canvas_width,
+      canvas_height,
+      monitors: monitor_rects,
+      windows: window_rects,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn monitor(id: &str, x: i32, y: i32, width: i32, height: i32) -> MonitorDto {
+    MonitorDto {
+      id: id.to_string(),
+      profile_id: "p".to_string(),
+      name: id.to_string(),
+      resolution: format!("{}x{}", width, height),
+      orientation: "Landscape".to_string(),
+      is_primary: x == 0 && y == 0,
+      x,
+      y,
+      width,
+      height,
+      display_index: 0,
+      brand: None,
+      model: None,
+      refresh_rate: None,
+      scale_factor: None,
+      is_builtin: None,
+      color_depth: None,
+      created_at: None,
+      updated_at: None,
+      fingerprint: None,
+      captured_at: None,
+      blanked: false,
+    }
+  }
+
+  #[test]
+  fn test_build_preview_normalizes_to_unit_canvas() {
+    let monitors = vec![
+      monitor("left", 0, 0, 2000, 1000),
+      monitor("right", 2000, 0, 2000, 1000),
+    ];
+    let windows = vec![WindowDto {
+      id: "w".to_string(),
+      profile_id: "p".to_string(),
+      app_id: "a".to_string(),
+      monitor_id: "right".to_string(),
+      x: 3000,
+      y: 500,
+      width: 2000,
+      height: 500,
+      is_maximized: false,
+      state: "normal".to_string(),
+      zoom_steps: None,
+    }];
+
+    let preview = LayoutService::build_preview("p", &monitors, &windows, &[]);
+
+    assert_eq!(preview.canvas_width, 1.0);
+    assert_eq!(preview.canvas_height, 0.25);
+    assert_eq!(preview.monitors[1].x, 0.5);
+    assert_eq!(preview.monitors[1].width, 0.5);
+    // The window hangs off the right edge and gets clipped
+    assert_eq!(preview.windows[0].x, 0.75);
+    assert_eq!(preview.windows[0].width, 0.25);
+    assert_eq!(preview.windows[0].label, "Unknown app");
+  }
+}
@@ -0,0 +1,120 @@
+// Sanitized log export
+//
+// Writes the recent activity log, system events and error log to a JSON file
+// with URLs, email addresses, file paths and app names redacted, so it can be
+// attached to a public bug report. App names are taken from the installed
+// apps and the apps in the user's profiles.
+
+use crate::{
+  error::Result,
+  models::{LogQueryParams, UserId},
+  repositories::AppRepository,
+  services::{InstalledApp, AUDIT_SERVICE},
+  state::{AppState, INSTALLED_APPS_CACHE_KEY},
+  utils::{
+    log_sanitizer::{LogSanitizer, RedactionCounts, SanitizeOptions},
+    paths,
+  },
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Most recent entries exported from each log
+const EXPORT_LIMIT: i64 = 1000;
+
+fn exports_dir() -> PathBuf {
+  paths::app_data_dir().join("exports")
+}
+
+/// The exported file
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SanitizedLogs {
+  exported_at: DateTime<Utc>,
+  app_version: &'static str,
+  redacted: SanitizeOptions,
+  activity_logs: Vec<Value>,
+  system_events: Vec<Value>,
+  error_logs: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SanitizedLogExportDto {
+  /// Where the export was saved
+  pub path: String,
+  pub activity_logs: usize,
+  pub system_events: usize,
+  pub error_logs: usize,
+  pub redactions: RedactionCounts,
+}
+
+fn sanitize_all<T: Serialize>(sanitizer: &mut LogSanitizer, entries: Vec<T>) -> Result<Vec<Value>> {
+  entries
+    .into_iter()
+    .map(|entry| {
+      let mut value = serde_json::to_value(entry)?;
+      sanitizer.sanitize_value(&mut value);
+      Ok(value)
+    })
+    .collect()
+}
+
+pub struct LogExportService;
+
+impl LogExportService {
+  pub async fn export_sanitized(
+    state: &AppState,
+    user_id: UserId,
+    options: SanitizeOptions,
+  ) -> Result<SanitizedLogExportDto> {
+    let db = &state.db;
+    let mut app_names = AppRepository::new(db.pool())
+      .find_names_by_user_id(user_id.as_uuid())
+      .await?;
+    if let Some(installed) = state.cached::<Vec<InstalledApp>>(INSTALLED_APPS_CACHE_KEY) {
+      app_names.extend(installed.into_iter().map(|app| app.name));
+    }
+    let mut sanitizer = LogSanitizer::new(options, app_names);
+
+    let params = || LogQueryParams {
+      limit: Some(EXPORT_LIMIT),
+      ..Default::default()
+    };
+    let activity_logs = AUDIT_SERVICE
+      .get_activity_logs(db, &user_id.to_string(), params())
+      .await?;
+    let system_events = AUDIT_SERVICE.get_system_events(db, params()).await?;
+    let error_logs = AUDIT_SERVICE.get_error_logs(db, params(), true).await?;
+
+    let exported_at = Utc::now();
+    let logs = SanitizedLogs {
+      exported_at,
+      app_version: env!("CARGO_PKG_VERSION"),
+      redacted: options,
+      activity_logs: sanitize_all(&mut sanitizer, activity_logs)?,
+      system_events: sanitize_all(&mut sanitizer, system_events)?,
+      error_logs: sanitize_all(&mut sanitizer, error_logs)?,
+    };
+
+    let dir = exports_dir();
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = dir.join(format!(
+      "smoothie-logs-{}.json",
+      exported_at.format("%Y%m%d-%H%M%S")
+    ));
+    paths::write_atomically(&path, &serde_json::to_vec_pretty(&logs)?).await?;
+
+    let export = SanitizedLogExportDto {
+      path: path.display().to_string(),
+      activity_logs: logs.activity_logs.len(),
+      system_events: logs.system_events.len(),
+      error_logs: logs.error_logs.len(),
+      redactions: sanitizer.counts(),
+    };
+    tracing::info!(path = %export.path, "Sanitized logs exported");
+    Ok(export)
+  }
+}
@@ -0,0 +1,141 @@
+//! Login Item Service
+//!
+//! Launch at login is a LaunchAgent in `~/Library/LaunchAgents` that runs the
+//! app binary when the user logs in. With background agent mode on, the agent
+//! passes `--background` so the app starts without opening its window, and the
+//! app drops its Dock icon and keeps running when the window is closed, so
+//! monitor watching and automations work without the UI ever being opened.
+//! The agent is rewritten at every start to follow the app if it moves.
+
+use crate::{
+  error::{Result, SmoothieError},
+  models::dto::UserSettingsDto,
+  utils::paths,
+};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Passed by the LaunchAgent to start without opening the window
+pub const BACKGROUND_ARG: &str = "--background";
+
+/// Whether closing the window should leave the app running
+static BACKGROUND_AGENT: AtomicBool = AtomicBool::new(false);
+
+fn launch_agent_path() -> Option<PathBuf> {
+  dirs::home_dir().map(|home| {
+    home
+      .join("Library/LaunchAgents")
+      .join(format!("{}.plist", paths::APP_IDENTIFIER))
+  })
+}
+
+fn escape_xml(value: &str) -> String {
+  value
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+}
+
+/// The LaunchAgent starting `exe` at login
+pub(crate) fn render_launch_agent(exe: &Path, background: bool) -> String {
+  let mut args = format!(
+    "    <string>{}</string>\n",
+    escape_xml(&exe.to_string_lossy())
+  );
+  if background {
+    args.push_str(&format!("    <string>{}</string>\n", BACKGROUND_ARG));
+  }
+  format!(
+    r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>Label</key>
+  <string>{}</string>
+  <key>ProgramArguments</key>
+  <array>
+{}  </array>
+  <key>RunAtLoad</key>
+  <true/>
+  <key>ProcessType</key>
+  <string>Interactive</string>
+</dict>
+</plist>
+"#,
+    paths::APP_IDENTIFIER,
+    args
+  )
+}
+
+pub struct LoginItemService;
+
+impl LoginItemService {
+  /// Whether this launch came from the LaunchAgent in background mode
+  pub fn launched_in_background() -> bool {
+    std::env::args().any(|arg| arg == BACKGROUND_ARG)
+  }
+
+  pub fn keeps_running_when_closed() -> bool {
+    BACKGROUND_AGENT.load(Ordering::Relaxed)
+  }
+
+  /// Install or remove the LaunchAgent to match the settings
+  pub async fn apply(launch_at_login: bool, background_agent: bool) -> Result<()> {
+    BACKGROUND_AGENT.store(background_agent, Ordering::Relaxed);
+
+    if !cfg!(target_os = "macos") {
+      return if launch_at_login {
+        Err(SmoothieError::ValidationError(
+          "Launch at login is only supported on macOS".into(),
+        ))
+      } else {
+        Ok(())
+      };
+    }
+
+    let path = launch_agent_path()
+      .ok_or_else(|| SmoothieError::SystemError("Home directory not found".into()))?;
+    if !launch_at_login {
+      return match tokio::fs::remove_file(&path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+      };
+    }
+
+    let exe = std::env::current_exe()?;
+    if let Some(dir) = path.parent() {
+      tokio::fs::create_dir_all(dir).await?;
+    }
+    paths::write_atomically(
+      &path,
+      render_launch_agent(&exe, background_agent).as_bytes(),
+    )
+    .await
+  }
+
+  /// Bring the LaunchAgent and window behaviour in line with saved settings
+  /// at startup
+  pub async fn apply_on_start(settings: &UserSettingsDto) {
+    if let Err(e) = Self::apply(settings.launch_at_login, settings.background_agent).await {
+      tracing::warn!("Failed to update the login item: {}", e);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_launch_agent_escapes_the_path_and_passes_background_flag() {
+    let exe = Path::new("/Applications/R&D <beta>/Smoothie.app/Contents/MacOS/smoothie");
+
+    let plist = render_launch_agent(exe, true);
+    assert!(plist.contains("<string>com.smoothie.desktop</string>"));
+    assert!(plist.contains(
+      "    <string>/Applications/R&amp;D &lt;beta&gt;/Smoothie.app/Contents/MacOS/smoothie</string>\n    <string>--background</string>\n  </array>"
+    ));
+
+    assert!(!render_launch_agent(exe, false).contains(BACKGROUND_ARG));
+  }
+}
@@ -1,21 +1,102 @@
 // Business logic services
 
+pub mod activation;
+pub mod activation_journal;
+pub mod activation_report;
+pub mod alert_service;
+pub mod api_version;
+pub mod app_blocklist_service;
+pub mod app_presence;
 pub mod app_service;
+pub mod audit_backend;
+pub mod audit_queue;
 pub mod audit_service;
 pub mod automation_service;
+pub mod bookmark_import_service;
 pub mod browser_service;
+pub mod change_log;
+pub mod checklist_service;
+pub mod connection_monitor;
+pub mod deactivation_service;
+pub mod declarative_service;
+pub mod demo_service;
+pub mod device_service;
+pub mod diagnostic_upload_service;
+pub mod edit_history;
+pub mod environment_service;
+pub mod file_service;
+pub mod fix_service;
+pub mod focus_session_service;
+pub mod focus_tracker;
+pub mod folder_sync_service;
+pub mod health_service;
+pub mod layout_service;
+pub mod log_export_service;
+pub mod login_item_service;
 pub mod monitor_service;
+pub mod monitor_summary;
+pub mod offline_cache;
+pub mod profile_lock;
+pub mod profile_resolver;
 pub mod profile_service;
+pub mod reconciliation_service;
+pub mod resource_guard;
+pub mod rule_action_service;
+pub mod script_export_service;
+pub mod share_service;
+pub mod snapshot_service;
+pub mod storage_service;
+pub mod sync_service;
+pub mod system_backend;
 pub mod system_service;
+pub mod team_service;
+pub mod telemetry_service;
+pub mod update_service;
+pub mod usage_report_service;
 pub mod user_settings_service;
+pub mod warmup_service;
 pub mod window_service;
 
+pub use alert_service::AlertService;
+pub use app_blocklist_service::AppBlocklistService;
 pub use app_service::AppService;
 #[allow(unused_imports)]
 pub use audit_service::{AuditService, AUDIT_SERVICE};
 pub use automation_service::AutomationService;
+pub use bookmark_import_service::BookmarkImportService;
 pub use browser_service::BrowserService;
+pub use checklist_service::ChecklistService;
+pub use connection_monitor::{ConnectionMonitor, ConnectionStatus};
+pub use deactivation_service::DeactivationService;
+pub use declarative_service::DeclarativeService;
+pub use demo_service::DemoService;
+pub use device_service::{BluetoothDevice, UsbDevice};
+pub use diagnostic_upload_service::DiagnosticUploadService;
+pub use environment_service::EnvironmentService;
+pub use file_service::FileService;
+pub use focus_session_service::FocusSessionService;
+pub use focus_tracker::FocusTracker;
+pub use folder_sync_service::FolderSyncService;
+pub use health_service::{HealthReport, HealthService};
+pub use layout_service::LayoutService;
+pub use log_export_service::LogExportService;
+pub use login_item_service::LoginItemService;
 pub use monitor_service::MonitorService;
+pub use offline_cache::OfflineCache;
+pub use profile_resolver::ProfileResolver;
 pub use profile_service::ProfileService;
-pub use system_service::{InstalledApp, RunningApp, SystemMonitor, SystemService, SystemWindow};
+pub use reconciliation_service::ReconciliationService;
+pub use resource_guard::ResourceGuard;
+pub use rule_action_service::RuleActionService;
+pub use script_export_service::ScriptExportService;
+pub use snapshot_service::SnapshotService;
+pub use storage_service::StorageService;
+pub use sync_service::{SyncPayload, SyncService};
+pub use system_backend::SystemBackend;
+pub use system_service::{InstalledApp, RunningApp, SystemMonitor, SystemWindow};
+pub use team_service::TeamService;
+pub use telemetry_service::{TelemetryPayload, TelemetryService};
+pub use update_service::UpdateService;
+pub use usage_report_service::UsageReportService;
 pub use user_settings_service::UserSettingsService;
+pub use warmup_service::WarmupService;
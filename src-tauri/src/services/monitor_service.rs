@@ -3,49 +3,134 @@
 use crate::{
   db::Database,
   error::{Result, SmoothieError},
-  models::dto::MonitorDto,
-  repositories::MonitorRepository,
-  services::SystemMonitor,
+  models::dto::{CreateMonitorRequest, MonitorDto},
+  models::entities::MonitorEntity,
+  repositories::{MonitorRepository, ProfilePart, ProfileRepository},
+  services::change_log::{ChangeLog, ChangeSubject},
+  services::profile_lock::ProfileLock,
+  services::system_service::DisplayKind,
+  services::{SystemBackend, SystemMonitor},
+  utils::validation,
 };
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
+/// How long displays get to settle before the arrangement is read back
+const LAYOUT_SETTLE_DELAY: Duration = Duration::from_millis(500);
+
+/// Result of applying monitor layout
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorLayoutResult {
+  /// Every monitor was found where it was requested
+  pub applied: bool,
+  pub monitor_count: usize,
+  pub message: String,
+  /// Apply attempts made, including the retry after a mismatch
+  pub attempts: u32,
+  pub monitors: Vec<MonitorVerification>,
+}
+
+impl MonitorLayoutResult {
+  /// Nothing was applied, e.g. no layout saved or loading it failed
+  pub fn skipped(message: impl Into<String>) -> Self {
+    Self {
+      applied: false,
+      monitor_count: 0,
+      message: message.into(),
+      attempts: 0,
+      monitors: Vec::new(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MonitorApplyStatus {
+  Applied,
+  /// Connected, but not at the requested position or resolution
+  Mismatch,
+  /// Not connected at all
+  Missing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorFrame {
+  pub x: i32,
+  pub y: i32,
+  pub width: i32,
+  pub height: i32,
+}
+
+/// How one requested monitor compares to what the system reports
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorVerification {
+  pub display_id: u32,
+  pub name: String,
+  pub status: MonitorApplyStatus,
+  pub expected: MonitorFrame,
+  pub actual: Option<MonitorFrame>,
+}
+
 /// Helper to parse UUID from string
 fn parse_uuid(s: &str) -> Result<Uuid> {
   Uuid::parse_str(s).map_err(|_| SmoothieError::ValidationError(format!("Invalid UUID: {}", s)))
 }
 
+/// Fingerprints to save captured displays under. Identical displays share a
+/// fingerprint, so the second and later ones in display order get `#2`, `#3`...
+fn capture_keys(monitors: &[SystemMonitor]) -> Vec<String> {
+  let fingerprints: Vec<String> = monitors.iter().map(SystemMonitor::fingerprint).collect();
+  fingerprints
+    .iter()
+    .enumerate()
+    .map(|(i, fingerprint)| {
+      let earlier = fingerprints
+        .iter()
+        .enumerate()
+        .filter(|(j, other)| {
+          *other == fingerprint && (monitors[*j].display_id, *j) < (monitors[i].display_id, i)
+        })
+        .count();
+      match earlier {
+        0 => fingerprint.clone(),
+        n => format!("{}#{}", fingerprint, n + 1),
+      }
+    })
+    .collect()
+}
+
 pub struct MonitorService;
 
 impl MonitorService {
-  #[allow(clippy::too_many_arguments)]
   pub async fn create_monitor(
     db: &Database,
     profile_id: &str,
-    name: String,
-    resolution: String,
-    orientation: String,
-    is_primary: bool,
-    x: i32,
-    y: i32,
-    width: i32,
-    height: i32,
-    display_index: i32,
+    req: CreateMonitorRequest,
   ) -> Result<MonitorDto> {
+    validation::validate(&req)?;
     let profile_uuid = parse_uuid(profile_id)?;
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
     let repo = MonitorRepository::new(db.pool());
 
     let entity = repo
       .create(
         profile_uuid,
-        &name,
-        &resolution,
-        &orientation,
-        is_primary,
-        x,
-        y,
-        width,
-        height,
-        display_index,
+        &req.name,
+        &req.resolution,
+        &req.orientation,
+        req.is_primary,
+        req.x,
+        req.y,
+        req.width,
+        req.height,
+        req.display_index,
       )
       .await?;
 
@@ -63,27 +148,75 @@ impl MonitorService {
   /// Get monitors as SystemMonitor format for applying layout
   pub async fn get_system_monitors(db: &Database, profile_id: &str) -> Result<Vec<SystemMonitor>> {
     let monitors = Self::get_monitors(db, profile_id).await?;
-    Ok(
-      monitors
-        .into_iter()
-        .map(|m| SystemMonitor {
-          display_id: m.display_index as u32,
-          name: m.name,
-          brand: m.brand,
-          model: m.model,
-          resolution: m.resolution,
-          width: m.width,
-          height: m.height,
-          x: m.x,
-          y: m.y,
-          scale_factor: m.scale_factor.unwrap_or(1.0),
-          refresh_rate: m.refresh_rate.map(|r| r as f64).unwrap_or(60.0),
-          is_primary: m.is_primary,
-          is_builtin: m.is_builtin.unwrap_or(false),
-          orientation: m.orientation,
-        })
-        .collect(),
-    )
+    Ok(monitors.into_iter().map(Self::to_system_monitor).collect())
+  }
+
+  /// A saved monitor in the form the layout is applied in
+  pub(crate) fn to_system_monitor(m: MonitorDto) -> SystemMonitor {
+    SystemMonitor {
+      display_id: m.display_index as u32,
+      name: m.name,
+      brand: m.brand,
+      model: m.model,
+      resolution: m.resolution,
+      width: m.width,
+      height: m.height,
+      x: m.x,
+      y: m.y,
+      scale_factor: m.scale_factor.unwrap_or(1.0),
+      refresh_rate: m.refresh_rate.map(|r| r as f64).unwrap_or(60.0),
+      is_primary: m.is_primary,
+      is_builtin: m.is_builtin.unwrap_or(false),
+      orientation: m.orientation,
+      // Not saved; what a display is only matters while it's connected
+      kind: DisplayKind::Physical,
+    }
+  }
+
+  /// The profile's saved layout to apply, without displays that are Sidecar,
+  /// AirPlay or virtual right now unless the profile includes those
+  pub async fn get_layout_to_apply(
+    db: &Database,
+    system: &dyn SystemBackend,
+    profile_id: &str,
+  ) -> Result<Vec<SystemMonitor>> {
+    let profile = ProfileRepository::new(db.pool())
+      .find_by_id(parse_uuid(profile_id)?)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?;
+    let monitors = Self::get_system_monitors(db, profile_id).await?;
+    if profile.include_virtual_displays {
+      return Ok(monitors);
+    }
+    Ok(Self::without_virtual_displays(system, monitors, profile_id))
+  }
+
+  /// `monitors` without the ones connected as Sidecar, AirPlay or virtual displays
+  pub(crate) fn without_virtual_displays(
+    system: &dyn SystemBackend,
+    monitors: Vec<SystemMonitor>,
+    profile_id: &str,
+  ) -> Vec<SystemMonitor> {
+    if monitors.is_empty() {
+      return monitors;
+    }
+    let virtual_ids: HashSet<u32> = system
+      .get_monitors()
+      .iter()
+      .filter(|m| m.is_virtual())
+      .map(|m| m.display_id)
+      .collect();
+    let (skipped, monitors): (Vec<_>, Vec<_>) = monitors
+      .into_iter()
+      .partition(|m| virtual_ids.contains(&m.display_id));
+    if !skipped.is_empty() {
+      tracing::info!(
+        profile_id,
+        skipped = skipped.len(),
+        "Leaving Sidecar, AirPlay and virtual displays out of the layout"
+      );
+    }
+    monitors
   }
 
   pub async fn update_monitor(
@@ -95,23 +228,401 @@ impl MonitorService {
     height: i32,
   ) -> Result<MonitorDto> {
     let monitor_uuid = parse_uuid(monitor_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::Monitor, monitor_uuid).await?;
     let repo = MonitorRepository::new(db.pool());
+    let before = repo
+      .find_by_id(monitor_uuid)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Monitor not found".into()))?;
 
     let entity = repo
       .update_position(monitor_uuid, x, y, width, height)
       .await?;
-    Ok(MonitorDto::from(entity))
+    let subject = ChangeSubject {
+      entity_type: "monitor",
+      entity_id: monitor_uuid,
+      profile_id: entity.profile_id,
+    };
+    let updated = MonitorDto::from(entity);
+    ChangeLog::record_update(db, subject, &MonitorDto::from(before), &updated).await;
+    Ok(updated)
+  }
+
+  /// Choose whether a display is kept dark while its profile is active
+  pub async fn set_monitor_blanked(
+    db: &Database,
+    monitor_id: &str,
+    blanked: bool,
+  ) -> Result<MonitorDto> {
+    let monitor_uuid = parse_uuid(monitor_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::Monitor, monitor_uuid).await?;
+    let repo = MonitorRepository::new(db.pool());
+    let before = repo
+      .find_by_id(monitor_uuid)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Monitor not found".into()))?;
+
+    let entity = repo.set_blanked(monitor_uuid, blanked).await?;
+    let subject = ChangeSubject {
+      entity_type: "monitor",
+      entity_id: monitor_uuid,
+      profile_id: entity.profile_id,
+    };
+    let updated = MonitorDto::from(entity);
+    ChangeLog::record_update(db, subject, &MonitorDto::from(before), &updated).await;
+    Ok(updated)
+  }
+
+  /// Connected displays the profile wants blanked. Saved monitors are matched
+  /// by the fingerprint they were captured under, so ones added by hand never
+  /// blank anything.
+  pub async fn displays_to_blank(
+    db: &Database,
+    profile_id: &str,
+    live: &[SystemMonitor],
+  ) -> Result<Vec<u32>> {
+    let profile_uuid = parse_uuid(profile_id)?;
+    let blanked: Vec<String> = MonitorRepository::new(db.pool())
+      .find_by_profile_id(profile_uuid)
+      .await?
+      .into_iter()
+      .filter(|m| m.blanked)
+      .filter_map(|m| m.fingerprint)
+      .collect();
+
+    Ok(
+      live
+        .iter()
+        .zip(capture_keys(live))
+        .filter(|(_, key)| blanked.contains(key))
+        .map(|(monitor, _)| monitor.display_id)
+        .collect(),
+    )
+  }
+
+  /// Save the displays connected right now to a profile. Displays the profile
+  /// already has, recognized by fingerprint, are updated in place.
+  pub async fn capture_layout(
+    db: &Database,
+    system: Arc<dyn SystemBackend>,
+    profile_id: &str,
+  ) -> Result<Vec<MonitorDto>> {
+    let profile_uuid = parse_uuid(profile_id)?;
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
+    let live = tokio::task::spawn_blocking(move || system.get_monitors())
+      .await
+      .map_err(|e| SmoothieError::SystemError(format!("Monitor detection failed: {}", e)))?;
+    if live.is_empty() {
+      return Err(SmoothieError::SystemError("No displays detected".into()));
+    }
+
+    let repo = MonitorRepository::new(db.pool());
+    let now = Utc::now();
+    let keys = capture_keys(&live);
+    let mut saved = Vec::with_capacity(live.len());
+    for (monitor, fingerprint) in live.into_iter().zip(keys) {
+      let entity = repo
+        .upsert_captured(&MonitorEntity {
+          id: Uuid::new_v4(),
+          profile_id: profile_uuid,
+          name: monitor.name,
+          resolution: monitor.resolution,
+          orientation: monitor.orientation,
+          is_primary: monitor.is_primary,
+          x: monitor.x,
+          y: monitor.y,
+          width: monitor.width,
+          height: monitor.height,
+          display_index: monitor.display_id as i32,
+          brand: monitor.brand,
+          model: monitor.model,
+          refresh_rate: Some(monitor.refresh_rate.round() as i32),
+          scale_factor: Some(monitor.scale_factor),
+          is_builtin: Some(monitor.is_builtin),
+          color_depth: None,
+          created_at: Some(now),
+          updated_at: Some(now),
+          fingerprint: Some(fingerprint),
+          captured_at: Some(now),
+          blanked: false,
+        })
+        .await?;
+      saved.push(MonitorDto::from(entity));
+    }
+
+    tracing::info!(
+      profile_id,
+      monitors = saved.len(),
+      "Monitor layout captured"
+    );
+
+    Ok(saved)
+  }
+
+  /// Remove captured monitors the profile's latest capture no longer saw
+  pub async fn prune_stale_monitors(db: &Database, profile_id: &str) -> Result<Vec<MonitorDto>> {
+    let profile_uuid = parse_uuid(profile_id)?;
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
+    let removed = MonitorRepository::new(db.pool())
+      .prune_stale(profile_uuid)
+      .await?;
+
+    let removed: Vec<MonitorDto> = removed.into_iter().map(MonitorDto::from).collect();
+    for monitor in &removed {
+      let subject = ChangeSubject {
+        entity_type: "monitor",
+        entity_id: parse_uuid(&monitor.id)?,
+        profile_id: profile_uuid,
+      };
+      ChangeLog::record_delete(db, subject, monitor).await;
+    }
+    if !removed.is_empty() {
+      tracing::info!(profile_id, removed = removed.len(), "Stale monitors pruned");
+    }
+    Ok(removed)
   }
 
   pub async fn delete_monitor(db: &Database, monitor_id: &str) -> Result<()> {
     let monitor_uuid = parse_uuid(monitor_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::Monitor, monitor_uuid).await?;
     let repo = MonitorRepository::new(db.pool());
+    let existing = repo.find_by_id(monitor_uuid).await?;
 
     let deleted = repo.delete(monitor_uuid).await?;
     if !deleted {
       return Err(SmoothieError::NotFound("Monitor not found".into()));
     }
 
+    if let Some(monitor) = existing {
+      let subject = ChangeSubject {
+        entity_type: "monitor",
+        entity_id: monitor_uuid,
+        profile_id: monitor.profile_id,
+      };
+      ChangeLog::record_delete(db, subject, &MonitorDto::from(monitor)).await;
+    }
     Ok(())
   }
+
+  /// Apply a layout and read the arrangement back to check it took effect.
+  ///
+  /// The external tools report success even when macOS ignores part of the
+  /// arrangement, so a mismatch is retried once before it is reported.
+  pub async fn apply_layout(
+    system: &dyn SystemBackend,
+    monitors: Vec<SystemMonitor>,
+  ) -> MonitorLayoutResult {
+    let monitor_count = monitors.len();
+    let mut attempts = 0;
+    let mut verified = Vec::new();
+
+    while attempts < 2 {
+      attempts += 1;
+      if let Err(e) = Self::apply_once(system, &monitors).await {
+        tracing::warn!("Monitor layout application failed: {}", e);
+        return MonitorLayoutResult {
+          applied: false,
+          monitor_count,
+          message: format!("Failed to apply monitor layout: {}", e),
+          attempts,
+          monitors: verified,
+        };
+      }
+
+      tokio::time::sleep(LAYOUT_SETTLE_DELAY).await;
+      verified = verify_layout(&monitors, &system.get_monitors());
+      if verified
+        .iter()
+        .all(|m| m.status == MonitorApplyStatus::Applied)
+      {
+        return MonitorLayoutResult {
+          applied: true,
+          monitor_count,
+          message: "Monitor layout applied and verified".to_string(),
+          attempts,
+          monitors: verified,
+        };
+      }
+      tracing::warn!(
+        attempt = attempts,
+        "Monitor layout did not match after applying"
+      );
+    }
+
+    let off = verified
+      .iter()
+      .filter(|m| m.status != MonitorApplyStatus::Applied)
+      .count();
+    MonitorLayoutResult {
+      applied: false,
+      monitor_count,
+      message: format!(
+        "{} of {} monitors did not end up where requested",
+        off, monitor_count
+      ),
+      attempts,
+      monitors: verified,
+    }
+  }
+
+  /// AppleScript first, then direct execution
+  async fn apply_once(system: &dyn SystemBackend, monitors: &[SystemMonitor]) -> Result<()> {
+    match system.apply_monitor_layout_applescript(monitors).await {
+      Ok(()) => Ok(()),
+      Err(e) => {
+        tracing::warn!("AppleScript method failed: {:?}, trying direct method", e);
+        system.apply_monitor_layout(monitors.to_vec())
+      }
+    }
+  }
+}
+
+/// Compare requested monitors against the live ones.
+///
+/// Saved layouts and the live system can disagree on where the origin is, so
+/// positions are compared relative to each side's primary display.
+fn verify_layout(
+  requested: &[SystemMonitor],
+  actual: &[SystemMonitor],
+) -> Vec<MonitorVerification> {
+  let origin = |monitors: &[SystemMonitor]| {
+    monitors
+      .iter()
+      .find(|m| m.is_primary)
+      .or(monitors.first())
+      .map(|m| (m.x, m.y))
+      .unwrap_or((0, 0))
+  };
+  let (rx, ry) = origin(requested);
+  let (ax, ay) = origin(actual);
+
+  requested
+    .iter()
+    .map(|want| {
+      let expected = MonitorFrame {
+        x: want.x - rx,
+        y: want.y - ry,
+        width: want.width,
+        height: want.height,
+      };
+      let found = actual
+        .iter()
+        .find(|m| m.display_id == want.display_id)
+        .or_else(|| {
+          let fingerprint = want.fingerprint();
+          actual
+            .iter()
+            .find(|m| !m.is_virtual() && m.fingerprint() == fingerprint)
+        });
+      let actual = found.map(|m| MonitorFrame {
+        x: m.x - ax,
+        y: m.y - ay,
+        width: m.width,
+        height: m.height,
+      });
+      let status = match actual {
+        None => MonitorApplyStatus::Missing,
+        Some(frame) if frame == expected => MonitorApplyStatus::Applied,
+        Some(_) => MonitorApplyStatus::Mismatch,
+      };
+      MonitorVerification {
+        display_id: want.display_id,
+        name: want.name.clone(),
+        status,
+        expected,
+        actual,
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use crate::services::system_backend::MockSystemBackend;
+
+  #[tokio::test]
+  async fn test_apply_layout_verifies_each_monitor() {
+    let system = MockSystemBackend::with_sample_layout();
+    let mut layout = system.get_monitors();
+    layout[1].x = -2560;
+    layout[1].y = 0;
+
+    let result = MonitorService::apply_layout(&system, layout.clone()).await;
+    assert!(result.applied, "{}", result.message);
+    assert_eq!(result.attempts, 1);
+    assert!(result
+      .monitors
+      .iter()
+      .all(|m| m.status == MonitorApplyStatus::Applied));
+
+    // A display that isn't connected can't be placed, even after the retry
+    layout.push(SystemMonitor {
+      display_id: 9,
+      name: "Projector".into(),
+      brand: None,
+      model: None,
+      width: 1920,
+      height: 1080,
+      ..layout[1].clone()
+    });
+    let result = MonitorService::apply_layout(&system, layout).await;
+    assert!(!result.applied);
+    assert_eq!(result.attempts, 2);
+    assert_eq!(result.monitors[2].status, MonitorApplyStatus::Missing);
+    assert_eq!(system.applied_layouts().len(), 3);
+  }
+
+  #[tokio::test]
+  async fn test_recapture_updates_monitors_in_place() {
+    let test_db = test_db_or_skip!();
+    let db = &test_db.db;
+    let user_id = test_db.seed_user().await;
+    let profile_id = test_db.seed_profile(user_id, "Desk").await.to_string();
+    let system = Arc::new(MockSystemBackend::with_sample_layout());
+
+    let first = MonitorService::capture_layout(db, system.clone(), &profile_id)
+      .await
+      .unwrap();
+    let mut layout = system.get_monitors();
+    layout[1].x = -2560;
+    // A second display of the same model
+    layout.push(SystemMonitor {
+      display_id: 3,
+      x: 4072,
+      ..layout[1].clone()
+    });
+    system.set_monitors(layout);
+    let second = MonitorService::capture_layout(db, system.clone(), &profile_id)
+      .await
+      .unwrap();
+
+    assert_eq!(second.len(), 3);
+    assert_eq!(second[1].id, first[1].id);
+    assert_eq!(second[1].x, -2560);
+    assert_ne!(second[2].id, first[1].id);
+    assert_eq!(
+      MonitorService::get_monitors(db, &profile_id)
+        .await
+        .unwrap()
+        .len(),
+      3
+    );
+
+    // Unplugging the second Dell leaves it stale after the next capture
+    let mut layout = system.get_monitors();
+    layout.pop();
+    system.set_monitors(layout);
+    MonitorService::capture_layout(db, system, &profile_id)
+      .await
+      .unwrap();
+    let removed = MonitorService::prune_stale_monitors(db, &profile_id)
+      .await
+      .unwrap();
+    assert_eq!(
+      removed.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(),
+      [second[2].id.as_str()]
+    );
+  }
 }
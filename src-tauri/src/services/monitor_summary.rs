@@ -0,0 +1,134 @@
+//! Plain-language summaries of monitor changes
+//!
+//! A recorded change keeps the full before and after display lists, which
+//! the history view and notifications can't show as they are. The summary
+//! names what a person would notice - "LG UltraFine disconnected; built-in
+//! display is now primary" - and is stored with the change.
+
+use crate::services::SystemMonitor;
+use serde_json::Value;
+
+/// What a display is called in a summary
+fn label(monitor: &SystemMonitor) -> String {
+  if monitor.is_builtin {
+    return "built-in display".to_string();
+  }
+  match (&monitor.brand, &monitor.model) {
+    (Some(brand), Some(model)) if !model.starts_with(brand.as_str()) => {
+      format!("{} {}", brand, model)
+    }
+    (_, Some(model)) => model.clone(),
+    _ => monitor.name.clone(),
+  }
+}
+
+/// The same physical display on both sides: by ID while it stays connected,
+/// else by what it is, as IDs can change on reconnect
+fn find_same<'a>(monitor: &SystemMonitor, among: &'a [SystemMonitor]) -> Option<&'a SystemMonitor> {
+  among
+    .iter()
+    .find(|m| m.display_id == monitor.display_id && label(m) == label(monitor))
+    .or_else(|| among.iter().find(|m| label(m) == label(monitor)))
+}
+
+fn capitalize(sentence: &str) -> String {
+  let mut chars = sentence.chars();
+  match chars.next() {
+    Some(first) => first.to_uppercase().chain(chars).collect(),
+    None => String::new(),
+  }
+}
+
+/// Describe how the displays went from `before` to `after`
+pub fn summarize(before: &[SystemMonitor], after: &[SystemMonitor]) -> String {
+  let mut parts = Vec::new();
+
+  for monitor in after {
+    if find_same(monitor, before).is_none() {
+      parts.push(format!("{} connected", label(monitor)));
+    }
+  }
+  for monitor in before {
+    if find_same(monitor, after).is_none() {
+      parts.push(format!("{} disconnected", label(monitor)));
+    }
+  }
+
+  let mut moved = false;
+  for monitor in after {
+    let Some(old) = find_same(monitor, before) else {
+      continue;
+    };
+    if (old.width, old.height) != (monitor.width, monitor.height) {
+      parts.push(format!(
+        "{} is now {}x{}",
+        label(monitor),
+        monitor.width,
+        monitor.height
+      ));
+    }
+    moved |= (old.x, old.y) != (monitor.x, monitor.y);
+  }
+
+  let primary = |monitors: &[SystemMonitor]| monitors.iter().find(|m| m.is_primary).map(label);
+  if let Some(new_primary) = primary(after).filter(|p| Some(p) != primary(before).as_ref()) {
+    parts.push(format!("{} is now primary", new_primary));
+  }
+
+  if parts.is_empty() {
+    parts.push(if moved {
+      "displays rearranged".to_string()
+    } else {
+      "no visible change to the displays".to_string()
+    });
+  }
+  capitalize(&parts.join("; "))
+}
+
+/// `summarize` for changes recorded as JSON; `None` when either side isn't a
+/// display list
+pub fn summarize_json(before: Option<&Value>, after: Option<&Value>) -> Option<String> {
+  let parse = |value: Option<&Value>| -> Option<Vec<SystemMonitor>> {
+    serde_json::from_value(value?.clone()).ok()
+  };
+  Some(summarize(&parse(before)?, &parse(after)?))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::services::system_backend::{MockSystemBackend, SystemBackend};
+
+  #[test]
+  fn test_summary_names_what_changed() {
+    let docked = MockSystemBackend::with_sample_layout().get_monitors();
+    let mut laptop = vec![docked[0].clone()];
+    assert_eq!(summarize(&docked, &laptop), "DELL U2720Q disconnected");
+
+    let mut external_primary = docked.clone();
+    external_primary[0].is_primary = false;
+    external_primary[1].is_primary = true;
+    assert_eq!(
+      summarize(&laptop, &external_primary),
+      "DELL U2720Q connected; DELL U2720Q is now primary"
+    );
+
+    laptop[0].width = 1728;
+    laptop[0].height = 1117;
+    assert_eq!(
+      summarize(&docked[..1], &laptop),
+      "Built-in display is now 1728x1117"
+    );
+
+    let mut moved = docked.clone();
+    moved[1].x += 100;
+    assert_eq!(summarize(&docked, &moved), "Displays rearranged");
+
+    let json = serde_json::to_value(&docked).unwrap();
+    assert_eq!(
+      summarize_json(Some(&json), Some(&json)).as_deref(),
+      Some("No visible change to the displays")
+    );
+    assert_eq!(summarize_json(Some(&json), None), None);
+  }
+}
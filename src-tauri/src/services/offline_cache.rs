@@ -0,0 +1,406 @@
+//! Local snapshot of profiles and settings for database outages
+//!
+//! Every read of the profile list, a profile or the settings that reaches
+//! Postgres is copied into a snapshot in the app data directory. When
+//! Postgres can't be reached, those reads are answered from the snapshot,
+//! and a profile in it can still be started: its layout applied and its
+//! apps, tabs and files opened. Starting from the snapshot records nothing -
+//! no activation history, no journal - and edits keep failing until the
+//! database is back. Only a failure to reach Postgres counts as an outage;
+//! any other database error is returned as it is.
+//!
+//! Changes land in memory at once and reach the file a moment later, so a
+//! burst of reads rewrites it once. Deleted profiles are dropped from it.
+
+use crate::{
+  error::{Result, SmoothieError},
+  models::{ProfileDto, ProfileId, ProfileResponse, UserId, UserSettingsDto},
+  services::{
+    activation::{ActivationStepKind, StepReport, StepStatus},
+    app_service::LaunchResult,
+    browser_service::OpenTabResult,
+    file_service::OpenFileResult,
+    monitor_service::MonitorLayoutResult,
+    AppService, BrowserService, FileService, MonitorService, SystemBackend,
+  },
+  utils::paths,
+};
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Version of the snapshot file; a snapshot in another format is started over
+const CACHE_FORMAT: u32 = 1;
+
+/// How long a change waits for others before the file is rewritten
+const WRITE_DELAY: Duration = Duration::from_secs(2);
+
+static STORE: OnceLock<OfflineStore> = OnceLock::new();
+
+fn store() -> &'static OfflineStore {
+  STORE.get_or_init(|| OfflineStore::open(paths::app_data_dir().join("offline-cache.json")))
+}
+
+/// Whether `error` means the database couldn't be reached, rather than that
+/// the request or the query was wrong
+pub fn is_outage(error: &SmoothieError) -> bool {
+  matches!(error, SmoothieError::DatabaseUnavailable(_))
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Snapshot {
+  format: u32,
+  /// Profile lists by user id
+  profiles: HashMap<String, Vec<ProfileDto>>,
+  /// Profiles with their monitors, apps, tabs and files by profile id
+  details: HashMap<String, ProfileResponse>,
+  /// Settings by user id
+  settings: HashMap<String, UserSettingsDto>,
+  saved_at: Option<DateTime<Utc>>,
+}
+
+struct OfflineStore {
+  path: PathBuf,
+  snapshot: RwLock<Snapshot>,
+  /// Set while a write is scheduled; changes made meanwhile go out with it
+  write_pending: AtomicBool,
+  /// Keeps writes of the file in order
+  write: tokio::sync::Mutex<()>,
+}
+
+impl OfflineStore {
+  fn open(path: PathBuf) -> Self {
+    let snapshot = std::fs::read(&path)
+      .ok()
+      .and_then(|data| serde_json::from_slice::<Snapshot>(&data).ok())
+      .filter(|snapshot| snapshot.format == CACHE_FORMAT)
+      .unwrap_or_default();
+    Self {
+      path,
+      snapshot: RwLock::new(snapshot),
+      write_pending: AtomicBool::new(false),
+      write: tokio::sync::Mutex::new(()),
+    }
+  }
+
+  /// Apply `change` now and schedule a write of the file, unless one is
+  /// already waiting
+  fn update(&'static self, change: impl FnOnce(&mut Snapshot)) {
+    {
+      let mut snapshot = self.snapshot.write();
+      change(&mut snapshot);
+      snapshot.format = CACHE_FORMAT;
+      snapshot.saved_at = Some(Utc::now());
+    }
+    if !self.write_pending.swap(true, Ordering::AcqRel) {
+      tokio::spawn(async move {
+        tokio::time::sleep(WRITE_DELAY).await;
+        self.flush().await;
+      });
+    }
+  }
+
+  async fn flush(&self) {
+    let _writing = self.write.lock().await;
+    self.write_pending.store(false, Ordering::Release);
+    let data = serde_json::to_vec(&*self.snapshot.read());
+    let result = match data {
+      Ok(data) => self.write_file(&data).await,
+      Err(e) => Err(e.into()),
+    };
+    if let Err(e) = result {
+      tracing::warn!(path = %self.path.display(), "Could not save the offline cache: {}", e);
+    }
+  }
+
+  async fn write_file(&self, data: &[u8]) -> Result<()> {
+    if let Some(dir) = self.path.parent() {
+      tokio::fs::create_dir_all(dir).await?;
+    }
+    paths::write_atomically(&self.path, data).await
+  }
+}
+
+/// What starting a profile from the snapshot did
+#[derive(Debug, Clone)]
+pub struct OfflineStart {
+  pub apps_launched: Vec<LaunchResult>,
+  pub tabs_opened: Vec<OpenTabResult>,
+  pub files_opened: Vec<OpenFileResult>,
+  pub monitor_layout: MonitorLayoutResult,
+  pub steps: Vec<StepReport>,
+}
+
+pub struct OfflineCache;
+
+impl OfflineCache {
+  /// Keep the user's profile list, dropping the saved details of profiles no
+  /// longer in it - deleted elsewhere, or with the user
+  pub fn save_profiles(user_id: UserId, profiles: &[ProfileDto]) {
+    let profiles = profiles.to_vec();
+    store().update(|s| {
+      let previous = s.profiles.insert(user_id.to_string(), profiles);
+      let current = &s.profiles[&user_id.to_string()];
+      for gone in previous
+        .into_iter()
+        .flatten()
+        .filter(|old| !current.iter().any(|p| p.id == old.id))
+      {
+        s.details.remove(&gone.id.to_string());
+      }
+    });
+  }
+
+  pub fn save_profile(profile: &ProfileResponse) {
+    let profile = profile.clone();
+    store().update(|s| {
+      s.details.insert(profile.id.to_string(), profile);
+    });
+  }
+
+  pub fn save_settings(settings: &UserSettingsDto) {
+    let settings = settings.clone();
+    store().update(|s| {
+      s.settings.insert(settings.user_id.clone(), settings);
+    });
+  }
+
+  /// Drop a deleted profile, so it can't be listed or started offline
+  pub fn forget_profile(profile_id: ProfileId) {
+    store().update(|s| {
+      s.details.remove(&profile_id.to_string());
+      for profiles in s.profiles.values_mut() {
+        profiles.retain(|p| p.id != profile_id);
+      }
+    });
+  }
+
+  pub fn profiles(user_id: UserId) -> Option<Vec<ProfileDto>> {
+    store()
+      .snapshot
+      .read()
+      .profiles
+      .get(&user_id.to_string())
+      .cloned()
+  }
+
+  pub fn profile(profile_id: ProfileId) -> Option<ProfileResponse> {
+    store()
+      .snapshot
+      .read()
+      .details
+      .get(&profile_id.to_string())
+      .cloned()
+  }
+
+  pub fn settings(user_id: &str) -> Option<UserSettingsDto> {
+    store().snapshot.read().settings.get(user_id).cloned()
+  }
+
+  /// `result`, or what `cached` finds in the snapshot when the database
+  /// couldn't be reached. `what` names the data in the log.
+  pub fn or_cached<T>(
+    result: Result<T>,
+    what: &str,
+    cached: impl FnOnce() -> Option<T>,
+  ) -> Result<T> {
+    match result {
+      Err(e) if is_outage(&e) => match cached() {
+        Some(value) => {
+          tracing::warn!(
+            what,
+            "Database unavailable, serving from the offline cache: {}",
+            e
+          );
+          Ok(value)
+        }
+        None => Err(e),
+      },
+      result => result,
+    }
+  }
+
+  /// Start a profile from the snapshot: apply its layout, then open its
+  /// apps, tabs and files. `steps`, when given, limits the run to those.
+  pub async fn start_profile(
+    system: Arc<dyn SystemBackend>,
+    profile_id: ProfileId,
+    steps: Option<&[ActivationStepKind]>,
+  ) -> Option<OfflineStart> {
+    let profile = Self::profile(profile_id)?;
+    let include_virtual_displays = {
+      let snapshot = store().snapshot.read();
+      snapshot
+        .profiles
+        .values()
+        .flatten()
+        .find(|p| p.id == profile_id)
+        .is_some_and(|p| p.include_virtual_displays)
+    };
+    tracing::warn!(profile_id = %profile_id, "Starting profile from the offline cache");
+
+    let wanted = |kind| steps.map_or(true, |steps| steps.contains(&kind));
+    let mut reports = Vec::new();
+    let mut report = |step, status, started: Instant, message: String| {
+      reports.push(StepReport {
+        step,
+        status,
+        message,
+        duration_ms: started.elapsed().as_millis() as i64,
+      });
+    };
+
+    let mut monitor_layout = MonitorLayoutResult::skipped("Monitor layout step was not run");
+    if wanted(ActivationStepKind::MonitorLayout) {
+      let started = Instant::now();
+      let monitors: Vec<_> = profile
+        .monitors
+        .into_iter()
+        .map(MonitorService::to_system_monitor)
+        .collect();
+      let monitors = if include_virtual_displays {
+        monitors
+      } else {
+        MonitorService::without_virtual_displays(system.as_ref(), monitors, &profile_id.to_string())
+      };
+      monitor_layout = if monitors.is_empty() {
+        MonitorLayoutResult::skipped("No monitor layout saved")
+      } else {
+        MonitorService::apply_layout(system.as_ref(), monitors).await
+      };
+      report(
+        ActivationStepKind::MonitorLayout,
+        StepStatus::Completed,
+        started,
+        monitor_layout.message.clone(),
+      );
+    }
+    if wanted(ActivationStepKind::BlankDisplays) {
+      report(
+        ActivationStepKind::BlankDisplays,
+        StepStatus::Skipped,
+        Instant::now(),
+        "Not available while the database is offline".to_string(),
+      );
+    }
+
+    let mut apps_launched = Vec::new();
+    if wanted(ActivationStepKind::Apps) {
+      let started = Instant::now();
+      let mut apps: Vec<_> = profile
+        .apps
+        .into_iter()
+        .filter(|app| app.launch_on_activate)
+        .collect();
+      apps.sort_by_key(|app| (app.order_index, app.startup_delay_ms));
+      for app in apps {
+        let mut result = AppService::launch_app_by_bundle_id(&app.bundle_id, &app.name);
+        result.app_id = Some(app.id);
+        apps_launched.push(result);
+      }
+      report(
+        ActivationStepKind::Apps,
+        StepStatus::Completed,
+        started,
+        format!("Launched {} apps", apps_launched.len()),
+      );
+    }
+
+    let mut tabs_opened = Vec::new();
+    if wanted(ActivationStepKind::BrowserTabs) {
+      let started = Instant::now();
+      for tab in &profile.browser_tabs {
+        tabs_opened.push(BrowserService::open_url_in_browser(&tab.url, &tab.browser));
+      }
+      report(
+        ActivationStepKind::BrowserTabs,
+        StepStatus::Completed,
+        started,
+        format!("Opened {} tabs", tabs_opened.len()),
+      );
+    }
+
+    let mut files_opened = Vec::new();
+    if wanted(ActivationStepKind::Files) {
+      let started = Instant::now();
+      let mut files: Vec<_> = profile
+        .files
+        .into_iter()
+        .filter(|file| file.open_on_activate)
+        .collect();
+      files.sort_by_key(|file| file.open_order);
+      for file in files {
+        files_opened.push(FileService::open_file(
+          &file.path,
+          file.target_app.as_deref(),
+        ));
+      }
+      report(
+        ActivationStepKind::Files,
+        StepStatus::Completed,
+        started,
+        format!("Opened {} files", files_opened.len()),
+      );
+    }
+
+    Some(OfflineStart {
+      apps_launched,
+      tabs_opened,
+      files_opened,
+      monitor_layout,
+      steps: reports,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use uuid::Uuid;
+
+  #[tokio::test]
+  async fn test_snapshot_survives_a_restart_and_answers_outages() {
+    let path = std::env::temp_dir().join(format!("smoothie-offline-{}.json", Uuid::new_v4()));
+    let store: &'static OfflineStore = Box::leak(Box::new(OfflineStore::open(path.clone())));
+    store.update(|s| {
+      s.profiles.insert("user".into(), Vec::new());
+    });
+    store.update(|s| {
+      s.settings.clear();
+    });
+    // Both changes wait for the one scheduled write
+    assert!(store.write_pending.load(Ordering::Acquire));
+    assert!(!path.exists());
+    store.flush().await;
+
+    let reopened = OfflineStore::open(path.clone());
+    assert!(reopened.snapshot.read().profiles.contains_key("user"));
+    assert!(reopened.snapshot.read().saved_at.is_some());
+    assert!(!store.write_pending.load(Ordering::Acquire));
+    let _ = std::fs::remove_file(&path);
+
+    let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+    let down: Result<u32> = Err(sqlx::Error::Io(refused).into());
+    assert_eq!(
+      OfflineCache::or_cached(down, "count", || Some(3)).unwrap(),
+      3
+    );
+    let down: Result<u32> = Err(sqlx::Error::PoolTimedOut.into());
+    assert!(OfflineCache::or_cached(down, "count", || None).is_err());
+    // A broken query or a slow one isn't an outage, so it isn't hidden
+    for failed in [
+      SmoothieError::from(sqlx::Error::RowNotFound),
+      SmoothieError::from(sqlx::Error::ColumnNotFound("name".into())),
+      SmoothieError::Timeout("get_profiles exceeded 10000ms".into()),
+      SmoothieError::NotFound("Profile not found".into()),
+    ] {
+      assert!(OfflineCache::or_cached(Err(failed), "count", || Some(3)).is_err());
+    }
+  }
+}
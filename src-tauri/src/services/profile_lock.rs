@@ -0,0 +1,110 @@
+//! Profile locks
+//!
+//! A layout tuned pixel by pixel is one stray drag away from being undone. A
+//! locked profile still activates as usual, but every change to it or to
+//! its monitors, windows, apps, tabs, files, rules, checklist, blocked apps,
+//! deactivation actions and shortcut is turned away until it is unlocked
+//! again.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  repositories::{ProfilePart, ProfileRepository},
+};
+use uuid::Uuid;
+
+fn locked(name: String) -> SmoothieError {
+  SmoothieError::Locked(format!("\"{}\" is locked; unlock it to change it", name))
+}
+
+pub struct ProfileLock;
+
+impl ProfileLock {
+  /// Fail if the profile is locked
+  pub async fn ensure_unlocked(db: &Database, profile_id: Uuid) -> Result<()> {
+    match ProfileRepository::new(db.pool())
+      .find_locked_name(profile_id)
+      .await?
+    {
+      Some(name) => Err(locked(name)),
+      None => Ok(()),
+    }
+  }
+
+  /// Fail if the profile owning row `id` of `part` is locked
+  pub async fn ensure_part_unlocked(db: &Database, part: ProfilePart, id: Uuid) -> Result<()> {
+    match ProfileRepository::new(db.pool())
+      .find_locked_owner_name(part, id)
+      .await?
+    {
+      Some(name) => Err(locked(name)),
+      None => Ok(()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use crate::models::ChecklistItemRequest;
+  use crate::services::{AppService, ChecklistService, DeactivationService, ProfileService};
+
+  #[tokio::test]
+  async fn test_locked_profile_rejects_edits_until_unlocked() {
+    let test_db = test_db_or_skip!();
+    let (_, profile_id) = test_db.seed_user_and_profile("Desk").await;
+    let (app_id,): (Uuid,) = sqlx::query_as(
+      "INSERT INTO apps (profile_id, name, bundle_id) VALUES ($1, 'Safari', 'com.apple.Safari') RETURNING id",
+    )
+    .bind(profile_id)
+    .fetch_one(test_db.pool())
+    .await
+    .unwrap();
+    let db = &test_db.db;
+
+    let profile = ProfileService::set_locked(db, profile_id.into(), true)
+      .await
+      .unwrap();
+    assert!(profile.is_locked);
+
+    let err = ProfileService::update_profile(db, profile_id.into(), Some("Oops".into()), None)
+      .await
+      .unwrap_err();
+    assert!(matches!(err, SmoothieError::Locked(_)));
+    assert!(ProfileService::delete_profile(db, profile_id.into())
+      .await
+      .is_err());
+    let err = AppService::delete_app(db, &app_id.to_string())
+      .await
+      .unwrap_err();
+    assert!(err.to_string().contains("\"Desk\" is locked"));
+    let err = ChecklistService::set_items(
+      db,
+      profile_id.into(),
+      vec![ChecklistItemRequest {
+        id: None,
+        label: "VPN connected?".into(),
+      }],
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, SmoothieError::Locked(_)));
+    let err = DeactivationService::set_actions(db, profile_id.into(), Vec::new())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, SmoothieError::Locked(_)));
+
+    ProfileService::set_locked(db, profile_id.into(), false)
+      .await
+      .unwrap();
+    let renamed =
+      ProfileService::update_profile(db, profile_id.into(), Some("Desk 2".into()), None)
+        .await
+        .unwrap();
+    assert_eq!(renamed.name, "Desk 2");
+    AppService::delete_app(db, &app_id.to_string())
+      .await
+      .unwrap();
+  }
+}
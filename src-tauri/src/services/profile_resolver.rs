@@ -0,0 +1,600 @@
+//! Picks the profile that fits the connected displays
+//!
+//! A profile can be bound to an exact set of displays (its monitor signature)
+//! or just to how many there are. When several profiles fit, the user's default
+//! for that monitor count wins, then the one already active, then the one used
+//! most recently. The monitor watcher switches to the pick whenever the
+//! displays change and monitor detection is on. Docking reconfigures the
+//! displays several times in a row, so a change is only handled once the
+//! setup has held still for a moment, as one change from the setup before the
+//! burst to the one it settled on.
+
+use crate::{
+  db::Database,
+  error::Result,
+  models::{
+    entities::ProfileEntity, ActivationSource, MonitorChangeType, MonitorProfileMapping, ProfileId,
+    UserId,
+  },
+  repositories::ProfileRepository,
+  services::{
+    resource_guard::ThrottledTicker, ChecklistService, ProfileService, SystemBackend,
+    SystemMonitor, UserSettingsService, AUDIT_SERVICE,
+  },
+  state::AppState,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const MONITOR_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How long the displays must hold still before a change is handled
+const SETTLE_WINDOW: Duration = Duration::from_secs(2);
+
+/// Poll interval while a change is settling
+const SETTLE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Why a profile was picked for the connected displays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchReason {
+  /// Bound to exactly these displays
+  Signature,
+  /// Bound to this many displays
+  MonitorCount,
+  /// The user's default for this many displays
+  DefaultMapping,
+}
+
+/// The profile picked for a monitor setup
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileMatchDto {
+  pub profile_id: String,
+  pub profile_name: String,
+  pub reason: MatchReason,
+  pub monitor_count: usize,
+  pub monitor_signature: String,
+}
+
+/// A burst of display changes that has settled
+struct SettledChange {
+  before: Vec<SystemMonitor>,
+  after: Vec<SystemMonitor>,
+  /// Readings that differed from the one before, the last included
+  changes: u32,
+}
+
+struct PendingChange {
+  latest: Vec<SystemMonitor>,
+  since: Instant,
+  changes: u32,
+}
+
+/// Coalesces readings into settled changes. Until the first reading there is
+/// no baseline, so nothing is reported.
+#[derive(Default)]
+struct MonitorDebouncer {
+  settled: Option<Vec<SystemMonitor>>,
+  pending: Option<PendingChange>,
+}
+
+impl MonitorDebouncer {
+  fn is_settling(&self) -> bool {
+    self.pending.is_some()
+  }
+
+  fn observe(&mut self, monitors: Vec<SystemMonitor>, now: Instant) -> Option<SettledChange> {
+    let Some(settled) = &self.settled else {
+      self.settled = Some(monitors);
+      return None;
+    };
+    let latest = self.pending.as_ref().map_or(settled, |p| &p.latest);
+    if changed(latest, &monitors) {
+      let changes = self.pending.as_ref().map_or(0, |p| p.changes) + 1;
+      self.pending = Some(PendingChange {
+        latest: monitors,
+        since: now,
+        changes,
+      });
+      return None;
+    }
+    if !self
+      .pending
+      .as_ref()
+      .is_some_and(|p| now.duration_since(p.since) >= SETTLE_WINDOW)
+    {
+      return None;
+    }
+
+    let pending = self.pending.take()?;
+    if !changed(settled, &pending.latest) {
+      tracing::debug!(
+        changes = pending.changes,
+        "Monitor setup changed and came back"
+      );
+      return None;
+    }
+    let before = self.settled.replace(pending.latest.clone())?;
+    Some(SettledChange {
+      before,
+      after: pending.latest,
+      changes: pending.changes,
+    })
+  }
+}
+
+/// Identity of a whole display setup: the sorted fingerprints of its displays,
+/// so the same monitors match wherever they're arranged
+pub fn monitor_signature(monitors: &[SystemMonitor]) -> String {
+  let mut fingerprints: Vec<String> = monitors.iter().map(SystemMonitor::fingerprint).collect();
+  fingerprints.sort();
+  fingerprints.join("+")
+}
+
+/// The displays a profile is matched and laid out against: Sidecar, AirPlay
+/// and virtual ones only count when it includes them
+pub fn displays_for(include_virtual: bool, monitors: &[SystemMonitor]) -> Vec<SystemMonitor> {
+  monitors
+    .iter()
+    .filter(|m| include_virtual || !m.is_virtual())
+    .cloned()
+    .collect()
+}
+
+/// The best profile for `monitors` among `profiles`, if any fits
+pub fn resolve(
+  profiles: &[ProfileEntity],
+  mappings: &[MonitorProfileMapping],
+  monitors: &[SystemMonitor],
+) -> Option<(Uuid, MatchReason)> {
+  let physical = displays_for(false, monitors);
+  let setup = |p: &ProfileEntity| {
+    if p.include_virtual_displays {
+      monitors
+    } else {
+      &physical[..]
+    }
+  };
+  let (signature, all_signature) = (monitor_signature(&physical), monitor_signature(monitors));
+  let count = physical.len() as i32;
+  let default = mappings
+    .iter()
+    .find(|m| m.monitor_count == count)
+    .and_then(|m| Uuid::parse_str(&m.profile_id).ok());
+
+  let by_signature: Vec<&ProfileEntity> = profiles
+    .iter()
+    .filter(|p| {
+      let signature = if p.include_virtual_displays {
+        &all_signature
+      } else {
+        &signature
+      };
+      p.monitor_signature.as_deref() == Some(signature.as_str())
+    })
+    .collect();
+  // A signature is the more specific binding, so a mismatched one rules a
+  // profile out even when its count fits
+  let by_count: Vec<&ProfileEntity> = profiles
+    .iter()
+    .filter(|p| {
+      p.monitor_signature.is_none() && p.required_monitor_count == Some(setup(p).len() as i32)
+    })
+    .collect();
+
+  if let Some(profile) = best_of(&by_signature, default) {
+    return Some((profile.id, MatchReason::Signature));
+  }
+  if let Some(profile) = best_of(&by_count, default) {
+    return Some((profile.id, MatchReason::MonitorCount));
+  }
+  default
+    .filter(|id| profiles.iter().any(|p| p.id == *id))
+    .map(|id| (id, MatchReason::DefaultMapping))
+}
+
+fn best_of<'a>(
+  candidates: &[&'a ProfileEntity],
+  default: Option<Uuid>,
+) -> Option<&'a ProfileEntity> {
+  candidates
+    .iter()
+    .copied()
+    .max_by_key(|p| (Some(p.id) == default, p.is_active, p.last_activated_at))
+}
+
+pub struct ProfileResolver;
+
+impl ProfileResolver {
+  /// The profile that fits the connected displays, without activating it
+  pub async fn resolve_for_monitors(
+    db: &Database,
+    system: Arc<dyn SystemBackend>,
+    user_id: UserId,
+  ) -> Result<Option<ProfileMatchDto>> {
+    let monitors = tokio::task::spawn_blocking(move || system.get_monitors())
+      .await
+      .unwrap_or_default();
+    Self::resolve_with(db, user_id, &monitors).await
+  }
+
+  async fn resolve_with(
+    db: &Database,
+    user_id: UserId,
+    monitors: &[SystemMonitor],
+  ) -> Result<Option<ProfileMatchDto>> {
+    if monitors.is_empty() {
+      return Ok(None);
+    }
+    let settings = UserSettingsService::get_settings(db, user_id.as_uuid()).await?;
+    let profiles = ProfileRepository::new(db.pool())
+      .find_by_user_id(user_id.as_uuid())
+      .await?;
+
+    Ok(
+      resolve(&profiles, &settings.monitor_profile_mappings, monitors).and_then(
+        |(profile_id, reason)| {
+          let profile = profiles.iter().find(|p| p.id == profile_id)?;
+          let setup = displays_for(profile.include_virtual_displays, monitors);
+          Some(ProfileMatchDto {
+            profile_id: profile_id.to_string(),
+            profile_name: profile.name.clone(),
+            reason,
+            monitor_count: setup.len(),
+            monitor_signature: monitor_signature(&setup),
+          })
+        },
+      ),
+    )
+  }
+
+  /// Watch for displays being connected, disconnected or rearranged, record
+  /// each settled change and switch to the profile that fits. The first
+  /// reading only sets the baseline.
+  pub fn spawn_monitor_watcher(state: Arc<AppState>, user_id: Uuid) {
+    let watcher_state = state.clone();
+    state.tasks.spawn("monitor_watcher", move || {
+      Self::watch_monitors(watcher_state.clone(), user_id.into())
+    });
+  }
+
+  async fn watch_monitors(state: Arc<AppState>, user_id: UserId) {
+    let mut ticker = ThrottledTicker::new(MONITOR_POLL_INTERVAL);
+    let mut debouncer = MonitorDebouncer::default();
+    loop {
+      if debouncer.is_settling() {
+        tokio::time::sleep(SETTLE_POLL_INTERVAL).await;
+      } else {
+        ticker.tick().await;
+      }
+      let system = state.system.clone();
+      let Ok(monitors) = tokio::task::spawn_blocking(move || system.get_monitors()).await else {
+        continue;
+      };
+      // No readings without Screen Recording permission
+      if monitors.is_empty() {
+        continue;
+      }
+
+      if let Some(change) = debouncer.observe(monitors, Instant::now()) {
+        tracing::info!(
+          from = change.before.len(),
+          to = change.after.len(),
+          changes = change.changes,
+          "Monitor setup changed"
+        );
+        if let Err(e) =
+          Self::on_monitors_changed(&state, user_id, &change.before, &change.after).await
+        {
+          tracing::warn!("Monitor change handling failed: {}", e);
+        }
+      }
+    }
+  }
+
+  async fn on_monitors_changed(
+    state: &AppState,
+    user_id: UserId,
+    before: &[SystemMonitor],
+    after: &[SystemMonitor],
+  ) -> Result<()> {
+    let settings = UserSettingsService::get_settings(&state.db, user_id.as_uuid()).await?;
+    let mut activated: Option<ProfileMatchDto> = None;
+    if state.automation_pause.is_paused() {
+      tracing::info!("Automation paused; keeping the active profile");
+    } else if settings.monitor_detection {
+      if let Some(pick) = Self::resolve_with(&state.db, user_id, after).await? {
+        let profile_id: ProfileId = pick.profile_id.parse()?;
+        let active = ProfileRepository::new(state.db.pool())
+          .find_by_id(profile_id.as_uuid())
+          .await?
+          .is_some_and(|p| p.is_active);
+        if !active {
+          ProfileService::activate_profile(
+            &state.db,
+            profile_id,
+            user_id,
+            ActivationSource::MonitorChange,
+          )
+          .await?;
+          state.invalidate_profile_caches();
+          ChecklistService::present(&state.db, state.system.as_ref(), profile_id).await;
+          tracing::info!(
+            profile = %pick.profile_name,
+            reason = ?pick.reason,
+            "Switched profile for the new monitor setup"
+          );
+          activated = Some(pick);
+        }
+      }
+    }
+
+    let user_id = user_id.to_string();
+    let change = AUDIT_SERVICE
+      .record_monitor_change(
+        &state.db,
+        Some(&user_id),
+        change_type(before, after),
+        Some(serde_json::to_value(before)?),
+        Some(serde_json::to_value(after)?),
+        activated.is_some(),
+        activated.as_ref().map(|pick| pick.profile_id.as_str()),
+      )
+      .await?;
+
+    if let (Some(summary), true) = (change.summary, settings.notifications_enabled) {
+      let body = match &activated {
+        Some(pick) => format!("{}. Switched to {}.", summary, pick.profile_name),
+        None => summary,
+      };
+      if let Err(e) = state
+        .system
+        .show_notification("Smoothie: Displays changed", &body)
+      {
+        tracing::warn!("Failed to show monitor change notification: {}", e);
+      }
+    }
+    Ok(())
+  }
+}
+
+fn changed(before: &[SystemMonitor], after: &[SystemMonitor]) -> bool {
+  let frames = |monitors: &[SystemMonitor]| {
+    let mut frames: Vec<_> = monitors
+      .iter()
+      .map(|m| (m.fingerprint(), m.x, m.y, m.is_primary))
+      .collect();
+    frames.sort();
+    frames
+  };
+  frames(before) != frames(after)
+}
+
+fn change_type(before: &[SystemMonitor], after: &[SystemMonitor]) -> MonitorChangeType {
+  let primary = |monitors: &[SystemMonitor]| {
+    monitors
+      .iter()
+      .find(|m| m.is_primary)
+      .map(SystemMonitor::fingerprint)
+  };
+  if after.len() > before.len() {
+    MonitorChangeType::Connected
+  } else if after.len() < before.len() {
+    MonitorChangeType::Disconnected
+  } else if monitor_signature(before) != monitor_signature(after) {
+    MonitorChangeType::ResolutionChanged
+  } else if primary(before) != primary(after) {
+    MonitorChangeType::PrimaryChanged
+  } else {
+    MonitorChangeType::ArrangementChanged
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use crate::models::ProfileType;
+  use crate::services::system_backend::MockSystemBackend;
+  use crate::services::system_service::DisplayKind;
+  use chrono::Utc;
+
+  fn profile(name: &str, count: Option<i32>, signature: Option<String>) -> ProfileEntity {
+    let now = Utc::now();
+    ProfileEntity {
+      id: Uuid::new_v4(),
+      user_id: Uuid::nil(),
+      name: name.into(),
+      description: None,
+      profile_type: ProfileType::Work,
+      is_active: false,
+      created_at: now,
+      updated_at: now,
+      last_used: None,
+      last_activated_at: None,
+      activation_count: None,
+      is_favorite: None,
+      color: None,
+      icon: None,
+      sort_order: None,
+      low_power_battery_threshold: None,
+      local_only: false,
+      required_monitor_count: count,
+      monitor_signature: signature,
+      notes: None,
+      include_virtual_displays: false,
+      is_locked: false,
+    }
+  }
+
+  #[test]
+  fn test_resolve_prefers_signature_then_count_then_default() {
+    let docked = MockSystemBackend::with_sample_layout().get_monitors();
+    let laptop = vec![docked[0].clone()];
+
+    let desk = profile("Desk", None, Some(monitor_signature(&docked)));
+    let any_two = profile("Any two", Some(2), None);
+    let solo = profile("Solo", Some(1), None);
+    let travel = profile("Travel", None, None);
+    let mappings = vec![MonitorProfileMapping {
+      monitor_count: 1,
+      profile_id: travel.id.to_string(),
+    }];
+    let profiles = vec![desk.clone(), any_two.clone(), solo.clone(), travel.clone()];
+
+    assert_eq!(
+      resolve(&profiles, &mappings, &docked),
+      Some((desk.id, MatchReason::Signature))
+    );
+    // Two monitors, but not the Desk ones
+    let mut other_two = docked.clone();
+    other_two[1].model = Some("LG 27UK850".into());
+    assert_eq!(
+      resolve(&profiles, &mappings, &other_two),
+      Some((any_two.id, MatchReason::MonitorCount))
+    );
+    assert_eq!(
+      resolve(&profiles, &mappings, &laptop),
+      Some((solo.id, MatchReason::MonitorCount))
+    );
+
+    // The default breaks a tie between profiles bound the same way
+    let also_solo = profile("Also solo", Some(1), None);
+    let mappings = vec![MonitorProfileMapping {
+      monitor_count: 1,
+      profile_id: also_solo.id.to_string(),
+    }];
+    let profiles = vec![solo, also_solo.clone(), travel.clone()];
+    assert_eq!(
+      resolve(&profiles, &mappings, &laptop),
+      Some((also_solo.id, MatchReason::MonitorCount))
+    );
+
+    let mappings = vec![MonitorProfileMapping {
+      monitor_count: 2,
+      profile_id: travel.id.to_string(),
+    }];
+    assert_eq!(
+      resolve(&profiles, &mappings, &docked),
+      Some((travel.id, MatchReason::DefaultMapping))
+    );
+    assert_eq!(resolve(&profiles, &[], &docked), None);
+
+    // An iPad joining doesn't change the setup, except for profiles that count it
+    let mut with_ipad = docked.clone();
+    with_ipad.push(SystemMonitor {
+      display_id: 3,
+      name: "Sidecar Display".into(),
+      kind: DisplayKind::Sidecar,
+      x: -1180,
+      ..docked[0].clone()
+    });
+    let profiles = vec![desk.clone()];
+    assert_eq!(
+      resolve(&profiles, &[], &with_ipad),
+      Some((desk.id, MatchReason::Signature))
+    );
+    let mut sketching = profile("Sketching", None, Some(monitor_signature(&with_ipad)));
+    sketching.include_virtual_displays = true;
+    let profiles = vec![desk.clone(), sketching.clone()];
+    assert_eq!(
+      resolve(&profiles, &[], &with_ipad),
+      Some((sketching.id, MatchReason::Signature))
+    );
+    assert_eq!(
+      resolve(&profiles, &[], &docked),
+      Some((desk.id, MatchReason::Signature))
+    );
+  }
+
+  #[test]
+  fn test_debouncer_coalesces_a_burst_into_one_change() {
+    let docked = MockSystemBackend::with_sample_layout().get_monitors();
+    let laptop = vec![docked[0].clone()];
+    let mut moved = docked.clone();
+    moved[1].x += 100;
+
+    let start = Instant::now();
+    let at = |ms: u64| start + Duration::from_millis(ms);
+    let mut debouncer = MonitorDebouncer::default();
+    assert!(debouncer.observe(laptop.clone(), at(0)).is_none());
+
+    // Docking: the external display shows up, then gets moved into place
+    assert!(debouncer.observe(docked.clone(), at(500)).is_none());
+    assert!(debouncer.observe(moved.clone(), at(1000)).is_none());
+    assert!(debouncer.observe(moved.clone(), at(2500)).is_none());
+    let change = debouncer.observe(moved.clone(), at(3000)).unwrap();
+    assert_eq!((change.before.len(), change.after.len()), (1, 2));
+    assert_eq!(change.after[1].x, moved[1].x);
+    assert_eq!(change.changes, 2);
+    assert!(!debouncer.is_settling());
+
+    // A flicker that comes back to the settled setup isn't a change
+    assert!(debouncer.observe(laptop, at(3500)).is_none());
+    assert!(debouncer.observe(moved.clone(), at(4000)).is_none());
+    assert!(debouncer.observe(moved, at(6000)).is_none());
+    assert!(!debouncer.is_settling());
+  }
+
+  #[tokio::test]
+  async fn test_resolve_for_monitors_uses_bindings_and_settings() {
+    let test_db = test_db_or_skip!();
+    let db = &test_db.db;
+    let user_id = test_db.seed_user().await;
+    let repo = ProfileRepository::new(db.pool());
+    let docked = repo
+      .create(user_id, "Docked", None, ProfileType::Work)
+      .await
+      .unwrap();
+    let laptop = repo
+      .create(user_id, "Laptop", None, ProfileType::Personal)
+      .await
+      .unwrap();
+    let system = Arc::new(MockSystemBackend::with_sample_layout());
+
+    ProfileService::set_monitor_binding(db, docked.id.into(), Some(2), None)
+      .await
+      .unwrap();
+    let pick = ProfileResolver::resolve_for_monitors(db, system.clone(), user_id.into())
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(pick.profile_id, docked.id.to_string());
+    assert_eq!(pick.reason, MatchReason::MonitorCount);
+
+    let undocked = vec![system.get_monitors()[0].clone()];
+    system.set_monitors(undocked);
+    assert!(
+      ProfileResolver::resolve_for_monitors(db, system.clone(), user_id.into())
+        .await
+        .unwrap()
+        .is_none()
+    );
+
+    let mapping = |count: i32, profile: &ProfileEntity| MonitorProfileMapping {
+      monitor_count: count,
+      profile_id: profile.id.to_string(),
+    };
+    assert!(UserSettingsService::set_monitor_profile_mappings(
+      db,
+      user_id,
+      vec![mapping(1, &laptop), mapping(1, &docked)],
+    )
+    .await
+    .is_err());
+    let settings =
+      UserSettingsService::set_monitor_profile_mappings(db, user_id, vec![mapping(1, &laptop)])
+        .await
+        .unwrap();
+    assert_eq!(settings.monitor_profile_mappings.len(), 1);
+    let pick = ProfileResolver::resolve_for_monitors(db, system, user_id.into())
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(pick.profile_id, laptop.id.to_string());
+    assert_eq!(pick.reason, MatchReason::DefaultMapping);
+  }
+}
@@ -3,21 +3,63 @@ use crate::{
   error::{Result, SmoothieError},
   logging::METRICS,
   models::dto::{
-    AppDto, BrowserTabDto, CreateProfileRequest, MonitorDto, ProfileDto, ProfileResponse,
+    AppDto, BrowserTabDto, CreateMonitorRequest, CreateProfileRequest, MonitorDto, ProfileDto,
+    ProfileFileDto, ProfileResponse, UpdateProfileRequest,
   },
+  models::entities::ProfileEntity,
+  models::{ActivationSource, ActivityStatus, ProfileId, UserId},
   repositories::{
-    AppRepository, AuditRepository, BrowserTabRepository, MonitorRepository, ProfileRepository,
+    AppRepository, AuditStore, BrowserTabRepository, MonitorRepository, ProfileFileRepository,
+    ProfileRepository, ProfileStore,
   },
+  services::{
+    audit_backend::audit_store,
+    change_log::{ChangeLog, ChangeSubject},
+    environment_service::PowerState,
+    profile_lock::ProfileLock,
+    DeactivationService, EnvironmentService,
+  },
+  utils::{markdown, validation},
 };
 use uuid::Uuid;
 
+/// Above this many monitors, apps and tabs combined, a profile's children are
+/// loaded in one aggregated query instead of three concurrent ones
+const LARGE_PROFILE_CHILDREN: i64 = 50;
+
+/// Most monitors a profile can be bound to
+pub const MAX_MONITORS: i32 = 16;
+
+/// Key under which activation metadata notes whether low-power mode applied
+pub const LOW_POWER_METADATA_KEY: &str = "lowPower";
+
+/// Record in activation metadata whether the profile's low-power threshold was hit,
+/// judged from the power state in the environment snapshot
+fn note_low_power(
+  metadata: Option<serde_json::Value>,
+  threshold: i32,
+) -> Option<serde_json::Value> {
+  let active = EnvironmentService::power_from_metadata(metadata.as_ref())
+    .is_some_and(|power| power.is_low(threshold));
+  let note = serde_json::json!({ "thresholdPercent": threshold, "active": active });
+
+  match metadata {
+    Some(serde_json::Value::Object(mut map)) => {
+      map.insert(LOW_POWER_METADATA_KEY.to_string(), note);
+      Some(serde_json::Value::Object(map))
+    }
+    Some(other) => Some(serde_json::json!({ "data": other, LOW_POWER_METADATA_KEY: note })),
+    None => Some(serde_json::json!({ LOW_POWER_METADATA_KEY: note })),
+  }
+}
+
 /// Service layer for profile operations
 /// Coordinates between handlers and repositories
 pub struct ProfileService;
 
 impl ProfileService {
   /// Ensure a user exists in the local database (creates if not exists)
-  async fn ensure_user_exists(db: &Database, user_id: Uuid) -> Result<()> {
+  pub(crate) async fn ensure_user_exists(db: &Database, user_id: Uuid) -> Result<()> {
     let result = sqlx::query(
       r#"
       INSERT INTO users (id, created_at, updated_at)
@@ -36,7 +78,7 @@ impl ProfileService {
       }
       Err(e) => {
         tracing::error!(user_id = %user_id, error = %e, "Failed to ensure user exists");
-        Err(SmoothieError::DatabaseError(e.to_string()))
+        Err(SmoothieError::from(e))
       }
     }
   }
@@ -44,38 +86,54 @@ impl ProfileService {
   /// Create a new profile
   pub async fn create_profile(
     db: &Database,
-    user_id: &str,
+    user_id: UserId,
     req: CreateProfileRequest,
   ) -> Result<ProfileDto> {
-    let user_uuid = parse_uuid(user_id)?;
+    let user_uuid = user_id.as_uuid();
 
     // Ensure the user exists in the local database
     Self::ensure_user_exists(db, user_uuid).await?;
 
-    let repo = ProfileRepository::new(db.pool());
+    Self::create_profile_with(
+      &ProfileRepository::new(db.pool()),
+      &*audit_store(db),
+      user_id,
+      req,
+    )
+    .await
+  }
+
+  /// Create a new profile against the given stores
+  pub async fn create_profile_with(
+    profiles: &dyn ProfileStore,
+    audit: &dyn AuditStore,
+    user_id: UserId,
+    req: CreateProfileRequest,
+  ) -> Result<ProfileDto> {
+    validation::validate(&req)?;
+    let user_uuid = user_id.as_uuid();
 
-    let entity = repo
+    let entity = profiles
       .create(
         user_uuid,
         &req.name,
         req.description.as_deref(),
-        &req.profile_type,
+        req.profile_type,
       )
       .await?;
 
-    // Insert tags if provided
-    if let Some(tags) = req.tags {
-      for tag in tags {
-        repo.add_tag(entity.id, &tag).await?;
-      }
+    if let Some(tags) = &req.tags {
+      profiles.set_tags(entity.id, tags).await?;
+    }
+    if let Some(notes) = req.notes.as_deref().and_then(markdown::sanitize_notes) {
+      profiles.set_notes(entity.id, Some(&notes)).await?;
     }
 
     tracing::info!(profile_id = %entity.id, user_id = %user_id, "Profile created");
     METRICS.record_profile_created();
 
     // Log the profile creation activity
-    let audit_repo = AuditRepository::new(db.pool());
-    let _ = audit_repo
+    let _ = audit
       .log_activity(
         user_uuid,
         None, // session_id - could be added later
@@ -87,114 +145,121 @@ impl ProfileService {
           "profile_type": req.profile_type,
           "description": req.description
         })),
-        "success",
+        ActivityStatus::Success,
         None,
         None,
       )
       .await;
 
     // Re-fetch to get updated data with tags
-    let updated = repo
+    let updated = profiles
       .find_by_id(entity.id)
       .await?
       .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?;
-    let tags = repo.find_tags(entity.id).await?;
-
-    // Get counts for related entities
-    let monitor_count = MonitorRepository::new(db.pool())
-      .count_by_profile_id(entity.id)
-      .await?;
-    let app_count = AppRepository::new(db.pool())
-      .count_by_profile_id(entity.id)
-      .await?;
-    let browser_tab_count = BrowserTabRepository::new(db.pool())
-      .count_by_profile_id(entity.id)
-      .await?;
 
-    Ok(ProfileDto::from_entity_with_counts(
-      updated,
-      tags,
-      monitor_count,
-      app_count,
-      browser_tab_count,
-    ))
+    Self::to_dto(profiles, updated).await
   }
 
   /// Get all profiles for a user
-  pub async fn get_profiles(db: &Database, user_id: &str) -> Result<Vec<ProfileDto>> {
-    let user_uuid = parse_uuid(user_id)?;
+  pub async fn get_profiles(db: &Database, user_id: UserId) -> Result<Vec<ProfileDto>> {
+    let user_uuid = user_id.as_uuid();
 
     // Ensure the user exists in the local database
     Self::ensure_user_exists(db, user_uuid).await?;
 
-    let repo = ProfileRepository::new(db.pool());
+    Self::get_profiles_with(&ProfileRepository::new(db.pool()), user_id).await
+  }
 
-    let profiles = repo.find_by_user_id(user_uuid).await?;
-    let mut result = Vec::with_capacity(profiles.len());
-
-    for profile in profiles {
-      let tags = repo.find_tags(profile.id).await?;
-
-      // Get counts for related entities
-      let monitor_count = MonitorRepository::new(db.pool())
-        .count_by_profile_id(profile.id)
-        .await?;
-      let app_count = AppRepository::new(db.pool())
-        .count_by_profile_id(profile.id)
-        .await?;
-      let browser_tab_count = BrowserTabRepository::new(db.pool())
-        .count_by_profile_id(profile.id)
-        .await?;
-
-      result.push(ProfileDto::from_entity_with_counts(
-        profile,
-        tags,
-        monitor_count,
-        app_count,
-        browser_tab_count,
-      ));
+  /// Get all profiles for a user from the given store
+  pub async fn get_profiles_with(
+    profiles: &dyn ProfileStore,
+    user_id: UserId,
+  ) -> Result<Vec<ProfileDto>> {
+    let user_uuid = user_id.as_uuid();
+    let entities = profiles.find_by_user_id(user_uuid).await?;
+    Self::to_dtos(profiles, entities).await
+  }
+
+  /// Put the user's profiles in the order of `profile_ids` and return the new ordering
+  pub async fn reorder_profiles(
+    db: &Database,
+    user_id: UserId,
+    profile_ids: &[ProfileId],
+  ) -> Result<Vec<ProfileDto>> {
+    let ids: Vec<Uuid> = profile_ids.iter().map(|id| id.as_uuid()).collect();
+    validation::validate_field("profile_ids", validation::distinct_ids(&ids))?;
+
+    if !ProfileRepository::new(db.pool())
+      .reorder(user_id.as_uuid(), &ids)
+      .await?
+    {
+      return Err(SmoothieError::NotFound("Profile not found".into()));
     }
 
-    Ok(result)
+    tracing::info!(user_id = %user_id, count = ids.len(), "Profiles reordered");
+
+    Self::get_profiles(db, user_id).await
   }
 
   /// Get a specific profile
-  pub async fn get_profile(db: &Database, profile_id: &str) -> Result<ProfileDto> {
-    let profile_uuid = parse_uuid(profile_id)?;
-    let repo = ProfileRepository::new(db.pool());
+  pub async fn get_profile(db: &Database, profile_id: ProfileId) -> Result<ProfileDto> {
+    Self::get_profile_with(&ProfileRepository::new(db.pool()), profile_id).await
+  }
 
-    let profile = repo
+  /// Get a specific profile from the given store
+  pub async fn get_profile_with(
+    profiles: &dyn ProfileStore,
+    profile_id: ProfileId,
+  ) -> Result<ProfileDto> {
+    let profile_uuid = profile_id.as_uuid();
+
+    let profile = profiles
       .find_by_id(profile_uuid)
       .await?
       .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?;
-    let tags = repo.find_tags(profile.id).await?;
-
-    // Get counts for related entities
-    let monitor_count = MonitorRepository::new(db.pool())
-      .count_by_profile_id(profile.id)
-      .await?;
-    let app_count = AppRepository::new(db.pool())
-      .count_by_profile_id(profile.id)
-      .await?;
-    let browser_tab_count = BrowserTabRepository::new(db.pool())
-      .count_by_profile_id(profile.id)
-      .await?;
 
-    Ok(ProfileDto::from_entity_with_counts(
-      profile,
-      tags,
-      monitor_count,
-      app_count,
-      browser_tab_count,
-    ))
+    Self::to_dto(profiles, profile).await
   }
 
   /// Get profile with full details (monitors, apps, browser tabs)
-  pub async fn get_profile_response(db: &Database, profile_id: &str) -> Result<ProfileResponse> {
+  pub async fn get_profile_response(
+    db: &Database,
+    profile_id: ProfileId,
+  ) -> Result<ProfileResponse> {
     let profile = Self::get_profile(db, profile_id).await?;
-    let monitors = MonitorService::get_monitors(db, profile_id).await?;
-    let apps = AppService::get_apps(db, profile_id).await?;
-    let browser_tabs = BrowserService::get_browser_tabs(db, profile_id).await?;
+    let profile_uuid = profile_id.as_uuid();
+    let files_repo = ProfileFileRepository::new(db.pool());
+
+    let child_count = profile.monitor_count + profile.app_count + profile.browser_tab_count;
+    let (monitors, apps, browser_tabs, files) = if child_count > LARGE_PROFILE_CHILDREN {
+      let profile_repo = ProfileRepository::new(db.pool());
+      let (children, files) = tokio::try_join!(
+        profile_repo.find_children(profile_uuid),
+        files_repo.find_by_profile_id(profile_uuid),
+      )?;
+      (
+        children
+          .monitors
+          .into_iter()
+          .map(MonitorDto::from)
+          .collect(),
+        children.apps.into_iter().map(AppDto::from).collect(),
+        children
+          .browser_tabs
+          .into_iter()
+          .map(BrowserTabDto::from)
+          .collect(),
+        files,
+      )
+    } else {
+      tokio::try_join!(
+        MonitorService::get_monitors(db, profile_id),
+        AppService::get_apps(db, profile_id),
+        BrowserService::get_browser_tabs(db, profile_id),
+        files_repo.find_by_profile_id(profile_uuid),
+      )?
+    };
+    let files = files.into_iter().map(ProfileFileDto::from).collect();
 
     Ok(ProfileResponse {
       id: profile.id,
@@ -206,8 +271,10 @@ impl ProfileService {
       monitors,
       apps,
       browser_tabs,
+      files,
       created_at: profile.created_at,
       last_used: profile.last_used,
+      notes: profile.notes,
     })
   }
 
@@ -215,43 +282,27 @@ impl ProfileService {
   #[allow(dead_code)]
   pub async fn update_profile(
     db: &Database,
-    profile_id: &str,
+    profile_id: ProfileId,
     name: Option<String>,
     description: Option<String>,
   ) -> Result<ProfileDto> {
-    let profile_uuid = parse_uuid(profile_id)?;
+    let profile_uuid = profile_id.as_uuid();
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
     let repo = ProfileRepository::new(db.pool());
 
     let updated = repo
       .update(profile_uuid, name.as_deref(), description.as_deref())
       .await?;
-    let tags = repo.find_tags(profile_uuid).await?;
-
-    // Get counts for related entities
-    let monitor_count = MonitorRepository::new(db.pool())
-      .count_by_profile_id(profile_uuid)
-      .await?;
-    let app_count = AppRepository::new(db.pool())
-      .count_by_profile_id(profile_uuid)
-      .await?;
-    let browser_tab_count = BrowserTabRepository::new(db.pool())
-      .count_by_profile_id(profile_uuid)
-      .await?;
 
     tracing::info!(profile_id = %profile_id, "Profile updated");
 
-    Ok(ProfileDto::from_entity_with_counts(
-      updated,
-      tags,
-      monitor_count,
-      app_count,
-      browser_tab_count,
-    ))
+    Self::to_dto(&repo, updated).await
   }
 
   /// Delete a profile
-  pub async fn delete_profile(db: &Database, profile_id: &str) -> Result<()> {
-    let profile_uuid = parse_uuid(profile_id)?;
+  pub async fn delete_profile(db: &Database, profile_id: ProfileId) -> Result<()> {
+    let profile_uuid = profile_id.as_uuid();
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
     let repo = ProfileRepository::new(db.pool());
 
     let deleted = repo.delete(profile_uuid).await?;
@@ -267,48 +318,82 @@ impl ProfileService {
   /// Activate a profile (deactivates all others for the user)
   pub async fn activate_profile(
     db: &Database,
-    profile_id: &str,
-    user_id: &str,
+    profile_id: ProfileId,
+    user_id: UserId,
+    source: ActivationSource,
   ) -> Result<ProfileDto> {
-    let profile_uuid = parse_uuid(profile_id)?;
-    let user_uuid = parse_uuid(user_id)?;
-    let repo = ProfileRepository::new(db.pool());
+    // Log the profile activation along with the environment it ran in
+    let mut metadata =
+      EnvironmentService::attach_to_metadata(None, EnvironmentService::capture_async().await);
 
-    let activated = repo.activate(profile_uuid, user_uuid).await?;
-    let tags = repo.find_tags(profile_uuid).await?;
+    // The outgoing profile cleans up before the new one takes over
+    let repo = ProfileRepository::new(db.pool());
+    if let Some(previous) =
+      Self::previously_active(&repo, user_id.as_uuid(), profile_id.as_uuid()).await?
+    {
+      let phase = DeactivationService::run(db, previous.id).await?;
+      if !phase.actions.is_empty() {
+        tracing::info!(
+          profile_id = %previous.id,
+          success = phase.success,
+          "Ran deactivation actions for {}",
+          previous.name
+        );
+      }
+      metadata = phase.attach_to_metadata(metadata);
+    }
 
-    // Get counts for related entities
-    let monitor_count = MonitorRepository::new(db.pool())
-      .count_by_profile_id(profile_uuid)
-      .await?;
-    let app_count = AppRepository::new(db.pool())
-      .count_by_profile_id(profile_uuid)
-      .await?;
-    let browser_tab_count = BrowserTabRepository::new(db.pool())
-      .count_by_profile_id(profile_uuid)
-      .await?;
+    Self::activate_profile_with(
+      &repo,
+      &*audit_store(db),
+      profile_id,
+      user_id,
+      source,
+      metadata,
+    )
+    .await
+  }
 
-    // Log the profile activation
-    let audit_repo = AuditRepository::new(db.pool());
-    let _ = audit_repo
+  /// Activate a profile against the given stores, recording `metadata` with the activation
+  pub async fn activate_profile_with(
+    profiles: &dyn ProfileStore,
+    audit: &dyn AuditStore,
+    profile_id: ProfileId,
+    user_id: UserId,
+    source: ActivationSource,
+    metadata: Option<serde_json::Value>,
+  ) -> Result<ProfileDto> {
+    let profile_uuid = profile_id.as_uuid();
+    let user_uuid = user_id.as_uuid();
+
+    let previous = Self::previously_active(profiles, user_uuid, profile_uuid).await?;
+    let activated = profiles.activate(profile_uuid, user_uuid).await?;
+    let metadata = match activated.low_power_battery_threshold {
+      Some(threshold) => note_low_power(metadata, threshold),
+      None => metadata,
+    };
+    let tags = profiles.find_tags(profile_uuid).await?;
+    let counts = profiles.count_children(profile_uuid).await?;
+
+    let _ = audit
       .record_profile_activation(
         user_uuid,
         profile_uuid,
-        None,     // session_id
-        "manual", // activation_source
-        None,     // previous_profile_id
-        Some(monitor_count as i32),
-        Some(monitor_count as i32), // assuming all applied
-        Some(app_count as i32),
-        Some(app_count as i32), // assuming all launched
-        Some(0),                // apps_failed
-        Some(browser_tab_count as i32),
-        Some(browser_tab_count as i32), // assuming all opened
-        Some(0),                        // windows_restored
-        None,                           // duration_ms
-        true,                           // success
-        None,                           // error_message
-        None,                           // metadata
+        None, // session_id
+        source,
+        previous.map(|profile| profile.id),
+        Some(counts.monitors as i32),
+        Some(counts.monitors as i32), // assuming all applied
+        Some(counts.apps as i32),
+        Some(counts.apps as i32), // assuming all launched
+        Some(0),                  // apps_failed
+        Some(counts.browser_tabs as i32),
+        Some(counts.browser_tabs as i32), // assuming all opened
+        Some(0),                          // windows_restored
+        None,                             // duration_ms
+        true,                             // success
+        None,                             // error_message
+        metadata,
       )
       .await;
 
@@ -316,8 +401,7 @@ impl ProfileService {
     METRICS.record_profile_activated();
 
     // Log the profile activation activity
-    let audit_repo = AuditRepository::new(db.pool());
-    let _ = audit_repo
+    let _ = audit
       .log_activity(
         user_uuid,
         None, // session_id
@@ -326,11 +410,11 @@ impl ProfileService {
         Some(activated.id),
         Some(&activated.name),
         Some(serde_json::json!({
-          "monitor_count": monitor_count,
-          "app_count": app_count,
-          "browser_tab_count": browser_tab_count
+          "monitor_count": counts.monitors,
+          "app_count": counts.apps,
+          "browser_tab_count": counts.browser_tabs
         })),
-        "success",
+        ActivityStatus::Success,
         None,
         None,
       )
@@ -339,17 +423,32 @@ impl ProfileService {
     Ok(ProfileDto::from_entity_with_counts(
       activated,
       tags,
-      monitor_count,
-      app_count,
-      browser_tab_count,
+      counts.monitors,
+      counts.apps,
+      counts.browser_tabs,
     ))
   }
 
+  /// The user's currently active profile, unless it is `profile_id` itself
+  async fn previously_active(
+    profiles: &dyn ProfileStore,
+    user_id: Uuid,
+    profile_id: Uuid,
+  ) -> Result<Option<ProfileEntity>> {
+    Ok(
+      profiles
+        .find_by_user_id(user_id)
+        .await?
+        .into_iter()
+        .find(|profile| profile.is_active && profile.id != profile_id),
+    )
+  }
+
   /// Duplicate a profile
   pub async fn duplicate_profile(
     db: &Database,
-    profile_id: &str,
-    user_id: &str,
+    profile_id: ProfileId,
+    user_id: UserId,
   ) -> Result<ProfileDto> {
     let source = Self::get_profile(db, profile_id).await?;
     let new_name = format!("{} (Copy)", source.name);
@@ -362,6 +461,7 @@ impl ProfileService {
         description: source.description,
         profile_type: source.profile_type,
         tags: Some(source.tags),
+        notes: source.notes,
       },
     )
     .await?;
@@ -369,18 +469,20 @@ impl ProfileService {
     // Copy monitors
     let monitors = MonitorService::get_monitors(db, profile_id).await?;
     for monitor in monitors {
-      MonitorService::create_monitor(
+      crate::services::MonitorService::create_monitor(
         db,
-        &new_profile.id,
-        monitor.name,
-        monitor.resolution,
-        monitor.orientation,
-        monitor.is_primary,
-        monitor.x,
-        monitor.y,
-        monitor.width,
-        monitor.height,
-        monitor.display_index,
+        &new_profile.id.to_string(),
+        CreateMonitorRequest {
+          name: monitor.name,
+          resolution: monitor.resolution,
+          orientation: monitor.orientation,
+          is_primary: monitor.is_primary,
+          x: monitor.x,
+          y: monitor.y,
+          width: monitor.width,
+          height: monitor.height,
+          display_index: monitor.display_index,
+        },
       )
       .await?;
     }
@@ -391,159 +493,286 @@ impl ProfileService {
         "Profile duplicated"
     );
 
-    Self::get_profile(db, &new_profile.id).await
+    Self::get_profile(db, new_profile.id).await
   }
 
   /// Get favorite profiles for a user
-  pub async fn get_favorite_profiles(db: &Database, user_id: &str) -> Result<Vec<ProfileDto>> {
-    let user_uuid = parse_uuid(user_id)?;
-    let repo = ProfileRepository::new(db.pool());
-
-    let profiles = repo.find_favorites(user_uuid).await?;
-    let mut result = Vec::with_capacity(profiles.len());
-
-    for profile in profiles {
-      let tags = repo.find_tags(profile.id).await?;
-
-      let monitor_count = MonitorRepository::new(db.pool())
-        .count_by_profile_id(profile.id)
-        .await?;
-      let app_count = AppRepository::new(db.pool())
-        .count_by_profile_id(profile.id)
-        .await?;
-      let browser_tab_count = BrowserTabRepository::new(db.pool())
-        .count_by_profile_id(profile.id)
-        .await?;
-
-      result.push(ProfileDto::from_entity_with_counts(
-        profile,
-        tags,
-        monitor_count,
-        app_count,
-        browser_tab_count,
-      ));
-    }
+  pub async fn get_favorite_profiles(db: &Database, user_id: UserId) -> Result<Vec<ProfileDto>> {
+    Self::get_favorite_profiles_with(&ProfileRepository::new(db.pool()), user_id).await
+  }
 
-    Ok(result)
+  /// Get favorite profiles for a user from the given store
+  pub async fn get_favorite_profiles_with(
+    profiles: &dyn ProfileStore,
+    user_id: UserId,
+  ) -> Result<Vec<ProfileDto>> {
+    let user_uuid = user_id.as_uuid();
+    let entities = profiles.find_favorites(user_uuid).await?;
+    Self::to_dtos(profiles, entities).await
   }
 
   /// Get most used profiles for a user
   pub async fn get_most_used_profiles(
     db: &Database,
-    user_id: &str,
+    user_id: UserId,
     limit: i64,
   ) -> Result<Vec<ProfileDto>> {
-    let user_uuid = parse_uuid(user_id)?;
-    let repo = ProfileRepository::new(db.pool());
-
-    let profiles = repo.find_most_used(user_uuid, limit).await?;
-    let mut result = Vec::with_capacity(profiles.len());
-
-    for profile in profiles {
-      let tags = repo.find_tags(profile.id).await?;
-
-      let monitor_count = MonitorRepository::new(db.pool())
-        .count_by_profile_id(profile.id)
-        .await?;
-      let app_count = AppRepository::new(db.pool())
-        .count_by_profile_id(profile.id)
-        .await?;
-      let browser_tab_count = BrowserTabRepository::new(db.pool())
-        .count_by_profile_id(profile.id)
-        .await?;
-
-      result.push(ProfileDto::from_entity_with_counts(
-        profile,
-        tags,
-        monitor_count,
-        app_count,
-        browser_tab_count,
-      ));
-    }
+    Self::get_most_used_profiles_with(&ProfileRepository::new(db.pool()), user_id, limit).await
+  }
 
-    Ok(result)
+  /// Get most used profiles for a user from the given store
+  pub async fn get_most_used_profiles_with(
+    profiles: &dyn ProfileStore,
+    user_id: UserId,
+    limit: i64,
+  ) -> Result<Vec<ProfileDto>> {
+    let user_uuid = user_id.as_uuid();
+    let entities = profiles.find_most_used(user_uuid, limit).await?;
+    Self::to_dtos(profiles, entities).await
   }
 
   /// Set favorite status for a profile
   pub async fn set_favorite(
     db: &Database,
-    profile_id: &str,
+    profile_id: ProfileId,
     is_favorite: bool,
   ) -> Result<ProfileDto> {
-    let profile_uuid = parse_uuid(profile_id)?;
+    let before = Self::get_profile(db, profile_id).await?;
+    let updated =
+      Self::set_favorite_with(&ProfileRepository::new(db.pool()), profile_id, is_favorite).await?;
+    let subject = ChangeSubject::profile(profile_id.as_uuid());
+    ChangeLog::record_update(db, subject, &before, &updated).await;
+    Ok(updated)
+  }
+
+  /// Set favorite status for a profile in the given store
+  pub async fn set_favorite_with(
+    profiles: &dyn ProfileStore,
+    profile_id: ProfileId,
+    is_favorite: bool,
+  ) -> Result<ProfileDto> {
+    let profile_uuid = profile_id.as_uuid();
+    let updated = profiles.set_favorite(profile_uuid, is_favorite).await?;
+
+    tracing::info!(profile_id = %profile_id, is_favorite = %is_favorite, "Profile favorite status updated");
+
+    Self::to_dto(profiles, updated).await
+  }
+
+  /// Skip launching apps and opening tabs when on battery below `threshold` percent;
+  /// `None` always runs the profile in full
+  pub async fn set_low_power_threshold(
+    db: &Database,
+    profile_id: ProfileId,
+    threshold: Option<i32>,
+  ) -> Result<ProfileDto> {
+    if threshold.is_some_and(|t| !(1..=100).contains(&t)) {
+      return Err(SmoothieError::ValidationError(
+        "Low-power threshold must be between 1 and 100 percent".into(),
+      ));
+    }
+    let profile_uuid = profile_id.as_uuid();
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
     let repo = ProfileRepository::new(db.pool());
+    let before = Self::get_profile(db, profile_id).await?;
+    let updated = repo
+      .set_low_power_threshold(profile_uuid, threshold)
+      .await?;
 
-    let updated = repo.set_favorite(profile_uuid, is_favorite).await?;
-    let tags = repo.find_tags(profile_uuid).await?;
+    tracing::info!(profile_id = %profile_id, ?threshold, "Profile low-power threshold updated");
 
-    let monitor_count = MonitorRepository::new(db.pool())
-      .count_by_profile_id(profile_uuid)
+    let updated = Self::to_dto(&repo, updated).await?;
+    ChangeLog::record_update(db, ChangeSubject::profile(profile_uuid), &before, &updated).await;
+    Ok(updated)
+  }
+
+  /// Whether Sidecar, AirPlay and virtual displays count towards the profile's
+  /// monitor binding and get its layout applied
+  pub async fn set_include_virtual_displays(
+    db: &Database,
+    profile_id: ProfileId,
+    include: bool,
+  ) -> Result<ProfileDto> {
+    ProfileLock::ensure_unlocked(db, profile_id.as_uuid()).await?;
+    let repo = ProfileRepository::new(db.pool());
+    let before = Self::get_profile(db, profile_id).await?;
+    let updated = repo
+      .set_include_virtual_displays(profile_id.as_uuid(), include)
       .await?;
-    let app_count = AppRepository::new(db.pool())
-      .count_by_profile_id(profile_uuid)
+
+    tracing::info!(profile_id = %profile_id, include, "Profile virtual display flag updated");
+
+    let updated = Self::to_dto(&repo, updated).await?;
+    let subject = ChangeSubject::profile(profile_id.as_uuid());
+    ChangeLog::record_update(db, subject, &before, &updated).await;
+    Ok(updated)
+  }
+
+  /// Keep a profile on this machine only; cloud sync skips it under every scope
+  pub async fn set_local_only(
+    db: &Database,
+    profile_id: ProfileId,
+    local_only: bool,
+  ) -> Result<ProfileDto> {
+    ProfileLock::ensure_unlocked(db, profile_id.as_uuid()).await?;
+    let repo = ProfileRepository::new(db.pool());
+    let before = Self::get_profile(db, profile_id).await?;
+    let updated = repo
+      .set_local_only(profile_id.as_uuid(), local_only)
       .await?;
-    let browser_tab_count = BrowserTabRepository::new(db.pool())
-      .count_by_profile_id(profile_uuid)
+
+    tracing::info!(profile_id = %profile_id, local_only, "Profile local-only flag updated");
+
+    let updated = Self::to_dto(&repo, updated).await?;
+    let subject = ChangeSubject::profile(profile_id.as_uuid());
+    ChangeLog::record_update(db, subject, &before, &updated).await;
+    Ok(updated)
+  }
+
+  /// Bind a profile to a number of monitors and/or an exact display setup (see
+  /// `profile_resolver::monitor_signature`) so it is picked automatically when
+  /// that setup is connected; `None` for both clears the binding
+  pub async fn set_monitor_binding(
+    db: &Database,
+    profile_id: ProfileId,
+    required_monitor_count: Option<i32>,
+    monitor_signature: Option<String>,
+  ) -> Result<ProfileDto> {
+    if required_monitor_count.is_some_and(|count| !(1..=MAX_MONITORS).contains(&count)) {
+      return Err(SmoothieError::ValidationError(format!(
+        "Monitor count must be between 1 and {}",
+        MAX_MONITORS
+      )));
+    }
+    let monitor_signature = monitor_signature
+      .map(|s| s.trim().to_string())
+      .filter(|s| !s.is_empty());
+    ProfileLock::ensure_unlocked(db, profile_id.as_uuid()).await?;
+    let repo = ProfileRepository::new(db.pool());
+    let before = Self::get_profile(db, profile_id).await?;
+    let updated = repo
+      .set_monitor_binding(
+        profile_id.as_uuid(),
+        required_monitor_count,
+        monitor_signature.as_deref(),
+      )
       .await?;
 
-    tracing::info!(profile_id = %profile_id, is_favorite = %is_favorite, "Profile favorite status updated");
+    tracing::info!(
+      profile_id = %profile_id,
+      ?required_monitor_count,
+      bound_to_signature = monitor_signature.is_some(),
+      "Profile monitor binding updated"
+    );
 
-    Ok(ProfileDto::from_entity_with_counts(
-      updated,
-      tags,
-      monitor_count,
-      app_count,
-      browser_tab_count,
-    ))
+    let updated = Self::to_dto(&repo, updated).await?;
+    let subject = ChangeSubject::profile(profile_id.as_uuid());
+    ChangeLog::record_update(db, subject, &before, &updated).await;
+    Ok(updated)
+  }
+
+  /// Lock a profile against edits, or unlock it. Not a profile edit itself, so
+  /// it isn't kept in the change history.
+  pub async fn set_locked(
+    db: &Database,
+    profile_id: ProfileId,
+    locked: bool,
+  ) -> Result<ProfileDto> {
+    let repo = ProfileRepository::new(db.pool());
+    let updated = repo.set_locked(profile_id.as_uuid(), locked).await?;
+
+    tracing::info!(profile_id = %profile_id, locked, "Profile lock updated");
+
+    Self::to_dto(&repo, updated).await
+  }
+
+  /// The current power state if the profile should skip heavy steps now, else `None`
+  pub async fn low_power_state(db: &Database, profile_id: ProfileId) -> Result<Option<PowerState>> {
+    let profile_uuid = profile_id.as_uuid();
+    let Some(threshold) = ProfileRepository::new(db.pool())
+      .find_by_id(profile_uuid)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?
+      .low_power_battery_threshold
+    else {
+      return Ok(None);
+    };
+
+    let power = tokio::task::spawn_blocking(EnvironmentService::get_power_state)
+      .await
+      .unwrap_or_default();
+    Ok(power.is_low(threshold).then_some(power))
   }
 
   /// Update a profile with extended fields (v4)
   pub async fn update_profile_extended(
     db: &Database,
-    profile_id: &str,
-    name: Option<String>,
-    description: Option<String>,
-    is_favorite: Option<bool>,
-    color: Option<String>,
-    icon: Option<String>,
-    sort_order: Option<i32>,
+    profile_id: ProfileId,
+    req: UpdateProfileRequest,
   ) -> Result<ProfileDto> {
-    let profile_uuid = parse_uuid(profile_id)?;
+    validation::validate(&req)?;
+    let profile_uuid = profile_id.as_uuid();
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
     let repo = ProfileRepository::new(db.pool());
+    let before = Self::get_profile(db, profile_id).await?;
 
     let updated = repo
       .update_extended(
         profile_uuid,
-        name.as_deref(),
-        description.as_deref(),
-        is_favorite,
-        color.as_deref(),
-        icon.as_deref(),
-        sort_order,
+        req.name.as_deref(),
+        req.description.as_deref(),
+        req.is_favorite,
+        req.color.as_deref(),
+        req.icon.as_deref(),
+        req.sort_order,
       )
       .await?;
-    let tags = repo.find_tags(profile_uuid).await?;
-
-    let monitor_count = MonitorRepository::new(db.pool())
-      .count_by_profile_id(profile_uuid)
-      .await?;
-    let app_count = AppRepository::new(db.pool())
-      .count_by_profile_id(profile_uuid)
-      .await?;
-    let browser_tab_count = BrowserTabRepository::new(db.pool())
-      .count_by_profile_id(profile_uuid)
-      .await?;
+    if let Some(tags) = &req.tags {
+      repo.set_tags(profile_uuid, tags).await?;
+    }
+    let updated = match &req.notes {
+      Some(notes) => {
+        let notes = markdown::sanitize_notes(notes);
+        repo.set_notes(profile_uuid, notes.as_deref()).await?;
+        repo
+          .find_by_id(profile_uuid)
+          .await?
+          .ok_or_else(|| SmoothieError::NotFound("Profile not found".into()))?
+      }
+      None => updated,
+    };
 
     tracing::info!(profile_id = %profile_id, "Profile updated with extended fields");
 
+    let updated = Self::to_dto(&repo, updated).await?;
+    ChangeLog::record_update(db, ChangeSubject::profile(profile_uuid), &before, &updated).await;
+    Ok(updated)
+  }
+
+  /// Build the DTO for a profile, loading its tags and related entity counts
+  async fn to_dto(profiles: &dyn ProfileStore, profile: ProfileEntity) -> Result<ProfileDto> {
+    let tags = profiles.find_tags(profile.id).await?;
+    let counts = profiles.count_children(profile.id).await?;
+
     Ok(ProfileDto::from_entity_with_counts(
-      updated,
+      profile,
       tags,
-      monitor_count,
-      app_count,
-      browser_tab_count,
+      counts.monitors,
+      counts.apps,
+      counts.browser_tabs,
     ))
   }
+
+  async fn to_dtos(
+    profiles: &dyn ProfileStore,
+    entities: Vec<ProfileEntity>,
+  ) -> Result<Vec<ProfileDto>> {
+    let mut result = Vec::with_capacity(entities.len());
+    for profile in entities {
+      result.push(Self::to_dto(profiles, profile).await?);
+    }
+    Ok(result)
+  }
 }
 
 // Helper services
@@ -552,43 +781,8 @@ pub struct AppService;
 pub struct BrowserService;
 
 impl MonitorService {
-  #[allow(clippy::too_many_arguments)]
-  pub async fn create_monitor(
-    db: &Database,
-    profile_id: &str,
-    name: String,
-    resolution: String,
-    orientation: String,
-    is_primary: bool,
-    x: i32,
-    y: i32,
-    width: i32,
-    height: i32,
-    display_index: i32,
-  ) -> Result<MonitorDto> {
-    let profile_uuid = parse_uuid(profile_id)?;
-    let repo = MonitorRepository::new(db.pool());
-
-    let entity = repo
-      .create(
-        profile_uuid,
-        &name,
-        &resolution,
-        &orientation,
-        is_primary,
-        x,
-        y,
-        width,
-        height,
-        display_index,
-      )
-      .await?;
-
-    Ok(MonitorDto::from(entity))
-  }
-
-  pub async fn get_monitors(db: &Database, profile_id: &str) -> Result<Vec<MonitorDto>> {
-    let profile_uuid = parse_uuid(profile_id)?;
+  pub async fn get_monitors(db: &Database, profile_id: ProfileId) -> Result<Vec<MonitorDto>> {
+    let profile_uuid = profile_id.as_uuid();
     let repo = MonitorRepository::new(db.pool());
 
     let monitors = repo.find_by_profile_id(profile_uuid).await?;
@@ -597,8 +791,8 @@ impl MonitorService {
 }
 
 impl AppService {
-  pub async fn get_apps(db: &Database, profile_id: &str) -> Result<Vec<AppDto>> {
-    let profile_uuid = parse_uuid(profile_id)?;
+  pub async fn get_apps(db: &Database, profile_id: ProfileId) -> Result<Vec<AppDto>> {
+    let profile_uuid = profile_id.as_uuid();
     let repo = AppRepository::new(db.pool());
 
     let apps = repo.find_by_profile_id(profile_uuid).await?;
@@ -607,8 +801,11 @@ impl AppService {
 }
 
 impl BrowserService {
-  pub async fn get_browser_tabs(db: &Database, profile_id: &str) -> Result<Vec<BrowserTabDto>> {
-    let profile_uuid = parse_uuid(profile_id)?;
+  pub async fn get_browser_tabs(
+    db: &Database,
+    profile_id: ProfileId,
+  ) -> Result<Vec<BrowserTabDto>> {
+    let profile_uuid = profile_id.as_uuid();
     let repo = BrowserTabRepository::new(db.pool());
 
     let tabs = repo.find_by_profile_id(profile_uuid).await?;
@@ -616,7 +813,132 @@ impl BrowserService {
   }
 }
 
-/// Parse a string as UUID
-fn parse_uuid(s: &str) -> Result<Uuid> {
-  Uuid::parse_str(s).map_err(|_| SmoothieError::ValidationError(format!("Invalid UUID: {}", s)))
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::ProfileType;
+  use crate::repositories::memory::{InMemoryAuditStore, InMemoryProfileStore};
+
+  fn user_id() -> UserId {
+    Uuid::from_u128(1).into()
+  }
+
+  fn request(name: &str, tags: Option<Vec<&str>>) -> CreateProfileRequest {
+    CreateProfileRequest {
+      name: name.to_string(),
+      description: Some("Deep work setup".to_string()),
+      profile_type: ProfileType::Work,
+      tags: tags.map(|tags| tags.into_iter().map(str::to_string).collect()),
+      notes: None,
+    }
+  }
+
+  #[tokio::test]
+  async fn test_create_profile_adds_tags_and_logs_activity() {
+    let profiles = InMemoryProfileStore::default();
+    let audit = InMemoryAuditStore::default();
+
+    let profile = ProfileService::create_profile_with(
+      &profiles,
+      &audit,
+      user_id(),
+      CreateProfileRequest {
+        notes: Some("For **client A**<script>x</script>".into()),
+        ..request("Focus", Some(vec!["coding", "coding", "music"]))
+      },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(profile.name, "Focus");
+    assert_eq!(profile.tags, vec!["coding", "music"]);
+    assert_eq!(profile.notes.as_deref(), Some("For **client A**x"));
+
+    let activities = audit.activities.lock();
+    assert_eq!(activities.len(), 1);
+    assert_eq!(activities[0].action, "profile_created");
+    assert_eq!(activities[0].entity_id, Some(profile.id.as_uuid()));
+  }
+
+  #[tokio::test]
+  async fn test_activate_profile_records_activation() {
+    let profiles = InMemoryProfileStore::default();
+    let audit = InMemoryAuditStore::default();
+    let first =
+      ProfileService::create_profile_with(&profiles, &audit, user_id(), request("A", None))
+        .await
+        .unwrap();
+    let second =
+      ProfileService::create_profile_with(&profiles, &audit, user_id(), request("B", None))
+        .await
+        .unwrap();
+    profiles.set_counts(second.id.as_uuid(), 2, 3, 4);
+
+    ProfileService::activate_profile_with(
+      &profiles,
+      &audit,
+      first.id,
+      user_id(),
+      ActivationSource::Manual,
+      None,
+    )
+    .await
+    .unwrap();
+    let activated = ProfileService::activate_profile_with(
+      &profiles,
+      &audit,
+      second.id,
+      user_id(),
+      ActivationSource::Automation,
+      Some(serde_json::json!({ "trigger": "test" })),
+    )
+    .await
+    .unwrap();
+
+    assert!(activated.is_active);
+    assert_eq!(activated.monitor_count, 2);
+    assert!(
+      !ProfileService::get_profile_with(&profiles, first.id)
+        .await
+        .unwrap()
+        .is_active
+    );
+
+    let activations = audit.activations.lock();
+    assert_eq!(activations.len(), 2);
+    assert_eq!(activations[0].previous_profile_id, None);
+    assert_eq!(activations[1].previous_profile_id, Some(first.id.as_uuid()));
+    assert_eq!(activations[1].apps_launched, Some(3));
+    assert_eq!(activations[1].tabs_opened, Some(4));
+    assert_eq!(
+      activations[1].activation_source,
+      ActivationSource::Automation
+    );
+    assert_eq!(
+      activations[1].metadata,
+      Some(serde_json::json!({ "trigger": "test" }))
+    );
+  }
+
+  #[tokio::test]
+  async fn test_missing_profile_is_not_found() {
+    let profiles = InMemoryProfileStore::default();
+    let audit = InMemoryAuditStore::default();
+    let missing = ProfileId::from(Uuid::new_v4());
+
+    let result = ProfileService::get_profile_with(&profiles, missing).await;
+    assert!(matches!(result, Err(SmoothieError::NotFound(_))));
+
+    let result = ProfileService::activate_profile_with(
+      &profiles,
+      &audit,
+      missing,
+      user_id(),
+      ActivationSource::Manual,
+      None,
+    )
+    .await;
+    assert!(matches!(result, Err(SmoothieError::NotFound(_))));
+    assert!(audit.activations.lock().is_empty());
+  }
 }
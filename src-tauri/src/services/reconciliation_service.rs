@@ -0,0 +1,248 @@
+//! Reconciliation Service
+//!
+//! When the app is killed or the machine loses power, rows that are normally
+//! closed on the way out stay open: sessions without an end, activations that
+//! never completed, and an older profile still flagged active next to the one
+//! switched to last. Half-written temp files from `paths::write_atomically`
+//! are left in the app data directory too. At startup everything older than
+//! this launch is repaired, and whatever was fixed is written to the system
+//! events so an unclean shutdown leaves a trace.
+
+use crate::{
+  db::Database,
+  error::Result,
+  models::Severity,
+  repositories::{AuditRepository, ProfileRepository},
+  services::{activation_journal::ActivationJournalService, AUDIT_SERVICE},
+  utils::paths,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// What startup reconciliation repaired
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconciliationReport {
+  pub sessions_ended: u64,
+  pub activations_failed: u64,
+  pub profiles_deactivated: Vec<uuid::Uuid>,
+  pub temp_files_removed: Vec<PathBuf>,
+  /// Left for the user to resume or roll back, not repaired here
+  pub interrupted_activations: usize,
+}
+
+impl ReconciliationReport {
+  fn is_clean(&self) -> bool {
+    self.sessions_ended == 0
+      && self.activations_failed == 0
+      && self.profiles_deactivated.is_empty()
+      && self.temp_files_removed.is_empty()
+      && self.interrupted_activations == 0
+  }
+
+  fn summary(&self) -> String {
+    let mut fixed = Vec::new();
+    if self.sessions_ended > 0 {
+      fixed.push(format!("ended {} abandoned sessions", self.sessions_ended));
+    }
+    if self.activations_failed > 0 {
+      fixed.push(format!(
+        "closed {} unfinished activations",
+        self.activations_failed
+      ));
+    }
+    if !self.profiles_deactivated.is_empty() {
+      fixed.push(format!(
+        "cleared {} stale active profiles",
+        self.profiles_deactivated.len()
+      ));
+    }
+    if !self.temp_files_removed.is_empty() {
+      fixed.push(format!(
+        "removed {} leftover temp files",
+        self.temp_files_removed.len()
+      ));
+    }
+    if self.interrupted_activations > 0 {
+      fixed.push(format!(
+        "found {} interrupted activations to resume or roll back",
+        self.interrupted_activations
+      ));
+    }
+    format!("Startup reconciliation {}", fixed.join(", "))
+  }
+}
+
+/// `write_atomically` temp files in `dir` and its subdirectories last written
+/// before `before`
+async fn stale_temp_files(dir: &Path, before: SystemTime) -> Vec<PathBuf> {
+  let mut found = Vec::new();
+  let mut dirs = vec![dir.to_path_buf()];
+  while let Some(dir) = dirs.pop() {
+    let Ok(mut entries) = tokio::fs::read_dir(&dir).await else {
+      continue;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+      let Ok(meta) = entry.metadata().await else {
+        continue;
+      };
+      let path = entry.path();
+      if meta.is_dir() {
+        dirs.push(path);
+      } else if path.extension().is_some_and(|e| e == "tmp")
+        && meta.modified().is_ok_and(|m| m < before)
+      {
+        found.push(path);
+      }
+    }
+  }
+  found.sort();
+  found
+}
+
+pub struct ReconciliationService;
+
+impl ReconciliationService {
+  /// Repair what an earlier launch left open, in the background. The cutoff is
+  /// taken now so the session this launch is about to start isn't touched.
+  pub fn spawn(db: Arc<Database>) {
+    let booted_at = Utc::now();
+    tokio::spawn(async move {
+      match Self::reconcile(&db, booted_at).await {
+        Ok(report) if !report.is_clean() => {
+          let message = report.summary();
+          tracing::warn!("{}", message);
+          let _ = AUDIT_SERVICE
+            .log_system_event(
+              &db,
+              "startup_reconciliation",
+              Severity::Warning,
+              "ReconciliationService",
+              &message,
+              Some(json!(report)),
+              None,
+            )
+            .await;
+        }
+        Ok(_) => tracing::debug!("Startup reconciliation found nothing to repair"),
+        Err(e) => tracing::warn!("Startup reconciliation failed: {}", e),
+      }
+    });
+  }
+
+  /// Repair state left before `booted_at`
+  pub async fn reconcile(db: &Database, booted_at: DateTime<Utc>) -> Result<ReconciliationReport> {
+    let audit = AuditRepository::new(db.pool());
+    let mut report = ReconciliationReport {
+      sessions_ended: audit.end_abandoned_sessions(booted_at).await?,
+      activations_failed: audit.fail_unfinished_activations(booted_at).await?,
+      profiles_deactivated: ProfileRepository::new(db.pool())
+        .deactivate_superseded()
+        .await?,
+      ..Default::default()
+    };
+
+    for path in stale_temp_files(&paths::app_data_dir(), booted_at.into()).await {
+      match tokio::fs::remove_file(&path).await {
+        Ok(()) => report.temp_files_removed.push(path),
+        Err(e) => tracing::warn!(path = %path.display(), "Failed to remove temp file: {}", e),
+      }
+    }
+
+    report.interrupted_activations = ActivationJournalService::list_interrupted(db)
+      .await
+      .map(|interrupted| interrupted.len())
+      .unwrap_or_default();
+
+    Ok(report)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use uuid::Uuid;
+
+  #[tokio::test]
+  async fn test_reconcile_repairs_rows_left_by_an_earlier_launch() {
+    let test_db = test_db_or_skip!();
+    let pool = test_db.pool();
+    let user = test_db.seed_user().await;
+    let profile = |name: &'static str, minutes_ago: i32| {
+      sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO profiles (user_id, name, type, is_active, last_activated_at)
+         VALUES ($1, $2, 'work', true, NOW() - $3 * INTERVAL '1 minute') RETURNING id",
+      )
+      .bind(user)
+      .bind(name)
+      .bind(minutes_ago)
+      .fetch_one(pool)
+    };
+    let older = profile("Gaming", 60).await.unwrap();
+    let current = profile("Work", 5).await.unwrap();
+
+    sqlx::query(
+      "INSERT INTO sessions (user_id, started_at, last_activity_at)
+       VALUES ($1, NOW() - INTERVAL '2 hours', NOW() - INTERVAL '1 hour')",
+    )
+    .bind(user)
+    .execute(pool)
+    .await
+    .unwrap();
+    sqlx::query(
+      "INSERT INTO profile_activations (user_id, profile_id, activation_source, started_at)
+       VALUES ($1, $2, 'manual', NOW() - INTERVAL '5 minutes')",
+    )
+    .bind(user)
+    .bind(current)
+    .execute(pool)
+    .await
+    .unwrap();
+
+    let booted_at = Utc::now();
+    // Started by this launch, so left alone
+    sqlx::query("INSERT INTO sessions (user_id) VALUES ($1)")
+      .bind(user)
+      .execute(pool)
+      .await
+      .unwrap();
+
+    let report = ReconciliationService::reconcile(&test_db.db, booted_at)
+      .await
+      .unwrap();
+    assert_eq!(report.sessions_ended, 1);
+    assert_eq!(report.activations_failed, 1);
+    assert_eq!(report.profiles_deactivated, vec![older]);
+
+    let (open_sessions,): (i64,) =
+      sqlx::query_as("SELECT COUNT(*) FROM sessions WHERE ended_at IS NULL")
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert_eq!(open_sessions, 1);
+    assert_eq!(
+      ProfileRepository::new(pool)
+        .find_active_id(user)
+        .await
+        .unwrap(),
+      Some(current)
+    );
+
+    let again = ReconciliationService::reconcile(&test_db.db, booted_at)
+      .await
+      .unwrap();
+    assert_eq!(
+      (
+        again.sessions_ended,
+        again.activations_failed,
+        again.profiles_deactivated.len()
+      ),
+      (0, 0, 0)
+    );
+  }
+}
@@ -0,0 +1,211 @@
+//! Resource Guard
+//!
+//! Samples the power source, load average and memory pressure every half
+//! minute. While the Mac runs on battery the background pollers - display,
+//! device, focus and file watchers and the installed-app rescan - wait twice
+//! as long between passes, and four times as long while the system is busy
+//! or short on memory. Enforcement loops (blocked apps, focus sessions) keep
+//! their pace, since users notice a blocked app staying open.
+
+use crate::services::EnvironmentService;
+use crate::state::AppState;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::process::Command;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// One-minute load average per core above which the system counts as busy
+const HIGH_LOAD_PER_CORE: f64 = 0.9;
+
+const BATTERY_FACTOR: u32 = 2;
+const BUSY_FACTOR: u32 = 4;
+
+static FACTOR: AtomicU32 = AtomicU32::new(1);
+static STATUS: OnceLock<RwLock<ThrottleStatus>> = OnceLock::new();
+
+fn status_lock() -> &'static RwLock<ThrottleStatus> {
+  STATUS.get_or_init(|| RwLock::new(ThrottleStatus::default()))
+}
+
+/// How far the background pollers are slowed down, for the health report
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThrottleStatus {
+  /// Multiplier on every throttled polling interval; 1 when not throttled
+  pub factor: u32,
+  pub on_battery: bool,
+  pub load_per_core: Option<f64>,
+  pub memory_pressure: bool,
+  /// When the current factor took effect
+  pub since: Option<DateTime<Utc>>,
+  pub sampled_at: Option<DateTime<Utc>>,
+}
+
+impl Default for ThrottleStatus {
+  fn default() -> Self {
+    Self {
+      factor: 1,
+      on_battery: false,
+      load_per_core: None,
+      memory_pressure: false,
+      since: None,
+      sampled_at: None,
+    }
+  }
+}
+
+fn throttle_factor(on_battery: bool, load_per_core: Option<f64>, memory_pressure: bool) -> u32 {
+  let busy = memory_pressure || load_per_core.is_some_and(|load| load > HIGH_LOAD_PER_CORE);
+  if busy {
+    BUSY_FACTOR
+  } else if on_battery {
+    BATTERY_FACTOR
+  } else {
+    1
+  }
+}
+
+/// The one-minute figure from `sysctl vm.loadavg` ("{ 1.52 1.61 1.70 }") or
+/// /proc/loadavg ("0.52 0.58 0.59 1/123 4567")
+fn parse_load_average(output: &str) -> Option<f64> {
+  output
+    .trim_start_matches(|c: char| c == '{' || c.is_whitespace())
+    .split_whitespace()
+    .next()?
+    .parse()
+    .ok()
+}
+
+fn load_per_core() -> Option<f64> {
+  let output = if cfg!(target_os = "macos") {
+    let output = Command::new("sysctl")
+      .args(["-n", "vm.loadavg"])
+      .output()
+      .ok()?;
+    String::from_utf8_lossy(&output.stdout).into_owned()
+  } else {
+    std::fs::read_to_string("/proc/loadavg").ok()?
+  };
+  let cores = std::thread::available_parallelism().ok()?.get() as f64;
+  Some(parse_load_average(&output)? / cores)
+}
+
+/// macOS reports 1 for normal memory pressure, 2 for warning and 4 for critical
+fn memory_pressure() -> bool {
+  if !cfg!(target_os = "macos") {
+    return false;
+  }
+  Command::new("sysctl")
+    .args(["-n", "kern.memorystatus_vm_pressure_level"])
+    .output()
+    .ok()
+    .and_then(|output| {
+      String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<u32>()
+        .ok()
+    })
+    .is_some_and(|level| level >= 2)
+}
+
+/// Stands in for `tokio::time::Interval`: the first tick is immediate and
+/// later ones wait the base period times the current throttle factor
+pub struct ThrottledTicker {
+  period: Duration,
+  started: bool,
+}
+
+impl ThrottledTicker {
+  pub fn new(period: Duration) -> Self {
+    Self {
+      period,
+      started: false,
+    }
+  }
+
+  pub async fn tick(&mut self) {
+    if self.started {
+      tokio::time::sleep(ResourceGuard::scaled(self.period)).await;
+    }
+    self.started = true;
+  }
+}
+
+pub struct ResourceGuard;
+
+impl ResourceGuard {
+  pub fn status() -> ThrottleStatus {
+    status_lock().read().clone()
+  }
+
+  /// `period` stretched by the current throttle factor
+  pub fn scaled(period: Duration) -> Duration {
+    period * FACTOR.load(Ordering::Relaxed)
+  }
+
+  /// Keep the throttle factor up to date in the background
+  pub fn spawn(state: Arc<AppState>) {
+    state.tasks.spawn("resource_guard", || async {
+      let mut ticker = tokio::time::interval(SAMPLE_INTERVAL);
+      loop {
+        ticker.tick().await;
+        match tokio::task::spawn_blocking(|| {
+          let on_battery = EnvironmentService::get_power_state().source == "battery";
+          (on_battery, load_per_core(), memory_pressure())
+        })
+        .await
+        {
+          Ok((on_battery, load, pressure)) => Self::record(on_battery, load, pressure),
+          Err(e) => tracing::debug!("Resource sample failed: {}", e),
+        }
+      }
+    });
+  }
+
+  fn record(on_battery: bool, load_per_core: Option<f64>, memory_pressure: bool) {
+    let factor = throttle_factor(on_battery, load_per_core, memory_pressure);
+    let now = Utc::now();
+    let mut status = status_lock().write();
+    if factor != status.factor {
+      tracing::info!(
+        factor,
+        on_battery,
+        load_per_core,
+        memory_pressure,
+        "Background polling throttle changed"
+      );
+      status.since = Some(now);
+    }
+    status.factor = factor;
+    status.on_battery = on_battery;
+    status.load_per_core = load_per_core;
+    status.memory_pressure = memory_pressure;
+    status.sampled_at = Some(now);
+    FACTOR.store(factor, Ordering::Relaxed);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_busy_system_throttles_harder_than_battery() {
+    assert_eq!(throttle_factor(false, Some(0.5), false), 1);
+    assert_eq!(throttle_factor(true, Some(0.5), false), BATTERY_FACTOR);
+    assert_eq!(throttle_factor(false, Some(1.5), false), BUSY_FACTOR);
+    assert_eq!(throttle_factor(true, None, true), BUSY_FACTOR);
+
+    assert_eq!(parse_load_average("{ 1.52 1.61 1.70 }\n"), Some(1.52));
+    assert_eq!(
+      parse_load_average("0.52 0.58 0.59 1/123 4567\n"),
+      Some(0.52)
+    );
+    assert_eq!(parse_load_average(""), None);
+  }
+}
@@ -0,0 +1,399 @@
+// Automation rule actions - the ordered steps a rule runs when it fires
+//
+// A rule without stored actions keeps the original behaviour of activating its profile.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::{
+    entities::{AutomationRuleEntity, RuleActionEntity},
+    enums::{ActivationSource, RuleActionType},
+  },
+  services::{BrowserService, ChecklistService, ProfileService, SystemBackend},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use uuid::Uuid;
+
+/// Hooks run for 30 seconds unless configured otherwise
+const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 30;
+const MAX_HOOK_TIMEOUT_SECS: u64 = 300;
+/// Longest hook output kept in an action result
+const MAX_OUTPUT_CHARS: usize = 500;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ActivateProfileConfig {
+  /// Defaults to the rule's own profile
+  profile_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RunHookConfig {
+  command: String,
+  #[serde(default)]
+  args: Vec<String>,
+  timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SendNotificationConfig {
+  title: String,
+  #[serde(default)]
+  body: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SetFocusModeConfig {
+  /// Name of a Shortcuts.app shortcut that switches the Focus mode
+  shortcut: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct OpenUrlConfig {
+  url: String,
+  browser: Option<String>,
+}
+
+/// A validated action, ready to run
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleAction {
+  ActivateProfile {
+    profile_id: Option<Uuid>,
+  },
+  RunHook {
+    command: String,
+    args: Vec<String>,
+    timeout: Duration,
+  },
+  SendNotification {
+    title: String,
+    body: String,
+  },
+  SetFocusMode {
+    shortcut: String,
+  },
+  OpenUrl {
+    url: String,
+    browser: Option<String>,
+  },
+}
+
+/// How one action went when a rule fired
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionResult {
+  pub position: i32,
+  pub action_type: RuleActionType,
+  pub success: bool,
+  pub message: String,
+  pub duration_ms: i64,
+}
+
+fn parse_config<T: serde::de::DeserializeOwned>(
+  action_type: RuleActionType,
+  config: &Value,
+) -> Result<T> {
+  // Actions that need no settings may omit the config entirely
+  let config = if config.is_null() {
+    Value::Object(Default::default())
+  } else {
+    config.clone()
+  };
+  serde_json::from_value(config).map_err(|e| {
+    SmoothieError::ValidationError(format!("Invalid {} action config: {}", action_type, e))
+  })
+}
+
+fn require_non_empty(value: &str, field: &str, action_type: RuleActionType) -> Result<()> {
+  if value.trim().is_empty() {
+    return Err(SmoothieError::ValidationError(format!(
+      "{} action requires a non-empty \"{}\"",
+      action_type, field
+    )));
+  }
+  Ok(())
+}
+
+impl RuleAction {
+  /// Validate a stored or submitted action config against its type
+  pub fn parse(action_type: RuleActionType, config: &Value) -> Result<Self> {
+    Ok(match action_type {
+      RuleActionType::ActivateProfile => {
+        let config: ActivateProfileConfig = parse_config(action_type, config)?;
+        let profile_id = config
+          .profile_id
+          .map(|id| {
+            Uuid::parse_str(&id)
+              .map_err(|_| SmoothieError::ValidationError(format!("Invalid UUID: {}", id)))
+          })
+          .transpose()?;
+        RuleAction::ActivateProfile { profile_id }
+      }
+      RuleActionType::RunHook => {
+        let config: RunHookConfig = parse_config(action_type, config)?;
+        require_non_empty(&config.command, "command", action_type)?;
+        let timeout_secs = config.timeout_secs.unwrap_or(DEFAULT_HOOK_TIMEOUT_SECS);
+        if !(1..=MAX_HOOK_TIMEOUT_SECS).contains(&timeout_secs) {
+          return Err(SmoothieError::ValidationError(format!(
+            "Hook timeout must be between 1 and {} seconds",
+            MAX_HOOK_TIMEOUT_SECS
+          )));
+        }
+        RuleAction::RunHook {
+          command: config.command,
+          args: config.args,
+          timeout: Duration::from_secs(timeout_secs),
+        }
+      }
+      RuleActionType::SendNotification => {
+        let config: SendNotificationConfig = parse_config(action_type, config)?;
+        require_non_empty(&config.title, "title", action_type)?;
+        RuleAction::SendNotification {
+          title: config.title,
+          body: config.body,
+        }
+      }
+      RuleActionType::SetFocusMode => {
+        let config: SetFocusModeConfig = parse_config(action_type, config)?;
+        require_non_empty(&config.shortcut, "shortcut", action_type)?;
+        RuleAction::SetFocusMode {
+          shortcut: config.shortcut,
+        }
+      }
+      RuleActionType::OpenUrl => {
+        let config: OpenUrlConfig = parse_config(action_type, config)?;
+        if !(config.url.starts_with("http://") || config.url.starts_with("https://")) {
+          return Err(SmoothieError::ValidationError(format!(
+            "Only http and https URLs can be opened, got \"{}\"",
+            config.url
+          )));
+        }
+        RuleAction::OpenUrl {
+          url: config.url,
+          browser: config.browser,
+        }
+      }
+    })
+  }
+
+  /// Run the action, returning a short description of what happened
+  async fn run(
+    &self,
+    db: &Database,
+    system: &dyn SystemBackend,
+    user_id: &str,
+    rule: &AutomationRuleEntity,
+  ) -> Result<String> {
+    match self {
+      RuleAction::ActivateProfile { profile_id } => {
+        let profile_id = profile_id.unwrap_or(rule.profile_id).into();
+        let profile = ProfileService::activate_profile(
+          db,
+          profile_id,
+          user_id.parse()?,
+          ActivationSource::Automation,
+        )
+        .await?;
+        ChecklistService::present(db, system, profile_id).await;
+        Ok(format!("Activated profile {}", profile.name))
+      }
+      RuleAction::RunHook {
+        command,
+        args,
+        timeout,
+      } => {
+        // No shell: the command and its arguments are passed through as-is
+        let mut child = Command::new(command);
+        child.args(args).kill_on_drop(true);
+        run_with_timeout(child, *timeout, command).await
+      }
+      RuleAction::SendNotification { title, body } => {
+        system.show_notification(title, body)?;
+        Ok(format!("Sent notification \"{}\"", title))
+      }
+      RuleAction::SetFocusMode { shortcut } => {
+        run_shortcut(shortcut).await?;
+        Ok(format!("Ran focus shortcut \"{}\"", shortcut))
+      }
+      RuleAction::OpenUrl { url, browser } => match browser {
+        Some(browser) => {
+          let result = BrowserService::open_url_in_browser(url, browser);
+          if result.success {
+            Ok(result.message)
+          } else {
+            Err(SmoothieError::SystemError(result.message))
+          }
+        }
+        None => {
+          Command::new("open")
+            .arg(url)
+            .spawn()
+            .map_err(|e| SmoothieError::SystemError(format!("Failed to open {}: {}", url, e)))?;
+          Ok(format!("Opened {}", url))
+        }
+      },
+    }
+  }
+}
+
+/// Run a Shortcuts.app shortcut by name. macOS has no public Focus API, so
+/// switching Focus modes goes through a shortcut the user made.
+pub(crate) async fn run_shortcut(name: &str) -> Result<String> {
+  let mut child = Command::new("shortcuts");
+  child.arg("run").arg(name).kill_on_drop(true);
+  run_with_timeout(
+    child,
+    Duration::from_secs(DEFAULT_HOOK_TIMEOUT_SECS),
+    "shortcuts",
+  )
+  .await
+}
+
+pub(crate) async fn run_with_timeout(
+  mut child: Command,
+  timeout: Duration,
+  name: &str,
+) -> Result<String> {
+  let output = tokio::time::timeout(timeout, child.output())
+    .await
+    .map_err(|_| {
+      SmoothieError::SystemError(format!("{} timed out after {}s", name, timeout.as_secs()))
+    })?
+    .map_err(|e| SmoothieError::SystemError(format!("Failed to run {}: {}", name, e)))?;
+
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  if output.status.success() {
+    Ok(truncate(stdout.trim()))
+  } else {
+    Err(SmoothieError::SystemError(format!(
+      "{} exited with {}: {}",
+      name,
+      output.status,
+      truncate(stderr.trim())
+    )))
+  }
+}
+
+fn truncate(text: &str) -> String {
+  text.chars().take(MAX_OUTPUT_CHARS).collect()
+}
+
+pub struct RuleActionService;
+
+impl RuleActionService {
+  /// The steps a rule takes, as (position, type, config); the profile
+  /// activation fallback is used when the rule has none stored
+  pub fn effective_actions(
+    rule: &AutomationRuleEntity,
+    actions: &[RuleActionEntity],
+  ) -> Vec<(i32, RuleActionType, Value)> {
+    if actions.is_empty() {
+      return vec![(
+        0,
+        RuleActionType::ActivateProfile,
+        serde_json::json!({ "profileId": rule.profile_id.to_string() }),
+      )];
+    }
+    actions
+      .iter()
+      .map(|action| (action.position, action.action_type, action.config.clone()))
+      .collect()
+  }
+
+  /// Run a rule's actions one after another. A failing action is reported
+  /// and the rest still run.
+  pub async fn run_all(
+    db: &Database,
+    system: &dyn SystemBackend,
+    user_id: &str,
+    rule: &AutomationRuleEntity,
+    actions: &[RuleActionEntity],
+  ) -> Vec<ActionResult> {
+    let mut results = Vec::new();
+    for (position, action_type, config) in Self::effective_actions(rule, actions) {
+      let started = Instant::now();
+      let outcome = match RuleAction::parse(action_type, &config) {
+        Ok(action) => action.run(db, system, user_id, rule).await,
+        Err(e) => Err(e),
+      };
+      if let Err(e) = &outcome {
+        tracing::warn!(rule_id = %rule.id, position, %action_type, "Rule action failed: {}", e);
+      }
+
+      results.push(ActionResult {
+        position,
+        action_type,
+        success: outcome.is_ok(),
+        message: outcome.unwrap_or_else(|e| e.to_string()),
+        duration_ms: started.elapsed().as_millis() as i64,
+      });
+    }
+    results
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_action_configs_are_validated() {
+    assert_eq!(
+      RuleAction::parse(RuleActionType::ActivateProfile, &Value::Null).unwrap(),
+      RuleAction::ActivateProfile { profile_id: None }
+    );
+    assert_eq!(
+      RuleAction::parse(
+        RuleActionType::RunHook,
+        &json!({"command": "/usr/bin/true", "args": ["-v"]})
+      )
+      .unwrap(),
+      RuleAction::RunHook {
+        command: "/usr/bin/true".to_string(),
+        args: vec!["-v".to_string()],
+        timeout: Duration::from_secs(DEFAULT_HOOK_TIMEOUT_SECS),
+      }
+    );
+
+    let invalid = [
+      (RuleActionType::RunHook, json!({"command": " "})),
+      (
+        RuleActionType::RunHook,
+        json!({"command": "ls", "timeoutSecs": 0}),
+      ),
+      (
+        RuleActionType::SendNotification,
+        json!({"body": "no title"}),
+      ),
+      (
+        RuleActionType::SetFocusMode,
+        json!({"shortcut": "Work", "extra": 1}),
+      ),
+      (
+        RuleActionType::OpenUrl,
+        json!({"url": "file:///etc/passwd"}),
+      ),
+      (
+        RuleActionType::ActivateProfile,
+        json!({"profileId": "nope"}),
+      ),
+    ];
+    for (action_type, config) in invalid {
+      assert!(
+        RuleAction::parse(action_type, &config).is_err(),
+        "{} {}",
+        action_type,
+        config
+      );
+    }
+  }
+}
@@ -0,0 +1,264 @@
+//! Script Export Service
+//!
+//! Turns a profile into a standalone bash script that reproduces it on a Mac
+//! without Smoothie: displayplacer arranges the displays, then `open` launches
+//! the apps, browser tabs and files in the order activation would. Display ids
+//! differ between machines, so the script reads them from `displayplacer list`
+//! when it runs and assigns them to the profile's monitors by display index.
+
+use crate::{
+  db::Database,
+  error::Result,
+  models::{MonitorDto, ProfileId, ProfileResponse},
+  services::{BrowserService, FileService, ProfileService},
+};
+use chrono::Utc;
+use std::fmt::Write;
+
+pub struct ScriptExportService;
+
+impl ScriptExportService {
+  /// Render `profile_id` as a shell script
+  pub async fn export_profile_as_script(db: &Database, profile_id: ProfileId) -> Result<String> {
+    let profile = ProfileService::get_profile_response(db, profile_id).await?;
+    Ok(render_script(
+      &profile,
+      &Utc::now().format("%Y-%m-%d").to_string(),
+    ))
+  }
+}
+
+/// Quote `value` as a single shell word
+fn quote(value: &str) -> String {
+  format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// A path for the script, keeping a leading `~/` relative to the user running it
+fn quote_path(path: &str) -> String {
+  match path.strip_prefix("~/") {
+    Some(rest) => format!("\"$HOME\"/{}", quote(rest)),
+    None => quote(path),
+  }
+}
+
+/// One displayplacer argument, with the screen id left to the script.
+/// Scaling and rotation follow `SystemService::apply_monitor_layout`.
+fn displayplacer_arg(index: usize, monitor: &MonitorDto) -> String {
+  let scaling = if monitor.scale_factor.unwrap_or(1.0) > 1.0 {
+    "on"
+  } else {
+    "off"
+  };
+  let degree = if monitor.orientation == "Portrait" {
+    90
+  } else {
+    0
+  };
+  format!(
+    "\"id:${{ids[{}]}} res:{}x{} scaling:{} origin:({},{}) degree:{}\"",
+    index, monitor.width, monitor.height, scaling, monitor.x, monitor.y, degree
+  )
+}
+
+fn render_displays(out: &mut String, monitors: &[&MonitorDto]) {
+  let _ = writeln!(
+    out,
+    "\
+if ! command -v displayplacer >/dev/null 2>&1; then
+  echo \"displayplacer not found, skipping the display layout (brew install displayplacer)\" >&2
+else
+  ids=($(displayplacer list | awk -F': ' '/^Contextual screen id:/ {{ print $2 }}' | sort -n))
+  if [ \"${{#ids[@]}}\" -ne {count} ]; then
+    echo \"This layout is for {count} display(s) but ${{#ids[@]}} are connected, skipping it\" >&2
+  else
+    displayplacer \\",
+    count = monitors.len()
+  );
+  for (i, monitor) in monitors.iter().enumerate() {
+    let end = if i + 1 < monitors.len() { " \\" } else { "" };
+    let _ = writeln!(out, "      {}{}", displayplacer_arg(i, monitor), end);
+  }
+  out.push_str("  fi\nfi\n\n");
+}
+
+/// The script for `profile`; `date` goes in the header
+pub(crate) fn render_script(profile: &ProfileResponse, date: &str) -> String {
+  let mut out = String::new();
+  let _ = writeln!(out, "#!/bin/bash");
+  let _ = writeln!(
+    out,
+    "# {} - exported from Smoothie on {}",
+    profile.name.replace('\n', " "),
+    date
+  );
+  let _ = writeln!(out, "set -u\n");
+
+  let mut monitors: Vec<&MonitorDto> = profile.monitors.iter().collect();
+  monitors.sort_by_key(|m| m.display_index);
+  if !monitors.is_empty() {
+    out.push_str("# Displays\n");
+    render_displays(&mut out, &monitors);
+  }
+
+  let mut apps: Vec<_> = profile
+    .apps
+    .iter()
+    .filter(|a| a.launch_on_activate)
+    .collect();
+  apps.sort_by_key(|a| a.order_index);
+  if !apps.is_empty() {
+    out.push_str("# Apps\n");
+    for app in apps {
+      if app.startup_delay_ms > 0 {
+        let _ = writeln!(out, "sleep {:.3}", f64::from(app.startup_delay_ms) / 1000.0);
+      }
+      let _ = writeln!(
+        out,
+        "open -b {} || echo {} >&2",
+        quote(&app.bundle_id),
+        quote(&format!("Could not launch {}", app.name))
+      );
+    }
+    out.push('\n');
+  }
+
+  let mut tabs: Vec<_> = profile.browser_tabs.iter().collect();
+  tabs.sort_by_key(|t| t.tab_order);
+  if !tabs.is_empty() {
+    out.push_str("# Browser tabs\n");
+    for tab in tabs {
+      let _ = writeln!(
+        out,
+        "open -b {} {} || open {}",
+        BrowserService::get_browser_bundle_id(&tab.browser),
+        quote(&tab.url),
+        quote(&tab.url)
+      );
+    }
+    out.push('\n');
+  }
+
+  let mut files: Vec<_> = profile
+    .files
+    .iter()
+    .filter(|f| f.open_on_activate)
+    .collect();
+  files.sort_by_key(|f| f.open_order);
+  if !files.is_empty() {
+    out.push_str("# Files\n");
+    for file in files {
+      let app = match &file.target_app {
+        Some(app) => format!("{} {} ", FileService::open_app_flag(app), quote(app)),
+        None => String::new(),
+      };
+      let _ = writeln!(out, "open {}{}", app, quote_path(&file.path));
+    }
+  }
+
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::{AppDto, BrowserTabDto, ProfileFileDto, ProfileType};
+
+  fn monitor(display_index: i32, x: i32, orientation: &str) -> MonitorDto {
+    MonitorDto {
+      id: String::new(),
+      profile_id: String::new(),
+      name: "Display".into(),
+      resolution: "2560x1440".into(),
+      orientation: orientation.into(),
+      is_primary: display_index == 0,
+      x,
+      y: 0,
+      width: 2560,
+      height: 1440,
+      display_index,
+      brand: None,
+      model: None,
+      refresh_rate: None,
+      scale_factor: Some(2.0),
+      is_builtin: None,
+      color_depth: None,
+      created_at: None,
+      updated_at: None,
+      fingerprint: None,
+      captured_at: None,
+      blanked: false,
+    }
+  }
+
+  #[test]
+  fn test_script_quotes_values_and_orders_monitors_by_display_index() {
+    let profile = ProfileResponse {
+      id: ProfileId::from(uuid::Uuid::nil()),
+      name: "Deep Work".into(),
+      description: None,
+      profile_type: ProfileType::Work,
+      is_active: false,
+      tags: vec![],
+      monitors: vec![monitor(1, 2560, "Portrait"), monitor(0, 0, "Landscape")],
+      apps: vec![AppDto {
+        id: String::new(),
+        profile_id: String::new(),
+        name: "Bob's Editor".into(),
+        bundle_id: "com.example.editor".into(),
+        exe_path: None,
+        launch_on_activate: true,
+        monitor_preference: None,
+        created_at: String::new(),
+        updated_at: None,
+        icon_path: None,
+        launch_args: None,
+        working_directory: None,
+        startup_delay_ms: 1500,
+        order_index: 0,
+      }],
+      browser_tabs: vec![BrowserTabDto {
+        id: String::new(),
+        profile_id: String::new(),
+        url: "https://example.com/?q=a'b".into(),
+        browser: "chrome".into(),
+        monitor_id: None,
+        tab_order: 0,
+        favicon: None,
+        created_at: String::new(),
+        updated_at: None,
+      }],
+      files: vec![ProfileFileDto {
+        id: String::new(),
+        profile_id: String::new(),
+        path: "~/Notes/today.md".into(),
+        target_app: Some("Typora".into()),
+        open_on_activate: true,
+        open_order: 0,
+        created_at: String::new(),
+        updated_at: String::new(),
+      }],
+      created_at: String::new(),
+      last_used: None,
+      notes: None,
+    };
+
+    let script = render_script(&profile, "2026-10-18");
+
+    assert!(script.starts_with("#!/bin/bash\n# Deep Work - exported from Smoothie on 2026-10-18\n"));
+    assert!(script.contains("-ne 2 ]"));
+    let first = script
+      .find("\"id:${ids[0]} res:2560x1440 scaling:on origin:(0,0) degree:0\" \\\n")
+      .unwrap();
+    let second = script
+      .find("\"id:${ids[1]} res:2560x1440 scaling:on origin:(2560,0) degree:90\"\n")
+      .unwrap();
+    assert!(first < second);
+    assert!(script.contains(
+      "sleep 1.500\nopen -b 'com.example.editor' || echo 'Could not launch Bob'\\''s Editor' >&2\n"
+    ));
+    assert!(script.contains(
+      "open -b com.google.Chrome 'https://example.com/?q=a'\\''b' || open 'https://example.com/?q=a'\\''b'\n"
+    ));
+    assert!(script.contains("open -a 'Typora' \"$HOME\"/'Notes/today.md'\n"));
+  }
+}
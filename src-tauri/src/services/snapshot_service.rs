@@ -0,0 +1,444 @@
+//! Snapshot Service
+//!
+//! Once a day every profile is copied into a JSON snapshot in the app data
+//! directory, and the oldest are removed beyond the number the user keeps.
+//! Snapshots don't depend on database backups: they hold each profile's rows
+//! as stored, app paths, windows, files and rules included, and restoring one
+//! puts profiles back exactly the way they were on that day, recreating any
+//! deleted since. A restore first takes a snapshot of the current state so it
+//! can be undone. Profiles created after the snapshot are left alone.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::{ProfileId, RestoreSnapshotDto, SnapshotDto, SnapshotKind, UserId},
+  repositories::{ProfileRows, ProfileSnapshotRepository},
+  services::{profile_lock::ProfileLock, ProfileService, UserSettingsService},
+  state::AppState,
+  utils::paths,
+};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Most snapshots a user can keep
+pub const MAX_SNAPSHOTS_KEPT: i32 = 365;
+
+/// Version of the snapshot file; restores refuse anything newer
+const SNAPSHOT_FORMAT: u32 = 1;
+
+const SNAPSHOT_DELAY: Duration = Duration::from_secs(5 * 60);
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Snapshot {
+  format: u32,
+  kind: SnapshotKind,
+  taken_at: DateTime<Utc>,
+  profiles: Vec<SnapshotProfile>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotProfile {
+  id: ProfileId,
+  rows: ProfileRows,
+}
+
+fn snapshots_dir() -> PathBuf {
+  paths::app_data_dir().join("snapshots")
+}
+
+/// Daily snapshots are named after their day; others also carry the time and kind
+fn snapshot_id(kind: SnapshotKind, taken_at: DateTime<Local>) -> String {
+  match kind {
+    SnapshotKind::Daily => taken_at.format("%Y-%m-%d").to_string(),
+    _ => format!("{}-{}", taken_at.format("%Y-%m-%d-%H%M%S"), kind),
+  }
+}
+
+/// The snapshot file for an id from the frontend, refusing anything that could
+/// point outside the snapshots directory
+fn snapshot_path(dir: &Path, id: &str) -> Result<PathBuf> {
+  let valid = !id.is_empty()
+    && id
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+  if !valid {
+    return Err(SmoothieError::ValidationError("Invalid snapshot id".into()));
+  }
+  Ok(dir.join(format!("{}.json", id)))
+}
+
+async fn read_snapshot(path: &Path) -> Result<Snapshot> {
+  let data = match tokio::fs::read(path).await {
+    Ok(data) => data,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+      return Err(SmoothieError::NotFound("Snapshot not found".into()))
+    }
+    Err(e) => return Err(e.into()),
+  };
+  Ok(serde_json::from_slice(&data)?)
+}
+
+pub struct SnapshotService;
+
+impl SnapshotService {
+  /// Saved snapshots, newest first
+  pub async fn list_snapshots() -> Result<Vec<SnapshotDto>> {
+    Self::list_in(&snapshots_dir()).await
+  }
+
+  /// Put profiles back the way they were in a snapshot; `profile_ids` limits
+  /// the restore to some of them
+  pub async fn restore_snapshot(
+    db: &Database,
+    user_id: UserId,
+    snapshot_id: &str,
+    profile_ids: Option<Vec<ProfileId>>,
+  ) -> Result<RestoreSnapshotDto> {
+    Self::restore_in(db, user_id, &snapshots_dir(), snapshot_id, profile_ids).await
+  }
+
+  /// Take today's snapshot in the background unless it exists or snapshots are off
+  pub fn spawn_daily(state: Arc<AppState>, user_id: Uuid) {
+    let snapshot_state = state.clone();
+    state.tasks.spawn("daily_profile_snapshot", move || {
+      let state = snapshot_state.clone();
+      async move {
+        let first_run = tokio::time::Instant::now() + SNAPSHOT_DELAY;
+        let mut ticker = tokio::time::interval_at(first_run, SNAPSHOT_INTERVAL);
+        loop {
+          ticker.tick().await;
+          let today = Local::now().date_naive();
+          if let Err(e) = Self::ensure_daily_in(&state.db, user_id, &snapshots_dir(), today).await {
+            tracing::warn!("Daily profile snapshot failed: {}", e);
+          }
+        }
+      }
+    });
+  }
+
+  async fn ensure_daily_in(
+    db: &Database,
+    user_id: Uuid,
+    dir: &Path,
+    today: NaiveDate,
+  ) -> Result<()> {
+    let kept = UserSettingsService::get_settings(db, user_id)
+      .await?
+      .snapshots_kept;
+    if kept == 0 || dir.join(format!("{}.json", today)).exists() {
+      return Ok(());
+    }
+    Self::take_in(db, user_id.into(), dir, SnapshotKind::Daily).await?;
+    Self::rotate_in(dir, kept as usize).await
+  }
+
+  /// Copy every profile, local-only ones included, into a new snapshot file
+  async fn take_in(
+    db: &Database,
+    user_id: UserId,
+    dir: &Path,
+    kind: SnapshotKind,
+  ) -> Result<SnapshotDto> {
+    let repo = ProfileSnapshotRepository::new(db.pool());
+    let mut profiles = Vec::new();
+    for profile in ProfileService::get_profiles(db, user_id).await? {
+      profiles.push(SnapshotProfile {
+        id: profile.id,
+        rows: repo.export(profile.id.as_uuid()).await?,
+      });
+    }
+
+    let now = Local::now();
+    let snapshot = Snapshot {
+      format: SNAPSHOT_FORMAT,
+      kind,
+      taken_at: now.with_timezone(&Utc),
+      profiles,
+    };
+    let id = snapshot_id(kind, now);
+    let data = serde_json::to_vec_pretty(&snapshot)?;
+    tokio::fs::create_dir_all(dir).await?;
+    paths::write_atomically(&snapshot_path(dir, &id)?, &data).await?;
+    tracing::info!(snapshot_id = %id, profiles = snapshot.profiles.len(), "Profile snapshot saved");
+
+    Ok(SnapshotDto {
+      id,
+      kind,
+      taken_at: snapshot.taken_at.to_rfc3339(),
+      profile_count: snapshot.profiles.len(),
+      size_bytes: data.len() as u64,
+    })
+  }
+
+  async fn list_in(dir: &Path) -> Result<Vec<SnapshotDto>> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+      Ok(entries) => entries,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+      Err(e) => return Err(e.into()),
+    };
+
+    let mut snapshots = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+      let path = entry.path();
+      if path.extension().and_then(|e| e.to_str()) != Some("json") {
+        continue;
+      }
+      let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+        continue;
+      };
+      match read_snapshot(&path).await {
+        Ok(snapshot) => snapshots.push((
+          snapshot.taken_at,
+          SnapshotDto {
+            id: id.to_string(),
+            kind: snapshot.kind,
+            taken_at: snapshot.taken_at.to_rfc3339(),
+            profile_count: snapshot.profiles.len(),
+            size_bytes: entry.metadata().await.map(|m| m.len()).unwrap_or(0),
+          },
+        )),
+        Err(e) => tracing::warn!(path = %path.display(), "Skipping unreadable snapshot: {}", e),
+      }
+    }
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.0));
+    Ok(snapshots.into_iter().map(|(_, dto)| dto).collect())
+  }
+
+  /// Delete all but the newest `kept` snapshots
+  async fn rotate_in(dir: &Path, kept: usize) -> Result<()> {
+    for old in Self::list_in(dir).await?.into_iter().skip(kept) {
+      tokio::fs::remove_file(snapshot_path(dir, &old.id)?).await?;
+      tracing::info!(snapshot_id = %old.id, "Old profile snapshot removed");
+    }
+    Ok(())
+  }
+
+  async fn restore_in(
+    db: &Database,
+    user_id: UserId,
+    dir: &Path,
+    snapshot_id: &str,
+    profile_ids: Option<Vec<ProfileId>>,
+  ) -> Result<RestoreSnapshotDto> {
+    let snapshot = read_snapshot(&snapshot_path(dir, snapshot_id)?).await?;
+    if snapshot.format > SNAPSHOT_FORMAT {
+      return Err(SmoothieError::ValidationError(
+        "This snapshot was taken by a newer version of Smoothie".into(),
+      ));
+    }
+
+    // Every profile is checked before any is touched, so a locked one can't
+    // leave the restore half done
+    let wanted = |id: &ProfileId| profile_ids.as_ref().map_or(true, |ids| ids.contains(id));
+    let selected: Vec<_> = snapshot
+      .profiles
+      .into_iter()
+      .filter(|p| wanted(&p.id))
+      .collect();
+    for saved in &selected {
+      ProfileLock::ensure_unlocked(db, saved.id.as_uuid()).await?;
+    }
+
+    let undo = Self::take_in(db, user_id, dir, SnapshotKind::PreRestore).await?;
+    let mut result = RestoreSnapshotDto {
+      snapshot_id: snapshot_id.to_string(),
+      restored: 0,
+      recreated: 0,
+      undo_snapshot_id: undo.id,
+    };
+
+    let repo = ProfileSnapshotRepository::new(db.pool());
+    for saved in &selected {
+      if repo.restore(user_id.as_uuid(), &saved.rows).await? {
+        result.recreated += 1;
+      } else {
+        result.restored += 1;
+      }
+    }
+
+    tracing::info!(
+      snapshot_id = %snapshot_id,
+      restored = result.restored,
+      recreated = result.recreated,
+      "Profile snapshot restored"
+    );
+    Ok(result)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use crate::models::{
+    CreateAppRequest, CreateMonitorRequest, CreateProfileRequest, ProfileType, UpdateProfileRequest,
+  };
+  use crate::services::{window_service::WindowService, AppService, MonitorService};
+
+  #[test]
+  fn test_snapshot_ids_stay_inside_the_snapshots_dir() {
+    let dir = Path::new("/snapshots");
+    assert_eq!(
+      snapshot_path(dir, "2026-10-18").unwrap(),
+      Path::new("/snapshots/2026-10-18.json")
+    );
+    for bad in ["", "../settings", "2026/10/18", "a.b"] {
+      assert!(snapshot_path(dir, bad).is_err(), "{:?} accepted", bad);
+    }
+  }
+
+  #[tokio::test]
+  async fn test_restore_brings_back_edited_and_deleted_profiles() {
+    let test_db = test_db_or_skip!();
+    let db = &test_db.db;
+    let user_id = Uuid::new_v4();
+    ProfileService::ensure_user_exists(db, user_id)
+      .await
+      .unwrap();
+    let dir = std::env::temp_dir().join(format!("smoothie-snapshots-{}", Uuid::new_v4()));
+
+    let create = |name: &str| CreateProfileRequest {
+      name: name.into(),
+      description: None,
+      profile_type: ProfileType::Work,
+      tags: None,
+      notes: None,
+    };
+    let work = ProfileService::create_profile(db, user_id.into(), create("Work"))
+      .await
+      .unwrap();
+    let games = ProfileService::create_profile(db, user_id.into(), create("Games"))
+      .await
+      .unwrap();
+    let app = |name: &str, exe_path: &str| CreateAppRequest {
+      name: name.into(),
+      bundle_id: format!("com.example.{}", name),
+      exe_path: Some(exe_path.into()),
+      launch_on_activate: true,
+      monitor_preference: None,
+      startup_delay_ms: None,
+      order_index: None,
+    };
+    let monitor = MonitorService::create_monitor(
+      db,
+      &games.id.to_string(),
+      CreateMonitorRequest {
+        name: "Desk".into(),
+        resolution: "2560x1440".into(),
+        orientation: "landscape".into(),
+        is_primary: true,
+        x: 0,
+        y: 0,
+        width: 2560,
+        height: 1440,
+        display_index: 0,
+      },
+    )
+    .await
+    .unwrap();
+    let steam = AppService::create_app(
+      db,
+      &games.id.to_string(),
+      &user_id.to_string(),
+      app("Steam", "/Applications/Steam.app"),
+    )
+    .await
+    .unwrap();
+    sqlx::query(
+      r#"
+      INSERT INTO windows (id, profile_id, app_id, monitor_id, x, y, width, height, state)
+      VALUES (uuid_generate_v4(), $1, $2::uuid, $3::uuid, 0, 0, 1280, 1440, 'normal')
+      "#,
+    )
+    .bind(games.id.as_uuid())
+    .bind(&steam.id)
+    .bind(&monitor.id)
+    .execute(test_db.pool())
+    .await
+    .unwrap();
+    let taken = SnapshotService::take_in(db, user_id.into(), &dir, SnapshotKind::Daily)
+      .await
+      .unwrap();
+    assert_eq!(taken.profile_count, 2);
+
+    ProfileService::update_profile_extended(
+      db,
+      work.id,
+      UpdateProfileRequest {
+        name: Some("Work (old)".into()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+    AppService::create_app(
+      db,
+      &work.id.to_string(),
+      &user_id.to_string(),
+      app("Slack", "/Applications/Slack.app"),
+    )
+    .await
+    .unwrap();
+    ProfileService::delete_profile(db, games.id).await.unwrap();
+
+    // With Work locked nothing is restored, not even Games
+    ProfileService::set_locked(db, work.id, true).await.unwrap();
+    assert!(matches!(
+      SnapshotService::restore_in(db, user_id.into(), &dir, &taken.id, None).await,
+      Err(SmoothieError::Locked(_))
+    ));
+    let profiles = ProfileService::get_profiles(db, user_id.into())
+      .await
+      .unwrap();
+    assert_eq!(profiles.len(), 1);
+    ProfileService::set_locked(db, work.id, false)
+      .await
+      .unwrap();
+
+    let restored = SnapshotService::restore_in(db, user_id.into(), &dir, &taken.id, None)
+      .await
+      .unwrap();
+    assert_eq!((restored.restored, restored.recreated), (1, 1));
+
+    let mut names: Vec<_> = ProfileService::get_profiles(db, user_id.into())
+      .await
+      .unwrap()
+      .into_iter()
+      .map(|p| p.name)
+      .collect();
+    names.sort();
+    assert_eq!(names, ["Games", "Work"]);
+
+    // Restored as saved, down to app paths and windows, and without what was added since
+    assert!(AppService::get_apps(db, &work.id.to_string())
+      .await
+      .unwrap()
+      .is_empty());
+    let apps = AppService::get_apps(db, &games.id.to_string())
+      .await
+      .unwrap();
+    assert_eq!(apps.len(), 1);
+    assert_eq!(apps[0].id, steam.id);
+    assert_eq!(apps[0].exe_path.as_deref(), Some("/Applications/Steam.app"));
+    let windows = WindowService::get_windows(db, &games.id.to_string())
+      .await
+      .unwrap();
+    assert_eq!(windows.len(), 1);
+    assert_eq!(windows[0].monitor_id, monitor.id);
+
+    // The undo snapshot counts towards the ones kept
+    SnapshotService::rotate_in(&dir, 1).await.unwrap();
+    let left = SnapshotService::list_in(&dir).await.unwrap();
+    assert_eq!(left.len(), 1);
+    assert_eq!(left[0].id, restored.undo_snapshot_id);
+
+    let _ = std::fs::remove_dir_all(&dir);
+  }
+}
@@ -0,0 +1,57 @@
+//! Storage Service
+//!
+//! Collects blobs that no app icon, profile icon, tab favicon or feedback
+//! report refers to any more. Collection runs once a day in the background
+//! and on demand; blobs stored within the last week are always kept, since
+//! the row that will refer to one may not be saved yet.
+
+use crate::{
+  error::Result, repositories::StorageRepository, services::resource_guard::ThrottledTicker,
+  state::AppState, storage::BlobGcReport,
+};
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+
+const GC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+/// Unreferenced blobs younger than this are kept
+const GC_GRACE_DAYS: i64 = 7;
+
+pub struct StorageService;
+
+impl StorageService {
+  pub async fn collect_garbage(state: &AppState) -> Result<BlobGcReport> {
+    let referenced = StorageRepository::new(state.db.pool())
+      .find_blob_references()
+      .await?;
+    let report = state
+      .storage
+      .collect_garbage(&referenced, Utc::now() - Duration::days(GC_GRACE_DAYS))
+      .await?;
+    if report.removed > 0 {
+      tracing::info!(
+        removed = report.removed,
+        bytes_freed = report.bytes_freed,
+        "Unreferenced blobs collected"
+      );
+    }
+    Ok(report)
+  }
+
+  /// Collect garbage once a day
+  pub fn spawn_gc(state: Arc<AppState>) {
+    let gc_state = state.clone();
+    state.tasks.spawn("blob_gc", move || {
+      let state = gc_state.clone();
+      async move {
+        let mut ticker = ThrottledTicker::new(GC_INTERVAL);
+        loop {
+          ticker.tick().await;
+          if let Err(e) = Self::collect_garbage(&state).await {
+            tracing::warn!("Blob garbage collection failed: {}", e);
+          }
+        }
+      }
+    });
+  }
+}
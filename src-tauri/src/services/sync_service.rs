@@ -0,0 +1,345 @@
+// Selective sync
+//
+// Builds what cloud sync uploads for a user and uploads it. The sync scope in
+// user settings picks the categories: profiles alone, profiles and settings,
+// or everything including the activity log. Profiles flagged local-only are
+// withheld under every scope, along with any activity that names them, so
+// uploads are always assembled here rather than read from the tables
+// directly. Each push is stored in the `sync` Storage bucket under the
+// user's id.
+
+use crate::{
+  db::{supabase::SupabaseClient, Database},
+  error::Result,
+  models::{
+    ActivityLogDto, LogQueryParams, ProfileDto, ProfileId, SyncScope, UserId, UserSettingsDto,
+  },
+  services::{ProfileService, UserSettingsService, AUDIT_SERVICE},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::future::Future;
+
+pub const SYNC_BUCKET: &str = "sync";
+
+/// Most recent activity entries included when the scope covers logs
+const SYNC_LOG_LIMIT: i64 = 500;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPayload {
+  pub scope: SyncScope,
+  pub profiles: Vec<ProfileDto>,
+  pub settings: Option<UserSettingsDto>,
+  pub activity_logs: Vec<ActivityLogDto>,
+  /// Local-only profiles left out of `profiles`
+  pub withheld_profile_ids: Vec<ProfileId>,
+}
+
+pub struct SyncService;
+
+impl SyncService {
+  /// Upload everything the user's sync scope allows to leave the machine,
+  /// returning what went out, or `None` while cloud sync is turned off
+  pub async fn push(db: &Database, user_id: UserId) -> Result<Option<SyncPayload>> {
+    Self::push_with(db, user_id, |payload| async move {
+      SupabaseClient::require_env("Cloud sync")?
+        .upload_object(SYNC_BUCKET, &object_path(user_id, Utc::now()), &payload)
+        .await
+    })
+    .await
+  }
+
+  /// `push`, handing the payload to `upload` instead of Supabase
+  async fn push_with<F, Fut>(
+    db: &Database,
+    user_id: UserId,
+    upload: F,
+  ) -> Result<Option<SyncPayload>>
+  where
+    F: FnOnce(SyncPayload) -> Fut,
+    Fut: Future<Output = Result<()>>,
+  {
+    let Some(payload) = Self::collect(db, user_id).await? else {
+      return Ok(None);
+    };
+    upload(payload.clone()).await?;
+    tracing::info!(
+      user_id = %user_id,
+      scope = %payload.scope,
+      profiles = payload.profiles.len(),
+      withheld = payload.withheld_profile_ids.len(),
+      "Cloud sync pushed"
+    );
+    Ok(Some(payload))
+  }
+
+  /// Everything the user's sync scope allows to leave the machine, or `None`
+  /// while cloud sync is turned off
+  async fn collect(db: &Database, user_id: UserId) -> Result<Option<SyncPayload>> {
+    let settings = UserSettingsService::get_settings(db, user_id.as_uuid()).await?;
+    if !settings.cloud_sync {
+      return Ok(None);
+    }
+    Self::build(db, user_id, settings).await.map(Some)
+  }
+
+  /// What the next sync would upload under the current scope, whether or not
+  /// cloud sync is on
+  pub async fn preview(db: &Database, user_id: UserId) -> Result<SyncPayload> {
+    let settings = UserSettingsService::get_settings(db, user_id.as_uuid()).await?;
+    Self::build(db, user_id, settings).await
+  }
+
+  async fn build(db: &Database, user_id: UserId, settings: UserSettingsDto) -> Result<SyncPayload> {
+    let scope = settings.sync_scope;
+    let profiles = ProfileService::get_profiles(db, user_id).await?;
+    let activity_logs = if scope.includes_logs() {
+      let params = LogQueryParams {
+        limit: Some(SYNC_LOG_LIMIT),
+        ..Default::default()
+      };
+      AUDIT_SERVICE
+        .get_activity_logs(db, &user_id.to_string(), params)
+        .await?
+    } else {
+      Vec::new()
+    };
+
+    Ok(select(scope, profiles, settings, activity_logs))
+  }
+}
+
+fn object_path(user_id: UserId, pushed_at: DateTime<Utc>) -> String {
+  format!(
+    "{}/{}.json",
+    user_id,
+    pushed_at.format("%Y%m%dT%H%M%S%.3fZ")
+  )
+}
+
+/// Apply `scope` and drop local-only profiles and the activity that names them
+fn select(
+  scope: SyncScope,
+  profiles: Vec<ProfileDto>,
+  settings: UserSettingsDto,
+  activity_logs: Vec<ActivityLogDto>,
+) -> SyncPayload {
+  let (withheld, profiles): (Vec<_>, Vec<_>) = profiles.into_iter().partition(|p| p.local_only);
+  let withheld_profile_ids: Vec<ProfileId> = withheld.iter().map(|p| p.id).collect();
+
+  let activity_logs = if scope.includes_logs() {
+    activity_logs
+      .into_iter()
+      .filter(|log| {
+        !withheld_profile_ids
+          .iter()
+          .any(|id| log.entity_id.as_deref() == Some(id.to_string().as_str()))
+      })
+      .collect()
+  } else {
+    Vec::new()
+  };
+
+  SyncPayload {
+    scope,
+    profiles,
+    settings: scope.includes_settings().then_some(settings),
+    activity_logs,
+    withheld_profile_ids,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use crate::models::entities::{ProfileEntity, UserSettingsEntity};
+  use crate::models::{ActivityStatus, ProfileType, ReportFormat};
+  use parking_lot::Mutex;
+  use uuid::Uuid;
+
+  fn profile(user_id: Uuid, name: &str, local_only: bool) -> ProfileDto {
+    let now = Utc::now();
+    ProfileDto::from(ProfileEntity {
+      id: Uuid::new_v4(),
+      user_id,
+      name: name.into(),
+      description: None,
+      profile_type: ProfileType::Work,
+      is_active: false,
+      created_at: now,
+      updated_at: now,
+      last_used: None,
+      last_activated_at: None,
+      activation_count: None,
+      is_favorite: None,
+      color: None,
+      icon: None,
+      sort_order: None,
+      low_power_battery_threshold: None,
+      local_only,
+      required_monitor_count: None,
+      monitor_signature: None,
+      notes: None,
+      include_virtual_displays: false,
+      is_locked: false,
+    })
+  }
+
+  fn activity(user_id: Uuid, entity_id: Option<String>) -> ActivityLogDto {
+    ActivityLogDto {
+      id: Uuid::new_v4().to_string(),
+      user_id: user_id.to_string(),
+      session_id: None,
+      action: "profile_activated".into(),
+      entity_type: Some("profile".into()),
+      entity_id,
+      entity_name: None,
+      details: None,
+      ip_address: None,
+      user_agent: None,
+      status: ActivityStatus::Success,
+      error_message: None,
+      duration_ms: None,
+      request_id: None,
+      created_at: Utc::now().to_rfc3339(),
+    }
+  }
+
+  fn settings(user_id: Uuid, scope: SyncScope) -> UserSettingsDto {
+    let now = Utc::now();
+    UserSettingsDto::from(UserSettingsEntity {
+      id: Uuid::new_v4(),
+      user_id,
+      theme: "dark".into(),
+      auto_restore: true,
+      monitor_detection: true,
+      animations_enabled: true,
+      cloud_sync: true,
+      auto_activate_time: "09:00".into(),
+      keyboard_shortcut: "Cmd+Shift+S".into(),
+      notifications_enabled: true,
+      created_at: now,
+      updated_at: now,
+      default_profile_id: None,
+      last_active_profile_id: None,
+      onboarding_completed: None,
+      onboarding_step: None,
+      feature_flags: None,
+      keyboard_shortcuts: None,
+      ui_preferences: None,
+      log_retention_days: 30,
+      auto_cleanup_logs: true,
+      latitude: None,
+      longitude: None,
+      sync_scope: scope,
+      monitor_profile_mappings: serde_json::json!([]),
+      weekly_report_enabled: false,
+      weekly_report_format: ReportFormat::Markdown,
+      snapshots_kept: 14,
+      declarative_watch_path: None,
+      launch_at_login: false,
+      background_agent: false,
+      diagnostic_uploads: false,
+      telemetry_enabled: false,
+      telemetry_sent_at: None,
+      displayplacer_path: None,
+    })
+  }
+
+  #[test]
+  fn test_scope_and_local_only_profiles_limit_the_payload() {
+    let user_id = Uuid::new_v4();
+    let shared = profile(user_id, "Shared", false);
+    let client = profile(user_id, "Client", true);
+    let logs = vec![
+      activity(user_id, Some(shared.id.to_string())),
+      activity(user_id, Some(client.id.to_string())),
+      activity(user_id, None),
+    ];
+    let profiles = vec![shared.clone(), client.clone()];
+
+    let payload = select(
+      SyncScope::Profiles,
+      profiles.clone(),
+      settings(user_id, SyncScope::Profiles),
+      logs.clone(),
+    );
+    assert_eq!(
+      payload.profiles.iter().map(|p| p.id).collect::<Vec<_>>(),
+      vec![shared.id]
+    );
+    assert_eq!(payload.withheld_profile_ids, vec![client.id]);
+    assert!(payload.settings.is_none());
+    assert!(payload.activity_logs.is_empty());
+
+    let payload = select(
+      SyncScope::ProfilesAndSettings,
+      profiles.clone(),
+      settings(user_id, SyncScope::ProfilesAndSettings),
+      logs.clone(),
+    );
+    assert!(payload.settings.is_some());
+    assert!(payload.activity_logs.is_empty());
+
+    let payload = select(
+      SyncScope::All,
+      profiles,
+      settings(user_id, SyncScope::All),
+      logs,
+    );
+    assert_eq!(payload.profiles.len(), 1);
+    assert_eq!(payload.activity_logs.len(), 2);
+    assert!(payload
+      .activity_logs
+      .iter()
+      .all(|log| log.entity_id != Some(client.id.to_string())));
+  }
+
+  #[tokio::test]
+  async fn test_push_leaves_local_only_profiles_behind() {
+    let test_db = test_db_or_skip!();
+    let db = &test_db.db;
+    let (user_id, shared) = test_db.seed_user_and_profile("Shared").await;
+    let client = test_db.seed_profile(user_id, "Client").await;
+    ProfileService::set_local_only(db, client.into(), true)
+      .await
+      .unwrap();
+
+    let uploaded = Mutex::new(Vec::new());
+    let upload = |payload: SyncPayload| {
+      uploaded.lock().push(payload);
+      async { Ok(()) }
+    };
+    // Nothing goes out until cloud sync is turned on
+    assert!(SyncService::push_with(db, user_id.into(), upload)
+      .await
+      .unwrap()
+      .is_none());
+    assert!(uploaded.lock().is_empty());
+
+    sqlx::query("UPDATE user_settings SET cloud_sync = true WHERE user_id = $1")
+      .bind(user_id)
+      .execute(test_db.pool())
+      .await
+      .unwrap();
+    SyncService::push_with(db, user_id.into(), upload)
+      .await
+      .unwrap();
+    let uploaded = uploaded.into_inner();
+    assert_eq!(uploaded.len(), 1);
+    assert_eq!(
+      uploaded[0]
+        .profiles
+        .iter()
+        .map(|p| p.id)
+        .collect::<Vec<_>>(),
+      vec![ProfileId::from(shared)]
+    );
+    assert_eq!(
+      uploaded[0].withheld_profile_ids,
+      vec![ProfileId::from(client)]
+    );
+  }
+}
@@ -0,0 +1,651 @@
+//! System backend abstraction
+//!
+//! Handlers and services talk to the operating system through the
+//! [`SystemBackend`] trait instead of calling [`SystemService`] directly, so the
+//! display/window logic can be exercised off-macOS with [`MockSystemBackend`].
+//!
+//! The backend is held in `AppState`. Setting `SMOOTHIE_SYSTEM_BACKEND=mock`
+//! swaps in the mock, which is handy for running the UI on a machine without
+//! the required permissions.
+
+use crate::error::{Result, SmoothieError};
+use crate::services::device_service::{BluetoothDevice, DeviceService, UsbDevice};
+use crate::services::system_service::{
+  DisplayKind, InstalledApp, RunningApp, SystemMonitor, SystemService, SystemWindow, WindowSource,
+};
+use async_trait::async_trait;
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Access to displays, windows and applications of the host system
+#[async_trait]
+pub trait SystemBackend: Send + Sync {
+  /// Check Screen Recording permission without prompting
+  fn check_display_permission(&self) -> bool;
+
+  /// Send the user to the Screen Recording permission pane
+  fn request_display_permission(&self) -> bool;
+
+  fn get_monitors(&self) -> Vec<SystemMonitor>;
+
+  fn get_windows(&self) -> Vec<SystemWindow>;
+
+  fn get_running_apps(&self) -> Vec<RunningApp>;
+
+  /// The app in the foreground, if it can be determined
+  fn get_frontmost_app(&self) -> Option<RunningApp>;
+
+  fn get_installed_apps(&self) -> Vec<InstalledApp>;
+
+  /// Monitors, windows and running apps captured in one pass
+  fn capture_system_layout(&self) -> (Vec<SystemMonitor>, Vec<SystemWindow>, Vec<RunningApp>);
+
+  /// Apply a monitor layout by running displayplacer directly
+  fn apply_monitor_layout(&self, monitors: Vec<SystemMonitor>) -> Result<()>;
+
+  /// Apply a monitor layout through AppleScript (prompts for admin rights)
+  async fn apply_monitor_layout_applescript(&self, monitors: &[SystemMonitor]) -> Result<()>;
+
+  /// Turn a display black until it is unblanked or the app exits
+  fn blank_display(&self, display_id: u32) -> Result<()>;
+
+  fn unblank_display(&self, display_id: u32) -> Result<()>;
+
+  /// Displays currently blanked by [`blank_display`](Self::blank_display)
+  fn blanked_displays(&self) -> Vec<u32>;
+
+  /// Show a native notification
+  fn show_notification(&self, title: &str, body: &str) -> Result<()>;
+
+  /// Move and resize a live window to the given frame
+  fn move_window(
+    &self,
+    window: &SystemWindow,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+  ) -> Result<()>;
+
+  /// Reset a live window's zoom and step it in (positive) or out, as Cmd-0
+  /// followed by Cmd-= or Cmd-- would
+  fn zoom_window(&self, window: &SystemWindow, steps: i32) -> Result<()>;
+
+  /// Hide a running app's windows, as Cmd-H does
+  fn hide_app(&self, app: &RunningApp) -> Result<()>;
+
+  /// Ask a running app to quit, as Cmd-Q does
+  fn quit_app(&self, app: &RunningApp) -> Result<()>;
+
+  /// Attached USB and Thunderbolt devices (docks, keyboards, ...)
+  fn get_usb_devices(&self) -> Vec<UsbDevice>;
+
+  /// Connected Bluetooth devices (headphones, keyboards, ...)
+  fn get_bluetooth_devices(&self) -> Vec<BluetoothDevice>;
+}
+
+/// Build the backend selected by `SMOOTHIE_SYSTEM_BACKEND` (`macos` by default)
+pub fn from_env() -> Arc<dyn SystemBackend> {
+  match std::env::var("SMOOTHIE_SYSTEM_BACKEND").as_deref() {
+    Ok("mock") => {
+      tracing::warn!("Using mock system backend - no real displays or windows will be touched");
+      Arc::new(MockSystemBackend::with_sample_layout())
+    }
+    _ => Arc::new(MacSystemBackend),
+  }
+}
+
+// ============================================================================
+// macOS backend
+// ============================================================================
+
+/// Real backend backed by CoreGraphics, displayplacer and friends
+pub struct MacSystemBackend;
+
+#[async_trait]
+impl SystemBackend for MacSystemBackend {
+  fn check_display_permission(&self) -> bool {
+    SystemService::check_display_permission()
+  }
+
+  fn request_display_permission(&self) -> bool {
+    SystemService::request_display_permission()
+  }
+
+  fn get_monitors(&self) -> Vec<SystemMonitor> {
+    SystemService::get_monitors()
+  }
+
+  fn get_windows(&self) -> Vec<SystemWindow> {
+    SystemService::get_windows()
+  }
+
+  fn get_running_apps(&self) -> Vec<RunningApp> {
+    SystemService::get_running_apps()
+  }
+
+  fn get_frontmost_app(&self) -> Option<RunningApp> {
+    SystemService::get_frontmost_app()
+  }
+
+  fn get_installed_apps(&self) -> Vec<InstalledApp> {
+    SystemService::get_installed_apps()
+  }
+
+  fn capture_system_layout(&self) -> (Vec<SystemMonitor>, Vec<SystemWindow>, Vec<RunningApp>) {
+    SystemService::capture_system_layout()
+  }
+
+  fn apply_monitor_layout(&self, monitors: Vec<SystemMonitor>) -> Result<()> {
+    SystemService::apply_monitor_layout(monitors)
+  }
+
+  async fn apply_monitor_layout_applescript(&self, monitors: &[SystemMonitor]) -> Result<()> {
+    SystemService::apply_monitor_layout_applescript(monitors).await
+  }
+
+  fn blank_display(&self, display_id: u32) -> Result<()> {
+    SystemService::blank_display(display_id)
+  }
+
+  fn unblank_display(&self, display_id: u32) -> Result<()> {
+    SystemService::unblank_display(display_id)
+  }
+
+  fn blanked_displays(&self) -> Vec<u32> {
+    SystemService::blanked_displays()
+  }
+
+  fn show_notification(&self, title: &str, body: &str) -> Result<()> {
+    SystemService::show_notification(title, body)
+  }
+
+  fn move_window(
+    &self,
+    window: &SystemWindow,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+  ) -> Result<()> {
+    SystemService::move_window(window, x, y, width, height)
+  }
+
+  fn zoom_window(&self, window: &SystemWindow, steps: i32) -> Result<()> {
+    SystemService::zoom_window(window, steps)
+  }
+
+  fn hide_app(&self, app: &RunningApp) -> Result<()> {
+    SystemService::hide_app(app)
+  }
+
+  fn quit_app(&self, app: &RunningApp) -> Result<()> {
+    SystemService::quit_app(app)
+  }
+
+  fn get_usb_devices(&self) -> Vec<UsbDevice> {
+    DeviceService::get_usb_devices()
+  }
+
+  fn get_bluetooth_devices(&self) -> Vec<BluetoothDevice> {
+    DeviceService::get_bluetooth_devices()
+  }
+}
+
+// ============================================================================
+// Mock backend
+// ============================================================================
+
+/// In-memory backend with a configurable system state.
+///
+/// Applied layouts are recorded and also move the mock's monitors, so reading
+/// the monitors back after an apply reflects the new arrangement.
+#[derive(Default)]
+pub struct MockSystemBackend {
+  monitors: RwLock<Vec<SystemMonitor>>,
+  windows: RwLock<Vec<SystemWindow>>,
+  running_apps: RwLock<Vec<RunningApp>>,
+  installed_apps: RwLock<Vec<InstalledApp>>,
+  usb_devices: RwLock<Vec<UsbDevice>>,
+  bluetooth_devices: RwLock<Vec<BluetoothDevice>>,
+  has_permission: RwLock<bool>,
+  applied_layouts: Mutex<Vec<Vec<SystemMonitor>>>,
+  notifications: Mutex<Vec<(String, String)>>,
+  zoomed: Mutex<Vec<(u32, i32)>>,
+  blanked: Mutex<HashSet<u32>>,
+}
+
+impl MockSystemBackend {
+  /// A laptop with an external display, two windows and the apps that own them
+  pub fn with_sample_layout() -> Self {
+    let backend = Self::default();
+    *backend.has_permission.write() = true;
+    backend.set_monitors(vec![
+      Self::monitor(1, "Built-in Display", 0, 0, 1512, 982, true, true),
+      Self::monitor(2, "DELL U2720Q", 1512, -200, 2560, 1440, false, false),
+    ]);
+    backend.set_windows(vec![
+      Self::window(101, 501, "Safari", "com.apple.Safari", 0, 25, 1512, 957, 1),
+      Self::window(
+        102,
+        502,
+        "Terminal",
+        "com.apple.Terminal",
+        1600,
+        0,
+        1200,
+        800,
+        2,
+      ),
+    ]);
+    *backend.running_apps.write() = vec![
+      Self::running_app(501, "Safari", "com.apple.Safari", true, 1),
+      Self::running_app(502, "Terminal", "com.apple.Terminal", false, 1),
+    ];
+    *backend.installed_apps.write() = vec![
+      Self::installed_app("Safari", "com.apple.Safari"),
+      Self::installed_app("Terminal", "com.apple.Terminal"),
+    ];
+    backend
+  }
+
+  pub fn set_monitors(&self, monitors: Vec<SystemMonitor>) {
+    *self.monitors.write() = monitors;
+  }
+
+  pub fn set_windows(&self, windows: Vec<SystemWindow>) {
+    *self.windows.write() = windows;
+  }
+
+  /// Bring the running app with `bundle_id` to the front
+  #[cfg(test)]
+  pub fn set_frontmost_app(&self, bundle_id: &str) {
+    for app in self.running_apps.write().iter_mut() {
+      app.is_active = app.bundle_id == bundle_id;
+    }
+  }
+
+  /// Layouts passed to `apply_monitor_layout*`, oldest first
+  #[cfg(test)]
+  pub fn applied_layouts(&self) -> Vec<Vec<SystemMonitor>> {
+    self.applied_layouts.lock().clone()
+  }
+
+  /// Notifications shown as (title, body), oldest first
+  #[cfg(test)]
+  pub fn notifications(&self) -> Vec<(String, String)> {
+    self.notifications.lock().clone()
+  }
+
+  /// Zooms applied as (window id, steps), oldest first
+  #[cfg(test)]
+  pub fn zoomed_windows(&self) -> Vec<(u32, i32)> {
+    self.zoomed.lock().clone()
+  }
+
+  fn record_layout(&self, layout: &[SystemMonitor]) -> Result<()> {
+    if layout.is_empty() {
+      return Err(SmoothieError::ValidationError(
+        "No monitors provided".into(),
+      ));
+    }
+
+    let mut monitors = self.monitors.write();
+    for target in layout {
+      if let Some(monitor) = monitors
+        .iter_mut()
+        .find(|m| m.display_id == target.display_id)
+      {
+        monitor.x = target.x;
+        monitor.y = target.y;
+      }
+    }
+    self.applied_layouts.lock().push(layout.to_vec());
+    Ok(())
+  }
+
+  fn monitor(
+    display_id: u32,
+    name: &str,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    is_primary: bool,
+    is_builtin: bool,
+  ) -> SystemMonitor {
+    SystemMonitor {
+      display_id,
+      name: name.to_string(),
+      brand: Some(if is_builtin { "Apple" } else { "Dell" }.to_string()),
+      model: None,
+      resolution: format!("{}x{}", width, height),
+      width,
+      height,
+      x,
+      y,
+      scale_factor: if is_builtin { 2.0 } else { 1.0 },
+      refresh_rate: 60.0,
+      is_primary,
+      is_builtin,
+      orientation: if width > height {
+        "Landscape"
+      } else {
+        "Portrait"
+      }
+      .to_string(),
+      kind: DisplayKind::Physical,
+    }
+  }
+
+  fn window(
+    window_id: u32,
+    pid: u32,
+    app_name: &str,
+    bundle_id: &str,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    display_id: u32,
+  ) -> SystemWindow {
+    SystemWindow {
+      window_id,
+      pid,
+      title: format!("{} window", app_name),
+      app_name: app_name.to_string(),
+      bundle_id: bundle_id.to_string(),
+      x,
+      y,
+      width,
+      height,
+      display_id,
+      is_minimized: false,
+      is_fullscreen: false,
+      layer: 0,
+      source: WindowSource::OnScreen,
+    }
+  }
+
+  fn running_app(
+    pid: u32,
+    name: &str,
+    bundle_id: &str,
+    is_active: bool,
+    window_count: u32,
+  ) -> RunningApp {
+    RunningApp {
+      pid,
+      name: name.to_string(),
+      bundle_id: bundle_id.to_string(),
+      path: Some(format!("/Applications/{}.app", name)),
+      is_active,
+      is_hidden: false,
+      window_count,
+    }
+  }
+
+  fn installed_app(name: &str, bundle_id: &str) -> InstalledApp {
+    InstalledApp {
+      name: name.to_string(),
+      bundle_id: bundle_id.to_string(),
+      path: format!("/Applications/{}.app", name),
+      version: Some("1.0".to_string()),
+      category: None,
+    }
+  }
+}
+
+#[async_trait]
+impl SystemBackend for MockSystemBackend {
+  fn check_display_permission(&self) -> bool {
+    *self.has_permission.read()
+  }
+
+  fn request_display_permission(&self) -> bool {
+    *self.has_permission.write() = true;
+    true
+  }
+
+  fn get_monitors(&self) -> Vec<SystemMonitor> {
+    self.monitors.read().clone()
+  }
+
+  fn get_windows(&self) -> Vec<SystemWindow> {
+    self.windows.read().clone()
+  }
+
+  fn get_running_apps(&self) -> Vec<RunningApp> {
+    self.running_apps.read().clone()
+  }
+
+  fn get_frontmost_app(&self) -> Option<RunningApp> {
+    self
+      .running_apps
+      .read()
+      .iter()
+      .find(|app| app.is_active)
+      .cloned()
+  }
+
+  fn get_installed_apps(&self) -> Vec<InstalledApp> {
+    self.installed_apps.read().clone()
+  }
+
+  fn capture_system_layout(&self) -> (Vec<SystemMonitor>, Vec<SystemWindow>, Vec<RunningApp>) {
+    (
+      self.get_monitors(),
+      self.get_windows(),
+      self.get_running_apps(),
+    )
+  }
+
+  fn apply_monitor_layout(&self, monitors: Vec<SystemMonitor>) -> Result<()> {
+    self.record_layout(&monitors)
+  }
+
+  async fn apply_monitor_layout_applescript(&self, monitors: &[SystemMonitor]) -> Result<()> {
+    self.record_layout(monitors)
+  }
+
+  fn blank_display(&self, display_id: u32) -> Result<()> {
+    if !self
+      .monitors
+      .read()
+      .iter()
+      .any(|m| m.display_id == display_id)
+    {
+      return Err(SmoothieError::SystemError(format!(
+        "Display {} isn't connected",
+        display_id
+      )));
+    }
+    self.blanked.lock().insert(display_id);
+    Ok(())
+  }
+
+  fn unblank_display(&self, display_id: u32) -> Result<()> {
+    self.blanked.lock().remove(&display_id);
+    Ok(())
+  }
+
+  fn blanked_displays(&self) -> Vec<u32> {
+    let mut blanked: Vec<u32> = self.blanked.lock().iter().copied().collect();
+    blanked.sort_unstable();
+    blanked
+  }
+
+  fn show_notification(&self, title: &str, body: &str) -> Result<()> {
+    self
+      .notifications
+      .lock()
+      .push((title.to_string(), body.to_string()));
+    Ok(())
+  }
+
+  fn move_window(
+    &self,
+    window: &SystemWindow,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+  ) -> Result<()> {
+    let mut windows = self.windows.write();
+    let target = windows
+      .iter_mut()
+      .find(|w| w.window_id == window.window_id)
+      .ok_or_else(|| SmoothieError::SystemError(format!("No window {}", window.window_id)))?;
+    target.x = x;
+    target.y = y;
+    target.width = width;
+    target.height = height;
+    Ok(())
+  }
+
+  fn zoom_window(&self, window: &SystemWindow, steps: i32) -> Result<()> {
+    if !self
+      .windows
+      .read()
+      .iter()
+      .any(|w| w.window_id == window.window_id)
+    {
+      return Err(SmoothieError::SystemError(format!(
+        "No window {}",
+        window.window_id
+      )));
+    }
+    self.zoomed.lock().push((window.window_id, steps));
+    Ok(())
+  }
+
+  fn hide_app(&self, app: &RunningApp) -> Result<()> {
+    let mut running = self.running_apps.write();
+    let target = running
+      .iter_mut()
+      .find(|a| a.pid == app.pid)
+      .ok_or_else(|| SmoothieError::SystemError(format!("{} isn't running", app.name)))?;
+    target.is_hidden = true;
+    target.is_active = false;
+    Ok(())
+  }
+
+  fn quit_app(&self, app: &RunningApp) -> Result<()> {
+    let mut running = self.running_apps.write();
+    let before = running.len();
+    running.retain(|a| a.pid != app.pid);
+    if running.len() == before {
+      return Err(SmoothieError::SystemError(format!(
+        "{} isn't running",
+        app.name
+      )));
+    }
+    self.windows.write().retain(|w| w.pid != app.pid);
+    Ok(())
+  }
+
+  fn get_usb_devices(&self) -> Vec<UsbDevice> {
+    self.usb_devices.read().clone()
+  }
+
+  fn get_bluetooth_devices(&self) -> Vec<BluetoothDevice> {
+    self.bluetooth_devices.read().clone()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn backend() -> MockSystemBackend {
+    MockSystemBackend::with_sample_layout()
+  }
+
+  #[test]
+  fn test_detect_monitors() {
+    let monitors = backend().get_monitors();
+
+    // Should have at least one monitor
+    assert!(!monitors.is_empty(), "Should detect at least one monitor");
+
+    // Should have exactly one primary monitor
+    let primary_count = monitors.iter().filter(|m| m.is_primary).count();
+    assert_eq!(primary_count, 1, "Should have exactly one primary monitor");
+  }
+
+  #[test]
+  fn test_detect_windows() {
+    let windows = backend().get_windows();
+    assert!(!windows.is_empty());
+
+    // Basic sanity checks on detected windows
+    for window in &windows {
+      assert!(!window.app_name.is_empty(), "Window should have app name");
+      assert!(window.width >= 50, "Window should have reasonable width");
+      assert!(window.height >= 50, "Window should have reasonable height");
+    }
+  }
+
+  #[test]
+  fn test_detect_running_apps() {
+    let apps = backend().get_running_apps();
+
+    assert!(!apps.is_empty(), "Should detect at least one running app");
+    assert_eq!(apps.iter().filter(|a| a.is_active).count(), 1);
+  }
+
+  #[test]
+  fn test_monitor_data_integrity() {
+    let monitors = backend().get_monitors();
+
+    for monitor in &monitors {
+      // Validate resolution string format
+      assert!(
+        monitor.resolution.contains('x'),
+        "Resolution should be in WxH format"
+      );
+
+      // Validate dimensions are positive
+      assert!(monitor.width > 0, "Width should be positive");
+      assert!(monitor.height > 0, "Height should be positive");
+
+      // Validate scale factor is reasonable
+      assert!(
+        monitor.scale_factor >= 1.0 && monitor.scale_factor <= 3.0,
+        "Scale factor should be between 1.0 and 3.0"
+      );
+
+      // Validate orientation matches dimensions
+      let expected_orientation = if monitor.width > monitor.height {
+        "Landscape"
+      } else {
+        "Portrait"
+      };
+      assert_eq!(
+        monitor.orientation, expected_orientation,
+        "Orientation should match dimensions"
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn test_apply_monitor_layout() {
+    let backend = backend();
+    let mut layout = backend.get_monitors();
+    layout[1].x = -2560;
+    layout[1].y = 0;
+
+    backend
+      .apply_monitor_layout_applescript(&layout)
+      .await
+      .unwrap();
+
+    assert_eq!(backend.applied_layouts().len(), 1);
+    let external = &backend.get_monitors()[1];
+    assert_eq!((external.x, external.y), (-2560, 0));
+
+    // An empty layout is rejected, like the real backend
+    assert!(backend.apply_monitor_layout(Vec::new()).is_err());
+  }
+}
@@ -0,0 +1,248 @@
+// Team workspaces
+//
+// Teams, memberships and team libraries live in Supabase; `sync` mirrors the
+// parts the current user can see into the local database so libraries can be
+// browsed offline. Library entries are read-only copies in the same sanitized
+// form as shared profiles: admins publish them, and members clone them into
+// ordinary local profiles.
+//
+// Requests go out as the signed-in user, and the project's row-level security
+// policies decide what they may read and write. The local role check in
+// `security::middleware::authorize_team` only fails early with a clearer
+// message; the policies are what enforce it:
+//
+//   CREATE FUNCTION team_role(team UUID) RETURNS TEXT
+//     LANGUAGE sql STABLE SECURITY DEFINER SET search_path = public AS
+//     $$ SELECT role FROM team_members WHERE team_id = team AND user_id = auth.uid() $$;
+//
+//   ALTER TABLE organizations ENABLE ROW LEVEL SECURITY;
+//   ALTER TABLE teams ENABLE ROW LEVEL SECURITY;
+//   ALTER TABLE team_members ENABLE ROW LEVEL SECURITY;
+//   ALTER TABLE team_profiles ENABLE ROW LEVEL SECURITY;
+//
+//   CREATE POLICY member_read ON teams FOR SELECT USING (team_role(id) IS NOT NULL);
+//   CREATE POLICY member_read ON team_members FOR SELECT
+//     USING (team_role(team_id) IS NOT NULL);
+//   CREATE POLICY member_read ON organizations FOR SELECT
+//     USING (id IN (SELECT organization_id FROM teams));
+//   CREATE POLICY member_read ON team_profiles FOR SELECT
+//     USING (team_role(team_id) IS NOT NULL);
+//   CREATE POLICY admin_publish ON team_profiles FOR INSERT
+//     WITH CHECK (team_role(team_id) = 'admin' AND published_by = auth.uid());
+
+use crate::{
+  db::{supabase::SupabaseClient, Database},
+  error::{Result, SmoothieError},
+  models::{
+    entities::{OrganizationEntity, TeamEntity, TeamLibraryProfileEntity, TeamMemberEntity},
+    ProfileDto, ProfileId, TeamDto, TeamId, TeamLibraryProfileDto, UserId,
+  },
+  repositories::{TeamRepository, TeamSnapshot},
+  security::middleware::authorize_team,
+  services::{
+    share_service::{ShareService, SharedProfile},
+    ProfileService,
+  },
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+const FEATURE: &str = "Team libraries";
+
+pub struct TeamService;
+
+/// Client acting as the signed-in user, so the team policies apply
+fn user_client() -> Result<SupabaseClient> {
+  let client = SupabaseClient::require_env(FEATURE)?;
+  if !client.is_signed_in() {
+    return Err(SmoothieError::ValidationError(format!(
+      "{} requires {}",
+      FEATURE,
+      crate::db::supabase::SUPABASE_REFRESH_TOKEN_ENV
+    )));
+  }
+  Ok(client)
+}
+
+impl TeamService {
+  /// Refresh the local copy of the user's teams and team libraries from Supabase
+  pub async fn sync(db: &Database, user_id: UserId) -> Result<Vec<TeamDto>> {
+    let client = user_client()?;
+    let snapshot = Self::fetch_snapshot(&client, user_id).await?;
+
+    ProfileService::ensure_user_exists(db, user_id.as_uuid()).await?;
+    TeamRepository::new(db.pool())
+      .replace_for_user(user_id.as_uuid(), &snapshot)
+      .await?;
+
+    tracing::info!(
+      user_id = %user_id,
+      teams = snapshot.teams.len(),
+      library_profiles = snapshot.library.len(),
+      "Team libraries synced"
+    );
+
+    Self::get_teams(db, user_id).await
+  }
+
+  /// Teams the user belongs to, as of the last sync
+  pub async fn get_teams(db: &Database, user_id: UserId) -> Result<Vec<TeamDto>> {
+    let rows = TeamRepository::new(db.pool())
+      .find_teams(user_id.as_uuid())
+      .await?;
+    Ok(rows.into_iter().map(TeamDto::from).collect())
+  }
+
+  pub async fn get_library(
+    db: &Database,
+    user_id: UserId,
+    team_id: TeamId,
+  ) -> Result<Vec<TeamLibraryProfileDto>> {
+    let repo = TeamRepository::new(db.pool());
+    let role = repo.find_role(team_id.as_uuid(), user_id.as_uuid()).await?;
+    authorize_team("get_team_library", role)?;
+
+    let entries = repo.find_library(team_id.as_uuid()).await?;
+    Ok(
+      entries
+        .into_iter()
+        .filter_map(|entry| match library_dto(entry) {
+          Ok(dto) => Some(dto),
+          Err(e) => {
+            tracing::warn!(error = %e, "Skipping unreadable team library profile");
+            None
+          }
+        })
+        .collect(),
+    )
+  }
+
+  /// Create a local profile from a team library entry
+  pub async fn clone_profile(
+    db: &Database,
+    user_id: UserId,
+    library_profile_id: Uuid,
+  ) -> Result<ProfileDto> {
+    let repo = TeamRepository::new(db.pool());
+    let entry = repo
+      .find_library_profile(library_profile_id)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound("Team library profile not found".into()))?;
+    let role = repo.find_role(entry.team_id, user_id.as_uuid()).await?;
+    authorize_team("clone_team_profile", role)?;
+
+    let shared: SharedProfile = serde_json::from_value(entry.document)?;
+    ShareService::create_from_shared(db, user_id, shared).await
+  }
+
+  /// Publish a sanitized copy of one of the user's profiles to a team library
+  pub async fn publish_profile(
+    db: &Database,
+    user_id: UserId,
+    team_id: TeamId,
+    profile_id: ProfileId,
+  ) -> Result<TeamLibraryProfileDto> {
+    let repo = TeamRepository::new(db.pool());
+    let role = repo.find_role(team_id.as_uuid(), user_id.as_uuid()).await?;
+    authorize_team("publish_team_profile", role)?;
+
+    let client = user_client()?;
+    if ProfileService::get_profile(db, profile_id).await?.user_id != user_id {
+      return Err(SmoothieError::NotFound("Profile not found".into()));
+    }
+    let shared = ShareService::shareable_copy(db, profile_id).await?;
+
+    let entry = TeamLibraryProfileEntity {
+      id: Uuid::new_v4(),
+      team_id: team_id.as_uuid(),
+      name: shared.profile.name.clone(),
+      published_by: user_id.as_uuid(),
+      published_at: Utc::now(),
+      document: serde_json::to_value(&shared)?,
+    };
+    // Refused by the admin_publish policy unless the user is a team admin
+    client.insert("team_profiles", &entry).await?;
+    repo.insert_library_profile(&entry).await?;
+
+    tracing::info!(team_id = %team_id, profile_id = %profile_id, "Profile published to team library");
+
+    library_dto(entry)
+  }
+
+  async fn fetch_snapshot(client: &SupabaseClient, user_id: UserId) -> Result<TeamSnapshot> {
+    let members: Vec<TeamMemberEntity> = client
+      .select(
+        "team_members",
+        &[
+          ("user_id", format!("eq.{}", user_id)),
+          ("order", "team_id".to_string()),
+        ],
+      )
+      .await?;
+    if members.is_empty() {
+      return Ok(TeamSnapshot::default());
+    }
+
+    let teams: Vec<TeamEntity> = client
+      .select(
+        "teams",
+        &[
+          ("id", in_list(members.iter().map(|m| m.team_id))),
+          ("order", "id".to_string()),
+        ],
+      )
+      .await?;
+    let organizations: Vec<OrganizationEntity> = client
+      .select(
+        "organizations",
+        &[
+          ("id", in_list(teams.iter().map(|t| t.organization_id))),
+          ("order", "id".to_string()),
+        ],
+      )
+      .await?;
+    let library: Vec<TeamLibraryProfileEntity> = client
+      .select(
+        "team_profiles",
+        &[
+          ("team_id", in_list(teams.iter().map(|t| t.id))),
+          (
+            "select",
+            "id,team_id,name,published_by,published_at,document".to_string(),
+          ),
+          ("order", "id".to_string()),
+        ],
+      )
+      .await?;
+
+    Ok(TeamSnapshot {
+      organizations,
+      teams,
+      members,
+      library,
+    })
+  }
+}
+
+/// PostgREST `in.(…)` filter value
+fn in_list(ids: impl Iterator<Item = Uuid>) -> String {
+  let ids: Vec<String> = ids.map(|id| id.to_string()).collect();
+  format!("in.({})", ids.join(","))
+}
+
+fn library_dto(entry: TeamLibraryProfileEntity) -> Result<TeamLibraryProfileDto> {
+  let shared: SharedProfile = serde_json::from_value(entry.document)?;
+  Ok(TeamLibraryProfileDto {
+    id: entry.id.to_string(),
+    team_id: entry.team_id.into(),
+    name: entry.name,
+    description: shared.profile.description,
+    profile_type: shared.profile.profile_type,
+    tags: shared.profile.tags.unwrap_or_default(),
+    monitor_count: shared.monitors.len() as i64,
+    app_count: shared.apps.len() as i64,
+    browser_tab_count: shared.browser_tabs.len() as i64,
+    published_by: entry.published_by.into(),
+    published_at: entry.published_at.to_rfc3339(),
+  })
+}
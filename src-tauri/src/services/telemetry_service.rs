@@ -0,0 +1,215 @@
+//! Telemetry Service
+//!
+//! Off unless the user turns it on. Once a week an opted-in install sends a
+//! handful of counters to the `telemetry_reports` table: how many activations
+//! ran in the last seven days, the macOS version to major.minor, how many
+//! displays are connected and how many profiles lay out each number of
+//! monitors. No ids, names, paths or timestamps finer than the ISO week go
+//! out, and `preview` builds exactly the report that would be sent.
+
+use crate::{
+  db::{supabase::SupabaseClient, Database},
+  error::{Result, SmoothieError},
+  models::UserId,
+  repositories::{AuditRepository, ProfileRepository, UserSettingsRepository},
+  services::{EnvironmentService, SystemBackend},
+  state::AppState,
+};
+use chrono::{DateTime, Datelike, Duration, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+
+pub const TELEMETRY_TABLE: &str = "telemetry_reports";
+
+/// Version of the report, bumped whenever a counter is added or changed
+const TELEMETRY_FORMAT: u32 = 1;
+
+/// How often the sender checks whether a report is due
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(6 * 60 * 60);
+
+/// Time between reports
+const REPORT_PERIOD_DAYS: i64 = 7;
+
+/// Monitors per profile above this are reported together, so an unusual
+/// setup doesn't single an install out
+const MAX_REPORTED_MONITORS: i64 = 6;
+
+/// Number of profiles laying out `monitors` displays
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorCountBucket {
+  /// The last bucket also counts profiles with more monitors
+  pub monitors: i64,
+  pub profiles: i64,
+}
+
+/// Everything one report sends
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetryPayload {
+  pub format: u32,
+  /// ISO week the report covers, e.g. `2026-W42`
+  pub week: String,
+  pub app_version: String,
+  pub os_name: String,
+  /// macOS version to major.minor
+  pub os_version: Option<String>,
+  pub activations_last_week: i64,
+  pub connected_monitors: usize,
+  pub profiles_by_monitor_count: Vec<MonitorCountBucket>,
+}
+
+fn iso_week(at: DateTime<Utc>) -> String {
+  let week = at.iso_week();
+  format!("{}-W{:02}", week.year(), week.week())
+}
+
+/// `14.5.1` -> `14.5`
+fn major_minor(version: &str) -> String {
+  version
+    .trim()
+    .split('.')
+    .take(2)
+    .collect::<Vec<_>>()
+    .join(".")
+}
+
+/// Fold every count above `MAX_REPORTED_MONITORS` into its bucket
+fn bucket_monitor_counts(counts: Vec<(i64, i64)>) -> Vec<MonitorCountBucket> {
+  let mut buckets: Vec<MonitorCountBucket> = Vec::new();
+  for (monitors, profiles) in counts {
+    let monitors = monitors.min(MAX_REPORTED_MONITORS);
+    match buckets.iter_mut().find(|b| b.monitors == monitors) {
+      Some(bucket) => bucket.profiles += profiles,
+      None => buckets.push(MonitorCountBucket { monitors, profiles }),
+    }
+  }
+  buckets
+}
+
+fn is_due(sent_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+  sent_at.map_or(true, |sent| {
+    now - sent >= Duration::days(REPORT_PERIOD_DAYS)
+  })
+}
+
+pub struct TelemetryService;
+
+impl TelemetryService {
+  /// The report that would be sent now, whether or not telemetry is on
+  pub async fn preview(
+    db: &Database,
+    system: Arc<dyn SystemBackend>,
+    user_id: UserId,
+  ) -> Result<TelemetryPayload> {
+    let now = Utc::now();
+    let user_id = user_id.as_uuid();
+    let activations_last_week = AuditRepository::new(db.pool())
+      .count_activations_since(user_id, now - Duration::days(REPORT_PERIOD_DAYS))
+      .await?;
+    let monitor_counts = ProfileRepository::new(db.pool())
+      .count_by_monitor_count(user_id)
+      .await?;
+    let (os, connected_monitors) = tokio::task::spawn_blocking(move || {
+      (
+        EnvironmentService::get_os_info(),
+        system.get_monitors().len(),
+      )
+    })
+    .await
+    .map_err(|e| SmoothieError::SystemError(format!("Telemetry collection failed: {}", e)))?;
+
+    Ok(TelemetryPayload {
+      format: TELEMETRY_FORMAT,
+      week: iso_week(now),
+      app_version: env!("CARGO_PKG_VERSION").to_string(),
+      os_name: os.name,
+      os_version: os.version.as_deref().map(major_minor),
+      activations_last_week,
+      connected_monitors,
+      profiles_by_monitor_count: bucket_monitor_counts(monitor_counts),
+    })
+  }
+
+  /// Send a report if telemetry is on and the last one is a week old.
+  /// Returns whether one was sent.
+  pub async fn send_if_due(
+    db: &Database,
+    system: Arc<dyn SystemBackend>,
+    user_id: UserId,
+  ) -> Result<bool> {
+    let settings = UserSettingsRepository::new(db.pool())
+      .get_or_create(user_id.as_uuid())
+      .await?;
+    let now = Utc::now();
+    if !settings.telemetry_enabled || !is_due(settings.telemetry_sent_at, now) {
+      return Ok(false);
+    }
+    let Some(client) = SupabaseClient::from_env() else {
+      tracing::debug!("Telemetry is on but no Supabase project is configured");
+      return Ok(false);
+    };
+
+    let payload = Self::preview(db, system, user_id).await?;
+    client.insert(TELEMETRY_TABLE, &payload).await?;
+    UserSettingsRepository::new(db.pool())
+      .mark_telemetry_sent(user_id.as_uuid(), now)
+      .await?;
+
+    tracing::info!(week = %payload.week, "Telemetry report sent");
+    Ok(true)
+  }
+
+  /// Check for a due report every few hours in the background
+  pub fn spawn(state: Arc<AppState>, user_id: uuid::Uuid) {
+    let sender_state = state.clone();
+    state.tasks.spawn("telemetry", move || {
+      let state = sender_state.clone();
+      async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+          ticker.tick().await;
+          if let Err(e) = Self::send_if_due(&state.db, state.system.clone(), user_id.into()).await {
+            tracing::warn!("Telemetry report failed: {}", e);
+          }
+        }
+      }
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+
+  #[test]
+  fn test_report_is_coarse_and_weekly() {
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+    assert_eq!(iso_week(now), "2026-W42");
+    assert_eq!(major_minor("14.5.1"), "14.5");
+    assert_eq!(major_minor("15.0"), "15.0");
+
+    assert_eq!(
+      bucket_monitor_counts(vec![(1, 3), (2, 1), (6, 1), (9, 2)]),
+      vec![
+        MonitorCountBucket {
+          monitors: 1,
+          profiles: 3
+        },
+        MonitorCountBucket {
+          monitors: 2,
+          profiles: 1
+        },
+        MonitorCountBucket {
+          monitors: 6,
+          profiles: 3
+        },
+      ]
+    );
+
+    assert!(is_due(None, now));
+    assert!(!is_due(Some(now - Duration::days(6)), now));
+    assert!(is_due(Some(now - Duration::days(7)), now));
+  }
+}
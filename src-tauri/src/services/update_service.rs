@@ -0,0 +1,385 @@
+//! Update Service
+//!
+//! Checks a release feed for newer versions and downloads the installer for
+//! this platform, verified against the SHA-256 checksum the feed lists. The
+//! feed is the `latest.json` the Tauri updater reads, so a release publishes
+//! one file for both; its `signature` fields are left to that updater, and a
+//! release without a `sha256` can only be installed through it. The feed may
+//! also carry notes for earlier versions under `releases`, shown after an
+//! update. Update checks, downloads and the first launch of a new version are
+//! recorded as system events.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::Severity,
+  services::AUDIT_SERVICE,
+  utils::{crypto, paths},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Overrides where the release feed is read from, e.g. a beta channel
+pub const UPDATE_FEED_ENV: &str = "SMOOTHIE_UPDATE_FEED_URL";
+const DEFAULT_FEED_URL: &str =
+  "https://github.com/SinanGncgl/smoothie/releases/latest/download/latest.json";
+
+const FEED_TIMEOUT: Duration = Duration::from_secs(15);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Records the version that last ran, to notice the first launch after an update
+const LAST_VERSION_FILE: &str = "last-version";
+
+#[derive(Debug, Deserialize)]
+struct ReleaseFeed {
+  version: String,
+  notes: Option<String>,
+  pub_date: Option<String>,
+  #[serde(default)]
+  platforms: HashMap<String, PlatformAsset>,
+  #[serde(default)]
+  releases: Vec<ReleaseNotesDto>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlatformAsset {
+  url: String,
+  sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfoDto {
+  pub current_version: String,
+  pub latest_version: String,
+  pub available: bool,
+  pub notes: Option<String>,
+  pub pub_date: Option<String>,
+  /// Installer for this platform, if the release has one
+  pub download_url: Option<String>,
+  /// False when the release has no checksum, so only the Tauri updater can
+  /// install it
+  pub verifiable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseNotesDto {
+  pub version: String,
+  pub notes: Option<String>,
+  #[serde(alias = "pub_date")]
+  pub pub_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadedUpdateDto {
+  pub version: String,
+  /// The verified installer, ready to open
+  pub path: String,
+  pub sha256: String,
+}
+
+pub struct UpdateService;
+
+impl UpdateService {
+  pub fn current_version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+  }
+
+  /// Whether the feed has a newer version than this one
+  pub async fn check_for_updates(db: &Database) -> Result<UpdateInfoDto> {
+    let feed = fetch_feed().await?;
+    let info = update_info(&feed, Self::current_version(), &platform_key());
+
+    if info.available {
+      let _ = AUDIT_SERVICE
+        .log_system_event(
+          db,
+          "update_available",
+          Severity::Info,
+          "UpdateService",
+          &format!("Smoothie {} is available", info.latest_version),
+          Some(json!({
+            "current_version": info.current_version,
+            "latest_version": info.latest_version,
+          })),
+          None,
+        )
+        .await;
+    }
+    Ok(info)
+  }
+
+  /// Notes for `version`, by default the one running
+  pub async fn get_release_notes(version: Option<&str>) -> Result<ReleaseNotesDto> {
+    let feed = fetch_feed().await?;
+    let version = version.unwrap_or(Self::current_version());
+    release_notes(&feed, version)
+      .ok_or_else(|| SmoothieError::NotFound(format!("No release notes for {}", version)))
+  }
+
+  /// Download the newest installer for this platform into the app data
+  /// directory, keeping it only if its checksum matches the feed
+  pub async fn download_update(db: &Database) -> Result<DownloadedUpdateDto> {
+    let feed = fetch_feed().await?;
+    if compare_versions(&feed.version, Self::current_version()) != Ordering::Greater {
+      return Err(SmoothieError::ValidationError(
+        "Smoothie is already up to date".into(),
+      ));
+    }
+    let asset = feed.platforms.get(&platform_key()).ok_or_else(|| {
+      SmoothieError::NotFound(format!("No {} download for this Mac", feed.version))
+    })?;
+    let expected = asset.sha256.as_deref().ok_or_else(|| {
+      SmoothieError::ValidationError(
+        "This release has no checksum and can only be installed by the updater".into(),
+      )
+    })?;
+
+    let bytes = http_client(DOWNLOAD_TIMEOUT)?
+      .get(&asset.url)
+      .send()
+      .await
+      .and_then(|r| r.error_for_status())
+      .map_err(|e| network_error("Update download", e))?
+      .bytes()
+      .await
+      .map_err(|e| network_error("Update download", e))?;
+
+    let actual = crypto::sha256_hex(&bytes);
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+      let _ = AUDIT_SERVICE
+        .log_system_event(
+          db,
+          "update_verification_failed",
+          Severity::Error,
+          "UpdateService",
+          &format!("Download of Smoothie {} failed verification", feed.version),
+          Some(json!({ "url": asset.url, "expected": expected, "actual": actual })),
+          None,
+        )
+        .await;
+      return Err(SmoothieError::ValidationError(
+        "The download doesn't match its checksum and was discarded".into(),
+      ));
+    }
+
+    let dir = paths::app_data_dir().join("updates");
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = dir.join(installer_name(&asset.url, &feed.version));
+    paths::write_atomically(&path, &bytes).await?;
+
+    let path = path.to_string_lossy().into_owned();
+    let _ = AUDIT_SERVICE
+      .log_system_event(
+        db,
+        "update_downloaded",
+        Severity::Info,
+        "UpdateService",
+        &format!("Smoothie {} downloaded and verified", feed.version),
+        Some(json!({ "version": feed.version, "path": path })),
+        None,
+      )
+      .await;
+
+    Ok(DownloadedUpdateDto {
+      version: feed.version,
+      path,
+      sha256: actual,
+    })
+  }
+
+  /// Record an `app_updated` event on the first launch of a new version
+  pub async fn record_launch(db: &Database) {
+    let marker = paths::app_data_dir().join(LAST_VERSION_FILE);
+    let current = Self::current_version();
+    let previous = tokio::fs::read_to_string(&marker).await.ok();
+    if previous.as_deref().map(str::trim) == Some(current) {
+      return;
+    }
+
+    if let Some(previous) = previous {
+      let _ = AUDIT_SERVICE
+        .log_system_event(
+          db,
+          "app_updated",
+          Severity::Info,
+          "UpdateService",
+          &format!("Updated from {} to {}", previous.trim(), current),
+          Some(json!({ "from": previous.trim(), "to": current })),
+          None,
+        )
+        .await;
+    }
+    if let Some(dir) = marker.parent() {
+      let _ = tokio::fs::create_dir_all(dir).await;
+    }
+    if let Err(e) = tokio::fs::write(&marker, current).await {
+      tracing::warn!("Failed to record the running version: {}", e);
+    }
+  }
+}
+
+fn http_client(timeout: Duration) -> Result<reqwest::Client> {
+  reqwest::Client::builder()
+    .timeout(timeout)
+    .build()
+    .map_err(|e| SmoothieError::SystemError(format!("HTTP client: {}", e)))
+}
+
+fn network_error(what: &str, e: reqwest::Error) -> SmoothieError {
+  if e.is_timeout() {
+    SmoothieError::Timeout(what.to_string())
+  } else {
+    SmoothieError::SystemError(format!("{}: {}", what, e))
+  }
+}
+
+async fn fetch_feed() -> Result<ReleaseFeed> {
+  let url = std::env::var(UPDATE_FEED_ENV)
+    .ok()
+    .filter(|v| !v.trim().is_empty())
+    .unwrap_or_else(|| DEFAULT_FEED_URL.to_string());
+  http_client(FEED_TIMEOUT)?
+    .get(url.trim())
+    .send()
+    .await
+    .and_then(|r| r.error_for_status())
+    .map_err(|e| network_error("Release feed", e))?
+    .json()
+    .await
+    .map_err(|e| SmoothieError::SerializationError(format!("Release feed: {}", e)))
+}
+
+/// The Tauri updater's name for this platform, e.g. `darwin-aarch64`
+fn platform_key() -> String {
+  let os = match std::env::consts::OS {
+    "macos" => "darwin",
+    other => other,
+  };
+  format!("{}-{}", os, std::env::consts::ARCH)
+}
+
+/// Compare dotted versions numerically, ignoring a leading `v` and any
+/// pre-release or build suffix
+fn compare_versions(a: &str, b: &str) -> Ordering {
+  let parts = |v: &str| -> Vec<u64> {
+    v.trim()
+      .trim_start_matches('v')
+      .split(['-', '+'])
+      .next()
+      .unwrap_or_default()
+      .split('.')
+      .map(|p| p.parse().unwrap_or(0))
+      .collect()
+  };
+  let (a, b) = (parts(a), parts(b));
+  (0..a.len().max(b.len()))
+    .map(|i| a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)))
+    .find(|o| o.is_ne())
+    .unwrap_or(Ordering::Equal)
+}
+
+fn update_info(feed: &ReleaseFeed, current: &str, platform: &str) -> UpdateInfoDto {
+  let asset = feed.platforms.get(platform);
+  UpdateInfoDto {
+    current_version: current.to_string(),
+    latest_version: feed.version.clone(),
+    available: compare_versions(&feed.version, current) == Ordering::Greater,
+    notes: feed.notes.clone(),
+    pub_date: feed.pub_date.clone(),
+    download_url: asset.map(|a| a.url.clone()),
+    verifiable: asset.is_some_and(|a| a.sha256.is_some()),
+  }
+}
+
+fn release_notes(feed: &ReleaseFeed, version: &str) -> Option<ReleaseNotesDto> {
+  if compare_versions(&feed.version, version) == Ordering::Equal {
+    return Some(ReleaseNotesDto {
+      version: feed.version.clone(),
+      notes: feed.notes.clone(),
+      pub_date: feed.pub_date.clone(),
+    });
+  }
+  feed
+    .releases
+    .iter()
+    .find(|r| compare_versions(&r.version, version) == Ordering::Equal)
+    .cloned()
+}
+
+/// File name for the download: the URL's last segment when it looks like
+/// one, so `Smoothie_1.2.0_aarch64.dmg` keeps its extension
+fn installer_name(url: &str, version: &str) -> String {
+  let last = url
+    .split(['?', '#'])
+    .next()
+    .unwrap_or_default()
+    .rsplit('/')
+    .next()
+    .unwrap_or_default();
+  let safe = !last.is_empty()
+    && !last.starts_with('.')
+    && last
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+  if safe {
+    last.to_string()
+  } else {
+    format!(
+      "Smoothie_{}.update",
+      version.replace(|c: char| !c.is_ascii_alphanumeric() && c != '.', "_")
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_feed_compares_versions_and_finds_notes() {
+    let feed: ReleaseFeed = serde_json::from_value(json!({
+      "version": "v1.10.0",
+      "notes": "Faster activation",
+      "pub_date": "2026-10-01T00:00:00Z",
+      "platforms": {
+        "darwin-aarch64": {
+          "url": "https://example.com/Smoothie_1.10.0_aarch64.dmg?x=1",
+          "signature": "untrusted comment: ...",
+          "sha256": "ab12"
+        },
+        "darwin-x86_64": { "url": "https://example.com/x64.dmg", "signature": "..." }
+      },
+      "releases": [{ "version": "1.9.2", "notes": "Bug fixes", "pub_date": "2026-09-01" }]
+    }))
+    .unwrap();
+
+    assert_eq!(compare_versions("1.10.0", "1.9.2"), Ordering::Greater);
+    assert_eq!(compare_versions("1.0", "1.0.0-beta.1"), Ordering::Equal);
+
+    let info = update_info(&feed, "1.9.2", "darwin-aarch64");
+    assert!(info.available && info.verifiable);
+    assert!(!update_info(&feed, "1.9.2", "darwin-x86_64").verifiable);
+    assert!(!update_info(&feed, "1.10.0", "darwin-aarch64").available);
+
+    assert_eq!(
+      release_notes(&feed, "1.9.2").unwrap().notes.as_deref(),
+      Some("Bug fixes")
+    );
+    assert!(release_notes(&feed, "1.8.0").is_none());
+
+    assert_eq!(
+      installer_name(&info.download_url.unwrap(), "1.10.0"),
+      "Smoothie_1.10.0_aarch64.dmg"
+    );
+    assert_eq!(
+      installer_name("https://example.com/latest/", "1.10.0"),
+      "Smoothie_1.10.0.update"
+    );
+  }
+}
@@ -0,0 +1,486 @@
+//! Usage Report Service
+//!
+//! Renders a Markdown or HTML summary of a range of days - profiles used,
+//! time spent under each, the apps most in front and the errors logged - and
+//! saves it in the app's data directory. With the weekly report switched on,
+//! last week's report is saved every Monday morning and announced with a
+//! notification.
+
+use crate::{
+  db::Database,
+  error::{Result, SmoothieError},
+  models::dto::{
+    AppFocusTimeDto, ErrorSummaryDto, ProfileUsageDto, UsageReportDto, UsageReportRequest,
+  },
+  models::ReportFormat,
+  repositories::AuditRepository,
+  services::{UserSettingsService, AUDIT_SERVICE},
+  state::AppState,
+  utils::paths,
+};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Longest range one report covers
+const MAX_REPORT_DAYS: i64 = 366;
+
+/// Apps and error groups listed in a report
+const TOP_APPS: i64 = 10;
+const TOP_ERRORS: i64 = 10;
+
+/// Monday's weekly report is due from this hour, local time
+const WEEKLY_REPORT_HOUR: u32 = 8;
+
+const WEEKLY_REPORT_DELAY: std::time::Duration = std::time::Duration::from_secs(2 * 60);
+const WEEKLY_REPORT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Where reports are saved
+fn reports_dir() -> PathBuf {
+  paths::app_data_dir().join("reports")
+}
+
+fn report_file_name(from: NaiveDate, to: NaiveDate, format: ReportFormat) -> String {
+  format!("smoothie-report-{}-to-{}.{}", from, to, format.extension())
+}
+
+/// Start of `date` in local time
+fn local_midnight(date: NaiveDate) -> DateTime<Utc> {
+  let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+  Local
+    .from_local_datetime(&midnight)
+    .earliest()
+    .map(|dt| dt.with_timezone(&Utc))
+    .unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+}
+
+/// The Monday-to-Sunday week the weekly report at `now` covers: the one
+/// before the latest Monday morning
+fn last_full_week(now: NaiveDateTime) -> (NaiveDate, NaiveDate) {
+  let today = now.date();
+  let mut monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+  if now
+    < monday
+      .and_hms_opt(WEEKLY_REPORT_HOUR, 0, 0)
+      .unwrap_or_default()
+  {
+    monday -= Duration::days(7);
+  }
+  (monday - Duration::days(7), monday - Duration::days(1))
+}
+
+/// "3h 25m", or "25m" under an hour
+fn format_duration(ms: i64) -> String {
+  let minutes = ms / 60_000;
+  match (minutes / 60, minutes % 60) {
+    (0, minutes) => format!("{}m", minutes),
+    (hours, minutes) => format!("{}h {}m", hours, minutes),
+  }
+}
+
+fn percent(share: f64) -> String {
+  format!("{:.0}%", share * 100.0)
+}
+
+/// Cell text that can't break a Markdown table row
+fn md_cell(text: &str) -> String {
+  text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+fn html_escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+fn summary_line(report: &UsageReportDto) -> String {
+  format!(
+    "{} tracked across {} profiles, {} activations.",
+    format_duration(report.total_focus_ms),
+    report.profiles.len(),
+    report.total_activations
+  )
+}
+
+fn render_markdown(report: &UsageReportDto) -> String {
+  let mut out = format!(
+    "# Smoothie usage report\n\n{} to {}\n\n{}\n",
+    report.from,
+    report.to,
+    summary_line(report)
+  );
+
+  out.push_str("\n## Profiles\n\n");
+  if report.profiles.is_empty() {
+    out.push_str("_No profiles used._\n");
+  } else {
+    out.push_str("| Profile | Time | Activations |\n| --- | ---: | ---: |\n");
+    for profile in &report.profiles {
+      out.push_str(&format!(
+        "| {} | {} | {} |\n",
+        md_cell(&profile.name),
+        format_duration(profile.focus_ms),
+        profile.activations
+      ));
+    }
+  }
+
+  out.push_str("\n## Top apps\n\n");
+  if report.top_apps.is_empty() {
+    out.push_str("_No app time tracked._\n");
+  } else {
+    out.push_str("| App | Time | Share |\n| --- | ---: | ---: |\n");
+    for app in &report.top_apps {
+      out.push_str(&format!(
+        "| {} | {} | {} |\n",
+        md_cell(&app.app_name),
+        format_duration(app.focus_ms),
+        percent(app.share)
+      ));
+    }
+  }
+
+  out.push_str("\n## Errors\n\n");
+  if report.errors.is_empty() {
+    out.push_str("_No errors._\n");
+  } else {
+    out.push_str("| Error | Severity | Occurrences |\n| --- | --- | ---: |\n");
+    for error in &report.errors {
+      out.push_str(&format!(
+        "| {}: {} | {} | {} |\n",
+        md_cell(&error.error_type),
+        md_cell(&error.message),
+        error.severity,
+        error.occurrences
+      ));
+    }
+  }
+  out
+}
+
+fn render_html(report: &UsageReportDto) -> String {
+  fn table(headers: &[&str], rows: Vec<Vec<String>>, empty: &str) -> String {
+    if rows.is_empty() {
+      return format!("<p><em>{}</em></p>\n", empty);
+    }
+    let head: String = headers.iter().map(|h| format!("<th>{}</th>", h)).collect();
+    let body: String = rows
+      .into_iter()
+      .map(|cells| {
+        let cells: String = cells
+          .into_iter()
+          .map(|c| format!("<td>{}</td>", c))
+          .collect();
+        format!("<tr>{}</tr>\n", cells)
+      })
+      .collect();
+    format!(
+      "<table>\n<thead><tr>{}</tr></thead>\n<tbody>\n{}</tbody>\n</table>\n",
+      head, body
+    )
+  }
+
+  let profiles = report
+    .profiles
+    .iter()
+    .map(|p| {
+      vec![
+        html_escape(&p.name),
+        format_duration(p.focus_ms),
+        p.activations.to_string(),
+      ]
+    })
+    .collect();
+  let apps = report
+    .top_apps
+    .iter()
+    .map(|a| {
+      vec![
+        html_escape(&a.app_name),
+        format_duration(a.focus_ms),
+        percent(a.share),
+      ]
+    })
+    .collect();
+  let errors = report
+    .errors
+    .iter()
+    .map(|e| {
+      vec![
+        format!(
+          "{}: {}",
+          html_escape(&e.error_type),
+          html_escape(&e.message)
+        ),
+        e.severity.to_string(),
+        e.occurrences.to_string(),
+      ]
+    })
+    .collect();
+
+  format!(
+    r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Smoothie usage report {from} to {to}</title>
+<style>
+body {{ font-family: -apple-system, sans-serif; max-width: 48rem; margin: 2rem auto; color: #222; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ text-align: left; padding: 0.3rem 0.6rem; border-bottom: 1px solid #ddd; }}
+</style>
+</head>
+<body>
+<h1>Smoothie usage report</h1>
+<p>{from} to {to}</p>
+<p>{summary}</p>
+<h2>Profiles</h2>
+{profiles}<h2>Top apps</h2>
+{apps}<h2>Errors</h2>
+{errors}</body>
+</html>
+"#,
+    from = report.from,
+    to = report.to,
+    summary = html_escape(&summary_line(report)),
+    profiles = table(
+      &["Profile", "Time", "Activations"],
+      profiles,
+      "No profiles used."
+    ),
+    apps = table(&["App", "Time", "Share"], apps, "No app time tracked."),
+    errors = table(&["Error", "Severity", "Occurrences"], errors, "No errors."),
+  )
+}
+
+fn render(report: &UsageReportDto) -> String {
+  match report.format {
+    ReportFormat::Markdown => render_markdown(report),
+    ReportFormat::Html => render_html(report),
+  }
+}
+
+pub struct UsageReportService;
+
+impl UsageReportService {
+  /// Render a report for the requested days and save it with the other reports
+  pub async fn generate_usage_report(
+    db: &Database,
+    user_id: Uuid,
+    req: UsageReportRequest,
+  ) -> Result<UsageReportDto> {
+    Self::generate_in(db, user_id, req, &reports_dir()).await
+  }
+
+  /// Render a report and save it in `dir`
+  pub async fn generate_in(
+    db: &Database,
+    user_id: Uuid,
+    req: UsageReportRequest,
+    dir: &Path,
+  ) -> Result<UsageReportDto> {
+    let to = req.to.unwrap_or_else(|| Local::now().date_naive());
+    let from = req.from.unwrap_or(to - Duration::days(6));
+    if from > to {
+      return Err(SmoothieError::ValidationError(
+        "The report can't start after it ends".into(),
+      ));
+    }
+    if (to - from).num_days() >= MAX_REPORT_DAYS {
+      return Err(SmoothieError::ValidationError(format!(
+        "A report covers at most {} days",
+        MAX_REPORT_DAYS
+      )));
+    }
+    let format = req.format.unwrap_or(ReportFormat::Markdown);
+
+    let since = local_midnight(from);
+    let until = local_midnight(to + Duration::days(1));
+    // Activations come from the rollup, so bring it up to date first
+    AUDIT_SERVICE.refresh_daily_stats(db, user_id).await?;
+    let repo = AuditRepository::new(db.pool());
+    let profiles = repo
+      .get_profile_usage(user_id, since, until, (from, to))
+      .await?;
+    let apps = repo.get_top_apps(user_id, since, until, TOP_APPS).await?;
+    let errors = repo
+      .get_error_summary(user_id, since, until, TOP_ERRORS)
+      .await?;
+
+    let total_focus_ms: i64 = profiles.iter().map(|p| p.focus_ms).sum();
+    let path = dir.join(report_file_name(from, to, format));
+    let report = UsageReportDto {
+      from,
+      to,
+      format,
+      path: path.display().to_string(),
+      total_focus_ms,
+      total_activations: profiles.iter().map(|p| p.activations).sum(),
+      profiles: profiles
+        .into_iter()
+        .map(|row| ProfileUsageDto {
+          profile_id: row.profile_id.to_string(),
+          name: row.name,
+          activations: row.activations,
+          focus_ms: row.focus_ms,
+        })
+        .collect(),
+      top_apps: apps
+        .into_iter()
+        .map(|row| AppFocusTimeDto {
+          share: if total_focus_ms > 0 {
+            row.focus_ms as f64 / total_focus_ms as f64
+          } else {
+            0.0
+          },
+          bundle_id: row.bundle_id,
+          app_name: row.app_name,
+          focus_ms: row.focus_ms,
+          sessions: row.sessions,
+        })
+        .collect(),
+      errors: errors
+        .into_iter()
+        .map(|row| ErrorSummaryDto {
+          error_type: row.error_type,
+          message: row.message,
+          severity: row.severity,
+          occurrences: row.occurrences,
+          last_occurred_at: row.last_occurred_at.to_rfc3339(),
+        })
+        .collect(),
+    };
+
+    tokio::fs::create_dir_all(dir).await?;
+    tokio::fs::write(&path, render(&report)).await?;
+    tracing::info!(path = %path.display(), %from, %to, "Usage report saved");
+
+    Ok(report)
+  }
+
+  /// Save last week's report on Monday mornings while the weekly report is on.
+  /// A report already on disk isn't written again, so a late start on Monday
+  /// or later in the week still gets exactly one.
+  pub fn spawn_weekly_report(state: Arc<AppState>, user_id: Uuid) {
+    let report_state = state.clone();
+    state.tasks.spawn("weekly_usage_report", move || {
+      let state = report_state.clone();
+      async move {
+        let first_run = tokio::time::Instant::now() + WEEKLY_REPORT_DELAY;
+        let mut ticker = tokio::time::interval_at(first_run, WEEKLY_REPORT_INTERVAL);
+        loop {
+          ticker.tick().await;
+          if let Err(e) = Self::send_weekly_report(&state, user_id).await {
+            tracing::warn!("Weekly usage report failed: {}", e);
+          }
+        }
+      }
+    });
+  }
+
+  async fn send_weekly_report(state: &AppState, user_id: Uuid) -> Result<()> {
+    let settings = UserSettingsService::get_settings(&state.db, user_id).await?;
+    if !settings.weekly_report_enabled {
+      return Ok(());
+    }
+
+    let format = settings.weekly_report_format;
+    let (from, to) = last_full_week(Local::now().naive_local());
+    let dir = reports_dir();
+    if dir.join(report_file_name(from, to, format)).exists() {
+      return Ok(());
+    }
+
+    let req = UsageReportRequest {
+      from: Some(from),
+      to: Some(to),
+      format: Some(format),
+    };
+    let report = Self::generate_in(&state.db, user_id, req, &dir).await?;
+
+    if settings.notifications_enabled {
+      let body = format!("{} Saved to {}", summary_line(&report), report.path);
+      if let Err(e) = state
+        .system
+        .show_notification("Smoothie: Your weekly report is ready", &body)
+      {
+        tracing::warn!("Failed to show weekly report notification: {}", e);
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::Severity;
+
+  fn date(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+  }
+
+  fn at(s: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+  }
+
+  #[test]
+  fn test_weekly_report_covers_the_week_before_monday_morning() {
+    // Monday 2026-10-12, after the report hour
+    assert_eq!(
+      last_full_week(at("2026-10-12 09:00")),
+      (date("2026-10-05"), date("2026-10-11"))
+    );
+    // Still too early on Monday: the week before last is the latest due
+    assert_eq!(
+      last_full_week(at("2026-10-12 07:59")),
+      (date("2026-09-28"), date("2026-10-04"))
+    );
+    // Later in the week, the same week as Monday's
+    assert_eq!(
+      last_full_week(at("2026-10-18 23:00")),
+      (date("2026-10-05"), date("2026-10-11"))
+    );
+  }
+
+  #[test]
+  fn test_markdown_report_lists_sections_and_escapes_cells() {
+    let report = UsageReportDto {
+      from: date("2026-10-05"),
+      to: date("2026-10-11"),
+      format: ReportFormat::Markdown,
+      path: String::new(),
+      total_focus_ms: 3 * 3_600_000 + 25 * 60_000,
+      total_activations: 4,
+      profiles: vec![ProfileUsageDto {
+        profile_id: Uuid::nil().to_string(),
+        name: "Work | Deep".into(),
+        activations: 4,
+        focus_ms: 3 * 3_600_000 + 25 * 60_000,
+      }],
+      top_apps: vec![],
+      errors: vec![ErrorSummaryDto {
+        error_type: "SystemError".into(),
+        message: "displayplacer missing".into(),
+        severity: Severity::Error,
+        occurrences: 3,
+        last_occurred_at: String::new(),
+      }],
+    };
+
+    let markdown = render_markdown(&report);
+    assert!(markdown.contains("3h 25m tracked across 1 profiles, 4 activations."));
+    assert!(markdown.contains("| Work \\| Deep | 3h 25m | 4 |"));
+    assert!(markdown.contains("_No app time tracked._"));
+    assert!(markdown.contains("| SystemError: displayplacer missing | error | 3 |"));
+
+    let html = render_html(&UsageReportDto {
+      format: ReportFormat::Html,
+      ..report
+    });
+    assert!(html.contains("<td>Work | Deep</td>"));
+  }
+}
@@ -2,11 +2,77 @@
 
 use crate::db::Database;
 use crate::error::{Result, SmoothieError};
-use crate::models::dto::UserSettingsDto;
-use crate::repositories::UserSettingsRepository;
+use crate::models::dto::{
+  MonitorProfileMapping, ShortcutConflictDto, ShortcutConflictKind, ShortcutValidationDto,
+  UserSettingsDto,
+};
+use crate::models::entities::{ProfileEntity, UserSettingsEntity};
+use crate::models::enums::{ReportFormat, SyncScope};
+use crate::repositories::{ProfileRepository, UserSettingsRepository};
+use crate::services::audit_service::MAX_LOG_RETENTION_DAYS;
+use crate::services::profile_lock::ProfileLock;
+use crate::services::profile_service::MAX_MONITORS;
+use crate::services::snapshot_service::MAX_SNAPSHOTS_KEPT;
+use crate::services::system_service::SystemService;
+use crate::services::{
+  DeclarativeService, DiagnosticUploadService, EnvironmentService, LoginItemService,
+};
+use crate::utils::paths::is_executable;
+use crate::utils::shortcut::Shortcut;
 use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::path::Path;
 use uuid::Uuid;
 
+/// Per-profile shortcuts by profile ID, as stored in `keyboard_shortcuts`
+fn profile_shortcuts(settings: &UserSettingsEntity) -> BTreeMap<String, String> {
+  settings
+    .keyboard_shortcuts
+    .clone()
+    .and_then(|value| serde_json::from_value(value).ok())
+    .unwrap_or_default()
+}
+
+/// What `shortcut` clashes with. The binding of `profile_id`, the profile it's
+/// for, doesn't count, and neither do bindings of deleted profiles.
+fn shortcut_conflicts(
+  settings: &UserSettingsEntity,
+  profiles: &[ProfileEntity],
+  shortcut: Shortcut,
+  profile_id: Option<&str>,
+) -> Vec<ShortcutConflictDto> {
+  let mut conflicts = Vec::new();
+  if Shortcut::parse(&settings.keyboard_shortcut).is_ok_and(|global| global == shortcut) {
+    conflicts.push(ShortcutConflictDto {
+      kind: ShortcutConflictKind::Smoothie,
+      description: "Smoothie's global shortcut".to_string(),
+      profile_id: None,
+    });
+  }
+  for (id, bound) in profile_shortcuts(settings) {
+    if profile_id == Some(id.as_str())
+      || !Shortcut::parse(&bound).is_ok_and(|bound| bound == shortcut)
+    {
+      continue;
+    }
+    if let Some(profile) = profiles.iter().find(|p| p.id.to_string() == id) {
+      conflicts.push(ShortcutConflictDto {
+        kind: ShortcutConflictKind::Smoothie,
+        description: format!("Activates profile \"{}\"", profile.name),
+        profile_id: Some(id),
+      });
+    }
+  }
+  if let Some(description) = shortcut.system_use() {
+    conflicts.push(ShortcutConflictDto {
+      kind: ShortcutConflictKind::System,
+      description: format!("macOS: {}", description),
+      profile_id: None,
+    });
+  }
+  conflicts
+}
+
 pub struct UserSettingsService;
 
 impl UserSettingsService {
@@ -22,7 +88,7 @@ impl UserSettingsService {
     .bind(user_id)
     .execute(pool)
     .await
-    .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    .map_err(SmoothieError::from)?;
 
     tracing::debug!(user_id = %user_id, "User ensured in local database");
     Ok(())
@@ -51,7 +117,18 @@ impl UserSettingsService {
     auto_activate_time: Option<String>,
     keyboard_shortcut: Option<String>,
     notifications_enabled: Option<bool>,
+    log_retention_days: Option<i32>,
+    auto_cleanup_logs: Option<bool>,
   ) -> Result<UserSettingsDto> {
+    if let Some(days) = log_retention_days {
+      if !(1..=MAX_LOG_RETENTION_DAYS as i32).contains(&days) {
+        return Err(SmoothieError::ValidationError(format!(
+          "Log retention must be between 1 and {} days",
+          MAX_LOG_RETENTION_DAYS
+        )));
+      }
+    }
+
     // Ensure the user exists in the local database
     Self::ensure_user_exists(db.pool(), user_id).await?;
 
@@ -72,9 +149,344 @@ impl UserSettingsService {
         auto_activate_time,
         keyboard_shortcut,
         notifications_enabled,
+        log_retention_days,
+        auto_cleanup_logs,
       )
       .await?;
 
     Ok(UserSettingsDto::from(settings))
   }
+
+  /// Set the location sunrise/sunset schedules use; `None` clears it so the
+  /// device location is used instead
+  pub async fn set_location(
+    db: &Database,
+    user_id: Uuid,
+    location: Option<(f64, f64)>,
+  ) -> Result<UserSettingsDto> {
+    if let Some((latitude, longitude)) = location {
+      if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return Err(SmoothieError::ValidationError(
+          "Latitude must be between -90 and 90 and longitude between -180 and 180".into(),
+        ));
+      }
+    }
+
+    Self::ensure_user_exists(db.pool(), user_id).await?;
+    let repo = UserSettingsRepository::new(db.pool());
+    let _ = repo.get_or_create(user_id).await?;
+    let settings = repo.set_location(user_id, location).await?;
+
+    Ok(UserSettingsDto::from(settings))
+  }
+
+  /// Choose what cloud sync uploads: profiles, profiles and settings, or
+  /// everything including logs
+  pub async fn set_sync_scope(
+    db: &Database,
+    user_id: Uuid,
+    scope: SyncScope,
+  ) -> Result<UserSettingsDto> {
+    Self::ensure_user_exists(db.pool(), user_id).await?;
+    let repo = UserSettingsRepository::new(db.pool());
+    let _ = repo.get_or_create(user_id).await?;
+    let settings = repo.set_sync_scope(user_id, scope).await?;
+
+    tracing::info!(user_id = %user_id, %scope, "Sync scope updated");
+
+    Ok(UserSettingsDto::from(settings))
+  }
+
+  /// Save a usage report for the previous week every Monday morning
+  pub async fn set_weekly_report(
+    db: &Database,
+    user_id: Uuid,
+    enabled: bool,
+    format: ReportFormat,
+  ) -> Result<UserSettingsDto> {
+    Self::ensure_user_exists(db.pool(), user_id).await?;
+    let repo = UserSettingsRepository::new(db.pool());
+    let _ = repo.get_or_create(user_id).await?;
+    let settings = repo.set_weekly_report(user_id, enabled, format).await?;
+
+    tracing::info!(user_id = %user_id, enabled, %format, "Weekly report setting updated");
+
+    Ok(UserSettingsDto::from(settings))
+  }
+
+  /// Keep this many daily profile snapshots; 0 stops taking them
+  pub async fn set_snapshots_kept(
+    db: &Database,
+    user_id: Uuid,
+    kept: i32,
+  ) -> Result<UserSettingsDto> {
+    if !(0..=MAX_SNAPSHOTS_KEPT).contains(&kept) {
+      return Err(SmoothieError::ValidationError(format!(
+        "Keep between 0 and {} snapshots",
+        MAX_SNAPSHOTS_KEPT
+      )));
+    }
+    Self::ensure_user_exists(db.pool(), user_id).await?;
+    let repo = UserSettingsRepository::new(db.pool());
+    let _ = repo.get_or_create(user_id).await?;
+    let settings = repo.set_snapshots_kept(user_id, kept).await?;
+
+    tracing::info!(user_id = %user_id, kept, "Snapshot retention updated");
+
+    Ok(UserSettingsDto::from(settings))
+  }
+
+  /// Watch a declarative profile file, or stop watching with `None`. The file
+  /// must exist and parse; it's stored as an absolute path.
+  pub async fn set_declarative_watch_path(
+    db: &Database,
+    user_id: Uuid,
+    path: Option<&str>,
+  ) -> Result<UserSettingsDto> {
+    let path = match path.map(str::trim).filter(|p| !p.is_empty()) {
+      Some(path) => Some(DeclarativeService::check_file(path)?),
+      None => None,
+    };
+    Self::ensure_user_exists(db.pool(), user_id).await?;
+    let repo = UserSettingsRepository::new(db.pool());
+    let _ = repo.get_or_create(user_id).await?;
+    let settings = repo
+      .set_declarative_watch_path(user_id, path.as_deref())
+      .await?;
+
+    tracing::info!(user_id = %user_id, path = ?path, "Declarative watch path updated");
+
+    Ok(UserSettingsDto::from(settings))
+  }
+
+  /// Start Smoothie at login, in the background without its window when
+  /// `background_agent` is on. The LaunchAgent is updated before the setting
+  /// is saved, so a failure leaves both as they were.
+  pub async fn set_login_item(
+    db: &Database,
+    user_id: Uuid,
+    launch_at_login: bool,
+    background_agent: bool,
+  ) -> Result<UserSettingsDto> {
+    Self::ensure_user_exists(db.pool(), user_id).await?;
+    LoginItemService::apply(launch_at_login, background_agent).await?;
+    let repo = UserSettingsRepository::new(db.pool());
+    let _ = repo.get_or_create(user_id).await?;
+    let settings = repo
+      .set_login_item(user_id, launch_at_login, background_agent)
+      .await?;
+
+    tracing::info!(
+      user_id = %user_id,
+      launch_at_login,
+      background_agent,
+      "Login item updated"
+    );
+
+    Ok(UserSettingsDto::from(settings))
+  }
+
+  /// Opt in to or out of diagnostic uploads. Turning them on needs an active
+  /// paid subscription; turning them off always works and stops uploads at
+  /// once.
+  pub async fn set_diagnostic_uploads(
+    db: &Database,
+    user_id: Uuid,
+    enabled: bool,
+  ) -> Result<UserSettingsDto> {
+    Self::ensure_user_exists(db.pool(), user_id).await?;
+    if enabled && !DiagnosticUploadService::is_subscribed(db, user_id).await {
+      return Err(SmoothieError::ValidationError(
+        "Diagnostic uploads need an active paid subscription".into(),
+      ));
+    }
+    let repo = UserSettingsRepository::new(db.pool());
+    let _ = repo.get_or_create(user_id).await?;
+    let settings = repo.set_diagnostic_uploads(user_id, enabled).await?;
+
+    tracing::info!(user_id = %user_id, enabled, "Diagnostic uploads updated");
+
+    Ok(UserSettingsDto::from(settings))
+  }
+
+  pub async fn set_telemetry_enabled(
+    db: &Database,
+    user_id: Uuid,
+    enabled: bool,
+  ) -> Result<UserSettingsDto> {
+    Self::ensure_user_exists(db.pool(), user_id).await?;
+    let repo = UserSettingsRepository::new(db.pool());
+    let _ = repo.get_or_create(user_id).await?;
+    let settings = repo.set_telemetry_enabled(user_id, enabled).await?;
+
+    tracing::info!(user_id = %user_id, enabled, "Telemetry updated");
+
+    Ok(UserSettingsDto::from(settings))
+  }
+
+  /// Use the displayplacer binary at `path` rather than the one found on the
+  /// system; `None` goes back to looking for it
+  pub async fn set_displayplacer_path(
+    db: &Database,
+    user_id: Uuid,
+    path: Option<&str>,
+  ) -> Result<UserSettingsDto> {
+    let path = path.map(str::trim).filter(|p| !p.is_empty());
+    if let Some(path) = path {
+      if !Path::new(path).is_absolute() || !is_executable(Path::new(path)) {
+        return Err(SmoothieError::ValidationError(format!(
+          "{} isn't an executable file",
+          path
+        )));
+      }
+    }
+    Self::ensure_user_exists(db.pool(), user_id).await?;
+    let repo = UserSettingsRepository::new(db.pool());
+    let _ = repo.get_or_create(user_id).await?;
+    let settings = repo.set_displayplacer_path(user_id, path).await?;
+    SystemService::set_displayplacer_path(settings.displayplacer_path.clone());
+
+    tracing::info!(user_id = %user_id, path = ?path, "displayplacer path updated");
+
+    Ok(UserSettingsDto::from(settings))
+  }
+
+  /// Set the profile to switch to for each monitor count when no profile is
+  /// bound to the connected displays more specifically
+  pub async fn set_monitor_profile_mappings(
+    db: &Database,
+    user_id: Uuid,
+    mappings: Vec<MonitorProfileMapping>,
+  ) -> Result<UserSettingsDto> {
+    let profiles = ProfileRepository::new(db.pool())
+      .find_by_user_id(user_id)
+      .await?;
+    let mut counts = std::collections::HashSet::new();
+    for mapping in &mappings {
+      if !(1..=MAX_MONITORS).contains(&mapping.monitor_count) {
+        return Err(SmoothieError::ValidationError(format!(
+          "Monitor count must be between 1 and {}",
+          MAX_MONITORS
+        )));
+      }
+      if !counts.insert(mapping.monitor_count) {
+        return Err(SmoothieError::ValidationError(format!(
+          "More than one default profile for {} monitors",
+          mapping.monitor_count
+        )));
+      }
+      if !profiles
+        .iter()
+        .any(|p| p.id.to_string() == mapping.profile_id)
+      {
+        return Err(SmoothieError::NotFound(format!(
+          "Profile {} not found",
+          mapping.profile_id
+        )));
+      }
+    }
+
+    Self::ensure_user_exists(db.pool(), user_id).await?;
+    let repo = UserSettingsRepository::new(db.pool());
+    let _ = repo.get_or_create(user_id).await?;
+    let settings = repo
+      .set_monitor_profile_mappings(user_id, serde_json::to_value(&mappings)?)
+      .await?;
+
+    tracing::info!(user_id = %user_id, count = mappings.len(), "Monitor profile mappings updated");
+
+    Ok(UserSettingsDto::from(settings))
+  }
+
+  /// Check a shortcut against Smoothie's own bindings and the system's before
+  /// it's saved. `profile_id` is the profile it would be bound to.
+  pub async fn validate_shortcut(
+    db: &Database,
+    user_id: Uuid,
+    shortcut: &str,
+    profile_id: Option<&str>,
+  ) -> Result<ShortcutValidationDto> {
+    let shortcut = Shortcut::parse(shortcut)?;
+    Self::ensure_user_exists(db.pool(), user_id).await?;
+    let settings = UserSettingsRepository::new(db.pool())
+      .get_or_create(user_id)
+      .await?;
+    let profiles = ProfileRepository::new(db.pool())
+      .find_by_user_id(user_id)
+      .await?;
+
+    Ok(ShortcutValidationDto {
+      shortcut: shortcut.to_string(),
+      conflicts: shortcut_conflicts(&settings, &profiles, shortcut, profile_id),
+    })
+  }
+
+  /// Bind a shortcut to a profile, or unbind it with `None`. A shortcut with
+  /// any conflict is refused.
+  pub async fn set_profile_shortcut(
+    db: &Database,
+    user_id: Uuid,
+    profile_id: &str,
+    shortcut: Option<&str>,
+  ) -> Result<UserSettingsDto> {
+    let profiles = ProfileRepository::new(db.pool())
+      .find_by_user_id(user_id)
+      .await?;
+    let Some(profile) = profiles.iter().find(|p| p.id.to_string() == profile_id) else {
+      return Err(SmoothieError::NotFound(format!(
+        "Profile {} not found",
+        profile_id
+      )));
+    };
+    ProfileLock::ensure_unlocked(db, profile.id).await?;
+
+    Self::ensure_user_exists(db.pool(), user_id).await?;
+    let repo = UserSettingsRepository::new(db.pool());
+    let settings = repo.get_or_create(user_id).await?;
+    let mut shortcuts = profile_shortcuts(&settings);
+    shortcuts.retain(|id, _| profiles.iter().any(|p| p.id.to_string() == *id));
+    match shortcut {
+      Some(shortcut) => {
+        let shortcut = Shortcut::parse(shortcut)?;
+        let conflicts = shortcut_conflicts(&settings, &profiles, shortcut, Some(profile_id));
+        if !conflicts.is_empty() {
+          let uses: Vec<_> = conflicts.into_iter().map(|c| c.description).collect();
+          return Err(SmoothieError::ValidationError(format!(
+            "{} is already used: {}",
+            shortcut,
+            uses.join("; ")
+          )));
+        }
+        shortcuts.insert(profile_id.to_string(), shortcut.to_string());
+      }
+      None => {
+        shortcuts.remove(profile_id);
+      }
+    }
+    let settings = repo
+      .set_keyboard_shortcuts(user_id, serde_json::to_value(&shortcuts)?)
+      .await?;
+
+    tracing::info!(user_id = %user_id, profile_id, bound = shortcut.is_some(), "Profile shortcut updated");
+
+    Ok(UserSettingsDto::from(settings))
+  }
+
+  /// The location for sunrise/sunset schedules: the one in settings, else the
+  /// device's own via CoreLocation
+  pub async fn resolve_location(db: &Database, user_id: Uuid) -> Result<Option<(f64, f64)>> {
+    let settings = UserSettingsRepository::new(db.pool())
+      .get_or_create(user_id)
+      .await?;
+    if let (Some(latitude), Some(longitude)) = (settings.latitude, settings.longitude) {
+      return Ok(Some((latitude, longitude)));
+    }
+
+    Ok(
+      tokio::task::spawn_blocking(EnvironmentService::get_location)
+        .await
+        .ok()
+        .flatten(),
+    )
+  }
 }
@@ -0,0 +1,99 @@
+// Startup warm-up
+//
+// Loads the profiles most likely to be opened first - favorites and the most
+// used - into the AppState cache and builds the installed-app list, so the
+// first activation after launch doesn't wait on a cold database or an mdfind scan.
+// The installed-app list is rebuilt every half hour afterwards so apps installed
+// while the app runs show up, less often on battery or under load.
+
+use crate::{
+  error::{Result, SmoothieError},
+  models::{ProfileId, UserId},
+  services::{resource_guard::ThrottledTicker, ProfileService},
+  state::{AppState, INSTALLED_APPS_CACHE_KEY},
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Most-used profiles prefetched alongside the favorites
+const MOST_USED_LIMIT: i64 = 5;
+
+const INSTALLED_APPS_REFRESH_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+pub struct WarmupService;
+
+impl WarmupService {
+  /// Warm the cache in the background
+  pub fn spawn(state: Arc<AppState>, user_id: Uuid) {
+    let warmup_state = state.clone();
+    state.tasks.spawn("cache_warmup", move || {
+      let state = warmup_state.clone();
+      async move {
+        if let Err(e) = Self::warm_up(&state, user_id.into()).await {
+          tracing::warn!("Cache warm-up failed: {}", e);
+        }
+      }
+    });
+
+    let refresh_state = state.clone();
+    state.tasks.spawn("installed_apps_refresh", move || {
+      let state = refresh_state.clone();
+      async move {
+        let mut ticker = ThrottledTicker::new(INSTALLED_APPS_REFRESH_INTERVAL);
+        // The first tick is immediate and warm-up has just scanned
+        ticker.tick().await;
+        loop {
+          ticker.tick().await;
+          if let Err(e) = Self::scan_installed_apps(&state).await {
+            tracing::warn!("Installed app rescan failed: {}", e);
+          }
+        }
+      }
+    });
+  }
+
+  pub async fn warm_up(state: &AppState, user_id: UserId) -> Result<()> {
+    let start = Instant::now();
+
+    let favorites = ProfileService::get_favorite_profiles(&state.db, user_id).await?;
+    let most_used =
+      ProfileService::get_most_used_profiles(&state.db, user_id, MOST_USED_LIMIT).await?;
+    let mut profile_ids: Vec<ProfileId> = Vec::new();
+    for profile in favorites.into_iter().chain(most_used) {
+      if !profile_ids.contains(&profile.id) {
+        profile_ids.push(profile.id);
+      }
+    }
+
+    for &profile_id in &profile_ids {
+      let key = format!("profile_{}", profile_id);
+      // Anything cached since startup is at least as fresh
+      if state.cache.contains_key(&key) {
+        continue;
+      }
+      let profile = ProfileService::get_profile_response(&state.db, profile_id).await?;
+      state.set_cache(&key, &profile);
+    }
+
+    let installed_apps = Self::scan_installed_apps(state).await?;
+
+    tracing::info!(
+      profiles = profile_ids.len(),
+      installed_apps,
+      duration_ms = start.elapsed().as_millis() as u64,
+      "Cache warmed up"
+    );
+    Ok(())
+  }
+
+  /// Rebuild the cached installed-app list, returning how many apps were found
+  async fn scan_installed_apps(state: &AppState) -> Result<usize> {
+    let system = state.system.clone();
+    let installed_apps = tokio::task::spawn_blocking(move || system.get_installed_apps())
+      .await
+      .map_err(|e| SmoothieError::SystemError(format!("Installed app scan failed: {}", e)))?;
+    state.set_cache(INSTALLED_APPS_CACHE_KEY, &installed_apps);
+    Ok(installed_apps.len())
+  }
+}
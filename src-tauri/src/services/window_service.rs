@@ -4,10 +4,47 @@ use crate::{
   db::Database,
   error::{Result, SmoothieError},
   logging::METRICS,
+  models::dto::{AppDto, MonitorDto},
+  models::entities::MonitorEntity,
+  repositories::{AppRepository, MonitorRepository, ProfilePart},
+  services::{profile_lock::ProfileLock, SystemBackend, SystemMonitor},
+  utils::display_coords::{self, DisplayFrame},
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::sync::Arc;
 use uuid::Uuid;
 
+/// Furthest a saved window can be zoomed out, in the app's zoom steps
+pub const MIN_ZOOM_STEPS: i32 = -5;
+/// Furthest a saved window can be zoomed in
+pub const MAX_ZOOM_STEPS: i32 = 10;
+
+/// Apps whose Cmd-0 / Cmd-= / Cmd-- shortcuts reset and step their zoom
+const ZOOMABLE_APPS: [&str; 14] = [
+  "com.apple.Safari",
+  "com.google.Chrome",
+  "org.mozilla.firefox",
+  "com.microsoft.edgemac",
+  "com.brave.Browser",
+  "company.thebrowser.Browser",
+  "com.microsoft.VSCode",
+  "com.tinyspeck.slackmacgap",
+  "notion.id",
+  "md.obsidian",
+  "com.apple.Terminal",
+  "com.googlecode.iterm2",
+  "com.apple.Preview",
+  "com.apple.mail",
+];
+
+/// Whether a zoom hint can be applied to the app's windows
+pub fn supports_zoom(bundle_id: &str) -> bool {
+  ZOOMABLE_APPS
+    .iter()
+    .any(|app| app.eq_ignore_ascii_case(bundle_id))
+}
+
 /// Window DTO for API responses
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -22,6 +59,41 @@ pub struct WindowDto {
   pub height: i32,
   pub is_maximized: bool,
   pub state: String,
+  /// Zoom steps from the app's default to open the window at; unset leaves
+  /// the app's zoom alone
+  pub zoom_steps: Option<i32>,
+}
+
+/// Target geometry for a window, in global desktop coordinates
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowRect {
+  pub x: i32,
+  pub y: i32,
+  pub width: i32,
+  pub height: i32,
+}
+
+/// Result of recording a live window into a profile
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignWindowResult {
+  pub window: WindowDto,
+  pub app: AppDto,
+  pub monitor: MonitorDto,
+  /// Whether the app had to be added to the profile
+  pub app_created: bool,
+  /// Whether the monitor had to be added to the profile
+  pub monitor_created: bool,
+}
+
+/// Saved windows moved back on screen by `recover_offscreen_windows`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveredWindowsResult {
+  /// The display the windows were moved to
+  pub primary_monitor: String,
+  pub windows: Vec<WindowDto>,
 }
 
 /// Helper to parse UUID from string
@@ -44,75 +116,90 @@ impl WindowService {
     is_maximized: bool,
     state: String,
   ) -> Result<WindowDto> {
-    let id = Uuid::new_v4();
-    let _profile_uuid = parse_uuid(profile_id)?;
-    let _app_uuid = parse_uuid(app_id)?;
-    let _monitor_uuid = parse_uuid(monitor_id)?;
+    let profile_uuid = parse_uuid(profile_id)?;
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
+    let app_uuid = parse_uuid(app_id)?;
+    let monitor_uuid = parse_uuid(monitor_id)?;
 
-    sqlx::query(
-            "INSERT INTO windows (id, profile_id, app_id, monitor_id, x, y, width, height, is_maximized, state) 
-             VALUES ($1, $2::uuid, $3::uuid, $4::uuid, $5, $6, $7, $8, $9, $10)"
-        )
-        .bind(id)
-        .bind(profile_id)
-        .bind(app_id)
-        .bind(monitor_id)
-        .bind(x)
-        .bind(y)
-        .bind(width)
-        .bind(height)
-        .bind(is_maximized)
-        .bind(&state)
-        .execute(db.pool())
-        .await
-        .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
-
-    METRICS.record_window_managed();
-
-    Ok(WindowDto {
-      id: id.to_string(),
-      profile_id: profile_id.to_string(),
-      app_id: app_id.to_string(),
-      monitor_id: monitor_id.to_string(),
-      x,
-      y,
-      width,
-      height,
+    let mut conn = db.pool().acquire().await.map_err(SmoothieError::from)?;
+    let window = insert_window(
+      &mut conn,
+      profile_uuid,
+      app_uuid,
+      monitor_uuid,
+      WindowRect {
+        x,
+        y,
+        width,
+        height,
+      },
       is_maximized,
       state,
-    })
+    )
+    .await?;
+
+    METRICS.record_window_managed();
+    Ok(window)
   }
 
   pub async fn get_windows(db: &Database, profile_id: &str) -> Result<Vec<WindowDto>> {
     let _profile_uuid = parse_uuid(profile_id)?;
 
-    let rows = sqlx::query_as::<_, (String, String, String, String, i32, i32, i32, i32, bool, String)>(
-            "SELECT id::text, profile_id::text, app_id::text, monitor_id::text, x, y, width, height, is_maximized, state FROM windows WHERE profile_id = $1::uuid"
-        )
-        .bind(profile_id)
-        .fetch_all(db.pool())
-        .await
-        .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    let rows = sqlx::query_as::<_, WindowRow>(&format!(
+      "SELECT {} FROM windows WHERE profile_id = $1::uuid",
+      WINDOW_COLUMNS
+    ))
+    .bind(profile_id)
+    .fetch_all(db.pool())
+    .await
+    .map_err(SmoothieError::from)?;
+
+    Ok(rows.into_iter().map(WindowDto::from).collect())
+  }
+
+  /// A profile's windows, with frames saved on a display whose scale has
+  /// changed since moved to cover the same part of it
+  pub async fn get_windows_on_live_displays(
+    db: &Database,
+    system: Arc<dyn SystemBackend>,
+    profile_id: &str,
+  ) -> Result<Vec<WindowDto>> {
+    let profile_uuid = parse_uuid(profile_id)?;
+    let windows = Self::get_windows(db, profile_id).await?;
+    let saved_monitors = MonitorRepository::new(db.pool())
+      .find_by_profile_id(profile_uuid)
+      .await?;
+    let live_monitors = tokio::task::spawn_blocking(move || system.get_monitors())
+      .await
+      .map_err(|e| SmoothieError::SystemError(format!("Monitor detection failed: {}", e)))?;
 
     Ok(
-      rows
+      windows
         .into_iter()
-        .map(
-          |(id, profile_id, app_id, monitor_id, x, y, width, height, is_maximized, state)| {
-            WindowDto {
-              id,
-              profile_id,
-              app_id,
-              monitor_id,
-              x,
-              y,
-              width,
-              height,
-              is_maximized,
-              state,
+        .map(|window| {
+          let displays = saved_monitors
+            .iter()
+            .find(|m| m.id.to_string() == window.monitor_id)
+            .and_then(|saved| {
+              live_monitors
+                .iter()
+                .find(|live| display_coords::is_same_display(saved, live))
+                .map(|live| (DisplayFrame::from(saved), DisplayFrame::from(live)))
+            });
+          match displays {
+            Some((saved, live)) => {
+              let rect = live.translate(&saved, window.rect());
+              WindowDto {
+                x: rect.x,
+                y: rect.y,
+                width: rect.width,
+                height: rect.height,
+                ..window
+              }
             }
-          },
-        )
+            None => window,
+          }
+        })
         .collect(),
     )
   }
@@ -125,7 +212,8 @@ impl WindowService {
     width: i32,
     height: i32,
   ) -> Result<WindowDto> {
-    let _window_uuid = parse_uuid(window_id)?;
+    let window_uuid = parse_uuid(window_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::Window, window_uuid).await?;
 
     sqlx::query("UPDATE windows SET x = $1, y = $2, width = $3, height = $4 WHERE id = $5::uuid")
       .bind(x)
@@ -135,38 +223,76 @@ impl WindowService {
       .bind(window_id)
       .execute(db.pool())
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
 
-    let row = sqlx::query_as::<_, (String, String, String, String, i32, i32, i32, i32, bool, String)>(
-            "SELECT id::text, profile_id::text, app_id::text, monitor_id::text, x, y, width, height, is_maximized, state FROM windows WHERE id = $1::uuid"
-        )
-        .bind(window_id)
-        .fetch_one(db.pool())
-        .await
-        .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+    Self::get_window(db, window_id).await
+  }
+
+  /// Open the window at `zoom_steps` from its app's default zoom, or leave
+  /// the zoom alone when unset
+  pub async fn set_window_zoom(
+    db: &Database,
+    window_id: &str,
+    zoom_steps: Option<i32>,
+  ) -> Result<WindowDto> {
+    if zoom_steps.is_some_and(|steps| !(MIN_ZOOM_STEPS..=MAX_ZOOM_STEPS).contains(&steps)) {
+      return Err(SmoothieError::ValidationError(format!(
+        "Zoom must be between {} and {} steps",
+        MIN_ZOOM_STEPS, MAX_ZOOM_STEPS
+      )));
+    }
+    let window = Self::get_window(db, window_id).await?;
+    ProfileLock::ensure_unlocked(db, parse_uuid(&window.profile_id)?).await?;
+    if zoom_steps.is_some() {
+      let app = AppRepository::new(db.pool())
+        .find_by_id(parse_uuid(&window.app_id)?)
+        .await?
+        .ok_or_else(|| SmoothieError::NotFound("App not found".into()))?;
+      if !supports_zoom(&app.bundle_id) {
+        return Err(SmoothieError::ValidationError(format!(
+          "{} can't be opened at a set zoom",
+          app.name
+        )));
+      }
+    }
+
+    sqlx::query("UPDATE windows SET zoom_steps = $1, updated_at = NOW() WHERE id = $2::uuid")
+      .bind(zoom_steps)
+      .bind(window_id)
+      .execute(db.pool())
+      .await
+      .map_err(SmoothieError::from)?;
 
     Ok(WindowDto {
-      id: row.0,
-      profile_id: row.1,
-      app_id: row.2,
-      monitor_id: row.3,
-      x: row.4,
-      y: row.5,
-      width: row.6,
-      height: row.7,
-      is_maximized: row.8,
-      state: row.9,
+      zoom_steps,
+      ..window
     })
   }
 
-  pub async fn delete_window(db: &Database, window_id: &str) -> Result<()> {
+  async fn get_window(db: &Database, window_id: &str) -> Result<WindowDto> {
     let _window_uuid = parse_uuid(window_id)?;
 
+    sqlx::query_as::<_, WindowRow>(&format!(
+      "SELECT {} FROM windows WHERE id = $1::uuid",
+      WINDOW_COLUMNS
+    ))
+    .bind(window_id)
+    .fetch_optional(db.pool())
+    .await
+    .map_err(SmoothieError::from)?
+    .map(WindowDto::from)
+    .ok_or_else(|| SmoothieError::NotFound("Window not found".into()))
+  }
+
+  pub async fn delete_window(db: &Database, window_id: &str) -> Result<()> {
+    let window_uuid = parse_uuid(window_id)?;
+    ProfileLock::ensure_part_unlocked(db, ProfilePart::Window, window_uuid).await?;
+
     let result = sqlx::query("DELETE FROM windows WHERE id = $1::uuid")
       .bind(window_id)
       .execute(db.pool())
       .await
-      .map_err(|e| SmoothieError::DatabaseError(e.to_string()))?;
+      .map_err(SmoothieError::from)?;
 
     if result.rows_affected() == 0 {
       return Err(SmoothieError::NotFound("Window not found".into()));
@@ -174,4 +300,551 @@ impl WindowService {
 
     Ok(())
   }
+
+  /// Reject a rect that wouldn't overlap any connected display. Passes when no
+  /// displays can be read (e.g. without screen recording permission).
+  pub async fn ensure_on_screen(system: Arc<dyn SystemBackend>, rect: WindowRect) -> Result<()> {
+    if rect.width <= 0 || rect.height <= 0 {
+      return Err(SmoothieError::ValidationError(
+        "Window must have a positive size".to_string(),
+      ));
+    }
+    let monitors = tokio::task::spawn_blocking(move || system.get_monitors())
+      .await
+      .map_err(|e| SmoothieError::SystemError(format!("Monitor detection failed: {}", e)))?;
+    if !monitors.is_empty() && !monitors.iter().any(|m| overlaps(rect, m)) {
+      return Err(SmoothieError::ValidationError(
+        "Window would be off screen: it doesn't overlap any connected display".to_string(),
+      ));
+    }
+    Ok(())
+  }
+
+  /// Move a profile's saved windows that no connected display shows, typically
+  /// ones left on an external display after undocking, onto the primary display.
+  ///
+  /// Each window keeps its offset from the corner of its old display where it
+  /// fits, and shrinks to the primary display if it is larger.
+  pub async fn recover_offscreen_windows(
+    db: &Database,
+    system: Arc<dyn SystemBackend>,
+    profile_id: &str,
+  ) -> Result<RecoveredWindowsResult> {
+    let profile_uuid = parse_uuid(profile_id)?;
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
+    let live_monitors = tokio::task::spawn_blocking(move || system.get_monitors())
+      .await
+      .map_err(|e| SmoothieError::SystemError(format!("Monitor detection failed: {}", e)))?;
+    let primary = live_monitors
+      .iter()
+      .find(|m| m.is_primary)
+      .or_else(|| live_monitors.first())
+      .cloned()
+      .ok_or_else(|| SmoothieError::SystemError("No displays detected".into()))?;
+
+    let offscreen: Vec<WindowDto> = Self::get_windows(db, profile_id)
+      .await?
+      .into_iter()
+      .filter(|w| !live_monitors.iter().any(|m| overlaps(w.rect(), m)))
+      .collect();
+    if offscreen.is_empty() {
+      return Ok(RecoveredWindowsResult {
+        primary_monitor: primary.name,
+        windows: Vec::new(),
+      });
+    }
+
+    let profile_monitors = MonitorRepository::new(db.pool())
+      .find_by_profile_id(profile_uuid)
+      .await?;
+    let mut tx = db.pool().begin().await.map_err(SmoothieError::from)?;
+    let (center_x, center_y) = (
+      primary.x + primary.width / 2,
+      primary.y + primary.height / 2,
+    );
+    let target_monitor = match profile_monitors.iter().find(|m| {
+      center_x >= m.x && center_x < m.x + m.width && center_y >= m.y && center_y < m.y + m.height
+    }) {
+      Some(monitor) => monitor.id,
+      None => {
+        record_live_monitor(&mut tx, profile_uuid, &primary, profile_monitors.len())
+          .await?
+          .id
+      }
+    };
+
+    let mut recovered = Vec::with_capacity(offscreen.len());
+    for window in offscreen {
+      let old_monitor = profile_monitors
+        .iter()
+        .find(|m| m.id.to_string() == window.monitor_id);
+      let offset = old_monitor.map_or((0, 0), |m| (window.x - m.x, window.y - m.y));
+      let rect = fit_onto(window.rect(), offset, &primary);
+
+      sqlx::query(
+        "UPDATE windows SET x = $1, y = $2, width = $3, height = $4, monitor_id = $5, updated_at = NOW() WHERE id = $6::uuid",
+      )
+      .bind(rect.x)
+      .bind(rect.y)
+      .bind(rect.width)
+      .bind(rect.height)
+      .bind(target_monitor)
+      .bind(&window.id)
+      .execute(&mut *tx)
+      .await
+      .map_err(SmoothieError::from)?;
+
+      recovered.push(WindowDto {
+        monitor_id: target_monitor.to_string(),
+        x: rect.x,
+        y: rect.y,
+        width: rect.width,
+        height: rect.height,
+        ..window
+      });
+    }
+
+    tx.commit().await.map_err(SmoothieError::from)?;
+
+    tracing::info!(
+      profile_id = %profile_id,
+      recovered = recovered.len(),
+      primary = %primary.name,
+      "Recovered off-screen windows"
+    );
+
+    Ok(RecoveredWindowsResult {
+      primary_monitor: primary.name,
+      windows: recovered,
+    })
+  }
+
+  /// Record a live window (from `SystemBackend::get_windows`) into a profile.
+  ///
+  /// The window's app is added to the profile if it isn't there yet, and the
+  /// window is attached to the profile monitor under the center of the target
+  /// rect. If the profile has no such monitor, the live display is recorded too.
+  pub async fn assign_window_to_profile(
+    db: &Database,
+    system: Arc<dyn SystemBackend>,
+    profile_id: &str,
+    window_id: u32,
+    target_rect: Option<WindowRect>,
+  ) -> Result<AssignWindowResult> {
+    let profile_uuid = parse_uuid(profile_id)?;
+    ProfileLock::ensure_unlocked(db, profile_uuid).await?;
+
+    let (live_windows, live_monitors) =
+      tokio::task::spawn_blocking(move || (system.get_windows(), system.get_monitors()))
+        .await
+        .map_err(|e| SmoothieError::SystemError(format!("Window detection failed: {}", e)))?;
+
+    let live_window = live_windows
+      .into_iter()
+      .find(|w| w.window_id == window_id)
+      .ok_or_else(|| {
+        SmoothieError::NotFound(format!("Window {} is no longer visible", window_id))
+      })?;
+
+    let rect = target_rect.unwrap_or(WindowRect {
+      x: live_window.x,
+      y: live_window.y,
+      width: live_window.width,
+      height: live_window.height,
+    });
+    if rect.width <= 0 || rect.height <= 0 {
+      return Err(SmoothieError::ValidationError(
+        "Target rect must have a positive size".to_string(),
+      ));
+    }
+    let (center_x, center_y) = (rect.x + rect.width / 2, rect.y + rect.height / 2);
+    let contains_center = |x: i32, y: i32, width: i32, height: i32| {
+      center_x >= x && center_x < x + width && center_y >= y && center_y < y + height
+    };
+
+    let live_monitor = live_monitors
+      .into_iter()
+      .find(|m| contains_center(m.x, m.y, m.width, m.height));
+
+    // Everything below is written in one transaction, so a failure part way
+    // doesn't leave a recorded display or app behind without its window
+    let mut tx = db.pool().begin().await.map_err(SmoothieError::from)?;
+
+    // Resolve the monitor, recording the live display if the profile doesn't have it
+    let profile_monitors = MonitorRepository::new(db.pool())
+      .find_by_profile_id(profile_uuid)
+      .await?;
+    let existing_monitor = live_monitor
+      .as_ref()
+      .and_then(|live| {
+        profile_monitors
+          .iter()
+          .find(|m| display_coords::is_same_display(m, live))
+      })
+      .or_else(|| {
+        profile_monitors
+          .iter()
+          .find(|m| contains_center(m.x, m.y, m.width, m.height))
+      })
+      .cloned();
+    let monitor_created = existing_monitor.is_none();
+    let monitor = match existing_monitor {
+      Some(monitor) => monitor,
+      None => {
+        let live_monitor = live_monitor.as_ref().ok_or_else(|| {
+          SmoothieError::ValidationError("Target rect is not on any connected display".into())
+        })?;
+
+        record_live_monitor(&mut tx, profile_uuid, live_monitor, profile_monitors.len()).await?
+      }
+    };
+
+    // Store the frame against the scale the monitor was saved with
+    let rect = match &live_monitor {
+      Some(live) if display_coords::is_same_display(&monitor, live) => {
+        DisplayFrame::from(&monitor).translate(&DisplayFrame::from(live), rect)
+      }
+      _ => rect,
+    };
+
+    // Resolve the app by bundle ID, adding it to the profile if needed
+    let profile_apps = AppRepository::new(db.pool())
+      .find_by_profile_id(profile_uuid)
+      .await?;
+    let existing_app = profile_apps
+      .iter()
+      .find(|a| !live_window.bundle_id.is_empty() && a.bundle_id == live_window.bundle_id)
+      .cloned();
+    let app_created = existing_app.is_none();
+    let app = match existing_app {
+      Some(app) => app,
+      None => {
+        AppRepository::create_in(
+          &mut tx,
+          profile_uuid,
+          &live_window.app_name,
+          &live_window.bundle_id,
+          None,
+          true,
+          Some(monitor.display_index),
+          None,
+          Some(profile_apps.len() as i32),
+        )
+        .await?
+      }
+    };
+
+    let is_maximized = rect.x == monitor.x
+      && rect.y == monitor.y
+      && rect.width == monitor.width
+      && rect.height == monitor.height;
+
+    let window = insert_window(
+      &mut tx,
+      profile_uuid,
+      app.id,
+      monitor.id,
+      rect,
+      is_maximized,
+      "normal".to_string(),
+    )
+    .await?;
+    tx.commit().await.map_err(SmoothieError::from)?;
+    METRICS.record_window_managed();
+
+    tracing::info!(
+      profile_id = %profile_id,
+      window_id = window_id,
+      app = %live_window.app_name,
+      app_created,
+      monitor_created,
+      "Assigned live window to profile"
+    );
+
+    Ok(AssignWindowResult {
+      window,
+      app: AppDto::from(app),
+      monitor: MonitorDto::from(monitor),
+      app_created,
+      monitor_created,
+    })
+  }
+}
+
+const WINDOW_COLUMNS: &str = "id::text, profile_id::text, app_id::text, monitor_id::text, x, y, width, height, is_maximized, state, zoom_steps";
+
+type WindowRow = (
+  String,
+  String,
+  String,
+  String,
+  i32,
+  i32,
+  i32,
+  i32,
+  bool,
+  String,
+  Option<i32>,
+);
+
+impl From<WindowRow> for WindowDto {
+  fn from(
+    (id, profile_id, app_id, monitor_id, x, y, width, height, is_maximized, state, zoom_steps): WindowRow,
+  ) -> Self {
+    Self {
+      id,
+      profile_id,
+      app_id,
+      monitor_id,
+      x,
+      y,
+      width,
+      height,
+      is_maximized,
+      state,
+      zoom_steps,
+    }
+  }
+}
+
+impl WindowDto {
+  fn rect(&self) -> WindowRect {
+    WindowRect {
+      x: self.x,
+      y: self.y,
+      width: self.width,
+      height: self.height,
+    }
+  }
+}
+
+/// Whether any part of `rect` is on `monitor`
+fn overlaps(rect: WindowRect, monitor: &SystemMonitor) -> bool {
+  rect.x < monitor.x + monitor.width
+    && monitor.x < rect.x + rect.width
+    && rect.y < monitor.y + monitor.height
+    && monitor.y < rect.y + rect.height
+}
+
+/// `rect` placed on `monitor` at `offset` from its top-left corner, shrunk
+/// and shifted as needed to lie entirely on it
+fn fit_onto(rect: WindowRect, offset: (i32, i32), monitor: &SystemMonitor) -> WindowRect {
+  let width = rect.width.clamp(1, monitor.width.max(1));
+  let height = rect.height.clamp(1, monitor.height.max(1));
+  WindowRect {
+    x: monitor.x + offset.0.clamp(0, monitor.width - width),
+    y: monitor.y + offset.1.clamp(0, monitor.height - height),
+    width,
+    height,
+  }
+}
+
+/// Save a connected display to a profile as its `index`-th monitor
+async fn record_live_monitor(
+  conn: &mut PgConnection,
+  profile_id: Uuid,
+  live: &SystemMonitor,
+  index: usize,
+) -> Result<MonitorEntity> {
+  MonitorRepository::create_with_metadata_in(
+    conn,
+    profile_id,
+    &live.name,
+    &live.resolution,
+    &live.orientation,
+    live.is_primary,
+    live.x,
+    live.y,
+    live.width,
+    live.height,
+    index as i32,
+    live.brand.as_deref(),
+    live.model.as_deref(),
+    Some(live.refresh_rate.round() as i32),
+    Some(live.scale_factor),
+    Some(live.is_builtin),
+    None,
+  )
+  .await
+}
+
+async fn insert_window(
+  conn: &mut PgConnection,
+  profile_id: Uuid,
+  app_id: Uuid,
+  monitor_id: Uuid,
+  rect: WindowRect,
+  is_maximized: bool,
+  state: String,
+) -> Result<WindowDto> {
+  let id = Uuid::new_v4();
+  sqlx::query(
+    "INSERT INTO windows (id, profile_id, app_id, monitor_id, x, y, width, height, is_maximized, state)
+     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+  )
+  .bind(id)
+  .bind(profile_id)
+  .bind(app_id)
+  .bind(monitor_id)
+  .bind(rect.x)
+  .bind(rect.y)
+  .bind(rect.width)
+  .bind(rect.height)
+  .bind(is_maximized)
+  .bind(&state)
+  .execute(conn)
+  .await
+  .map_err(SmoothieError::from)?;
+
+  Ok(WindowDto {
+    id: id.to_string(),
+    profile_id: profile_id.to_string(),
+    app_id: app_id.to_string(),
+    monitor_id: monitor_id.to_string(),
+    x: rect.x,
+    y: rect.y,
+    width: rect.width,
+    height: rect.height,
+    is_maximized,
+    state,
+    zoom_steps: None,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db::test_support::test_db_or_skip;
+  use crate::services::system_backend::MockSystemBackend;
+
+  #[test]
+  fn test_fit_onto_keeps_offset_and_clamps_size() {
+    let primary = MockSystemBackend::with_sample_layout().get_monitors()[0].clone();
+    let rect = WindowRect {
+      x: 3000,
+      y: 100,
+      width: 800,
+      height: 600,
+    };
+    assert!(!overlaps(rect, &primary));
+
+    let fitted = fit_onto(rect, (100, 50), &primary);
+    assert_eq!(
+      (fitted.x, fitted.y, fitted.width, fitted.height),
+      (100, 50, 800, 600)
+    );
+
+    let huge = WindowRect {
+      width: 2560,
+      height: 1440,
+      ..rect
+    };
+    let fitted = fit_onto(huge, (500, 500), &primary);
+    assert_eq!(
+      (fitted.x, fitted.y, fitted.width, fitted.height),
+      (0, 0, 1512, 982)
+    );
+    assert!(overlaps(fitted, &primary));
+  }
+
+  #[tokio::test]
+  async fn test_recover_offscreen_windows_after_undocking() {
+    let test_db = test_db_or_skip!();
+    let db = &test_db.db;
+    let user_id = test_db.seed_user().await;
+    let profile_id = test_db.seed_profile(user_id, "Desk").await.to_string();
+    let system = Arc::new(MockSystemBackend::with_sample_layout());
+
+    let safari =
+      WindowService::assign_window_to_profile(db, system.clone(), &profile_id, 101, None)
+        .await
+        .unwrap();
+    let terminal =
+      WindowService::assign_window_to_profile(db, system.clone(), &profile_id, 102, None)
+        .await
+        .unwrap();
+
+    // Undock: only the built-in display is left
+    let built_in = system.get_monitors()[0].clone();
+    system.set_monitors(vec![built_in.clone()]);
+    let off_dell = WindowRect {
+      x: 1600,
+      y: 0,
+      width: 1200,
+      height: 800,
+    };
+    assert!(WindowService::ensure_on_screen(system.clone(), off_dell)
+      .await
+      .is_err());
+
+    let result = WindowService::recover_offscreen_windows(db, system.clone(), &profile_id)
+      .await
+      .unwrap();
+    assert_eq!(result.primary_monitor, built_in.name);
+    assert_eq!(result.windows.len(), 1);
+    let moved = &result.windows[0];
+    assert_eq!(moved.id, terminal.window.id);
+    assert_eq!(moved.monitor_id, safari.monitor.id);
+    // 88px in from the Dell's left edge; pushed up so its 800px height fits
+    assert_eq!(
+      (moved.x, moved.y, moved.width, moved.height),
+      (88, 182, 1200, 800)
+    );
+
+    let windows = WindowService::get_windows(db, &profile_id).await.unwrap();
+    assert!(windows.iter().all(|w| overlaps(w.rect(), &built_in)));
+    assert!(
+      WindowService::recover_offscreen_windows(db, system.clone(), &profile_id)
+        .await
+        .unwrap()
+        .windows
+        .is_empty()
+    );
+
+    // A locked profile's windows stay where they were saved
+    sqlx::query("UPDATE windows SET x = 3000 WHERE id = $1")
+      .bind(parse_uuid(&safari.window.id).unwrap())
+      .execute(db.pool())
+      .await
+      .unwrap();
+    crate::services::ProfileService::set_locked(db, parse_uuid(&profile_id).unwrap().into(), true)
+      .await
+      .unwrap();
+    assert!(matches!(
+      WindowService::recover_offscreen_windows(db, system, &profile_id).await,
+      Err(SmoothieError::Locked(_))
+    ));
+    let windows = WindowService::get_windows(db, &profile_id).await.unwrap();
+    assert!(windows.iter().any(|w| w.x == 3000));
+  }
+
+  #[tokio::test]
+  async fn test_window_zoom_is_kept_within_range() {
+    let test_db = test_db_or_skip!();
+    let db = &test_db.db;
+    let user_id = test_db.seed_user().await;
+    let profile_id = test_db
+      .seed_profile(user_id, "Presentation")
+      .await
+      .to_string();
+    let system = Arc::new(MockSystemBackend::with_sample_layout());
+    let safari = WindowService::assign_window_to_profile(db, system, &profile_id, 101, None)
+      .await
+      .unwrap()
+      .window;
+
+    assert!(
+      WindowService::set_window_zoom(db, &safari.id, Some(MAX_ZOOM_STEPS + 1))
+        .await
+        .is_err()
+    );
+    WindowService::set_window_zoom(db, &safari.id, Some(3))
+      .await
+      .unwrap();
+    let windows = WindowService::get_windows(db, &profile_id).await.unwrap();
+    assert_eq!(windows[0].zoom_steps, Some(3));
+
+    assert!(supports_zoom("com.google.chrome"));
+    assert!(!supports_zoom("com.apple.finder"));
+  }
 }
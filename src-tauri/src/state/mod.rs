@@ -1,20 +1,130 @@
 // Application state management
 
+pub mod startup;
+pub mod supervisor;
+
 use crate::db::Database;
+use crate::models::AutomationPauseDto;
+use crate::services::{system_backend, SystemBackend};
+use crate::storage::BlobStorage;
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{de::DeserializeOwned, Serialize};
 use std::sync::Arc;
+use supervisor::TaskSupervisor;
+use uuid::Uuid;
+
+/// Cache key for the installed-app list, which is slow to build
+pub const INSTALLED_APPS_CACHE_KEY: &str = "installed_apps";
 
 pub struct AppState {
   pub db: Arc<Database>,
+  // Access to displays/windows/apps - swapped for a mock in tests
+  pub system: Arc<dyn SystemBackend>,
   // In-memory cache for frequently accessed data
   pub cache: DashMap<String, Arc<serde_json::Value>>,
+  // Named background tasks, restarted when they panic
+  pub tasks: TaskSupervisor,
+  // Presentation mode: automation and monitor-driven profile switches on hold
+  pub automation_pause: AutomationPause,
+  // Blocked apps the user chose to keep open anyway
+  pub blocked_app_overrides: BlockedAppOverrides,
+  // Icons, favicons and attachments, stored by content hash
+  pub storage: BlobStorage,
+}
+
+/// Holds automation off, for a while or until resumed. While paused, rules
+/// don't fire and monitor changes don't switch profiles.
+#[derive(Default)]
+pub struct AutomationPause {
+  // `Some(None)` is paused until resumed
+  paused: RwLock<Option<Option<DateTime<Utc>>>>,
+}
+
+impl AutomationPause {
+  /// Pause for `duration`, or until `resume` when `None`
+  pub fn pause(&self, duration: Option<chrono::Duration>) -> AutomationPauseDto {
+    *self.paused.write() = Some(duration.map(|d| Utc::now() + d));
+    self.status()
+  }
+
+  pub fn resume(&self) -> AutomationPauseDto {
+    *self.paused.write() = None;
+    self.status()
+  }
+
+  pub fn is_paused(&self) -> bool {
+    match *self.paused.read() {
+      Some(Some(until)) => until > Utc::now(),
+      Some(None) => true,
+      None => false,
+    }
+  }
+
+  pub fn status(&self) -> AutomationPauseDto {
+    AutomationPauseDto {
+      paused: self.is_paused(),
+      until: self
+        .paused
+        .read()
+        .flatten()
+        .filter(|_| self.is_paused())
+        .map(|until| until.to_rfc3339()),
+    }
+  }
+}
+
+/// Blocked apps let through for one profile. An override ends when it runs
+/// out or, sooner, when another profile is activated.
+#[derive(Default)]
+pub struct BlockedAppOverrides {
+  // (profile, lowercased bundle id) -> end, `None` for the whole activation
+  allowed: DashMap<(Uuid, String), Option<DateTime<Utc>>>,
+}
+
+impl BlockedAppOverrides {
+  /// Let `bundle_id` run under `profile_id` for `duration`, or until the profile changes
+  pub fn allow(
+    &self,
+    profile_id: Uuid,
+    bundle_id: &str,
+    duration: Option<chrono::Duration>,
+  ) -> Option<DateTime<Utc>> {
+    let until = duration.map(|d| Utc::now() + d);
+    self
+      .allowed
+      .insert((profile_id, bundle_id.to_lowercase()), until);
+    until
+  }
+
+  pub fn is_allowed(&self, profile_id: Uuid, bundle_id: &str) -> bool {
+    self
+      .allowed
+      .get(&(profile_id, bundle_id.to_lowercase()))
+      .is_some_and(|until| until.map_or(true, |until| until > Utc::now()))
+  }
+
+  /// Forget the overrides of every profile but `profile_id`
+  pub fn retain_profile(&self, profile_id: Option<Uuid>) {
+    self.allowed.retain(|(id, _), _| Some(*id) == profile_id);
+  }
 }
 
 impl AppState {
   pub fn new(db: Arc<Database>) -> Self {
+    Self::with_system_backend(db, system_backend::from_env())
+  }
+
+  pub fn with_system_backend(db: Arc<Database>, system: Arc<dyn SystemBackend>) -> Self {
     Self {
       db,
+      system,
       cache: DashMap::new(),
+      tasks: TaskSupervisor::new(),
+      automation_pause: AutomationPause::default(),
+      blocked_app_overrides: BlockedAppOverrides::default(),
+      storage: BlobStorage::local(),
     }
   }
 
@@ -22,4 +132,64 @@ impl AppState {
   pub fn invalidate_cache(&self, key: &str) {
     self.cache.remove(key);
   }
+
+  /// Cached value for `key`, if there is one of the expected shape
+  pub fn cached<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+    let value = self.cache.get(key)?.clone();
+    T::deserialize(value.as_ref()).ok()
+  }
+
+  pub fn set_cache<T: Serialize>(&self, key: &str, value: &T) {
+    if let Ok(value) = serde_json::to_value(value) {
+      self.cache.insert(key.to_string(), Arc::new(value));
+    }
+  }
+
+  /// Clear every cached profile, for changes that touch more than one
+  /// (an activation flips two `is_active` flags) or whose profile isn't known
+  pub fn invalidate_profile_caches(&self) {
+    self.cache.retain(|key, _| !key.starts_with("profile_"));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_automation_pause_expires_or_lasts_until_resumed() {
+    let pause = AutomationPause::default();
+    assert!(!pause.is_paused());
+
+    let status = pause.pause(Some(chrono::Duration::minutes(30)));
+    assert!(status.paused);
+    assert!(status.until.is_some());
+
+    pause.pause(Some(chrono::Duration::seconds(-1)));
+    assert!(!pause.is_paused());
+    assert_eq!(pause.status().until, None);
+
+    assert_eq!(pause.pause(None).until, None);
+    assert!(pause.is_paused());
+    assert!(!pause.resume().paused);
+  }
+
+  #[test]
+  fn test_blocked_app_overrides_end_with_the_profile() {
+    let overrides = BlockedAppOverrides::default();
+    let (work, home) = (Uuid::new_v4(), Uuid::new_v4());
+
+    overrides.allow(work, "com.tinyspeck.slackmacgap", None);
+    overrides.allow(
+      work,
+      "com.valvesoftware.steam",
+      Some(chrono::Duration::seconds(-1)),
+    );
+    assert!(overrides.is_allowed(work, "com.tinyspeck.SlackMacGap"));
+    assert!(!overrides.is_allowed(home, "com.tinyspeck.slackmacgap"));
+    assert!(!overrides.is_allowed(work, "com.valvesoftware.steam"));
+
+    overrides.retain_profile(Some(home));
+    assert!(!overrides.is_allowed(work, "com.tinyspeck.slackmacgap"));
+  }
 }
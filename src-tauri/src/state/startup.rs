@@ -0,0 +1,126 @@
+// Startup sequence and readiness
+//
+// The app comes up in a fixed order: configuration, the database connection,
+// migrations, the audit session, background watchers, then the window. Each
+// stage is recorded as it starts, and until the last one is done IPC commands
+// fail with `SmoothieError::StartingUp` rather than racing the steps still in
+// flight. The frontend polls `get_startup_status` and retries after that.
+
+use crate::error::{Result, SmoothieError};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Commands that answer before startup is done
+const ALWAYS_AVAILABLE: [&str; 2] = ["get_startup_status", "get_api_version"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StartupStage {
+  Config,
+  Database,
+  Migrations,
+  AuditSession,
+  Watchers,
+  Window,
+  Ready,
+}
+
+impl StartupStage {
+  const ALL: [StartupStage; 7] = [
+    StartupStage::Config,
+    StartupStage::Database,
+    StartupStage::Migrations,
+    StartupStage::AuditSession,
+    StartupStage::Watchers,
+    StartupStage::Window,
+    StartupStage::Ready,
+  ];
+}
+
+static STAGE: AtomicU8 = AtomicU8::new(StartupStage::Config as u8);
+static STARTED_AT: OnceLock<Instant> = OnceLock::new();
+static READY_AFTER: OnceLock<Duration> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupStatus {
+  /// The stage running now, or `ready`
+  pub stage: StartupStage,
+  pub ready: bool,
+  /// How long startup took, once it's done
+  pub ready_after_ms: Option<u64>,
+}
+
+pub struct Startup;
+
+impl Startup {
+  pub fn stage() -> StartupStage {
+    StartupStage::ALL[STAGE.load(Ordering::Acquire) as usize]
+  }
+
+  pub fn is_ready() -> bool {
+    Self::stage() == StartupStage::Ready
+  }
+
+  /// Move on to `stage`. Stages only go forward.
+  pub fn begin(stage: StartupStage) {
+    let started_at = *STARTED_AT.get_or_init(Instant::now);
+    let previous = STAGE.fetch_max(stage as u8, Ordering::AcqRel);
+    if previous >= stage as u8 {
+      return;
+    }
+    let elapsed_ms = started_at.elapsed().as_millis() as u64;
+    if stage == StartupStage::Ready {
+      let _ = READY_AFTER.set(started_at.elapsed());
+      tracing::info!(elapsed_ms, "Startup complete");
+    } else {
+      tracing::info!(?stage, elapsed_ms, "Startup stage");
+    }
+  }
+
+  pub fn status() -> StartupStatus {
+    StartupStatus {
+      stage: Self::stage(),
+      ready: Self::is_ready(),
+      ready_after_ms: READY_AFTER.get().map(|d| d.as_millis() as u64),
+    }
+  }
+
+  /// Fail `command` with `StartingUp` until startup is done
+  pub fn check(command: &str) -> Result<()> {
+    if Self::is_ready() || ALWAYS_AVAILABLE.contains(&command) {
+      return Ok(());
+    }
+    Err(SmoothieError::StartingUp(format!(
+      "{} is unavailable until startup finishes (at {:?})",
+      command,
+      Self::stage()
+    )))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_commands_wait_for_the_last_stage() {
+    assert!(matches!(
+      Startup::check("get_profiles"),
+      Err(SmoothieError::StartingUp(_))
+    ));
+    assert!(Startup::check("get_startup_status").is_ok());
+    assert!(Startup::check("get_api_version").is_ok());
+
+    Startup::begin(StartupStage::Watchers);
+    Startup::begin(StartupStage::Database);
+    assert_eq!(Startup::stage(), StartupStage::Watchers);
+    assert!(!Startup::status().ready);
+
+    Startup::begin(StartupStage::Ready);
+    assert!(Startup::check("get_profiles").is_ok());
+    assert!(Startup::status().ready_after_ms.is_some());
+  }
+}
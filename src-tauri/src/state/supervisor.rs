@@ -0,0 +1,224 @@
+// Supervision for long-lived background tasks
+//
+// Watchers, rollups and startup jobs register here under a name instead of
+// being spawned and forgotten. A task that panics is rebuilt from its factory
+// after an exponential backoff; one that keeps panicking is given up on and
+// left in the `failed` state for `get_background_tasks` to report.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Delay before the first restart; doubled for each consecutive panic
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Consecutive panics after which a task is no longer restarted
+const MAX_CONSECUTIVE_PANICS: u32 = 5;
+
+/// A task that ran at least this long before panicking starts its backoff over
+const STABLE_RUN: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+  Running,
+  /// Panicked and waiting out its backoff
+  Restarting,
+  Completed,
+  /// Gave up after too many consecutive panics
+  Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackgroundTaskStatus {
+  pub name: String,
+  pub state: TaskState,
+  pub restarts: u32,
+  pub started_at: DateTime<Utc>,
+  pub finished_at: Option<DateTime<Utc>>,
+  pub last_panic: Option<String>,
+  pub last_panic_at: Option<DateTime<Utc>>,
+}
+
+pub struct TaskSupervisor {
+  tasks: Arc<DashMap<String, BackgroundTaskStatus>>,
+  backoff: Duration,
+  max_backoff: Duration,
+}
+
+impl TaskSupervisor {
+  pub fn new() -> Self {
+    Self::with_backoff(RESTART_BACKOFF, MAX_RESTART_BACKOFF)
+  }
+
+  pub fn with_backoff(backoff: Duration, max_backoff: Duration) -> Self {
+    Self {
+      tasks: Arc::new(DashMap::new()),
+      backoff,
+      max_backoff,
+    }
+  }
+
+  /// Run the future built by `task` under `name`, rebuilding it whenever it panics
+  pub fn spawn<F, Fut>(&self, name: &str, task: F)
+  where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+  {
+    let name = name.to_string();
+    let tasks = self.tasks.clone();
+    let (base_backoff, max_backoff) = (self.backoff, self.max_backoff);
+    tasks.insert(
+      name.clone(),
+      BackgroundTaskStatus {
+        name: name.clone(),
+        state: TaskState::Running,
+        restarts: 0,
+        started_at: Utc::now(),
+        finished_at: None,
+        last_panic: None,
+        last_panic_at: None,
+      },
+    );
+
+    tokio::spawn(async move {
+      let mut consecutive_panics = 0;
+      loop {
+        let started = Instant::now();
+        let panic = match tokio::spawn(task()).await {
+          Ok(()) => None,
+          Err(e) if e.is_panic() => Some(panic_message(e.into_panic())),
+          // Cancelled along with the runtime
+          Err(_) => return,
+        };
+
+        let Some(message) = panic else {
+          tracing::debug!(task = %name, "Background task finished");
+          if let Some(mut status) = tasks.get_mut(&name) {
+            status.state = TaskState::Completed;
+            status.finished_at = Some(Utc::now());
+          }
+          return;
+        };
+
+        if started.elapsed() >= STABLE_RUN {
+          consecutive_panics = 0;
+        }
+        consecutive_panics += 1;
+        let give_up = consecutive_panics >= MAX_CONSECUTIVE_PANICS;
+        let backoff = base_backoff
+          .saturating_mul(1 << (consecutive_panics - 1).min(16))
+          .min(max_backoff);
+        if give_up {
+          tracing::error!(
+            task = %name,
+            panic = %message,
+            "Background task keeps panicking, giving up"
+          );
+        } else {
+          tracing::error!(
+            task = %name,
+            panic = %message,
+            retry_in_ms = backoff.as_millis() as u64,
+            "Background task panicked, restarting"
+          );
+        }
+        if let Some(mut status) = tasks.get_mut(&name) {
+          status.state = if give_up {
+            TaskState::Failed
+          } else {
+            TaskState::Restarting
+          };
+          status.last_panic = Some(message);
+          status.last_panic_at = Some(Utc::now());
+          if give_up {
+            status.finished_at = Some(Utc::now());
+          }
+        }
+        if give_up {
+          return;
+        }
+
+        tokio::time::sleep(backoff).await;
+        if let Some(mut status) = tasks.get_mut(&name) {
+          status.state = TaskState::Running;
+          status.restarts += 1;
+          status.started_at = Utc::now();
+        }
+      }
+    });
+  }
+
+  /// Every registered task, by name
+  pub fn statuses(&self) -> Vec<BackgroundTaskStatus> {
+    let mut statuses: Vec<_> = self.tasks.iter().map(|entry| entry.clone()).collect();
+    statuses.sort_by(|a, b| a.name.cmp(&b.name));
+    statuses
+  }
+}
+
+impl Default for TaskSupervisor {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+  if let Some(message) = payload.downcast_ref::<&str>() {
+    message.to_string()
+  } else if let Some(message) = payload.downcast_ref::<String>() {
+    message.clone()
+  } else {
+    "unknown panic".to_string()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicU32, Ordering};
+
+  async fn wait_for(supervisor: &TaskSupervisor, name: &str, state: TaskState) {
+    for _ in 0..200 {
+      let status = supervisor.statuses().into_iter().find(|s| s.name == name);
+      if status.is_some_and(|s| s.state == state) {
+        return;
+      }
+      tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("{} never reached {:?}", name, state);
+  }
+
+  #[tokio::test]
+  async fn test_restarts_panicking_tasks_until_they_finish_or_give_up() {
+    let supervisor =
+      TaskSupervisor::with_backoff(Duration::from_millis(1), Duration::from_millis(4));
+
+    let attempts = Arc::new(AtomicU32::new(0));
+    let counter = attempts.clone();
+    supervisor.spawn("flaky", move || {
+      let counter = counter.clone();
+      async move {
+        if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+          panic!("not yet");
+        }
+      }
+    });
+    supervisor.spawn("broken", || async { panic!("always") });
+
+    wait_for(&supervisor, "flaky", TaskState::Completed).await;
+    wait_for(&supervisor, "broken", TaskState::Failed).await;
+
+    let statuses = supervisor.statuses();
+    assert_eq!(statuses[0].name, "broken");
+    assert_eq!(statuses[0].restarts, MAX_CONSECUTIVE_PANICS - 1);
+    assert_eq!(statuses[0].last_panic.as_deref(), Some("always"));
+    assert_eq!(statuses[1].restarts, 2);
+    assert!(statuses[1].finished_at.is_some());
+  }
+}
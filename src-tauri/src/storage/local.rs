@@ -0,0 +1,68 @@
+// Blob backend on the local file system
+
+use super::{BlobBackend, BlobEntry};
+use crate::error::Result;
+use crate::utils::paths;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+
+/// Stores each blob as a file named by its key under `root`
+pub struct LocalBlobBackend {
+  root: PathBuf,
+}
+
+impl LocalBlobBackend {
+  pub fn new(root: PathBuf) -> Self {
+    Self { root }
+  }
+}
+
+#[async_trait]
+impl BlobBackend for LocalBlobBackend {
+  async fn put(&self, key: &str, data: &[u8]) -> Result<()> {
+    let path = self.root.join(key);
+    if let Some(dir) = path.parent() {
+      tokio::fs::create_dir_all(dir).await?;
+    }
+    paths::write_atomically(&path, data).await
+  }
+
+  async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+    match tokio::fs::read(self.root.join(key)).await {
+      Ok(data) => Ok(Some(data)),
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+      Err(e) => Err(e.into()),
+    }
+  }
+
+  async fn delete(&self, key: &str) -> Result<()> {
+    match tokio::fs::remove_file(self.root.join(key)).await {
+      Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+      _ => Ok(()),
+    }
+  }
+
+  async fn list(&self, prefix: &str) -> Result<Vec<BlobEntry>> {
+    let mut entries = Vec::new();
+    let mut dir = match tokio::fs::read_dir(self.root.join(prefix)).await {
+      Ok(dir) => dir,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(entries),
+      Err(e) => return Err(e.into()),
+    };
+    while let Some(entry) = dir.next_entry().await? {
+      let name = entry.file_name().to_string_lossy().into_owned();
+      let meta = entry.metadata().await?;
+      // Skip half-written files from `write_atomically`
+      if !meta.is_file() || name.ends_with(".tmp") {
+        continue;
+      }
+      entries.push(BlobEntry {
+        key: format!("{}/{}", prefix, name),
+        size: meta.len(),
+        modified: meta.modified().map(DateTime::<Utc>::from)?,
+      });
+    }
+    Ok(entries)
+  }
+}
@@ -0,0 +1,274 @@
+// Blob storage for icons, favicons and attachments
+//
+// Blobs are content-addressed: the key is the SHA-256 of the bytes, so the
+// same icon saved from ten profiles is stored once. Rows refer to a blob by
+// its reference, `blob:<kind>/<hash>`. Each kind has a limit per blob and a
+// quota for all of them together, and blobs nothing refers to any more are
+// collected once they're a week old. Where the bytes live is up to the
+// backend; `LocalBlobBackend` keeps them in the app data directory.
+
+pub mod local;
+
+pub use local::LocalBlobBackend;
+
+use crate::error::{Result, SmoothieError};
+use crate::utils::{crypto, paths};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Prefix of a blob reference as stored in the database
+pub const BLOB_REF_PREFIX: &str = "blob:";
+
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BlobKind {
+  Icon,
+  Favicon,
+  /// Screenshots and files attached to feedback
+  Attachment,
+}
+
+impl BlobKind {
+  pub const ALL: [BlobKind; 3] = [BlobKind::Icon, BlobKind::Favicon, BlobKind::Attachment];
+
+  fn dir(self) -> &'static str {
+    match self {
+      BlobKind::Icon => "icons",
+      BlobKind::Favicon => "favicons",
+      BlobKind::Attachment => "attachments",
+    }
+  }
+
+  fn from_dir(dir: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|kind| kind.dir() == dir)
+  }
+
+  /// Largest single blob
+  pub fn max_size(self) -> u64 {
+    match self {
+      BlobKind::Icon => MIB,
+      BlobKind::Favicon => 256 * KIB,
+      BlobKind::Attachment => 10 * MIB,
+    }
+  }
+
+  /// Bytes all blobs of the kind may take together
+  pub fn quota(self) -> u64 {
+    match self {
+      BlobKind::Icon => 50 * MIB,
+      BlobKind::Favicon => 20 * MIB,
+      BlobKind::Attachment => 200 * MIB,
+    }
+  }
+}
+
+/// A stored blob as the backend lists it
+#[derive(Debug, Clone)]
+pub struct BlobEntry {
+  /// `<kind dir>/<hash>`
+  pub key: String,
+  pub size: u64,
+  pub modified: DateTime<Utc>,
+}
+
+/// Where blob bytes are kept. Keys are validated before they get here.
+#[async_trait]
+pub trait BlobBackend: Send + Sync {
+  async fn put(&self, key: &str, data: &[u8]) -> Result<()>;
+  async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+  async fn delete(&self, key: &str) -> Result<()>;
+  /// Every blob whose key starts with `prefix/`
+  async fn list(&self, prefix: &str) -> Result<Vec<BlobEntry>>;
+}
+
+/// Space used by one kind of blob
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobUsage {
+  pub kind: BlobKind,
+  pub blobs: usize,
+  pub bytes: u64,
+  pub quota: u64,
+}
+
+/// What a garbage collection pass removed
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobGcReport {
+  pub removed: usize,
+  pub bytes_freed: u64,
+}
+
+fn blob_ref(key: &str) -> String {
+  format!("{}{}", BLOB_REF_PREFIX, key)
+}
+
+/// The kind and backend key of a `blob:<kind>/<hash>` reference
+fn parse_ref(reference: &str) -> Result<(BlobKind, &str)> {
+  let invalid = || SmoothieError::ValidationError(format!("Invalid blob reference: {}", reference));
+  let key = reference
+    .strip_prefix(BLOB_REF_PREFIX)
+    .ok_or_else(invalid)?;
+  let (dir, hash) = key.split_once('/').ok_or_else(invalid)?;
+  let kind = BlobKind::from_dir(dir).ok_or_else(invalid)?;
+  let is_sha256 = hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+  if !is_sha256 {
+    return Err(invalid());
+  }
+  Ok((kind, key))
+}
+
+pub struct BlobStorage {
+  backend: Arc<dyn BlobBackend>,
+}
+
+impl BlobStorage {
+  pub fn new(backend: Arc<dyn BlobBackend>) -> Self {
+    Self { backend }
+  }
+
+  /// Blobs under `<app data dir>/blobs`
+  pub fn local() -> Self {
+    Self::new(Arc::new(LocalBlobBackend::new(
+      paths::app_data_dir().join("blobs"),
+    )))
+  }
+
+  /// Store `data` and return its reference. Storing bytes that are already
+  /// there returns the existing reference without writing.
+  pub async fn put(&self, kind: BlobKind, data: &[u8]) -> Result<String> {
+    let size = data.len() as u64;
+    if size == 0 || size > kind.max_size() {
+      return Err(SmoothieError::ValidationError(format!(
+        "{:?} blobs must be between 1 byte and {} KiB",
+        kind,
+        kind.max_size() / KIB
+      )));
+    }
+
+    let key = format!("{}/{}", kind.dir(), crypto::sha256_hex(data));
+    let existing = self.backend.list(kind.dir()).await?;
+    if existing.iter().any(|entry| entry.key == key) {
+      return Ok(blob_ref(&key));
+    }
+    let used: u64 = existing.iter().map(|entry| entry.size).sum();
+    if used + size > kind.quota() {
+      return Err(SmoothieError::ValidationError(format!(
+        "{:?} storage is full ({} of {} MiB used)",
+        kind,
+        used / MIB,
+        kind.quota() / MIB
+      )));
+    }
+
+    self.backend.put(&key, data).await?;
+    tracing::debug!(key = %key, size, "Blob stored");
+    Ok(blob_ref(&key))
+  }
+
+  pub async fn get(&self, reference: &str) -> Result<Vec<u8>> {
+    let (_, key) = parse_ref(reference)?;
+    self
+      .backend
+      .get(key)
+      .await?
+      .ok_or_else(|| SmoothieError::NotFound(format!("Blob {}", reference)))
+  }
+
+  /// Whether `reference` is a stored blob of `kind`
+  pub async fn contains(&self, reference: &str, kind: BlobKind) -> Result<bool> {
+    let (ref_kind, key) = parse_ref(reference)?;
+    if ref_kind != kind {
+      return Ok(false);
+    }
+    let entries = self.backend.list(kind.dir()).await?;
+    Ok(entries.iter().any(|entry| entry.key == key))
+  }
+
+  pub async fn usage(&self) -> Result<Vec<BlobUsage>> {
+    let mut usage = Vec::with_capacity(BlobKind::ALL.len());
+    for kind in BlobKind::ALL {
+      let entries = self.backend.list(kind.dir()).await?;
+      usage.push(BlobUsage {
+        kind,
+        blobs: entries.len(),
+        bytes: entries.iter().map(|entry| entry.size).sum(),
+        quota: kind.quota(),
+      });
+    }
+    Ok(usage)
+  }
+
+  /// Delete blobs missing from `referenced` that were last written before
+  /// `older_than`, so one stored just before its row is saved survives
+  pub async fn collect_garbage(
+    &self,
+    referenced: &HashSet<String>,
+    older_than: DateTime<Utc>,
+  ) -> Result<BlobGcReport> {
+    let mut report = BlobGcReport::default();
+    for kind in BlobKind::ALL {
+      for entry in self.backend.list(kind.dir()).await? {
+        if entry.modified >= older_than || referenced.contains(&blob_ref(&entry.key)) {
+          continue;
+        }
+        self.backend.delete(&entry.key).await?;
+        report.removed += 1;
+        report.bytes_freed += entry.size;
+      }
+    }
+    Ok(report)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use uuid::Uuid;
+
+  #[tokio::test]
+  async fn test_blobs_are_deduplicated_capped_and_collected() {
+    let dir = std::env::temp_dir().join(format!("smoothie-blobs-{}", Uuid::new_v4()));
+    let storage = BlobStorage::new(Arc::new(LocalBlobBackend::new(dir.clone())));
+
+    let icon = storage.put(BlobKind::Icon, b"png bytes").await.unwrap();
+    assert!(icon.starts_with("blob:icons/"));
+    assert_eq!(
+      storage.put(BlobKind::Icon, b"png bytes").await.unwrap(),
+      icon
+    );
+    assert_eq!(storage.get(&icon).await.unwrap(), b"png bytes");
+    assert!(storage.contains(&icon, BlobKind::Icon).await.unwrap());
+    assert!(!storage.contains(&icon, BlobKind::Favicon).await.unwrap());
+
+    let too_big = vec![0u8; (BlobKind::Favicon.max_size() + 1) as usize];
+    assert!(storage.put(BlobKind::Favicon, &too_big).await.is_err());
+    assert!(storage.get("blob:icons/../../etc/passwd").await.is_err());
+
+    let orphan = storage.put(BlobKind::Favicon, b"ico bytes").await.unwrap();
+    let referenced = HashSet::from([icon.clone()]);
+    let kept = storage
+      .collect_garbage(&referenced, Utc::now() - chrono::Duration::days(7))
+      .await
+      .unwrap();
+    assert_eq!(kept.removed, 0);
+    let report = storage
+      .collect_garbage(&referenced, Utc::now() + chrono::Duration::seconds(1))
+      .await
+      .unwrap();
+    assert_eq!((report.removed, report.bytes_freed), (1, 9));
+    assert!(storage.get(&orphan).await.is_err());
+    assert!(storage.get(&icon).await.is_ok());
+
+    let usage = storage.usage().await.unwrap();
+    assert_eq!((usage[0].blobs, usage[0].bytes), (1, 9));
+
+    let _ = tokio::fs::remove_dir_all(dir).await;
+  }
+}
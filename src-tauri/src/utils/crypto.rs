@@ -0,0 +1,86 @@
+// Passphrase-based encryption for files that leave the machine
+//
+// Keys are derived from a passphrase with PBKDF2-HMAC-SHA256 and data is sealed
+// with AES-256-GCM under a fresh random nonce, stored ahead of the ciphertext.
+
+use crate::error::{Result, SmoothieError};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use sha2::{Digest, Sha256};
+
+pub const KEY_LEN: usize = 32;
+pub const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const PBKDF2_ROUNDS: u32 = 200_000;
+
+pub type SecretKey = [u8; KEY_LEN];
+
+pub fn random_salt() -> [u8; SALT_LEN] {
+  use aes_gcm::aead::rand_core::RngCore;
+  let mut salt = [0u8; SALT_LEN];
+  OsRng.fill_bytes(&mut salt);
+  salt
+}
+
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> SecretKey {
+  pbkdf2::pbkdf2_hmac_array::<Sha256, KEY_LEN>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS)
+}
+
+/// `nonce || ciphertext`
+pub fn seal(key: &SecretKey, plaintext: &[u8]) -> Result<Vec<u8>> {
+  let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+  let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+  let ciphertext = cipher
+    .encrypt(&nonce, plaintext)
+    .map_err(|_| SmoothieError::SystemError("Encryption failed".into()))?;
+  let mut sealed = nonce.to_vec();
+  sealed.extend_from_slice(&ciphertext);
+  Ok(sealed)
+}
+
+/// Fails for data sealed under a different key or altered since
+pub fn open(key: &SecretKey, sealed: &[u8]) -> Result<Vec<u8>> {
+  if sealed.len() < NONCE_LEN {
+    return Err(SmoothieError::ValidationError(
+      "Encrypted data is truncated".into(),
+    ));
+  }
+  let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+  Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+    .decrypt(Nonce::from_slice(nonce), ciphertext)
+    .map_err(|_| SmoothieError::ValidationError("Wrong key or corrupted data".into()))
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+  to_hex(&Sha256::digest(data))
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+  if hex.len() % 2 != 0 {
+    return None;
+  }
+  (0..hex.len())
+    .step_by(2)
+    .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_sealed_data_opens_only_with_the_same_passphrase() {
+    let salt = random_salt();
+    let key = derive_key("correct horse", &salt);
+    let sealed = seal(&key, b"profile bundle").unwrap();
+
+    assert_eq!(open(&key, &sealed).unwrap(), b"profile bundle");
+    assert!(open(&derive_key("wrong horse", &salt), &sealed).is_err());
+    assert_eq!(from_hex(&to_hex(&sealed)).unwrap(), sealed);
+  }
+}
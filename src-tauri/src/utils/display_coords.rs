@@ -0,0 +1,157 @@
+// Converting window frames between points and a display's pixels.
+//
+// macOS places displays and windows in points, one global space shared by all
+// displays; a display's pixels are its points times its scale factor. Window
+// frames are stored in points, which only describe the same part of a screen
+// while the display keeps the scale it had when they were saved. Going through
+// the display's pixels keeps a window in place when the scale changed since.
+
+use crate::models::entities::MonitorEntity;
+use crate::services::window_service::WindowRect;
+use crate::services::SystemMonitor;
+
+/// Scale factor to convert with; unknown or nonsensical ones count as 1x
+pub fn normalize_scale(scale: f64) -> f64 {
+  if scale.is_finite() && scale > 0.0 {
+    scale
+  } else {
+    1.0
+  }
+}
+
+pub fn points_to_pixels(points: i32, scale: f64) -> i32 {
+  (points as f64 * normalize_scale(scale)).round() as i32
+}
+
+pub fn pixels_to_points(pixels: i32, scale: f64) -> i32 {
+  (pixels as f64 / normalize_scale(scale)).round() as i32
+}
+
+/// A rect in one display's pixels, from its top-left corner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelRect {
+  pub x: i32,
+  pub y: i32,
+  pub width: i32,
+  pub height: i32,
+}
+
+/// Where a display sits in global points, and its scale factor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayFrame {
+  pub x: i32,
+  pub y: i32,
+  pub width: i32,
+  pub height: i32,
+  pub scale_factor: f64,
+}
+
+impl DisplayFrame {
+  /// `rect`, in global points, as pixels of this display
+  pub fn pixels_of(&self, rect: WindowRect) -> PixelRect {
+    PixelRect {
+      x: points_to_pixels(rect.x - self.x, self.scale_factor),
+      y: points_to_pixels(rect.y - self.y, self.scale_factor),
+      width: points_to_pixels(rect.width, self.scale_factor),
+      height: points_to_pixels(rect.height, self.scale_factor),
+    }
+  }
+
+  /// Pixels of this display as a rect in global points
+  pub fn points_of(&self, rect: PixelRect) -> WindowRect {
+    WindowRect {
+      x: self.x + pixels_to_points(rect.x, self.scale_factor),
+      y: self.y + pixels_to_points(rect.y, self.scale_factor),
+      width: pixels_to_points(rect.width, self.scale_factor),
+      height: pixels_to_points(rect.height, self.scale_factor),
+    }
+  }
+
+  /// `rect`, recorded while the display was at `from`, covering the same
+  /// pixels of the display as it is now
+  pub fn translate(&self, from: &DisplayFrame, rect: WindowRect) -> WindowRect {
+    if from == self {
+      return rect;
+    }
+    self.points_of(from.pixels_of(rect))
+  }
+}
+
+impl From<&SystemMonitor> for DisplayFrame {
+  fn from(monitor: &SystemMonitor) -> Self {
+    Self {
+      x: monitor.x,
+      y: monitor.y,
+      width: monitor.width,
+      height: monitor.height,
+      scale_factor: normalize_scale(monitor.scale_factor),
+    }
+  }
+}
+
+impl From<&MonitorEntity> for DisplayFrame {
+  fn from(monitor: &MonitorEntity) -> Self {
+    Self {
+      x: monitor.x,
+      y: monitor.y,
+      width: monitor.width,
+      height: monitor.height,
+      scale_factor: normalize_scale(monitor.scale_factor.unwrap_or(1.0)),
+    }
+  }
+}
+
+/// Whether a saved monitor is the record of a connected display. Unlike the
+/// fingerprint this ignores size, which changes with the display's scale.
+pub fn is_same_display(saved: &MonitorEntity, live: &SystemMonitor) -> bool {
+  !live.is_virtual()
+    && saved.brand == live.brand
+    && saved.model.as_deref().unwrap_or(&saved.name) == live.model.as_deref().unwrap_or(&live.name)
+    && saved.is_builtin.unwrap_or(false) == live.is_builtin
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_translate_keeps_window_on_same_pixels_across_scales() {
+    // A 4K display at 2x, then switched to 1x
+    let retina = DisplayFrame {
+      x: 1512,
+      y: 0,
+      width: 1920,
+      height: 1080,
+      scale_factor: 2.0,
+    };
+    let native = DisplayFrame {
+      width: 3840,
+      height: 2160,
+      scale_factor: 1.0,
+      ..retina
+    };
+    assert_eq!(
+      points_to_pixels(retina.width, retina.scale_factor),
+      native.width
+    );
+
+    let right_half = WindowRect {
+      x: 1512 + 960,
+      y: 0,
+      width: 960,
+      height: 1080,
+    };
+    let moved = native.translate(&retina, right_half);
+    assert_eq!(
+      (moved.x, moved.y, moved.width, moved.height),
+      (1512 + 1920, 0, 1920, 2160)
+    );
+
+    let back = retina.translate(&native, moved);
+    assert_eq!(
+      (back.x, back.width, back.height),
+      (right_half.x, right_half.width, right_half.height)
+    );
+    assert_eq!(normalize_scale(f64::NAN), 1.0);
+  }
+}
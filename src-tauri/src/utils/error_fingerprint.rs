@@ -0,0 +1,83 @@
+// Error fingerprinting - groups errors that differ only in interpolated values
+
+use regex::Regex;
+
+lazy_static::lazy_static! {
+  // Order matters: UUIDs and hex literals contain digits that the number pattern would split
+  static ref VOLATILE_PARTS: [(Regex, &'static str); 5] = [
+    (
+      Regex::new(r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b").unwrap(),
+      "<uuid>",
+    ),
+    (Regex::new(r"(?i)\b0x[0-9a-f]+\b").unwrap(), "<hex>"),
+    (Regex::new(r#""[^"]*"|'[^']*'"#).unwrap(), "<str>"),
+    (Regex::new(r"\d+(\.\d+)?").unwrap(), "<n>"),
+    (Regex::new(r"\s+").unwrap(), " "),
+  ];
+}
+
+/// Replace IDs, numbers and quoted values so messages from the same failure compare equal
+pub fn normalize_message(message: &str) -> String {
+  let mut normalized = message.trim().to_string();
+  for (pattern, replacement) in VOLATILE_PARTS.iter() {
+    normalized = pattern.replace_all(&normalized, *replacement).into_owned();
+  }
+  normalized
+}
+
+/// Stable fingerprint of an error from its type, normalized message and source location.
+///
+/// The line number is left out on purpose so unrelated edits to a file don't
+/// split an existing group. FNV-1a is used because the value is stored and
+/// must not change between builds the way `DefaultHasher` may.
+pub fn error_fingerprint(
+  error_type: &str,
+  message: &str,
+  source_file: Option<&str>,
+  source_function: Option<&str>,
+) -> String {
+  const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+  const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+  let normalized = normalize_message(message);
+  let parts = [
+    error_type,
+    normalized.as_str(),
+    source_file.unwrap_or(""),
+    source_function.unwrap_or(""),
+  ];
+
+  let mut hash = FNV_OFFSET;
+  for part in parts {
+    // Separator byte keeps ("ab", "c") and ("a", "bc") apart
+    for byte in part.bytes().chain(std::iter::once(0xff)) {
+      hash ^= byte as u64;
+      hash = hash.wrapping_mul(FNV_PRIME);
+    }
+  }
+  format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_interpolated_values_share_a_fingerprint() {
+    assert_eq!(
+      normalize_message("Profile 3f1c2a9e-8d7b-4c6a-9e1f-0a2b3c4d5e6f not found after 250ms"),
+      "Profile <uuid> not found after <n>ms"
+    );
+    assert_eq!(
+      normalize_message("Failed to open 'report.pdf':   code 0x1F"),
+      "Failed to open <str>: code <hex>"
+    );
+
+    let a = error_fingerprint("database", "row 12 missing", Some("repo.rs"), None);
+    let b = error_fingerprint("database", "row 9041 missing", Some("repo.rs"), None);
+    let other_file = error_fingerprint("database", "row 12 missing", Some("other.rs"), None);
+    assert_eq!(a, b);
+    assert_ne!(a, other_file);
+    assert_eq!(a.len(), 16);
+  }
+}
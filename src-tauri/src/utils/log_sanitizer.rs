@@ -0,0 +1,175 @@
+// Log sanitizing - redacts what could identify a user before logs are shared
+//
+// URLs, email addresses, file paths and app names (with their bundle ids) are
+// replaced with placeholders like `<url>`, each category switchable on its
+// own. Replacement runs in that order so a URL's path isn't reported as a
+// file path too.
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+lazy_static::lazy_static! {
+  static ref URL: Regex = Regex::new(r#"(?i)\b[a-z][a-z0-9+.\-]*://[^\s"'<>]+"#).unwrap();
+  static ref EMAIL: Regex =
+    Regex::new(r"(?i)\b[a-z0-9._%+\-]+@[a-z0-9\-]+(?:\.[a-z0-9\-]+)*\.[a-z]{2,}\b").unwrap();
+  // Only paths starting a token, so "and/or" and ratios are left alone
+  static ref FILE_PATH: Regex = Regex::new(r#"(^|[\s"'(=:\[])~?/[^\s"'()\[\]<>,;]+"#).unwrap();
+  static ref BUNDLE_ID: Regex =
+    Regex::new(r"\b(?:com|org|net|io|dev|app)\.[A-Za-z0-9\-]+(?:\.[A-Za-z0-9\-]+)+\b").unwrap();
+}
+
+/// Names shorter than this are too likely to be ordinary words
+const MIN_APP_NAME_LEN: usize = 3;
+
+/// What to redact; everything by default
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SanitizeOptions {
+  pub urls: bool,
+  pub emails: bool,
+  pub file_paths: bool,
+  /// App names and bundle ids
+  pub app_names: bool,
+}
+
+impl Default for SanitizeOptions {
+  fn default() -> Self {
+    Self {
+      urls: true,
+      emails: true,
+      file_paths: true,
+      app_names: true,
+    }
+  }
+}
+
+/// How many values of each kind were redacted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactionCounts {
+  pub urls: usize,
+  pub emails: usize,
+  pub file_paths: usize,
+  pub app_names: usize,
+}
+
+pub struct LogSanitizer {
+  options: SanitizeOptions,
+  app_names: Option<Regex>,
+  counts: RedactionCounts,
+}
+
+fn redact(pattern: &Regex, text: &str, placeholder: &str, count: &mut usize) -> String {
+  pattern
+    .replace_all(text, |_: &Captures| {
+      *count += 1;
+      placeholder.to_string()
+    })
+    .into_owned()
+}
+
+impl LogSanitizer {
+  /// `app_names` are the names to treat as apps, e.g. the installed apps
+  pub fn new(options: SanitizeOptions, app_names: impl IntoIterator<Item = String>) -> Self {
+    let mut names: Vec<String> = app_names
+      .into_iter()
+      .map(|name| name.trim().to_string())
+      .filter(|name| name.chars().count() >= MIN_APP_NAME_LEN)
+      .collect();
+    // Longest first so "Visual Studio Code" wins over "Code"
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    names.dedup();
+    let app_names = (options.app_names && !names.is_empty()).then(|| {
+      let alternation: Vec<String> = names.iter().map(|name| regex::escape(name)).collect();
+      Regex::new(&format!(r"\b(?:{})\b", alternation.join("|"))).unwrap()
+    });
+    Self {
+      options,
+      app_names,
+      counts: RedactionCounts::default(),
+    }
+  }
+
+  pub fn counts(&self) -> RedactionCounts {
+    self.counts
+  }
+
+  pub fn sanitize(&mut self, text: &str) -> String {
+    let mut text = text.to_string();
+    if self.options.urls {
+      text = redact(&URL, &text, "<url>", &mut self.counts.urls);
+    }
+    if self.options.emails {
+      text = redact(&EMAIL, &text, "<email>", &mut self.counts.emails);
+    }
+    if self.options.file_paths {
+      let count = &mut self.counts.file_paths;
+      text = FILE_PATH
+        .replace_all(&text, |caps: &Captures| {
+          *count += 1;
+          format!("{}<path>", &caps[1])
+        })
+        .into_owned();
+    }
+    if self.options.app_names {
+      text = redact(&BUNDLE_ID, &text, "<bundle-id>", &mut self.counts.app_names);
+      if let Some(pattern) = &self.app_names {
+        text = redact(pattern, &text, "<app>", &mut self.counts.app_names);
+      }
+    }
+    text
+  }
+
+  /// Sanitize every string in `value`; object keys are kept as they are
+  pub fn sanitize_value(&mut self, value: &mut Value) {
+    match value {
+      Value::String(text) => *text = self.sanitize(text),
+      Value::Array(items) => items.iter_mut().for_each(|item| self.sanitize_value(item)),
+      Value::Object(fields) => fields
+        .values_mut()
+        .for_each(|field| self.sanitize_value(field)),
+      _ => {}
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_redacts_each_category_it_is_asked_to() {
+    let apps = vec!["Code".to_string(), "Visual Studio Code".to_string()];
+    let mut sanitizer = LogSanitizer::new(SanitizeOptions::default(), apps.clone());
+    let line = "Visual Studio Code (com.microsoft.VSCode) failed to open \
+                /Users/jane/Projects/app.rs and https://jira.example.com/browse/X-1 \
+                for jane.doe@example.com, read/write";
+    assert_eq!(
+      sanitizer.sanitize(line),
+      "<app> (<bundle-id>) failed to open <path> and <url> for <email>, read/write"
+    );
+    assert_eq!(
+      sanitizer.counts(),
+      RedactionCounts {
+        urls: 1,
+        emails: 1,
+        file_paths: 1,
+        app_names: 2,
+      }
+    );
+
+    let mut value = serde_json::json!({ "path": "~/Downloads/x.dmg", "n": 3 });
+    sanitizer.sanitize_value(&mut value);
+    assert_eq!(value, serde_json::json!({ "path": "<path>", "n": 3 }));
+
+    let mut keep_apps = LogSanitizer::new(
+      SanitizeOptions {
+        app_names: false,
+        ..Default::default()
+      },
+      apps,
+    );
+    assert_eq!(keep_apps.sanitize("Code crashed"), "Code crashed");
+  }
+}
@@ -0,0 +1,62 @@
+// Markdown sanitization for profile notes
+//
+// Notes are rendered as markdown in the profile view, so they are cleaned up
+// when saved rather than trusted at render time: raw HTML and comments are
+// dropped, links can't use script-capable schemes, and control characters
+// other than tabs and newlines go away.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Longest notes, in characters
+pub const MAX_NOTES_LENGTH: u64 = 20_000;
+
+lazy_static! {
+  static ref HTML_COMMENT: Regex = Regex::new(r"<!--[\s\S]*?(-->|$)").unwrap();
+  static ref HTML_TAG: Regex = Regex::new(r"</?[A-Za-z][\w-]*(\s[^>]*)?/?>").unwrap();
+  // `[text](javascript:...)`, `<javascript:...>` and `[ref]: javascript:...`;
+  // link targets may hold one level of balanced parentheses
+  static ref INLINE_LINK: Regex =
+    Regex::new(r"(?i)\]\(\s*<?\s*(javascript|vbscript|data|file):(?:[^()\s]|\([^()]*\))*[^)]*\)").unwrap();
+  static ref AUTOLINK: Regex = Regex::new(r"(?i)<\s*(javascript|vbscript|data|file):[^>]*>").unwrap();
+  static ref REFERENCE_LINK: Regex =
+    Regex::new(r"(?im)^(\s{0,3}\[[^\]]+\]:\s*)<?(javascript|vbscript|data|file):\S*").unwrap();
+}
+
+/// Clean up notes for storage; `None` when nothing is left
+pub fn sanitize_notes(notes: &str) -> Option<String> {
+  let notes = notes.replace("\r\n", "\n").replace('\r', "\n");
+  let notes: String = notes
+    .chars()
+    .filter(|c| !c.is_control() || matches!(c, '\n' | '\t'))
+    .collect();
+  let notes = HTML_COMMENT.replace_all(&notes, "");
+  let notes = HTML_TAG.replace_all(&notes, "");
+  let notes = INLINE_LINK.replace_all(&notes, "](#)");
+  let notes = AUTOLINK.replace_all(&notes, "");
+  let notes = REFERENCE_LINK.replace_all(&notes, "${1}#");
+
+  let notes = notes.trim();
+  (!notes.is_empty()).then(|| notes.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_notes_keep_markdown_and_lose_html() {
+    let notes = "# Client A\r\n\r\nUse **Figma** on the left.<script>alert(1)</script>\n\
+                 - [docs](https://example.com)\n- [bad](javascript:alert(1))\n\
+                 <!-- hidden --><img src=x onerror=alert(1)>a < b > c\u{0}\n\
+                 [ref]: vbscript:msgbox\n<data:text/html,hi>";
+    assert_eq!(
+      sanitize_notes(notes).unwrap(),
+      "# Client A\n\nUse **Figma** on the left.alert(1)\n\
+       - [docs](https://example.com)\n- [bad](#)\n\
+       a < b > c\n\
+       [ref]: #"
+    );
+    assert_eq!(sanitize_notes("  <br/>\n "), None);
+  }
+}
@@ -1,2 +1,13 @@
 // Utility functions and helpers
 // Add utility modules here as needed
+
+pub mod crypto;
+pub mod display_coords;
+pub mod error_fingerprint;
+pub mod log_sanitizer;
+pub mod markdown;
+pub mod paths;
+pub mod rule_conditions;
+pub mod shortcut;
+pub mod sun;
+pub mod validation;
@@ -0,0 +1,39 @@
+// Where Smoothie keeps files outside the database
+
+use crate::error::Result;
+use std::path::{Path, PathBuf};
+
+/// Matches the bundle identifier in tauri.conf.json
+pub(crate) const APP_IDENTIFIER: &str = "com.smoothie.desktop";
+
+/// Per-machine app data directory, e.g. `~/Library/Application Support/com.smoothie.desktop`
+pub fn app_data_dir() -> PathBuf {
+  dirs::data_local_dir()
+    .unwrap_or_else(std::env::temp_dir)
+    .join(APP_IDENTIFIER)
+}
+
+/// Whether `path` is a file this user can run
+pub fn is_executable(path: &Path) -> bool {
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    path
+      .metadata()
+      .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+      .unwrap_or(false)
+  }
+  #[cfg(not(unix))]
+  {
+    path.is_file()
+  }
+}
+
+/// Write through a temporary file and rename it into place, so readers and
+/// file sync clients never see a half-written file
+pub async fn write_atomically(path: &Path, data: &[u8]) -> Result<()> {
+  let tmp = path.with_extension("tmp");
+  tokio::fs::write(&tmp, data).await?;
+  tokio::fs::rename(&tmp, path).await?;
+  Ok(())
+}
@@ -0,0 +1,374 @@
+// Automation rule conditions - a JSON-logic style subset checked before a rule acts
+//
+// Example: monitor count == 3 AND time between 09:00-17:00 AND on AC power
+//
+//   {"and": [
+//     {"==": [{"var": "monitor_count"}, 3]},
+//     {"<=": ["09:00", {"var": "time"}, "17:00"]},
+//     {"==": [{"var": "on_ac_power"}, true]}
+//   ]}
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Variables a condition can read with `{"var": name}`
+pub const CONDITION_VARIABLES: [&str; 8] = [
+  "monitor_count",
+  "time",
+  "hour",
+  "minute",
+  "weekday",
+  "on_ac_power",
+  "battery_percent",
+  "running_apps",
+];
+
+/// Facts about the machine that conditions are checked against
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ConditionContext {
+  pub monitor_count: usize,
+  /// Local time as "HH:MM", so string comparisons order correctly
+  pub time: String,
+  pub hour: u32,
+  pub minute: u32,
+  /// 1 = Monday ... 7 = Sunday
+  pub weekday: u32,
+  /// `None` when the power source couldn't be determined
+  pub on_ac_power: Option<bool>,
+  pub battery_percent: Option<u8>,
+  /// Bundle ids of running apps
+  pub running_apps: Vec<String>,
+}
+
+impl ConditionContext {
+  fn var(&self, name: &str) -> Value {
+    match name {
+      "monitor_count" => Value::from(self.monitor_count),
+      "time" => Value::from(self.time.clone()),
+      "hour" => Value::from(self.hour),
+      "minute" => Value::from(self.minute),
+      "weekday" => Value::from(self.weekday),
+      "on_ac_power" => self.on_ac_power.map(Value::from).unwrap_or(Value::Null),
+      "battery_percent" => self.battery_percent.map(Value::from).unwrap_or(Value::Null),
+      "running_apps" => Value::from(self.running_apps.clone()),
+      _ => Value::Null,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+  Eq,
+  Ne,
+  Lt,
+  Le,
+  Gt,
+  Ge,
+}
+
+impl CompareOp {
+  fn symbol(self) -> &'static str {
+    match self {
+      CompareOp::Eq => "==",
+      CompareOp::Ne => "!=",
+      CompareOp::Lt => "<",
+      CompareOp::Le => "<=",
+      CompareOp::Gt => ">",
+      CompareOp::Ge => ">=",
+    }
+  }
+
+  fn from_symbol(symbol: &str) -> Option<Self> {
+    Some(match symbol {
+      "==" => CompareOp::Eq,
+      "!=" => CompareOp::Ne,
+      "<" => CompareOp::Lt,
+      "<=" => CompareOp::Le,
+      ">" => CompareOp::Gt,
+      ">=" => CompareOp::Ge,
+      _ => return None,
+    })
+  }
+
+  fn holds(self, left: &Value, right: &Value) -> bool {
+    match self {
+      CompareOp::Eq => values_equal(left, right),
+      CompareOp::Ne => !values_equal(left, right),
+      CompareOp::Lt => compare_values(left, right) == Some(Ordering::Less),
+      CompareOp::Le => matches!(
+        compare_values(left, right),
+        Some(Ordering::Less | Ordering::Equal)
+      ),
+      CompareOp::Gt => compare_values(left, right) == Some(Ordering::Greater),
+      CompareOp::Ge => matches!(
+        compare_values(left, right),
+        Some(Ordering::Greater | Ordering::Equal)
+      ),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+  Var(String),
+  Literal(Value),
+}
+
+impl Operand {
+  fn resolve(&self, context: &ConditionContext) -> Value {
+    match self {
+      Operand::Var(name) => context.var(name),
+      Operand::Literal(value) => value.clone(),
+    }
+  }
+}
+
+impl fmt::Display for Operand {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Operand::Var(name) => write!(f, "{}", name),
+      Operand::Literal(value) => write!(f, "{}", value),
+    }
+  }
+}
+
+/// A parsed condition expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+  All(Vec<Condition>),
+  Any(Vec<Condition>),
+  Not(Box<Condition>),
+  /// Two operands, or three for a range check like `{"<=": [lo, x, hi]}`
+  Compare {
+    op: CompareOp,
+    operands: Vec<Operand>,
+  },
+  /// Membership in an array, or substring of a string
+  In {
+    needle: Operand,
+    haystack: Operand,
+  },
+}
+
+/// Outcome of one condition, with the outcome of each nested one
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionResult {
+  pub condition: String,
+  pub passed: bool,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub children: Vec<ConditionResult>,
+}
+
+impl Condition {
+  /// Parse a JSON-logic style expression, rejecting unknown operators and variables
+  pub fn parse(value: &Value) -> Result<Self, String> {
+    let Value::Object(map) = value else {
+      return Err(format!("Expected a condition object, got {}", value));
+    };
+    if map.len() != 1 {
+      return Err("A condition object must have exactly one operator".to_string());
+    }
+    let (op, args) = map.iter().next().unwrap();
+
+    match op.as_str() {
+      "and" | "or" => {
+        let Value::Array(items) = args else {
+          return Err(format!("\"{}\" expects an array of conditions", op));
+        };
+        let conditions = items
+          .iter()
+          .map(Self::parse)
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(if op == "and" {
+          Condition::All(conditions)
+        } else {
+          Condition::Any(conditions)
+        })
+      }
+      "!" | "not" => {
+        // JSON-logic allows the single argument to be wrapped in an array
+        let inner = match args {
+          Value::Array(items) if items.len() == 1 => &items[0],
+          other => other,
+        };
+        Ok(Condition::Not(Box::new(Self::parse(inner)?)))
+      }
+      "in" => match operand_list(args)?.as_slice() {
+        [needle, haystack] => Ok(Condition::In {
+          needle: needle.clone(),
+          haystack: haystack.clone(),
+        }),
+        _ => Err("\"in\" expects two arguments".to_string()),
+      },
+      symbol => {
+        let op = CompareOp::from_symbol(symbol)
+          .ok_or_else(|| format!("Unknown condition operator \"{}\"", symbol))?;
+        let operands = operand_list(args)?;
+        let range_allowed = matches!(op, CompareOp::Lt | CompareOp::Le);
+        if operands.len() != 2 && !(range_allowed && operands.len() == 3) {
+          return Err(format!(
+            "\"{}\" expects two arguments{}",
+            symbol,
+            if range_allowed {
+              " (or three for a range)"
+            } else {
+              ""
+            }
+          ));
+        }
+        Ok(Condition::Compare { op, operands })
+      }
+    }
+  }
+
+  /// Evaluate against `context`, recording the result of every nested condition
+  pub fn evaluate(&self, context: &ConditionContext) -> ConditionResult {
+    let (passed, children) = match self {
+      Condition::All(conditions) => {
+        let children: Vec<_> = conditions.iter().map(|c| c.evaluate(context)).collect();
+        (children.iter().all(|c| c.passed), children)
+      }
+      Condition::Any(conditions) => {
+        let children: Vec<_> = conditions.iter().map(|c| c.evaluate(context)).collect();
+        (children.iter().any(|c| c.passed), children)
+      }
+      Condition::Not(inner) => {
+        let child = inner.evaluate(context);
+        (!child.passed, vec![child])
+      }
+      Condition::Compare { op, operands } => {
+        let values: Vec<_> = operands.iter().map(|o| o.resolve(context)).collect();
+        (
+          values.windows(2).all(|pair| op.holds(&pair[0], &pair[1])),
+          Vec::new(),
+        )
+      }
+      Condition::In { needle, haystack } => {
+        let needle = needle.resolve(context);
+        let passed = match (haystack.resolve(context), &needle) {
+          (Value::Array(items), _) => items.iter().any(|item| values_equal(item, &needle)),
+          (Value::String(text), Value::String(part)) => text.contains(part.as_str()),
+          _ => false,
+        };
+        (passed, Vec::new())
+      }
+    };
+
+    ConditionResult {
+      condition: self.to_string(),
+      passed,
+      children,
+    }
+  }
+}
+
+impl fmt::Display for Condition {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Condition::All(conditions) | Condition::Any(conditions) => {
+        let joiner = if matches!(self, Condition::All(_)) {
+          " and "
+        } else {
+          " or "
+        };
+        let parts: Vec<_> = conditions.iter().map(|c| format!("({})", c)).collect();
+        write!(f, "{}", parts.join(joiner))
+      }
+      Condition::Not(inner) => write!(f, "not ({})", inner),
+      Condition::Compare { op, operands } => {
+        let parts: Vec<_> = operands.iter().map(|o| o.to_string()).collect();
+        write!(f, "{}", parts.join(&format!(" {} ", op.symbol())))
+      }
+      Condition::In { needle, haystack } => write!(f, "{} in {}", needle, haystack),
+    }
+  }
+}
+
+fn operand_list(args: &Value) -> Result<Vec<Operand>, String> {
+  let Value::Array(items) = args else {
+    return Err(format!("Expected an array of arguments, got {}", args));
+  };
+  items.iter().map(parse_operand).collect()
+}
+
+fn parse_operand(value: &Value) -> Result<Operand, String> {
+  match value {
+    Value::Object(map) => match map.get("var") {
+      Some(Value::String(name)) if map.len() == 1 => {
+        if CONDITION_VARIABLES.contains(&name.as_str()) {
+          Ok(Operand::Var(name.clone()))
+        } else {
+          Err(format!(
+            "Unknown variable \"{}\", expected one of: {}",
+            name,
+            CONDITION_VARIABLES.join(", ")
+          ))
+        }
+      }
+      _ => Err(format!(
+        "Expected a value or {{\"var\": name}}, got {}",
+        value
+      )),
+    },
+    other => Ok(Operand::Literal(other.clone())),
+  }
+}
+
+fn values_equal(left: &Value, right: &Value) -> bool {
+  match (left.as_f64(), right.as_f64()) {
+    (Some(l), Some(r)) => l == r,
+    _ => left == right,
+  }
+}
+
+/// Numbers compare numerically and strings lexically; anything else is unordered
+fn compare_values(left: &Value, right: &Value) -> Option<Ordering> {
+  match (left, right) {
+    (Value::Number(l), Value::Number(r)) => l.as_f64()?.partial_cmp(&r.as_f64()?),
+    (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_conditions_report_each_check() {
+    let condition = Condition::parse(&json!({"and": [
+      {"==": [{"var": "monitor_count"}, 3]},
+      {"<=": ["09:00", {"var": "time"}, "17:00"]},
+      {"==": [{"var": "on_ac_power"}, true]},
+      {"in": ["com.apple.Safari", {"var": "running_apps"}]}
+    ]}))
+    .unwrap();
+    let mut context = ConditionContext {
+      monitor_count: 3,
+      time: "10:30".to_string(),
+      on_ac_power: Some(true),
+      running_apps: vec!["com.apple.Safari".to_string()],
+      ..Default::default()
+    };
+    assert!(condition.evaluate(&context).passed);
+
+    context.time = "18:00".to_string();
+    context.on_ac_power = None;
+    let result = condition.evaluate(&context);
+    assert!(!result.passed);
+    let passed: Vec<_> = result.children.iter().map(|c| c.passed).collect();
+    assert_eq!(passed, [true, false, false, true]);
+    assert_eq!(
+      result.children[1].condition,
+      "\"09:00\" <= time <= \"17:00\""
+    );
+
+    assert!(Condition::parse(&json!({"==": [{"var": "cpu"}, 1]})).is_err());
+    assert!(Condition::parse(&json!({"xor": []})).is_err());
+  }
+}
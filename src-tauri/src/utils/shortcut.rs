@@ -0,0 +1,236 @@
+// Keyboard shortcuts - parsing, a canonical spelling and the system's own
+//
+// "cmd+shift+1", "⌘⇧1" and "Shift+Command+1" all parse to the same shortcut
+// and print as "Shift+Cmd+1", modifiers in the order macOS menus show them.
+// Comparing canonical forms is what conflict checks rely on.
+
+use crate::error::{Result, SmoothieError};
+use std::fmt;
+
+const CTRL: u8 = 1;
+const OPTION: u8 = 1 << 1;
+const SHIFT: u8 = 1 << 2;
+const CMD: u8 = 1 << 3;
+
+/// Modifier bits with their names, in display order
+const MODIFIERS: [(u8, &str); 4] = [
+  (CTRL, "Ctrl"),
+  (OPTION, "Option"),
+  (SHIFT, "Shift"),
+  (CMD, "Cmd"),
+];
+
+/// Keys with a name rather than a character, and their aliases
+const NAMED_KEYS: [(&str, &[&str]); 12] = [
+  ("Space", &["space"]),
+  ("Tab", &["tab", "⇥"]),
+  ("Return", &["return", "enter", "↩"]),
+  ("Escape", &["escape", "esc", "⎋"]),
+  ("Delete", &["delete", "backspace", "⌫"]),
+  ("ForwardDelete", &["forwarddelete", "del", "⌦"]),
+  ("Up", &["up", "arrowup", "↑"]),
+  ("Down", &["down", "arrowdown", "↓"]),
+  ("Left", &["left", "arrowleft", "←"]),
+  ("Right", &["right", "arrowright", "→"]),
+  ("Home", &["home"]),
+  ("End", &["end"]),
+];
+
+/// Shortcuts macOS keeps for itself, with what they do
+const SYSTEM_SHORTCUTS: &[(&str, &str)] = &[
+  ("Cmd+Space", "Spotlight"),
+  ("Option+Cmd+Space", "Finder search window"),
+  ("Ctrl+Space", "Select the previous input source"),
+  ("Ctrl+Cmd+Space", "Emoji & Symbols"),
+  ("Cmd+Tab", "Switch apps"),
+  ("Shift+Cmd+Tab", "Switch apps backwards"),
+  ("Cmd+`", "Switch windows of the front app"),
+  ("Cmd+Q", "Quit the front app"),
+  ("Cmd+W", "Close the front window"),
+  ("Cmd+H", "Hide the front app"),
+  ("Option+Cmd+H", "Hide other apps"),
+  ("Cmd+M", "Minimize the front window"),
+  ("Cmd+N", "New window"),
+  ("Cmd+T", "New tab"),
+  ("Cmd+O", "Open"),
+  ("Cmd+S", "Save"),
+  ("Cmd+P", "Print"),
+  ("Cmd+F", "Find"),
+  ("Cmd+A", "Select all"),
+  ("Cmd+C", "Copy"),
+  ("Cmd+V", "Paste"),
+  ("Cmd+X", "Cut"),
+  ("Cmd+Z", "Undo"),
+  ("Shift+Cmd+Z", "Redo"),
+  ("Cmd+,", "App settings"),
+  ("Option+Cmd+Escape", "Force Quit Applications"),
+  ("Ctrl+Cmd+Q", "Lock Screen"),
+  ("Ctrl+Cmd+F", "Toggle full screen"),
+  ("Option+Cmd+D", "Show or hide the Dock"),
+  ("Shift+Cmd+3", "Screenshot of the screen"),
+  ("Shift+Cmd+4", "Screenshot of a selection"),
+  ("Shift+Cmd+5", "Screenshot and recording options"),
+  ("Ctrl+Shift+Cmd+3", "Copy a screenshot of the screen"),
+  ("Ctrl+Shift+Cmd+4", "Copy a screenshot of a selection"),
+  ("Ctrl+Up", "Mission Control"),
+  ("Ctrl+Down", "Application windows"),
+  ("Ctrl+Left", "Move a space left"),
+  ("Ctrl+Right", "Move a space right"),
+  ("Shift+Cmd+Q", "Log Out"),
+  ("Shift+Cmd+/", "Help menu"),
+];
+
+/// A modifier set and a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+  modifiers: u8,
+  key: Key,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Key {
+  Char(char),
+  Named(&'static str),
+  Function(u8),
+}
+
+fn invalid(shortcut: &str, reason: &str) -> SmoothieError {
+  SmoothieError::ValidationError(format!("Invalid shortcut \"{}\": {}", shortcut, reason))
+}
+
+fn parse_modifier(token: &str) -> Option<u8> {
+  match token {
+    "cmd" | "command" | "meta" | "super" | "⌘" => Some(CMD),
+    "shift" | "⇧" => Some(SHIFT),
+    "option" | "opt" | "alt" | "⌥" => Some(OPTION),
+    "ctrl" | "control" | "⌃" => Some(CTRL),
+    _ => None,
+  }
+}
+
+fn parse_key(token: &str) -> Option<Key> {
+  if let Some((name, _)) = NAMED_KEYS
+    .iter()
+    .find(|(_, aliases)| aliases.contains(&token))
+  {
+    return Some(Key::Named(name));
+  }
+  if let Some(n) = token.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+    return (1..=20).contains(&n).then_some(Key::Function(n));
+  }
+  let mut chars = token.chars();
+  match (chars.next(), chars.next()) {
+    (Some(c), None) if c.is_ascii_graphic() => Some(Key::Char(c.to_ascii_uppercase())),
+    _ => None,
+  }
+}
+
+impl Shortcut {
+  /// Parse `+`-separated or symbol-prefixed shortcuts like "Cmd+Shift+1" or
+  /// "⌘⇧1". Anything but a function key needs a modifier.
+  pub fn parse(shortcut: &str) -> Result<Self> {
+    let trimmed = shortcut.trim();
+    if trimmed.is_empty() {
+      return Err(invalid(shortcut, "it's empty"));
+    }
+
+    // Split "⌘⇧1" into "⌘", "⇧", "1"; "Cmd++" means Cmd and the + key
+    let mut rest = trimmed;
+    let mut tokens = Vec::new();
+    while let Some(c) = rest.chars().next().filter(|c| "⌘⇧⌥⌃".contains(*c)) {
+      tokens.push(c.to_string());
+      rest = &rest[c.len_utf8()..];
+    }
+    let rest = rest.trim_start_matches('+');
+    if let Some(head) = rest.strip_suffix("++") {
+      tokens.extend(head.split('+').map(str::to_string));
+      tokens.push("+".to_string());
+    } else if rest == "+" {
+      tokens.push("+".to_string());
+    } else {
+      tokens.extend(rest.split('+').map(str::to_string));
+    }
+
+    let (key_token, modifier_tokens) = tokens
+      .split_last()
+      .ok_or_else(|| invalid(shortcut, "it has no key"))?;
+    let mut modifiers = 0;
+    for token in modifier_tokens {
+      let token = token.trim().to_lowercase();
+      let modifier = parse_modifier(&token).ok_or_else(|| invalid(shortcut, "unknown modifier"))?;
+      if modifiers & modifier != 0 {
+        return Err(invalid(shortcut, "a modifier is repeated"));
+      }
+      modifiers |= modifier;
+    }
+    let key_token = key_token.trim().to_lowercase();
+    if parse_modifier(&key_token).is_some() {
+      return Err(invalid(shortcut, "it has no key"));
+    }
+    let key = parse_key(&key_token).ok_or_else(|| invalid(shortcut, "unknown key"))?;
+    if modifiers == 0 && !matches!(key, Key::Function(_)) {
+      return Err(invalid(shortcut, "it needs a modifier"));
+    }
+    Ok(Self { modifiers, key })
+  }
+
+  /// What macOS does with this shortcut, if it's one of the system's own
+  pub fn system_use(&self) -> Option<&'static str> {
+    SYSTEM_SHORTCUTS
+      .iter()
+      .find(|(shortcut, _)| Self::parse(shortcut).is_ok_and(|s| s == *self))
+      .map(|(_, description)| *description)
+  }
+}
+
+impl fmt::Display for Shortcut {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (bit, name) in MODIFIERS {
+      if self.modifiers & bit != 0 {
+        write!(f, "{}+", name)?;
+      }
+    }
+    match self.key {
+      Key::Char(c) => write!(f, "{}", c),
+      Key::Named(name) => write!(f, "{}", name),
+      Key::Function(n) => write!(f, "F{}", n),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_shortcuts_parse_to_one_spelling() {
+    let canonical = |s: &str| Shortcut::parse(s).map(|s| s.to_string());
+    assert_eq!(canonical("cmd+shift+1").unwrap(), "Shift+Cmd+1");
+    assert_eq!(canonical("⌘⇧1").unwrap(), "Shift+Cmd+1");
+    assert_eq!(canonical(" Command + Shift + 1 ").unwrap(), "Shift+Cmd+1");
+    assert_eq!(canonical("alt+ctrl+esc").unwrap(), "Ctrl+Option+Escape");
+    assert_eq!(canonical("Cmd++").unwrap(), "Cmd++");
+    assert_eq!(canonical("F5").unwrap(), "F5");
+
+    for bad in [
+      "",
+      "K",
+      "Cmd+Shift",
+      "Cmd+Cmd+K",
+      "Hyper+K",
+      "Cmd+F25",
+      "Cmd+KK",
+    ] {
+      assert!(canonical(bad).is_err(), "{} parsed", bad);
+    }
+
+    assert_eq!(
+      Shortcut::parse("command+space").unwrap().system_use(),
+      Some("Spotlight")
+    );
+    assert_eq!(
+      Shortcut::parse("Ctrl+Option+Cmd+1").unwrap().system_use(),
+      None
+    );
+  }
+}
@@ -0,0 +1,80 @@
+// Sunrise and sunset times from the sunrise equation (NOAA's simplified form).
+// Accurate to a minute or two away from the poles, which is plenty for scheduling.
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Julian date of 2000-01-01 12:00 UTC
+const J2000: f64 = 2_451_545.0;
+/// Julian date of the Unix epoch
+const UNIX_EPOCH_JD: f64 = 2_440_587.5;
+/// Sun's apparent altitude at rise and set, allowing for refraction and its radius
+const HORIZON_DEGREES: f64 = -0.833;
+/// Axial tilt of the Earth
+const OBLIQUITY_DEGREES: f64 = 23.4397;
+
+/// Sunrise and sunset on `date` at the given location (east longitude positive).
+/// `None` during polar day or polar night, when the sun doesn't cross the horizon.
+pub fn sun_times(
+  date: NaiveDate,
+  latitude: f64,
+  longitude: f64,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+  let days_since_j2000 = (date - NaiveDate::from_ymd_opt(2000, 1, 1)?).num_days() as f64;
+  let mean_solar_time = days_since_j2000 - longitude / 360.0;
+
+  let mean_anomaly = (357.5291 + 0.985_600_28 * mean_solar_time).rem_euclid(360.0);
+  let m = mean_anomaly.to_radians();
+  let center = 1.9148 * m.sin() + 0.0200 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+  let ecliptic_longitude = (mean_anomaly + center + 180.0 + 102.9372)
+    .rem_euclid(360.0)
+    .to_radians();
+
+  let transit =
+    J2000 + mean_solar_time + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+  let declination = (ecliptic_longitude.sin() * OBLIQUITY_DEGREES.to_radians().sin()).asin();
+
+  let phi = latitude.to_radians();
+  let cos_hour_angle = (HORIZON_DEGREES.to_radians().sin() - phi.sin() * declination.sin())
+    / (phi.cos() * declination.cos());
+  if !(-1.0..=1.0).contains(&cos_hour_angle) {
+    return None;
+  }
+  let hour_angle = cos_hour_angle.acos().to_degrees();
+
+  Some((
+    julian_to_utc(transit - hour_angle / 360.0)?,
+    julian_to_utc(transit + hour_angle / 360.0)?,
+  ))
+}
+
+fn julian_to_utc(julian: f64) -> Option<DateTime<Utc>> {
+  DateTime::from_timestamp(((julian - UNIX_EPOCH_JD) * 86_400.0).round() as i64, 0)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::NaiveDateTime;
+
+  fn utc(s: &str) -> DateTime<Utc> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+      .unwrap()
+      .and_utc()
+  }
+
+  #[test]
+  fn test_sun_times_match_published_tables() {
+    // London on the summer solstice: sunrise 04:43, sunset 21:21 BST
+    let (sunrise, sunset) = sun_times(
+      NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(),
+      51.5074,
+      -0.1278,
+    )
+    .unwrap();
+    assert!((sunrise - utc("2024-06-21 03:43")).num_minutes().abs() <= 3);
+    assert!((sunset - utc("2024-06-21 20:21")).num_minutes().abs() <= 3);
+
+    // Tromsø has no sunrise in mid-December
+    assert!(sun_times(NaiveDate::from_ymd_opt(2024, 12, 21).unwrap(), 69.65, 18.96).is_none());
+  }
+}
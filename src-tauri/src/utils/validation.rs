@@ -0,0 +1,259 @@
+// Input validation - declarative rules for request DTOs
+//
+// Request structs derive `validator::Validate` and point `custom(...)` rules at
+// the functions below. Services call `validate` before touching the database;
+// failures become `SmoothieError::InvalidFields`, keyed by the camelCase field
+// name the frontend sent, so forms can show each message inline.
+
+use crate::error::{Result, SmoothieError};
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use uuid::Uuid;
+use validator::{Validate, ValidateUrl, ValidationError};
+
+pub const FEEDBACK_TYPES: &[&str] = &[
+  "feature_request",
+  "bug_report",
+  "general_feedback",
+  "question",
+];
+pub const FEEDBACK_PRIORITIES: &[&str] = &["low", "medium", "high", "critical"];
+pub const ORIENTATIONS: &[&str] = &["landscape", "portrait"];
+pub const BROWSERS: &[&str] = &[
+  "safari",
+  "chrome",
+  "google chrome",
+  "firefox",
+  "mozilla firefox",
+  "arc",
+  "brave",
+  "brave browser",
+  "edge",
+  "microsoft edge",
+  "opera",
+  "vivaldi",
+];
+
+const MAX_TAGS: usize = 20;
+const MAX_TAG_LENGTH: usize = 32;
+
+lazy_static::lazy_static! {
+  static ref HEX_COLOR: Regex = Regex::new(r"^#([0-9a-fA-F]{3}|[0-9a-fA-F]{6}|[0-9a-fA-F]{8})$").unwrap();
+  static ref RESOLUTION: Regex = Regex::new(r"^[1-9]\d{1,4}x[1-9]\d{1,4}$").unwrap();
+}
+
+/// Messages per field, keyed by the camelCase name the frontend uses
+pub type FieldErrors = BTreeMap<String, Vec<String>>;
+
+/// Run a DTO's declarative rules
+pub fn validate<T: Validate>(input: &T) -> Result<()> {
+  input.validate().map_err(|errors| {
+    let fields = errors
+      .field_errors()
+      .into_iter()
+      .map(|(field, errors)| (to_camel_case(&field), errors.iter().map(describe).collect()))
+      .collect();
+    SmoothieError::InvalidFields(fields)
+  })
+}
+
+/// Check a single loose argument for commands that don't take a request struct
+pub fn validate_field(field: &str, result: std::result::Result<(), ValidationError>) -> Result<()> {
+  result.map_err(|error| {
+    SmoothieError::InvalidFields(FieldErrors::from([(
+      to_camel_case(field),
+      vec![describe(&error)],
+    )]))
+  })
+}
+
+// ============================================================================
+// Custom rules
+// ============================================================================
+
+pub fn not_blank(value: &str) -> std::result::Result<(), ValidationError> {
+  if value.trim().is_empty() {
+    return Err(invalid("blank", "Must not be blank"));
+  }
+  Ok(())
+}
+
+pub fn feedback_type(value: &str) -> std::result::Result<(), ValidationError> {
+  one_of(value, FEEDBACK_TYPES)
+}
+
+pub fn feedback_priority(value: &str) -> std::result::Result<(), ValidationError> {
+  one_of(value, FEEDBACK_PRIORITIES)
+}
+
+pub fn orientation(value: &str) -> std::result::Result<(), ValidationError> {
+  one_of(value, ORIENTATIONS)
+}
+
+pub fn browser(value: &str) -> std::result::Result<(), ValidationError> {
+  one_of(value, BROWSERS)
+}
+
+pub fn hex_color(value: &str) -> std::result::Result<(), ValidationError> {
+  if !HEX_COLOR.is_match(value) {
+    return Err(invalid("hex_color", "Must be a hex color like #1e90ff"));
+  }
+  Ok(())
+}
+
+pub fn resolution(value: &str) -> std::result::Result<(), ValidationError> {
+  if !RESOLUTION.is_match(value) {
+    return Err(invalid(
+      "resolution",
+      "Must be WIDTHxHEIGHT, e.g. 2560x1440",
+    ));
+  }
+  Ok(())
+}
+
+/// An absolute http(s) URL
+pub fn web_url(value: &str) -> std::result::Result<(), ValidationError> {
+  let lower = value.to_ascii_lowercase();
+  let has_scheme = lower.starts_with("http://") || lower.starts_with("https://");
+  if !has_scheme || !value.validate_url() {
+    return Err(invalid("url", "Must be a full http:// or https:// URL"));
+  }
+  Ok(())
+}
+
+pub fn tags(tags: &[String]) -> std::result::Result<(), ValidationError> {
+  if tags.len() > MAX_TAGS {
+    return Err(invalid("tags", "At most 20 tags are allowed"));
+  }
+  if tags
+    .iter()
+    .any(|tag| tag.trim().is_empty() || tag.chars().count() > MAX_TAG_LENGTH)
+  {
+    return Err(invalid("tags", "Tags must be 1-32 characters"));
+  }
+  Ok(())
+}
+
+/// Every entry a full http(s) URL once surrounding whitespace is trimmed
+pub fn web_urls(urls: &[String]) -> std::result::Result<(), ValidationError> {
+  urls.iter().try_for_each(|url| web_url(url.trim()))
+}
+
+pub fn bundle_ids(ids: &[String]) -> std::result::Result<(), ValidationError> {
+  if ids.iter().any(|id| id.trim().is_empty() || id.len() > 255) {
+    return Err(invalid("bundle_id", "Bundle IDs must be 1-255 characters"));
+  }
+  Ok(())
+}
+
+/// Each id at most once, e.g. in a new ordering
+pub fn distinct_ids(ids: &[Uuid]) -> std::result::Result<(), ValidationError> {
+  let unique: HashSet<&Uuid> = ids.iter().collect();
+  if unique.len() != ids.len() {
+    return Err(invalid("distinct", "Must not list the same item twice"));
+  }
+  Ok(())
+}
+
+/// Case-insensitive membership; the UI sends display casing ("Work", "Landscape")
+fn one_of(value: &str, allowed: &[&str]) -> std::result::Result<(), ValidationError> {
+  if allowed.iter().any(|a| a.eq_ignore_ascii_case(value)) {
+    return Ok(());
+  }
+  Err(invalid(
+    "one_of",
+    &format!("Must be one of: {}", allowed.join(", ")),
+  ))
+}
+
+fn invalid(code: &'static str, message: &str) -> ValidationError {
+  ValidationError::new(code).with_message(Cow::Owned(message.to_string()))
+}
+
+/// Human-readable message for an error, filling in the built-in validators' params
+fn describe(error: &ValidationError) -> String {
+  if let Some(message) = &error.message {
+    return message.to_string();
+  }
+  let param = |name: &str| error.params.get(name).map(|v| v.to_string());
+  match error.code.as_ref() {
+    "length" => match (param("min"), param("max")) {
+      (Some(min), Some(max)) => format!("Must be {}-{} characters", min, max),
+      (Some(min), None) => format!("Must be at least {} characters", min),
+      (None, Some(max)) => format!("Must be at most {} characters", max),
+      (None, None) => "Invalid length".to_string(),
+    },
+    "range" => match (param("min"), param("max")) {
+      (Some(min), Some(max)) => format!("Must be between {} and {}", min, max),
+      (Some(min), None) => format!("Must be at least {}", min),
+      (None, Some(max)) => format!("Must be at most {}", max),
+      (None, None) => "Out of range".to_string(),
+    },
+    "email" => "Must be a valid email address".to_string(),
+    "url" => "Must be a valid URL".to_string(),
+    code => format!("Invalid value ({})", code),
+  }
+}
+
+fn to_camel_case(field: &str) -> String {
+  let mut out = String::with_capacity(field.len());
+  let mut upper = false;
+  for c in field.chars() {
+    if c == '_' {
+      upper = true;
+    } else if upper {
+      out.extend(c.to_uppercase());
+      upper = false;
+    } else {
+      out.push(c);
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::{CreateProfileRequest, ProfileType, UpdateProfileRequest};
+
+  #[test]
+  fn test_field_errors_are_keyed_for_the_ui() {
+    let req = CreateProfileRequest {
+      name: "  ".into(),
+      description: None,
+      profile_type: ProfileType::Work,
+      tags: Some(vec!["ok".into(), "".into()]),
+      notes: None,
+    };
+
+    let Err(SmoothieError::InvalidFields(fields)) = validate(&req) else {
+      panic!("expected field errors");
+    };
+    assert_eq!(fields["name"], vec!["Must not be blank"]);
+    assert_eq!(fields["tags"], vec!["Tags must be 1-32 characters"]);
+
+    let update = UpdateProfileRequest {
+      sort_order: Some(-1),
+      ..Default::default()
+    };
+    let Err(SmoothieError::InvalidFields(fields)) = validate(&update) else {
+      panic!("expected field errors");
+    };
+    assert_eq!(fields["sortOrder"], vec!["Must be at least 0"]);
+
+    let ok = CreateProfileRequest {
+      name: "Deep Work".into(),
+      description: None,
+      profile_type: ProfileType::Work,
+      tags: None,
+      notes: None,
+    };
+    assert!(validate(&ok).is_ok());
+
+    assert!(web_url("https://example.com/a?b=1").is_ok());
+    assert!(web_url("javascript:alert(1)").is_err());
+    assert!(hex_color("#1E90FF").is_ok());
+    assert!(hex_color("blue").is_err());
+  }
+}